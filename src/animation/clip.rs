@@ -0,0 +1,412 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::math::{ Quat, Vec2, Vec3, Vec4 };
+
+/**
+ * What a clip does when it reaches its end
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+pub enum LoopMode {
+    /**
+     * Holds the last frame and finishes
+     **/
+    Once,
+    #[default]
+    Loop,
+    /**
+     * Plays back to the start and then forwards again
+     **/
+    PingPong,
+}
+
+/**
+ * A named marker on a clip's timeline, fired when the playhead passes it
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct KeyframeEvent {
+    pub time: f32,
+    pub name: String,
+}
+
+impl KeyframeEvent {
+    pub fn new(time: f32, name: &str) -> KeyframeEvent {
+        KeyframeEvent { time, name: name.to_string() }
+    }
+}
+
+/**
+ * What the state machine needs to know about a clip, implemented by sprite and skeletal clips
+ **/
+pub trait AnimationClip {
+    fn name(&self) -> &str;
+    fn duration(&self) -> f32;
+    fn loop_mode(&self) -> LoopMode;
+    fn events(&self) -> &[KeyframeEvent];
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+pub enum Interpolation {
+    Step,
+    #[default]
+    Linear,
+}
+
+/**
+ * Values keyframes can hold
+ **/
+pub trait Interpolate: Copy {
+    fn interpolate(self, other: Self, t: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    fn interpolate(self, other: f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for Vec2 {
+    fn interpolate(self, other: Vec2, t: f32) -> Vec2 {
+        self.lerp(other, t)
+    }
+}
+
+impl Interpolate for Vec3 {
+    fn interpolate(self, other: Vec3, t: f32) -> Vec3 {
+        self.lerp(other, t)
+    }
+}
+
+impl Interpolate for Vec4 {
+    fn interpolate(self, other: Vec4, t: f32) -> Vec4 {
+        self.lerp(other, t)
+    }
+}
+
+impl Interpolate for Quat {
+    fn interpolate(self, other: Quat, t: f32) -> Quat {
+        self.slerp(other, t)
+    }
+}
+
+/**
+ * Keyframed values, `times` are in seconds, ascending and the same length as `values`
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Track<T> {
+    pub times: Vec<f32>,
+    pub values: Vec<T>,
+    #[serde(default)]
+    pub interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(keys: Vec<(f32, T)>, interpolation: Interpolation) -> Track<T> {
+        let (times, values) = keys.into_iter().unzip();
+        Track { times, values, interpolation }
+    }
+
+    /**
+     * Time of the last key
+     **/
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+
+    /**
+     * The value at a time, held at the first and last keys outside of them
+     **/
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let len = self.times.len().min(self.values.len());
+        if len == 0 {
+            return None;
+        }
+        let next = self.times[..len].partition_point(|x| *x <= time);
+        if next == 0 {
+            return Some(self.values[0]);
+        }
+        if next == len || self.interpolation == Interpolation::Step {
+            return Some(self.values[next - 1]);
+        }
+        let (t0, t1) = (self.times[next - 1], self.times[next]);
+        let t = if t1 > t0 { (time - t0) / (t1 - t0) } else { 1.0 };
+        Some(self.values[next - 1].interpolate(self.values[next], t))
+    }
+}
+
+/**
+ * Where a clip is and which way it's going
+ * Only moved by advance, so the same deltas always give the same times and events
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Playback {
+    pub time: f32,
+    pub speed: f32,
+    reverse: bool,
+    finished: bool,
+}
+
+//caps the wraps one advance can do when a tiny clip gets a huge delta
+const MAX_WRAPS: u32 = 64;
+
+impl Playback {
+    pub fn new(speed: Option<f32>) -> Playback {
+        Playback { time: 0.0, speed: speed.unwrap_or(1.0), reverse: false, finished: false }
+    }
+
+    #[inline]
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /**
+     * Time through the clip from 0 to 1
+     **/
+    pub fn normalized(&self, duration: f32) -> f32 {
+        if duration > 0.0 { (self.time / duration).clamp(0.0, 1.0) } else { 1.0 }
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.reverse = false;
+        self.finished = false;
+    }
+
+    /**
+     * Moves the playhead `dt` seconds scaled by speed, calling `fired` with every event it
+     * passes in the order it passes them. Clips don't play backwards, a negative speed holds
+     * An event fires when the playhead reaches its time, so one at 0 fires as the clip starts
+     * and on every loop. Looping clips treat an event at their full length as one at 0
+     **/
+    pub fn advance<F: FnMut(&KeyframeEvent)>(&mut self, dt: f32, duration: f32, mode: LoopMode, events: &[KeyframeEvent], mut fired: F) {
+        if duration <= 0.0 {
+            self.time = 0.0;
+            self.finished = mode == LoopMode::Once;
+            return;
+        }
+        let event_time = |x: &KeyframeEvent| if mode == LoopMode::Loop && x.time >= duration { 0.0 } else { x.time };
+        let mut remaining = (dt * self.speed).max(0.0);
+        let mut wraps = 0;
+        while remaining > 0.0 && !self.finished && wraps < MAX_WRAPS {
+            if !self.reverse {
+                let from = self.time;
+                let step = remaining.min(duration - from);
+                let to = from + step;
+                remaining -= step;
+                let mut passed: Vec<&KeyframeEvent> = events.iter().filter(|x| event_time(x) >= from && event_time(x) < to).collect();
+                passed.sort_by(|a, b| event_time(a).total_cmp(&event_time(b)));
+                passed.into_iter().for_each(&mut fired);
+                self.time = to;
+                if to < duration {
+                    break;
+                }
+                wraps += 1;
+                match mode {
+                    LoopMode::Once => {
+                        events.iter().filter(|x| x.time >= duration).for_each(&mut fired);
+                        self.finished = true;
+                    },
+                    LoopMode::Loop => self.time = 0.0,
+                    LoopMode::PingPong => self.reverse = true
+                }
+            } else {
+                let from = self.time;
+                let step = remaining.min(from);
+                let to = from - step;
+                remaining -= step;
+                let mut passed: Vec<&KeyframeEvent> = events.iter().filter(|x| x.time > to && x.time <= from).collect();
+                passed.sort_by(|a, b| b.time.total_cmp(&a.time));
+                passed.into_iter().for_each(&mut fired);
+                self.time = to;
+                if to > 0.0 {
+                    break;
+                }
+                wraps += 1;
+                self.reverse = false;
+            }
+        }
+    }
+}
+
+impl Default for Playback {
+    fn default() -> Playback {
+        Playback::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track() -> Track<f32> {
+        Track::new(vec![(0.0, 0.0), (1.0, 10.0), (3.0, 30.0)], Interpolation::Linear)
+    }
+
+    //names of the events fired by one advance
+    fn advance(playback: &mut Playback, dt: f32, mode: LoopMode, events: &[KeyframeEvent]) -> Vec<String> {
+        let mut fired = Vec::new();
+        playback.advance(dt, 1.0, mode, events, |x| fired.push(x.name.clone()));
+        fired
+    }
+
+    fn quarters() -> Vec<KeyframeEvent> {
+        vec![KeyframeEvent::new(0.75, "c"), KeyframeEvent::new(0.25, "a"), KeyframeEvent::new(0.5, "b")]
+    }
+
+    #[test]
+    fn track_samples_keys_exactly() {
+        let track = track();
+        assert_eq!(track.sample(0.0), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(10.0));
+        assert_eq!(track.sample(3.0), Some(30.0));
+        assert_eq!(track.duration(), 3.0);
+    }
+
+    #[test]
+    fn track_interpolates_between_keys() {
+        let track = track();
+        assert_eq!(track.sample(0.5), Some(5.0));
+        assert_eq!(track.sample(2.0), Some(20.0));
+        //just either side of a key lands on either side of its value
+        assert!(track.sample(1.0 - 1e-4).unwrap() < 10.0);
+        assert!(track.sample(1.0 + 1e-4).unwrap() > 10.0);
+    }
+
+    #[test]
+    fn track_holds_outside_keys() {
+        let track = track();
+        assert_eq!(track.sample(-1.0), Some(0.0));
+        assert_eq!(track.sample(100.0), Some(30.0));
+        assert_eq!(Track::<f32>::new(Vec::new(), Interpolation::Linear).sample(0.0), None);
+    }
+
+    #[test]
+    fn step_track_holds_until_next_key() {
+        let track = Track::new(vec![(0.0, 0.0), (1.0, 10.0)], Interpolation::Step);
+        assert_eq!(track.sample(0.99), Some(0.0));
+        assert_eq!(track.sample(1.0), Some(10.0));
+    }
+
+    #[test]
+    fn quaternion_track_slerps() {
+        let track = Track::new(vec![(0.0, Quat::IDENTITY), (1.0, Quat::from_rotation_z(1.0))], Interpolation::Linear);
+        assert!(track.sample(0.5).unwrap().approx_eq(Quat::from_rotation_z(0.5), 1e-5));
+    }
+
+    #[test]
+    fn loop_wraps_around() {
+        let mut playback = Playback::default();
+        advance(&mut playback, 1.25, LoopMode::Loop, &[]);
+        assert_eq!(playback.time, 0.25);
+        advance(&mut playback, 0.75, LoopMode::Loop, &[]);
+        assert_eq!(playback.time, 0.0);
+        assert!(!playback.finished());
+    }
+
+    #[test]
+    fn once_holds_the_end() {
+        let mut playback = Playback::default();
+        advance(&mut playback, 1.5, LoopMode::Once, &[]);
+        assert_eq!(playback.time, 1.0);
+        assert!(playback.finished());
+        assert_eq!(playback.normalized(1.0), 1.0);
+        playback.restart();
+        assert_eq!(playback.time, 0.0);
+        assert!(!playback.finished());
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_each_end() {
+        let mut playback = Playback::default();
+        advance(&mut playback, 1.25, LoopMode::PingPong, &[]);
+        assert_eq!(playback.time, 0.75);
+        advance(&mut playback, 0.5, LoopMode::PingPong, &[]);
+        assert_eq!(playback.time, 0.25);
+        advance(&mut playback, 0.5, LoopMode::PingPong, &[]);
+        assert_eq!(playback.time, 0.25);
+        advance(&mut playback, 0.25, LoopMode::PingPong, &[]);
+        assert_eq!(playback.time, 0.5);
+    }
+
+    #[test]
+    fn speed_scales_and_negative_holds() {
+        let mut playback = Playback::new(Some(2.0));
+        advance(&mut playback, 0.25, LoopMode::Loop, &[]);
+        assert_eq!(playback.time, 0.5);
+        playback.speed = -1.0;
+        advance(&mut playback, 0.25, LoopMode::Loop, &[]);
+        assert_eq!(playback.time, 0.5);
+    }
+
+    #[test]
+    fn small_steps_fire_each_event_once() {
+        let events = quarters();
+        let mut playback = Playback::default();
+        let mut fired = Vec::new();
+        //1/64 adds up exactly, so one lap ends right back at 0
+        for _ in 0..64 {
+            fired.extend(advance(&mut playback, 1.0 / 64.0, LoopMode::Loop, &events));
+        }
+        assert_eq!(fired, vec!["a", "b", "c"]);
+        assert_eq!(playback.time, 0.0);
+    }
+
+    #[test]
+    fn large_step_fires_skipped_events_in_order() {
+        let events = quarters();
+        let mut playback = Playback::default();
+        assert_eq!(advance(&mut playback, 0.9, LoopMode::Loop, &events), vec!["a", "b", "c"]);
+        //two laps from 0.9 pass every event twice
+        assert_eq!(advance(&mut playback, 2.0, LoopMode::Loop, &events), vec!["a", "b", "c", "a", "b", "c"]);
+        assert!((playback.time - 0.9).abs() < 1e-5);
+        assert!(advance(&mut playback, 0.05, LoopMode::Loop, &events).is_empty());
+    }
+
+    #[test]
+    fn event_on_a_step_boundary_fires_once() {
+        let events = quarters();
+        let mut playback = Playback::default();
+        //the first step ends on "a" without passing it, the next one starts there
+        assert!(advance(&mut playback, 0.25, LoopMode::Loop, &events).is_empty());
+        assert_eq!(advance(&mut playback, 0.25, LoopMode::Loop, &events), vec!["a"]);
+        assert_eq!(advance(&mut playback, 0.25, LoopMode::Loop, &events), vec!["b"]);
+    }
+
+    #[test]
+    fn event_at_start_fires_every_loop() {
+        let events = vec![KeyframeEvent::new(0.0, "start"), KeyframeEvent::new(1.0, "end")];
+        let mut playback = Playback::default();
+        //a looping clip's end is its start, so both fire together
+        assert_eq!(advance(&mut playback, 0.5, LoopMode::Loop, &events), vec!["start", "end"]);
+        assert!(advance(&mut playback, 0.25, LoopMode::Loop, &events).is_empty());
+        assert_eq!(advance(&mut playback, 0.5, LoopMode::Loop, &events), vec!["start", "end"]);
+    }
+
+    #[test]
+    fn once_fires_end_event_once() {
+        let events = vec![KeyframeEvent::new(0.5, "middle"), KeyframeEvent::new(1.0, "end")];
+        let mut playback = Playback::default();
+        assert_eq!(advance(&mut playback, 5.0, LoopMode::Once, &events), vec!["middle", "end"]);
+        assert!(advance(&mut playback, 5.0, LoopMode::Once, &events).is_empty());
+    }
+
+    #[test]
+    fn ping_pong_fires_events_both_ways() {
+        let events = quarters();
+        let mut playback = Playback::default();
+        assert_eq!(advance(&mut playback, 2.0, LoopMode::PingPong, &events), vec!["a", "b", "c", "c", "b", "a"]);
+        assert_eq!(playback.time, 0.0);
+        assert_eq!(advance(&mut playback, 0.3, LoopMode::PingPong, &events), vec!["a"]);
+    }
+
+    #[test]
+    fn huge_step_stops_after_max_wraps() {
+        let events = vec![KeyframeEvent::new(0.5, "x")];
+        let mut playback = Playback::default();
+        let fired = advance(&mut playback, 1.0e6, LoopMode::Loop, &events);
+        assert_eq!(fired.len(), MAX_WRAPS as usize);
+    }
+}
@@ -0,0 +1,106 @@
+use std::f32::consts::PI;
+
+use serde::{ Deserialize, Serialize };
+
+/**
+ * Easing curves, the usual Penner set
+ * Elastic and back overshoot, so eased values can leave the 0 to 1 range
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+pub enum Ease {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+}
+
+//overshoot of the back curves, about 10%
+const BACK: f32 = 1.70158;
+
+impl Ease {
+    /**
+     * Eased progress for a linear progress `t`, clamped to 0 to 1 first
+     * Every curve gives 0 at 0 and 1 at 1
+     **/
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Ease::Linear => t,
+            Ease::QuadIn => t * t,
+            Ease::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::QuadInOut => if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 },
+            Ease::CubicIn => t * t * t,
+            Ease::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Ease::CubicInOut => if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 },
+            Ease::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Ease::SineOut => (t * PI / 2.0).sin(),
+            Ease::SineInOut => -((t * PI).cos() - 1.0) / 2.0,
+            Ease::ElasticIn => 1.0 - elastic_out(1.0 - t),
+            Ease::ElasticOut => elastic_out(t),
+            Ease::ElasticInOut => if t < 0.5 {
+                (1.0 - elastic_out(1.0 - 2.0 * t)) / 2.0
+            } else {
+                (1.0 + elastic_out(2.0 * t - 1.0)) / 2.0
+            },
+            Ease::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Ease::BounceOut => bounce_out(t),
+            Ease::BounceInOut => if t < 0.5 {
+                (1.0 - bounce_out(1.0 - 2.0 * t)) / 2.0
+            } else {
+                (1.0 + bounce_out(2.0 * t - 1.0)) / 2.0
+            },
+            Ease::BackIn => back_in(t),
+            Ease::BackOut => 1.0 - back_in(1.0 - t),
+            Ease::BackInOut => if t < 0.5 {
+                back_in(2.0 * t) / 2.0
+            } else {
+                1.0 - back_in(2.0 - 2.0 * t) / 2.0
+            },
+        }
+    }
+}
+
+fn elastic_out(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    2.0_f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * (2.0 * PI / 3.0)).sin() + 1.0
+}
+
+fn bounce_out(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+fn back_in(t: f32) -> f32 {
+    (BACK + 1.0) * t * t * t - BACK * t * t
+}
@@ -0,0 +1,52 @@
+pub mod clip;
+pub mod ease;
+pub mod sprite;
+pub mod skeleton;
+pub mod state;
+pub mod tween;
+pub mod world;
+
+pub use self::clip::{ AnimationClip, Interpolate, Interpolation, KeyframeEvent, LoopMode, Playback, Track };
+pub use self::ease::Ease;
+pub use self::sprite::{ SpriteAnimation, SpriteFrame, SpriteSheet };
+pub use self::skeleton::{ Bone, BoneChannel, Pose, SkeletalClip, Skeleton };
+pub use self::state::{ AnimationController, ClipSample, Condition, Motion, Parameter, State, StateMachine, Transition };
+pub use self::tween::{ Repeat, Tween, TweenHandle, TweenManager, TweenOwner };
+pub use self::world::{ AnimationWorld, Animator, AnimatorHandle, FiredEvent, SkeletalAnimator, SpriteAnimator };
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AnimationError {
+    Json(serde_json::Error),
+    InvalidSkeleton(String),
+    InvalidStateMachine(String),
+    UnknownState(String),
+}
+
+impl fmt::Display for AnimationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AnimationError::Json(e) => write!(f, "Animation JSON error: {}", e),
+            AnimationError::InvalidSkeleton(msg) => write!(f, "Invalid skeleton: {}", msg),
+            AnimationError::InvalidStateMachine(msg) => write!(f, "Invalid state machine: {}", msg),
+            AnimationError::UnknownState(name) => write!(f, "No animation state named {}", name),
+        }
+    }
+}
+
+impl Error for AnimationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnimationError::Json(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<serde_json::Error> for AnimationError {
+    fn from(e: serde_json::Error) -> AnimationError {
+        AnimationError::Json(e)
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::animation::AnimationError;
+use crate::animation::clip::{ AnimationClip, KeyframeEvent, LoopMode, Track };
+use crate::core::transform::Transform;
+use crate::math::{ Mat4, Quat, Vec3 };
+
+/**
+ * `rest` is relative to the parent bone, roots are relative to the model
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Bone {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub rest: Transform,
+}
+
+impl Bone {
+    pub fn new(name: &str, parent: Option<usize>, rest: Transform) -> Bone {
+        Bone { name: name.to_string(), parent, rest }
+    }
+}
+
+/**
+ * Bones ordered so parents come before their children, which lets poses be resolved in
+ * one pass. Inverse bind matrices come from the rest pose
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Skeleton {
+    bones: Vec<Bone>,
+    inverse_bind: Vec<Mat4>,
+    names: HashMap<String, usize>,
+}
+
+impl Skeleton {
+    pub fn new(bones: Vec<Bone>) -> Result<Skeleton, AnimationError> {
+        let mut names = HashMap::new();
+        for (i, bone) in bones.iter().enumerate() {
+            if let Some(parent) = bone.parent {
+                if parent >= i {
+                    return Err(AnimationError::InvalidSkeleton(format!("bone {} comes before its parent", bone.name)));
+                }
+            }
+            if names.insert(bone.name.clone(), i).is_some() {
+                return Err(AnimationError::InvalidSkeleton(format!("bone {} is named twice", bone.name)));
+            }
+        }
+        let mut skeleton = Skeleton { bones, inverse_bind: Vec::new(), names };
+        let rest = skeleton.rest_pose().model_matrices(&skeleton);
+        skeleton.inverse_bind = rest.iter().map(|x| x.inverse().unwrap_or(Mat4::IDENTITY)).collect();
+        Ok(skeleton)
+    }
+
+    pub fn bones(&self) -> &[Bone] {
+        &self.bones
+    }
+
+    pub fn len(&self) -> usize {
+        self.bones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bones.is_empty()
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.names.get(name).copied()
+    }
+
+    pub fn inverse_bind(&self) -> &[Mat4] {
+        &self.inverse_bind
+    }
+
+    pub fn rest_pose(&self) -> Pose {
+        Pose { locals: self.bones.iter().map(|x| x.rest).collect() }
+    }
+}
+
+/**
+ * Local transform of every bone in a skeleton
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    pub locals: Vec<Transform>,
+}
+
+impl Pose {
+    /**
+     * Moves this pose `weight` of the way to another one, 1 ends up at `other`
+     **/
+    pub fn blend(&mut self, other: &Pose, weight: f32) {
+        let weight = weight.clamp(0.0, 1.0);
+        for (a, b) in self.locals.iter_mut().zip(other.locals.iter()) {
+            *a = blend_transform(a, b, weight);
+        }
+    }
+
+    /**
+     * Bone to model space for every bone
+     **/
+    pub fn model_matrices(&self, skeleton: &Skeleton) -> Vec<Mat4> {
+        let mut model: Vec<Mat4> = Vec::with_capacity(self.locals.len());
+        for (i, local) in self.locals.iter().enumerate() {
+            let matrix = local.to_matrix();
+            let parent = skeleton.bones.get(i).and_then(|x| x.parent).and_then(|x| model.get(x));
+            model.push(match parent {
+                Some(parent) => *parent * matrix,
+                None => matrix
+            });
+        }
+        model
+    }
+
+    /**
+     * Matrices taking bind pose vertices to this pose, what a skinning shader wants
+     **/
+    pub fn skinning_matrices(&self, skeleton: &Skeleton) -> Vec<Mat4> {
+        self.model_matrices(skeleton).into_iter()
+            .zip(skeleton.inverse_bind.iter())
+            .map(|(model, inverse)| model * *inverse)
+            .collect()
+    }
+}
+
+/**
+ * Lerps translation and scale and slerps rotation, weight 1 gives `b`
+ **/
+pub fn blend_transform(a: &Transform, b: &Transform, weight: f32) -> Transform {
+    Transform {
+        translation: a.translation.lerp(b.translation, weight),
+        rotation: a.rotation.slerp(b.rotation, weight).normalize(),
+        scale: a.scale.lerp(b.scale, weight),
+    }
+}
+
+/**
+ * Keys for one bone, parts without a track keep the rest pose
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct BoneChannel {
+    pub bone: String,
+    #[serde(default)]
+    pub translation: Option<Track<Vec3>>,
+    #[serde(default)]
+    pub rotation: Option<Track<Quat>>,
+    #[serde(default)]
+    pub scale: Option<Track<Vec3>>,
+}
+
+impl BoneChannel {
+    pub fn new(bone: &str) -> BoneChannel {
+        BoneChannel { bone: bone.to_string(), translation: None, rotation: None, scale: None }
+    }
+}
+
+/**
+ * Keyframed bone transforms, bones are matched by name so a clip works on any skeleton with
+ * the same bone names
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SkeletalClip {
+    pub name: String,
+    pub duration: f32,
+    #[serde(default)]
+    pub loop_mode: LoopMode,
+    pub channels: Vec<BoneChannel>,
+    #[serde(default)]
+    pub events: Vec<KeyframeEvent>,
+}
+
+impl SkeletalClip {
+    /**
+     * The clip is as long as its longest track
+     **/
+    pub fn new(name: &str, channels: Vec<BoneChannel>, loop_mode: Option<LoopMode>) -> SkeletalClip {
+        let duration = channels.iter()
+            .flat_map(|x| [
+                x.translation.as_ref().map(|x| x.duration()),
+                x.rotation.as_ref().map(|x| x.duration()),
+                x.scale.as_ref().map(|x| x.duration())
+            ])
+            .flatten()
+            .fold(0.0, f32::max);
+        SkeletalClip { name: name.to_string(), duration, loop_mode: loop_mode.unwrap_or_default(), channels, events: Vec::new() }
+    }
+
+    pub fn from_json(json: &str) -> Result<SkeletalClip, AnimationError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn with_event(mut self, time: f32, name: &str) -> SkeletalClip {
+        self.events.push(KeyframeEvent::new(time, name));
+        self
+    }
+
+    /**
+     * Writes the clip at a time over a pose, channels for bones the skeleton doesn't have are
+     * skipped
+     **/
+    pub fn sample(&self, skeleton: &Skeleton, time: f32, pose: &mut Pose) {
+        for channel in &self.channels {
+            let local = match skeleton.find(&channel.bone).and_then(|x| pose.locals.get_mut(x)) {
+                Some(x) => x,
+                None => continue
+            };
+            if let Some(x) = channel.translation.as_ref().and_then(|x| x.sample(time)) {
+                local.translation = x;
+            }
+            if let Some(x) = channel.rotation.as_ref().and_then(|x| x.sample(time)) {
+                local.rotation = x.normalize();
+            }
+            if let Some(x) = channel.scale.as_ref().and_then(|x| x.sample(time)) {
+                local.scale = x;
+            }
+        }
+    }
+}
+
+impl AnimationClip for SkeletalClip {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    fn events(&self) -> &[KeyframeEvent] {
+        &self.events
+    }
+}
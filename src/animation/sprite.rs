@@ -0,0 +1,194 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::animation::clip::{ AnimationClip, KeyframeEvent, LoopMode };
+use crate::math::Vec2;
+
+/**
+ * A texture cut into a grid of equal frames, numbered left to right then top to bottom
+ * `margin` is around the whole grid and `spacing` between frames, both in pixels
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SpriteSheet {
+    pub texture: String,
+    pub texture_size: (u32, u32),
+    pub frame_size: (u32, u32),
+    #[serde(default)]
+    pub margin: u32,
+    #[serde(default)]
+    pub spacing: u32,
+}
+
+impl SpriteSheet {
+    pub fn new(texture: &str, texture_size: (u32, u32), frame_size: (u32, u32)) -> SpriteSheet {
+        SpriteSheet { texture: texture.to_string(), texture_size, frame_size, margin: 0, spacing: 0 }
+    }
+
+    pub fn columns(&self) -> u32 {
+        let stride = self.frame_size.0 + self.spacing;
+        if stride == 0 {
+            return 0;
+        }
+        (self.texture_size.0.saturating_sub(self.margin * 2) + self.spacing) / stride
+    }
+
+    pub fn rows(&self) -> u32 {
+        let stride = self.frame_size.1 + self.spacing;
+        if stride == 0 {
+            return 0;
+        }
+        (self.texture_size.1.saturating_sub(self.margin * 2) + self.spacing) / stride
+    }
+
+    pub fn len(&self) -> u32 {
+        self.columns() * self.rows()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * Top left of a frame in pixels
+     **/
+    pub fn frame_position(&self, index: u32) -> Option<(u32, u32)> {
+        if index >= self.len() {
+            return None;
+        }
+        let (column, row) = (index % self.columns(), index / self.columns());
+        Some((
+            self.margin + column * (self.frame_size.0 + self.spacing),
+            self.margin + row * (self.frame_size.1 + self.spacing)
+        ))
+    }
+
+    /**
+     * Texture coordinates of a frame's top left and bottom right corners
+     **/
+    pub fn uv(&self, index: u32) -> Option<(Vec2, Vec2)> {
+        let (x, y) = self.frame_position(index)?;
+        let size = Vec2::new(self.texture_size.0 as f32, self.texture_size.1 as f32);
+        let min = Vec2::new(x as f32 / size.x, y as f32 / size.y);
+        let max = Vec2::new((x + self.frame_size.0) as f32 / size.x, (y + self.frame_size.1) as f32 / size.y);
+        Some((min, max))
+    }
+}
+
+/**
+ * One frame of a flipbook, `index` is into the sprite sheet
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SpriteFrame {
+    pub index: u32,
+    pub duration: f32,
+}
+
+/**
+ * Flipbook animation through sprite sheet frames, each shown for its own duration
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct SpriteAnimation {
+    pub name: String,
+    pub frames: Vec<SpriteFrame>,
+    #[serde(default)]
+    pub loop_mode: LoopMode,
+    #[serde(default)]
+    pub events: Vec<KeyframeEvent>,
+}
+
+impl SpriteAnimation {
+    pub fn new(name: &str, frames: Vec<SpriteFrame>, loop_mode: Option<LoopMode>) -> SpriteAnimation {
+        SpriteAnimation { name: name.to_string(), frames, loop_mode: loop_mode.unwrap_or_default(), events: Vec::new() }
+    }
+
+    /**
+     * `count` frames of the sheet from `first` at a fixed frame rate
+     **/
+    pub fn from_range(name: &str, first: u32, count: u32, fps: f32, loop_mode: Option<LoopMode>) -> SpriteAnimation {
+        let duration = if fps > 0.0 { 1.0 / fps } else { 0.0 };
+        let frames = (first..first + count).map(|index| SpriteFrame { index, duration }).collect();
+        SpriteAnimation::new(name, frames, loop_mode)
+    }
+
+    pub fn with_event(mut self, time: f32, name: &str) -> SpriteAnimation {
+        self.events.push(KeyframeEvent::new(time, name));
+        self
+    }
+
+    /**
+     * Position in `frames` showing at a time, the last frame once past the end
+     **/
+    pub fn frame_at(&self, time: f32) -> Option<usize> {
+        if self.frames.is_empty() {
+            return None;
+        }
+        let mut end = 0.0;
+        for (i, frame) in self.frames.iter().enumerate() {
+            end += frame.duration;
+            if time < end {
+                return Some(i);
+            }
+        }
+        Some(self.frames.len() - 1)
+    }
+
+    /**
+     * Sheet index showing at a time
+     **/
+    pub fn sheet_index(&self, time: f32) -> Option<u32> {
+        self.frame_at(time).map(|x| self.frames[x].index)
+    }
+}
+
+impl AnimationClip for SpriteAnimation {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn duration(&self) -> f32 {
+        self.frames.iter().map(|x| x.duration).sum()
+    }
+
+    fn loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    fn events(&self) -> &[KeyframeEvent] {
+        &self.events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_changes_on_boundaries() {
+        let animation = SpriteAnimation::from_range("walk", 4, 4, 4.0, None);
+        assert_eq!(animation.duration(), 1.0);
+        assert_eq!(animation.sheet_index(0.0), Some(4));
+        assert_eq!(animation.sheet_index(0.24), Some(4));
+        assert_eq!(animation.sheet_index(0.25), Some(5));
+        assert_eq!(animation.sheet_index(0.75), Some(7));
+        assert_eq!(animation.sheet_index(5.0), Some(7));
+        assert_eq!(SpriteAnimation::new("empty", Vec::new(), None).frame_at(0.0), None);
+    }
+
+    #[test]
+    fn uneven_frame_durations() {
+        let frames = vec![SpriteFrame { index: 0, duration: 0.5 }, SpriteFrame { index: 1, duration: 0.1 }, SpriteFrame { index: 2, duration: 0.4 }];
+        let animation = SpriteAnimation::new("uneven", frames, None);
+        assert_eq!(animation.frame_at(0.55), Some(1));
+        assert_eq!(animation.frame_at(0.6), Some(2));
+    }
+
+    #[test]
+    fn sheet_grid_positions() {
+        let sheet = SpriteSheet::new("sheet.png", (64, 32), (16, 16));
+        assert_eq!((sheet.columns(), sheet.rows(), sheet.len()), (4, 2, 8));
+        assert_eq!(sheet.frame_position(5), Some((16, 16)));
+        assert_eq!(sheet.frame_position(8), None);
+    }
+}
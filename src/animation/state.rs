@@ -0,0 +1,650 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::animation::AnimationError;
+use crate::animation::clip::{ AnimationClip, KeyframeEvent, LoopMode, Playback };
+
+/**
+ * Values a state machine's transitions test, set by the game
+ * Triggers stay set until a transition uses them
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Parameter {
+    Float(f32),
+    Int(i32),
+    Bool(bool),
+    Trigger(bool),
+}
+
+impl Parameter {
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Parameter::Float(x) => *x,
+            Parameter::Int(x) => *x as f32,
+            Parameter::Bool(x) | Parameter::Trigger(x) => if *x { 1.0 } else { 0.0 }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Condition {
+    Greater(String, f32),
+    Less(String, f32),
+    Equals(String, i32),
+    True(String),
+    False(String),
+    Trigger(String),
+}
+
+impl Condition {
+    /**
+     * Parameters that were never set count as 0 and false
+     **/
+    pub fn met(&self, parameters: &HashMap<String, Parameter>) -> bool {
+        let value = |name: &String| parameters.get(name).map_or(0.0, |x| x.as_f32());
+        match self {
+            Condition::Greater(name, x) => value(name) > *x,
+            Condition::Less(name, x) => value(name) < *x,
+            Condition::Equals(name, x) => match parameters.get(name) {
+                Some(Parameter::Int(v)) => v == x,
+                Some(p) => p.as_f32() == *x as f32,
+                None => *x == 0
+            },
+            Condition::True(name) | Condition::Trigger(name) => value(name) != 0.0,
+            Condition::False(name) => value(name) == 0.0
+        }
+    }
+}
+
+/**
+ * What a state plays, clips are looked up by name in the animator's clips
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Motion {
+    Clip(String),
+    /**
+     * Blends between the two clips whose thresholds the parameter is between, clips play
+     * in step with each other so a walk and run keep their feet together
+     **/
+    Blend1D { parameter: String, clips: Vec<(f32, String)> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct State {
+    pub name: String,
+    pub motion: Motion,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+impl State {
+    pub fn new(name: &str, motion: Motion) -> State {
+        State { name: name.to_string(), motion, speed: 1.0 }
+    }
+
+    pub fn clip(name: &str, clip: &str) -> State {
+        State::new(name, Motion::Clip(clip.to_string()))
+    }
+}
+
+/**
+ * Moves from one state to another when all of its conditions are met
+ * `from` of None is any state. `exit_time` holds the transition until the current state is
+ * that far through, 0 to 1. A transition with no conditions or exit time is taken right away
+ * `duration` is the crossfade in seconds
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Transition {
+    pub from: Option<String>,
+    pub to: String,
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    #[serde(default)]
+    pub duration: f32,
+    #[serde(default)]
+    pub exit_time: Option<f32>,
+}
+
+impl Transition {
+    pub fn new(from: Option<&str>, to: &str, conditions: Vec<Condition>, duration: f32) -> Transition {
+        Transition { from: from.map(|x| x.to_string()), to: to.to_string(), conditions, duration, exit_time: None }
+    }
+
+    pub fn with_exit_time(mut self, exit_time: f32) -> Transition {
+        self.exit_time = Some(exit_time);
+        self
+    }
+}
+
+/**
+ * States and the transitions between them, starts in the first state
+ * Transitions are checked in order and the first one that's ready is taken
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct StateMachine {
+    pub states: Vec<State>,
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+impl StateMachine {
+    pub fn new(states: Vec<State>, transitions: Vec<Transition>) -> Result<StateMachine, AnimationError> {
+        let machine = StateMachine { states, transitions };
+        machine.validate()?;
+        Ok(machine)
+    }
+
+    pub fn from_json(json: &str) -> Result<StateMachine, AnimationError> {
+        let machine: StateMachine = serde_json::from_str(json)?;
+        machine.validate()?;
+        Ok(machine)
+    }
+
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|x| x.name == name)
+    }
+
+    fn validate(&self) -> Result<(), AnimationError> {
+        if self.states.is_empty() {
+            return Err(AnimationError::InvalidStateMachine("no states".to_string()));
+        }
+        for transition in &self.transitions {
+            for name in transition.from.iter().chain(std::iter::once(&transition.to)) {
+                if self.find(name).is_none() {
+                    return Err(AnimationError::UnknownState(name.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * One clip to sample and how much of the final result it is
+ * `time` is in the clip's own seconds
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipSample {
+    pub clip: usize,
+    pub time: f32,
+    pub weight: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Active {
+    state: Option<usize>,
+    motion: Motion,
+    playback: Playback,
+    //length at the last update, blends change length with their parameter
+    duration: Option<f32>,
+}
+
+impl Active {
+    //keeps the phase when a blend's length changes so it doesn't jump
+    fn rescale(&mut self, duration: f32) {
+        if let Some(last) = self.duration.filter(|x| *x > 0.0 && *x != duration) {
+            self.playback.time *= duration / last;
+        }
+        self.duration = Some(duration);
+    }
+}
+
+/**
+ * Runs a state machine, or clips played directly, for one animator
+ * Works with any clip type, what it produces is which clips to sample, when and how much
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationController {
+    machine: Option<StateMachine>,
+    parameters: HashMap<String, Parameter>,
+    current: Option<Active>,
+    previous: Option<Active>,
+    fade: f32,
+    fade_duration: f32,
+}
+
+impl AnimationController {
+    pub fn new(machine: Option<StateMachine>) -> AnimationController {
+        let mut controller = AnimationController {
+            machine,
+            parameters: HashMap::new(),
+            current: None,
+            previous: None,
+            fade: 0.0,
+            fade_duration: 0.0,
+        };
+        if controller.machine.is_some() {
+            controller.enter(0, 0.0);
+        }
+        controller
+    }
+
+    pub fn machine(&self) -> Option<&StateMachine> {
+        self.machine.as_ref()
+    }
+
+    /**
+     * Name of the current state, None while playing a clip directly
+     **/
+    pub fn state(&self) -> Option<&str> {
+        let machine = self.machine.as_ref()?;
+        let index = self.current.as_ref()?.state?;
+        Some(&machine.states[index].name)
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<Parameter> {
+        self.parameters.get(name).copied()
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.parameters.insert(name.to_string(), Parameter::Float(value));
+    }
+
+    pub fn set_int(&mut self, name: &str, value: i32) {
+        self.parameters.insert(name.to_string(), Parameter::Int(value));
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.parameters.insert(name.to_string(), Parameter::Bool(value));
+    }
+
+    pub fn set_trigger(&mut self, name: &str) {
+        self.parameters.insert(name.to_string(), Parameter::Trigger(true));
+    }
+
+    /**
+     * Jumps to a state, fading over `duration` seconds. False if there's no such state
+     **/
+    pub fn go_to(&mut self, state: &str, duration: f32) -> bool {
+        match self.machine.as_ref().and_then(|x| x.find(state)) {
+            Some(x) => {
+                self.enter(x, duration);
+                true
+            },
+            None => false
+        }
+    }
+
+    /**
+     * Plays a clip outside of the state machine, fading over `duration` seconds
+     * The machine takes over again on its next transition from any state
+     **/
+    pub fn play(&mut self, clip: &str, duration: f32) {
+        let active = Active { state: None, motion: Motion::Clip(clip.to_string()), playback: Playback::new(None), duration: None };
+        self.start(active, duration);
+    }
+
+    /**
+     * Whether the current motion played to the end of a clip that doesn't loop
+     **/
+    pub fn finished(&self) -> bool {
+        self.current.as_ref().is_some_and(|x| x.playback.finished())
+    }
+
+    /**
+     * How far into the current state a crossfade is, 1 when there's no fade
+     **/
+    pub fn fade_weight(&self) -> f32 {
+        match &self.previous {
+            Some(_) if self.fade_duration > 0.0 => (self.fade / self.fade_duration).clamp(0.0, 1.0),
+            _ => 1.0
+        }
+    }
+
+    /**
+     * Takes any ready transition then moves time on, calling `fired` with the clip and each
+     * keyframe event the current state passes. Fading out states don't fire events
+     **/
+    pub fn update<C: AnimationClip, F: FnMut(&C, &KeyframeEvent)>(&mut self, clips: &[C], dt: f32, mut fired: F) {
+        self.check_transitions(clips);
+
+        let parameters = &self.parameters;
+        if let Some(active) = self.current.as_mut() {
+            let (duration, mode, events, heaviest) = timing(&active.motion, clips, parameters);
+            active.rescale(duration);
+            if let Some(clip) = heaviest.map(|x| &clips[x]) {
+                active.playback.advance(dt, duration, mode, &events, |x| fired(clip, x));
+            }
+        }
+        if let Some(active) = self.previous.as_mut() {
+            let (duration, mode, _, _) = timing(&active.motion, clips, parameters);
+            active.rescale(duration);
+            active.playback.advance(dt, duration, mode, &[], |_| {});
+        }
+        if self.previous.is_some() {
+            self.fade += dt.max(0.0);
+            if self.fade >= self.fade_duration {
+                self.previous = None;
+            }
+        }
+    }
+
+    /**
+     * The clips to sample for the current frame, fading out ones first, weights add up to 1
+     **/
+    pub fn samples<C: AnimationClip>(&self, clips: &[C]) -> Vec<ClipSample> {
+        let weight = self.fade_weight();
+        let mut samples = Vec::new();
+        if let Some(active) = self.previous.as_ref().filter(|_| weight < 1.0) {
+            self.push_samples(active, clips, 1.0 - weight, &mut samples);
+        }
+        if let Some(active) = &self.current {
+            self.push_samples(active, clips, weight, &mut samples);
+        }
+        samples
+    }
+
+    /**
+     * The current state's samples alone, for animation that can't blend like sprites
+     **/
+    pub fn current_samples<C: AnimationClip>(&self, clips: &[C]) -> Vec<ClipSample> {
+        let mut samples = Vec::new();
+        if let Some(active) = &self.current {
+            self.push_samples(active, clips, 1.0, &mut samples);
+        }
+        samples
+    }
+
+    fn push_samples<C: AnimationClip>(&self, active: &Active, clips: &[C], weight: f32, samples: &mut Vec<ClipSample>) {
+        let (duration, _, _, _) = timing(&active.motion, clips, &self.parameters);
+        let phase = active.playback.normalized(duration);
+        for (clip, w) in weights(&active.motion, clips, &self.parameters) {
+            samples.push(ClipSample { clip, time: phase * clips[clip].duration(), weight: w * weight });
+        }
+    }
+
+    fn check_transitions<C: AnimationClip>(&mut self, clips: &[C]) {
+        let machine = match &self.machine {
+            Some(x) => x,
+            None => return
+        };
+        let current = self.current.as_ref().and_then(|x| x.state);
+        let progress = match &self.current {
+            Some(active) => {
+                let (duration, _, _, _) = timing(&active.motion, clips, &self.parameters);
+                active.playback.normalized(duration)
+            },
+            None => 1.0
+        };
+        let mut taken = None;
+        for transition in &machine.transitions {
+            let to = match machine.find(&transition.to) {
+                Some(x) => x,
+                None => continue
+            };
+            let from_ok = match &transition.from {
+                Some(from) => current.is_some() && machine.find(from) == current,
+                None => current != Some(to)
+            };
+            if !from_ok || transition.exit_time.is_some_and(|x| progress < x) {
+                continue;
+            }
+            if transition.conditions.iter().all(|x| x.met(&self.parameters)) {
+                taken = Some((to, transition.duration, transition.conditions.clone()));
+                break;
+            }
+        }
+        if let Some((to, duration, conditions)) = taken {
+            for condition in conditions {
+                if let Condition::Trigger(name) = condition {
+                    self.parameters.insert(name, Parameter::Trigger(false));
+                }
+            }
+            self.enter(to, duration);
+        }
+    }
+
+    fn enter(&mut self, state: usize, duration: f32) {
+        let (motion, speed) = match self.machine.as_ref().and_then(|x| x.states.get(state)) {
+            Some(x) => (x.motion.clone(), x.speed),
+            None => return
+        };
+        self.start(Active { state: Some(state), motion, playback: Playback::new(Some(speed)), duration: None }, duration);
+    }
+
+    fn start(&mut self, active: Active, duration: f32) {
+        let previous = self.current.replace(active);
+        if duration > 0.0 && previous.is_some() {
+            self.previous = previous;
+            self.fade = 0.0;
+            self.fade_duration = duration;
+        } else {
+            self.previous = None;
+        }
+    }
+}
+
+fn find_clip<C: AnimationClip>(clips: &[C], name: &str) -> Option<usize> {
+    clips.iter().position(|x| x.name() == name)
+}
+
+//clips a motion is made of with their weights, missing clips are left out
+fn weights<C: AnimationClip>(motion: &Motion, clips: &[C], parameters: &HashMap<String, Parameter>) -> Vec<(usize, f32)> {
+    match motion {
+        Motion::Clip(name) => find_clip(clips, name).map(|x| (x, 1.0)).into_iter().collect(),
+        Motion::Blend1D { parameter, clips: points } => {
+            let mut points: Vec<(f32, usize)> = points.iter().filter_map(|(t, name)| find_clip(clips, name).map(|x| (*t, x))).collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let value = parameters.get(parameter).map_or(0.0, |x| x.as_f32());
+            let (first, last) = match (points.first(), points.last()) {
+                (Some(a), Some(b)) => (*a, *b),
+                _ => return Vec::new()
+            };
+            if value <= first.0 {
+                return vec![(first.1, 1.0)];
+            }
+            if value >= last.0 {
+                return vec![(last.1, 1.0)];
+            }
+            let next = points.partition_point(|x| x.0 <= value);
+            let (a, b) = (points[next - 1], points[next]);
+            let t = if b.0 > a.0 { (value - a.0) / (b.0 - a.0) } else { 1.0 };
+            vec![(a.1, 1.0 - t), (b.1, t)]
+        }
+    }
+}
+
+//length, loop mode, events and heaviest clip of a motion, blends take the loop mode and
+//events from the heaviest clip with the length averaged by weight
+fn timing<C: AnimationClip>(motion: &Motion, clips: &[C], parameters: &HashMap<String, Parameter>) -> (f32, LoopMode, Vec<KeyframeEvent>, Option<usize>) {
+    let weights = weights(motion, clips, parameters);
+    let index = match weights.iter().max_by(|a, b| a.1.total_cmp(&b.1)) {
+        Some(x) => x.0,
+        None => return (0.0, LoopMode::Once, Vec::new(), None)
+    };
+    let heaviest = &clips[index];
+    let duration: f32 = weights.iter().map(|(x, w)| clips[*x].duration() * w).sum();
+    let scale = if heaviest.duration() > 0.0 { duration / heaviest.duration() } else { 0.0 };
+    let events = heaviest.events().iter().map(|x| KeyframeEvent { time: x.time * scale, name: x.name.clone() }).collect();
+    (duration, heaviest.loop_mode(), events, Some(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::SpriteAnimation;
+
+    fn clips() -> Vec<SpriteAnimation> {
+        vec![
+            SpriteAnimation::from_range("idle", 0, 4, 4.0, None),
+            SpriteAnimation::from_range("walk", 4, 4, 2.0, None),
+            SpriteAnimation::from_range("run", 8, 4, 4.0, None),
+            SpriteAnimation::from_range("attack", 12, 4, 4.0, Some(LoopMode::Once)).with_event(0.5, "hit"),
+        ]
+    }
+
+    fn machine() -> StateMachine {
+        StateMachine::new(
+            vec![
+                State::clip("idle", "idle"),
+                State::new("move", Motion::Blend1D { parameter: "speed".to_string(), clips: vec![(1.0, "walk".to_string()), (3.0, "run".to_string())] }),
+                State::clip("attack", "attack"),
+            ],
+            vec![
+                Transition::new(Some("idle"), "move", vec![Condition::Greater("speed".to_string(), 0.5)], 0.5),
+                Transition::new(Some("move"), "idle", vec![Condition::Less("speed".to_string(), 0.5)], 0.0),
+                Transition::new(None, "attack", vec![Condition::Trigger("attack".to_string())], 0.0),
+                Transition::new(Some("attack"), "idle", Vec::new(), 0.0).with_exit_time(1.0),
+            ]
+        ).unwrap()
+    }
+
+    fn update(controller: &mut AnimationController, clips: &[SpriteAnimation], dt: f32) -> Vec<String> {
+        let mut fired = Vec::new();
+        controller.update(clips, dt, |clip, event| fired.push(format!("{}:{}", clip.name, event.name)));
+        fired
+    }
+
+    #[test]
+    fn rejects_unknown_states() {
+        assert!(StateMachine::new(Vec::new(), Vec::new()).is_err());
+        let unknown = StateMachine::new(vec![State::clip("idle", "idle")], vec![Transition::new(None, "jump", Vec::new(), 0.0)]);
+        assert!(matches!(unknown, Err(AnimationError::UnknownState(x)) if x == "jump"));
+    }
+
+    #[test]
+    fn starts_in_first_state() {
+        let controller = AnimationController::new(Some(machine()));
+        assert_eq!(controller.state(), Some("idle"));
+        assert_eq!(controller.samples(&clips()), vec![ClipSample { clip: 0, time: 0.0, weight: 1.0 }]);
+    }
+
+    #[test]
+    fn conditions_move_between_states() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        update(&mut controller, &clips, 0.1);
+        assert_eq!(controller.state(), Some("idle"));
+        controller.set_float("speed", 1.0);
+        update(&mut controller, &clips, 0.1);
+        assert_eq!(controller.state(), Some("move"));
+        controller.set_float("speed", 0.0);
+        update(&mut controller, &clips, 0.1);
+        assert_eq!(controller.state(), Some("idle"));
+    }
+
+    #[test]
+    fn crossfade_blends_weights() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.set_float("speed", 1.0);
+        update(&mut controller, &clips, 0.25);
+        //the fade starts with the transition and has moved on by this update's dt
+        assert_eq!(controller.fade_weight(), 0.5);
+        let samples = controller.samples(&clips);
+        assert_eq!(samples.len(), 2);
+        assert_eq!((samples[0].clip, samples[0].weight), (0, 0.5));
+        assert_eq!((samples[1].clip, samples[1].weight), (1, 0.5));
+        assert_eq!(controller.current_samples(&clips).len(), 1);
+
+        update(&mut controller, &clips, 0.25);
+        assert_eq!(controller.fade_weight(), 1.0);
+        assert_eq!(controller.samples(&clips), vec![ClipSample { clip: 1, time: 0.5, weight: 1.0 }]);
+    }
+
+    #[test]
+    fn blend_weights_follow_parameter() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("move", 0.0);
+        controller.set_float("speed", 2.0);
+        let samples = controller.samples(&clips);
+        assert_eq!(samples.iter().map(|x| (x.clip, x.weight)).collect::<Vec<_>>(), vec![(1, 0.5), (2, 0.5)]);
+        controller.set_float("speed", 0.75);
+        assert_eq!(controller.samples(&clips).iter().map(|x| (x.clip, x.weight)).collect::<Vec<_>>(), vec![(1, 1.0)]);
+        controller.set_float("speed", 10.0);
+        assert_eq!(controller.samples(&clips).iter().map(|x| (x.clip, x.weight)).collect::<Vec<_>>(), vec![(2, 1.0)]);
+    }
+
+    #[test]
+    fn blended_clips_stay_in_phase() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("move", 0.0);
+        controller.set_float("speed", 2.0);
+        //walk is 2s and run 1s, half of each makes a 1.5s cycle
+        update(&mut controller, &clips, 0.75);
+        let samples = controller.samples(&clips);
+        assert!((samples[0].time - 1.0).abs() < 1e-5 && (samples[1].time - 0.5).abs() < 1e-5, "{:?}", samples);
+    }
+
+    #[test]
+    fn trigger_is_used_up() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.set_trigger("attack");
+        update(&mut controller, &clips, 0.1);
+        assert_eq!(controller.state(), Some("attack"));
+        assert_eq!(controller.parameter("attack"), Some(Parameter::Trigger(false)));
+        update(&mut controller, &clips, 0.1);
+        assert_eq!(controller.state(), Some("attack"));
+    }
+
+    #[test]
+    fn exit_time_holds_transition() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("attack", 0.0);
+        update(&mut controller, &clips, 0.5);
+        update(&mut controller, &clips, 0.25);
+        assert_eq!(controller.state(), Some("attack"));
+        update(&mut controller, &clips, 0.5);
+        assert!(controller.finished());
+        update(&mut controller, &clips, 0.0);
+        assert_eq!(controller.state(), Some("idle"));
+    }
+
+    #[test]
+    fn events_fire_once_per_crossing() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("attack", 0.0);
+        let mut fired = Vec::new();
+        for _ in 0..20 {
+            fired.extend(update(&mut controller, &clips, 0.125));
+        }
+        assert_eq!(fired, vec!["attack:hit"]);
+    }
+
+    #[test]
+    fn large_dt_fires_skipped_event() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("attack", 0.0);
+        assert_eq!(update(&mut controller, &clips, 5.0), vec!["attack:hit"]);
+        assert!(update(&mut controller, &clips, 5.0).is_empty());
+    }
+
+    #[test]
+    fn fading_out_state_fires_nothing() {
+        let clips = clips();
+        let mut controller = AnimationController::new(Some(machine()));
+        controller.go_to("attack", 0.0);
+        update(&mut controller, &clips, 0.25);
+        controller.go_to("idle", 1.0);
+        assert!(update(&mut controller, &clips, 0.5).is_empty());
+    }
+
+    #[test]
+    fn play_runs_clip_outside_machine() {
+        let clips = clips();
+        let mut controller = AnimationController::new(None);
+        assert!(controller.samples(&clips).is_empty());
+        controller.play("walk", 0.0);
+        update(&mut controller, &clips, 0.5);
+        assert_eq!(controller.state(), None);
+        assert_eq!(controller.samples(&clips), vec![ClipSample { clip: 1, time: 0.5, weight: 1.0 }]);
+        assert!(!controller.go_to("idle", 0.0));
+    }
+}
@@ -0,0 +1,466 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::animation::clip::Interpolate;
+use crate::animation::ease::Ease;
+use crate::core::layers::LayerStack;
+use crate::physics::arena::Arena;
+
+//time passed to a tween that is being entered from before its start, or after its end when
+//going backwards, so zero length steps and callbacks on the boundary still fire
+const BEFORE: f32 = -1.0;
+//most iterations one update plays through, a long hitch skips the rest
+const MAX_ITERATIONS: u32 = 64;
+
+/**
+ * How many more times a tween plays after the first
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Repeat {
+    Times(u32),
+    Forever,
+}
+
+impl Default for Repeat {
+    fn default() -> Repeat {
+        Repeat::Times(0)
+    }
+}
+
+/**
+ * Something a value tween can write to
+ **/
+trait TweenTrack: Send {
+    fn duration(&self) -> f32;
+    fn apply(&mut self, t: f32);
+}
+
+struct ValueTrack<T, F> {
+    from: T,
+    to: T,
+    duration: f32,
+    ease: Ease,
+    set: F,
+}
+
+impl<T: Interpolate + Send, F: FnMut(T) + Send> TweenTrack for ValueTrack<T, F> {
+    fn duration(&self) -> f32 {
+        self.duration
+    }
+
+    fn apply(&mut self, t: f32) {
+        (self.set)(self.from.interpolate(self.to, self.ease.apply(t)));
+    }
+}
+
+enum TweenKind {
+    Value(Box<dyn TweenTrack>),
+    Wait(f32),
+    Call(Box<dyn FnMut() + Send>),
+    Sequence(Vec<Tween>),
+    Parallel(Vec<Tween>),
+}
+
+impl TweenKind {
+    fn duration(&self) -> f32 {
+        match self {
+            TweenKind::Value(x) => x.duration(),
+            TweenKind::Wait(x) => *x,
+            TweenKind::Call(_) => 0.0,
+            TweenKind::Sequence(x) => x.iter().map(|x| x.duration()).sum(),
+            TweenKind::Parallel(x) => x.iter().map(|x| x.duration()).fold(0.0, f32::max),
+        }
+    }
+
+    /**
+     * Plays from one local time to another, either can be outside 0 to duration
+     **/
+    fn render(&mut self, from: f32, to: f32) {
+        match self {
+            TweenKind::Value(track) => {
+                let duration = track.duration();
+                let t = if duration > 0.0 { to / duration } else if to >= 0.0 { 1.0 } else { 0.0 };
+                track.apply(t.clamp(0.0, 1.0));
+            },
+            TweenKind::Wait(_) => (),
+            TweenKind::Call(call) => if (from < 0.0 && to >= 0.0) || (to <= 0.0 && from > 0.0) {
+                call();
+            },
+            TweenKind::Sequence(children) => {
+                let mut start = 0.0;
+                let mut spans = Vec::with_capacity(children.len());
+                for child in children.iter() {
+                    let duration = child.duration();
+                    spans.push((start, start + duration));
+                    start += duration;
+                }
+                //children in the order time passes through them, so the last one touched wins
+                if to >= from {
+                    for (child, (start, end)) in children.iter_mut().zip(spans) {
+                        if to >= start && from <= end {
+                            child.render(from - start, to - start);
+                        }
+                    }
+                } else {
+                    for (child, (start, end)) in children.iter_mut().zip(spans).rev() {
+                        if from >= start && to <= end {
+                            child.render(from - start, to - start);
+                        }
+                    }
+                }
+            },
+            TweenKind::Parallel(children) => for child in children.iter_mut() {
+                child.render(from, to);
+            },
+        }
+    }
+}
+
+/**
+ * A value animated over time, or a group of them
+ * Tweens write through a setter rather than holding the property, so anything that can be
+ * reached from a `Send` closure can be tweened. Build them up with the `with_` methods and hand
+ * them to a TweenManager
+ **/
+pub struct Tween {
+    kind: TweenKind,
+    delay: f32,
+    repeat: Repeat,
+    yoyo: bool,
+    on_complete: Option<Box<dyn FnMut() + Send>>,
+}
+
+impl Tween {
+    fn from_kind(kind: TweenKind) -> Tween {
+        Tween { kind, delay: 0.0, repeat: Repeat::default(), yoyo: false, on_complete: None }
+    }
+
+    /**
+     * Moves a value from `from` to `to` over `duration` seconds, `set` gets every new value
+     **/
+    pub fn value<T, F>(from: T, to: T, duration: f32, ease: Option<Ease>, set: F) -> Tween
+    where T: Interpolate + Send + 'static, F: FnMut(T) + Send + 'static {
+        Tween::from_kind(TweenKind::Value(Box::new(ValueTrack {
+            from,
+            to,
+            duration: duration.max(0.0),
+            ease: ease.unwrap_or_default(),
+            set
+        })))
+    }
+
+    /**
+     * Does nothing for a while, for gaps in sequences
+     **/
+    pub fn wait(duration: f32) -> Tween {
+        Tween::from_kind(TweenKind::Wait(duration.max(0.0)))
+    }
+
+    /**
+     * Runs a callback when time passes it, in either direction
+     **/
+    pub fn call<F: FnMut() + Send + 'static>(call: F) -> Tween {
+        Tween::from_kind(TweenKind::Call(Box::new(call)))
+    }
+
+    /**
+     * Plays tweens one after the other
+     **/
+    pub fn sequence(tweens: Vec<Tween>) -> Tween {
+        Tween::from_kind(TweenKind::Sequence(tweens))
+    }
+
+    /**
+     * Plays tweens together, the group lasts as long as its longest tween
+     **/
+    pub fn parallel(tweens: Vec<Tween>) -> Tween {
+        Tween::from_kind(TweenKind::Parallel(tweens))
+    }
+
+    /**
+     * Waits before the first play, repeats don't wait again
+     **/
+    pub fn with_delay(mut self, delay: f32) -> Tween {
+        self.delay = delay.max(0.0);
+        self
+    }
+
+    /**
+     * Zero length tweens ignore repeats
+     **/
+    pub fn with_repeat(mut self, repeat: Repeat) -> Tween {
+        self.repeat = repeat;
+        self
+    }
+
+    /**
+     * Every other repeat plays backwards
+     **/
+    pub fn with_yoyo(mut self, yoyo: bool) -> Tween {
+        self.yoyo = yoyo;
+        self
+    }
+
+    /**
+     * Called once when the last repeat ends, tweens that repeat forever never complete
+     **/
+    pub fn with_on_complete<F: FnMut() + Send + 'static>(mut self, on_complete: F) -> Tween {
+        self.on_complete = Some(Box::new(on_complete));
+        self
+    }
+
+    /**
+     * Length of one play
+     **/
+    pub fn iteration_duration(&self) -> f32 {
+        self.kind.duration()
+    }
+
+    /**
+     * Length including the delay and every repeat, infinite for tweens repeating forever
+     **/
+    pub fn duration(&self) -> f32 {
+        self.delay + self.active_duration()
+    }
+
+    fn iterations(&self) -> u32 {
+        match self.repeat {
+            Repeat::Times(x) => x.saturating_add(1),
+            Repeat::Forever => u32::MAX,
+        }
+    }
+
+    fn active_duration(&self) -> f32 {
+        let length = self.kind.duration();
+        match self.repeat {
+            _ if length <= 0.0 => 0.0,
+            Repeat::Times(x) => length * (x as f32 + 1.0),
+            Repeat::Forever => f32::INFINITY,
+        }
+    }
+
+    //iteration a time falls in, a time on a boundary belongs to the iteration it ends
+    fn iteration(&self, time: f32, length: f32) -> u32 {
+        if time <= 0.0 {
+            return 0;
+        }
+        ((time / length).ceil() as u32).saturating_sub(1).min(self.iterations() - 1)
+    }
+
+    fn render_iteration(&mut self, iteration: u32, from: f32, to: f32, length: f32) {
+        if self.yoyo && iteration % 2 == 1 {
+            self.kind.render(length - from, length - to);
+        } else {
+            self.kind.render(from, to);
+        }
+    }
+
+    /**
+     * Plays from one time to another, times include the delay
+     **/
+    fn render(&mut self, from: f32, to: f32) {
+        let (from, to) = (from - self.delay, to - self.delay);
+        let length = self.kind.duration();
+        let end = self.active_duration();
+        if length <= 0.0 {
+            self.kind.render(from, to);
+        } else if to >= from {
+            if to >= 0.0 && from <= end {
+                let last = self.iteration(to, length);
+                let first = self.iteration(from, length).max(last.saturating_sub(MAX_ITERATIONS));
+                for i in first..=last {
+                    let base = i as f32 * length;
+                    let a = if i == first { from - base } else { BEFORE };
+                    let b = if i == last { to - base } else { length };
+                    self.render_iteration(i, a, b, length);
+                }
+            }
+        } else if from >= 0.0 && to <= end {
+            let last = self.iteration(to, length);
+            let first = self.iteration(from, length).min(last.saturating_add(MAX_ITERATIONS));
+            for i in (last..=first).rev() {
+                let base = i as f32 * length;
+                let a = if i == first { from - base } else { length - BEFORE };
+                let b = if i == last { to - base } else { 0.0 };
+                self.render_iteration(i, a, b, length);
+            }
+        }
+
+        if from < end && end <= to {
+            if let Some(on_complete) = self.on_complete.as_mut() {
+                on_complete();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Tween {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tween")
+            .field("duration", &self.duration())
+            .field("repeat", &self.repeat)
+            .field("yoyo", &self.yoyo)
+            .finish()
+    }
+}
+
+/**
+ * Handle to a tween in a TweenManager, stale once the tween completes or is cancelled
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TweenHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl TweenHandle {
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> TweenHandle {
+        TweenHandle { index: bits as u32, generation: (bits >> 32) as u32 }
+    }
+}
+
+/**
+ * What a tween belongs to, it's cancelled when that leaves the layer stack
+ * Layers are matched by debug name, since that's all that identifies them
+ **/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TweenOwner {
+    Object(u32),
+    Layer(String),
+}
+
+struct ActiveTween {
+    tween: Tween,
+    owner: Option<TweenOwner>,
+    //None until the first update, which plays from before the start
+    elapsed: Option<f32>,
+    paused: bool,
+}
+
+/**
+ * Every running tween, updated on the update thread after the layers
+ * Setters and callbacks run while the manager is locked, so they can't add tweens to it
+ * themselves, chain them with sequences instead
+ **/
+#[derive(Default)]
+pub struct TweenManager {
+    tweens: Arena<ActiveTween>,
+}
+
+impl TweenManager {
+    pub fn new() -> TweenManager {
+        TweenManager { tweens: Arena::new() }
+    }
+
+    /**
+     * Starts a tween on the next update, owned tweens stop when their owner is removed
+     **/
+    pub fn add(&mut self, tween: Tween, owner: Option<TweenOwner>) -> TweenHandle {
+        let (index, generation) = self.tweens.insert(ActiveTween { tween, owner, elapsed: None, paused: false });
+        TweenHandle { index, generation }
+    }
+
+    /**
+     * Stops a tween where it is without completing it, false if it had already finished
+     **/
+    pub fn cancel(&mut self, handle: TweenHandle) -> bool {
+        self.tweens.remove(handle.index, handle.generation).is_some()
+    }
+
+    /**
+     * Cancels every tween an owner has, returns how many there were
+     **/
+    pub fn cancel_owner(&mut self, owner: &TweenOwner) -> usize {
+        self.cancel_where(|x| x.owner.as_ref() == Some(owner))
+    }
+
+    /**
+     * Cancels the tweens whose owning object or layer isn't in the stack any more
+     **/
+    pub fn cancel_orphans(&mut self, stack: &LayerStack) -> usize {
+        if self.tweens.iter().all(|(_, _, x)| x.owner.is_none()) {
+            return 0;
+        }
+        let mut objects = HashSet::new();
+        let mut layers = HashSet::new();
+        for layer in stack.iter() {
+            layers.insert(layer.debug_name().as_str());
+            objects.extend(layer.objects().map(|x| x.id()));
+        }
+        self.cancel_where(|x| match &x.owner {
+            Some(TweenOwner::Object(id)) => !objects.contains(id),
+            Some(TweenOwner::Layer(name)) => !layers.contains(name.as_str()),
+            None => false
+        })
+    }
+
+    fn cancel_where<F: Fn(&ActiveTween) -> bool>(&mut self, cancel: F) -> usize {
+        let doomed: Vec<(u32, u32)> = self.tweens.iter()
+            .filter(|(_, _, x)| cancel(x))
+            .map(|(index, generation, _)| (index, generation))
+            .collect();
+        for (index, generation) in &doomed {
+            self.tweens.remove(*index, *generation);
+        }
+        doomed.len()
+    }
+
+    pub fn contains(&self, handle: TweenHandle) -> bool {
+        self.tweens.get(handle.index, handle.generation).is_some()
+    }
+
+    /**
+     * Seconds a tween has played, including its delay
+     **/
+    pub fn elapsed(&self, handle: TweenHandle) -> Option<f32> {
+        self.tweens.get(handle.index, handle.generation).map(|x| x.elapsed.unwrap_or(0.0))
+    }
+
+    pub fn set_paused(&mut self, handle: TweenHandle, paused: bool) {
+        if let Some(x) = self.tweens.get_mut(handle.index, handle.generation) {
+            x.paused = paused;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tweens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tweens.is_empty()
+    }
+
+    /**
+     * Cancels everything
+     **/
+    pub fn clear(&mut self) {
+        self.tweens = Arena::new();
+    }
+
+    /**
+     * Advances every unpaused tween by `dt` seconds and drops the ones that completed
+     **/
+    pub fn update(&mut self, dt: f32) {
+        let dt = dt.max(0.0);
+        let mut finished = Vec::new();
+        for (index, generation, active) in self.tweens.iter_mut() {
+            if active.paused {
+                continue;
+            }
+            let from = active.elapsed.unwrap_or(BEFORE);
+            let to = active.elapsed.unwrap_or(0.0) + dt;
+            active.elapsed = Some(to);
+            active.tween.render(from, to);
+            if to >= active.tween.duration() {
+                finished.push((index, generation));
+            }
+        }
+        for (index, generation) in finished {
+            self.tweens.remove(index, generation);
+        }
+    }
+}
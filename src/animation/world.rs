@@ -0,0 +1,425 @@
+use std::sync::{ Arc, RwLock };
+
+use crate::animation::skeleton::{ Pose, SkeletalClip, Skeleton };
+use crate::animation::sprite::{ SpriteAnimation, SpriteSheet };
+use crate::animation::state::{ AnimationController, StateMachine };
+use crate::core::signals::{ SyncData, SyncSignal, SyncSlot, SyncSlotPair };
+use crate::events::animation_events::AnimationKeyframeEvent;
+use crate::events::event::{ Event, EventData, EventType };
+use crate::jobs::JobSystem;
+use crate::math::{ Mat4, Vec2 };
+use crate::physics::arena::Arena;
+
+/**
+ * Handle to an animator in an AnimationWorld, stale once the animator is removed
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AnimatorHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl AnimatorHandle {
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> AnimatorHandle {
+        AnimatorHandle { index: bits as u32, generation: (bits >> 32) as u32 }
+    }
+}
+
+/**
+ * A keyframe event an animator passed during the last update
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiredEvent {
+    pub animator: AnimatorHandle,
+    pub clip: String,
+    pub name: String,
+}
+
+/**
+ * Flipbook animation on a sprite sheet
+ * Sprites can't blend, so crossfades between states switch frames straight away
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAnimator {
+    pub sheet: SpriteSheet,
+    animations: Vec<SpriteAnimation>,
+    controller: AnimationController,
+}
+
+impl SpriteAnimator {
+    /**
+     * Without a state machine nothing plays until `controller_mut().play` is called
+     **/
+    pub fn new(sheet: SpriteSheet, animations: Vec<SpriteAnimation>, machine: Option<StateMachine>) -> SpriteAnimator {
+        SpriteAnimator { sheet, animations, controller: AnimationController::new(machine) }
+    }
+
+    pub fn animations(&self) -> &[SpriteAnimation] {
+        &self.animations
+    }
+
+    pub fn controller(&self) -> &AnimationController {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut AnimationController {
+        &mut self.controller
+    }
+
+    /**
+     * Sheet frame to draw, from the heaviest clip when the state blends
+     **/
+    pub fn frame(&self) -> Option<u32> {
+        let sample = self.controller.current_samples(&self.animations).into_iter().max_by(|a, b| a.weight.total_cmp(&b.weight))?;
+        self.animations[sample.clip].sheet_index(sample.time)
+    }
+
+    /**
+     * Texture coordinates of the frame to draw
+     **/
+    pub fn uv(&self) -> Option<(Vec2, Vec2)> {
+        self.sheet.uv(self.frame()?)
+    }
+
+    /**
+     * Moves the animation on, `fired` gets the clip and name of each keyframe event passed
+     **/
+    pub fn update<F: FnMut(&str, &str)>(&mut self, dt: f32, mut fired: F) {
+        self.controller.update(&self.animations, dt, |clip, x| fired(&clip.name, &x.name));
+    }
+}
+
+/**
+ * Skeletal animation, the pose is sampled and blended on every update
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkeletalAnimator {
+    skeleton: Arc<Skeleton>,
+    clips: Vec<SkeletalClip>,
+    controller: AnimationController,
+    pose: Pose,
+    //scratch pose for each clip before it's blended in
+    sampled: Pose,
+}
+
+impl SkeletalAnimator {
+    pub fn new(skeleton: Arc<Skeleton>, clips: Vec<SkeletalClip>, machine: Option<StateMachine>) -> SkeletalAnimator {
+        let pose = skeleton.rest_pose();
+        SkeletalAnimator { sampled: pose.clone(), pose, skeleton, clips, controller: AnimationController::new(machine) }
+    }
+
+    pub fn skeleton(&self) -> &Arc<Skeleton> {
+        &self.skeleton
+    }
+
+    pub fn clips(&self) -> &[SkeletalClip] {
+        &self.clips
+    }
+
+    pub fn controller(&self) -> &AnimationController {
+        &self.controller
+    }
+
+    pub fn controller_mut(&mut self) -> &mut AnimationController {
+        &mut self.controller
+    }
+
+    /**
+     * The pose as of the last update
+     **/
+    pub fn pose(&self) -> &Pose {
+        &self.pose
+    }
+
+    pub fn skinning_matrices(&self) -> Vec<Mat4> {
+        self.pose.skinning_matrices(&self.skeleton)
+    }
+
+    /**
+     * Moves the animation on and samples the new pose, `fired` is the same as for sprites
+     **/
+    pub fn update<F: FnMut(&str, &str)>(&mut self, dt: f32, mut fired: F) {
+        self.controller.update(&self.clips, dt, |clip, x| fired(&clip.name, &x.name));
+        self.sample();
+    }
+
+    /**
+     * Rebuilds the pose from the controller, update does this already
+     **/
+    pub fn sample(&mut self) {
+        let rest = self.skeleton.rest_pose();
+        self.pose.locals.clone_from(&rest.locals);
+        let mut total = 0.0;
+        for sample in self.controller.samples(&self.clips) {
+            if sample.weight <= 0.0 {
+                continue;
+            }
+            self.sampled.locals.clone_from(&rest.locals);
+            self.clips[sample.clip].sample(&self.skeleton, sample.time, &mut self.sampled);
+            //running weighted average, the first clip replaces the rest pose outright
+            total += sample.weight;
+            self.pose.blend(&self.sampled, sample.weight / total);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Animator {
+    Sprite(SpriteAnimator),
+    Skeletal(SkeletalAnimator),
+}
+
+impl Animator {
+    pub fn controller_mut(&mut self) -> &mut AnimationController {
+        match self {
+            Animator::Sprite(x) => x.controller_mut(),
+            Animator::Skeletal(x) => x.controller_mut()
+        }
+    }
+
+    fn update<F: FnMut(&str, &str)>(&mut self, dt: f32, fired: F) {
+        match self {
+            Animator::Sprite(x) => x.update(dt, fired),
+            Animator::Skeletal(x) => x.update(dt, fired)
+        }
+    }
+}
+
+/**
+ * Every animator in the game, updated with the engine's update delta
+ * Nothing reads the clock, so the same deltas always give the same poses and events, which
+ * makes it safe to step headlessly
+ **/
+pub struct AnimationWorld {
+    animators: Arena<Animator>,
+    events: Vec<FiredEvent>,
+    slots: Vec<SyncSlotPair>,
+}
+
+impl AnimationWorld {
+    pub fn new() -> AnimationWorld {
+        AnimationWorld { animators: Arena::new(), events: Vec::new(), slots: vec![] }
+    }
+
+    pub fn add(&mut self, animator: Animator) -> AnimatorHandle {
+        let (index, generation) = self.animators.insert(animator);
+        AnimatorHandle { index, generation }
+    }
+
+    pub fn add_sprite(&mut self, animator: SpriteAnimator) -> AnimatorHandle {
+        self.add(Animator::Sprite(animator))
+    }
+
+    pub fn add_skeletal(&mut self, animator: SkeletalAnimator) -> AnimatorHandle {
+        self.add(Animator::Skeletal(animator))
+    }
+
+    pub fn remove(&mut self, handle: AnimatorHandle) -> Option<Animator> {
+        self.animators.remove(handle.index, handle.generation)
+    }
+
+    pub fn get(&self, handle: AnimatorHandle) -> Option<&Animator> {
+        self.animators.get(handle.index, handle.generation)
+    }
+
+    pub fn get_mut(&mut self, handle: AnimatorHandle) -> Option<&mut Animator> {
+        self.animators.get_mut(handle.index, handle.generation)
+    }
+
+    pub fn sprite(&self, handle: AnimatorHandle) -> Option<&SpriteAnimator> {
+        match self.get(handle)? {
+            Animator::Sprite(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn sprite_mut(&mut self, handle: AnimatorHandle) -> Option<&mut SpriteAnimator> {
+        match self.get_mut(handle)? {
+            Animator::Sprite(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn skeletal(&self, handle: AnimatorHandle) -> Option<&SkeletalAnimator> {
+        match self.get(handle)? {
+            Animator::Skeletal(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn skeletal_mut(&mut self, handle: AnimatorHandle) -> Option<&mut SkeletalAnimator> {
+        match self.get_mut(handle)? {
+            Animator::Skeletal(x) => Some(x),
+            _ => None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.animators.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.animators.is_empty()
+    }
+
+    /**
+     * Keyframe events fired in the last update, in animator order then time order
+     **/
+    pub fn events(&self) -> &[FiredEvent] {
+        &self.events
+    }
+
+    /**
+     * Advances every animator by `dt` seconds and fires AnimationKeyframeEvents for the
+     * keyframes they pass
+     **/
+    pub fn update(&mut self, dt: f32) {
+        self.events.clear();
+        let events = &mut self.events;
+        for (index, generation, animator) in self.animators.iter_mut() {
+            let handle = AnimatorHandle { index, generation };
+            animator.update(dt, |clip, name| events.push(FiredEvent { animator: handle, clip: clip.to_string(), name: name.to_string() }));
+        }
+        self.emit_fired();
+    }
+
+    /**
+     * Same as update with the animators spread over the job system, events still come out
+     * in animator order
+     **/
+    pub fn update_parallel(&mut self, dt: f32, jobs: &JobSystem) {
+        self.events.clear();
+        let mut animators: Vec<(AnimatorHandle, &mut Animator, Vec<FiredEvent>)> = self.animators.iter_mut()
+            .map(|(index, generation, x)| (AnimatorHandle { index, generation }, x, Vec::new()))
+            .collect();
+        jobs.parallel_for_mut(&mut animators, Some(16), |(handle, animator, fired)| {
+            let handle = *handle;
+            animator.update(dt, |clip, name| fired.push(FiredEvent { animator: handle, clip: clip.to_string(), name: name.to_string() }));
+        });
+        for (_, _, fired) in animators {
+            self.events.extend(fired);
+        }
+        self.emit_fired();
+    }
+
+    fn emit_fired(&self) {
+        for event in &self.events {
+            let res = self.emit(SyncData::Sig(AnimationKeyframeEvent::new(
+                format!("Animator {:?} passed keyframe event {} in {}", event.animator, event.name, event.clip),
+                event.name.clone()
+            )));
+            if let Err(e) = res {
+                error!("Failed to emit animation event: {}", e);
+            }
+        }
+    }
+}
+
+impl Default for AnimationWorld {
+    fn default() -> AnimationWorld {
+        AnimationWorld::new()
+    }
+}
+
+impl SyncSignal<AnimationKeyframeEvent, EventData> for AnimationWorld {
+    fn connect<AnimationKeyframeEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::AnimationKeyframe));
+    }
+
+    fn emit(&self, event: SyncData<AnimationKeyframeEvent>) -> Result<(), &str> {
+        let ev = event.sig();
+        let data = SyncData::Sig(match ev.get_data() {
+            Some(x) => x,
+            None => return Err("No data in event!")
+        });
+        let mut handled = ev.get_handled();
+        for slot in &self.slots {
+            if slot.1 == EventType::AnimationKeyframe && !handled {
+                handled = match slot.0.write() {
+                    Ok(mut x) => x.consume(&data),
+                    _ => {
+                        debug!("Unable to lock slot for signal consumption");
+                        false
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::Any;
+    use crate::animation::LoopMode;
+
+    //names of the keyframe events the world emitted
+    struct Recorder(Vec<String>);
+
+    impl SyncSlot<EventData> for Recorder {
+        fn consume(&mut self, event: &SyncData<&EventData>) -> bool {
+            if let EventData::StringD(name, _) = event.sig() {
+                self.0.push(name.clone());
+            }
+            false
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    fn world() -> (AnimationWorld, AnimatorHandle, AnimatorHandle) {
+        let mut world = AnimationWorld::new();
+        let sheet = SpriteSheet::new("sheet.png", (64, 64), (16, 16));
+        let clip = |name: &str, event: &str| SpriteAnimation::from_range(name, 0, 4, 4.0, Some(LoopMode::Loop)).with_event(0.5, event);
+        let mut a = SpriteAnimator::new(sheet.clone(), vec![clip("a", "step")], None);
+        a.controller_mut().play("a", 0.0);
+        let mut b = SpriteAnimator::new(sheet, vec![clip("b", "swing")], None);
+        b.controller_mut().play("b", 0.0);
+        let a = world.add_sprite(a);
+        let b = world.add_sprite(b);
+        (world, a, b)
+    }
+
+    #[test]
+    fn events_fire_once_in_animator_order() {
+        let (mut world, a, b) = world();
+        world.update(0.25);
+        assert!(world.events().is_empty());
+        world.update(0.5);
+        let fired: Vec<_> = world.events().iter().map(|x| (x.animator, x.name.as_str())).collect();
+        assert_eq!(fired, vec![(a, "step"), (b, "swing")]);
+        world.update(0.5);
+        assert!(world.events().is_empty());
+        //three laps in one update fire three times each
+        world.update(3.0);
+        assert_eq!(world.events().len(), 6);
+    }
+
+    #[test]
+    fn parallel_update_matches_update() {
+        let jobs = JobSystem::new(Some(3));
+        let (mut serial, _, _) = world();
+        let (mut parallel, _, _) = world();
+        for dt in [0.1, 0.4, 2.3, 0.05, 0.7].iter() {
+            serial.update(*dt);
+            parallel.update_parallel(*dt, &jobs);
+            assert_eq!(serial.events(), parallel.events());
+        }
+    }
+
+    #[test]
+    fn events_reach_connected_slots() {
+        let (mut world, _, _) = world();
+        let recorder = Arc::new(RwLock::new(Recorder(Vec::new())));
+        SyncSignal::<AnimationKeyframeEvent, EventData>::connect::<AnimationKeyframeEvent>(&mut world, recorder.clone());
+        world.update(0.75);
+        assert_eq!(recorder.read().unwrap().0, vec!["step", "swing"]);
+    }
+}
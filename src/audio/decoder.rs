@@ -0,0 +1,208 @@
+use std::fs::File;
+use std::io::{ BufReader, Read, Seek, SeekFrom };
+use std::path::{ Path, PathBuf };
+
+use crate::audio::AudioError;
+
+/**
+ * Number of frames a stream decodes per read, WAV and FLAC aim for this while OGG
+ * hands out whole packets
+ **/
+const STREAM_CHUNK_FRAMES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioFormat {
+    Wav,
+    Ogg,
+    Flac,
+}
+
+enum StreamDecoder {
+    Wav(hound::WavReader<BufReader<File>>),
+    Ogg(Box<lewton::inside_ogg::OggStreamReader<BufReader<File>>>),
+    Flac(claxon::FlacReader<BufReader<File>>, Vec<i32>),
+}
+
+/**
+ * Incrementally decodes a WAV, OGG Vorbis or FLAC file
+ * Used directly for long music tracks so they never have to be held in memory all at once,
+ * and by Sound::load to decode short effects up front
+ **/
+pub struct SoundStream {
+    path: PathBuf,
+    decoder: StreamDecoder,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl SoundStream {
+    /**
+     * Opens a file for streaming, the format is detected from the file contents rather
+     * than the extension
+     **/
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SoundStream, AudioError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = File::open(&path)?;
+        let mut magic = [0u8; 4];
+        file.read_exact(&mut magic)?;
+        file.seek(SeekFrom::Start(0))?;
+        let format = match &magic {
+            b"RIFF" => AudioFormat::Wav,
+            b"OggS" => AudioFormat::Ogg,
+            b"fLaC" => AudioFormat::Flac,
+            _ => return Err(AudioError::UnsupportedFormat)
+        };
+
+        let reader = BufReader::new(file);
+        let (decoder, channels, sample_rate) = match format {
+            AudioFormat::Wav => {
+                let wav = hound::WavReader::new(reader)?;
+                let spec = wav.spec();
+                (StreamDecoder::Wav(wav), spec.channels, spec.sample_rate)
+            },
+            AudioFormat::Ogg => {
+                let ogg = lewton::inside_ogg::OggStreamReader::new(reader)?;
+                let (channels, rate) = (ogg.ident_hdr.audio_channels as u16, ogg.ident_hdr.audio_sample_rate);
+                (StreamDecoder::Ogg(Box::new(ogg)), channels, rate)
+            },
+            AudioFormat::Flac => {
+                let flac = claxon::FlacReader::new(reader)?;
+                let info = flac.streaminfo();
+                (StreamDecoder::Flac(flac, Vec::new()), info.channels as u16, info.sample_rate)
+            }
+        };
+
+        if channels == 0 || sample_rate == 0 {
+            return Err(AudioError::InvalidSound(format!("{} has no channels or no sample rate", path.display())));
+        }
+        Ok(SoundStream { path, decoder, channels, sample_rate })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /**
+     * Decodes the next chunk and appends it to `out` as interleaved samples in -1..1
+     * Returns the number of frames appended, 0 once the end of the file is reached
+     **/
+    pub fn read(&mut self, out: &mut Vec<f32>) -> Result<usize, AudioError> {
+        let channels = self.channels as usize;
+        let start = out.len();
+        match &mut self.decoder {
+            StreamDecoder::Wav(wav) => {
+                let spec = wav.spec();
+                let count = STREAM_CHUNK_FRAMES * channels;
+                match spec.sample_format {
+                    hound::SampleFormat::Float => for sample in wav.samples::<f32>().take(count) {
+                        out.push(sample?);
+                    },
+                    hound::SampleFormat::Int => {
+                        let scale = int_scale(spec.bits_per_sample as u32);
+                        for sample in wav.samples::<i32>().take(count) {
+                            out.push(sample? as f32 * scale);
+                        }
+                    }
+                }
+            },
+            StreamDecoder::Ogg(ogg) => {
+                //packets can legitimately decode to nothing, keep going until one has samples
+                while let Some(packet) = ogg.read_dec_packet_itl()? {
+                    if !packet.is_empty() {
+                        out.extend(packet.iter().map(|&x| x as f32 / 32768.0));
+                        break;
+                    }
+                }
+            },
+            StreamDecoder::Flac(flac, buffer) => {
+                let scale = int_scale(flac.streaminfo().bits_per_sample);
+                let block = flac.blocks().read_next_or_eof(std::mem::take(buffer))?;
+                if let Some(block) = block {
+                    for i in 0..block.duration() {
+                        for ch in 0..block.channels() {
+                            out.push(block.sample(ch, i) as f32 * scale);
+                        }
+                    }
+                    *buffer = block.into_buffer();
+                }
+            }
+        }
+        Ok((out.len() - start) / channels)
+    }
+
+    /**
+     * Starts decoding again from the beginning of the file
+     **/
+    pub fn rewind(&mut self) -> Result<(), AudioError> {
+        *self = SoundStream::open(&self.path)?;
+        Ok(())
+    }
+}
+
+fn int_scale(bits: u32) -> f32 {
+    1.0 / (1u64 << (bits.max(1) - 1)) as f32
+}
+
+/**
+ * Fully decoded sound held in memory as interleaved f32 samples
+ * Share it between voices with an Arc, playing it again doesn't copy the samples
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sound {
+    samples: Vec<f32>,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl Sound {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Sound, AudioError> {
+        let mut stream = SoundStream::open(path)?;
+        let mut samples = Vec::new();
+        while stream.read(&mut samples)? > 0 {}
+        Sound::from_samples(samples, stream.channels(), stream.sample_rate())
+    }
+
+    /**
+     * Wraps already decoded or generated interleaved samples
+     **/
+    pub fn from_samples(samples: Vec<f32>, channels: u16, sample_rate: u32) -> Result<Sound, AudioError> {
+        if channels == 0 || sample_rate == 0 {
+            return Err(AudioError::InvalidSound("sounds need at least one channel and a sample rate".to_string()));
+        }
+        if !samples.len().is_multiple_of(channels as usize) {
+            return Err(AudioError::InvalidSound(format!("{} samples can't be split into {} channels", samples.len(), channels)));
+        }
+        Ok(Sound { samples, channels, sample_rate })
+    }
+
+    pub fn samples(&self) -> &[f32] {
+        &self.samples
+    }
+
+    pub fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /**
+     * Length in seconds
+     **/
+    pub fn duration(&self) -> f32 {
+        self.frames() as f32 / self.sample_rate as f32
+    }
+}
@@ -0,0 +1,578 @@
+use std::sync::Arc;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::audio::{ AudioError, AudioOutput, Sound, SoundStream };
+use crate::core::transform::Transform;
+use crate::math::{ Quat, Vec3 };
+
+/**
+ * Frames mixed per call to the output by Mixer::render
+ **/
+const RENDER_BLOCK_FRAMES: usize = 512;
+
+/**
+ * Volume groups, every voice plays through Music or Sfx (or straight into Master)
+ * and all of them are scaled by Master
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub enum Bus {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl Bus {
+    fn index(self) -> usize {
+        match self {
+            Bus::Master => 0,
+            Bus::Music => 1,
+            Bus::Sfx => 2
+        }
+    }
+}
+
+/**
+ * Where sounds are heard from, spatial voices are panned and attenuated relative to it
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Listener {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Listener {
+    pub fn new(position: Vec3, rotation: Quat) -> Listener {
+        Listener { position, rotation }
+    }
+
+    /**
+     * Listener placed at a transform, pass a world transform for listeners inside a hierarchy
+     **/
+    pub fn from_transform(transform: &Transform) -> Listener {
+        Listener { position: transform.translation, rotation: transform.rotation }
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * -Vec3::Z
+    }
+}
+
+impl Default for Listener {
+    fn default() -> Listener {
+        Listener { position: Vec3::ZERO, rotation: Quat::IDENTITY }
+    }
+}
+
+/**
+ * Position and distance falloff of a voice in the world
+ * Gain is 1 inside min_distance and falls off as min / (min + rolloff * (d - min)),
+ * past max_distance it stops falling
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spatial {
+    pub position: Vec3,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    pub rolloff: f32,
+}
+
+impl Spatial {
+    pub fn new(position: Vec3) -> Spatial {
+        Spatial { position, min_distance: 1.0, max_distance: 100.0, rolloff: 1.0 }
+    }
+
+    pub fn attenuation(&self, distance: f32) -> f32 {
+        let min = self.min_distance.max(f32::EPSILON);
+        let d = distance.max(min).min(self.max_distance.max(min));
+        min / (min + self.rolloff.max(0.0) * (d - min))
+    }
+}
+
+/**
+ * Playback settings of a single voice, can be changed while it plays through Mixer::params_mut
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoiceParams {
+    pub bus: Bus,
+    pub volume: f32,
+    /**
+     * Playback speed, 2.0 plays an octave higher and twice as fast
+     **/
+    pub pitch: f32,
+    /**
+     * Stereo balance from -1 (left) to 1 (right), ignored by spatial voices
+     **/
+    pub pan: f32,
+    pub looping: bool,
+    /**
+     * Spatial voices are downmixed to mono and panned relative to the listener
+     **/
+    pub spatial: Option<Spatial>,
+}
+
+impl VoiceParams {
+    pub fn new(bus: Bus) -> VoiceParams {
+        VoiceParams { bus, volume: 1.0, pitch: 1.0, pan: 0.0, looping: false, spatial: None }
+    }
+
+    pub fn spatial(bus: Bus, position: Vec3) -> VoiceParams {
+        VoiceParams { spatial: Some(Spatial::new(position)), ..VoiceParams::new(bus) }
+    }
+
+    /**
+     * Left and right gain of this voice before bus volumes are applied
+     **/
+    fn gains(&self, listener: &Listener) -> (f32, f32) {
+        match &self.spatial {
+            Some(spatial) => {
+                let offset = spatial.position - listener.position;
+                let distance = offset.length();
+                let pan = if distance > f32::EPSILON {
+                    (offset.dot(listener.right()) / distance).clamp(-1.0, 1.0)
+                } else {
+                    0.0
+                };
+                //equal power pan so a voice circling the listener keeps the same loudness
+                let angle = (pan + 1.0) * std::f32::consts::FRAC_PI_4;
+                let gain = self.volume * spatial.attenuation(distance);
+                (angle.cos() * gain, angle.sin() * gain)
+            },
+            None => {
+                let pan = self.pan.clamp(-1.0, 1.0);
+                (self.volume * (1.0 - pan).min(1.0), self.volume * (1.0 + pan).min(1.0))
+            }
+        }
+    }
+}
+
+impl Default for VoiceParams {
+    fn default() -> VoiceParams {
+        VoiceParams::new(Bus::Sfx)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoiceId(u64);
+
+enum Source {
+    Buffer(Arc<Sound>),
+    /**
+     * `buffer` holds the decoded frames from the current playback position onwards
+     **/
+    Stream { stream: Box<SoundStream>, buffer: Vec<f32> },
+}
+
+impl Source {
+    fn channels(&self) -> usize {
+        match self {
+            Source::Buffer(sound) => sound.channels() as usize,
+            Source::Stream { stream, .. } => stream.channels() as usize
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Source::Buffer(sound) => sound.sample_rate(),
+            Source::Stream { stream, .. } => stream.sample_rate()
+        }
+    }
+
+    /**
+     * Stereo frame at `index`, mono sources are duplicated and channels past the second dropped
+     * None once a non looping source has run out
+     **/
+    fn frame(&mut self, index: usize, looping: bool) -> Result<Option<(f32, f32)>, AudioError> {
+        let channels = self.channels();
+        let samples = match self {
+            Source::Buffer(sound) => {
+                let frames = sound.frames();
+                if frames == 0 || (!looping && index >= frames) {
+                    return Ok(None);
+                }
+                let start = (index % frames) * channels;
+                &sound.samples()[start..start + channels]
+            },
+            Source::Stream { stream, buffer } => {
+                while buffer.len() < (index + 1) * channels {
+                    if stream.read(buffer)? == 0 {
+                        if !looping {
+                            return Ok(None);
+                        }
+                        stream.rewind()?;
+                        if stream.read(buffer)? == 0 {
+                            return Ok(None);
+                        }
+                    }
+                }
+                &buffer[index * channels..(index + 1) * channels]
+            }
+        };
+        Ok(Some(if channels == 1 { (samples[0], samples[0]) } else { (samples[0], samples[1]) }))
+    }
+
+    /**
+     * Keeps the playback position small so it doesn't lose precision on long or looping sounds
+     * Non looping buffers keep their position, wrapping would replay a sound that ended on a block boundary
+     **/
+    fn advance(&mut self, position: &mut f64, looping: bool) {
+        let whole = position.floor() as usize;
+        match self {
+            Source::Buffer(sound) => {
+                let frames = sound.frames();
+                if looping && frames > 0 && whole >= frames {
+                    *position -= (whole - whole % frames) as f64;
+                }
+            },
+            Source::Stream { buffer, stream } => {
+                let channels = stream.channels() as usize;
+                let whole = whole.min(buffer.len() / channels);
+                buffer.drain(..whole * channels);
+                *position -= whole as f64;
+            }
+        }
+    }
+}
+
+struct Voice {
+    id: VoiceId,
+    source: Source,
+    params: VoiceParams,
+    position: f64,
+    paused: bool,
+    finished: bool,
+}
+
+/**
+ * Mixes any number of voices into interleaved stereo f32 at a fixed output rate
+ * The mixer has no clock of its own, it produces exactly as many frames as it is asked for,
+ * which keeps it deterministic and lets it be driven offline through render()
+ **/
+pub struct Mixer {
+    sample_rate: u32,
+    volumes: [f32; 3],
+    listener: Listener,
+    voices: Vec<Voice>,
+    next_id: u64,
+}
+
+impl Mixer {
+    pub fn new(sample_rate: u32) -> Mixer {
+        Mixer {
+            sample_rate: sample_rate.max(1),
+            volumes: [1.0; 3],
+            listener: Listener::default(),
+            voices: Vec::new(),
+            next_id: 0
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn volume(&self, bus: Bus) -> f32 {
+        self.volumes[bus.index()]
+    }
+
+    /**
+     * Sets a bus volume, clamped to 0..1
+     **/
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        self.volumes[bus.index()] = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn listener(&self) -> &Listener {
+        &self.listener
+    }
+
+    pub fn set_listener(&mut self, listener: Listener) {
+        self.listener = listener;
+    }
+
+    /**
+     * Starts playing a sound held in memory
+     **/
+    pub fn play(&mut self, sound: Arc<Sound>, params: VoiceParams) -> VoiceId {
+        self.add_voice(Source::Buffer(sound), params)
+    }
+
+    /**
+     * Starts playing a stream, decoding it a chunk at a time as the mixer needs it
+     **/
+    pub fn play_stream(&mut self, stream: SoundStream, params: VoiceParams) -> VoiceId {
+        self.add_voice(Source::Stream { stream: Box::new(stream), buffer: Vec::new() }, params)
+    }
+
+    /**
+     * Stops and removes a voice, returns false if it had already finished
+     **/
+    pub fn stop(&mut self, id: VoiceId) -> bool {
+        let count = self.voices.len();
+        self.voices.retain(|x| x.id != id);
+        self.voices.len() != count
+    }
+
+    pub fn stop_all(&mut self) {
+        self.voices.clear();
+    }
+
+    pub fn pause(&mut self, id: VoiceId) -> bool {
+        self.voice_mut(id).map(|x| x.paused = true).is_some()
+    }
+
+    pub fn resume(&mut self, id: VoiceId) -> bool {
+        self.voice_mut(id).map(|x| x.paused = false).is_some()
+    }
+
+    /**
+     * True while the voice exists and isn't paused
+     **/
+    pub fn is_playing(&self, id: VoiceId) -> bool {
+        self.voices.iter().any(|x| x.id == id && !x.paused)
+    }
+
+    pub fn params(&self, id: VoiceId) -> Option<&VoiceParams> {
+        self.voices.iter().find(|x| x.id == id).map(|x| &x.params)
+    }
+
+    pub fn params_mut(&mut self, id: VoiceId) -> Option<&mut VoiceParams> {
+        self.voice_mut(id).map(|x| &mut x.params)
+    }
+
+    /**
+     * Moves a spatial voice, returns false if the voice is gone or isn't spatial
+     **/
+    pub fn set_position(&mut self, id: VoiceId, position: Vec3) -> bool {
+        match self.params_mut(id).and_then(|x| x.spatial.as_mut()) {
+            Some(spatial) => {
+                spatial.position = position;
+                true
+            },
+            None => false
+        }
+    }
+
+    pub fn voice_count(&self) -> usize {
+        self.voices.len()
+    }
+
+    /**
+     * Overwrites `out` with the next `out.len() / 2` stereo frames
+     * Voices that run out are removed, a voice whose stream fails to decode is logged and removed
+     **/
+    pub fn mix(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = 0.0;
+        }
+
+        let frames = out.len() / 2;
+        let listener = self.listener;
+        let volumes = self.volumes;
+        let output_rate = self.sample_rate as f64;
+        for voice in self.voices.iter_mut().filter(|x| !x.paused) {
+            let bus = match voice.params.bus {
+                Bus::Master => 1.0,
+                bus => volumes[bus.index()]
+            };
+            let (left_gain, right_gain) = voice.params.gains(&listener);
+            let (left_gain, right_gain) = (left_gain * bus * volumes[0], right_gain * bus * volumes[0]);
+            let step = voice.source.sample_rate() as f64 / output_rate * voice.params.pitch.max(0.0) as f64;
+            let looping = voice.params.looping;
+            let mono = voice.params.spatial.is_some();
+
+            for frame in 0..frames {
+                let index = voice.position.floor() as usize;
+                let t = (voice.position - index as f64) as f32;
+                let current = match voice.source.frame(index, looping) {
+                    Ok(Some(x)) => x,
+                    Ok(None) => {
+                        voice.finished = true;
+                        break;
+                    },
+                    Err(e) => {
+                        error!("Audio voice stopped, failed to decode: {}", e);
+                        voice.finished = true;
+                        break;
+                    }
+                };
+                //the last frame is held rather than interpolated towards silence
+                let next = match voice.source.frame(index + 1, looping) {
+                    Ok(Some(x)) => x,
+                    _ => current
+                };
+                let mut left = current.0 + (next.0 - current.0) * t;
+                let mut right = current.1 + (next.1 - current.1) * t;
+                if mono {
+                    left = (left + right) * 0.5;
+                    right = left;
+                }
+                out[frame * 2] += left * left_gain;
+                out[frame * 2 + 1] += right * right_gain;
+                voice.position += step;
+            }
+            voice.source.advance(&mut voice.position, looping);
+        }
+        self.voices.retain(|x| !x.finished);
+
+        for sample in out.iter_mut() {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+    }
+
+    /**
+     * Mixes `frames` frames and writes them to `output` as fast as possible
+     * Meant for tests and offline rendering, the Audio system drives its output in real time
+     **/
+    pub fn render(&mut self, frames: usize, output: &mut dyn AudioOutput) -> Result<(), AudioError> {
+        let mut block = vec![0.0; RENDER_BLOCK_FRAMES * 2];
+        let mut remaining = frames;
+        while remaining > 0 {
+            let count = remaining.min(RENDER_BLOCK_FRAMES);
+            self.mix(&mut block[..count * 2]);
+            output.write(&block[..count * 2])?;
+            remaining -= count;
+        }
+        Ok(())
+    }
+
+    fn add_voice(&mut self, source: Source, params: VoiceParams) -> VoiceId {
+        let id = VoiceId(self.next_id);
+        self.next_id += 1;
+        self.voices.push(Voice { id, source, params, position: 0.0, paused: false, finished: false });
+        id
+    }
+
+    fn voice_mut(&mut self, id: VoiceId) -> Option<&mut Voice> {
+        self.voices.iter_mut().find(|x| x.id == id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::NullOutput;
+
+    const RATE: u32 = 1000;
+
+    fn constant(value: f32, frames: usize) -> Arc<Sound> {
+        Arc::new(Sound::from_samples(vec![value; frames], 1, RATE).unwrap())
+    }
+
+    fn mix(mixer: &mut Mixer, frames: usize) -> Vec<f32> {
+        let mut out = vec![0.0; frames * 2];
+        mixer.mix(&mut out);
+        out
+    }
+
+    #[test]
+    fn bus_volumes_scale_voices() {
+        let mut mixer = Mixer::new(RATE);
+        mixer.set_volume(Bus::Master, 0.5);
+        mixer.set_volume(Bus::Sfx, 0.5);
+        mixer.set_volume(Bus::Music, 0.25);
+        let sfx = mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Sfx));
+        assert!(mix(&mut mixer, 4).iter().all(|x| (x - 0.2).abs() < 1e-6));
+
+        mixer.stop(sfx);
+        mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Music));
+        assert!(mix(&mut mixer, 4).iter().all(|x| (x - 0.1).abs() < 1e-6));
+
+        //voices on Master only get the master volume once
+        mixer.stop_all();
+        mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Master));
+        assert!(mix(&mut mixer, 4).iter().all(|x| (x - 0.4).abs() < 1e-6));
+    }
+
+    #[test]
+    fn bus_volume_is_clamped() {
+        let mut mixer = Mixer::new(RATE);
+        mixer.set_volume(Bus::Sfx, 3.0);
+        assert_eq!(mixer.volume(Bus::Sfx), 1.0);
+        mixer.set_volume(Bus::Sfx, -1.0);
+        assert_eq!(mixer.volume(Bus::Sfx), 0.0);
+        mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Sfx));
+        assert!(mix(&mut mixer, 4).iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn pan_moves_voice_between_channels() {
+        let mut mixer = Mixer::new(RATE);
+        let params = VoiceParams { pan: -1.0, ..VoiceParams::new(Bus::Sfx) };
+        mixer.play(constant(0.5, 100), params);
+        let out = mix(&mut mixer, 4);
+        assert!(out.chunks(2).all(|x| x[0] == 0.5 && x[1] == 0.0), "{:?}", out);
+    }
+
+    #[test]
+    fn loud_mix_is_clipped() {
+        let mut mixer = Mixer::new(RATE);
+        mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Sfx));
+        mixer.play(constant(0.8, 100), VoiceParams::new(Bus::Music));
+        assert!(mix(&mut mixer, 8).iter().all(|x| *x == 1.0));
+
+        mixer.stop_all();
+        mixer.play(constant(-0.8, 100), VoiceParams::new(Bus::Sfx));
+        mixer.play(constant(-0.8, 100), VoiceParams::new(Bus::Sfx));
+        assert!(mix(&mut mixer, 8).iter().all(|x| *x == -1.0));
+    }
+
+    #[test]
+    fn voice_ends_and_is_removed() {
+        let mut mixer = Mixer::new(RATE);
+        let id = mixer.play(constant(0.5, 10), VoiceParams::new(Bus::Sfx));
+        let out = mix(&mut mixer, 16);
+        assert!(out[..20].iter().all(|x| *x == 0.5), "{:?}", out);
+        assert!(out[20..].iter().all(|x| *x == 0.0), "{:?}", out);
+        assert_eq!(mixer.voice_count(), 0);
+        assert!(!mixer.is_playing(id));
+        assert!(!mixer.stop(id));
+        assert!(mix(&mut mixer, 4).iter().all(|x| *x == 0.0));
+    }
+
+    #[test]
+    fn voice_ending_on_block_boundary() {
+        let mut mixer = Mixer::new(RATE);
+        mixer.play(constant(0.5, 8), VoiceParams::new(Bus::Sfx));
+        assert!(mix(&mut mixer, 8).iter().all(|x| *x == 0.5));
+        assert!(mix(&mut mixer, 8).iter().all(|x| *x == 0.0));
+        assert_eq!(mixer.voice_count(), 0);
+    }
+
+    #[test]
+    fn looping_voice_keeps_playing() {
+        let mut mixer = Mixer::new(RATE);
+        let params = VoiceParams { looping: true, ..VoiceParams::new(Bus::Sfx) };
+        let id = mixer.play(constant(0.5, 10), params);
+        for _ in 0..5 {
+            assert!(mix(&mut mixer, 16).iter().all(|x| *x == 0.5));
+        }
+        assert!(mixer.is_playing(id));
+    }
+
+    #[test]
+    fn paused_voice_is_silent_and_resumes() {
+        let mut mixer = Mixer::new(RATE);
+        let id = mixer.play(constant(0.5, 10), VoiceParams::new(Bus::Sfx));
+        mixer.pause(id);
+        assert!(mix(&mut mixer, 16).iter().all(|x| *x == 0.0));
+        assert_eq!(mixer.voice_count(), 1);
+        mixer.resume(id);
+        assert_eq!(mix(&mut mixer, 16).iter().filter(|x| **x == 0.5).count(), 20);
+    }
+
+    #[test]
+    fn render_writes_every_frame() {
+        let mut mixer = Mixer::new(RATE);
+        mixer.play(constant(0.25, 2000), VoiceParams::new(Bus::Sfx));
+        let mut output = NullOutput::new(RATE);
+        mixer.render(1500, &mut output).unwrap();
+        assert_eq!(output.frames_written(), 1500);
+        assert_eq!(output.peak(), 0.25);
+    }
+}
@@ -0,0 +1,73 @@
+pub mod decoder;
+pub mod mixer;
+pub mod output;
+pub mod system;
+
+pub use self::decoder::{ Sound, SoundStream };
+pub use self::mixer::{ Bus, Listener, Mixer, Spatial, VoiceId, VoiceParams };
+pub use self::output::{ AudioOutput, NullCounters, NullOutput, WavOutput };
+pub use self::system::Audio;
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AudioError {
+    Io(std::io::Error),
+    Wav(hound::Error),
+    Vorbis(lewton::VorbisError),
+    Flac(claxon::Error),
+    UnsupportedFormat,
+    InvalidSound(String),
+    Poisoned,
+}
+
+impl fmt::Display for AudioError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AudioError::Io(e) => write!(f, "Audio IO error: {}", e),
+            AudioError::Wav(e) => write!(f, "WAV error: {}", e),
+            AudioError::Vorbis(e) => write!(f, "OGG Vorbis error: {}", e),
+            AudioError::Flac(e) => write!(f, "FLAC error: {}", e),
+            AudioError::UnsupportedFormat => write!(f, "Audio file is not WAV, OGG Vorbis or FLAC"),
+            AudioError::InvalidSound(msg) => write!(f, "Invalid sound: {}", msg),
+            AudioError::Poisoned => write!(f, "Audio mixer lock is poisoned"),
+        }
+    }
+}
+
+impl Error for AudioError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AudioError::Io(e) => Some(e),
+            AudioError::Wav(e) => Some(e),
+            AudioError::Vorbis(e) => Some(e),
+            AudioError::Flac(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for AudioError {
+    fn from(e: std::io::Error) -> AudioError {
+        AudioError::Io(e)
+    }
+}
+
+impl From<hound::Error> for AudioError {
+    fn from(e: hound::Error) -> AudioError {
+        AudioError::Wav(e)
+    }
+}
+
+impl From<lewton::VorbisError> for AudioError {
+    fn from(e: lewton::VorbisError) -> AudioError {
+        AudioError::Vorbis(e)
+    }
+}
+
+impl From<claxon::Error> for AudioError {
+    fn from(e: claxon::Error) -> AudioError {
+        AudioError::Flac(e)
+    }
+}
@@ -0,0 +1,122 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU32, AtomicU64, Ordering };
+
+use crate::audio::AudioError;
+
+/**
+ * Destination for mixed audio, always interleaved stereo f32
+ * Outputs backed by real hardware should block in write() until the device has room,
+ * the Audio system keeps outputs that don't block paced to real time itself
+ **/
+pub trait AudioOutput: Send {
+    fn sample_rate(&self) -> u32;
+    fn write(&mut self, samples: &[f32]) -> Result<(), AudioError>;
+}
+
+/**
+ * Discards everything written to it, for headless runs and machines without a sound card
+ * Keeps a frame count and the loudest sample so tests can check something was mixed,
+ * take counters() before handing the output to the Audio system to read them while it runs
+ **/
+pub struct NullOutput {
+    sample_rate: u32,
+    counters: NullCounters,
+}
+
+impl NullOutput {
+    pub fn new(sample_rate: u32) -> NullOutput {
+        NullOutput { sample_rate, counters: NullCounters::default() }
+    }
+
+    /**
+     * A handle to the counters that stays readable after the output moves to the audio thread
+     **/
+    pub fn counters(&self) -> NullCounters {
+        self.counters.clone()
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.counters.frames_written()
+    }
+
+    pub fn peak(&self) -> f32 {
+        self.counters.peak()
+    }
+}
+
+impl AudioOutput for NullOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), AudioError> {
+        let peak = samples.iter().fold(0.0f32, |peak, x| peak.max(x.abs()));
+        self.counters.frames.fetch_add(samples.len() as u64 / 2, Ordering::Relaxed);
+        //bits of non negative floats order the same as the floats
+        self.counters.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/**
+ * Frame count and loudest sample of a NullOutput, shared with the output wherever it runs
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct NullCounters {
+    frames: Arc<AtomicU64>,
+    peak: Arc<AtomicU32>,
+}
+
+impl NullCounters {
+    pub fn frames_written(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> f32 {
+        f32::from_bits(self.peak.load(Ordering::Relaxed))
+    }
+}
+
+/**
+ * Writes the mix to a 32 bit float stereo WAV file
+ **/
+pub struct WavOutput {
+    sample_rate: u32,
+    writer: hound::WavWriter<BufWriter<File>>,
+}
+
+impl WavOutput {
+    pub fn create<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<WavOutput, AudioError> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float
+        };
+        Ok(WavOutput { sample_rate, writer: hound::WavWriter::create(path, spec)? })
+    }
+
+    /**
+     * Flushes the file and fixes up the header, dropping the output does the same
+     * but can't report errors
+     **/
+    pub fn finalize(self) -> Result<(), AudioError> {
+        Ok(self.writer.finalize()?)
+    }
+}
+
+impl AudioOutput for WavOutput {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn write(&mut self, samples: &[f32]) -> Result<(), AudioError> {
+        for &sample in samples {
+            self.writer.write_sample(sample)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,142 @@
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex, MutexGuard };
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use crate::audio::{ AudioError, AudioOutput, Bus, Mixer };
+use crate::core::settings::AudioSettings;
+
+/**
+ * Frames mixed per block on the audio thread, about 11ms at 44.1kHz
+ **/
+const BLOCK_FRAMES: usize = 512;
+
+/**
+ * Owns the mixer and a thread that keeps feeding it to an output
+ * The thread stays at most a couple of blocks ahead of real time, so outputs that
+ * never block (null, wav) advance at the same speed a sound card would
+ * MagnusApplication::start_audio creates one from the application's settings
+ **/
+pub struct Audio {
+    mixer: Arc<Mutex<Mixer>>,
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Audio {
+    pub fn new(settings: AudioSettings, mut output: Box<dyn AudioOutput>) -> Audio {
+        let sample_rate = output.sample_rate().max(1);
+        let mut mixer = Mixer::new(sample_rate);
+        for &bus in &[Bus::Master, Bus::Music, Bus::Sfx] {
+            mixer.set_volume(bus, settings.volume(bus));
+        }
+        let mixer = Arc::new(Mutex::new(mixer));
+        let running = Arc::new(AtomicBool::new(true));
+
+        debug!("Starting audio thread at {}Hz", sample_rate);
+        let thread = {
+            let mixer = Arc::clone(&mixer);
+            let running = Arc::clone(&running);
+            thread::spawn(move || {
+                let lead = Duration::from_secs_f64(2.0 * BLOCK_FRAMES as f64 / sample_rate as f64);
+                let mut block = vec![0.0; BLOCK_FRAMES * 2];
+                let mut written: u64 = 0;
+                let start = Instant::now();
+                while running.load(Ordering::SeqCst) {
+                    let played = Duration::from_secs_f64(written as f64 / sample_rate as f64);
+                    let elapsed = start.elapsed();
+                    if played > elapsed + lead {
+                        thread::sleep(played - elapsed - lead);
+                        continue;
+                    }
+
+                    match mixer.lock() {
+                        Ok(mut x) => x.mix(&mut block),
+                        _ => {
+                            error!("Audio mixer Mutex is Poisoned");
+                            error!("Audio thread shutting down");
+                            break;
+                        }
+                    }
+                    if let Err(e) = output.write(&block) {
+                        error!("Audio output failed: {}", e);
+                        error!("Audio thread shutting down");
+                        break;
+                    }
+                    written += BLOCK_FRAMES as u64;
+                }
+                running.store(false, Ordering::SeqCst);
+            })
+        };
+
+        Audio { mixer, running, thread: Some(thread) }
+    }
+
+    /**
+     * Locks the mixer to play, stop or move voices
+     * Keep the guard short lived, the audio thread can't mix while it is held
+     **/
+    pub fn mixer(&self) -> Result<MutexGuard<'_, Mixer>, AudioError> {
+        self.mixer.lock().map_err(|_| AudioError::Poisoned)
+    }
+
+    pub fn set_volume(&self, bus: Bus, volume: f32) -> Result<(), AudioError> {
+        self.mixer()?.set_volume(bus, volume);
+        Ok(())
+    }
+
+    /**
+     * Current bus volumes, hand these to Settings::set_audio to persist them
+     **/
+    pub fn volumes(&self) -> Result<AudioSettings, AudioError> {
+        let mixer = self.mixer()?;
+        Ok(AudioSettings::new(mixer.volume(Bus::Master), mixer.volume(Bus::Music), mixer.volume(Bus::Sfx)))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /**
+     * Stops the audio thread, dropping Audio does the same
+     **/
+    pub fn shutdown(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Audio thread panicked");
+            }
+        }
+    }
+}
+
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{ NullOutput, Sound, VoiceParams };
+
+    #[test]
+    fn counters_readable_while_audio_runs() {
+        let output = NullOutput::new(8000);
+        let counters = output.counters();
+        let mut audio = Audio::new(AudioSettings::new(1.0, 1.0, 0.5), Box::new(output));
+        let sound = Arc::new(Sound::from_samples(vec![0.5; 100], 1, 8000).unwrap());
+        let params = VoiceParams { looping: true, ..VoiceParams::new(Bus::Sfx) };
+        audio.mixer().unwrap().play(sound, params);
+
+        let start = Instant::now();
+        while counters.frames_written() < 4 * BLOCK_FRAMES as u64 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(counters.frames_written() >= 4 * BLOCK_FRAMES as u64);
+        assert!((counters.peak() - 0.25).abs() < 1e-6, "{}", counters.peak());
+        audio.shutdown();
+        assert!(!audio.is_running());
+    }
+}
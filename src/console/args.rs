@@ -0,0 +1,213 @@
+use std::fmt;
+
+use crate::console::ConsoleError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Int,
+    Float,
+    /**
+     * 1/0, true/false or on/off
+     **/
+    Bool,
+    Text,
+    /**
+     * Everything left on the line as one Text argument, only valid last
+     **/
+    Rest,
+}
+
+impl fmt::Display for ArgKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgKind::Int => write!(f, "int"),
+            ArgKind::Float => write!(f, "float"),
+            ArgKind::Bool => write!(f, "bool"),
+            ArgKind::Text | ArgKind::Rest => write!(f, "text"),
+        }
+    }
+}
+
+/**
+ * A parsed argument, checked against its ArgSpec
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Text(String),
+}
+
+impl Arg {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Arg::Int(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    /**
+     * Ints widen to floats
+     **/
+    pub fn as_float(&self) -> Option<f64> {
+        match self {
+            Arg::Float(x) => Some(*x),
+            Arg::Int(x) => Some(*x as f64),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Arg::Bool(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Arg::Text(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Arg::Int(x) => write!(f, "{}", x),
+            Arg::Float(x) => write!(f, "{}", x),
+            Arg::Bool(x) => write!(f, "{}", if *x { 1 } else { 0 }),
+            Arg::Text(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/**
+ * One argument a command takes
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArgSpec {
+    pub name: String,
+    pub kind: ArgKind,
+    pub optional: bool,
+    //offered by autocompletion, Text arguments are also limited to these when there are any
+    pub choices: Vec<String>,
+}
+
+impl ArgSpec {
+    pub fn new(name: &str, kind: ArgKind) -> ArgSpec {
+        ArgSpec { name: name.to_string(), kind, optional: false, choices: Vec::new() }
+    }
+
+    pub fn optional(mut self) -> ArgSpec {
+        self.optional = true;
+        self
+    }
+
+    pub fn choices(mut self, choices: &[&str]) -> ArgSpec {
+        self.choices = choices.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /**
+     * What autocompletion offers for this argument
+     **/
+    pub fn suggestions(&self) -> Vec<String> {
+        match self.kind {
+            ArgKind::Bool if self.choices.is_empty() => vec!["0".to_string(), "1".to_string()],
+            _ => self.choices.clone()
+        }
+    }
+
+    /**
+     * The kind, or the choices when there are any
+     **/
+    pub fn expected(&self) -> String {
+        if self.choices.is_empty() { self.kind.to_string() } else { self.choices.join("|") }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<Arg, ConsoleError> {
+        let invalid = || ConsoleError::InvalidArgument { name: self.name.clone(), expected: self.expected(), got: text.to_string() };
+        match self.kind {
+            ArgKind::Int => text.parse().map(Arg::Int).map_err(|_| invalid()),
+            ArgKind::Float => text.parse().map(Arg::Float).map_err(|_| invalid()),
+            ArgKind::Bool => match text.to_ascii_lowercase().as_str() {
+                "1" | "true" | "on" => Ok(Arg::Bool(true)),
+                "0" | "false" | "off" => Ok(Arg::Bool(false)),
+                _ => Err(invalid())
+            },
+            ArgKind::Text | ArgKind::Rest => {
+                if self.choices.is_empty() {
+                    return Ok(Arg::Text(text.to_string()));
+                }
+                //choices are matched without case so "trace" finds "Trace"
+                self.choices.iter().find(|x| x.eq_ignore_ascii_case(text)).map(|x| Arg::Text(x.clone())).ok_or_else(invalid)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ArgSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.optional {
+            write!(f, "[{}: {}]", self.name, self.expected())
+        }
+        else {
+            write!(f, "<{}: {}>", self.name, self.expected())
+        }
+    }
+}
+
+/**
+ * Splits a line on whitespace, double quotes keep spaces in an argument
+ * Returns each token with the byte offset it starts at
+ **/
+pub fn tokenize(line: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.get_or_insert_with(|| (i, String::new()));
+            },
+            c if c.is_whitespace() && !quoted => {
+                if let Some(x) = current.take() {
+                    tokens.push(x);
+                }
+            },
+            c => current.get_or_insert_with(|| (i, String::new())).1.push(c)
+        }
+    }
+    if let Some(x) = current {
+        tokens.push(x);
+    }
+    tokens
+}
+
+/**
+ * Checks `tokens` against `specs`, Rest takes everything from its token to the end of `line`
+ **/
+pub fn parse_args(specs: &[ArgSpec], line: &str, tokens: &[(usize, String)]) -> Result<Vec<Arg>, ConsoleError> {
+    let mut args = Vec::with_capacity(specs.len());
+    for (i, spec) in specs.iter().enumerate() {
+        match tokens.get(i) {
+            Some((start, token)) if spec.kind == ArgKind::Rest => {
+                //a single token already had its quotes taken off
+                let rest = if tokens.len() == i + 1 { token.as_str() } else { line[*start..].trim_end() };
+                args.push(spec.parse(rest)?);
+                return Ok(args);
+            },
+            Some((_, token)) => args.push(spec.parse(token)?),
+            None if spec.optional => break,
+            None => return Err(ConsoleError::MissingArgument(spec.name.clone()))
+        }
+    }
+    if tokens.len() > specs.len() {
+        return Err(ConsoleError::TooManyArguments(specs.len()));
+    }
+    Ok(args)
+}
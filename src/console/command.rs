@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::console::{ Console, ConsoleError };
+use crate::console::args::{ Arg, ArgSpec };
+use crate::core::settings::Settings;
+
+/**
+ * What a command runs, returning Err prints the message as an error
+ **/
+pub type CommandHandler = Box<dyn Fn(&mut CommandContext, &[Arg]) -> Result<(), String> + Send + Sync>;
+
+/**
+ * A console command, arguments are parsed and checked before the handler sees them
+ **/
+pub struct Command {
+    pub name: String,
+    pub help: String,
+    pub args: Vec<ArgSpec>,
+    handler: CommandHandler,
+}
+
+impl Command {
+    pub fn new<F>(name: &str, help: &str, args: Vec<ArgSpec>, handler: F) -> Command
+        where F: Fn(&mut CommandContext, &[Arg]) -> Result<(), String> + Send + Sync + 'static {
+        Command { name: name.to_string(), help: help.to_string(), args, handler: Box::new(handler) }
+    }
+
+    pub fn run(&self, ctx: &mut CommandContext, args: &[Arg]) -> Result<(), String> {
+        (self.handler)(ctx, args)
+    }
+
+    /**
+     * name <arg: kind> [optional: kind]
+     **/
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for x in &self.args {
+            usage.push_str(&format!(" {}", x));
+        }
+        usage
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Command {{ {} }}", self.usage())
+    }
+}
+
+/**
+ * Handed to commands while they run
+ * Settings changes are picked up by the application once the command returns
+ **/
+pub struct CommandContext<'a> {
+    console: &'a Console,
+    settings: &'a mut Settings,
+    //scripts running scripts, to stop a script from running itself forever
+    depth: u32,
+}
+
+impl<'a> CommandContext<'a> {
+    pub(crate) fn new(console: &'a Console, settings: &'a mut Settings, depth: u32) -> CommandContext<'a> {
+        CommandContext { console, settings, depth }
+    }
+
+    #[inline]
+    pub fn console(&self) -> &Console {
+        self.console
+    }
+
+    #[inline]
+    pub fn settings(&self) -> &Settings {
+        self.settings
+    }
+
+    #[inline]
+    pub fn settings_mut(&mut self) -> &mut Settings {
+        self.settings
+    }
+
+    /**
+     * Adds a line to the console's scrollback
+     **/
+    pub fn print<S: Into<String>>(&self, text: S) {
+        self.console.print(text);
+    }
+
+    /**
+     * Runs another command line, for commands built out of others
+     **/
+    pub fn execute(&mut self, line: &str) -> Result<(), ConsoleError> {
+        self.console.execute_at(line, self.settings, self.depth)
+    }
+
+    /**
+     * Runs every line of a script file, see Console::run_script
+     **/
+    pub fn run_script(&mut self, path: &str) -> Result<(), ConsoleError> {
+        self.console.run_script_at(path, self.settings, self.depth + 1)
+    }
+}
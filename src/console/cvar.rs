@@ -0,0 +1,135 @@
+use std::fmt;
+
+use crate::audio::Bus;
+use crate::console::args::{ Arg, ArgKind, ArgSpec };
+use crate::core::settings::{ GraphicsMode, LogFormat, LogLevel, Settings };
+
+type CvarGet = Box<dyn Fn(&Settings) -> String + Send + Sync>;
+type CvarSet = Box<dyn Fn(&mut Settings, &Arg) -> Result<(), String> + Send + Sync>;
+
+/**
+ * A console variable backed by a Settings field
+ * Typing the name prints the value, the name and a value sets it
+ **/
+pub struct Cvar {
+    pub name: String,
+    pub help: String,
+    pub value: ArgSpec,
+    get: CvarGet,
+    set: CvarSet,
+}
+
+impl Cvar {
+    pub fn new<G, S>(name: &str, help: &str, value: ArgSpec, get: G, set: S) -> Cvar
+        where G: Fn(&Settings) -> String + Send + Sync + 'static,
+        S: Fn(&mut Settings, &Arg) -> Result<(), String> + Send + Sync + 'static {
+        Cvar { name: name.to_string(), help: help.to_string(), value, get: Box::new(get), set: Box::new(set) }
+    }
+
+    pub fn get(&self, settings: &Settings) -> String {
+        (self.get)(settings)
+    }
+
+    pub fn set(&self, settings: &mut Settings, value: &Arg) -> Result<(), String> {
+        (self.set)(settings, value)
+    }
+}
+
+impl fmt::Debug for Cvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cvar {{ {} {} }}", self.name, self.value)
+    }
+}
+
+fn in_range(value: i64, min: i64, max: i64) -> Result<u32, String> {
+    if value < min || value > max {
+        return Err(format!("expected {} to {}", min, max));
+    }
+    Ok(value as u32)
+}
+
+fn volume(name: &str, bus: Bus) -> Cvar {
+    Cvar::new(name, &format!("{:?} bus volume from 0 to 1", bus), ArgSpec::new("volume", ArgKind::Float),
+        move |x| x.audio().volume(bus).to_string(),
+        move |x, value| {
+            x.set_volume(bus, value.as_float().unwrap_or_default() as f32);
+            Ok(())
+        })
+}
+
+/**
+ * The cvars every console starts with, one per Settings field worth changing at runtime
+ **/
+pub fn settings_cvars() -> Vec<Cvar> {
+    vec![
+        Cvar::new("graphics.width", "Window width in screen coordinates", ArgSpec::new("width", ArgKind::Int),
+            |x| x.graphics().width().to_string(),
+            |x, value| {
+                let width = in_range(value.as_int().unwrap_or_default(), 320, 7680)?;
+                x.set_size(width, x.graphics().height());
+                Ok(())
+            }),
+        Cvar::new("graphics.height", "Window height in screen coordinates", ArgSpec::new("height", ArgKind::Int),
+            |x| x.graphics().height().to_string(),
+            |x, value| {
+                let height = in_range(value.as_int().unwrap_or_default(), 240, 4320)?;
+                x.set_size(x.graphics().width(), height);
+                Ok(())
+            }),
+        Cvar::new("graphics.vsync", "Swap interval, 0 off, 1 adaptive, 2 on",
+            ArgSpec::new("interval", ArgKind::Int).choices(&["0", "1", "2"]),
+            |x| x.graphics().vsync().to_string(),
+            |x, value| {
+                x.set_vsync(in_range(value.as_int().unwrap_or_default(), 0, 2)? as u8);
+                Ok(())
+            }),
+        Cvar::new("graphics.mode", "Rendering backend, applies on the next launch",
+            ArgSpec::new("mode", ArgKind::Text).choices(&["OpenGL", "Vulkan", "DirectX"]),
+            |x| format!("{:?}", x.graphics().mode()),
+            |x, value| {
+                x.set_graphics_mode(match value.as_str() {
+                    Some("Vulkan") => GraphicsMode::Vulkan,
+                    Some("DirectX") => GraphicsMode::DirectX,
+                    _ => GraphicsMode::OpenGL
+                });
+                Ok(())
+            }),
+        volume("audio.master", Bus::Master),
+        volume("audio.music", Bus::Music),
+        volume("audio.sfx", Bus::Sfx),
+        Cvar::new("log.level", "Default log level, log.module changes single modules",
+            ArgSpec::new("level", ArgKind::Text).choices(&["Off", "Error", "Warn", "Info", "Debug", "Trace"]),
+            |x| format!("{:?}", x.logging().level()),
+            |x, value| {
+                x.logging_mut().set_level(parse_level(value.as_str().unwrap_or_default()).unwrap_or(LogLevel::Info));
+                Ok(())
+            }),
+        Cvar::new("log.format", "Log file format", ArgSpec::new("format", ArgKind::Text).choices(&["Text", "Json"]),
+            |x| format!("{:?}", x.logging().format()),
+            |x, value| {
+                x.logging_mut().set_format(if value.as_str() == Some("Json") { LogFormat::Json } else { LogFormat::Text });
+                Ok(())
+            }),
+        Cvar::new("log.stderr", "Whether log messages are also written to stderr", ArgSpec::new("enabled", ArgKind::Bool),
+            |x| (x.logging().stderr() as u8).to_string(),
+            |x, value| {
+                x.logging_mut().set_stderr(value.as_bool().unwrap_or_default());
+                Ok(())
+            }),
+    ]
+}
+
+/**
+ * A level name as the log.level choices spell it
+ **/
+pub fn parse_level(name: &str) -> Option<LogLevel> {
+    match name {
+        "Off" => Some(LogLevel::Off),
+        "Error" => Some(LogLevel::Error),
+        "Warn" => Some(LogLevel::Warn),
+        "Info" => Some(LogLevel::Info),
+        "Debug" => Some(LogLevel::Debug),
+        "Trace" => Some(LogLevel::Trace),
+        _ => None
+    }
+}
@@ -0,0 +1,69 @@
+pub mod args;
+pub mod command;
+pub mod cvar;
+pub mod registry;
+
+pub use self::args::{ Arg, ArgKind, ArgSpec };
+pub use self::command::{ Command, CommandContext, CommandHandler };
+pub use self::cvar::Cvar;
+pub use self::registry::{ Console, ConsoleLine, LineKind };
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/**
+ * Script the application runs through the console before its first frame, when it exists
+ **/
+pub const STARTUP_SCRIPT: &str = "autoexec.cfg";
+
+/**
+ * Glfw keycode of the key left of 1, which opens and closes the console
+ **/
+pub const TOGGLE_KEY: i32 = 96;
+
+#[derive(Debug)]
+pub enum ConsoleError {
+    UnknownCommand(String),
+    MissingArgument(String),
+    TooManyArguments(usize),
+    InvalidArgument { name: String, expected: String, got: String },
+    /**
+     * The command ran and reported a problem
+     **/
+    Command(String),
+    /**
+     * Scripts ran each other too deep, most likely a script running itself
+     **/
+    ScriptDepth(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ConsoleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConsoleError::UnknownCommand(name) => write!(f, "Unknown command: {}", name),
+            ConsoleError::MissingArgument(name) => write!(f, "Missing argument: {}", name),
+            ConsoleError::TooManyArguments(max) => write!(f, "Too many arguments, expected at most {}", max),
+            ConsoleError::InvalidArgument { name, expected, got } => write!(f, "Expected {} for {}, got {}", expected, name, got),
+            ConsoleError::Command(msg) => write!(f, "{}", msg),
+            ConsoleError::ScriptDepth(path) => write!(f, "Scripts nested too deep running {}", path),
+            ConsoleError::Io(e) => write!(f, "Failed to read console script: {}", e),
+        }
+    }
+}
+
+impl Error for ConsoleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConsoleError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ConsoleError {
+    fn from(e: io::Error) -> ConsoleError {
+        ConsoleError::Io(e)
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::{ BTreeMap, VecDeque };
+use std::fs;
+use std::sync::{ Arc, Mutex, PoisonError, RwLock };
+
+use log::Level;
+
+use crate::console::ConsoleError;
+use crate::console::args::{ parse_args, tokenize, ArgKind, ArgSpec };
+use crate::console::command::{ Command, CommandContext };
+use crate::console::cvar::{ parse_level, settings_cvars, Cvar };
+use crate::core::logging;
+use crate::core::settings::Settings;
+
+/**
+ * Lines the console keeps, older ones are dropped
+ **/
+pub const SCROLLBACK: usize = 1000;
+
+/**
+ * Input lines kept for the up and down keys
+ **/
+pub const HISTORY: usize = 100;
+
+//how deep exec can nest before a script is assumed to be running itself
+const MAX_SCRIPT_DEPTH: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Input,
+    Output,
+    Error,
+    Log(Level),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConsoleLine {
+    pub kind: LineKind,
+    pub text: String,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    commands: BTreeMap<String, Arc<Command>>,
+    cvars: BTreeMap<String, Arc<Cvar>>,
+}
+
+#[derive(Debug, Default)]
+struct Scrollback {
+    lines: VecDeque<ConsoleLine>,
+    //the newest log line already copied in
+    last_log: u64,
+}
+
+/**
+ * Commands and cvars by name, plus what the console overlay shows
+ * Engine modules and games register commands on it, the application shares one through DebugUiState
+ **/
+#[derive(Debug)]
+pub struct Console {
+    registry: RwLock<Registry>,
+    scrollback: Mutex<Scrollback>,
+    history: Mutex<VecDeque<String>>,
+}
+
+impl Console {
+    /**
+     * A console with the built in commands and a cvar for each runtime setting
+     **/
+    pub fn new() -> Console {
+        let console = Console {
+            registry: RwLock::new(Registry::default()),
+            scrollback: Mutex::new(Scrollback::default()),
+            history: Mutex::new(VecDeque::new()),
+        };
+        for x in builtin_commands() {
+            console.register(x);
+        }
+        for x in settings_cvars() {
+            console.register_cvar(x);
+        }
+        console
+    }
+
+    /**
+     * Adds a command, replacing any with the same name
+     **/
+    pub fn register(&self, command: Command) {
+        let mut registry = self.registry.write().unwrap_or_else(PoisonError::into_inner);
+        if registry.cvars.contains_key(&command.name) {
+            warn!("Console command {} hides the cvar with the same name", command.name);
+        }
+        if registry.commands.insert(command.name.clone(), Arc::new(command)).is_some() {
+            debug!("Replaced a console command");
+        }
+    }
+
+    pub fn register_cvar(&self, cvar: Cvar) {
+        let mut registry = self.registry.write().unwrap_or_else(PoisonError::into_inner);
+        registry.cvars.insert(cvar.name.clone(), Arc::new(cvar));
+    }
+
+    /**
+     * Returns whether there was a command or cvar called `name`
+     **/
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut registry = self.registry.write().unwrap_or_else(PoisonError::into_inner);
+        registry.commands.remove(name).is_some() | registry.cvars.remove(name).is_some()
+    }
+
+    pub fn command(&self, name: &str) -> Option<Arc<Command>> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner).commands.get(name).cloned()
+    }
+
+    pub fn cvar(&self, name: &str) -> Option<Arc<Cvar>> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner).cvars.get(name).cloned()
+    }
+
+    /**
+     * Every command, sorted by name
+     **/
+    pub fn commands(&self) -> Vec<Arc<Command>> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner).commands.values().cloned().collect()
+    }
+
+    /**
+     * Every cvar, sorted by name
+     **/
+    pub fn cvars(&self) -> Vec<Arc<Cvar>> {
+        self.registry.read().unwrap_or_else(PoisonError::into_inner).cvars.values().cloned().collect()
+    }
+
+    /**
+     * Runs a line typed into the console, echoing it and printing any error to the scrollback
+     **/
+    pub fn submit(&self, line: &str, settings: &mut Settings) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        self.push(LineKind::Input, format!("> {}", line));
+        {
+            let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+            if history.back().map(|x| x.as_str()) != Some(line) {
+                if history.len() >= HISTORY {
+                    history.pop_front();
+                }
+                history.push_back(line.to_string());
+            }
+        }
+        if let Err(e) = self.execute(line, settings) {
+            self.push(LineKind::Error, e.to_string());
+        }
+    }
+
+    /**
+     * Runs one command line, blank lines and lines starting with # or // do nothing
+     **/
+    pub fn execute(&self, line: &str, settings: &mut Settings) -> Result<(), ConsoleError> {
+        self.execute_at(line, settings, 0)
+    }
+
+    pub(crate) fn execute_at(&self, line: &str, settings: &mut Settings, depth: u32) -> Result<(), ConsoleError> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+            return Ok(());
+        }
+        let tokens = tokenize(line);
+        let name = match tokens.first() {
+            Some(x) => x.1.as_str(),
+            None => return Ok(())
+        };
+        let args_start = tokens.get(1).map_or(line.len(), |x| x.0);
+        let rest = &tokens[1..];
+
+        if let Some(command) = self.command(name) {
+            let args = parse_args(&command.args, &line[args_start..], &rebase(rest, args_start))?;
+            let mut ctx = CommandContext::new(self, settings, depth);
+            return command.run(&mut ctx, &args).map_err(ConsoleError::Command);
+        }
+        if let Some(cvar) = self.cvar(name) {
+            if !rest.is_empty() {
+                let value = parse_args(std::slice::from_ref(&cvar.value), &line[args_start..], &rebase(rest, args_start))?;
+                cvar.set(settings, &value[0]).map_err(ConsoleError::Command)?;
+            }
+            self.print(format!("{} = {}", cvar.name, cvar.get(settings)));
+            return Ok(());
+        }
+        Err(ConsoleError::UnknownCommand(name.to_string()))
+    }
+
+    /**
+     * Runs a file of commands, one per line, errors are printed with their line number and
+     * the rest of the file still runs
+     * Fails only when the file can't be read
+     **/
+    pub fn run_script(&self, path: &str, settings: &mut Settings) -> Result<(), ConsoleError> {
+        self.run_script_at(path, settings, 0)
+    }
+
+    pub(crate) fn run_script_at(&self, path: &str, settings: &mut Settings, depth: u32) -> Result<(), ConsoleError> {
+        if depth > MAX_SCRIPT_DEPTH {
+            return Err(ConsoleError::ScriptDepth(path.to_string()));
+        }
+        let script = fs::read_to_string(path)?;
+        for (i, line) in script.lines().enumerate() {
+            if let Err(e) = self.execute_at(line, settings, depth) {
+                self.push(LineKind::Error, format!("{}:{}: {}", path, i + 1, e));
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Ways to finish `input`, each a whole line
+     * Command and cvar names first, then argument choices once a name is typed
+     **/
+    pub fn complete(&self, input: &str) -> Vec<String> {
+        let tokens = tokenize(input);
+        let typing_new = input.is_empty() || input.ends_with(char::is_whitespace);
+        if tokens.len() <= 1 && !typing_new || tokens.is_empty() {
+            let prefix = tokens.first().map_or("", |x| x.1.as_str());
+            let registry = self.registry.read().unwrap_or_else(PoisonError::into_inner);
+            let mut names: Vec<String> = registry.commands.keys().chain(registry.cvars.keys())
+                .filter(|x| x.starts_with(prefix))
+                .cloned()
+                .collect();
+            names.sort();
+            names.dedup();
+            return names;
+        }
+
+        let specs = match (self.command(&tokens[0].1), self.cvar(&tokens[0].1)) {
+            (Some(x), _) => x.args.clone(),
+            (None, Some(x)) => vec![x.value.clone()],
+            _ => return Vec::new()
+        };
+        let (index, start, prefix) = if typing_new {
+            (tokens.len() - 1, input.len(), "")
+        }
+        else {
+            let last = &tokens[tokens.len() - 1];
+            (tokens.len() - 2, last.0, last.1.as_str())
+        };
+        let spec = match specs.get(index) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+        spec.suggestions().into_iter()
+            .filter(|x| x.to_ascii_lowercase().starts_with(&prefix.to_ascii_lowercase()))
+            .map(|x| format!("{}{}", &input[..start], x))
+            .collect()
+    }
+
+    pub fn print<S: Into<String>>(&self, text: S) {
+        self.push(LineKind::Output, text.into());
+    }
+
+    pub fn print_error<S: Into<String>>(&self, text: S) {
+        self.push(LineKind::Error, text.into());
+    }
+
+    fn push(&self, kind: LineKind, text: String) {
+        let mut scrollback = self.scrollback.lock().unwrap_or_else(PoisonError::into_inner);
+        for line in text.lines() {
+            if scrollback.lines.len() >= SCROLLBACK {
+                scrollback.lines.pop_front();
+            }
+            scrollback.lines.push_back(ConsoleLine { kind, text: line.to_string() });
+        }
+    }
+
+    /**
+     * Copies log messages logged since the last call into the scrollback
+     **/
+    pub fn sync_log(&self) {
+        let mut scrollback = self.scrollback.lock().unwrap_or_else(PoisonError::into_inner);
+        for x in logging::console_since(scrollback.last_log) {
+            scrollback.last_log = x.id;
+            if scrollback.lines.len() >= SCROLLBACK {
+                scrollback.lines.pop_front();
+            }
+            let text = format!("[{}][{}][{}] {}", x.time, x.target, x.level, x.message);
+            scrollback.lines.push_back(ConsoleLine { kind: LineKind::Log(x.level), text });
+        }
+    }
+
+    /**
+     * The scrollback, oldest first
+     **/
+    pub fn lines(&self) -> Vec<ConsoleLine> {
+        self.scrollback.lock().unwrap_or_else(PoisonError::into_inner).lines.iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.scrollback.lock().unwrap_or_else(PoisonError::into_inner).lines.clear();
+    }
+
+    /**
+     * Submitted lines, oldest first
+     **/
+    pub fn history(&self) -> Vec<String> {
+        self.history.lock().unwrap_or_else(PoisonError::into_inner).iter().cloned().collect()
+    }
+}
+
+impl Default for Console {
+    fn default() -> Console {
+        Console::new()
+    }
+}
+
+/**
+ * The longest start every candidate shares, what Tab fills in when there's more than one
+ **/
+pub fn common_prefix(candidates: &[String]) -> String {
+    let first = match candidates.first() {
+        Some(x) => x,
+        None => return String::new()
+    };
+    let mut len = first.len();
+    for x in &candidates[1..] {
+        len = first.char_indices().zip(x.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+//token offsets relative to where the arguments start
+fn rebase(tokens: &[(usize, String)], start: usize) -> Vec<(usize, String)> {
+    tokens.iter().map(|(i, x)| (i - start, x.clone())).collect()
+}
+
+fn builtin_commands() -> Vec<Command> {
+    vec![
+        Command::new("help", "Lists commands and cvars, or shows how to use one",
+            vec![ArgSpec::new("name", ArgKind::Text).optional()],
+            |ctx, args| {
+                let console = ctx.console();
+                if let Some(name) = args.first().and_then(|x| x.as_str()) {
+                    return match (console.command(name), console.cvar(name)) {
+                        (Some(x), _) => {
+                            console.print(format!("{} - {}", x.usage(), x.help));
+                            Ok(())
+                        },
+                        (None, Some(x)) => {
+                            console.print(format!("{} {} - {}", x.name, x.value, x.help));
+                            Ok(())
+                        },
+                        _ => Err(format!("No command or cvar called {}", name))
+                    };
+                }
+                for x in console.commands() {
+                    console.print(format!("{} - {}", x.usage(), x.help));
+                }
+                for x in console.cvars() {
+                    console.print(format!("{} {} - {}", x.name, x.value, x.help));
+                }
+                Ok(())
+            }),
+        Command::new("cvars", "Lists every cvar with its value", Vec::new(), |ctx, _| {
+            for x in ctx.console().cvars() {
+                ctx.print(format!("{} = {}", x.name, x.get(ctx.settings())));
+            }
+            Ok(())
+        }),
+        Command::new("echo", "Prints its arguments", vec![ArgSpec::new("text", ArgKind::Rest).optional()], |ctx, args| {
+            ctx.print(args.first().map(|x| x.to_string()).unwrap_or_default());
+            Ok(())
+        }),
+        Command::new("clear", "Empties the scrollback", Vec::new(), |ctx, _| {
+            ctx.console().clear();
+            Ok(())
+        }),
+        Command::new("exec", "Runs each line of a script file as a command", vec![ArgSpec::new("file", ArgKind::Text)],
+            |ctx, args| {
+                let path = args[0].to_string();
+                ctx.run_script(&path).map_err(|e| format!("{}: {}", path, e))
+            }),
+        Command::new("log.module", "Sets the log level for one module, Default goes back to log.level",
+            vec![
+                ArgSpec::new("module", ArgKind::Text),
+                ArgSpec::new("level", ArgKind::Text).choices(&["Off", "Error", "Warn", "Info", "Debug", "Trace", "Default"])
+            ],
+            |ctx, args| {
+                let module = args[0].to_string();
+                let level = args[1].as_str().and_then(parse_level);
+                ctx.settings_mut().logging_mut().set_module_level(&module, level);
+                Ok(())
+            }),
+    ]
+}
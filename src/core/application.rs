@@ -6,14 +6,21 @@
  *
  **/
 use std::any::Any;
-use std::sync::{ Arc, RwLock };
+use std::sync::{ Arc, Mutex, Once, PoisonError, RwLock };
 use crate::core::signals::{ SyncData, SyncSignal, SyncSlot, SyncSlotPair };
 use crate::events::event::*;
 use crate::events::event::Event;
 use crate::events::window_events::*;
+use crate::events::render_events::*;
 use crate::events::mouse_events::*;
 use crate::events::key_events::*;
+use crate::events::physics_events::*;
+use crate::events::animation_events::*;
+use crate::core::crash;
+use crate::core::error::MagnusError;
+use crate::core::logging;
 use crate::core::settings::Settings;
+#[cfg(windows)]
 use crate::core::settings::GraphicsMode;
 use crate::core::graphics;
 use crate::core::graphics::opengl::OpenGLContext;
@@ -22,6 +29,23 @@ use crate::core::graphics::vulkan::VulkanContext;
 use crate::core::graphics::directx::DirectXContext;
 use crate::core::window::*;
 use crate::core::layers::*;
+use crate::core::camera::Camera;
+use crate::core::frame::{ triple_buffer, FramePacket, FrameWriter, Surface, TilemapFrame };
+use crate::core::stats::{ self, FrameStats, RenderSample };
+use crate::core::timestep::FixedTimestep;
+use crate::core::input::InputState;
+use crate::jobs::JobSystem;
+use crate::math::{ Vec2, Vec3 };
+use crate::physics::{ PhysicsWorld2D, PhysicsWorld3D };
+use crate::animation::{ AnimationWorld, TweenManager };
+use crate::audio::{ Audio, AudioOutput };
+use crate::console::{ Console, STARTUP_SCRIPT };
+use crate::particles::{ ParticleRenderer, ParticleWorld };
+use crate::profiling::{ GlGpuTimer, VulkanGpuTimer };
+use crate::scripting::{ self, ScriptHost };
+use crate::text::FontSet;
+use crate::tilemap::{ self, Tilemap, TilemapRenderer };
+use crate::ui::{ DebugUi, DebugUiState, GameUi, UiPainter, UiRenderer, UiTree };
 
 #[repr(C)]
 pub struct MagnusApplication<T: graphics::context::ContextLimiter> {
@@ -31,209 +55,393 @@ pub struct MagnusApplication<T: graphics::context::ContextLimiter> {
     window: Window<T>,
     layer_stack: LayerStack,
     event_handler: Arc<RwLock<dyn SyncSlot<EventData>>>,
+    jobs: Arc<JobSystem>,
+    physics_2d: Arc<RwLock<PhysicsWorld2D>>,
+    physics_3d: Arc<RwLock<PhysicsWorld3D>>,
+    animation: Arc<RwLock<AnimationWorld>>,
+    tweens: Arc<Mutex<TweenManager>>,
+    particles: Arc<RwLock<ParticleWorld>>,
+    camera: Arc<RwLock<Camera>>,
+    input: Arc<RwLock<InputState>>,
+    debug_ui: Arc<DebugUiState>,
+    fonts: Arc<FontSet>,
+    uis: Vec<Arc<Mutex<UiTree>>>,
+    tilemaps: Vec<Arc<RwLock<Tilemap>>>,
+    audio: Option<Arc<Audio>>,
 }
 
 impl MagnusApplication<OpenGLContext> {
-    pub fn new(name: String, settings: Settings) -> MagnusApplication<OpenGLContext> {
-        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode());
+    pub fn new(name: String, settings: Settings) -> Result<MagnusApplication<OpenGLContext>, MagnusError> {
+        apply_logging(&settings);
+        record_crash_context(&name, &settings);
+        register_components();
+        let gl_version = match settings.graphics().gl_version() {
+            (0, _) => None,
+            x => Some(x)
+        };
+        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode(), gl_version);
+        let mut window = Window::<OpenGLContext>::new(props)?;
+        //loaded here so a context that can't draw fails before the application exists
+        window.get_context().api_context().load_symbols()?;
+        crash::record_gl_device();
+        let debug_ui = Arc::new(DebugUiState::new(&name, settings.clone()));
 
-        MagnusApplication {
+        Ok(MagnusApplication {
             name,
             running: true,
             settings,
-            window: Window::<OpenGLContext>::new(props),
+            window,
             layer_stack: LayerStack::new(None, None),
             event_handler: EventHandler::new(),
-        }
+            jobs: Arc::new(JobSystem::new(None)),
+            physics_2d: Arc::new(RwLock::new(PhysicsWorld2D::new(Vec2::new(0.0, -9.81)))),
+            physics_3d: Arc::new(RwLock::new(PhysicsWorld3D::new(Vec3::new(0.0, -9.81, 0.0)))),
+            animation: Arc::new(RwLock::new(AnimationWorld::new())),
+            tweens: Arc::new(Mutex::new(TweenManager::new())),
+            particles: Arc::new(RwLock::new(ParticleWorld::new())),
+            camera: Arc::new(RwLock::new(Camera::default())),
+            input: Arc::new(RwLock::new(InputState::new())),
+            debug_ui,
+            fonts: Arc::new(FontSet::default()),
+            uis: Vec::new(),
+            tilemaps: Vec::new(),
+            audio: None,
+        })
     }
 
-    pub fn run(mut self)  {
-        use std::thread;
+    /**
+     * The render thread owns the window, it polls events and passes them to the update thread
+     * over a channel, then draws the newest FramePacket the update thread published
+     * Neither thread ever waits on the other
+     **/
+    pub fn run(mut self) -> Result<(), MagnusError> {
         use std::sync::atomic::{ AtomicBool, Ordering };
+        use std::sync::mpsc;
 
         debug!("Application {} Started", self.name);
-        self.window.get_context().api_context().load_symbols().expect("Failed to load graphics context symbols");
-        self.window.set_vsync(0);
-        SyncSignal::<WindowResizeEvent, EventData>::connect::<WindowResizeEvent>(&mut self.window, Arc::clone(&self.event_handler));
-        SyncSignal::<WindowCloseEvent, EventData>::connect::<WindowCloseEvent>(&mut self.window, Arc::clone(&self.event_handler));
+        self.window.set_vsync(self.settings.graphics().vsync());
+        //settings the script changes are applied with the first frame, like the settings editor's
+        run_startup_script(&self.debug_ui);
+        ScriptHost::global().set_input(Arc::clone(&self.input));
+        let (event_sender, events) = mpsc::channel();
+        let forwarder: Arc<RwLock<dyn SyncSlot<EventData>>> = Arc::new(RwLock::new(EventForwarder { sender: event_sender }));
+        //contacts and keyframes join the window's events and reach the layers at the start of the next tick
+        self.connect_physics(&forwarder);
+        self.connect_animation(&forwarder);
+        connect_input_events(&mut self.window, &forwarder);
+        connect_surface_events(&mut self.window, &forwarder);
+        SyncSignal::<WindowCloseEvent, EventData>::connect::<WindowCloseEvent>(&mut self.window, Arc::clone(&forwarder));
+        SyncSignal::<WindowFocusEvent, EventData>::connect::<WindowFocusEvent>(&mut self.window, Arc::clone(&forwarder));
         unsafe {
             gl::ClearColor(1.0, 0.0, 1.0, 1.0);
         }
-        let close_backup = Arc::new(AtomicBool::new(false));
-        let event_handler = Arc::clone(&self.event_handler);
-        let window = Arc::new(RwLock::new(self.window));
-        let layer_stack = Arc::new(RwLock::new(self.layer_stack));
-        debug!("Starting update thread");
-        let update_thread = {
-            let closed_backup = Arc::clone(&close_backup);
-            let stack = Arc::clone(&layer_stack);
-            let win = Arc::clone(&window);
-            thread::spawn(move || {
-                let mut close = false;
-                'update: while !close {
-                    std::thread::sleep(std::time::Duration::from_millis(10));
-                    match win.write() {
-                        Ok(mut x) => if x.on_update() {
-                            warn!("App should close!");
-                            close = true;
-                        },
-                        _ => {
-                            error!("Window RWLock is Poisoned (Update Thread)");
-                            error!("Signalling to shutdown");
-                            close = true;
-                        }
-                    }
-
-                    if close {
-                        //shutting down this thread can poison the shared RWLocks
-                        //so need a backup way to tell main to shutdown other than just
-                        //the signal emitted to the event handler
-                        warn!("Update thread shutting down");
-                        closed_backup.store(true, Ordering::SeqCst);
-                        break 'update;
-                    }
-
-                    match stack.try_write() {
-                        Ok(mut x) => for item in x.iter_mut() {
-                            item.on_update();
-                        },
-                        _ => debug!("Unable to lock layer stack for updates")
-                    }
-                }
-            })
+        let mut ui_painter = match UiPainter::new() {
+            Ok(x) => Some(x),
+            Err(e) => {
+                error!("Debug UI won't be drawn: {}", e);
+                None
+            }
         };
-
-        let mut close = false;
-        let mut frames: usize = 0;
-        let mut timer_start = std::time::Instant::now();
-        'main: while !close {
-
-            if close_backup.load(Ordering::SeqCst) {
-                warn!("Main/Render thread shutting down via backup signal");
-                break 'main;
+        let mut particle_renderer = match ParticleRenderer::new() {
+            Ok(x) => Some(x),
+            Err(e) => {
+                error!("Particles won't be drawn: {}", e);
+                None
             }
-
-            match event_handler.try_read() {
-                Ok(x) => if x.as_any().downcast_ref::<EventHandler>().unwrap().should_close() {
-                    close = true;
-                }
-                _ => debug!("Unable to lock event_handler for should_close check")
+        };
+        let mut ui_renderer = match UiRenderer::new(None) {
+            Ok(x) => Some(x),
+            Err(e) => {
+                error!("Game UI won't be drawn: {}", e);
+                None
             }
+        };
+        //made as maps show up in the frame packets, one per map in the order they were pushed
+        let mut tilemap_renderers: Vec<TilemapRenderer> = Vec::new();
+        let mut tilemaps_drawable = true;
+        //GPU passes are only timed when the profiler is compiled in
+        let mut gpu_timer = GlGpuTimer::new();
+        gpu_timer.set_enabled(cfg!(feature = "profiling"));
+        let fonts = Arc::clone(&self.fonts);
+        let debug_ui = Arc::clone(&self.debug_ui);
+        let running = Arc::new(AtomicBool::new(true));
+        let (writer, mut reader) = triple_buffer(FramePacket::default());
+        let (surface_sender, surfaces) = mpsc::channel();
+        let (sample_sender, samples) = mpsc::channel();
+        let mut surface = window_surface(&mut self.window);
+        //the update thread lays out UIs for the window it's told about, so tell it before it starts
+        let _ = surface_sender.send(surface);
+        debug!("Starting update thread");
+        let update_thread = self.spawn_update(&running, events, surfaces, samples, writer)?;
 
+        let mut last_frame = std::time::Instant::now();
+        let mut frames = 0u64;
+        let mut gpu_memory = None;
+        loop {
+            profile_scope!("render frame");
+            let close = {
+                profile_scope!("poll events");
+                self.window.on_update()
+            };
             if close {
-                warn!("Main/Render thread shutting down normally");
-                break 'main;
+                warn!("App should close!");
+                break;
+            }
+            if update_thread.is_finished() {
+                error!("Update thread stopped, shutting down");
+                break;
             }
 
-            //debug!("Window width is {}", self.window.get_width());
-            //debug!("Changing clear color");
+            if let Some(settings) = debug_ui.take_settings_change() {
+                apply_settings(&mut self.window, &mut self.settings, settings);
+            }
+            let current = window_surface(&mut self.window);
+            if current != surface {
+                surface = current;
+                let _ = surface_sender.send(surface);
+            }
+            let (size, framebuffer) = (surface.size, surface.framebuffer);
+            debug_ui.set_screen(size, framebuffer);
+
+            //keeps drawing the last packet until a newer one is published
+            reader.update();
+            let packet = reader.packet();
+            gpu_timer.begin_frame();
+            gpu_timer.begin("clear");
             unsafe {
                 gl::Clear(gl::COLOR_BUFFER_BIT);
             }
-            match window.write() {
-                Ok(mut x) => x.get_context().swap_buffers(),
-                _ => {
-                    error!("Window RWLock is Poisoned (Render Thread)");
-                    error!("Assuming close signal has been given");
-                    error!("Shutting down");
-                    return;
+            gpu_timer.begin("tilemaps");
+            while tilemaps_drawable && tilemap_renderers.len() < packet.tilemaps.len() {
+                match TilemapRenderer::new() {
+                    Ok(x) => tilemap_renderers.push(x),
+                    Err(e) => {
+                        error!("Tilemap {} and the ones after it won't be drawn: {}", tilemap_renderers.len(), e);
+                        tilemaps_drawable = false;
+                    }
                 }
             }
-            frames += 1;
-            if std::time::Instant::now().duration_since(timer_start).as_millis() > 1000 {
-                debug!("{} FPS", frames);
-                frames = 0;
-                timer_start = std::time::Instant::now();
+            for (map, renderer) in packet.tilemaps.iter().zip(tilemap_renderers.iter_mut()) {
+                profile_scope!("draw tilemap");
+                renderer.draw_at(&map.map, map.time, &packet.camera, framebuffer);
+            }
+            if let Some(renderer) = particle_renderer.as_mut() {
+                profile_scope!("draw particles");
+                gpu_timer.begin("particles");
+                renderer.draw(&packet.particles, &packet.camera, framebuffer);
             }
+            if let Some(renderer) = ui_renderer.as_mut() {
+                profile_scope!("draw game ui");
+                gpu_timer.begin("game ui");
+                for commands in &packet.ui {
+                    renderer.draw(&fonts, commands, size, framebuffer);
+                }
+            }
+            if let (Some(painter), Some((frame, textures))) = (ui_painter.as_mut(), debug_ui.take_output()) {
+                profile_scope!("draw debug ui");
+                gpu_timer.begin("debug ui");
+                painter.paint(&frame, textures, (framebuffer.0 as u32, framebuffer.1 as u32));
+            }
+            gpu_timer.end();
+            {
+                profile_scope!("swap buffers");
+                self.window.get_context().swap_buffers();
+            }
+
+            //the update thread records the sample, the render thread never takes the stats lock
+            let now = std::time::Instant::now();
+            //the memory query walks the extension list, once a second or so is plenty
+            if frames.is_multiple_of(60) {
+                gpu_memory = stats::gl_gpu_memory();
+            }
+            frames += 1;
+            let textures = tilemap_renderers.iter().map(|x| x.texture_count()).sum::<usize>()
+                + ui_painter.as_ref().map_or(0, |x| x.texture_count());
+            let _ = sample_sender.send(RenderSample {
+                frame_time: now.duration_since(last_frame).as_secs_f32(),
+                gpu_time: gpu_timer.last_frame_time().map(|x| x.as_secs_f32()),
+                gpu_memory,
+                draw_calls: stats::take_draw_calls(),
+                assets: vec![("fonts", fonts.len()), ("textures", textures), ("ui trees", packet.ui.len())],
+            });
+            last_frame = now;
+        }
+        running.store(false, Ordering::SeqCst);
+        let joined = update_thread.join();
+        #[cfg(feature = "profiling")]
+        match crate::profiling::Profiler::global().write_chrome_trace(crate::profiling::TRACE_FILE) {
+            Ok(()) => info!("Wrote profile to {}", crate::profiling::TRACE_FILE),
+            Err(e) => error!("Couldn't write profile: {}", e)
         }
-        update_thread.join();
+        joined.map_err(|_| MagnusError::ThreadPanicked("update"))
     }
 }
 
 impl MagnusApplication<VulkanContext> {
-    pub fn new(name: String, settings: Settings) -> MagnusApplication<VulkanContext> {
-        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode());
+    pub fn new(name: String, settings: Settings) -> Result<MagnusApplication<VulkanContext>, MagnusError> {
+        apply_logging(&settings);
+        record_crash_context(&name, &settings);
+        register_components();
+        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode(), None);
+        let debug_ui = Arc::new(DebugUiState::new(&name, settings.clone()));
 
-        MagnusApplication {
+        Ok(MagnusApplication {
             name,
             running: true,
+            window: Window::<VulkanContext>::new(props, settings.graphics().vulkan_id())?,
             settings,
-            window: Window::<VulkanContext>::new(props, settings.graphics().vulkan_id()),
             layer_stack: LayerStack::new(None, None),
             event_handler: EventHandler::new(),
-        }
+            jobs: Arc::new(JobSystem::new(None)),
+            physics_2d: Arc::new(RwLock::new(PhysicsWorld2D::new(Vec2::new(0.0, -9.81)))),
+            physics_3d: Arc::new(RwLock::new(PhysicsWorld3D::new(Vec3::new(0.0, -9.81, 0.0)))),
+            animation: Arc::new(RwLock::new(AnimationWorld::new())),
+            tweens: Arc::new(Mutex::new(TweenManager::new())),
+            particles: Arc::new(RwLock::new(ParticleWorld::new())),
+            camera: Arc::new(RwLock::new(Camera::default())),
+            input: Arc::new(RwLock::new(InputState::new())),
+            debug_ui,
+            fonts: Arc::new(FontSet::default()),
+            uis: Vec::new(),
+            tilemaps: Vec::new(),
+            audio: None,
+        })
     }
 
-    pub fn run(mut self)  {
-        use std::thread;
+    /**
+     * Same split as the OpenGL loop, the main thread owns the window and forwards its events
+     * while the update thread owns the layer stack and publishes a FramePacket every tick
+     * There's no Vulkan renderer yet, taking a new packet counts as a frame and the GPU timer
+     * times each one with timestamps in a submission of its own
+     **/
+    pub fn run(mut self) -> Result<(), MagnusError> {
+        use std::sync::atomic::{ AtomicBool, Ordering };
+        use std::sync::mpsc;
 
         debug!("Application {} Started", self.name);
+        run_startup_script(&self.debug_ui);
+        ScriptHost::global().set_input(Arc::clone(&self.input));
+        let (event_sender, events) = mpsc::channel();
+        let forwarder: Arc<RwLock<dyn SyncSlot<EventData>>> = Arc::new(RwLock::new(EventForwarder { sender: event_sender }));
+        self.connect_physics(&forwarder);
+        self.connect_animation(&forwarder);
+        connect_input_events(&mut self.window, &forwarder);
+        connect_surface_events(&mut self.window, &forwarder);
+        SyncSignal::<WindowCloseEvent, EventData>::connect::<WindowCloseEvent>(&mut self.window, Arc::clone(&forwarder));
+        SyncSignal::<WindowFocusEvent, EventData>::connect::<WindowFocusEvent>(&mut self.window, Arc::clone(&forwarder));
+        let debug_ui = Arc::clone(&self.debug_ui);
+        let running = Arc::new(AtomicBool::new(true));
+        let (writer, mut reader) = triple_buffer(FramePacket::default());
+        let (surface_sender, surfaces) = mpsc::channel();
+        let (sample_sender, samples) = mpsc::channel();
+        let mut surface = vulkan_surface(&mut self.window);
+        let _ = surface_sender.send(surface);
         debug!("Starting update thread");
-        let settings = Arc::new(RwLock::new(self.settings));
-        let window = Arc::new(RwLock::new(self.window));
-        let layer_stack = Arc::new(RwLock::new(self.layer_stack));
-        let update_thread = {
-            let stack = Arc::clone(&layer_stack);
-            let win = Arc::clone(&window);
-            thread::spawn(move || {
-                loop {
-                    match stack.try_write() {
-                        Ok(mut x) => for item in x.iter_mut() {
-                            item.on_update();
-                        },
-                        _ => debug!("Unable to lock layer stack for updates")
-                    }
+        let update_thread = self.spawn_update(&running, events, surfaces, samples, writer)?;
 
-                    match win.try_write() {
-                        Ok(mut x) => if x.on_update() {
-                            return
-                        },
-                        _ => debug!("Unable to lock window for updates")
-                    }
-                }
-            })
+        //GPU passes are only timed when the profiler is compiled in
+        let queue = self.window.get_context().api_context().get_queue();
+        let mut gpu_timer = match VulkanGpuTimer::new(self.window.get_context().api_context().get_device(), None) {
+            Ok(mut x) => {
+                x.set_enabled(cfg!(feature = "profiling"));
+                Some(x)
+            },
+            Err(e) => {
+                warn!("GPU time won't be measured: {}", e);
+                None
+            }
         };
-        'main: loop {
-            debug!("Changing clear color");
-            unsafe {
-                match settings.try_read() {
-                    Ok(x) => if x.graphics().mode() == GraphicsMode::OpenGL {
-                        gl::ClearColor(1.0, 0.0, 1.0, 1.0);
-                        gl::Clear(gl::COLOR_BUFFER_BIT);
-                    },
-                    _ => debug!("Unable to lock settings for graphics mode check")
-                }
+        let mut last_frame = std::time::Instant::now();
+        loop {
+            profile_scope!("render frame");
+            if self.window.on_update() {
+                warn!("App should close!");
+                break;
+            }
+            if update_thread.is_finished() {
+                error!("Update thread stopped, shutting down");
+                break;
+            }
+
+            let current = vulkan_surface(&mut self.window);
+            if current != surface {
+                surface = current;
+                let _ = surface_sender.send(surface);
             }
+            debug_ui.set_screen(surface.size, surface.framebuffer);
 
-            match window.try_read() {
-                Ok(x) => if x.should_close() {
-                    break 'main;
-                },
-                _ => debug!("Unable to lock window for should_close check")
+            if reader.update() {
+                if let Some(timer) = gpu_timer.as_mut() {
+                    if let Err(e) = timer.submit_frame(&queue, "frame") {
+                        error!("Stopped measuring GPU time: {}", e);
+                        gpu_timer = None;
+                    }
+                }
+                let now = std::time::Instant::now();
+                let _ = sample_sender.send(RenderSample {
+                    frame_time: now.duration_since(last_frame).as_secs_f32(),
+                    gpu_time: gpu_timer.as_ref().and_then(|x| x.last_frame_time()).map(|x| x.as_secs_f32()),
+                    draw_calls: stats::take_draw_calls(),
+                    assets: vec![("ui trees", reader.packet().ui.len())],
+                    ..RenderSample::default()
+                });
+                last_frame = now;
             }
         }
-        update_thread.join();
+        running.store(false, Ordering::SeqCst);
+        let joined = update_thread.join();
+        #[cfg(feature = "profiling")]
+        match crate::profiling::Profiler::global().write_chrome_trace(crate::profiling::TRACE_FILE) {
+            Ok(()) => info!("Wrote profile to {}", crate::profiling::TRACE_FILE),
+            Err(e) => error!("Couldn't write profile: {}", e)
+        }
+        joined.map_err(|_| MagnusError::ThreadPanicked("update"))
     }
 }
 
 #[cfg(windows)]
 impl MagnusApplication<DirectXContext> {
-    pub fn new(name: String, width: i32, height: i32, settings: Settings) -> MagnusApplication<DirectXContext> {
-        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode());
+    pub fn new(name: String, width: i32, height: i32, settings: Settings) -> Result<MagnusApplication<DirectXContext>, MagnusError> {
+        apply_logging(&settings);
+        record_crash_context(&name, &settings);
+        register_components();
+        let props = WindowProps::new(name.clone(), Some(settings.graphics().size()), settings.graphics().mode(), None);
+        let debug_ui = Arc::new(DebugUiState::new(&name, settings.clone()));
 
-        MagnusApplication {
+        Ok(MagnusApplication {
             name,
             running: true,
             settings,
-            window: Window::<DirectXContext>::new(props),
+            window: Window::<DirectXContext>::new(props)?,
             layer_stack: LayerStack::new(None, None),
-            event_handler: EventHandler::new()
-        }
+            event_handler: EventHandler::new(),
+            jobs: Arc::new(JobSystem::new(None)),
+            physics_2d: Arc::new(RwLock::new(PhysicsWorld2D::new(Vec2::new(0.0, -9.81)))),
+            physics_3d: Arc::new(RwLock::new(PhysicsWorld3D::new(Vec3::new(0.0, -9.81, 0.0)))),
+            animation: Arc::new(RwLock::new(AnimationWorld::new())),
+            tweens: Arc::new(Mutex::new(TweenManager::new())),
+            particles: Arc::new(RwLock::new(ParticleWorld::new())),
+            camera: Arc::new(RwLock::new(Camera::default())),
+            input: Arc::new(RwLock::new(InputState::new())),
+            debug_ui,
+            fonts: Arc::new(FontSet::default()),
+            uis: Vec::new(),
+            tilemaps: Vec::new(),
+            audio: None,
+        })
     }
 
-    pub fn run(mut self)  {
+    pub fn run(mut self) -> Result<(), MagnusError> {
 
         debug!("Application {} Started", self.name);
+        run_startup_script(&self.debug_ui);
+        let (event_sender, world_events) = std::sync::mpsc::channel();
+        let forwarder: Arc<RwLock<dyn SyncSlot<EventData>>> = Arc::new(RwLock::new(EventForwarder { sender: event_sender }));
+        self.connect_physics(&forwarder);
+        self.connect_animation(&forwarder);
+        self.connect_input();
+        let mut timestep = FixedTimestep::default();
+        let mut last_update = std::time::Instant::now();
         'main: loop {
             debug!("Window width is {}", self.window.get_width());
             debug!("Changing clear color");
@@ -243,13 +451,28 @@ impl MagnusApplication<DirectXContext> {
                     gl::Clear(gl::COLOR_BUFFER_BIT);
                 }
             }
-            for item in self.layer_stack.iter_mut() {
-                item.on_update();
+            update_layers(&mut self.layer_stack);
+            cancel_orphaned_tweens(&self.tweens, &self.layer_stack);
+            update_frame(&self.jobs, &self.animation, &self.tweens, &self.particles, &self.tilemaps, &mut last_update);
+            for _ in 0..timestep.advance() {
+                match self.physics_2d.write() {
+                    Ok(mut x) => x.step(timestep.step()),
+                    _ => error!("Physics RWLock is Poisoned")
+                }
+                match self.physics_3d.write() {
+                    Ok(mut x) => x.step(timestep.step()),
+                    _ => error!("Physics RWLock is Poisoned")
+                }
+                for item in self.layer_stack.iter_mut() {
+                    item.on_fixed_update(timestep.step());
+                }
             }
+            forward_to_layers(&world_events, &mut self.layer_stack);
             if self.window.on_update() {
                 break 'main;
             }
         }
+        Ok(())
     }
 }
 
@@ -274,6 +497,257 @@ impl<T: graphics::context::ContextLimiter> MagnusApplication<T> {
         &mut self.layer_stack
     }
 
+    /**
+     * Worker threads shared by the engine, animation and particles are updated on it and
+     * games can spawn their own jobs and task graphs
+     **/
+    pub fn jobs(&self) -> Arc<JobSystem> {
+        Arc::clone(&self.jobs)
+    }
+
+    /**
+     * Starts the audio thread on `output` with the volumes from the application's settings
+     * The engine has no sound card output of its own, so nothing plays until the game calls this,
+     * a NullOutput keeps the mixer running on machines without one
+     * Starting again replaces it, the old thread stops once the last handle to it is dropped
+     **/
+    pub fn start_audio(&mut self, output: Box<dyn AudioOutput>) -> Arc<Audio> {
+        let audio = Arc::new(Audio::new(self.settings.audio(), output));
+        self.audio = Some(Arc::clone(&audio));
+        audio
+    }
+
+    /**
+     * The audio system start_audio created, None before that
+     **/
+    pub fn audio(&self) -> Option<Arc<Audio>> {
+        self.audio.clone()
+    }
+
+    /**
+     * The 2D physics world, stepped on the update thread at the fixed tick rate
+     **/
+    pub fn physics_2d(&self) -> Arc<RwLock<PhysicsWorld2D>> {
+        Arc::clone(&self.physics_2d)
+    }
+
+    /**
+     * The 3D physics world, stepped right after the 2D one on the same tick
+     **/
+    pub fn physics_3d(&self) -> Arc<RwLock<PhysicsWorld3D>> {
+        Arc::clone(&self.physics_3d)
+    }
+
+    /**
+     * Sprite and skeletal animators, updated on the update thread with the frame delta
+     **/
+    pub fn animation(&self) -> Arc<RwLock<AnimationWorld>> {
+        Arc::clone(&self.animation)
+    }
+
+    /**
+     * Running tweens, stepped right after the animators
+     * Tweens owned by an object or layer are cancelled once it leaves the layer stack
+     **/
+    pub fn tweens(&self) -> Arc<Mutex<TweenManager>> {
+        Arc::clone(&self.tweens)
+    }
+
+    /**
+     * Particle emitters, simulated on the update thread and drawn before the UI
+     **/
+    pub fn particles(&self) -> Arc<RwLock<ParticleWorld>> {
+        Arc::clone(&self.particles)
+    }
+
+    /**
+     * Camera the world is drawn with, copied into every frame packet
+     **/
+    pub fn camera(&self) -> Arc<RwLock<Camera>> {
+        Arc::clone(&self.camera)
+    }
+
+    /**
+     * Adds a tilemap to animate on the update thread and draw before particles, in the order
+     * they were pushed. Push them before calling run
+     * The renderer draws a copy taken when the map's revision changes, so edit tiles with set_tile
+     **/
+    pub fn push_tilemap(&mut self, map: Tilemap) -> Arc<RwLock<Tilemap>> {
+        let map = Arc::new(RwLock::new(map));
+        self.tilemaps.push(Arc::clone(&map));
+        map
+    }
+
+    /**
+     * Pushes the debug UI overlay, see DebugUi
+     **/
+    pub fn push_debug_ui(&mut self) {
+        self.layer_stack.push_overlay(DebugUi::new(Arc::clone(&self.debug_ui)).into_layer());
+    }
+
+    /**
+     * Pushes a game UI as an overlay, it's measured with the application's fonts and drawn
+     * over the frame by the OpenGL backend
+     **/
+    pub fn push_ui(&mut self, ui: GameUi) {
+        let tree = ui.tree();
+        if let Ok(mut x) = tree.lock() {
+            let fonts = Arc::clone(&self.fonts);
+            x.set_text_measure(move |text, size| fonts.measure(text, size));
+        }
+        self.uis.push(tree);
+        self.layer_stack.push_overlay(ui.into_layer());
+    }
+
+    /**
+     * Fonts game UIs are measured and drawn with, egui's built in fonts by default
+     **/
+    pub fn fonts(&self) -> Arc<FontSet> {
+        Arc::clone(&self.fonts)
+    }
+
+    /**
+     * Replaces the fonts, UIs already pushed are measured with the new ones too
+     **/
+    pub fn set_fonts(&mut self, fonts: FontSet) {
+        self.fonts = Arc::new(fonts);
+        for ui in &self.uis {
+            if let Ok(mut x) = ui.lock() {
+                let fonts = Arc::clone(&self.fonts);
+                x.set_text_measure(move |text, size| fonts.measure(text, size));
+            }
+        }
+    }
+
+    /**
+     * State the debug UI shares with the render loop, for showing or hiding it from code
+     **/
+    pub fn debug_ui(&self) -> Arc<DebugUiState> {
+        Arc::clone(&self.debug_ui)
+    }
+
+    /**
+     * The developer console, register commands and cvars on it before run
+     **/
+    pub fn console(&self) -> Arc<Console> {
+        self.debug_ui.console()
+    }
+
+    /**
+     * Keyboard and mouse state, fed by the window once the application runs
+     **/
+    pub fn input(&self) -> Arc<RwLock<InputState>> {
+        Arc::clone(&self.input)
+    }
+
+    /**
+     * Starts the update thread, it owns the layer stack from here on
+     * Each tick it handles the events and surfaces the window sent, steps the game, publishes
+     * a FramePacket to `writer` and records the frames the renderer measured
+     **/
+    fn spawn_update(&mut self, running: &Arc<std::sync::atomic::AtomicBool>, events: std::sync::mpsc::Receiver<EventData>,
+        surfaces: std::sync::mpsc::Receiver<Surface>, samples: std::sync::mpsc::Receiver<RenderSample>,
+        mut writer: FrameWriter<FramePacket>) -> Result<std::thread::JoinHandle<()>, MagnusError> {
+        use std::sync::atomic::Ordering;
+
+        let running = Arc::clone(running);
+        let mut stack = std::mem::replace(&mut self.layer_stack, LayerStack::new(None, None));
+        let mut builder = FrameBuilder::new(Arc::clone(&self.camera), Arc::clone(&self.particles), self.tilemaps.clone(),
+            std::mem::take(&mut self.uis));
+        let event_handler = Arc::clone(&self.event_handler);
+        let input = Arc::clone(&self.input);
+        let debug_ui = Arc::clone(&self.debug_ui);
+        let physics = Arc::clone(&self.physics_2d);
+        let physics_3d = Arc::clone(&self.physics_3d);
+        let animation = Arc::clone(&self.animation);
+        let tweens = Arc::clone(&self.tweens);
+        let particles = Arc::clone(&self.particles);
+        let tilemaps = self.tilemaps.clone();
+        let jobs = Arc::clone(&self.jobs);
+        Ok(std::thread::Builder::new().name("magnus-update".to_string()).spawn(move || {
+            let mut last_update = std::time::Instant::now();
+            let mut timestep = FixedTimestep::default();
+            let mut surface = Surface::default();
+            while running.load(Ordering::SeqCst) {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+                profile_scope!("update tick");
+                let tick_start = std::time::Instant::now();
+                if let Some(x) = surfaces.try_iter().last() {
+                    surface = x;
+                }
+                for event in events.try_iter() {
+                    dispatch_event(&event, &*event_handler, &input, &mut stack);
+                }
+                let close = event_handler.read().unwrap_or_else(PoisonError::into_inner)
+                    .as_any().downcast_ref::<EventHandler>().is_some_and(|x| x.should_close());
+                if close {
+                    break;
+                }
+
+                debug_ui.sync_layers(&mut stack);
+                update_layers(&mut stack);
+                cancel_orphaned_tweens(&tweens, &stack);
+                let dt = update_frame(&jobs, &animation, &tweens, &particles, &tilemaps, &mut last_update);
+
+                fixed_update(&mut timestep, &physics, &physics_3d, &mut stack);
+
+                builder.fill(writer.packet(), dt, &stack, surface);
+                writer.publish();
+
+                let emitters = particles.read().unwrap_or_else(PoisonError::into_inner).len();
+                let mut stats = FrameStats::global().lock().unwrap_or_else(PoisonError::into_inner);
+                for sample in samples.try_iter() {
+                    stats.record_render(&sample);
+                }
+                stats.record_update(tick_start.elapsed().as_secs_f32());
+                stats.set_asset_count("particle emitters", emitters);
+                stats.set_asset_count("tilemaps", tilemaps.len());
+            }
+            warn!("Update thread shutting down");
+        })?)
+    }
+
+    /**
+     * Feeds the input state from the window and hands it to scripts
+     **/
+    #[cfg(windows)]
+    fn connect_input(&mut self) {
+        let slot: Arc<RwLock<dyn SyncSlot<EventData>>> = self.input.clone();
+        connect_input_events(&mut self.window, &slot);
+        SyncSignal::<WindowFocusEvent, EventData>::connect::<WindowFocusEvent>(&mut self.window, slot);
+        ScriptHost::global().set_input(Arc::clone(&self.input));
+    }
+
+    /**
+     * Keyframe events go to `slot`, queued for the layers like contacts
+     **/
+    fn connect_animation(&mut self, slot: &Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        match self.animation.write() {
+            Ok(mut x) => SyncSignal::<AnimationKeyframeEvent, EventData>::connect::<AnimationKeyframeEvent>(&mut *x, Arc::clone(slot)),
+            _ => error!("Animation RWLock is Poisoned, keyframe events won't be delivered")
+        }
+    }
+
+    /**
+     * Contact events go to `slot`, which has to queue them, the worlds step while the layer stack is busy
+     **/
+    fn connect_physics(&mut self, slot: &Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        match self.physics_2d.write() {
+            Ok(mut x) => {
+                SyncSignal::<ContactBeginEvent, EventData>::connect::<ContactBeginEvent>(&mut *x, Arc::clone(slot));
+                SyncSignal::<ContactEndEvent, EventData>::connect::<ContactEndEvent>(&mut *x, Arc::clone(slot));
+            },
+            _ => error!("Physics RWLock is Poisoned, contact events won't be delivered")
+        }
+        match self.physics_3d.write() {
+            Ok(mut x) => {
+                SyncSignal::<ContactBeginEvent, EventData>::connect::<ContactBeginEvent>(&mut *x, Arc::clone(slot));
+                SyncSignal::<ContactEndEvent, EventData>::connect::<ContactEndEvent>(&mut *x, Arc::clone(slot));
+            },
+            _ => error!("Physics RWLock is Poisoned, contact events won't be delivered")
+        }
+    }
+
     #[inline(always)]
     pub fn get_running(&self) -> bool {
         self.running
@@ -314,6 +788,321 @@ impl<T: graphics::context::ContextLimiter> MagnusApplication<T> {
     //}
 }
 
+/**
+ * Connects the window's keyboard and mouse events to a slot
+ **/
+fn connect_input_events<T: graphics::context::ContextLimiter>(window: &mut Window<T>, slot: &Arc<RwLock<dyn SyncSlot<EventData>>>) {
+    SyncSignal::<KeyPressedEvent, EventData>::connect::<KeyPressedEvent>(window, Arc::clone(slot));
+    SyncSignal::<KeyReleasedEvent, EventData>::connect::<KeyReleasedEvent>(window, Arc::clone(slot));
+    SyncSignal::<MouseButtonPressedEvent, EventData>::connect::<MouseButtonPressedEvent>(window, Arc::clone(slot));
+    SyncSignal::<MouseButtonReleasedEvent, EventData>::connect::<MouseButtonReleasedEvent>(window, Arc::clone(slot));
+    SyncSignal::<MouseMovedEvent, EventData>::connect::<MouseMovedEvent>(window, Arc::clone(slot));
+    SyncSignal::<MouseScrolledEvent, EventData>::connect::<MouseScrolledEvent>(window, Arc::clone(slot));
+    SyncSignal::<TextInputEvent, EventData>::connect::<TextInputEvent>(window, Arc::clone(slot));
+}
+
+/**
+ * Connects the window's size and content scale events to a slot, game UIs lay out from them
+ **/
+fn connect_surface_events<T: graphics::context::ContextLimiter>(window: &mut Window<T>, slot: &Arc<RwLock<dyn SyncSlot<EventData>>>) {
+    SyncSignal::<WindowResizeEvent, EventData>::connect::<WindowResizeEvent>(window, Arc::clone(slot));
+    SyncSignal::<RenderFramebufferResizeEvent, EventData>::connect::<RenderFramebufferResizeEvent>(window, Arc::clone(slot));
+    SyncSignal::<RenderContentScaleResizeEvent, EventData>::connect::<RenderContentScaleResizeEvent>(window, Arc::clone(slot));
+}
+
+/**
+ * Takes settings edited in the debug UI or console, resizing the window and switching vsync and logging if they changed
+ **/
+fn apply_settings(window: &mut Window<OpenGLContext>, current: &mut Settings, settings: Settings) {
+    let (width, height) = settings.graphics().size();
+    if settings.graphics().size() != current.graphics().size() {
+        window.set_width(width);
+        window.set_height(height);
+    }
+    if settings.graphics().vsync() != current.graphics().vsync() {
+        window.set_vsync(settings.graphics().vsync());
+    }
+    if settings.logging() != current.logging() {
+        apply_logging(&settings);
+    }
+    crash::set_settings(&settings);
+    *current = settings;
+}
+
+/**
+ * Runs the console's startup script when there is one
+ **/
+fn run_startup_script(debug_ui: &DebugUiState) {
+    if !std::path::Path::new(STARTUP_SCRIPT).exists() {
+        return;
+    }
+    match debug_ui.run_script(STARTUP_SCRIPT) {
+        Ok(()) => info!("Ran {}", STARTUP_SCRIPT),
+        Err(e) => error!("Couldn't run {}: {}", STARTUP_SCRIPT, e)
+    }
+}
+
+/**
+ * Gives crash reports the application, its settings and the backend it's about to create
+ **/
+fn record_crash_context(name: &str, settings: &Settings) {
+    crash::set_application(name);
+    crash::set_settings(settings);
+    crash::clear_device_info();
+    crash::set_device_info("backend", settings.graphics().backend().to_string());
+}
+
+/**
+ * Registers the scripting and tilemap components with the registry scripts load prefabs through
+ * Only the first application registers them, later ones share the same script host
+ **/
+fn register_components() {
+    static REGISTERED: Once = Once::new();
+    REGISTERED.call_once(|| {
+        let mut registry = ScriptHost::global().registry().write().unwrap_or_else(PoisonError::into_inner);
+        scripting::register_components(&mut registry);
+        tilemap::register_components(&mut registry);
+    });
+}
+
+/**
+ * Points the logger at the logging section of `settings`, keeping whatever parts of it work
+ **/
+fn apply_logging(settings: &Settings) {
+    if let Err(e) = logging::configure(settings.logging()) {
+        error!("Logging settings only partly applied: {}", e);
+    }
+}
+
+/**
+ * Steps animation, tweens, particles and animated tiles by the real time since the last update
+ * Returns the time stepped
+ **/
+fn update_frame(jobs: &JobSystem, animation: &RwLock<AnimationWorld>, tweens: &Mutex<TweenManager>, particles: &RwLock<ParticleWorld>,
+    tilemaps: &[Arc<RwLock<Tilemap>>], last: &mut std::time::Instant) -> f32 {
+    profile_scope!("update frame");
+    let now = std::time::Instant::now();
+    let dt = now.duration_since(*last).as_secs_f32();
+    *last = now;
+    match animation.write() {
+        Ok(mut x) => {
+            profile_scope!("animation");
+            x.update_parallel(dt, jobs)
+        },
+        _ => error!("Animation RWLock is Poisoned (Update Thread)")
+    }
+    match tweens.lock() {
+        Ok(mut x) => x.update(dt),
+        _ => error!("Tween Mutex is Poisoned (Update Thread)")
+    }
+    match particles.write() {
+        Ok(mut x) => {
+            profile_scope!("particles");
+            x.update_parallel(dt, jobs)
+        },
+        _ => error!("Particle RWLock is Poisoned (Update Thread)")
+    }
+    for map in tilemaps {
+        match map.write() {
+            Ok(mut x) => x.update(dt),
+            _ => error!("Tilemap RWLock is Poisoned (Update Thread)")
+        }
+    }
+    dt
+}
+
+/**
+ * Updates every layer, then adds the entities their scripts spawned to the layer that asked
+ * Spawns from outside a layer update, like the console's, go to the first layer
+ **/
+fn update_layers(stack: &mut LayerStack) {
+    let host = ScriptHost::global();
+    for (i, item) in stack.iter_mut().enumerate() {
+        profile_scope!("layer update", item.debug_name());
+        host.set_layer(Some(i));
+        item.on_update();
+    }
+    host.set_layer(None);
+    for spawn in host.take_spawns() {
+        let entity = match spawn.instantiate() {
+            Ok(x) => x,
+            Err(e) => {
+                error!("Failed to spawn {} from a script: {}", spawn.name, e);
+                continue;
+            }
+        };
+        match stack.iter_mut().nth(spawn.layer.unwrap_or(0)) {
+            Some(layer) => layer.push_object(Box::new(entity)),
+            None => warn!("No layer left to spawn {} into", spawn.name)
+        }
+    }
+}
+
+fn cancel_orphaned_tweens(tweens: &Mutex<TweenManager>, stack: &LayerStack) {
+    match tweens.lock() {
+        Ok(mut x) => {
+            let cancelled = x.cancel_orphans(stack);
+            if cancelled > 0 {
+                debug!("Cancelled {} tweens whose owner was removed", cancelled);
+            }
+        },
+        _ => error!("Tween Mutex is Poisoned (Update Thread)")
+    }
+}
+
+/**
+ * Runs the physics steps and on_fixed_update calls that are due, on the update thread
+ **/
+fn fixed_update(timestep: &mut FixedTimestep, physics: &RwLock<PhysicsWorld2D>, physics_3d: &RwLock<PhysicsWorld3D>, stack: &mut LayerStack) {
+    for _ in 0..timestep.advance() {
+        profile_scope!("fixed step");
+        match physics.write() {
+            Ok(mut x) => {
+                profile_scope!("physics 2d");
+                x.step(timestep.step())
+            },
+            _ => error!("Physics RWLock is Poisoned (Update Thread)")
+        }
+        match physics_3d.write() {
+            Ok(mut x) => {
+                profile_scope!("physics 3d");
+                x.step(timestep.step())
+            },
+            _ => error!("Physics RWLock is Poisoned (Update Thread)")
+        }
+        for item in stack.iter_mut() {
+            item.on_fixed_update(timestep.step());
+        }
+    }
+}
+
+/**
+ * What the window's drawable area is right now
+ **/
+fn window_surface(window: &mut Window<OpenGLContext>) -> Surface {
+    let glfw_window = window.get_context().api_context().get_window();
+    Surface {
+        size: glfw_window.get_size(),
+        framebuffer: glfw_window.get_framebuffer_size(),
+        content_scale: glfw_window.get_content_scale().0,
+    }
+}
+
+/**
+ * What the Vulkan window's drawable area is right now
+ **/
+fn vulkan_surface(window: &mut Window<VulkanContext>) -> Surface {
+    let surface = window.get_context().api_context().get_surface();
+    let glfw_window = surface.window();
+    Surface {
+        size: glfw_window.get_size(),
+        framebuffer: glfw_window.get_framebuffer_size(),
+        content_scale: glfw_window.get_content_scale().0,
+    }
+}
+
+/**
+ * Hands a window event to everything on the update thread that listens for it
+ **/
+fn dispatch_event(data: &EventData, handler: &RwLock<dyn SyncSlot<EventData>>, input: &RwLock<InputState>, stack: &mut LayerStack) {
+    profile_scope!("event dispatch", data.event_type());
+    FrameStats::global().lock().unwrap_or_else(PoisonError::into_inner).count_event(*data.event_type());
+    let event = SyncData::Sig(data);
+    //a panic while one of these was held leaves nothing half written that matters here
+    handler.write().unwrap_or_else(PoisonError::into_inner).consume(&event);
+    input.write().unwrap_or_else(PoisonError::into_inner).consume(&event);
+    //layers get input, surface, contact and keyframe events, closing and focus are the engine's
+    if !matches!(data.event_type(), EventType::WindowClose | EventType::WindowFocus) {
+        stack.on_event(&mut ForwardedEvent::new(data.clone()));
+    }
+}
+
+/**
+ * Hands the events queued since the last call to the layers, for the DirectX loop which
+ * doesn't pass everything through dispatch_event
+ **/
+#[cfg(windows)]
+fn forward_to_layers(events: &std::sync::mpsc::Receiver<EventData>, stack: &mut LayerStack) {
+    for data in events.try_iter() {
+        stack.on_event(&mut ForwardedEvent::new(data));
+    }
+}
+
+/**
+ * Sends every event the window, physics or animation emit on to the update thread
+ **/
+struct EventForwarder {
+    sender: std::sync::mpsc::Sender<EventData>,
+}
+
+impl SyncSlot<EventData> for EventForwarder {
+    fn consume(&mut self, event: &SyncData<&EventData>) -> bool {
+        //fails only once the update thread is gone, and then nobody is left to tell
+        let _ = self.sender.send((*event.sig()).clone());
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/**
+ * Copies what the renderers need out of the game state into a FramePacket, on the update thread
+ **/
+struct FrameBuilder {
+    frame: u64,
+    camera: Arc<RwLock<Camera>>,
+    particles: Arc<RwLock<ParticleWorld>>,
+    tilemaps: Vec<Arc<RwLock<Tilemap>>>,
+    snapshots: Vec<TilemapFrame>,
+    uis: Vec<Arc<Mutex<UiTree>>>,
+}
+
+impl FrameBuilder {
+    fn new(camera: Arc<RwLock<Camera>>, particles: Arc<RwLock<ParticleWorld>>, tilemaps: Vec<Arc<RwLock<Tilemap>>>,
+        uis: Vec<Arc<Mutex<UiTree>>>) -> FrameBuilder {
+        let snapshots = tilemaps.iter().map(|x| {
+            let map = x.read().unwrap_or_else(PoisonError::into_inner);
+            TilemapFrame { map: Arc::new(map.clone()), time: map.time() }
+        }).collect();
+        FrameBuilder { frame: 0, camera, particles, tilemaps, snapshots, uis }
+    }
+
+    fn fill(&mut self, packet: &mut FramePacket, dt: f32, stack: &LayerStack, surface: Surface) {
+        profile_scope!("frame packet");
+        self.frame += 1;
+        packet.frame = self.frame;
+        packet.dt = dt;
+        packet.camera = *self.camera.read().unwrap_or_else(PoisonError::into_inner);
+
+        packet.transforms.clear();
+        for layer in stack.iter().filter(|x| x.enabled()) {
+            packet.transforms.extend(layer.objects().filter_map(|x| x.transform().map(|t| (x.id(), t.to_matrix()))));
+        }
+        self.particles.read().unwrap_or_else(PoisonError::into_inner).batches(&mut packet.particles);
+
+        //maps are only copied again when they changed, the packet shares the copy otherwise
+        for (map, snapshot) in self.tilemaps.iter().zip(self.snapshots.iter_mut()) {
+            let map = map.read().unwrap_or_else(PoisonError::into_inner);
+            if map.revision() != snapshot.map.revision() {
+                snapshot.map = Arc::new(map.clone());
+            }
+            snapshot.time = map.time();
+        }
+        packet.tilemaps.clone_from(&self.snapshots);
+
+        packet.ui.resize_with(self.uis.len(), Vec::new);
+        let size = Vec2::new(surface.size.0 as f32, surface.size.1 as f32);
+        let framebuffer = Vec2::new(surface.framebuffer.0 as f32, surface.framebuffer.1 as f32);
+        for (ui, commands) in self.uis.iter().zip(packet.ui.iter_mut()) {
+            let mut x = ui.lock().unwrap_or_else(PoisonError::into_inner);
+            x.set_window(size, framebuffer, surface.content_scale);
+            *commands = x.draw();
+        }
+    }
+}
+
 struct EventHandler {
     slots: Vec<SyncSlotPair>,
     should_close: bool
@@ -442,6 +1231,22 @@ impl SyncSlot<EventData> for EventHandler {
                     panic!("This will never happen");
                 }
             },
+            //layers get contacts and keyframes as well, these are only logged here
+            EventType::ContactBegin => {
+                if let EventData::U64p(a, b, _) = data {
+                    debug!("Contact began between colliders {} and {}", a, b);
+                }
+            },
+            EventType::ContactEnd => {
+                if let EventData::U64p(a, b, _) = data {
+                    debug!("Contact ended between colliders {} and {}", a, b);
+                }
+            },
+            EventType::AnimationKeyframe => {
+                if let EventData::StringD(name, _) = data {
+                    debug!("Animation keyframe event {}", name);
+                }
+            },
         }
         false
     }
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::core::application::MagnusApplication;
+use crate::core::crash;
+use crate::core::error::MagnusError;
+use crate::core::graphics::opengl::OpenGLContext;
+use crate::core::graphics::vulkan::VulkanContext;
+#[cfg(windows)]
+use crate::core::graphics::directx::DirectXContext;
+use crate::core::settings::{ Backend, Settings };
+
+static REPORT: OnceLock<BackendReport> = OnceLock::new();
+
+/**
+ * A backend that didn't start and why
+ **/
+#[derive(Debug)]
+pub struct BackendFailure {
+    pub backend: Backend,
+    pub error: MagnusError,
+}
+
+impl fmt::Display for BackendFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.backend, self.error)
+    }
+}
+
+/**
+ * How startup went, the backend that started and every one tried before it
+ **/
+#[derive(Debug)]
+pub struct BackendReport {
+    pub backend: Backend,
+    pub failures: Vec<BackendFailure>,
+}
+
+//an application created on one of the backends, ready to run
+enum Started {
+    OpenGL(MagnusApplication<OpenGLContext>),
+    Vulkan(MagnusApplication<VulkanContext>),
+    #[cfg(windows)]
+    DirectX(MagnusApplication<DirectXContext>),
+}
+
+impl Started {
+    fn run(self) -> Result<(), MagnusError> {
+        match self {
+            Started::OpenGL(x) => x.run(),
+            Started::Vulkan(x) => x.run(),
+            #[cfg(windows)]
+            Started::DirectX(x) => x.run(),
+        }
+    }
+}
+
+/**
+ * The configured backend first, then the rest of the preference list without repeats
+ **/
+pub fn order(settings: &Settings) -> Vec<Backend> {
+    let mut order = vec![settings.graphics().backend()];
+    for x in settings.backends() {
+        if !order.contains(x) {
+            order.push(*x);
+        }
+    }
+    order
+}
+
+fn start(backend: Backend, name: &str, settings: Settings) -> Result<Started, MagnusError> {
+    match backend {
+        Backend::OpenGL { .. } => MagnusApplication::<OpenGLContext>::new(name.to_string(), settings).map(Started::OpenGL),
+        Backend::Vulkan => MagnusApplication::<VulkanContext>::new(name.to_string(), settings).map(Started::Vulkan),
+        #[cfg(windows)]
+        Backend::DirectX => {
+            let (width, height) = settings.graphics().size();
+            MagnusApplication::<DirectXContext>::new(name.to_string(), width as i32, height as i32, settings).map(Started::DirectX)
+        },
+        #[cfg(not(windows))]
+        Backend::DirectX => Err(MagnusError::BackendUnavailable(backend))
+    }
+}
+
+/**
+ * Creates the application on the first backend in `order` that starts and runs it
+ * A backend other than the configured one is written back to {name}.json so the next launch starts with it
+ **/
+pub fn launch(name: String, settings: Settings) -> Result<(), MagnusError> {
+    let configured = settings.graphics().backend();
+    let mut failures = Vec::new();
+    for backend in order(&settings) {
+        info!("Starting {} backend", backend);
+        let mut attempt = settings.clone();
+        attempt.set_backend(backend);
+        match start(backend, &name, attempt.clone()) {
+            Ok(app) => {
+                if backend != configured {
+                    match attempt.write(&name) {
+                        Ok(()) => info!("Saved {} as the graphics backend", backend),
+                        Err(e) => warn!("Couldn't save {} as the graphics backend: {}", backend, e)
+                    }
+                }
+                record(backend, failures);
+                return app.run();
+            },
+            Err(e) => {
+                warn!("{} backend failed to start: {}", backend, e);
+                failures.push(BackendFailure { backend, error: e });
+            }
+        }
+    }
+    Err(MagnusError::NoBackend(failures))
+}
+
+fn record(backend: Backend, failures: Vec<BackendFailure>) {
+    info!("Started on {}", backend);
+    if !failures.is_empty() {
+        let failed = failures.iter().map(|x| x.to_string()).collect::<Vec<String>>().join("; ");
+        crash::set_device_info("failed backends", failed);
+    }
+    if REPORT.set(BackendReport { backend, failures }).is_err() {
+        warn!("Backend already chosen once, keeping the first report");
+    }
+}
+
+/**
+ * How the running application's backend was chosen, None before launch picked one
+ **/
+pub fn report() -> Option<&'static BackendReport> {
+    REPORT.get()
+}
@@ -0,0 +1,59 @@
+use crate::math::{ Mat4, Vec2, Vec3, Vec4 };
+
+/**
+ * View and projection the world is drawn with
+ * The game sets it from the update thread, each frame packet carries a copy to the renderers
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub view: Mat4,
+    pub projection: Mat4,
+}
+
+impl Camera {
+    pub fn new(view: Mat4, projection: Mat4) -> Camera {
+        Camera { view, projection }
+    }
+
+    /**
+     * Looks down -z at `center`, showing `half_height` world units above and below it
+     **/
+    pub fn orthographic_2d(center: Vec2, half_height: f32, aspect: f32) -> Camera {
+        let half_width = half_height * aspect;
+        Camera {
+            view: Mat4::from_translation(Vec3::new(-center.x, -center.y, 0.0)),
+            projection: Mat4::orthographic_rh_gl(-half_width, half_width, -half_height, half_height, -1000.0, 1000.0),
+        }
+    }
+
+    pub fn view_projection(&self) -> Mat4 {
+        self.projection * self.view
+    }
+
+    /**
+     * World space bounds of what's on screen at z = 0, exact for orthographic cameras
+     * None when the matrices can't be inverted
+     **/
+    pub fn visible_rect(&self) -> Option<(Vec2, Vec2)> {
+        let inverse = self.view_projection().inverse()?;
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            let near = inverse * Vec4::new(x, y, -1.0, 1.0);
+            let far = inverse * Vec4::new(x, y, 1.0, 1.0);
+            let (near, far) = (near.truncate() / near.w, far.truncate() / far.w);
+            //where the corner's ray crosses z = 0, clamped to the part between the clip planes
+            let t = if (far.z - near.z).abs() > f32::EPSILON { (-near.z / (far.z - near.z)).clamp(0.0, 1.0) } else { 0.0 };
+            let point = near.lerp(far, t).truncate();
+            min = min.min(point);
+            max = max.max(point);
+        }
+        if min.x.is_finite() && max.x.is_finite() { Some((min, max)) } else { None }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera { view: Mat4::IDENTITY, projection: Mat4::IDENTITY }
+    }
+}
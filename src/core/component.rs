@@ -0,0 +1,175 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::core::scene::{ AssetRef, ComponentData, SceneError };
+use crate::core::transform::{ self, Transform };
+
+pub use serde_json::{ Value, Error as ValueError, to_value };
+
+/**
+ * A piece of data attached to an Entity
+ * Components are saved to scenes by their kind and layout version, so changing a
+ * component's layout means bumping its version and registering a migration
+ **/
+pub trait Component: Send + Sync {
+    fn kind(&self) -> &'static str;
+
+    fn version(&self) -> u32;
+
+    fn save(&self) -> Result<Value, ValueError>;
+
+    fn assets(&self) -> Vec<AssetRef> {
+        Vec::new()
+    }
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/**
+ * The kind and layout version of a Component type, read by ComponentRegistry::register
+ * Component::kind and Component::version should return these
+ **/
+pub trait ComponentKind: Component {
+    const KIND: &'static str;
+
+    const VERSION: u32;
+}
+
+/**
+ * Implements Component and ComponentKind for a type that is Serialize + Deserialize
+ * usage: impl_component!(MyComponent, "my_component", 1);
+ **/
+#[macro_export]
+macro_rules! impl_component {
+    ($t:ty, $kind:expr, $version:expr) => {
+        impl $crate::core::component::ComponentKind for $t {
+            const KIND: &'static str = $kind;
+
+            const VERSION: u32 = $version;
+        }
+
+        impl $crate::core::component::Component for $t {
+            fn kind(&self) -> &'static str {
+                <$t as $crate::core::component::ComponentKind>::KIND
+            }
+
+            fn version(&self) -> u32 {
+                <$t as $crate::core::component::ComponentKind>::VERSION
+            }
+
+            fn save(&self) -> Result<$crate::core::component::Value, $crate::core::component::ValueError> {
+                $crate::core::component::to_value(self)
+            }
+
+            fn as_any(&self) -> &dyn std::any::Any {
+                self
+            }
+
+            fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+                self
+            }
+        }
+    }
+}
+
+pub type ComponentLoader = fn(Value) -> Result<Box<dyn Component>, ValueError>;
+pub type ComponentMigration = fn(Value) -> Result<Value, SceneError>;
+
+struct ComponentEntry {
+    version: u32,
+    loader: ComponentLoader,
+}
+
+/**
+ * Maps component kinds to the functions used to load them back out of a scene
+ * and to the migrations that move old component layouts forward
+ **/
+pub struct ComponentRegistry {
+    entries: HashMap<String, ComponentEntry>,
+    migrations: HashMap<(String, u32), ComponentMigration>,
+}
+
+impl ComponentRegistry {
+    /**
+     * Creates a registry with Transform already registered
+     * Modules with their own components register them through their register_components
+     **/
+    pub fn new() -> ComponentRegistry {
+        let mut registry = ComponentRegistry { entries: HashMap::new(), migrations: HashMap::new() };
+        registry.register::<Transform>();
+        registry.register_migration(Transform::KIND, 1, transform::migrate_v1);
+        registry
+    }
+
+    /**
+     * Registers `T` under its ComponentKind::KIND at ComponentKind::VERSION
+     **/
+    pub fn register<T: ComponentKind + Serialize + DeserializeOwned + 'static>(&mut self) {
+        fn load<T: Component + DeserializeOwned + 'static>(value: Value)
+            -> Result<Box<dyn Component>, ValueError> {
+            let component: T = serde_json::from_value(value)?;
+            Ok(Box::new(component))
+        }
+
+        if self.entries.insert(T::KIND.to_string(), ComponentEntry { version: T::VERSION, loader: load::<T> }).is_some() {
+            warn!("Component kind {} registered more than once", T::KIND);
+        }
+    }
+
+    /**
+     * Registers a migration that takes data for `kind` at `from_version` to `from_version + 1`
+     **/
+    pub fn register_migration(&mut self, kind: &str, from_version: u32, migration: ComponentMigration) {
+        self.migrations.insert((kind.to_string(), from_version), migration);
+    }
+
+    pub fn is_registered(&self, kind: &str) -> bool {
+        self.entries.contains_key(kind)
+    }
+
+    pub fn current_version(&self, kind: &str) -> Option<u32> {
+        self.entries.get(kind).map(|x| x.version)
+    }
+
+    pub fn migrate(&self, data: ComponentData) -> Result<ComponentData, SceneError> {
+        let current = match self.entries.get(&data.kind) {
+            Some(x) => x.version,
+            None => return Err(SceneError::UnknownComponent(data.kind))
+        };
+        if data.version > current {
+            return Err(SceneError::UnsupportedComponentVersion(data.kind, data.version));
+        }
+
+        let ComponentData { kind, mut version, data: mut value } = data;
+        while version < current {
+            match self.migrations.get(&(kind.clone(), version)) {
+                Some(migration) => {
+                    debug!("Migrating component {} from version {} to {}", kind, version, version + 1);
+                    value = migration(value)?;
+                    version += 1;
+                },
+                None => return Err(SceneError::MissingMigration(kind, version))
+            }
+        }
+        Ok(ComponentData { kind, version, data: value })
+    }
+
+    pub fn load(&self, data: ComponentData) -> Result<Box<dyn Component>, SceneError> {
+        let data = self.migrate(data)?;
+        match self.entries.get(&data.kind) {
+            Some(entry) => (entry.loader)(data.data).map_err(SceneError::Serde),
+            None => Err(SceneError::UnknownComponent(data.kind))
+        }
+    }
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> ComponentRegistry {
+        ComponentRegistry::new()
+    }
+}
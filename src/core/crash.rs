@@ -0,0 +1,336 @@
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{ self, PanicHookInfo, UnwindSafe };
+use std::path::{ Path, PathBuf };
+use std::sync::{ Mutex, OnceLock, PoisonError, TryLockError };
+use std::sync::atomic::{ AtomicBool, AtomicU32, Ordering };
+use std::thread;
+
+use crate::core::logging;
+use crate::core::settings::Settings;
+
+/**
+ * Where crash reports are written when install isn't given a directory
+ **/
+pub const CRASH_DIRECTORY: &str = "crash_reports";
+
+/**
+ * Log lines at the end of every crash report
+ **/
+pub const LOG_LINES: usize = logging::CRASH_LINES;
+
+static CONTEXT: OnceLock<Mutex<CrashContext>> = OnceLock::new();
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+//panics on several threads at once each get their own report
+static REPORTS: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+    //how many recover calls this thread is inside, panics under one are caught and aren't crashes
+    static RECOVERING: Cell<u32> = const { Cell::new(0) };
+}
+
+/**
+ * What the engine knows about the running game, kept up to date so the panic hook only has to read it
+ **/
+#[derive(Debug, Default)]
+struct CrashContext {
+    directory: PathBuf,
+    message_box: bool,
+    application: String,
+    settings: Option<Settings>,
+    //backend, renderer, driver, in the order they were recorded
+    device: Vec<(String, String)>,
+}
+
+fn context() -> &'static Mutex<CrashContext> {
+    CONTEXT.get_or_init(|| Mutex::new(CrashContext::default()))
+}
+
+/**
+ * The context without blocking, a panic while it was locked would otherwise hang the hook
+ **/
+fn try_context<R, F: FnOnce(&mut CrashContext) -> R>(f: F) -> Option<R> {
+    match context().try_lock() {
+        Ok(mut x) => Some(f(&mut x)),
+        Err(TryLockError::Poisoned(x)) => Some(f(&mut x.into_inner())),
+        Err(TryLockError::WouldBlock) => None
+    }
+}
+
+fn with_context<F: FnOnce(&mut CrashContext)>(f: F) {
+    f(&mut context().lock().unwrap_or_else(PoisonError::into_inner));
+}
+
+/**
+ * Installs the panic hook, every panic after this writes a crash report to `directory`
+ * (CRASH_DIRECTORY by default) before the previous hook runs, except those caught by recover
+ * `message_box` also tells the player where the report went with a native dialog
+ * Installing again only changes the directory and the dialog
+ **/
+pub fn install(directory: Option<PathBuf>, message_box: bool) {
+    with_context(|x| {
+        x.directory = directory.unwrap_or_else(|| PathBuf::from(CRASH_DIRECTORY));
+        x.message_box = message_box;
+    });
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if !recovering() {
+            crashed(&report(info));
+        }
+        previous(info);
+    }));
+}
+
+/**
+ * catch_unwind for panics the engine recovers from, like a failing script, job or task
+ * The panic hook doesn't write crash reports for them, whoever catches one reports it as an error
+ **/
+pub fn recover<R, F: FnOnce() -> R + UnwindSafe>(f: F) -> thread::Result<R> {
+    RECOVERING.with(|x| x.set(x.get() + 1));
+    let result = panic::catch_unwind(f);
+    RECOVERING.with(|x| x.set(x.get() - 1));
+    result
+}
+
+/**
+ * resume_unwind for a panic caught by recover on another thread and carried over to this one,
+ * writes the crash report the panic hook skipped unless this thread is recovering too
+ **/
+pub fn resume(payload: Box<dyn Any + Send>) -> ! {
+    if INSTALLED.load(Ordering::SeqCst) && !recovering() {
+        crashed(&build_report(&payload_message(&*payload), None));
+    }
+    panic::resume_unwind(payload)
+}
+
+fn recovering() -> bool {
+    RECOVERING.with(|x| x.get() > 0)
+}
+
+/**
+ * Writes `report` and tells the player where it went
+ **/
+fn crashed(report: &str) {
+    let (directory, message_box) = try_context(|x| (x.directory.clone(), x.message_box))
+        .unwrap_or_else(|| (PathBuf::from(CRASH_DIRECTORY), false));
+    //straight to stderr, the panic may have happened inside the logger
+    match write_report(&directory, report) {
+        Ok(path) => {
+            eprintln!("Crash report written to {}", path.display());
+            if message_box {
+                show_message_box(&format!("The game crashed.\n\nA crash report was written to:\n{}", path.display()));
+            }
+        },
+        Err(e) => eprintln!("Couldn't write crash report: {}", e)
+    }
+}
+
+fn payload_message(payload: &(dyn Any + Send)) -> String {
+    match payload.downcast_ref::<&str>() {
+        Some(x) => x.to_string(),
+        None => match payload.downcast_ref::<String>() {
+            Some(x) => x.clone(),
+            None => String::from("Box<dyn Any>")
+        }
+    }
+}
+
+pub fn set_application(name: &str) {
+    with_context(|x| x.application = name.to_string());
+}
+
+/**
+ * The Settings snapshot put in crash reports, applications call this again whenever settings change
+ **/
+pub fn set_settings(settings: &Settings) {
+    with_context(|x| x.settings = Some(settings.clone()));
+}
+
+/**
+ * Records a line of graphics backend or device info, replacing the value of an existing `key`
+ **/
+pub fn set_device_info(key: &str, value: String) {
+    with_context(|x| match x.device.iter_mut().find(|(k, _)| k == key) {
+        Some((_, v)) => *v = value,
+        None => x.device.push((key.to_string(), value))
+    });
+}
+
+/**
+ * Forgets the device info recorded so far, for starting over on another backend
+ **/
+pub fn clear_device_info() {
+    with_context(|x| x.device.clear());
+}
+
+/**
+ * Records vendor, renderer and version of the current OpenGL context
+ **/
+pub fn record_gl_device() {
+    let string = |name| unsafe {
+        let x = gl::GetString(name);
+        if x.is_null() {
+            return String::from("unknown");
+        }
+        std::ffi::CStr::from_ptr(x as *const std::os::raw::c_char).to_string_lossy().into_owned()
+    };
+    set_device_info("backend", String::from("OpenGL"));
+    set_device_info("vendor", string(gl::VENDOR));
+    set_device_info("renderer", string(gl::RENDERER));
+    set_device_info("version", string(gl::VERSION));
+}
+
+/**
+ * Logs an error the engine can't carry on from without panicking, like a backend that failed to start,
+ * and shows it in a dialog when install asked for one
+ **/
+pub fn report_error(error: &dyn std::error::Error) {
+    error!("{}", error);
+    if try_context(|x| x.message_box).unwrap_or(false) {
+        show_message_box(&format!("The game couldn't start.\n\n{}", error));
+    }
+}
+
+/**
+ * The full text of a crash report for `info`
+ **/
+pub fn report(info: &PanicHookInfo) -> String {
+    let location = info.location().map(|x| format!("{}:{}:{}", x.file(), x.line(), x.column()));
+    build_report(&payload_message(info.payload()), location.as_deref())
+}
+
+fn build_report(message: &str, location: Option<&str>) -> String {
+    let thread = thread::current();
+    let mut text = String::new();
+    //writing to a String never fails
+    let _ = writeln!(text, "Magnus crash report");
+    let _ = writeln!(text, "Time: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f %z"));
+    let _ = writeln!(text, "Engine version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(text, "OS: {} {}", std::env::consts::OS, std::env::consts::ARCH);
+    let _ = writeln!(text, "Thread: {} ({:?})", thread.name().unwrap_or("<unnamed>"), thread.id());
+    let _ = writeln!(text, "Panic: {}", message);
+    if let Some(x) = location {
+        let _ = writeln!(text, "Location: {}", x);
+    }
+
+    let written = try_context(|x| {
+        let _ = writeln!(text, "Application: {}", x.application);
+        let _ = writeln!(text, "\n[Graphics]");
+        if x.device.is_empty() {
+            let _ = writeln!(text, "no graphics context yet");
+        }
+        for (key, value) in &x.device {
+            let _ = writeln!(text, "{}: {}", key, value);
+        }
+        let _ = writeln!(text, "\n[Settings]");
+        match x.settings.as_ref().map(serde_json::to_string_pretty) {
+            Some(Ok(x)) => text.push_str(&x),
+            Some(Err(e)) => text.push_str(&format!("couldn't serialize settings: {}", e)),
+            None => text.push_str("no settings recorded")
+        }
+        text.push('\n');
+    });
+    if written.is_none() {
+        let _ = writeln!(text, "\n[Context]\nlocked by the crashing thread");
+    }
+
+    let _ = writeln!(text, "\n[Backtrace]\n{}", Backtrace::force_capture());
+
+    let _ = writeln!(text, "[Log]");
+    for x in logging::recent_lines(LOG_LINES) {
+        let _ = writeln!(text, "{} [{}][{}] {}", x.time, x.level, x.target, x.message);
+    }
+    text
+}
+
+/**
+ * Writes `report` to a new file in `directory`, returns its path
+ **/
+pub fn write_report(directory: &Path, report: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let count = REPORTS.fetch_add(1, Ordering::SeqCst);
+    let mut name = format!("crash-{}", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    if count > 0 {
+        name.push_str(&format!("-{}", count));
+    }
+    let path = directory.join(name + ".txt");
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(windows)]
+fn show_message_box(text: &str) {
+    use std::os::raw::c_void;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn MessageBoxW(hwnd: *mut c_void, text: *const u16, caption: *const u16, kind: u32) -> i32;
+    }
+    //MB_OK | MB_ICONERROR
+    const KIND: u32 = 0x10;
+    let wide = |x: &str| x.encode_utf16().chain(std::iter::once(0)).collect::<Vec<u16>>();
+    unsafe {
+        MessageBoxW(std::ptr::null_mut(), wide(text).as_ptr(), wide("Crash").as_ptr(), KIND);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn show_message_box(text: &str) {
+    let script = format!("display alert \"Crash\" message \"{}\" as critical", text.replace('\\', "\\\\").replace('"', "\\\""));
+    if let Err(e) = std::process::Command::new("osascript").args(["-e", script.as_str()]).status() {
+        eprintln!("Couldn't show crash dialog: {}", e);
+    }
+}
+
+/**
+ * Tries the dialog tools desktops usually ship with, in order
+ **/
+#[cfg(all(unix, not(target_os = "macos")))]
+fn show_message_box(text: &str) {
+    use std::process::Command;
+
+    let tools: [(&str, Vec<&str>); 3] = [
+        ("zenity", vec!["--error", "--title=Crash", "--no-wrap", "--text", text]),
+        ("kdialog", vec!["--title", "Crash", "--error", text]),
+        ("xmessage", vec!["-center", text]),
+    ];
+    for (tool, args) in tools.iter() {
+        if Command::new(tool).args(args).status().is_ok() {
+            return;
+        }
+    }
+    eprintln!("Couldn't show crash dialog, none of zenity, kdialog or xmessage are installed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recover_marks_the_thread_only_while_it_runs() {
+        assert!(!recovering());
+        let result = recover(|| {
+            assert!(recovering());
+            //nested recovers keep the outer one marked
+            let inner = recover(|| panic!("inner"));
+            assert!(inner.is_err());
+            assert!(recovering());
+            panic!("outer")
+        });
+        let payload = result.unwrap_err();
+        assert_eq!(payload_message(&*payload), "outer");
+        assert!(!recovering());
+    }
+
+    #[test]
+    fn recover_passes_values_through() {
+        assert_eq!(recover(|| 7).unwrap(), 7);
+        assert!(!recovering());
+    }
+}
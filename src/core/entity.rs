@@ -0,0 +1,181 @@
+use crate::core::component::{ Component, ComponentRegistry };
+use crate::core::object::Object;
+use crate::core::prefab::PrefabLink;
+use crate::core::scene::{ AssetRef, ComponentData, EntityData, SceneError };
+use crate::core::transform::Transform;
+use crate::events::event::Event;
+use crate::scripting::script::{ Script, ScriptTarget };
+use crate::scripting::ScriptComponent;
+
+/**
+ * Generic Object made up of Components, and optionally child Entities
+ * This is the Object type that scenes know how to save and load
+ **/
+pub struct Entity {
+    id: u32,
+    name: String,
+    components: Vec<Box<dyn Component>>,
+    children: Vec<Entity>,
+    prefab: Option<PrefabLink>,
+}
+
+impl Entity {
+    pub fn new(id: u32, name: String) -> Entity {
+        Entity { id, name, components: Vec::new(), children: Vec::new(), prefab: None }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    /**
+     * The prefab this entity was instantiated from, if any
+     **/
+    pub fn prefab(&self) -> Option<&PrefabLink> {
+        self.prefab.as_ref()
+    }
+
+    pub fn set_prefab(&mut self, prefab: Option<PrefabLink>) {
+        self.prefab = prefab;
+    }
+
+    pub fn add_component(&mut self, component: Box<dyn Component>) {
+        self.components.push(component);
+    }
+
+    pub fn remove_component<T: Component + 'static>(&mut self) -> Option<Box<dyn Component>> {
+        let index = self.components.iter().position(|x| x.as_any().is::<T>())?;
+        Some(self.components.remove(index))
+    }
+
+    pub fn component<T: Component + 'static>(&self) -> Option<&T> {
+        self.components.iter().find_map(|x| x.as_any().downcast_ref::<T>())
+    }
+
+    pub fn component_mut<T: Component + 'static>(&mut self) -> Option<&mut T> {
+        self.components.iter_mut().find_map(|x| x.as_any_mut().downcast_mut::<T>())
+    }
+
+    pub fn components(&self) -> &[Box<dyn Component>] {
+        &self.components
+    }
+
+    pub fn add_child(&mut self, child: Entity) {
+        self.children.push(child);
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> Entity {
+        self.children.remove(index)
+    }
+
+    pub fn children(&self) -> &[Entity] {
+        &self.children
+    }
+
+    pub fn children_mut(&mut self) -> &mut [Entity] {
+        &mut self.children
+    }
+
+    /**
+     * Asset references of this entity's components and all of its children
+     **/
+    pub fn assets(&self) -> Vec<AssetRef> {
+        let mut assets: Vec<AssetRef> = self.components.iter().flat_map(|x| x.assets()).collect();
+        for child in &self.children {
+            assets.extend(child.assets());
+        }
+        assets
+    }
+
+    /**
+     * Runs `run` for each script attached to this entity, then for its children's scripts
+     * Scripts see the entity's Transform, one is added if a script moves an entity without one
+     **/
+    fn run_scripts(&mut self, run: &mut dyn FnMut(&mut Script, &mut ScriptTarget)) {
+        for index in 0..self.components.len() {
+            if !self.components[index].as_any().is::<ScriptComponent>() {
+                continue;
+            }
+            let transform = self.component::<Transform>().copied();
+            let mut target = ScriptTarget::new(self.id, &self.name, transform.unwrap_or_default());
+            if let Some(x) = self.components[index].as_any_mut().downcast_mut::<ScriptComponent>() {
+                run(x.script_mut(), &mut target);
+            }
+            match self.component_mut::<Transform>() {
+                Some(x) => *x = target.transform,
+                None => if target.transform != Transform::identity() {
+                    self.add_component(Box::new(target.transform));
+                }
+            }
+        }
+        for child in self.children.iter_mut() {
+            child.run_scripts(run);
+        }
+    }
+
+    pub fn to_data(&self) -> Result<EntityData, SceneError> {
+        let mut components = Vec::with_capacity(self.components.len());
+        for component in &self.components {
+            components.push(ComponentData {
+                kind: component.kind().to_string(),
+                version: component.version(),
+                data: component.save()?
+            });
+        }
+
+        let mut children = Vec::with_capacity(self.children.len());
+        for child in &self.children {
+            children.push(child.to_data()?);
+        }
+
+        Ok(EntityData { id: self.id, name: self.name.clone(), components, children, prefab: self.prefab.clone() })
+    }
+
+    pub fn from_data(data: EntityData, registry: &ComponentRegistry) -> Result<Entity, SceneError> {
+        let mut entity = Entity::new(data.id, data.name);
+        entity.prefab = data.prefab;
+        for component in data.components {
+            entity.add_component(registry.load(component)?);
+        }
+        for child in data.children {
+            entity.add_child(Entity::from_data(child, registry)?);
+        }
+        Ok(entity)
+    }
+}
+
+impl Object for Entity {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn save(&self) -> Result<Option<EntityData>, SceneError> {
+        self.to_data().map(Some)
+    }
+
+    fn assets(&self) -> Vec<AssetRef> {
+        Entity::assets(self)
+    }
+
+    fn transform(&self) -> Option<Transform> {
+        self.component::<Transform>().copied()
+    }
+
+    fn on_update(&mut self) {
+        self.run_scripts(&mut |script, target| script.update(target));
+    }
+
+    fn on_fixed_update(&mut self, dt: f32) {
+        self.run_scripts(&mut |script, target| script.fixed_update(target, dt));
+    }
+
+    fn on_event(&mut self, e: &mut dyn Event) {
+        self.run_scripts(&mut |script, target| if !e.get_handled() {
+            script.event(target, e);
+        });
+    }
+}
@@ -6,53 +6,27 @@ extern "Rust" {
     pub fn prelude() -> (Settings, String);
 }
 
+/**
+ * Defines main, which installs the logger and crash handler, asks the game's prelude for its settings
+ * and launches on the first backend that starts, see core::backend::launch
+ **/
 #[macro_export]
-#[cfg(not(windows))]
 macro_rules! magnus_engine {
     () => {
         pub fn main() {
             use magnus::core::setup_logger;
-            use magnus::core::application::MagnusApplication;
-            use magnus::core::graphics::opengl::OpenGLContext;
-            use magnus::core::graphics::vulkan::VulkanContext;
-            use magnus::core::graphics::context::ContextLimiter;
 
             if setup_logger().is_err() {
                 panic!("Error, could not init loggers!");
             }
+            //release builds also tell the player where the crash report went
+            magnus::core::crash::install(None, cfg!(not(debug_assertions)));
 
             let (settings, app_name) = prelude();
-            match settings.graphics().mode() {
-                GraphicsMode::OpenGL => MagnusApplication::<OpenGLContext>::new(app_name, settings).run(),
-                GraphicsMode::Vulkan => MagnusApplication::<VulkanContext>::new(app_name, settings).run(),
-                _ => panic!("Not running on windows")
-            };
-        }
-    }
-}
-
-#[macro_export]
-#[cfg(windows)]
-macro_rules! magnus_engine {
-    () => {
-        pub fn main() {
-            use magnus::core::setup_logger;
-            use magnus::core::application::MagnusApplication;
-            use magnus::core::graphics::opengl::OpenGLContext;
-            use magnus::core::graphics::vulkan::VulkanContext;
-            use magnus::core::graphics::directx::DirectXContext;
-            use magnus::core::graphics::context::ContextLimiter;
-
-            if setup_logger().is_err() {
-                panic!("Error, could not init loggers!");
+            if let Err(e) = magnus::core::backend::launch(app_name, settings) {
+                magnus::core::crash::report_error(&e);
+                std::process::exit(1);
             }
-
-            let (settings, app_name) = prelude();
-            match settings.graphics().mode() {
-                GraphicsMode::OpenGL => MagnusApplication::<OpenGLContext>::new(app_name, settings).run(),
-                GraphicsMode::Vulkan => MagnusApplication::<VulkanContext>::new(app_name, settings).run(),
-                GraphicsMode::DirectX => MagnusApplication::<DirectXContext>::new(app_name, settings).run()
-            };
         }
     }
 }
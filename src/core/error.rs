@@ -0,0 +1,103 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::core::backend::BackendFailure;
+use crate::core::graphics::{ DeviceCreationError, SymbolLoadError };
+use crate::core::settings::Backend;
+
+/**
+ * Everything that can stop the engine from starting, returned by window, context and application creation
+ **/
+#[derive(Debug)]
+pub enum MagnusError {
+    GlfwInit(glfw::InitError),
+    /**
+     * Glfw couldn't open the window with this title, the reason is in the log
+     **/
+    WindowCreation(String),
+    SymbolLoad(SymbolLoadError),
+    DeviceCreation(DeviceCreationError),
+    Io(io::Error),
+    Serde(serde_json::Error),
+    /**
+     * The backend doesn't exist on this platform, like DirectX outside Windows
+     **/
+    BackendUnavailable(Backend),
+    /**
+     * Every backend launch tried failed
+     **/
+    NoBackend(Vec<BackendFailure>),
+    /**
+     * A thread the engine runs on panicked, its crash report has the details
+     **/
+    ThreadPanicked(&'static str),
+}
+
+impl fmt::Display for MagnusError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MagnusError::GlfwInit(e) => write!(f, "Failed to init GLFW: {}", e),
+            MagnusError::WindowCreation(title) => write!(f, "Failed to create window {}", title),
+            MagnusError::SymbolLoad(e) => write!(f, "{}", e),
+            MagnusError::DeviceCreation(e) => write!(f, "{}", e),
+            MagnusError::Io(e) => write!(f, "IO error: {}", e),
+            MagnusError::Serde(e) => write!(f, "Serialization error: {}", e),
+            MagnusError::BackendUnavailable(backend) => write!(f, "{} isn't available on this platform", backend),
+            MagnusError::NoBackend(failures) => {
+                write!(f, "No graphics backend could start")?;
+                for x in failures {
+                    write!(f, "\n{}", x)?;
+                }
+                Ok(())
+            },
+            MagnusError::ThreadPanicked(name) => write!(f, "The {} thread panicked", name),
+        }
+    }
+}
+
+impl Error for MagnusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MagnusError::GlfwInit(e) => Some(e),
+            MagnusError::WindowCreation(_) => None,
+            MagnusError::SymbolLoad(e) => Some(e),
+            MagnusError::DeviceCreation(e) => Some(e),
+            MagnusError::Io(e) => Some(e),
+            MagnusError::Serde(e) => Some(e),
+            MagnusError::BackendUnavailable(_) => None,
+            MagnusError::NoBackend(_) => None,
+            MagnusError::ThreadPanicked(_) => None,
+        }
+    }
+}
+
+impl From<glfw::InitError> for MagnusError {
+    fn from(e: glfw::InitError) -> MagnusError {
+        MagnusError::GlfwInit(e)
+    }
+}
+
+impl From<SymbolLoadError> for MagnusError {
+    fn from(e: SymbolLoadError) -> MagnusError {
+        MagnusError::SymbolLoad(e)
+    }
+}
+
+impl From<DeviceCreationError> for MagnusError {
+    fn from(e: DeviceCreationError) -> MagnusError {
+        MagnusError::DeviceCreation(e)
+    }
+}
+
+impl From<io::Error> for MagnusError {
+    fn from(e: io::Error) -> MagnusError {
+        MagnusError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for MagnusError {
+    fn from(e: serde_json::Error) -> MagnusError {
+        MagnusError::Serde(e)
+    }
+}
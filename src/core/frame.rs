@@ -0,0 +1,150 @@
+use std::cell::UnsafeCell;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU8, Ordering };
+
+use crate::core::camera::Camera;
+use crate::math::Mat4;
+use crate::particles::ParticleBatch;
+use crate::tilemap::Tilemap;
+use crate::ui::DrawCommand;
+
+//low bits of the shared index pick a slot, this bit is set while it holds an unread packet
+const SLOT: u8 = 0b011;
+const FRESH: u8 = 0b100;
+
+struct Slots<T> {
+    slots: [UnsafeCell<T>; 3],
+    //the slot neither side holds right now
+    back: AtomicU8,
+}
+
+//each slot is only touched by the side whose index points at it, handing one over goes
+//through the atomic swap on `back`
+//Sync is needed as well since FrameReader::packet hands out &T through a shared reference,
+//so a reader shared between threads gives each of them the same slot
+unsafe impl<T: Send + Sync> Sync for Slots<T> {}
+
+/**
+ * Fills packets for a FrameReader on another thread, never waits for it
+ **/
+pub struct FrameWriter<T> {
+    shared: Arc<Slots<T>>,
+    index: u8,
+}
+
+/**
+ * Reads the newest packet a FrameWriter published, never waits for it
+ **/
+pub struct FrameReader<T> {
+    shared: Arc<Slots<T>>,
+    index: u8,
+}
+
+impl<T> std::fmt::Debug for FrameWriter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "FrameWriter {{ index: {} }}", self.index)
+    }
+}
+
+impl<T> std::fmt::Debug for FrameReader<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "FrameReader {{ index: {} }}", self.index)
+    }
+}
+
+/**
+ * Lock free triple buffer between one writing and one reading thread
+ * The writer always has a slot to fill and the reader always has the last complete packet,
+ * packets the reader never got to are overwritten rather than queued
+ **/
+pub fn triple_buffer<T: Clone>(initial: T) -> (FrameWriter<T>, FrameReader<T>) {
+    let shared = Arc::new(Slots {
+        slots: [UnsafeCell::new(initial.clone()), UnsafeCell::new(initial.clone()), UnsafeCell::new(initial)],
+        back: AtomicU8::new(1),
+    });
+    (FrameWriter { shared: Arc::clone(&shared), index: 0 }, FrameReader { shared, index: 2 })
+}
+
+impl<T: Send> FrameWriter<T> {
+    /**
+     * The packet being filled, it holds whatever was in the slot before so reuse its buffers
+     * but overwrite everything in it
+     **/
+    pub fn packet(&mut self) -> &mut T {
+        unsafe { &mut *self.shared.slots[self.index as usize].get() }
+    }
+
+    /**
+     * Hands the filled packet to the reader and takes a free slot for the next one
+     **/
+    pub fn publish(&mut self) {
+        let old = self.shared.back.swap(self.index | FRESH, Ordering::AcqRel);
+        self.index = old & SLOT;
+    }
+}
+
+impl<T: Send> FrameReader<T> {
+    /**
+     * Takes the newest published packet if there's one it hasn't seen
+     * Returns whether the packet changed
+     **/
+    pub fn update(&mut self) -> bool {
+        if self.shared.back.load(Ordering::Relaxed) & FRESH == 0 {
+            return false;
+        }
+        let old = self.shared.back.swap(self.index, Ordering::AcqRel);
+        self.index = old & SLOT;
+        true
+    }
+
+    /**
+     * The packet taken by the last update
+     **/
+    pub fn packet(&self) -> &T {
+        unsafe { &*self.shared.slots[self.index as usize].get() }
+    }
+}
+
+/**
+ * Size of the window's drawable area, sent by the render thread when it changes
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Surface {
+    pub size: (i32, i32),
+    pub framebuffer: (i32, i32),
+    pub content_scale: f32,
+}
+
+impl Default for Surface {
+    fn default() -> Surface {
+        Surface { size: (0, 0), framebuffer: (0, 0), content_scale: 1.0 }
+    }
+}
+
+/**
+ * A tilemap as the update thread last left it
+ * `map` is copied again only when its revision changes, `time` drives tile animations
+ **/
+#[derive(Debug, Clone)]
+pub struct TilemapFrame {
+    pub map: Arc<Tilemap>,
+    pub time: f32,
+}
+
+/**
+ * Everything the render thread needs to draw one frame, built by the update thread
+ * Once published it is never written to while the renderer reads it
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct FramePacket {
+    //counts up from 1, 0 is the empty packet the buffer starts with
+    pub frame: u64,
+    pub dt: f32,
+    pub camera: Camera,
+    //world matrices of top level objects with a Transform, by object id
+    pub transforms: Vec<(u32, Mat4)>,
+    pub particles: Vec<ParticleBatch>,
+    pub tilemaps: Vec<TilemapFrame>,
+    //draw commands for each game UI, in the order they were pushed
+    pub ui: Vec<Vec<DrawCommand>>,
+}
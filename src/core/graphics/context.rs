@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use vulkano::instance::Instance;
 
+use crate::core::graphics::DeviceCreationError;
 use crate::core::graphics::opengl::OpenGLContext;
 #[cfg(windows)]
 use crate::core::graphics::directx::DirectXContext;
@@ -59,13 +60,13 @@ impl Context<OpenGLContext> {
 }
 
 impl Context<VulkanContext> {
-    pub fn new(window: glfw::Window, instance: Arc<Instance>, id: usize) -> Context<VulkanContext> {
-        Context {
+    pub fn new(window: glfw::Window, instance: Arc<Instance>, id: usize) -> Result<Context<VulkanContext>, DeviceCreationError> {
+        Ok(Context {
             api_context: VulkanContext::new(
                              window,
                              instance,
-                             id)
-        }
+                             id)?
+        })
     }
 
     pub fn set_width(&mut self, w: u32) {
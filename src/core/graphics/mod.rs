@@ -7,6 +7,9 @@ pub mod context;
 use std::error::Error;
 use std::fmt;
 
+use vulkano::instance::InstanceCreationError;
+use vulkano_glfw::VulkanoGlfwError;
+
 #[derive(Debug)]
 pub struct SymbolLoadError {
     details: String
@@ -33,23 +36,54 @@ impl Error for SymbolLoadError {
 #[derive(Debug)]
 pub enum DeviceCreationError {
     NotVulkanContext,
-    FailedToCreateVulkanDevice
+    FailedToCreateVulkanDevice(vulkano::device::DeviceCreationError),
+    /**
+     * Glfw found no Vulkan loader or the loader is missing the surface extensions
+     **/
+    VulkanUnsupported(VulkanoGlfwError),
+    FailedToCreateVulkanInstance(InstanceCreationError),
+    FailedToCreateVulkanSurface(VulkanoGlfwError),
+    /**
+     * Settings asked for a physical device id the instance doesn't have
+     **/
+    NoPhysicalDevice(usize),
+    NoGraphicsQueue,
 }
 
 impl fmt::Display for DeviceCreationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", match self {
-            DeviceCreationError::NotVulkanContext => "Not Vulkan Context",
-            DeviceCreationError::FailedToCreateVulkanDevice => "Failed To Create Vulkan Device"
-        })
+        match self {
+            DeviceCreationError::NotVulkanContext => write!(f, "Not Vulkan Context"),
+            DeviceCreationError::FailedToCreateVulkanDevice(e) => write!(f, "Failed To Create Vulkan Device: {}", e),
+            DeviceCreationError::VulkanUnsupported(e) => write!(f, "Vulkan Unsupported: {}", e),
+            DeviceCreationError::FailedToCreateVulkanInstance(e) => write!(f, "Failed To Create Vulkan Instance: {}", e),
+            DeviceCreationError::FailedToCreateVulkanSurface(e) => write!(f, "Failed To Create Vulkan Surface: {}", e),
+            DeviceCreationError::NoPhysicalDevice(id) => write!(f, "No Vulkan Physical Device With Id {}", id),
+            DeviceCreationError::NoGraphicsQueue => write!(f, "No Vulkan Queue Family Supports Graphics"),
+        }
     }
 }
 
 impl Error for DeviceCreationError {
-    fn description(&self) -> & str {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            DeviceCreationError::NotVulkanContext => "Not Vulkan Context",
-            DeviceCreationError::FailedToCreateVulkanDevice => "Failed To Create Vulkan Device"
+            DeviceCreationError::FailedToCreateVulkanDevice(e) => Some(e),
+            DeviceCreationError::VulkanUnsupported(e) => Some(e),
+            DeviceCreationError::FailedToCreateVulkanInstance(e) => Some(e),
+            DeviceCreationError::FailedToCreateVulkanSurface(e) => Some(e),
+            _ => None
         }
     }
 }
+
+impl From<vulkano::device::DeviceCreationError> for DeviceCreationError {
+    fn from(e: vulkano::device::DeviceCreationError) -> DeviceCreationError {
+        DeviceCreationError::FailedToCreateVulkanDevice(e)
+    }
+}
+
+impl From<InstanceCreationError> for DeviceCreationError {
+    fn from(e: InstanceCreationError) -> DeviceCreationError {
+        DeviceCreationError::FailedToCreateVulkanInstance(e)
+    }
+}
@@ -2,14 +2,19 @@ use std::fmt;
 use std::sync::Arc;
 
 use vulkano::instance::{ Instance, PhysicalDevice };
-use vulkano::device::{ Device, DeviceExtensions };
+use vulkano::device::{ Device, DeviceExtensions, Queue };
 use vulkano::swapchain::Surface;
 
+use crate::core::crash;
+use crate::core::graphics::DeviceCreationError;
+
 pub struct VulkanContext {
     glfw: glfw::Glfw,
     device_id: usize,
     instance: Arc<Instance>,
     device: Arc<Device>,
+    //the graphics queue the device was created with
+    queue: Arc<Queue>,
     surface: Arc<Surface<glfw::Window>>,
 }
 
@@ -24,27 +29,42 @@ impl fmt::Debug for VulkanContext {
 
 impl VulkanContext {
 
-    pub fn new(window: glfw::Window, instance: Arc<Instance>, id: usize) -> VulkanContext {
+    pub fn new(window: glfw::Window, instance: Arc<Instance>, id: usize) -> Result<VulkanContext, DeviceCreationError> {
         debug!("vulkan instance references is: {}", Arc::strong_count(&Arc::clone(&instance)));
         debug!("vulkan extensions are : {:?}", instance.loaded_extensions());
         debug!("creating vulkan devices");
 
         let temp = instance.clone();
-        let physical_device = PhysicalDevice::from_index(&temp, id).unwrap();
-        let queue_family = physical_device.queue_families().find(|&q| q.supports_graphics()).expect("Couldn't find graphical queue family");
-        VulkanContext {
+        let physical_device = PhysicalDevice::from_index(&temp, id).ok_or(DeviceCreationError::NoPhysicalDevice(id))?;
+        crash::set_device_info("device", format!("{} ({:?})", physical_device.name(), physical_device.ty()));
+        crash::set_device_info("vulkan version", format!("{:?}", physical_device.api_version()));
+        crash::set_device_info("driver version", physical_device.driver_version().to_string());
+        let queue_family = physical_device.queue_families().find(|&q| q.supports_graphics()).ok_or(DeviceCreationError::NoGraphicsQueue)?;
+        let (device, mut queues) = Device::new(physical_device, &physical_device.supported_features(),
+            &DeviceExtensions::supported_by_device(physical_device), [(queue_family, 0.5)].iter().cloned())?;
+        let queue = queues.next().ok_or(DeviceCreationError::NoGraphicsQueue)?;
+        Ok(VulkanContext {
             glfw: window.glfw,
             device_id: id,
-            surface: vulkano_glfw::create_window_surface(Arc::clone(&instance), window).expect("Failed to create vulkan surface"),
+            surface: vulkano_glfw::create_window_surface(Arc::clone(&instance), window).map_err(DeviceCreationError::FailedToCreateVulkanSurface)?,
             instance,
-            device: Device::new(physical_device, &physical_device.supported_features(), &DeviceExtensions::supported_by_device(physical_device), [(queue_family, 0.5)].iter().cloned()).expect("Failed to create vulkan device").0
-        }
+            device,
+            queue,
+        })
     }
 
     pub fn get_surface(&mut self) -> Arc<Surface<glfw::Window>> {
         self.surface.clone()
     }
 
+    pub fn get_device(&self) -> Arc<Device> {
+        Arc::clone(&self.device)
+    }
+
+    pub fn get_queue(&self) -> Arc<Queue> {
+        Arc::clone(&self.queue)
+    }
+
     pub fn get_glfw(&mut self) -> &mut glfw::Glfw {
         &mut self.glfw
     }
@@ -0,0 +1,91 @@
+use std::any::Any;
+use std::collections::HashSet;
+
+use crate::core::signals::{ SyncData, SyncSlot };
+use crate::events::event::{ EventData, EventType };
+use crate::math::Vec2;
+
+/**
+ * Current keyboard and mouse state, kept up to date from the window's input events
+ * Lets code that runs outside of event handling (scripts, update loops) poll for input
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct InputState {
+    keys: HashSet<i32>,
+    mouse_buttons: HashSet<i32>,
+    mouse_position: Vec2,
+    //accumulated until read with take_scroll
+    scroll: Vec2,
+}
+
+impl InputState {
+    pub fn new() -> InputState {
+        InputState::default()
+    }
+
+    /**
+     * Whether the key with this glfw keycode is held down
+     **/
+    #[inline]
+    pub fn key_down(&self, key: i32) -> bool {
+        self.keys.contains(&key)
+    }
+
+    #[inline]
+    pub fn mouse_button_down(&self, button: i32) -> bool {
+        self.mouse_buttons.contains(&button)
+    }
+
+    /**
+     * Cursor position in window coordinates
+     **/
+    #[inline]
+    pub fn mouse_position(&self) -> Vec2 {
+        self.mouse_position
+    }
+
+    /**
+     * Scrolling since the last call
+     **/
+    pub fn take_scroll(&mut self) -> Vec2 {
+        std::mem::take(&mut self.scroll)
+    }
+
+    /**
+     * Releases everything, e.g. when the window loses focus and release events won't arrive
+     **/
+    pub fn clear(&mut self) {
+        self.keys.clear();
+        self.mouse_buttons.clear();
+    }
+}
+
+impl SyncSlot<EventData> for InputState {
+    fn consume(&mut self, event: &SyncData<&EventData>) -> bool {
+        let data = *event.sig();
+        match (data.event_type(), data) {
+            (EventType::KeyPressed, EventData::I32p(key, _, _)) => {
+                self.keys.insert(*key);
+            },
+            (EventType::KeyReleased, EventData::I32p(key, _, _)) => {
+                self.keys.remove(key);
+            },
+            (EventType::MouseButtonPressed, EventData::I32p(button, _, _)) => {
+                self.mouse_buttons.insert(*button);
+            },
+            (EventType::MouseButtonReleased, EventData::I32p(button, _, _)) => {
+                self.mouse_buttons.remove(button);
+            },
+            (EventType::MouseMoved, EventData::F32p(x, y, _)) => self.mouse_position = Vec2::new(*x, *y),
+            (EventType::MouseScrolled, EventData::F32p(x, y, _)) => self.scroll += Vec2::new(*x, *y),
+            (EventType::WindowFocus, EventData::Bool(false, _)) => self.clear(),
+            _ => {}
+        }
+        //input state only watches, other slots still get the event
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,7 +1,10 @@
-use std::slice::IterMut;
+use std::slice::{ Iter, IterMut };
 
+use crate::core::component::ComponentRegistry;
+use crate::core::entity::Entity;
 use crate::core::object::Object;
-use crate::events::event::Event;
+use crate::core::scene::{ AssetRef, LayerData, SceneError };
+use crate::events::event::{ Event, EventData, EventType };
 
 pub struct Layer {
 	enabled: bool,
@@ -56,12 +59,70 @@ impl Layer {
 		self.objects.get(index).expect("Index out of bounds!")
 	}
 
-	pub fn on_event(&mut self, e: &mut dyn Event) {
+	pub fn replace_object(&mut self, obj: Box<dyn Object>, index: usize) -> Box<dyn Object> {
+		std::mem::replace(&mut self.objects[index], obj)
+	}
+
+	#[inline]
+	pub fn object_count(&self) -> usize {
+		self.objects.len()
+	}
+
+	pub fn objects(&self) -> Iter<'_, Box<dyn Object>> {
+		self.objects.iter()
+	}
+
+	pub fn assets(&self) -> Vec<AssetRef> {
+		self.objects.iter().flat_map(|x| x.assets()).collect()
+	}
+
+	pub fn to_data(&self, overlay: bool) -> Result<LayerData, SceneError> {
+		let mut objects = Vec::with_capacity(self.objects.len());
+		for obj in &self.objects {
+			match obj.save()? {
+				Some(x) => objects.push(x),
+				None    => debug!("Object {} in layer {} isn't saveable, skipping", obj.name(), self.debug_name)
+			}
+		}
+		Ok(LayerData { debug_name: self.debug_name.clone(), enabled: self.enabled, overlay, objects })
+	}
 
+	pub fn from_data(data: LayerData, registry: &ComponentRegistry) -> Result<Layer, SceneError> {
+		let mut objects: Vec<Box<dyn Object>> = Vec::with_capacity(data.objects.len());
+		for obj in data.objects {
+			objects.push(Box::new(Entity::from_data(obj, registry)?));
+		}
+		Ok(Layer::new(data.enabled, Some(objects), data.debug_name))
+	}
+
+	pub fn on_event(&mut self, e: &mut dyn Event) {
+		if !self.enabled {
+			return;
+		}
+		for obj in self.objects.iter_mut() {
+			if e.get_handled() {
+				break;
+			}
+			obj.on_event(e);
+		}
 	}
 
 	pub fn on_update(&mut self) {
+		if !self.enabled {
+			return;
+		}
+		for obj in self.objects.iter_mut() {
+			obj.on_update();
+		}
+	}
 
+	pub fn on_fixed_update(&mut self, dt: f32) {
+		if !self.enabled {
+			return;
+		}
+		for obj in self.objects.iter_mut() {
+			obj.on_fixed_update(dt);
+		}
 	}
 }
 
@@ -83,7 +144,7 @@ impl LayerStack {
 
 	pub fn push_layer(&mut self, layer: Layer) {
 		self.layers.insert(self.insert_index, layer);
-		self.insert_index += self.insert_index;
+		self.insert_index += 1;
 	}
 
 	pub fn push_overlay(&mut self, layer: Layer) {
@@ -91,7 +152,7 @@ impl LayerStack {
 	}
 
 	pub fn remove_layer(&mut self) -> Layer {
-		self.insert_index -= self.insert_index;
+		self.insert_index -= 1;
 		self.layers.remove(self.insert_index)
 	}
 
@@ -99,7 +160,121 @@ impl LayerStack {
 		self.layers.remove(self.layers.len() - 1)
 	}
 
+	pub fn iter(&self) -> Iter<'_, Layer> {
+		self.layers.iter()
+	}
+
 	pub fn iter_mut(&mut self) -> IterMut<'_, Layer> {
 		self.layers.iter_mut()
 	}
+
+	//Index of the first overlay, everything before it is a regular layer
+	#[inline]
+	pub fn overlay_start(&self) -> usize {
+		self.insert_index
+	}
+
+	//Overlays see events first, they sit on top of the regular layers
+	pub fn on_event(&mut self, e: &mut dyn Event) {
+		for layer in self.layers.iter_mut().rev() {
+			if e.get_handled() {
+				break;
+			}
+			layer.on_event(e);
+		}
+	}
+}
+
+/**
+ * Event rebuilt from the data a signal delivered, so it can be handed on to layers
+ **/
+#[derive(Debug)]
+pub struct ForwardedEvent {
+	event_type: EventType,
+	msg: String,
+	data: EventData,
+	handled: bool,
+}
+
+impl ForwardedEvent {
+	pub fn new(data: EventData) -> ForwardedEvent {
+		ForwardedEvent { event_type: *data.event_type(), msg: data.to_string(), data, handled: false }
+	}
+}
+
+impl std::fmt::Display for ForwardedEvent {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "ForwardedEvent: (event_type: {}, msg: {}, data: {}, handled: {})",
+		self.event_type, self.msg, self.data, self.handled)
+	}
+}
+
+impl Event for ForwardedEvent {
+	fn get_event_type(&self) -> EventType {
+		self.event_type
+	}
+
+	//the original category isn't part of the signal data
+	fn get_category_flags(&self) -> u32 {
+		0
+	}
+
+	fn get_msg(&self) -> &String {
+		&self.msg
+	}
+
+	fn get_data(&self) -> Option<&EventData> {
+		Some(&self.data)
+	}
+
+	fn get_handled(&self) -> bool {
+		self.handled
+	}
+
+	fn set_handled(&mut self, handled: bool) {
+		self.handled = handled;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn names(stack: &LayerStack) -> Vec<&str> {
+		stack.iter().map(|x| x.debug_name().as_str()).collect()
+	}
+
+	#[test]
+	fn layers_stay_below_overlays() {
+		let mut stack = LayerStack::new(None, None);
+		stack.push_overlay(Layer::new(true, None, "overlay a".to_string()));
+		stack.push_layer(Layer::new(true, None, "layer a".to_string()));
+		stack.push_layer(Layer::new(true, None, "layer b".to_string()));
+		stack.push_overlay(Layer::new(true, None, "overlay b".to_string()));
+		stack.push_layer(Layer::new(true, None, "layer c".to_string()));
+		assert_eq!(names(&stack), ["layer a", "layer b", "layer c", "overlay a", "overlay b"]);
+		assert_eq!(stack.overlay_start(), 3);
+	}
+
+	#[test]
+	fn removing_takes_the_last_of_each_kind() {
+		let mut stack = LayerStack::new(None, None);
+		for name in ["layer a", "layer b", "layer c"] {
+			stack.push_layer(Layer::new(true, None, name.to_string()));
+		}
+		stack.push_overlay(Layer::new(true, None, "overlay a".to_string()));
+		stack.push_overlay(Layer::new(true, None, "overlay b".to_string()));
+
+		assert_eq!(stack.remove_layer().debug_name(), "layer c");
+		assert_eq!(stack.overlay_start(), 2);
+		assert_eq!(stack.remove_overlay().debug_name(), "overlay b");
+		assert_eq!(stack.overlay_start(), 2);
+		stack.push_layer(Layer::new(true, None, "layer d".to_string()));
+		assert_eq!(names(&stack), ["layer a", "layer b", "layer d", "overlay a"]);
+		assert_eq!(stack.remove_layer().debug_name(), "layer d");
+		assert_eq!(stack.remove_layer().debug_name(), "layer b");
+		assert_eq!(stack.remove_layer().debug_name(), "layer a");
+		assert_eq!(stack.overlay_start(), 0);
+		assert_eq!(names(&stack), ["overlay a"]);
+	}
 }
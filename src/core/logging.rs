@@ -0,0 +1,400 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::fs::{ self, File, OpenOptions };
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
+use std::sync::{ Mutex, OnceLock, PoisonError, RwLock, TryLockError };
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use fern::colors::{ Color, ColoredLevelConfig };
+use log::{ Level, LevelFilter, Log, Metadata, Record, SetLoggerError };
+use serde_json::json;
+
+use crate::core::settings::{ LogFormat, LogLevel, LogSettings };
+
+/**
+ * Messages the in-game console keeps, older ones are dropped
+ **/
+pub const CONSOLE_LINES: usize = 1000;
+
+/**
+ * Messages kept for crash reports, whether or not the console is on
+ **/
+pub const CRASH_LINES: usize = 200;
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug)]
+pub enum LogError {
+    Io(io::Error),
+    /**
+     * Something other than the engine already installed a logger
+     **/
+    SetLogger(SetLoggerError),
+}
+
+impl fmt::Display for LogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LogError::Io(e) => write!(f, "Log file error: {}", e),
+            LogError::SetLogger(e) => write!(f, "Couldn't install logger: {}", e),
+        }
+    }
+}
+
+impl Error for LogError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            LogError::Io(e) => Some(e),
+            LogError::SetLogger(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for LogError {
+    fn from(e: io::Error) -> LogError {
+        LogError::Io(e)
+    }
+}
+
+impl From<SetLoggerError> for LogError {
+    fn from(e: SetLoggerError) -> LogError {
+        LogError::SetLogger(e)
+    }
+}
+
+/**
+ * A message kept for the in-game console
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogLine {
+    //counts up from 1 across the whole run, for picking up only new lines
+    pub id: u64,
+    pub time: String,
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+struct Filters {
+    level: LevelFilter,
+    //longest first so the first match is the most specific
+    modules: Vec<(String, LevelFilter)>,
+    format: LogFormat,
+    stderr: bool,
+    console: bool,
+}
+
+impl Filters {
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .find(|(module, _)| target == module || target.strip_prefix(module.as_str()).is_some_and(|x| x.starts_with("::")))
+            .map_or(self.level, |x| x.1)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|x| x.1).fold(self.level, |a, b| a.max(b))
+    }
+
+    fn sort(&mut self) {
+        self.modules.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    }
+}
+
+/**
+ * The log file, moved aside to {path}.1, {path}.2 and so on when it gets too big
+ **/
+#[derive(Debug)]
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_size: u64,
+    keep: u32,
+}
+
+impl LogFile {
+    fn open(path: &Path, max_size: u64, keep: u32) -> io::Result<LogFile> {
+        if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(LogFile { path: path.to_path_buf(), file, written, max_size, keep })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.max_size > 0 && self.written > 0 && self.written + len > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.written += len;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        let numbered = |i: u32| {
+            let mut x = self.path.clone().into_os_string();
+            x.push(format!(".{}", i));
+            PathBuf::from(x)
+        };
+        //the oldest is overwritten, with nothing kept the file just starts over
+        for i in (1..self.keep).rev() {
+            let from = numbered(i);
+            if from.exists() {
+                fs::rename(from, numbered(i + 1))?;
+            }
+        }
+        if self.keep > 0 {
+            fs::rename(&self.path, numbered(1))?;
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Console {
+    lines: VecDeque<LogLine>,
+    //ids are handed out under the same lock so lines are always in id order
+    last_id: u64,
+}
+
+impl Console {
+    fn push(&mut self, capacity: usize, time: String, record: &Record, message: String) {
+        if self.lines.len() >= capacity {
+            self.lines.pop_front();
+        }
+        self.last_id += 1;
+        let id = self.last_id;
+        self.lines.push_back(LogLine { id, time, level: record.level(), target: record.target().to_string(), message });
+    }
+}
+
+struct Logger {
+    filters: RwLock<Filters>,
+    file: Mutex<Option<LogFile>>,
+    console: Mutex<Console>,
+    //always on and small, crash reports would be empty with the console turned off
+    crash: Mutex<Console>,
+    colors: ColoredLevelConfig,
+}
+
+impl Logger {
+    fn global() -> &'static Logger {
+        LOGGER.get_or_init(|| {
+            let defaults = LogSettings::default();
+            Logger {
+                filters: RwLock::new(Filters {
+                    level: defaults.level().filter(),
+                    modules: Vec::new(),
+                    format: defaults.format(),
+                    stderr: defaults.stderr(),
+                    console: defaults.console(),
+                }),
+                file: Mutex::new(None),
+                console: Mutex::new(Console::default()),
+                crash: Mutex::new(Console::default()),
+                colors: ColoredLevelConfig::new()
+                    .error(Color::Red)
+                    .warn(Color::Yellow)
+                    .info(Color::White)
+                    .debug(Color::Green)
+                    .trace(Color::Blue),
+            }
+        })
+    }
+
+    fn filters(&self) -> std::sync::RwLockReadGuard<'_, Filters> {
+        self.filters.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update_filters<F: FnOnce(&mut Filters)>(&self, change: F) {
+        let mut filters = self.filters.write().unwrap_or_else(PoisonError::into_inner);
+        change(&mut filters);
+        filters.sort();
+        log::set_max_level(filters.max_level());
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filters().level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        let (format, stderr, console) = {
+            let filters = self.filters();
+            if record.level() > filters.level_for(record.target()) {
+                return;
+            }
+            (filters.format, filters.stderr, filters.console)
+        };
+        let now = chrono::Local::now();
+        let message = record.args().to_string();
+        let text = format!("[{}][{}][{}] {}", now.format("%Y-%m-%d %H:%M:%S"), record.target(), record.level(), message);
+
+        if stderr {
+            let color = self.colors.get_color(&record.level());
+            let _ = writeln!(io::stderr().lock(), "\x1B[{}m{}\x1B[0m", color.to_fg_str(), text);
+        }
+        if let Some(file) = self.file.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            let line = match format {
+                LogFormat::Text => text,
+                LogFormat::Json => json!({
+                    "time": now.to_rfc3339(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "module": record.module_path(),
+                    "file": record.file(),
+                    "line": record.line(),
+                    "message": &message
+                }).to_string()
+            };
+            //nowhere left to report a failing log file
+            let _ = file.write_line(&line);
+        }
+        let time = now.format("%H:%M:%S").to_string();
+        if console {
+            self.console.lock().unwrap_or_else(PoisonError::into_inner).push(CONSOLE_LINES, time.clone(), record, message.clone());
+        }
+        self.crash.lock().unwrap_or_else(PoisonError::into_inner).push(CRASH_LINES, time, record, message);
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            let _ = file.file.flush();
+        }
+        let _ = io::stderr().flush();
+    }
+}
+
+/**
+ * Installs the engine logger with `settings`, later calls only reconfigure it
+ **/
+pub fn init(settings: &LogSettings) -> Result<(), LogError> {
+    let logger = Logger::global();
+    let configured = configure(settings);
+    if !INSTALLED.swap(true, Ordering::SeqCst) {
+        if let Err(e) = log::set_logger(logger) {
+            INSTALLED.store(false, Ordering::SeqCst);
+            return Err(e.into());
+        }
+        log::set_max_level(logger.filters().max_level());
+    }
+    configured
+}
+
+/**
+ * Applies `settings` to the running logger, reopening the log file if it moved
+ * Everything but the file is applied even when the file can't be opened
+ **/
+pub fn configure(settings: &LogSettings) -> Result<(), LogError> {
+    let logger = Logger::global();
+    logger.update_filters(|x| {
+        x.level = settings.level().filter();
+        x.modules = settings.modules().iter().map(|(module, level)| (module.clone(), level.filter())).collect();
+        x.format = settings.format();
+        x.stderr = settings.stderr();
+        x.console = settings.console();
+    });
+
+    let mut file = logger.file.lock().unwrap_or_else(PoisonError::into_inner);
+    let path = settings.file().map(PathBuf::from);
+    match (file.as_mut(), path) {
+        (Some(current), Some(path)) if current.path == path => {
+            current.max_size = settings.max_file_size();
+            current.keep = settings.max_files();
+        },
+        (_, Some(path)) => {
+            *file = None;
+            *file = Some(LogFile::open(&path, settings.max_file_size(), settings.max_files())?);
+        },
+        (_, None) => *file = None
+    }
+    Ok(())
+}
+
+pub fn level() -> LogLevel {
+    Logger::global().filters().level.into()
+}
+
+/**
+ * Changes the default level while running, module levels still take precedence
+ **/
+pub fn set_level(level: LogLevel) {
+    Logger::global().update_filters(|x| x.level = level.filter());
+}
+
+/**
+ * The level messages from `target` are kept at
+ **/
+pub fn module_level(target: &str) -> LogLevel {
+    Logger::global().filters().level_for(target).into()
+}
+
+/**
+ * Changes the level for everything under `module` while running, None goes back to the default level
+ **/
+pub fn set_module_level(module: &str, level: Option<LogLevel>) {
+    Logger::global().update_filters(|x| {
+        x.modules.retain(|(m, _)| m != module);
+        if let Some(level) = level {
+            x.modules.push((module.to_string(), level.filter()));
+        }
+    });
+}
+
+/**
+ * Console lines logged after the one with `id`, pass 0 for everything still kept
+ **/
+pub fn console_since(id: u64) -> Vec<LogLine> {
+    let console = Logger::global().console.lock().unwrap_or_else(PoisonError::into_inner);
+    console.lines.iter().filter(|x| x.id > id).cloned().collect()
+}
+
+/**
+ * The last `count` lines logged, up to CRASH_LINES, for crash reports
+ * Kept even with the console turned off, ids count separately from the console's
+ * Never blocks, a panic while the buffer was locked gives no lines rather than a hang
+ **/
+pub fn recent_lines(count: usize) -> Vec<LogLine> {
+    let console = match Logger::global().crash.try_lock() {
+        Ok(x) => x,
+        Err(TryLockError::Poisoned(x)) => x.into_inner(),
+        Err(TryLockError::WouldBlock) => return Vec::new()
+    };
+    console.lines.iter().skip(console.lines.len().saturating_sub(count)).cloned().collect()
+}
+
+pub fn clear_console() {
+    Logger::global().console.lock().unwrap_or_else(PoisonError::into_inner).lines.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recent_lines_kept_with_console_off() {
+        let mut settings = LogSettings::default();
+        settings.set_console(false);
+        settings.set_stderr(false);
+        settings.set_file(None);
+        init(&settings).unwrap();
+        for i in 0..CRASH_LINES + 10 {
+            info!("crash buffer line {}", i);
+        }
+        let lines = recent_lines(CRASH_LINES * 2);
+        assert_eq!(lines.len(), CRASH_LINES);
+        assert_eq!(lines.last().unwrap().message, format!("crash buffer line {}", CRASH_LINES + 9));
+        assert!(lines.windows(2).all(|x| x[0].id < x[1].id));
+        assert!(console_since(0).iter().all(|x| !x.message.starts_with("crash buffer line")));
+    }
+}
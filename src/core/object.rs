@@ -1,5 +1,42 @@
+use crate::core::scene::{ AssetRef, EntityData, SceneError };
+use crate::core::transform::Transform;
+use crate::events::event::Event;
 
 pub trait Object: Send + Sync {
     fn id(&self) -> u32;
     fn name(&self) -> &String;
+
+    /**
+     * Saves this object for scene serialization
+     * Objects that don't take part in scenes return Ok(None) and are skipped
+     **/
+    fn save(&self) -> Result<Option<EntityData>, SceneError> {
+        Ok(None)
+    }
+
+    fn assets(&self) -> Vec<AssetRef> {
+        Vec::new()
+    }
+
+    /**
+     * Where the object is, objects without a place in the world return None
+     **/
+    fn transform(&self) -> Option<Transform> {
+        None
+    }
+
+    /**
+     * Called once per update of the layer holding this object
+     **/
+    fn on_update(&mut self) {}
+
+    /**
+     * Called once per fixed tick with the tick length, after the physics step
+     **/
+    fn on_fixed_update(&mut self, _dt: f32) {}
+
+    /**
+     * Objects mark the event handled to stop it reaching the objects and layers after them
+     **/
+    fn on_event(&mut self, _e: &mut dyn Event) {}
 }
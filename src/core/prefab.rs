@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::core::component::ComponentRegistry;
+use crate::core::entity::Entity;
+use crate::core::layers::LayerStack;
+use crate::core::scene::{ ComponentData, EntityData, SceneError };
+
+/**
+ * Current version of the prefab file layout
+ * Component data inside a prefab is versioned and migrated the same way as in scenes
+ **/
+pub const PREFAB_FORMAT_VERSION: u32 = 1;
+
+/**
+ * A single change made to a prefab instance, relative to the prefab it came from
+ * `path` is the list of child keys from the instance root down to the entity the change applies to,
+ * where a child key is the child's name, or name#n for the nth sibling sharing that name
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum PrefabOverride {
+    Property { path: Vec<String>, component: String, pointer: String, value: serde_json::Value },
+    AddComponent { path: Vec<String>, component: ComponentData },
+    AddChild { path: Vec<String>, child: EntityData },
+}
+
+/**
+ * Links an entity to the prefab asset it was instantiated from, plus the overrides made on that instance
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct PrefabLink {
+    pub path: String,
+    #[serde(default)]
+    pub overrides: Vec<PrefabOverride>,
+}
+
+impl PrefabLink {
+    pub fn new(path: &str) -> PrefabLink {
+        PrefabLink { path: path.to_string(), overrides: Vec::new() }
+    }
+}
+
+/**
+ * An entity subtree saved as an asset so it can be instantiated many times
+ * The root may contain nested prefab instances, which are stored as links rather than expanded
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Prefab {
+    version: u32,
+    name: String,
+    root: EntityData,
+}
+
+impl Prefab {
+    pub fn new(name: &str, root: EntityData) -> Prefab {
+        Prefab { version: PREFAB_FORMAT_VERSION, name: name.to_string(), root }
+    }
+
+    /**
+     * Builds a prefab from an existing entity, keeping any nested prefab instances as links
+     **/
+    pub fn from_entity(name: &str, entity: &Entity, library: &mut PrefabLibrary) -> Result<Prefab, SceneError> {
+        let mut root = entity.to_data()?;
+        root.prefab = None;
+        root.children = root.children.into_iter()
+            .map(|x| library.compact(x))
+            .collect::<Result<Vec<EntityData>, SceneError>>()?;
+        Ok(Prefab::new(name, root))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path.as_ref(), json)?;
+        debug!("Prefab {} written to {}", self.name, path.as_ref().display());
+        Ok(())
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Prefab, SceneError> {
+        let file = fs::read_to_string(path.as_ref())?;
+        let prefab: Prefab = serde_json::from_str(&file)?;
+        if prefab.version > PREFAB_FORMAT_VERSION {
+            return Err(SceneError::UnsupportedPrefabVersion(prefab.version));
+        }
+        Ok(prefab)
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn root(&self) -> &EntityData {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut EntityData {
+        &mut self.root
+    }
+}
+
+/**
+ * Cache of loaded prefabs, keyed by the path they were loaded from
+ * Handles expanding prefab instances into full entity data and reducing them back down to overrides
+ **/
+pub struct PrefabLibrary {
+    prefabs: HashMap<String, Prefab>,
+}
+
+impl PrefabLibrary {
+    pub fn new() -> PrefabLibrary {
+        PrefabLibrary { prefabs: HashMap::new() }
+    }
+
+    pub fn insert(&mut self, path: &str, prefab: Prefab) -> Option<Prefab> {
+        self.prefabs.insert(path.to_string(), prefab)
+    }
+
+    pub fn get(&self, path: &str) -> Option<&Prefab> {
+        self.prefabs.get(path)
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<&Prefab, SceneError> {
+        if !self.prefabs.contains_key(path) {
+            debug!("Loading prefab {}", path);
+            let prefab = Prefab::read(path)?;
+            self.prefabs.insert(path.to_string(), prefab);
+        }
+        Ok(&self.prefabs[path])
+    }
+
+    pub fn instantiate(&mut self, path: &str, id: u32, name: String, registry: &ComponentRegistry)
+        -> Result<Entity, SceneError> {
+        let data = EntityData {
+            id,
+            name,
+            components: Vec::new(),
+            children: Vec::new(),
+            prefab: Some(PrefabLink::new(path))
+        };
+        Entity::from_data(self.expand(data)?, registry)
+    }
+
+    /**
+     * Expands every prefab instance in `data` (including nested ones) into full entity data
+     * The expanded instance keeps its PrefabLink so it can be compacted again later
+     **/
+    pub fn expand(&mut self, data: EntityData) -> Result<EntityData, SceneError> {
+        self.expand_inner(data, &mut Vec::new())
+    }
+
+    fn expand_inner(&mut self, mut data: EntityData, stack: &mut Vec<String>) -> Result<EntityData, SceneError> {
+        let link = match data.prefab.take() {
+            Some(x) => x,
+            None => {
+                let mut children = Vec::with_capacity(data.children.len());
+                for child in data.children {
+                    children.push(self.expand_inner(child, stack)?);
+                }
+                data.children = children;
+                return Ok(data);
+            }
+        };
+
+        if stack.contains(&link.path) {
+            return Err(SceneError::PrefabCycle(link.path));
+        }
+        stack.push(link.path.clone());
+        let base = self.load(&link.path)?.root.clone();
+        let mut expanded = self.expand_inner(base, stack)?;
+        expanded.id = data.id;
+        expanded.name = data.name;
+        for item in &link.overrides {
+            self.apply_override(&mut expanded, item, stack)?;
+        }
+        stack.pop();
+
+        expanded.prefab = Some(link);
+        Ok(expanded)
+    }
+
+    fn apply_override(&mut self, root: &mut EntityData, item: &PrefabOverride, stack: &mut Vec<String>)
+        -> Result<(), SceneError> {
+        match item {
+            PrefabOverride::Property { path, component, pointer, value } => {
+                let target = find_entity_mut(root, path)?;
+                let data = match target.components.iter_mut().find(|x| &x.kind == component) {
+                    Some(x) => &mut x.data,
+                    None => return Err(SceneError::InvalidOverride(
+                            format!("{} has no component {}", path.join("/"), component)))
+                };
+                set_pointer(data, pointer, value.clone())
+            },
+            PrefabOverride::AddComponent { path, component } => {
+                find_entity_mut(root, path)?.components.push(component.clone());
+                Ok(())
+            },
+            PrefabOverride::AddChild { path, child } => {
+                let child = self.expand_inner(child.clone(), stack)?;
+                find_entity_mut(root, path)?.children.push(child);
+                Ok(())
+            }
+        }
+    }
+
+    /**
+     * Reduces every expanded prefab instance in `data` back down to its link and the list of
+     * overrides that differ from the prefab
+     * Removing components or children from an instance can't be expressed as an override, so those
+     * come back the next time the instance is expanded
+     **/
+    pub fn compact(&mut self, mut data: EntityData) -> Result<EntityData, SceneError> {
+        let link = match data.prefab.take() {
+            Some(x) => x,
+            None => {
+                let mut children = Vec::with_capacity(data.children.len());
+                for child in data.children {
+                    children.push(self.compact(child)?);
+                }
+                data.children = children;
+                return Ok(data);
+            }
+        };
+
+        let base = self.expand(EntityData {
+            id: data.id,
+            name: data.name.clone(),
+            components: Vec::new(),
+            children: Vec::new(),
+            prefab: Some(PrefabLink::new(&link.path))
+        })?;
+
+        let mut overrides = Vec::new();
+        self.diff_entity(&base, &data, &mut Vec::new(), &mut overrides)?;
+        Ok(EntityData {
+            id: data.id,
+            name: data.name,
+            components: Vec::new(),
+            children: Vec::new(),
+            prefab: Some(PrefabLink { path: link.path, overrides })
+        })
+    }
+
+    fn diff_entity(&mut self, base: &EntityData, instance: &EntityData, path: &mut Vec<String>,
+                   out: &mut Vec<PrefabOverride>) -> Result<(), SceneError> {
+        for component in &instance.components {
+            match base.components.iter().find(|x| x.kind == component.kind) {
+                Some(x) => diff_value(&x.data, &component.data, String::new(), &mut |pointer, value| {
+                    out.push(PrefabOverride::Property {
+                        path: path.clone(),
+                        component: component.kind.clone(),
+                        pointer,
+                        value
+                    });
+                }),
+                None => out.push(PrefabOverride::AddComponent { path: path.clone(), component: component.clone() })
+            }
+        }
+
+        for (index, child) in instance.children.iter().enumerate() {
+            let key = child_key(&instance.children, index);
+            match find_child(&base.children, &key) {
+                Some(x) => {
+                    path.push(key);
+                    self.diff_entity(&base.children[x], child, path, out)?;
+                    path.pop();
+                },
+                None => out.push(PrefabOverride::AddChild { path: path.clone(), child: self.compact(child.clone())? })
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Swaps in a new version of the prefab at `path` and rebuilds every instance of it in the stack,
+     * keeping each instance's overrides
+     **/
+    pub fn propagate(&mut self, path: &str, prefab: Prefab, stack: &mut LayerStack, registry: &ComponentRegistry)
+        -> Result<usize, SceneError> {
+        let mut instances = Vec::new();
+        for (layer_index, layer) in stack.iter().enumerate() {
+            for (obj_index, obj) in layer.objects().enumerate() {
+                if let Some(data) = obj.save()? {
+                    if references(&data, path) {
+                        instances.push((layer_index, obj_index, self.compact(data)?));
+                    }
+                }
+            }
+        }
+
+        self.prefabs.insert(path.to_string(), prefab);
+
+        let count = instances.len();
+        for (layer_index, obj_index, data) in instances {
+            let entity = Entity::from_data(self.expand(data)?, registry)?;
+            if let Some(layer) = stack.iter_mut().nth(layer_index) {
+                layer.replace_object(Box::new(entity), obj_index);
+            }
+        }
+        debug!("Propagated prefab {} to {} instances", path, count);
+        Ok(count)
+    }
+}
+
+impl Default for PrefabLibrary {
+    fn default() -> PrefabLibrary {
+        PrefabLibrary::new()
+    }
+}
+
+fn references(data: &EntityData, path: &str) -> bool {
+    match &data.prefab {
+        Some(link) if link.path == path => true,
+        _ => data.children.iter().any(|x| references(x, path))
+    }
+}
+
+fn child_key(children: &[EntityData], index: usize) -> String {
+    let name = &children[index].name;
+    match children[..index].iter().filter(|x| &x.name == name).count() {
+        0 => name.clone(),
+        n => format!("{}#{}", name, n)
+    }
+}
+
+fn find_child(children: &[EntityData], key: &str) -> Option<usize> {
+    (0..children.len()).find(|&x| child_key(children, x) == key)
+}
+
+fn find_entity_mut<'a>(root: &'a mut EntityData, path: &[String]) -> Result<&'a mut EntityData, SceneError> {
+    let mut current = root;
+    for key in path {
+        current = match find_child(&current.children, key) {
+            Some(x) => &mut current.children[x],
+            None => return Err(SceneError::InvalidOverride(format!("no child {} in {}", key, current.name)))
+        };
+    }
+    Ok(current)
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/**
+ * Reports every leaf of `instance` that differs from `base` as a JSON pointer and the new value
+ * Arrays are treated as leaves
+ **/
+fn diff_value(base: &serde_json::Value, instance: &serde_json::Value, pointer: String,
+              out: &mut dyn FnMut(String, serde_json::Value)) {
+    match (base, instance) {
+        (serde_json::Value::Object(b), serde_json::Value::Object(i)) => {
+            for (key, value) in i {
+                let child = format!("{}/{}", pointer, escape_pointer(key));
+                match b.get(key) {
+                    Some(x) => diff_value(x, value, child, out),
+                    None => out(child, value.clone())
+                }
+            }
+        },
+        _ => if base != instance {
+            out(pointer, instance.clone());
+        }
+    }
+}
+
+fn set_pointer(data: &mut serde_json::Value, pointer: &str, value: serde_json::Value) -> Result<(), SceneError> {
+    if pointer.is_empty() {
+        *data = value;
+        return Ok(());
+    }
+    if let Some(x) = data.pointer_mut(pointer) {
+        *x = value;
+        return Ok(());
+    }
+
+    let split = match pointer.rfind('/') {
+        Some(x) => x,
+        None => return Err(SceneError::InvalidOverride(format!("bad pointer {}", pointer)))
+    };
+    let key = pointer[split + 1..].replace("~1", "/").replace("~0", "~");
+    match data.pointer_mut(&pointer[..split]) {
+        Some(serde_json::Value::Object(x)) => {
+            x.insert(key, value);
+            Ok(())
+        },
+        _ => Err(SceneError::InvalidOverride(format!("no property at {}", pointer)))
+    }
+}
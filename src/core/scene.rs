@@ -0,0 +1,279 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::core::component::ComponentRegistry;
+use crate::core::layers::{ Layer, LayerStack };
+use crate::core::prefab::{ PrefabLibrary, PrefabLink, PREFAB_FORMAT_VERSION };
+
+/**
+ * Current version of the scene file layout
+ * Bump this and add an entry to SCENE_MIGRATIONS whenever the layout of the
+ * structs below changes
+ **/
+pub const SCENE_FORMAT_VERSION: u32 = 1;
+
+type SceneMigration = fn(serde_json::Value) -> Result<serde_json::Value, SceneError>;
+
+/**
+ * Scene layout migrations, SCENE_MIGRATIONS[n] takes a scene at version n + 1 to n + 2
+ **/
+const SCENE_MIGRATIONS: &[SceneMigration] = &[];
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Serde(serde_json::Error),
+    MissingVersion,
+    UnsupportedSceneVersion(u32),
+    UnsupportedPrefabVersion(u32),
+    MissingSceneMigration(u32),
+    UnknownComponent(String),
+    UnsupportedComponentVersion(String, u32),
+    MissingMigration(String, u32),
+    PrefabCycle(String),
+    InvalidOverride(String),
+    InvalidData(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "Scene IO error: {}", e),
+            SceneError::Serde(e) => write!(f, "Scene (de)serialization error: {}", e),
+            SceneError::MissingVersion => write!(f, "Scene has no format version"),
+            SceneError::UnsupportedSceneVersion(v) =>
+                write!(f, "Scene format version {} is newer than supported version {}", v, SCENE_FORMAT_VERSION),
+            SceneError::UnsupportedPrefabVersion(v) =>
+                write!(f, "Prefab format version {} is newer than supported version {}", v, PREFAB_FORMAT_VERSION),
+            SceneError::MissingSceneMigration(v) => write!(f, "No migration for scene format version {}", v),
+            SceneError::UnknownComponent(kind) => write!(f, "Component kind {} is not registered", kind),
+            SceneError::UnsupportedComponentVersion(kind, v) =>
+                write!(f, "Component {} version {} is newer than the registered version", kind, v),
+            SceneError::MissingMigration(kind, v) => write!(f, "No migration for component {} version {}", kind, v),
+            SceneError::PrefabCycle(path) => write!(f, "Prefab {} contains itself", path),
+            SceneError::InvalidOverride(msg) => write!(f, "Invalid prefab override: {}", msg),
+            SceneError::InvalidData(msg) => write!(f, "Invalid component data: {}", msg),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            SceneError::Serde(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> SceneError {
+        SceneError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for SceneError {
+    fn from(e: serde_json::Error) -> SceneError {
+        SceneError::Serde(e)
+    }
+}
+
+/**
+ * Reference to an asset on disk used by a component (texture, mesh, sound, etc)
+ **/
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Serialize, Deserialize)]
+pub struct AssetRef {
+    kind: String,
+    path: String,
+}
+
+impl AssetRef {
+    pub fn new(kind: &str, path: &str) -> AssetRef {
+        AssetRef { kind: kind.to_string(), path: path.to_string() }
+    }
+
+    pub fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ComponentData {
+    pub kind: String,
+    pub version: u32,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct EntityData {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub components: Vec<ComponentData>,
+    #[serde(default)]
+    pub children: Vec<EntityData>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefab: Option<PrefabLink>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct LayerData {
+    pub debug_name: String,
+    pub enabled: bool,
+    pub overlay: bool,
+    #[serde(default)]
+    pub objects: Vec<EntityData>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Scene {
+    version: u32,
+    name: String,
+    #[serde(default)]
+    assets: Vec<AssetRef>,
+    #[serde(default)]
+    layers: Vec<LayerData>,
+}
+
+impl Scene {
+    pub fn new(name: &str) -> Scene {
+        Scene { version: SCENE_FORMAT_VERSION, name: name.to_string(), assets: Vec::new(), layers: Vec::new() }
+    }
+
+    /**
+     * Captures every layer and overlay in the stack, along with the objects in them that can be saved
+     **/
+    pub fn from_layer_stack(name: &str, stack: &LayerStack) -> Result<Scene, SceneError> {
+        let mut scene = Scene::new(name);
+        for (index, layer) in stack.iter().enumerate() {
+            scene.layers.push(layer.to_data(index >= stack.overlay_start())?);
+            scene.assets.extend(layer.assets());
+        }
+        scene.assets.sort();
+        scene.assets.dedup();
+        Ok(scene)
+    }
+
+    pub fn into_layer_stack(self, registry: &ComponentRegistry) -> Result<LayerStack, SceneError> {
+        let mut layers = Vec::with_capacity(self.layers.len());
+        let mut overlays = Vec::new();
+        for data in self.layers {
+            if data.overlay {
+                overlays.push(Layer::from_data(data, registry)?);
+            } else {
+                layers.push(Layer::from_data(data, registry)?);
+            }
+        }
+        let index = layers.len();
+        layers.extend(overlays);
+        Ok(LayerStack::new(Some(layers), Some(index)))
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path.as_ref(), json)?;
+        debug!("Scene {} written to {}", self.name, path.as_ref().display());
+        Ok(())
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let file = fs::read_to_string(path.as_ref())?;
+        Scene::from_json(&file)
+    }
+
+    /**
+     * Parses a scene, migrating it forward to SCENE_FORMAT_VERSION first if it is older
+     * Component data is migrated separately by the ComponentRegistry when the scene is loaded
+     **/
+    pub fn from_json(json: &str) -> Result<Scene, SceneError> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let value = migrate_scene(value)?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /**
+     * Replaces every prefab instance in the scene with the prefab's contents plus its overrides
+     * Call this after reading a scene and before turning it into a LayerStack
+     **/
+    pub fn expand_prefabs(&mut self, library: &mut PrefabLibrary) -> Result<(), SceneError> {
+        for layer in &mut self.layers {
+            for obj in &mut layer.objects {
+                *obj = library.expand(obj.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /**
+     * Reduces every prefab instance in the scene to its prefab link and the overrides made on it
+     * Call this before writing a scene so later edits to the prefab show up in it
+     **/
+    pub fn compact_prefabs(&mut self, library: &mut PrefabLibrary) -> Result<(), SceneError> {
+        for layer in &mut self.layers {
+            for obj in &mut layer.objects {
+                *obj = library.compact(obj.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn assets(&self) -> &[AssetRef] {
+        &self.assets
+    }
+
+    pub fn layers(&self) -> &[LayerData] {
+        &self.layers
+    }
+
+    pub fn layers_mut(&mut self) -> &mut Vec<LayerData> {
+        &mut self.layers
+    }
+}
+
+fn migrate_scene(mut value: serde_json::Value) -> Result<serde_json::Value, SceneError> {
+    let mut version = match value.get("version").and_then(|x| x.as_u64()) {
+        Some(x) => x as u32,
+        None => return Err(SceneError::MissingVersion)
+    };
+    if version > SCENE_FORMAT_VERSION {
+        return Err(SceneError::UnsupportedSceneVersion(version));
+    }
+
+    while version < SCENE_FORMAT_VERSION {
+        let migration = match version.checked_sub(1).and_then(|x| SCENE_MIGRATIONS.get(x as usize)) {
+            Some(x) => x,
+            None => return Err(SceneError::MissingSceneMigration(version))
+        };
+        debug!("Migrating scene from format version {} to {}", version, version + 1);
+        value = migration(value)?;
+        version += 1;
+        value["version"] = serde_json::Value::from(version);
+    }
+    Ok(value)
+}
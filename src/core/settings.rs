@@ -1,38 +1,47 @@
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 
+use log::LevelFilter;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+use crate::audio::Bus;
+use crate::core::error::MagnusError;
+
+#[derive(Debug, PartialEq, Clone)]
 #[derive(Serialize, Deserialize)]
 pub struct Settings {
-    graphics: GraphicsSettings
+    graphics: GraphicsSettings,
+    #[serde(default)]
+    audio: AudioSettings,
+    #[serde(default)]
+    logging: LogSettings,
+    #[serde(default = "default_backends")]
+    backends: Vec<Backend>
 }
 
 impl Settings {
     pub fn new(name: &str, graphics_mode: GraphicsMode) -> Settings {
-        let mut filename = String::from(name);
-        filename.push_str(".json");
-        let filename_temp = filename.clone();
-        let settings = Settings { graphics: GraphicsSettings::new(None, Some((800, 600)), Some(graphics_mode)) };
-        let json = serde_json::to_string(&settings).unwrap();
-        match fs::write(filename_temp, json) {
-            Ok(_) => debug!("New settings file written to disk"),
-            _ => debug!("Error writing settings file to disk: {}", filename)
+        let settings = Settings {
+            graphics: GraphicsSettings::new(None, Some((800, 600)), Some(graphics_mode)),
+            audio: AudioSettings::default(),
+            logging: LogSettings::default(),
+            backends: default_backends()
+        };
+        //the defaults still work without a file, the next write gets another try
+        match settings.write(name) {
+            Ok(()) => debug!("New settings file written to disk"),
+            Err(e) => warn!("Error writing settings file {}.json to disk: {}", name, e)
         };
         settings
     }
 
-    pub fn read(name: &str) -> Result<Settings, String>  {
+    pub fn read(name: &str) -> Result<Settings, MagnusError>  {
         let mut filename = String::from(name);
         filename.push_str(".json");
-        let file = fs::read_to_string(filename);
-        match file {
-            Ok(i) => match serde_json::from_str(&i) {
-                Ok(j) => j,
-                _ => Err("Failed to deserialize settings".to_string())
-            },
-            _ => Err("Failed to read settings from disk".to_string())
-        }
+        let file = fs::read_to_string(filename)?;
+        Ok(serde_json::from_str(&file)?)
     }
 
     pub fn graphics(&self) -> GraphicsSettings {
@@ -42,6 +51,73 @@ impl Settings {
     pub fn set_graphics_mode(&mut self, mode: GraphicsMode) {
         self.graphics.mode = mode;
     }
+
+    pub fn set_size(&mut self, width: u32, height: u32) {
+        self.graphics.width = width;
+        self.graphics.height = height;
+    }
+
+    /**
+     * 0 off, 1 adaptive, 2 on, anything else turns it off like Window::set_vsync
+     **/
+    pub fn set_vsync(&mut self, interval: u8) {
+        self.graphics.vsync = if interval <= 2 { interval } else { 0 };
+    }
+
+    /**
+     * Switches the graphics mode to `backend`, OpenGL also takes its version
+     **/
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.graphics.mode = backend.mode();
+        if let Backend::OpenGL { major, minor } = backend {
+            self.graphics.gl_version = (major, minor);
+        }
+    }
+
+    /**
+     * Backends tried in order at startup when the configured one fails
+     **/
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+
+    pub fn set_backends(&mut self, backends: Vec<Backend>) {
+        self.backends = backends;
+    }
+
+    pub fn audio(&self) -> AudioSettings {
+        self.audio
+    }
+
+    pub fn set_audio(&mut self, audio: AudioSettings) {
+        self.audio = audio;
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        self.audio.set_volume(bus, volume);
+    }
+
+    pub fn logging(&self) -> &LogSettings {
+        &self.logging
+    }
+
+    pub fn logging_mut(&mut self) -> &mut LogSettings {
+        &mut self.logging
+    }
+
+    pub fn set_logging(&mut self, logging: LogSettings) {
+        self.logging = logging;
+    }
+
+    /**
+     * Writes the settings back to {name}.json so changes survive a restart
+     **/
+    pub fn write(&self, name: &str) -> Result<(), MagnusError> {
+        let mut filename = String::from(name);
+        filename.push_str(".json");
+        fs::write(filename, serde_json::to_string(self)?)?;
+        Ok(())
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -50,7 +126,13 @@ pub struct GraphicsSettings {
     width: u32,
     height: u32,
     mode: GraphicsMode,
-    vulkan_id: usize
+    vulkan_id: usize,
+    //swap interval passed to Window::set_vsync, off in files written before it existed
+    #[serde(default)]
+    vsync: u8,
+    //core profile version OpenGL windows ask for, 0.0 takes whatever the driver gives
+    #[serde(default)]
+    gl_version: (u32, u32)
 }
 
 impl GraphicsSettings {
@@ -71,7 +153,9 @@ impl GraphicsSettings {
             vulkan_id: match id {
                 Some(i) => i,
                 None => 0
-            }
+            },
+            vsync: 0,
+            gl_version: (0, 0)
         }
     }
 
@@ -94,6 +178,25 @@ impl GraphicsSettings {
     pub fn vulkan_id(&self) -> usize {
         self.vulkan_id
     }
+
+    pub fn vsync(&self) -> u8 {
+        self.vsync
+    }
+
+    pub fn gl_version(&self) -> (u32, u32) {
+        self.gl_version
+    }
+
+    /**
+     * The mode along with the OpenGL version it asks for
+     **/
+    pub fn backend(&self) -> Backend {
+        match self.mode {
+            GraphicsMode::OpenGL => Backend::OpenGL { major: self.gl_version.0, minor: self.gl_version.1 },
+            GraphicsMode::Vulkan => Backend::Vulkan,
+            GraphicsMode::DirectX => Backend::DirectX
+        }
+    }
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -103,3 +206,255 @@ pub enum GraphicsMode {
     OpenGL,
     Vulkan
 }
+
+/**
+ * A graphics mode the engine can try to start with, OpenGL at a core profile version
+ **/
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+pub enum Backend {
+    Vulkan,
+    /**
+     * 0.0 takes whatever version the driver gives
+     **/
+    OpenGL { major: u32, minor: u32 },
+    DirectX
+}
+
+impl Backend {
+    pub fn mode(&self) -> GraphicsMode {
+        match self {
+            Backend::Vulkan => GraphicsMode::Vulkan,
+            Backend::OpenGL { .. } => GraphicsMode::OpenGL,
+            Backend::DirectX => GraphicsMode::DirectX
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Vulkan => write!(f, "Vulkan"),
+            Backend::OpenGL { major: 0, .. } => write!(f, "OpenGL"),
+            Backend::OpenGL { major, minor } => write!(f, "OpenGL {}.{}", major, minor),
+            Backend::DirectX => write!(f, "DirectX")
+        }
+    }
+}
+
+/**
+ * Vulkan, then OpenGL 4.5, then OpenGL 3.3 which every renderer's shaders still compile on
+ **/
+pub fn default_backends() -> Vec<Backend> {
+    vec![Backend::Vulkan, Backend::OpenGL { major: 4, minor: 5 }, Backend::OpenGL { major: 3, minor: 3 }]
+}
+
+/**
+ * Bus volumes from 0 to 1, missing from settings files written before audio existed
+ * so they default to full volume
+ **/
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+pub struct AudioSettings {
+    master: f32,
+    music: f32,
+    sfx: f32
+}
+
+impl AudioSettings {
+    pub fn new(master: f32, music: f32, sfx: f32) -> AudioSettings {
+        AudioSettings {
+            master: master.clamp(0.0, 1.0),
+            music: music.clamp(0.0, 1.0),
+            sfx: sfx.clamp(0.0, 1.0)
+        }
+    }
+
+    pub fn volume(&self, bus: Bus) -> f32 {
+        match bus {
+            Bus::Master => self.master,
+            Bus::Music => self.music,
+            Bus::Sfx => self.sfx
+        }
+    }
+
+    pub fn set_volume(&mut self, bus: Bus, volume: f32) {
+        let volume = volume.clamp(0.0, 1.0);
+        match bus {
+            Bus::Master => self.master = volume,
+            Bus::Music => self.music = volume,
+            Bus::Sfx => self.sfx = volume
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> AudioSettings {
+        AudioSettings { master: 1.0, music: 1.0, sfx: 1.0 }
+    }
+}
+
+/**
+ * How much gets logged, least to most
+ **/
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace
+}
+
+impl LogLevel {
+    pub fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace
+        }
+    }
+}
+
+impl From<LevelFilter> for LogLevel {
+    fn from(filter: LevelFilter) -> LogLevel {
+        match filter {
+            LevelFilter::Off => LogLevel::Off,
+            LevelFilter::Error => LogLevel::Error,
+            LevelFilter::Warn => LogLevel::Warn,
+            LevelFilter::Info => LogLevel::Info,
+            LevelFilter::Debug => LogLevel::Debug,
+            LevelFilter::Trace => LogLevel::Trace
+        }
+    }
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Serialize, Deserialize)]
+pub enum LogFormat {
+    /**
+     * [date][target][level] message, like stderr without the colors
+     **/
+    Text,
+    /**
+     * One JSON object per line for log tooling
+     **/
+    Json
+}
+
+/**
+ * Where log messages go and which ones are kept, see core::logging
+ * Missing from settings files written before it existed, so every field falls back to the
+ * defaults: Trace to stderr and magnus_log.log in debug builds, Warn to the file only in release
+ **/
+#[derive(Debug, Eq, PartialEq, Clone)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    level: LogLevel,
+    //by module path, the longest matching prefix wins over `level`
+    modules: BTreeMap<String, LogLevel>,
+    file: Option<String>,
+    format: LogFormat,
+    //the file is rotated once it would grow past this many bytes, 0 never rotates
+    max_file_size: u64,
+    //rotated files kept as {file}.1 to {file}.{max_files}
+    max_files: u32,
+    stderr: bool,
+    console: bool
+}
+
+impl LogSettings {
+    pub fn level(&self) -> LogLevel {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: LogLevel) {
+        self.level = level;
+    }
+
+    pub fn modules(&self) -> &BTreeMap<String, LogLevel> {
+        &self.modules
+    }
+
+    /**
+     * Level for everything under `module`, e.g. "magnus::physics", None goes back to the default level
+     **/
+    pub fn set_module_level(&mut self, module: &str, level: Option<LogLevel>) {
+        match level {
+            Some(x) => self.modules.insert(module.to_string(), x),
+            None => self.modules.remove(module)
+        };
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /**
+     * None turns off the log file
+     **/
+    pub fn set_file(&mut self, file: Option<String>) {
+        self.file = file;
+    }
+
+    pub fn format(&self) -> LogFormat {
+        self.format
+    }
+
+    pub fn set_format(&mut self, format: LogFormat) {
+        self.format = format;
+    }
+
+    pub fn max_file_size(&self) -> u64 {
+        self.max_file_size
+    }
+
+    pub fn max_files(&self) -> u32 {
+        self.max_files
+    }
+
+    pub fn set_rotation(&mut self, max_file_size: u64, max_files: u32) {
+        self.max_file_size = max_file_size;
+        self.max_files = max_files;
+    }
+
+    pub fn stderr(&self) -> bool {
+        self.stderr
+    }
+
+    pub fn set_stderr(&mut self, stderr: bool) {
+        self.stderr = stderr;
+    }
+
+    /**
+     * Whether messages are kept for the in-game console
+     **/
+    pub fn console(&self) -> bool {
+        self.console
+    }
+
+    pub fn set_console(&mut self, console: bool) {
+        self.console = console;
+    }
+}
+
+impl Default for LogSettings {
+    fn default() -> LogSettings {
+        LogSettings {
+            level: if cfg!(debug_assertions) { LogLevel::Trace } else { LogLevel::Warn },
+            modules: BTreeMap::new(),
+            file: Some("magnus_log.log".to_string()),
+            format: LogFormat::Text,
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 3,
+            stderr: cfg!(debug_assertions),
+            console: true
+        }
+    }
+}
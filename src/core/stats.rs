@@ -0,0 +1,340 @@
+use std::collections::{ BTreeMap, HashMap, VecDeque };
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{ Mutex, OnceLock, PoisonError };
+use std::sync::atomic::{ AtomicU32, Ordering };
+
+use crate::events::event::EventType;
+
+/**
+ * Frames kept in the history
+ **/
+pub const FRAME_HISTORY: usize = 240;
+
+//draw calls issued since the render loop last took them
+static DRAW_CALLS: AtomicU32 = AtomicU32::new(0);
+static STATS: OnceLock<Mutex<FrameStats>> = OnceLock::new();
+
+/**
+ * Counts draw calls toward the frame being rendered, renderers call it as they draw
+ **/
+pub fn add_draw_calls(count: u32) {
+    DRAW_CALLS.fetch_add(count, Ordering::Relaxed);
+}
+
+/**
+ * Takes the draw calls counted since the last call
+ **/
+pub fn take_draw_calls() -> u32 {
+    DRAW_CALLS.swap(0, Ordering::Relaxed)
+}
+
+//GL_NVX_gpu_memory_info, both in KB
+const GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX: u32 = 0x9048;
+const GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX: u32 = 0x9049;
+
+/**
+ * Video memory in use in bytes on the current OpenGL context
+ * Only NVIDIA drivers report it, None elsewhere
+ **/
+pub fn gl_gpu_memory() -> Option<u64> {
+    unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        let supported = (0..count.max(0) as u32).any(|i| {
+            let name = gl::GetStringi(gl::EXTENSIONS, i);
+            !name.is_null() && std::ffi::CStr::from_ptr(name as *const _).to_bytes() == b"GL_NVX_gpu_memory_info"
+        });
+        if !supported {
+            return None;
+        }
+        let (mut total, mut available) = (0, 0);
+        gl::GetIntegerv(GPU_MEMORY_INFO_TOTAL_AVAILABLE_MEMORY_NVX, &mut total);
+        gl::GetIntegerv(GPU_MEMORY_INFO_CURRENT_AVAILABLE_VIDMEM_NVX, &mut available);
+        Some((total - available).max(0) as u64 * 1024)
+    }
+}
+
+/**
+ * What was measured for one rendered frame, times in seconds
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSample {
+    pub frame: u64,
+    pub frame_time: f32,
+    //the newest update tick when the frame was drawn
+    pub update_time: f32,
+    //None until the GPU timer has results, and without the profiling feature
+    pub gpu_time: Option<f32>,
+    pub draw_calls: u32,
+}
+
+/**
+ * What the render thread measured for one frame
+ * The renderer sends these to the update thread, which records them, so it never waits on the stats lock
+ **/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderSample {
+    pub frame_time: f32,
+    pub gpu_time: Option<f32>,
+    pub gpu_memory: Option<u64>,
+    pub draw_calls: u32,
+    pub assets: Vec<(&'static str, usize)>,
+}
+
+/**
+ * Engine wide performance numbers, the render loop records frames and the update thread
+ * records ticks and events
+ * Read it from anywhere with FrameStats::snapshot
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameStats {
+    history: VecDeque<FrameSample>,
+    capacity: usize,
+    frames: u64,
+    update_time: f32,
+    gpu_time: Option<f32>,
+    events: HashMap<EventType, u64>,
+    gpu_memory: Option<u64>,
+    assets: BTreeMap<String, usize>,
+}
+
+impl FrameStats {
+    pub fn new(capacity: Option<usize>) -> FrameStats {
+        let capacity = capacity.unwrap_or(FRAME_HISTORY).max(1);
+        FrameStats {
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            frames: 0,
+            update_time: 0.0,
+            gpu_time: None,
+            events: HashMap::new(),
+            gpu_memory: None,
+            assets: BTreeMap::new(),
+        }
+    }
+
+    /**
+     * The stats the engine records into
+     **/
+    pub fn global() -> &'static Mutex<FrameStats> {
+        STATS.get_or_init(|| Mutex::new(FrameStats::new(None)))
+    }
+
+    /**
+     * A copy of the global stats
+     **/
+    pub fn snapshot() -> FrameStats {
+        FrameStats::global().lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /**
+     * Ends a rendered frame, taking the draw calls counted since the last one
+     **/
+    pub fn record_frame(&mut self, dt: f32) {
+        self.push_frame(dt, take_draw_calls());
+    }
+
+    /**
+     * Ends a frame the render thread measured
+     **/
+    pub fn record_render(&mut self, sample: &RenderSample) {
+        self.gpu_time = sample.gpu_time;
+        self.gpu_memory = sample.gpu_memory;
+        for &(kind, count) in &sample.assets {
+            self.set_asset_count(kind, count);
+        }
+        self.push_frame(sample.frame_time, sample.draw_calls);
+    }
+
+    fn push_frame(&mut self, dt: f32, draw_calls: u32) {
+        self.frames += 1;
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back(FrameSample {
+            frame: self.frames,
+            frame_time: dt,
+            update_time: self.update_time,
+            gpu_time: self.gpu_time,
+            draw_calls,
+        });
+    }
+
+    pub fn record_update(&mut self, dt: f32) {
+        self.update_time = dt;
+    }
+
+    pub fn record_gpu_time(&mut self, dt: Option<f32>) {
+        self.gpu_time = dt;
+    }
+
+    pub fn count_event(&mut self, event: EventType) {
+        *self.events.entry(event).or_insert(0) += 1;
+    }
+
+    pub fn set_gpu_memory(&mut self, bytes: Option<u64>) {
+        self.gpu_memory = bytes;
+    }
+
+    pub fn set_asset_count(&mut self, kind: &str, count: usize) {
+        self.assets.insert(kind.to_string(), count);
+    }
+
+    #[inline]
+    pub fn history(&self) -> &VecDeque<FrameSample> {
+        &self.history
+    }
+
+    /**
+     * Frames rendered since the application started
+     **/
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn last(&self) -> f32 {
+        self.history.back().map(|x| x.frame_time).unwrap_or(0.0)
+    }
+
+    pub fn average(&self) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        self.history.iter().map(|x| x.frame_time).sum::<f32>() / self.history.len() as f32
+    }
+
+    pub fn min(&self) -> f32 {
+        self.history.iter().map(|x| x.frame_time).reduce(f32::min).unwrap_or(0.0)
+    }
+
+    pub fn max(&self) -> f32 {
+        self.history.iter().map(|x| x.frame_time).fold(0.0, f32::max)
+    }
+
+    /**
+     * Frame time 99% of the frames in the history were faster than
+     **/
+    pub fn p99(&self) -> f32 {
+        self.percentile(0.99)
+    }
+
+    /**
+     * `fraction` between 0 and 1, nearest rank
+     **/
+    pub fn percentile(&self, fraction: f32) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+        let mut times: Vec<f32> = self.history.iter().map(|x| x.frame_time).collect();
+        times.sort_by(f32::total_cmp);
+        let rank = (fraction.clamp(0.0, 1.0) * times.len() as f32).ceil() as usize;
+        times[rank.clamp(1, times.len()) - 1]
+    }
+
+    pub fn fps(&self) -> f32 {
+        let average = self.average();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+
+    pub fn update_time(&self) -> f32 {
+        self.update_time
+    }
+
+    pub fn gpu_time(&self) -> Option<f32> {
+        self.gpu_time
+    }
+
+    pub fn draw_calls(&self) -> u32 {
+        self.history.back().map(|x| x.draw_calls).unwrap_or(0)
+    }
+
+    /**
+     * Events handled since the application started, by type
+     **/
+    pub fn event_counts(&self) -> &HashMap<EventType, u64> {
+        &self.events
+    }
+
+    pub fn event_count(&self, event: EventType) -> u64 {
+        self.events.get(&event).copied().unwrap_or(0)
+    }
+
+    /**
+     * Video memory in use in bytes, None when the driver doesn't say
+     **/
+    pub fn gpu_memory(&self) -> Option<u64> {
+        self.gpu_memory
+    }
+
+    /**
+     * Loaded assets by kind
+     **/
+    pub fn assets(&self) -> &BTreeMap<String, usize> {
+        &self.assets
+    }
+
+    /**
+     * The frame history as CSV, one row per frame with times in milliseconds
+     * gpu_ms is empty for frames without a GPU time
+     **/
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("frame,frame_ms,update_ms,gpu_ms,draw_calls\n");
+        for x in &self.history {
+            let gpu = x.gpu_time.map(|t| (t * 1000.0).to_string()).unwrap_or_default();
+            let _ = writeln!(out, "{},{},{},{},{}", x.frame, x.frame_time * 1000.0, x.update_time * 1000.0, gpu, x.draw_calls);
+        }
+        out
+    }
+
+    /**
+     * Everything else as name,value rows, event counts as event_{type} and assets as assets_{kind}
+     **/
+    pub fn summary_csv(&self) -> String {
+        let mut out = String::from("name,value\n");
+        let _ = writeln!(out, "frames,{}", self.frames);
+        let _ = writeln!(out, "fps,{}", self.fps());
+        for (name, value) in [("min_ms", self.min()), ("max_ms", self.max()), ("average_ms", self.average()), ("p99_ms", self.p99()),
+            ("update_ms", self.update_time)] {
+            let _ = writeln!(out, "{},{}", name, value * 1000.0);
+        }
+        if let Some(x) = self.gpu_time {
+            let _ = writeln!(out, "gpu_ms,{}", x * 1000.0);
+        }
+        let _ = writeln!(out, "draw_calls,{}", self.draw_calls());
+        if let Some(x) = self.gpu_memory {
+            let _ = writeln!(out, "gpu_memory_bytes,{}", x);
+        }
+        let mut events: Vec<(String, u64)> = self.events.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+        events.sort();
+        for (name, count) in events {
+            let _ = writeln!(out, "event_{},{}", name, count);
+        }
+        for (kind, count) in &self.assets {
+            let _ = writeln!(out, "assets_{},{}", kind, count);
+        }
+        out
+    }
+
+    /**
+     * Writes to_csv to `path`
+     **/
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_csv())
+    }
+
+    /**
+     * Writes summary_csv to `path`
+     **/
+    pub fn write_summary_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.summary_csv())
+    }
+}
+
+impl Default for FrameStats {
+    fn default() -> FrameStats {
+        FrameStats::new(None)
+    }
+}
@@ -0,0 +1,76 @@
+use std::time::{ Duration, Instant };
+
+/**
+ * Tick rate physics and on_fixed_update run at, independent of the frame rate
+ **/
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+
+/**
+ * Accumulates real time and hands it out in fixed steps
+ * After a long stall only `max_steps` run and the rest of the backlog is dropped, otherwise
+ * a slow step makes the next frame slower still
+ **/
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+    last: Instant,
+    max_steps: u32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Option<f32>, max_steps: Option<u32>) -> FixedTimestep {
+        FixedTimestep {
+            step: step.unwrap_or(FIXED_TIMESTEP),
+            accumulator: 0.0,
+            last: Instant::now(),
+            max_steps: max_steps.unwrap_or(5),
+        }
+    }
+
+    #[inline]
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /**
+     * Adds the time since the last call and returns how many steps to run now
+     **/
+    pub fn advance(&mut self) -> u32 {
+        let now = Instant::now();
+        self.accumulator += now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step && steps < self.max_steps {
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+        if steps == self.max_steps {
+            self.accumulator = self.accumulator.min(self.step);
+        }
+        steps
+    }
+
+    /**
+     * How far between the last step and the next one the current time is, 0 to 1
+     * For interpolating what gets drawn between physics states
+     **/
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+
+    /**
+     * Time left until the next step is due
+     **/
+    pub fn until_next(&self) -> Duration {
+        Duration::from_secs_f32((self.step - self.accumulator).max(0.0))
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> FixedTimestep {
+        FixedTimestep::new(None, None)
+    }
+}
@@ -0,0 +1,399 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::core::scene::SceneError;
+use crate::math::{ Mat4, Quat, Vec3 };
+
+/**
+ * Local translation, rotation and scale of an object
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl_component!(Transform, "transform", 2);
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE }
+    }
+
+    pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Transform {
+        Transform { translation, rotation: rotation.normalize(), scale }
+    }
+
+    pub fn from_translation(translation: Vec3) -> Transform {
+        Transform { translation, ..Transform::identity() }
+    }
+
+    pub fn to_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    /**
+     * Decomposes an affine matrix into translation, rotation and scale
+     * Any shear in the matrix is lost
+     **/
+    pub fn from_matrix(m: &Mat4) -> Transform {
+        let (scale, rotation, translation) = m.to_scale_rotation_translation();
+        Transform { translation, rotation, scale }
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Transform {
+        Transform::identity()
+    }
+}
+
+/**
+ * Version 1 stored translation, rotation and scale as plain arrays
+ **/
+pub fn migrate_v1(mut value: serde_json::Value) -> Result<serde_json::Value, SceneError> {
+    fn to_object(value: &serde_json::Value, fields: &[&str]) -> Result<serde_json::Value, SceneError> {
+        let array = match value.as_array() {
+            Some(x) if x.len() == fields.len() => x,
+            _ => return Err(SceneError::InvalidData(format!("transform v1 expected {} element array", fields.len())))
+        };
+        let mut object = serde_json::Map::new();
+        for (field, v) in fields.iter().zip(array) {
+            object.insert(field.to_string(), v.clone());
+        }
+        Ok(serde_json::Value::Object(object))
+    }
+
+    let xyz: &[&str] = &["x", "y", "z"];
+    let xyzw: &[&str] = &["x", "y", "z", "w"];
+    for (key, fields) in &[("translation", xyz), ("rotation", xyzw), ("scale", xyz)] {
+        let converted = to_object(&value[*key], fields)?;
+        value[*key] = converted;
+    }
+    Ok(value)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TransformError {
+    InvalidId,
+    Cycle,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            TransformError::InvalidId => "Invalid Transform Id",
+            TransformError::Cycle => "Transform Would Become Its Own Ancestor"
+        })
+    }
+}
+
+impl Error for TransformError {}
+
+/**
+ * Handle to a node in a TransformHierarchy
+ * Handles to destroyed nodes are detected through the generation and rejected
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransformId {
+    index: u32,
+    generation: u32,
+}
+
+struct TransformNode {
+    local: Transform,
+    world: Mat4,
+    parent: Option<TransformId>,
+    children: Vec<TransformId>,
+    dirty: bool,
+    alive: bool,
+    generation: u32,
+}
+
+/**
+ * Parent/child tree of transforms with cached world matrices
+ * Changing a node's local transform or parent marks it and its descendants dirty, world
+ * matrices are only recomputed for dirty nodes, either lazily through world() or all at
+ * once through update()
+ **/
+pub struct TransformHierarchy {
+    nodes: Vec<TransformNode>,
+    free: Vec<u32>,
+    roots: Vec<TransformId>,
+}
+
+impl TransformHierarchy {
+    pub fn new() -> TransformHierarchy {
+        TransformHierarchy { nodes: Vec::new(), free: Vec::new(), roots: Vec::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> TransformHierarchy {
+        TransformHierarchy { nodes: Vec::with_capacity(capacity), free: Vec::new(), roots: Vec::new() }
+    }
+
+    pub fn create(&mut self, local: Transform, parent: Option<TransformId>) -> Result<TransformId, TransformError> {
+        if let Some(p) = parent {
+            self.node(p)?;
+        }
+
+        let id = match self.free.pop() {
+            Some(index) => {
+                let node = &mut self.nodes[index as usize];
+                node.generation += 1;
+                node.alive = true;
+                node.local = local;
+                node.parent = parent;
+                node.dirty = true;
+                TransformId { index, generation: node.generation }
+            },
+            None => {
+                self.nodes.push(TransformNode {
+                    local,
+                    world: Mat4::IDENTITY,
+                    parent,
+                    children: Vec::new(),
+                    dirty: true,
+                    alive: true,
+                    generation: 0
+                });
+                TransformId { index: self.nodes.len() as u32 - 1, generation: 0 }
+            }
+        };
+
+        match parent {
+            Some(p) => self.nodes[p.index as usize].children.push(id),
+            None => self.roots.push(id)
+        }
+        Ok(id)
+    }
+
+    /**
+     * Destroys a node and everything below it
+     **/
+    pub fn destroy(&mut self, id: TransformId) -> Result<(), TransformError> {
+        self.node(id)?;
+        self.detach(id);
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let node = &mut self.nodes[current.index as usize];
+            stack.append(&mut node.children);
+            node.alive = false;
+            node.parent = None;
+            self.free.push(current.index);
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, id: TransformId) -> bool {
+        self.node(id).is_ok()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn local(&self, id: TransformId) -> Result<&Transform, TransformError> {
+        Ok(&self.node(id)?.local)
+    }
+
+    pub fn set_local(&mut self, id: TransformId, local: Transform) -> Result<(), TransformError> {
+        self.node(id)?;
+        self.nodes[id.index as usize].local = local;
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    pub fn set_translation(&mut self, id: TransformId, translation: Vec3) -> Result<(), TransformError> {
+        let mut local = *self.local(id)?;
+        local.translation = translation;
+        self.set_local(id, local)
+    }
+
+    pub fn set_rotation(&mut self, id: TransformId, rotation: Quat) -> Result<(), TransformError> {
+        let mut local = *self.local(id)?;
+        local.rotation = rotation.normalize();
+        self.set_local(id, local)
+    }
+
+    pub fn set_scale(&mut self, id: TransformId, scale: Vec3) -> Result<(), TransformError> {
+        let mut local = *self.local(id)?;
+        local.scale = scale;
+        self.set_local(id, local)
+    }
+
+    pub fn parent(&self, id: TransformId) -> Result<Option<TransformId>, TransformError> {
+        Ok(self.node(id)?.parent)
+    }
+
+    pub fn children(&self, id: TransformId) -> Result<&[TransformId], TransformError> {
+        Ok(&self.node(id)?.children)
+    }
+
+    pub fn roots(&self) -> &[TransformId] {
+        &self.roots
+    }
+
+    /**
+     * Moves `id` under `parent` (or to the root when None)
+     * When `keep_world` is set the local transform is rewritten so the node stays where it is in world space
+     **/
+    pub fn set_parent(&mut self, id: TransformId, parent: Option<TransformId>, keep_world: bool)
+        -> Result<(), TransformError> {
+        self.node(id)?;
+        if let Some(p) = parent {
+            self.node(p)?;
+            let mut current = Some(p);
+            while let Some(x) = current {
+                if x == id {
+                    return Err(TransformError::Cycle);
+                }
+                current = self.nodes[x.index as usize].parent;
+            }
+        }
+
+        let world = self.world(id)?;
+        self.detach(id);
+        self.nodes[id.index as usize].parent = parent;
+        match parent {
+            Some(p) => self.nodes[p.index as usize].children.push(id),
+            None => self.roots.push(id)
+        }
+
+        if keep_world {
+            let local = match parent {
+                Some(p) => self.world(p)?.inverse().unwrap_or(Mat4::IDENTITY) * world,
+                None => world
+            };
+            self.nodes[id.index as usize].local = Transform::from_matrix(&local);
+        }
+        self.mark_dirty(id);
+        Ok(())
+    }
+
+    /**
+     * World matrix of a node, recomputing it and any dirty ancestors if needed
+     **/
+    pub fn world(&mut self, id: TransformId) -> Result<Mat4, TransformError> {
+        self.node(id)?;
+        let mut chain = Vec::new();
+        let mut current = Some(id);
+        while let Some(x) = current {
+            let node = &self.nodes[x.index as usize];
+            if !node.dirty {
+                break;
+            }
+            chain.push(x);
+            current = node.parent;
+        }
+
+        while let Some(x) = chain.pop() {
+            self.recompute(x);
+        }
+        Ok(self.nodes[id.index as usize].world)
+    }
+
+    /**
+     * World matrix of a node if it is up to date
+     **/
+    pub fn cached_world(&self, id: TransformId) -> Option<&Mat4> {
+        match self.node(id) {
+            Ok(node) if !node.dirty => Some(&node.world),
+            _ => None
+        }
+    }
+
+    /**
+     * Recomputes every dirty world matrix, parents before children
+     **/
+    pub fn update(&mut self) {
+        let mut stack: Vec<TransformId> = self.roots.iter().rev().cloned().collect();
+        while let Some(id) = stack.pop() {
+            let node = &self.nodes[id.index as usize];
+            if node.dirty {
+                self.recompute(id);
+            }
+            stack.extend(self.nodes[id.index as usize].children.iter().rev());
+        }
+    }
+
+    pub fn world_position(&mut self, id: TransformId) -> Result<Vec3, TransformError> {
+        Ok(self.world(id)?.translation())
+    }
+
+    pub fn world_transform(&mut self, id: TransformId) -> Result<Transform, TransformError> {
+        Ok(Transform::from_matrix(&self.world(id)?))
+    }
+
+    pub fn local_to_world_point(&mut self, id: TransformId, point: Vec3) -> Result<Vec3, TransformError> {
+        Ok(self.world(id)?.transform_point3(point))
+    }
+
+    pub fn world_to_local_point(&mut self, id: TransformId, point: Vec3) -> Result<Vec3, TransformError> {
+        Ok(self.world_inverse(id)?.transform_point3(point))
+    }
+
+    pub fn local_to_world_direction(&mut self, id: TransformId, dir: Vec3) -> Result<Vec3, TransformError> {
+        Ok(self.world(id)?.transform_vector3(dir))
+    }
+
+    pub fn world_to_local_direction(&mut self, id: TransformId, dir: Vec3) -> Result<Vec3, TransformError> {
+        Ok(self.world_inverse(id)?.transform_vector3(dir))
+    }
+
+    fn world_inverse(&mut self, id: TransformId) -> Result<Mat4, TransformError> {
+        Ok(self.world(id)?.inverse().unwrap_or(Mat4::IDENTITY))
+    }
+
+    fn node(&self, id: TransformId) -> Result<&TransformNode, TransformError> {
+        match self.nodes.get(id.index as usize) {
+            Some(node) if node.alive && node.generation == id.generation => Ok(node),
+            _ => Err(TransformError::InvalidId)
+        }
+    }
+
+    fn detach(&mut self, id: TransformId) {
+        match self.nodes[id.index as usize].parent {
+            Some(p) => self.nodes[p.index as usize].children.retain(|&x| x != id),
+            None => self.roots.retain(|&x| x != id)
+        }
+    }
+
+    //a dirty node always has dirty descendants, so we can stop at the first one that is already dirty
+    fn mark_dirty(&mut self, id: TransformId) {
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let node = &mut self.nodes[current.index as usize];
+            if node.dirty && current != id {
+                continue;
+            }
+            node.dirty = true;
+            stack.extend(node.children.iter());
+        }
+    }
+
+    fn recompute(&mut self, id: TransformId) {
+        let node = &self.nodes[id.index as usize];
+        let local = node.local.to_matrix();
+        let world = match node.parent {
+            Some(p) => self.nodes[p.index as usize].world * local,
+            None => local
+        };
+        let node = &mut self.nodes[id.index as usize];
+        node.world = world;
+        node.dirty = false;
+    }
+}
+
+impl Default for TransformHierarchy {
+    fn default() -> TransformHierarchy {
+        TransformHierarchy::new()
+    }
+}
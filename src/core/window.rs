@@ -9,7 +9,10 @@ use crate::events::event::{ EventType, EventData, Event };
 use crate::events::key_events::*;
 use crate::events::mouse_events::*;
 use crate::events::window_events::*;
+use crate::events::render_events::*;
+use crate::core::error::MagnusError;
 use crate::core::graphics;
+use crate::core::graphics::DeviceCreationError;
 use crate::core::graphics::context::ContextLimiter;
 use crate::core::graphics::opengl::OpenGLContext;
 use crate::core::graphics::vulkan::VulkanContext;
@@ -22,11 +25,13 @@ pub struct WindowProps {
     title: String,
     width: u32,
     height: u32,
-    graphics_mode: GraphicsMode
+    graphics_mode: GraphicsMode,
+    //core profile version for OpenGL windows, the driver picks when None
+    gl_version: Option<(u32, u32)>
 }
 
 impl WindowProps {
-    pub fn new(title: String, size: Option<(u32, u32)>, graphics_mode: GraphicsMode) -> WindowProps {
+    pub fn new(title: String, size: Option<(u32, u32)>, graphics_mode: GraphicsMode, gl_version: Option<(u32, u32)>) -> WindowProps {
         WindowProps {
             title,
             width: match size {
@@ -37,7 +42,8 @@ impl WindowProps {
                 Some((_w, h)) => h,
                 None => 600
             },
-            graphics_mode
+            graphics_mode,
+            gl_version
         }
     }
 }
@@ -57,24 +63,41 @@ unsafe impl<T: ContextLimiter> std::marker::Sync for Window<T> {}
 
 static mut GLFW_S: Option<glfw::Glfw> = None;
 
+/**
+ * Glfw is initialized once for all windows, its errors are logged instead of panicking
+ * so failures come back from Window::new
+ **/
+fn init_glfw() -> Result<(), MagnusError> {
+    unsafe {
+        if GLFW_S.is_none() {
+            GLFW_S = Some(glfw::init(glfw::LOG_ERRORS)?);
+        }
+    }
+    Ok(())
+}
+
 impl Window<OpenGLContext> {
-    pub fn new(props: WindowProps) -> Window<OpenGLContext> {
+    pub fn new(props: WindowProps) -> Result<Window<OpenGLContext>, MagnusError> {
         use glfw::Context;
 
-        unsafe {
-            if GLFW_S.is_none() {
-                GLFW_S = Some(glfw::init(glfw::FAIL_ON_ERRORS).expect("Failed to init GLFW"));
-            }
-        }
+        init_glfw()?;
 
         debug!("Creating Window: {}", props.title);
         let mut window: glfw::Window;
         let events: Receiver<(f64, glfw::WindowEvent)>;
         let x: (glfw::Window, Receiver<(f64, glfw::WindowEvent)>);
         unsafe {
+            //a Vulkan window that failed before this one leaves NoApi set
+            GLFW_S.unwrap().default_window_hints();
+            if let Some((major, minor)) = props.gl_version {
+                debug!("Requesting OpenGL {}.{} core profile", major, minor);
+                GLFW_S.unwrap().window_hint(glfw::WindowHint::ContextVersion(major, minor));
+                GLFW_S.unwrap().window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
+                GLFW_S.unwrap().window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
+            }
             debug!("Creating glfw window");
             x = GLFW_S.unwrap().create_window(props.width, props.height, props.title.as_str(),
-            glfw::WindowMode::Windowed).expect("Failed to create GLFW Window");
+            glfw::WindowMode::Windowed).ok_or_else(|| MagnusError::WindowCreation(props.title.clone()))?;
         }
 
         window = x.0;
@@ -84,14 +107,14 @@ impl Window<OpenGLContext> {
 
         let context = graphics::context::Context::<OpenGLContext>::new(window);
 
-        Window {
+        Ok(Window {
             props,
             vsync: 0,
             event_receiver: events,
             context,
             slots: vec![],
             should_close: false
-        }
+        })
     }
 
     pub fn get_context(&mut self) -> &mut graphics::context::Context<OpenGLContext> {
@@ -176,6 +199,11 @@ impl Window<OpenGLContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::Char(c) => {
+                    let x = TextInputEvent::new(format!("Text input {}", c), c as u32, 0);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 glfw::WindowEvent::Focus(focus) => {
                     let x = WindowFocusEvent::new("Window Focused".to_string(), focus);
                     let res = self.emit(SyncData::Sig(x));
@@ -191,6 +219,16 @@ impl Window<OpenGLContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::FramebufferSize(x, y) => {
+                    let x = RenderFramebufferResizeEvent::new(format!("Framebuffer Resized x: {}, y: {}", x, y), x as f32, y as f32);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
+                glfw::WindowEvent::ContentScale(x, y) => {
+                    let x = RenderContentScaleResizeEvent::new(format!("Content Scale x: {}, y: {}", x, y), x, y);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 _ => {
                     if event == glfw::WindowEvent::Close {
                         let x = WindowCloseEvent::new("Window Should Close".to_string());
@@ -207,14 +245,10 @@ impl Window<OpenGLContext> {
 }
 
 impl Window<VulkanContext> {
-    pub fn new(props: WindowProps, id: usize) -> Window<VulkanContext> {
+    pub fn new(props: WindowProps, id: usize) -> Result<Window<VulkanContext>, MagnusError> {
         use vulkano::instance::Instance;
 
-        unsafe {
-            if GLFW_S.is_none() {
-                GLFW_S = Some(glfw::init(glfw::FAIL_ON_ERRORS).expect("Failed to init GLFW"));
-            }
-        }
+        init_glfw()?;
 
         debug!("Creating Window: {}", props.title);
         let mut window: glfw::Window;
@@ -225,24 +259,24 @@ impl Window<VulkanContext> {
             GLFW_S.unwrap().window_hint(glfw::WindowHint::ClientApi(glfw::ClientApiHint::NoApi));
             debug!("Creating glfw window");
             x = GLFW_S.unwrap().create_window(props.width, props.height, props.title.as_str(),
-            glfw::WindowMode::Windowed).expect("Failed to create GLFW Window");
+            glfw::WindowMode::Windowed).ok_or_else(|| MagnusError::WindowCreation(props.title.clone()))?;
         }
 
         window = x.0;
         events = x.1;
         window.set_all_polling(true);
         let ext = &vulkano_glfw::get_required_instance_extensions(&window.glfw)
-            .expect("Error getting required vulkan instance extensions");
-        let instance = Instance::new(None, ext, None).expect("Failed to create vulkan instance");
+            .map_err(DeviceCreationError::VulkanUnsupported)?;
+        let instance = Instance::new(None, ext, None).map_err(DeviceCreationError::from)?;
 
-        Window {
+        Ok(Window {
             props,
             vsync: 0,
             event_receiver: events,
-            context: graphics::context::Context::<VulkanContext>::new(window, instance, id),
+            context: graphics::context::Context::<VulkanContext>::new(window, instance, id)?,
             slots: vec![],
             should_close: false
-        }
+        })
     }
 
     pub fn get_context(&mut self) -> &mut graphics::context::Context<VulkanContext> {
@@ -327,6 +361,11 @@ impl Window<VulkanContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::Char(c) => {
+                    let x = TextInputEvent::new(format!("Text input {}", c), c as u32, 0);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 glfw::WindowEvent::Focus(focus) => {
                     let x = WindowFocusEvent::new("Window Focused".to_string(), focus);
                     let res = self.emit(SyncData::Sig(x));
@@ -342,6 +381,16 @@ impl Window<VulkanContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::FramebufferSize(x, y) => {
+                    let x = RenderFramebufferResizeEvent::new(format!("Framebuffer Resized x: {}, y: {}", x, y), x as f32, y as f32);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
+                glfw::WindowEvent::ContentScale(x, y) => {
+                    let x = RenderContentScaleResizeEvent::new(format!("Content Scale x: {}, y: {}", x, y), x, y);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 _ => {
                     if event == glfw::WindowEvent::Close {
                         let x = WindowCloseEvent::new("Window Should Close".to_string());
@@ -361,13 +410,9 @@ impl Window<VulkanContext> {
 
 #[cfg(windows)]
 impl Window<DirectXContext> {
-    pub fn new(props: WindowProps) -> Window<DirectXContext> {
+    pub fn new(props: WindowProps) -> Result<Window<DirectXContext>, MagnusError> {
 
-        unsafe {
-            if GLFW_S.is_none() {
-                GLFW_S = Some(glfw::init(glfw::FAIL_ON_ERRORS).expect("Failed to init GLFW"));
-            }
-        }
+        init_glfw()?;
 
         debug!("Creating Window: {}", props.title);
         let mut window: glfw::Window;
@@ -378,7 +423,7 @@ impl Window<DirectXContext> {
             GLFW_S.unwrap().window_hint(glfw::WindowHint::ClientApi(glfw::ClientApiHint::NoApi));
             debug!("Creating glfw window");
             x = GLFW_S.unwrap().create_window(props.width, props.height, props.title.as_str(),
-            glfw::WindowMode::Windowed).expect("Failed to create GLFW Window");
+            glfw::WindowMode::Windowed).ok_or_else(|| MagnusError::WindowCreation(props.title.clone()))?;
         }
 
         window = x.0;
@@ -387,14 +432,14 @@ impl Window<DirectXContext> {
 
         let context = graphics::context::Context::<DirectXContext>::new(window);
 
-        Window {
+        Ok(Window {
             props,
             vsync: 0,
             event_receiver: events,
             context,
             slots: vec![],
             should_close: false
-        }
+        })
     }
 
     pub fn get_context(&mut self) -> &mut graphics::context::Context<DirectXContext> {
@@ -479,6 +524,11 @@ impl Window<DirectXContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::Char(c) => {
+                    let x = TextInputEvent::new(format!("Text input {}", c), c as u32, 0);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 glfw::WindowEvent::Focus(focus) => {
                     let x = WindowFocusEvent::new("Window Focused".to_string(), focus);
                     let res = self.emit(SyncData::Sig(x));
@@ -494,6 +544,16 @@ impl Window<DirectXContext> {
                     let res = self.emit(SyncData::Sig(x));
                     debug!("Emit Result: {:?}", res);
                 },
+                glfw::WindowEvent::FramebufferSize(x, y) => {
+                    let x = RenderFramebufferResizeEvent::new(format!("Framebuffer Resized x: {}, y: {}", x, y), x as f32, y as f32);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
+                glfw::WindowEvent::ContentScale(x, y) => {
+                    let x = RenderContentScaleResizeEvent::new(format!("Content Scale x: {}, y: {}", x, y), x, y);
+                    let res = self.emit(SyncData::Sig(x));
+                    debug!("Emit Result: {:?}", res);
+                },
                 _ => {
                     if event == glfw::WindowEvent::Close {
                         let x = WindowCloseEvent::new("Window Should Close".to_string());
@@ -631,7 +691,7 @@ impl<T: ContextLimiter> SyncSignal<WindowRefreshEvent, EventData> for Window<T>
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::WindowRefresh && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -658,7 +718,7 @@ impl<T: ContextLimiter> SyncSignal<WindowIconifyEvent, EventData> for Window<T>
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::WindowIconify && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -685,7 +745,7 @@ impl<T: ContextLimiter> SyncSignal<WindowMaximizeEvent, EventData> for Window<T>
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::WindowMaximize && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -712,7 +772,7 @@ impl<T: ContextLimiter> SyncSignal<KeyPressedEvent, EventData> for Window<T> {
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::KeyPressed && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -739,7 +799,34 @@ impl<T: ContextLimiter> SyncSignal<KeyReleasedEvent, EventData> for Window<T> {
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::KeyReleased && !handled {
+                handled = match slot.0.write() {
+                    Ok(mut x) => x.consume(&data),
+                    _ => {
+                        debug!("Unable to lock slot for signal consumption");
+                        false
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: ContextLimiter> SyncSignal<TextInputEvent, EventData> for Window<T> {
+    fn connect<TextInputEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::TextInput));
+    }
+
+    fn emit(&self, event: SyncData<TextInputEvent>) -> Result<(), &str> {
+        let ev = event.sig();
+        let data = SyncData::Sig(match ev.get_data() {
+            Some(x) => x,
+            None => return Err("No data in event!")
+        });
+        let mut handled = ev.get_handled();
+        for slot in &self.slots {
+            if slot.1 == EventType::TextInput && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -766,7 +853,7 @@ impl<T: ContextLimiter> SyncSignal<MouseButtonPressedEvent, EventData> for Windo
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::MouseButtonPressed && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -793,7 +880,7 @@ impl<T: ContextLimiter> SyncSignal<MouseButtonReleasedEvent, EventData> for Wind
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::MouseButtonReleased && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -820,7 +907,7 @@ impl<T: ContextLimiter> SyncSignal<MouseEnteredEvent, EventData> for Window<T> {
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::MouseEntered && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -847,7 +934,7 @@ impl<T: ContextLimiter> SyncSignal<MouseMovedEvent, EventData> for Window<T> {
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::MouseMoved && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -874,7 +961,35 @@ impl<T: ContextLimiter> SyncSignal<MouseScrolledEvent, EventData> for Window<T>
         });
         let mut handled = ev.get_handled();
         for slot in &self.slots {
-            if slot.1 == EventType::WindowMoved && !handled {
+            if slot.1 == EventType::MouseScrolled && !handled {
+                handled = match slot.0.write() {
+                    Ok(mut x) => x.consume(&data),
+                    _ => {
+                        debug!("Unable to lock slot for signal consumption");
+                        false
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+
+impl<T: ContextLimiter> SyncSignal<RenderFramebufferResizeEvent, EventData> for Window<T> {
+    fn connect<RenderFramebufferResizeEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::RenderFramebufferResize));
+    }
+
+    fn emit(&self, event: SyncData<RenderFramebufferResizeEvent>) -> Result<(), &str> {
+        let ev = event.sig();
+        let data = SyncData::Sig(match ev.get_data() {
+            Some(x) => x,
+            None => return Err("No data in event!")
+        });
+        let mut handled = ev.get_handled();
+        for slot in &self.slots {
+            if slot.1 == EventType::RenderFramebufferResize && !handled {
                 handled = match slot.0.write() {
                     Ok(mut x) => x.consume(&data),
                     _ => {
@@ -888,3 +1003,29 @@ impl<T: ContextLimiter> SyncSignal<MouseScrolledEvent, EventData> for Window<T>
     }
 }
 
+impl<T: ContextLimiter> SyncSignal<RenderContentScaleResizeEvent, EventData> for Window<T> {
+    fn connect<RenderContentScaleResizeEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::RenderContentScaleResize));
+    }
+
+    fn emit(&self, event: SyncData<RenderContentScaleResizeEvent>) -> Result<(), &str> {
+        let ev = event.sig();
+        let data = SyncData::Sig(match ev.get_data() {
+            Some(x) => x,
+            None => return Err("No data in event!")
+        });
+        let mut handled = ev.get_handled();
+        for slot in &self.slots {
+            if slot.1 == EventType::RenderContentScaleResize && !handled {
+                handled = match slot.0.write() {
+                    Ok(mut x) => x.consume(&data),
+                    _ => {
+                        debug!("Unable to lock slot for signal consumption");
+                        false
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+use crate::events::event::*;
+use crate::events::event::EventType::AnimationKeyframe;
+use crate::events::event::EventCategory::EventAnimation;
+use crate::events::event::EventData::StringD;
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct AnimationKeyframeEvent {
+    event_type: EventType,
+    category_flags: u32,
+    msg: String,
+    data: EventData,
+    handled: bool
+}
+
+impl AnimationKeyframeEvent {
+    /**
+     * `name` is the keyframe event's name, the animator it came from is in
+     * AnimationWorld::events for the same update
+     **/
+    pub fn new(message: String, name: String) -> AnimationKeyframeEvent {
+        AnimationKeyframeEvent { event_type: AnimationKeyframe,
+        category_flags: EventAnimation as u32,
+        msg: message, data: StringD(name, AnimationKeyframe), handled: false }
+    }
+}
+
+unsafe impl std::marker::Send for AnimationKeyframeEvent {}
+unsafe impl std::marker::Sync for AnimationKeyframeEvent {}
+
+impl std::fmt::Display for AnimationKeyframeEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AnimationKeyframeEvent: (event_type: {}, category_flags: {},
+        msg: {}, data: {}, handled: {})",
+        self.event_type, self.category_flags, self.msg, self.data, self.handled)
+    }
+}
+
+impl Event for AnimationKeyframeEvent {
+
+    fn get_event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    fn get_category_flags(&self) -> u32 {
+        self.category_flags
+    }
+
+    fn get_msg(&self) -> &String {
+        &(self.msg)
+    }
+
+    fn get_data(&self) -> Option<& EventData> {
+        Some(& self.data)
+    }
+
+    fn get_handled(&self) -> bool {
+        self.handled
+    }
+
+    fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
@@ -1,5 +1,7 @@
+use crate::math::Vec2;
+
 #[derive(Debug)]
-#[derive(PartialEq, Eq)]
+#[derive(PartialEq, Eq, Hash)]
 #[derive(Clone, Copy)]
 pub enum EventType {
     None = 0,
@@ -8,7 +10,9 @@ pub enum EventType {
     RenderFramebufferResize, RenderContentScaleResize,
     AppTick, AppUpdate, AppRender, AppFileDropped,
     KeyPressed, KeyReleased, TextInput,
-    MouseButtonPressed, MouseButtonReleased, MouseEntered, MouseMoved, MouseScrolled
+    MouseButtonPressed, MouseButtonReleased, MouseEntered, MouseMoved, MouseScrolled,
+    ContactBegin, ContactEnd,
+    AnimationKeyframe
 }
 
 impl std::fmt::Display for EventType {
@@ -35,7 +39,10 @@ impl std::fmt::Display for EventType {
             EventType::MouseButtonReleased => write!(f, "MouseButtonReleased"),
             EventType::MouseEntered => write!(f, "MouseEntered"),
             EventType::MouseMoved => write!(f, "MouseMoved"),
-            EventType::MouseScrolled => write!(f, "MouseScrolled")
+            EventType::MouseScrolled => write!(f, "MouseScrolled"),
+            EventType::ContactBegin => write!(f, "ContactBegin"),
+            EventType::ContactEnd => write!(f, "ContactEnd"),
+            EventType::AnimationKeyframe => write!(f, "AnimationKeyframe")
         }
     }
 }
@@ -48,7 +55,9 @@ pub enum EventCategory {
     EventInput          = BIT!(1),
     EventKeyboard       = BIT!(2),
     EventMouse          = BIT!(3),
-    EventMouseButton    = BIT!(4)
+    EventMouseButton    = BIT!(4),
+    EventPhysics        = BIT!(5),
+    EventAnimation      = BIT!(6)
 }
 
 pub trait Event : std::fmt::Display {
@@ -88,7 +97,7 @@ impl<'a> EventDispatcher<'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[derive(PartialEq)]
 pub enum EventData {
     Bool(bool, EventType),
@@ -148,6 +157,16 @@ impl EventData {
             EventData::PathBufs(_, kind) => kind
         }
     }
+
+    /**
+     * The pair in F32p data (window size, mouse position, etc) as a Vec2
+     **/
+    pub fn as_vec2(&self) -> Option<Vec2> {
+        match self {
+            EventData::F32p(x, y, _) => Some(Vec2::new(*x, *y)),
+            _ => None
+        }
+    }
 }
 
 unsafe impl std::marker::Send for EventData {}
@@ -4,3 +4,5 @@ pub mod render_events;
 pub mod application_events;
 pub mod key_events;
 pub mod mouse_events;
+pub mod physics_events;
+pub mod animation_events;
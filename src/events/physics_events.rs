@@ -0,0 +1,122 @@
+use crate::events::event::*;
+use crate::events::event::EventType::{ContactBegin, ContactEnd};
+use crate::events::event::EventCategory::EventPhysics;
+use crate::events::event::EventData::U64p;
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct ContactBeginEvent {
+    event_type: EventType,
+    category_flags: u32,
+    msg: String,
+    data: EventData,
+    handled: bool
+}
+
+impl ContactBeginEvent {
+    /**
+     * `a` and `b` are the collider handles in bits (ColliderHandle::to_bits), lower handle first
+     **/
+    pub fn new(message: String, a: u64, b: u64) -> ContactBeginEvent {
+        ContactBeginEvent { event_type: ContactBegin,
+        category_flags: EventPhysics as u32,
+        msg: message, data: U64p(a, b, ContactBegin), handled: false }
+    }
+}
+
+unsafe impl std::marker::Send for ContactBeginEvent {}
+unsafe impl std::marker::Sync for ContactBeginEvent {}
+
+impl std::fmt::Display for ContactBeginEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContactBeginEvent: (event_type: {}, category_flags: {},
+        msg: {}, data: {}, handled: {})",
+        self.event_type, self.category_flags, self.msg, self.data, self.handled)
+    }
+}
+
+impl Event for ContactBeginEvent {
+
+    fn get_event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    fn get_category_flags(&self) -> u32 {
+        self.category_flags
+    }
+
+    fn get_msg(&self) -> &String {
+        &(self.msg)
+    }
+
+    fn get_data(&self) -> Option<& EventData> {
+        Some(& self.data)
+    }
+
+    fn get_handled(&self) -> bool {
+        self.handled
+    }
+
+    fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
+
+#[derive(Debug)]
+#[derive(PartialEq)]
+pub struct ContactEndEvent {
+    event_type: EventType,
+    category_flags: u32,
+    msg: String,
+    data: EventData,
+    handled: bool
+}
+
+impl ContactEndEvent {
+    /**
+     * `a` and `b` are the collider handles in bits (ColliderHandle::to_bits), lower handle first
+     **/
+    pub fn new(message: String, a: u64, b: u64) -> ContactEndEvent {
+        ContactEndEvent { event_type: ContactEnd,
+        category_flags: EventPhysics as u32,
+        msg: message, data: U64p(a, b, ContactEnd), handled: false }
+    }
+}
+
+unsafe impl std::marker::Send for ContactEndEvent {}
+unsafe impl std::marker::Sync for ContactEndEvent {}
+
+impl std::fmt::Display for ContactEndEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ContactEndEvent: (event_type: {}, category_flags: {},
+        msg: {}, data: {}, handled: {})",
+        self.event_type, self.category_flags, self.msg, self.data, self.handled)
+    }
+}
+
+impl Event for ContactEndEvent {
+
+    fn get_event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    fn get_category_flags(&self) -> u32 {
+        self.category_flags
+    }
+
+    fn get_msg(&self) -> &String {
+        &(self.msg)
+    }
+
+    fn get_data(&self) -> Option<& EventData> {
+        Some(& self.data)
+    }
+
+    fn get_handled(&self) -> bool {
+        self.handled
+    }
+
+    fn set_handled(&mut self, handled: bool) {
+        self.handled = handled;
+    }
+}
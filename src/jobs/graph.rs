@@ -0,0 +1,479 @@
+use std::any::{ type_name, TypeId };
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use crate::core::crash;
+use crate::jobs::JobError;
+use crate::jobs::pool::{ panic_message, JobSystem, Scope };
+
+/**
+ * Something a task touches, named after its type
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Resource {
+    id: TypeId,
+    name: &'static str,
+}
+
+impl Resource {
+    pub fn of<T: ?Sized + 'static>() -> Resource {
+        Resource { id: TypeId::of::<T>(), name: type_name::<T>() }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/**
+ * What a task reads and writes, any number of tasks can read a resource at once but a write
+ * needs it to itself
+ **/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Access {
+    reads: Vec<Resource>,
+    writes: Vec<Resource>,
+}
+
+impl Access {
+    pub fn new() -> Access {
+        Access::default()
+    }
+
+    pub fn read<T: ?Sized + 'static>(mut self) -> Access {
+        self.reads.push(Resource::of::<T>());
+        self
+    }
+
+    pub fn write<T: ?Sized + 'static>(mut self) -> Access {
+        self.writes.push(Resource::of::<T>());
+        self
+    }
+
+    pub fn reads(&self) -> &[Resource] {
+        &self.reads
+    }
+
+    pub fn writes(&self) -> &[Resource] {
+        &self.writes
+    }
+
+    /**
+     * A resource both touch where at least one of them writes
+     **/
+    pub fn conflict(&self, other: &Access) -> Option<Resource> {
+        self.writes.iter()
+            .find(|x| other.writes.contains(x) || other.reads.contains(x))
+            .or_else(|| other.writes.iter().find(|x| self.reads.contains(x)))
+            .copied()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TaskId(u32);
+
+struct Task<'a> {
+    name: String,
+    access: Access,
+    after: Vec<TaskId>,
+    run: Box<dyn FnMut() + Send + 'a>,
+}
+
+/**
+ * Tasks with dependencies between them, run as a batch on a JobSystem
+ * Tasks that write something another reads or writes must be ordered by a dependency,
+ * otherwise the graph is rejected before anything runs. Tasks are kept, so a graph built once
+ * can run every frame
+ **/
+pub struct TaskGraph<'a> {
+    tasks: Vec<Task<'a>>,
+    //topological order, None when tasks changed since the last check
+    order: Option<Vec<usize>>,
+}
+
+impl<'a> std::fmt::Debug for TaskGraph<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let names: Vec<&str> = self.tasks.iter().map(|x| x.name.as_str()).collect();
+        write!(f, "TaskGraph {{ tasks: {:?} }}", names)
+    }
+}
+
+impl<'a> TaskGraph<'a> {
+    pub fn new() -> TaskGraph<'a> {
+        TaskGraph { tasks: Vec::new(), order: None }
+    }
+
+    pub fn add<F: FnMut() + Send + 'a>(&mut self, name: &str, access: Access, task: F) -> TaskId {
+        self.tasks.push(Task { name: name.to_string(), access, after: Vec::new(), run: Box::new(task) });
+        self.order = None;
+        TaskId(self.tasks.len() as u32 - 1)
+    }
+
+    /**
+     * Makes `task` wait for `on` to finish
+     **/
+    pub fn depend(&mut self, task: TaskId, on: TaskId) -> Result<(), JobError> {
+        for x in [task, on] {
+            if x.0 as usize >= self.tasks.len() {
+                return Err(JobError::InvalidTask(x.0));
+            }
+        }
+        let after = &mut self.tasks[task.0 as usize].after;
+        if !after.contains(&on) {
+            after.push(on);
+            self.order = None;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub fn name(&self, task: TaskId) -> Option<&str> {
+        self.tasks.get(task.0 as usize).map(|x| x.name.as_str())
+    }
+
+    /**
+     * Checks for dependency cycles and for conflicting tasks that could run at the same time
+     **/
+    pub fn validate(&mut self) -> Result<(), JobError> {
+        if self.order.is_some() {
+            return Ok(());
+        }
+        let order = self.sort()?;
+
+        //reaches[i][j] when task j always finishes before task i starts
+        let count = self.tasks.len();
+        let mut reaches = vec![vec![false; count]; count];
+        for &i in &order {
+            for dep in &self.tasks[i].after {
+                let dep = dep.0 as usize;
+                //dependencies come first in the order, so their rows are already complete
+                let inherited = reaches[dep].clone();
+                reaches[i][dep] = true;
+                for (x, y) in reaches[i].iter_mut().zip(inherited) {
+                    *x |= y;
+                }
+            }
+        }
+        for (i, a) in self.tasks.iter().enumerate() {
+            for (j, b) in self.tasks.iter().enumerate().skip(i + 1) {
+                if reaches[i][j] || reaches[j][i] {
+                    continue;
+                }
+                if let Some(resource) = a.access.conflict(&b.access) {
+                    return Err(JobError::Conflict(a.name.clone(), b.name.clone(), resource.name()));
+                }
+            }
+        }
+        self.order = Some(order);
+        Ok(())
+    }
+
+    /**
+     * Kahn's algorithm, ready tasks are taken in the order they were added
+     **/
+    fn sort(&self) -> Result<Vec<usize>, JobError> {
+        let count = self.tasks.len();
+        let mut waiting: Vec<usize> = self.tasks.iter().map(|x| x.after.len()).collect();
+        let mut order = Vec::with_capacity(count);
+        let mut done = vec![false; count];
+        while order.len() < count {
+            let next = match (0..count).find(|&i| !done[i] && waiting[i] == 0) {
+                Some(x) => x,
+                None => {
+                    let stuck = (0..count).filter(|&i| !done[i]).map(|i| self.tasks[i].name.clone()).collect();
+                    return Err(JobError::Cycle(stuck));
+                }
+            };
+            done[next] = true;
+            order.push(next);
+            for (i, task) in self.tasks.iter().enumerate() {
+                if task.after.contains(&TaskId(next as u32)) {
+                    waiting[i] -= 1;
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /**
+     * Runs every task once, each as soon as the ones it depends on are done
+     * On a single threaded JobSystem tasks run one at a time in a fixed order and a panic
+     * stops the rest, otherwise it only stops the tasks that depend on the one that panicked
+     * Either way the first panic is returned
+     **/
+    pub fn run(&mut self, jobs: &JobSystem) -> Result<(), JobError> {
+        self.validate()?;
+        let order = self.order.clone().unwrap_or_default();
+        if jobs.is_single_threaded() {
+            for i in order {
+                let task = &mut self.tasks[i];
+                profile_scope!("task", task.name);
+                if let Err(e) = crash::recover(AssertUnwindSafe(&mut task.run)) {
+                    return Err(JobError::Panicked(format!("{}: {}", task.name, panic_message(&*e))));
+                }
+            }
+            return Ok(());
+        }
+
+        let roots: Vec<usize> = order.into_iter().filter(|&i| self.tasks[i].after.is_empty()).collect();
+        let count = self.tasks.len();
+        let mut dependents = vec![Vec::new(); count];
+        for (i, task) in self.tasks.iter().enumerate() {
+            for dep in &task.after {
+                dependents[dep.0 as usize].push(i);
+            }
+        }
+        let state = RunState {
+            waiting: self.tasks.iter().map(|x| AtomicUsize::new(x.after.len())).collect(),
+            dependents,
+            tasks: self.tasks.iter_mut().map(Mutex::new).collect(),
+            error: Mutex::new(None),
+        };
+        let shared = &state;
+        jobs.scope(|s| {
+            for i in roots {
+                s.spawn(move |s| shared.run(s, i));
+            }
+        });
+        match state.error.into_inner() {
+            Ok(Some(e)) => Err(e),
+            Ok(None) => Ok(()),
+            Err(_) => Err(JobError::Panicked("task graph state was poisoned".to_string()))
+        }
+    }
+}
+
+impl<'a> Default for TaskGraph<'a> {
+    fn default() -> TaskGraph<'a> {
+        TaskGraph::new()
+    }
+}
+
+/**
+ * Shared between the jobs of one TaskGraph::run
+ **/
+struct RunState<'g, 'a> {
+    waiting: Vec<AtomicUsize>,
+    dependents: Vec<Vec<usize>>,
+    tasks: Vec<Mutex<&'g mut Task<'a>>>,
+    error: Mutex<Option<JobError>>,
+}
+
+impl<'g, 'a> RunState<'g, 'a> {
+    fn run<'s>(&'s self, scope: &Scope<'s>, index: usize) {
+        let result = match self.tasks[index].lock() {
+            Ok(mut task) => {
+                let task = &mut **task;
+                profile_scope!("task", task.name);
+                crash::recover(AssertUnwindSafe(&mut task.run))
+                    .map_err(|e| JobError::Panicked(format!("{}: {}", task.name, panic_message(&*e))))
+            },
+            Err(_) => Err(JobError::Panicked("task lock was poisoned".to_string()))
+        };
+        if let Err(e) = result {
+            if let Ok(mut x) = self.error.lock() {
+                x.get_or_insert(e);
+            }
+            return;
+        }
+        for &next in &self.dependents[index] {
+            if self.waiting[next].fetch_sub(1, Ordering::SeqCst) == 1 {
+                scope.spawn(move |s| self.run(s, next));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    struct Physics;
+    struct Transforms;
+    struct Particles;
+
+    //a task that records its name when it runs
+    fn record<'a>(log: &'a Mutex<Vec<&'static str>>, name: &'static str) -> impl FnMut() + Send + 'a {
+        move || log.lock().unwrap().push(name)
+    }
+
+    fn logged(log: &Mutex<Vec<&'static str>>) -> Vec<&'static str> {
+        log.lock().unwrap().clone()
+    }
+
+    fn systems() -> Vec<JobSystem> {
+        vec![JobSystem::single_threaded(), JobSystem::new(Some(4))]
+    }
+
+    #[test]
+    fn runs_in_topological_order() {
+        for jobs in systems() {
+            let log = Mutex::new(Vec::new());
+            let mut graph = TaskGraph::new();
+            //added backwards so insertion order alone would be wrong
+            let render = graph.add("render", Access::new().read::<Transforms>(), record(&log, "render"));
+            let particles = graph.add("particles", Access::new().write::<Particles>(), record(&log, "particles"));
+            let transforms = graph.add("transforms", Access::new().read::<Physics>().write::<Transforms>(), record(&log, "transforms"));
+            let physics = graph.add("physics", Access::new().write::<Physics>(), record(&log, "physics"));
+            graph.depend(transforms, physics).unwrap();
+            graph.depend(render, transforms).unwrap();
+            graph.depend(render, particles).unwrap();
+            graph.run(&jobs).unwrap();
+
+            let log = logged(&log);
+            let at = |name| log.iter().position(|x| *x == name).unwrap();
+            assert_eq!(log.len(), 4);
+            assert!(at("physics") < at("transforms"));
+            assert!(at("transforms") < at("render"));
+            assert!(at("particles") < at("render"));
+        }
+    }
+
+    #[test]
+    fn single_threaded_order_is_fixed() {
+        let jobs = JobSystem::single_threaded();
+        let log = Mutex::new(Vec::new());
+        let mut graph = TaskGraph::new();
+        let c = graph.add("c", Access::new(), record(&log, "c"));
+        let a = graph.add("a", Access::new(), record(&log, "a"));
+        graph.add("b", Access::new(), record(&log, "b"));
+        graph.depend(c, a).unwrap();
+        graph.run(&jobs).unwrap();
+        graph.run(&jobs).unwrap();
+        //c was added first, so it goes as soon as a frees it
+        assert_eq!(logged(&log), vec!["a", "c", "b", "a", "c", "b"]);
+    }
+
+    #[test]
+    fn graph_runs_again() {
+        let jobs = JobSystem::new(Some(2));
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut graph = TaskGraph::new();
+        for i in 0..10 {
+            let count = Arc::clone(&count);
+            graph.add(&format!("task {}", i), Access::new(), move || {
+                count.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        for _ in 0..5 {
+            graph.run(&jobs).unwrap();
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 50);
+    }
+
+    #[test]
+    fn cycle_is_rejected_before_running() {
+        let log = Mutex::new(Vec::new());
+        let mut graph = TaskGraph::new();
+        let a = graph.add("a", Access::new(), record(&log, "a"));
+        let b = graph.add("b", Access::new(), record(&log, "b"));
+        let c = graph.add("c", Access::new(), record(&log, "c"));
+        graph.add("free", Access::new(), record(&log, "free"));
+        graph.depend(a, c).unwrap();
+        graph.depend(b, a).unwrap();
+        graph.depend(c, b).unwrap();
+        for jobs in systems() {
+            assert_eq!(graph.run(&jobs), Err(JobError::Cycle(vec!["a".to_string(), "b".to_string(), "c".to_string()])));
+        }
+        assert!(logged(&log).is_empty());
+    }
+
+    #[test]
+    fn self_dependency_is_a_cycle() {
+        let mut graph = TaskGraph::new();
+        let a = graph.add("a", Access::new(), || {});
+        graph.depend(a, a).unwrap();
+        assert_eq!(graph.validate(), Err(JobError::Cycle(vec!["a".to_string()])));
+    }
+
+    #[test]
+    fn unordered_writers_conflict() {
+        let mut graph = TaskGraph::new();
+        graph.add("a", Access::new().write::<Physics>(), || {});
+        graph.add("b", Access::new().write::<Physics>(), || {});
+        assert_eq!(graph.validate(), Err(JobError::Conflict("a".to_string(), "b".to_string(), type_name::<Physics>())));
+    }
+
+    #[test]
+    fn unordered_reader_and_writer_conflict() {
+        let log = Mutex::new(Vec::new());
+        let mut graph = TaskGraph::new();
+        graph.add("reader", Access::new().read::<Physics>(), || {});
+        graph.add("other", Access::new().write::<Particles>(), || {});
+        graph.add("writer", Access::new().write::<Physics>(), || {});
+        assert_eq!(graph.validate(), Err(JobError::Conflict("reader".to_string(), "writer".to_string(), type_name::<Physics>())));
+        graph.add("late", Access::new(), record(&log, "late"));
+        assert!(graph.run(&JobSystem::single_threaded()).is_err());
+        assert!(logged(&log).is_empty());
+    }
+
+    #[test]
+    fn readers_share_a_resource() {
+        let mut graph = TaskGraph::new();
+        graph.add("a", Access::new().read::<Physics>(), || {});
+        graph.add("b", Access::new().read::<Physics>(), || {});
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn ordering_resolves_conflict_even_through_other_tasks() {
+        let mut graph = TaskGraph::new();
+        let a = graph.add("a", Access::new().write::<Physics>(), || {});
+        let b = graph.add("b", Access::new(), || {});
+        let c = graph.add("c", Access::new().write::<Physics>(), || {});
+        assert!(graph.validate().is_err());
+        graph.depend(b, a).unwrap();
+        graph.depend(c, b).unwrap();
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn conflict_is_symmetric() {
+        let read = Access::new().read::<Physics>();
+        let write = Access::new().write::<Physics>();
+        assert_eq!(read.conflict(&write), Some(Resource::of::<Physics>()));
+        assert_eq!(write.conflict(&read), Some(Resource::of::<Physics>()));
+        assert_eq!(read.conflict(&read), None);
+        assert_eq!(write.conflict(&Access::new().write::<Particles>()), None);
+    }
+
+    #[test]
+    fn invalid_task_ids_are_rejected() {
+        let mut graph = TaskGraph::new();
+        let a = graph.add("a", Access::new(), || {});
+        assert_eq!(graph.depend(a, TaskId(5)), Err(JobError::InvalidTask(5)));
+        assert_eq!(graph.depend(TaskId(3), a), Err(JobError::InvalidTask(3)));
+    }
+
+    #[test]
+    fn panic_stops_dependents_only() {
+        let jobs = JobSystem::new(Some(2));
+        let log = Mutex::new(Vec::new());
+        let mut graph = TaskGraph::new();
+        let broken = graph.add("broken", Access::new(), || panic!("task failed"));
+        let after = graph.add("after", Access::new(), record(&log, "after"));
+        graph.add("independent", Access::new(), record(&log, "independent"));
+        graph.depend(after, broken).unwrap();
+        assert_eq!(graph.run(&jobs), Err(JobError::Panicked("broken: task failed".to_string())));
+        assert_eq!(logged(&log), vec!["independent"]);
+    }
+
+    #[test]
+    fn single_threaded_panic_stops_the_rest() {
+        let log = Mutex::new(Vec::new());
+        let mut graph = TaskGraph::new();
+        graph.add("broken", Access::new(), || panic!("task failed"));
+        graph.add("independent", Access::new(), record(&log, "independent"));
+        assert_eq!(graph.run(&JobSystem::single_threaded()), Err(JobError::Panicked("broken: task failed".to_string())));
+        assert!(logged(&log).is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+pub mod pool;
+pub mod graph;
+
+pub use self::pool::{ JobHandle, JobSystem, Scope };
+pub use self::graph::{ Access, Resource, TaskGraph, TaskId };
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobError {
+    /**
+     * A job or task panicked, with the panic message
+     **/
+    Panicked(String),
+    /**
+     * Tasks that depend on each other in a loop
+     **/
+    Cycle(Vec<String>),
+    /**
+     * Two tasks touch the same resource, at least one writing, with nothing ordering them
+     **/
+    Conflict(String, String, &'static str),
+    InvalidTask(u32),
+}
+
+impl fmt::Display for JobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobError::Panicked(msg) => write!(f, "Job panicked: {}", msg),
+            JobError::Cycle(tasks) => write!(f, "Task dependency cycle between {}", tasks.join(", ")),
+            JobError::Conflict(a, b, resource) => write!(f, "Tasks {} and {} both access {} with nothing ordering them", a, b, resource),
+            JobError::InvalidTask(id) => write!(f, "Invalid task id {}", id),
+        }
+    }
+}
+
+impl Error for JobError {}
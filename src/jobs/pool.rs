@@ -0,0 +1,527 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::mem;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::{ Arc, Condvar, Mutex };
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+
+use crate::core::crash;
+use crate::jobs::JobError;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+type JobSlot<T> = Arc<(Mutex<Option<Result<T, JobError>>>, Condvar)>;
+
+//how long an idle thread sleeps before looking for work again, wakeups normally come first
+const IDLE_WAIT: Duration = Duration::from_millis(1);
+
+thread_local! {
+    //the pool this thread works for and its index in it
+    static WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/**
+ * Queues shared by the workers, each worker has its own deque and steals from the others
+ * when it runs dry. Jobs from outside the pool go into the injector
+ **/
+struct Shared {
+    injector: Mutex<VecDeque<Job>>,
+    locals: Vec<Mutex<VecDeque<Job>>>,
+    queued: AtomicUsize,
+    shutdown: AtomicBool,
+    sleep: Mutex<()>,
+    wake: Condvar,
+}
+
+impl Shared {
+    fn id(self: &Arc<Shared>) -> usize {
+        Arc::as_ptr(self) as usize
+    }
+
+    /**
+     * Index of the calling thread if it's one of this pool's workers
+     **/
+    fn worker(self: &Arc<Shared>) -> Option<usize> {
+        match WORKER.with(|x| x.get()) {
+            Some((pool, index)) if pool == self.id() => Some(index),
+            _ => None
+        }
+    }
+
+    fn push(self: &Arc<Shared>, job: Job) {
+        match self.worker() {
+            Some(index) => lock(&self.locals[index]).push_back(job),
+            None => lock(&self.injector).push_back(job)
+        }
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        //taking the lock means a worker can't miss this between checking and sleeping
+        let _guard = lock(&self.sleep);
+        self.wake.notify_one();
+    }
+
+    /**
+     * Newest job from our own deque, then the oldest from the injector, then the oldest from
+     * another worker
+     **/
+    fn find(self: &Arc<Shared>) -> Option<Job> {
+        let worker = self.worker();
+        let job = worker.and_then(|i| lock(&self.locals[i]).pop_back())
+            .or_else(|| lock(&self.injector).pop_front())
+            .or_else(|| {
+                let start = worker.map_or(0, |i| i + 1);
+                (0..self.locals.len())
+                    .map(|i| (start + i) % self.locals.len())
+                    .filter(|&i| Some(i) != worker)
+                    .find_map(|i| lock(&self.locals[i]).pop_front())
+            });
+        if job.is_some() {
+            self.queued.fetch_sub(1, Ordering::SeqCst);
+        }
+        job
+    }
+
+    fn run_worker(self: Arc<Shared>, index: usize) {
+        WORKER.with(|x| x.set(Some((self.id(), index))));
+        loop {
+            if let Some(job) = self.find() {
+                job();
+                continue;
+            }
+            let guard = lock(&self.sleep);
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            if self.queued.load(Ordering::SeqCst) > 0 {
+                continue;
+            }
+            let _ = self.wake.wait_timeout(guard, IDLE_WAIT);
+        }
+    }
+}
+
+//jobs never panic while holding a queue lock, so a poisoned lock is still usable
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(x) = payload.downcast_ref::<&str>() {
+        x.to_string()
+    } else if let Some(x) = payload.downcast_ref::<String>() {
+        x.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/**
+ * Work stealing thread pool
+ * With no worker threads every job runs straight away on the thread that spawns it, in the
+ * order it was spawned, which keeps tests deterministic
+ **/
+pub struct JobSystem {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for JobSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "JobSystem {{ threads: {} }}", self.threads.len())
+    }
+}
+
+impl JobSystem {
+    /**
+     * `threads` defaults to one worker per core, Some(0) runs everything single threaded
+     **/
+    pub fn new(threads: Option<usize>) -> JobSystem {
+        let threads = threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |x| x.get()));
+        let shared = Arc::new(Shared {
+            injector: Mutex::new(VecDeque::new()),
+            locals: (0..threads).map(|_| Mutex::new(VecDeque::new())).collect(),
+            queued: AtomicUsize::new(0),
+            shutdown: AtomicBool::new(false),
+            sleep: Mutex::new(()),
+            wake: Condvar::new(),
+        });
+        let threads = (0..threads).map(|i| {
+            let shared = Arc::clone(&shared);
+            thread::Builder::new()
+                .name(format!("magnus-worker-{}", i))
+                .spawn(move || shared.run_worker(i))
+                .expect("Failed to start job worker thread")
+        }).collect::<Vec<_>>();
+        debug!("Started job system with {} worker threads", threads.len());
+        JobSystem { shared, threads }
+    }
+
+    pub fn single_threaded() -> JobSystem {
+        JobSystem::new(Some(0))
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.len()
+    }
+
+    pub fn is_single_threaded(&self) -> bool {
+        self.threads.is_empty()
+    }
+
+    /**
+     * Runs `job` on the pool, for work like asset decoding whose result is picked up later
+     **/
+    pub fn spawn<T: Send + 'static, F: FnOnce() -> T + Send + 'static>(&self, job: F) -> JobHandle<T> {
+        let slot: JobSlot<T> = Arc::new((Mutex::new(None), Condvar::new()));
+        let result = Arc::clone(&slot);
+        let job = move || {
+            let value = crash::recover(AssertUnwindSafe(job)).map_err(|e| JobError::Panicked(panic_message(&*e)));
+            *lock(&result.0) = Some(value);
+            result.1.notify_all();
+        };
+        if self.is_single_threaded() {
+            job();
+        } else {
+            self.shared.push(Box::new(job));
+        }
+        JobHandle { slot, shared: Arc::clone(&self.shared) }
+    }
+
+    /**
+     * Runs `f`, which can spawn jobs borrowing from the caller's stack, and returns once all
+     * of them have finished. The calling thread helps with queued jobs while it waits
+     * A panic in any job is carried on into the caller once the others are done
+     **/
+    pub fn scope<'env, R>(&'env self, f: impl FnOnce(&Scope<'env>) -> R) -> R {
+        let scope = Scope {
+            system: self,
+            pending: Arc::new((Mutex::new(0), Condvar::new())),
+            panic: Arc::new(Mutex::new(None)),
+            _env: PhantomData,
+        };
+        let result = panic::catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+        let result = match result {
+            Ok(x) => x,
+            Err(e) => panic::resume_unwind(e)
+        };
+        if let Some(e) = lock(&scope.panic).take() {
+            crash::resume(e);
+        }
+        result
+    }
+
+    /**
+     * Calls `f` for every index below `len`, split into batches of `batch` (64 by default)
+     **/
+    pub fn parallel_for<F: Fn(usize) + Sync>(&self, len: usize, batch: Option<usize>, f: F) {
+        let batch = batch.unwrap_or(64).max(1);
+        let f = &f;
+        self.scope(|s| {
+            for start in (0..len).step_by(batch) {
+                s.spawn(move |_| (start..(start + batch).min(len)).for_each(f));
+            }
+        });
+    }
+
+    /**
+     * Calls `f` on every item, split into batches of `batch` (64 by default)
+     **/
+    pub fn parallel_for_mut<T: Send, F: Fn(&mut T) + Sync>(&self, items: &mut [T], batch: Option<usize>, f: F) {
+        let batch = batch.unwrap_or(64).max(1);
+        let f = &f;
+        self.scope(|s| {
+            for chunk in items.chunks_mut(batch) {
+                s.spawn(move |_| chunk.iter_mut().for_each(f));
+            }
+        });
+    }
+
+    /**
+     * Runs one queued job on the calling thread, false if there was nothing to run
+     **/
+    fn help(&self) -> bool {
+        match self.shared.find() {
+            Some(job) => {
+                job();
+                true
+            },
+            None => false
+        }
+    }
+}
+
+impl Default for JobSystem {
+    fn default() -> JobSystem {
+        JobSystem::new(None)
+    }
+}
+
+impl Drop for JobSystem {
+    //jobs still queued are dropped without running
+    fn drop(&mut self) {
+        {
+            let _guard = lock(&self.shared.sleep);
+            self.shared.shutdown.store(true, Ordering::SeqCst);
+            self.shared.wake.notify_all();
+        }
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                error!("Job worker thread panicked");
+            }
+        }
+    }
+}
+
+/**
+ * Result of a job started with JobSystem::spawn
+ **/
+pub struct JobHandle<T> {
+    slot: JobSlot<T>,
+    shared: Arc<Shared>,
+}
+
+impl<T> JobHandle<T> {
+    pub fn is_finished(&self) -> bool {
+        lock(&self.slot.0).is_some()
+    }
+
+    /**
+     * The result if the job has finished, otherwise the handle back
+     **/
+    pub fn try_take(self) -> Result<Result<T, JobError>, JobHandle<T>> {
+        let result = lock(&self.slot.0).take();
+        match result {
+            Some(x) => Ok(x),
+            None => Err(self)
+        }
+    }
+
+    /**
+     * Blocks until the job is done, running other queued jobs in the meantime
+     **/
+    pub fn wait(self) -> Result<T, JobError> {
+        loop {
+            if let Some(x) = lock(&self.slot.0).take() {
+                return x;
+            }
+            if let Some(job) = self.shared.find() {
+                job();
+                continue;
+            }
+            let guard = lock(&self.slot.0);
+            if guard.is_none() {
+                let _ = self.slot.1.wait_timeout(guard, IDLE_WAIT);
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for JobHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "JobHandle {{ finished: {} }}", self.is_finished())
+    }
+}
+
+/**
+ * Spawns jobs that may borrow anything that outlives the JobSystem::scope call
+ **/
+pub struct Scope<'env> {
+    system: &'env JobSystem,
+    pending: Arc<(Mutex<usize>, Condvar)>,
+    panic: Arc<Mutex<Option<Box<dyn Any + Send>>>>,
+    //invariant so 'env can't be shrunk to fit a shorter borrow
+    _env: PhantomData<&'env mut &'env ()>,
+}
+
+//lets a job hand the scope it runs in to nested spawns, it outlives every job in it
+struct ScopePtr(*const ());
+
+unsafe impl Send for ScopePtr {}
+
+impl<'env> Scope<'env> {
+    pub fn system(&self) -> &'env JobSystem {
+        self.system
+    }
+
+    /**
+     * The job gets the scope back so it can spawn more work into it
+     **/
+    pub fn spawn<F: FnOnce(&Scope<'env>) + Send + 'env>(&self, job: F) {
+        *lock(&self.pending.0) += 1;
+        let pending = Arc::clone(&self.pending);
+        let panic_slot = Arc::clone(&self.panic);
+        let scope = ScopePtr(self as *const Scope<'env> as *const ());
+        let job = move || {
+            let scope = scope;
+            //safe as long as the scope is alive, and JobSystem::scope waits for every job
+            let scope = unsafe { &*(scope.0 as *const Scope<'env>) };
+            if let Err(e) = crash::recover(AssertUnwindSafe(|| job(scope))) {
+                lock(&panic_slot).get_or_insert(e);
+            }
+            let mut count = lock(&pending.0);
+            *count -= 1;
+            if *count == 0 {
+                pending.1.notify_all();
+            }
+        };
+        let job: Box<dyn FnOnce() + Send + 'env> = Box::new(job);
+        if self.system.is_single_threaded() {
+            job();
+        } else {
+            //the scope outlives the job, so nothing it borrows can go away under it
+            let job: Job = unsafe { mem::transmute::<Box<dyn FnOnce() + Send + 'env>, Job>(job) };
+            self.system.shared.push(job);
+        }
+    }
+
+    fn wait(&self) {
+        loop {
+            if *lock(&self.pending.0) == 0 {
+                return;
+            }
+            if self.system.help() {
+                continue;
+            }
+            let count = lock(&self.pending.0);
+            if *count > 0 {
+                let _ = self.pending.1.wait_timeout(count, IDLE_WAIT);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn spawn_returns_result() {
+        for jobs in [JobSystem::single_threaded(), JobSystem::new(Some(4))].iter() {
+            let handles: Vec<_> = (0..32u64).map(|i| jobs.spawn(move || i * i)).collect();
+            let results: Vec<u64> = handles.into_iter().map(|x| x.wait().unwrap()).collect();
+            assert_eq!(results, (0..32u64).map(|i| i * i).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn spawn_reports_panic() {
+        for jobs in [JobSystem::single_threaded(), JobSystem::new(Some(2))].iter() {
+            let handle = jobs.spawn(|| -> u32 { panic!("job failed") });
+            assert_eq!(handle.wait(), Err(JobError::Panicked("job failed".to_string())));
+        }
+    }
+
+    #[test]
+    fn try_take_gives_handle_back_until_done() {
+        let jobs = JobSystem::new(Some(1));
+        let (sender, receiver) = std::sync::mpsc::channel::<()>();
+        let handle = jobs.spawn(move || {
+            receiver.recv().unwrap();
+            7
+        });
+        let handle = match handle.try_take() {
+            Err(x) => x,
+            Ok(_) => panic!("job finished before it was let go")
+        };
+        sender.send(()).unwrap();
+        assert_eq!(handle.wait(), Ok(7));
+    }
+
+    #[test]
+    fn single_threaded_runs_in_spawn_order_on_caller() {
+        let jobs = JobSystem::single_threaded();
+        assert!(jobs.is_single_threaded());
+        let caller = thread::current().id();
+        let order = Mutex::new(Vec::new());
+        jobs.scope(|s| {
+            for i in 0..8 {
+                let order = &order;
+                s.spawn(move |s| {
+                    assert_eq!(thread::current().id(), caller);
+                    order.lock().unwrap().push(i);
+                    //nested jobs run before spawn returns, so they land right after their parent
+                    s.spawn(move |_| order.lock().unwrap().push(i + 100));
+                });
+            }
+        });
+        let expected: Vec<i32> = (0..8).flat_map(|i| vec![i, i + 100]).collect();
+        assert_eq!(order.into_inner().unwrap(), expected);
+    }
+
+    #[test]
+    fn scope_returns_value_after_every_job() {
+        let jobs = JobSystem::new(Some(4));
+        let mut values = vec![0u32; 100];
+        let total = AtomicUsize::new(0);
+        let result = jobs.scope(|s| {
+            for (i, x) in values.iter_mut().enumerate() {
+                let total = &total;
+                s.spawn(move |s| {
+                    *x = i as u32 * 2;
+                    s.spawn(move |_| {
+                        total.fetch_add(i, Ordering::SeqCst);
+                    });
+                });
+            }
+            "done"
+        });
+        assert_eq!(result, "done");
+        assert_eq!(total.load(Ordering::SeqCst), (0..100).sum::<usize>());
+        assert!(values.iter().enumerate().all(|(i, x)| *x == i as u32 * 2));
+    }
+
+    #[test]
+    fn scope_carries_job_panic_after_the_rest() {
+        let jobs = JobSystem::new(Some(2));
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| jobs.scope(|s| {
+            s.spawn(|_| panic!("scoped job failed"));
+            for _ in 0..16 {
+                s.spawn(|_| {
+                    thread::sleep(Duration::from_millis(1));
+                    finished.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+        })));
+        let payload = result.unwrap_err();
+        assert_eq!(panic_message(&*payload), "scoped job failed");
+        assert_eq!(finished.load(Ordering::SeqCst), 16);
+    }
+
+    #[test]
+    fn idle_workers_steal_from_a_busy_one() {
+        let jobs = JobSystem::new(Some(4));
+        let threads = Mutex::new(HashSet::new());
+        jobs.scope(|s| {
+            //spawned from a worker, so every child starts out in that worker's own deque
+            s.spawn(|s| {
+                for _ in 0..64 {
+                    s.spawn(|_| {
+                        thread::sleep(Duration::from_millis(1));
+                        threads.lock().unwrap().insert(thread::current().id());
+                    });
+                }
+            });
+        });
+        assert!(threads.into_inner().unwrap().len() > 1);
+    }
+
+    #[test]
+    fn parallel_for_visits_every_index_once() {
+        for jobs in [JobSystem::single_threaded(), JobSystem::new(Some(3))].iter() {
+            let counts: Vec<AtomicUsize> = (0..1000).map(|_| AtomicUsize::new(0)).collect();
+            jobs.parallel_for(counts.len(), Some(7), |i| {
+                counts[i].fetch_add(1, Ordering::SeqCst);
+            });
+            assert!(counts.iter().all(|x| x.load(Ordering::SeqCst) == 1));
+
+            let mut items: Vec<usize> = (0..1000).collect();
+            jobs.parallel_for_mut(&mut items, None, |x| *x *= 3);
+            assert!(items.iter().enumerate().all(|(i, x)| *x == i * 3));
+        }
+    }
+}
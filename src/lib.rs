@@ -13,10 +13,30 @@ extern crate glfw;
 extern crate raw_window_handle;
 extern crate vulkano;
 extern crate vulkano_glfw_v2 as vulkano_glfw;
+extern crate hound;
+extern crate lewton;
+extern crate claxon;
+extern crate rhai;
+extern crate egui;
+extern crate ab_glyph;
+extern crate unicode_segmentation;
 
 #[cfg(windows)]
 extern crate dxplr;
 
+#[macro_use]
+pub mod profiling;
 #[macro_use]
 pub mod core;
+pub mod jobs;
+pub mod console;
 pub mod events;
+pub mod math;
+pub mod audio;
+pub mod physics;
+pub mod animation;
+pub mod particles;
+pub mod tilemap;
+pub mod scripting;
+pub mod ui;
+pub mod text;
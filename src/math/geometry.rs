@@ -0,0 +1,548 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::math::matrix::Mat4;
+use crate::math::vector::{ Vec3, Vec4 };
+
+/**
+ * Axis aligned bounding box
+ **/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    #[inline]
+    pub fn new(min: Vec3, max: Vec3) -> Aabb {
+        Aabb { min: min.min(max), max: min.max(max) }
+    }
+
+    pub fn from_center_extents(center: Vec3, half_extents: Vec3) -> Aabb {
+        Aabb { min: center - half_extents, max: center + half_extents }
+    }
+
+    /**
+     * Smallest box containing every point, or None when there are no points
+     **/
+    pub fn from_points(points: &[Vec3]) -> Option<Aabb> {
+        let first = *points.first()?;
+        Some(points.iter().fold(Aabb { min: first, max: first }, |b, &p| Aabb { min: b.min.min(p), max: b.max.max(p) }))
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline]
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn merge(&self, other: &Aabb) -> Aabb {
+        Aabb { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn expand(&self, amount: f32) -> Aabb {
+        Aabb { min: self.min - Vec3::splat(amount), max: self.max + Vec3::splat(amount) }
+    }
+
+    #[inline]
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x
+            && p.y >= self.min.y && p.y <= self.max.y
+            && p.z >= self.min.z && p.z <= self.max.z
+    }
+
+    #[inline]
+    pub fn intersects_aabb(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x
+            && self.min.y <= other.max.y && self.max.y >= other.min.y
+            && self.min.z <= other.max.z && self.max.z >= other.min.z
+    }
+
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        p.max(self.min).min(self.max)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.closest_point(sphere.center).distance(sphere.center) <= sphere.radius
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (a, b) = (self.min, self.max);
+        [
+            Vec3::new(a.x, a.y, a.z), Vec3::new(b.x, a.y, a.z), Vec3::new(a.x, b.y, a.z), Vec3::new(b.x, b.y, a.z),
+            Vec3::new(a.x, a.y, b.z), Vec3::new(b.x, a.y, b.z), Vec3::new(a.x, b.y, b.z), Vec3::new(b.x, b.y, b.z)
+        ]
+    }
+
+    /**
+     * Box containing this box after it has been transformed by `m`
+     **/
+    pub fn transform(&self, m: &Mat4) -> Aabb {
+        let center = m.transform_point3(self.center());
+        let e = self.half_extents();
+        let extent = Vec3::new(
+            m.cols[0].x.abs() * e.x + m.cols[1].x.abs() * e.y + m.cols[2].x.abs() * e.z,
+            m.cols[0].y.abs() * e.x + m.cols[1].y.abs() * e.y + m.cols[2].y.abs() * e.z,
+            m.cols[0].z.abs() * e.x + m.cols[1].z.abs() * e.y + m.cols[2].z.abs() * e.z
+        );
+        Aabb::from_center_extents(center, extent)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    #[inline]
+    pub fn new(center: Vec3, radius: f32) -> Sphere {
+        Sphere { center, radius }
+    }
+
+    #[inline]
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.center.distance(p) <= self.radius
+    }
+
+    #[inline]
+    pub fn intersects_sphere(&self, other: &Sphere) -> bool {
+        let r = self.radius + other.radius;
+        (self.center - other.center).length_squared() <= r * r
+    }
+
+    #[inline]
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        aabb.intersects_sphere(self)
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        Aabb::from_center_extents(self.center, Vec3::splat(self.radius))
+    }
+}
+
+/**
+ * Plane of points p where normal.dot(p) + d == 0
+ **/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Plane {
+    pub normal: Vec3,
+    pub d: f32,
+}
+
+impl Plane {
+    #[inline]
+    pub fn new(normal: Vec3, d: f32) -> Plane {
+        Plane { normal, d }
+    }
+
+    pub fn from_point_normal(point: Vec3, normal: Vec3) -> Plane {
+        let normal = normal.normalize();
+        Plane { normal, d: -normal.dot(point) }
+    }
+
+    /**
+     * Plane through three points, facing the side they wind counter clockwise around
+     **/
+    pub fn from_points(a: Vec3, b: Vec3, c: Vec3) -> Plane {
+        Plane::from_point_normal(a, (b - a).cross(c - a))
+    }
+
+    /**
+     * Plane from the coefficients of a Vec4 (normal in xyz, d in w), normalized
+     **/
+    pub fn from_vec4(v: Vec4) -> Plane {
+        let len = v.truncate().length();
+        if len > 0.0 {
+            Plane { normal: v.truncate() / len, d: v.w / len }
+        } else {
+            Plane { normal: Vec3::Y, d: 0.0 }
+        }
+    }
+
+    /**
+     * Positive in front of the plane (the side the normal points to), negative behind it
+     **/
+    #[inline]
+    pub fn signed_distance(&self, p: Vec3) -> f32 {
+        self.normal.dot(p) + self.d
+    }
+
+    #[inline]
+    pub fn project_point(&self, p: Vec3) -> Vec3 {
+        p - self.normal * self.signed_distance(p)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.signed_distance(sphere.center).abs() <= sphere.radius
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        let e = aabb.half_extents();
+        let r = e.x * self.normal.x.abs() + e.y * self.normal.y.abs() + e.z * self.normal.z.abs();
+        self.signed_distance(aabb.center()).abs() <= r
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    /**
+     * Ray from `origin` along `direction`, the direction is normalized so hit distances are in world units
+     **/
+    pub fn new(origin: Vec3, direction: Vec3) -> Ray {
+        Ray { origin, direction: direction.normalize() }
+    }
+
+    #[inline]
+    pub fn at(&self, t: f32) -> Vec3 {
+        self.origin + self.direction * t
+    }
+
+    /**
+     * Distance along the ray to the box, 0 if the origin is inside it
+     **/
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::INFINITY;
+        for axis in 0..3 {
+            let o = self.origin[axis];
+            let d = self.direction[axis];
+            if d.abs() < f32::EPSILON {
+                if o < aabb.min[axis] || o > aabb.max[axis] {
+                    return None;
+                }
+            } else {
+                let inv = 1.0 / d;
+                let mut t0 = (aabb.min[axis] - o) * inv;
+                let mut t1 = (aabb.max[axis] - o) * inv;
+                if t0 > t1 {
+                    std::mem::swap(&mut t0, &mut t1);
+                }
+                t_min = t_min.max(t0);
+                t_max = t_max.min(t1);
+                if t_min > t_max {
+                    return None;
+                }
+            }
+        }
+        Some(t_min)
+    }
+
+    /**
+     * Distance along the ray to the sphere, 0 if the origin is inside it
+     **/
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let m = self.origin - sphere.center;
+        let b = m.dot(self.direction);
+        let c = m.length_squared() - sphere.radius * sphere.radius;
+        if c > 0.0 && b > 0.0 {
+            return None;
+        }
+        let disc = b * b - c;
+        if disc < 0.0 {
+            return None;
+        }
+        Some((-b - disc.sqrt()).max(0.0))
+    }
+
+    pub fn intersect_plane(&self, plane: &Plane) -> Option<f32> {
+        let denom = plane.normal.dot(self.direction);
+        if denom.abs() < f32::EPSILON {
+            return None;
+        }
+        let t = -plane.signed_distance(self.origin) / denom;
+        if t >= 0.0 { Some(t) } else { None }
+    }
+
+    /**
+     * Möller-Trumbore ray/triangle test, hits from both sides
+     **/
+    pub fn intersect_triangle(&self, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+        let e1 = b - a;
+        let e2 = c - a;
+        let p = self.direction.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let inv = 1.0 / det;
+        let s = self.origin - a;
+        let u = s.dot(p) * inv;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(e1);
+        let v = self.direction.dot(q) * inv;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let t = e2.dot(q) * inv;
+        if t >= 0.0 { Some(t) } else { None }
+    }
+}
+
+/**
+ * Six planes (left, right, bottom, top, near, far) facing into the view volume
+ **/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Frustum {
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /**
+     * Extracts the frustum of a combined projection * view matrix
+     * `zero_to_one` should be set for matrices that map depth to 0..1 (the _zo projections)
+     **/
+    pub fn from_matrix(m: &Mat4, zero_to_one: bool) -> Frustum {
+        let (r0, r1, r2, r3) = (m.row(0), m.row(1), m.row(2), m.row(3));
+        let near = if zero_to_one { r2 } else { r3 + r2 };
+        Frustum {
+            planes: [
+                Plane::from_vec4(r3 + r0),
+                Plane::from_vec4(r3 - r0),
+                Plane::from_vec4(r3 + r1),
+                Plane::from_vec4(r3 - r1),
+                Plane::from_vec4(near),
+                Plane::from_vec4(r3 - r2)
+            ]
+        }
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        self.planes.iter().all(|x| x.signed_distance(p) >= 0.0)
+    }
+
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        self.planes.iter().all(|x| x.signed_distance(sphere.center) >= -sphere.radius)
+    }
+
+    /**
+     * Conservative test, may report boxes just outside a frustum corner as visible
+     **/
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let positive = Vec3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z }
+            );
+            plane.signed_distance(positive) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::testing::{ Rng, CASES };
+
+    const EPSILON: f32 = 1e-4;
+
+    fn unit_box() -> Aabb {
+        Aabb::new(Vec3::splat(-1.0), Vec3::ONE)
+    }
+
+    #[test]
+    fn ray_hits_aabb_face() {
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::X);
+        assert_eq!(ray.intersect_aabb(&unit_box()), Some(4.0));
+        let diagonal = Ray::new(Vec3::splat(-3.0), Vec3::ONE);
+        let t = diagonal.intersect_aabb(&unit_box()).unwrap();
+        assert!((t - 2.0 * 3f32.sqrt()).abs() < EPSILON, "{}", t);
+    }
+
+    #[test]
+    fn ray_misses_aabb() {
+        assert_eq!(Ray::new(Vec3::new(-5.0, 2.0, 0.0), Vec3::X).intersect_aabb(&unit_box()), None);
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0)).intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn ray_pointing_away_from_aabb_misses() {
+        assert_eq!(Ray::new(Vec3::new(-5.0, 0.0, 0.0), -Vec3::X).intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn ray_inside_aabb_hits_at_zero() {
+        assert_eq!(Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::Y).intersect_aabb(&unit_box()), Some(0.0));
+    }
+
+    #[test]
+    fn ray_parallel_to_aabb_slab() {
+        //parallel to x and y, only inside the slab on those axes hits
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::Z).intersect_aabb(&unit_box()), Some(4.0));
+        assert_eq!(Ray::new(Vec3::new(2.0, 0.0, -5.0), Vec3::Z).intersect_aabb(&unit_box()), None);
+    }
+
+    #[test]
+    fn ray_aabb_hit_point_is_on_the_box() {
+        let mut rng = Rng::new(30);
+        for _ in 0..CASES {
+            let aabb = Aabb::from_center_extents(rng.vec3(-10.0, 10.0), rng.vec3(0.5, 5.0));
+            let origin = rng.vec3(-30.0, 30.0);
+            //aim at a random point inside the box, the ray always hits
+            let target = aabb.min + (aabb.max - aabb.min) * Vec3::new(rng.unit(), rng.unit(), rng.unit());
+            let ray = Ray::new(origin, target - origin);
+            let t = ray.intersect_aabb(&aabb).expect("ray aimed into the box");
+            assert!(t <= origin.distance(target) + EPSILON);
+            assert!(aabb.expand(1e-3).contains_point(ray.at(t)), "{:?} {:?} {}", ray, aabb, t);
+            //and the opposite direction only hits from inside
+            let back = Ray::new(origin, origin - target).intersect_aabb(&aabb);
+            assert!(back.is_none() || aabb.contains_point(origin), "{:?} {:?}", ray, aabb);
+        }
+    }
+
+    #[test]
+    fn ray_hits_sphere() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -10.0), 2.0);
+        let t = Ray::new(Vec3::ZERO, -Vec3::Z).intersect_sphere(&sphere).unwrap();
+        assert!((t - 8.0).abs() < EPSILON, "{}", t);
+        let grazing = Ray::new(Vec3::new(2.0, 0.0, 0.0), -Vec3::Z).intersect_sphere(&sphere).unwrap();
+        assert!((grazing - 10.0).abs() < EPSILON, "{}", grazing);
+    }
+
+    #[test]
+    fn ray_misses_sphere() {
+        let sphere = Sphere::new(Vec3::new(0.0, 0.0, -10.0), 2.0);
+        assert_eq!(Ray::new(Vec3::new(3.0, 0.0, 0.0), -Vec3::Z).intersect_sphere(&sphere), None);
+        assert_eq!(Ray::new(Vec3::ZERO, Vec3::Z).intersect_sphere(&sphere), None);
+    }
+
+    #[test]
+    fn ray_inside_sphere_hits_at_zero() {
+        let sphere = Sphere::new(Vec3::ZERO, 2.0);
+        assert_eq!(Ray::new(Vec3::new(0.5, 0.0, 0.0), Vec3::X).intersect_sphere(&sphere), Some(0.0));
+    }
+
+    #[test]
+    fn ray_sphere_hit_point_is_on_the_surface() {
+        let mut rng = Rng::new(31);
+        for _ in 0..CASES {
+            let sphere = Sphere::new(rng.vec3(-10.0, 10.0), rng.range(0.5, 5.0));
+            let origin = sphere.center + rng.direction() * rng.range(sphere.radius + 0.1, 40.0);
+            let target = sphere.center + rng.direction() * rng.range(0.0, sphere.radius * 0.9);
+            let ray = Ray::new(origin, target - origin);
+            let t = ray.intersect_sphere(&sphere).expect("ray aimed into the sphere");
+            assert!((ray.at(t).distance(sphere.center) - sphere.radius).abs() < 1e-3, "{:?} {:?}", ray, sphere);
+            assert_eq!(Ray::new(origin, origin - target).intersect_sphere(&sphere), None);
+        }
+    }
+
+    #[test]
+    fn aabb_overlap_and_touching() {
+        let a = unit_box();
+        assert!(a.intersects_aabb(&Aabb::new(Vec3::ZERO, Vec3::splat(3.0))));
+        assert!(a.intersects_aabb(&Aabb::new(Vec3::new(1.0, -1.0, -1.0), Vec3::new(2.0, 1.0, 1.0))));
+        assert!(a.intersects_aabb(&Aabb::new(Vec3::splat(-0.5), Vec3::splat(0.5))));
+    }
+
+    #[test]
+    fn aabb_separated_on_each_axis() {
+        let a = unit_box();
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z].iter() {
+            let offset = *axis * 2.5;
+            let b = Aabb::from_center_extents(offset, Vec3::ONE);
+            assert!(!a.intersects_aabb(&b), "{}", offset);
+            assert!(!b.intersects_aabb(&a), "{}", offset);
+        }
+    }
+
+    #[test]
+    fn aabb_overlap_matches_interval_check() {
+        let mut rng = Rng::new(32);
+        for _ in 0..CASES {
+            let a = Aabb::from_center_extents(rng.vec3(-5.0, 5.0), rng.vec3(0.1, 3.0));
+            let b = Aabb::from_center_extents(rng.vec3(-5.0, 5.0), rng.vec3(0.1, 3.0));
+            let expected = (0..3).all(|i| a.min[i] <= b.max[i] && b.min[i] <= a.max[i]);
+            assert_eq!(a.intersects_aabb(&b), expected, "{:?} {:?}", a, b);
+            assert_eq!(b.intersects_aabb(&a), expected, "{:?} {:?}", a, b);
+            //the merged box contains both
+            let merged = a.merge(&b);
+            assert!(merged.contains_point(a.min) && merged.contains_point(b.max));
+        }
+    }
+
+    #[test]
+    fn aabb_sphere_overlap() {
+        let a = unit_box();
+        assert!(a.intersects_sphere(&Sphere::new(Vec3::new(2.0, 0.0, 0.0), 1.0)));
+        assert!(!a.intersects_sphere(&Sphere::new(Vec3::new(2.0, 2.0, 0.0), 1.0)));
+        assert!(Sphere::new(Vec3::new(1.5, 0.0, 0.0), 1.0).intersects_aabb(&a));
+        assert!(Sphere::new(Vec3::ZERO, 1.0).intersects_sphere(&Sphere::new(Vec3::new(2.0, 0.0, 0.0), 1.0)));
+        assert!(!Sphere::new(Vec3::ZERO, 1.0).intersects_sphere(&Sphere::new(Vec3::new(2.1, 0.0, 0.0), 1.0)));
+    }
+
+    #[test]
+    fn aabb_from_points_and_transform() {
+        assert_eq!(Aabb::from_points(&[]), None);
+        let aabb = Aabb::from_points(&[Vec3::new(1.0, -2.0, 0.0), Vec3::new(-1.0, 3.0, 4.0)]).unwrap();
+        assert_eq!(aabb, Aabb::new(Vec3::new(-1.0, -2.0, 0.0), Vec3::new(1.0, 3.0, 4.0)));
+        let moved = unit_box().transform(&Mat4::from_translation(Vec3::new(5.0, 0.0, 0.0)));
+        assert!(moved.min.approx_eq(Vec3::new(4.0, -1.0, -1.0), EPSILON));
+        assert!(moved.max.approx_eq(Vec3::new(6.0, 1.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn ray_hits_plane() {
+        let floor = Plane::from_point_normal(Vec3::ZERO, Vec3::Y);
+        assert_eq!(Ray::new(Vec3::new(0.0, 5.0, 0.0), -Vec3::Y).intersect_plane(&floor), Some(5.0));
+        assert_eq!(Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::Y).intersect_plane(&floor), None);
+        assert_eq!(Ray::new(Vec3::new(0.0, 5.0, 0.0), Vec3::X).intersect_plane(&floor), None);
+        assert_eq!(floor.signed_distance(Vec3::new(3.0, -2.0, 1.0)), -2.0);
+        assert_eq!(floor.project_point(Vec3::new(3.0, -2.0, 1.0)), Vec3::new(3.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn ray_hits_triangle_from_both_sides() {
+        let (a, b, c) = (Vec3::new(-1.0, -1.0, 0.0), Vec3::new(1.0, -1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, 3.0), -Vec3::Z).intersect_triangle(a, b, c), Some(3.0));
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, -3.0), Vec3::Z).intersect_triangle(a, b, c), Some(3.0));
+        assert_eq!(Ray::new(Vec3::new(2.0, 0.0, 3.0), -Vec3::Z).intersect_triangle(a, b, c), None);
+        assert_eq!(Ray::new(Vec3::new(0.0, 0.0, 3.0), Vec3::Z).intersect_triangle(a, b, c), None);
+    }
+
+    #[test]
+    fn frustum_culls_outside_view() {
+        let proj = Mat4::perspective_rh_gl(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0);
+        let view = Mat4::look_at_rh(Vec3::ZERO, -Vec3::Z, Vec3::Y);
+        let frustum = Frustum::from_matrix(&(proj * view), false);
+        assert!(frustum.contains_point(Vec3::new(0.0, 0.0, -10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, 10.0)));
+        assert!(!frustum.contains_point(Vec3::new(0.0, 0.0, -200.0)));
+        assert!(!frustum.contains_point(Vec3::new(20.0, 0.0, -10.0)));
+        assert!(frustum.intersects_sphere(&Sphere::new(Vec3::new(11.0, 0.0, -10.0), 2.0)));
+        assert!(!frustum.intersects_sphere(&Sphere::new(Vec3::new(20.0, 0.0, -10.0), 2.0)));
+        assert!(frustum.intersects_aabb(&Aabb::from_center_extents(Vec3::new(0.0, 0.0, -0.5), Vec3::ONE)));
+        assert!(!frustum.intersects_aabb(&Aabb::from_center_extents(Vec3::new(0.0, 0.0, 5.0), Vec3::ONE)));
+
+        let zo = Frustum::from_matrix(&(Mat4::perspective_rh_zo(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0) * view), true);
+        assert!(zo.contains_point(Vec3::new(0.0, 0.0, -1.5)));
+        assert!(!zo.contains_point(Vec3::new(0.0, 0.0, -0.5)));
+    }
+}
@@ -0,0 +1,597 @@
+use std::ops::{ Add, Mul, MulAssign, Sub };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::math::quaternion::Quat;
+use crate::math::vector::{ Vec3, Vec4 };
+
+/**
+ * Column major 3x3 matrix
+ **/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Mat3 {
+    pub cols: [Vec3; 3],
+}
+
+impl Mat3 {
+    pub const IDENTITY: Mat3 = Mat3 { cols: [Vec3::X, Vec3::Y, Vec3::Z] };
+    pub const ZERO: Mat3 = Mat3 { cols: [Vec3::ZERO; 3] };
+
+    #[inline]
+    pub fn from_cols(x: Vec3, y: Vec3, z: Vec3) -> Mat3 {
+        Mat3 { cols: [x, y, z] }
+    }
+
+    pub fn from_quat(q: Quat) -> Mat3 {
+        let (x2, y2, z2) = (q.x + q.x, q.y + q.y, q.z + q.z);
+        let (xx, xy, xz) = (q.x * x2, q.x * y2, q.x * z2);
+        let (yy, yz, zz) = (q.y * y2, q.y * z2, q.z * z2);
+        let (wx, wy, wz) = (q.w * x2, q.w * y2, q.w * z2);
+        Mat3::from_cols(
+            Vec3::new(1.0 - (yy + zz), xy + wz, xz - wy),
+            Vec3::new(xy - wz, 1.0 - (xx + zz), yz + wx),
+            Vec3::new(xz + wy, yz - wx, 1.0 - (xx + yy))
+        )
+    }
+
+    pub fn from_scale(scale: Vec3) -> Mat3 {
+        Mat3::from_cols(Vec3::X * scale.x, Vec3::Y * scale.y, Vec3::Z * scale.z)
+    }
+
+    /**
+     * a * b^T
+     **/
+    pub fn from_outer_product(a: Vec3, b: Vec3) -> Mat3 {
+        Mat3::from_cols(a * b.x, a * b.y, a * b.z)
+    }
+
+    /**
+     * Upper left 3x3 of a 4x4 matrix
+     **/
+    pub fn from_mat4(m: &Mat4) -> Mat3 {
+        Mat3::from_cols(m.cols[0].truncate(), m.cols[1].truncate(), m.cols[2].truncate())
+    }
+
+    #[inline]
+    pub fn row(&self, index: usize) -> Vec3 {
+        Vec3::new(self.cols[0][index], self.cols[1][index], self.cols[2][index])
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        Mat3::from_cols(self.row(0), self.row(1), self.row(2))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.cols[2].dot(self.cols[0].cross(self.cols[1]))
+    }
+
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let [a, b, c] = self.cols;
+        let inv_det = 1.0 / det;
+        Some(Mat3::from_cols(b.cross(c) * inv_det, c.cross(a) * inv_det, a.cross(b) * inv_det).transpose())
+    }
+
+    #[inline]
+    pub fn mul_vec3(&self, v: Vec3) -> Vec3 {
+        self.cols[0] * v.x + self.cols[1] * v.y + self.cols[2] * v.z
+    }
+
+    pub fn to_cols_array(&self) -> [f32; 9] {
+        let [a, b, c] = self.cols;
+        [a.x, a.y, a.z, b.x, b.y, b.z, c.x, c.y, c.z]
+    }
+}
+
+impl Default for Mat3 {
+    fn default() -> Mat3 {
+        Mat3::IDENTITY
+    }
+}
+
+impl Mul for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: Mat3) -> Mat3 {
+        Mat3::from_cols(self.mul_vec3(rhs.cols[0]), self.mul_vec3(rhs.cols[1]), self.mul_vec3(rhs.cols[2]))
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+    #[inline]
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.mul_vec3(rhs)
+    }
+}
+
+impl Mul<f32> for Mat3 {
+    type Output = Mat3;
+    fn mul(self, rhs: f32) -> Mat3 {
+        Mat3::from_cols(self.cols[0] * rhs, self.cols[1] * rhs, self.cols[2] * rhs)
+    }
+}
+
+impl Add for Mat3 {
+    type Output = Mat3;
+    fn add(self, rhs: Mat3) -> Mat3 {
+        Mat3::from_cols(self.cols[0] + rhs.cols[0], self.cols[1] + rhs.cols[1], self.cols[2] + rhs.cols[2])
+    }
+}
+
+impl Sub for Mat3 {
+    type Output = Mat3;
+    fn sub(self, rhs: Mat3) -> Mat3 {
+        Mat3::from_cols(self.cols[0] - rhs.cols[0], self.cols[1] - rhs.cols[1], self.cols[2] - rhs.cols[2])
+    }
+}
+
+/**
+ * Column major 4x4 matrix, laid out the way OpenGL and Vulkan expect uniform data
+ **/
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Mat4 {
+    pub cols: [Vec4; 4],
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 = Mat4 { cols: [Vec4::X, Vec4::Y, Vec4::Z, Vec4::W] };
+    pub const ZERO: Mat4 = Mat4 { cols: [Vec4::ZERO; 4] };
+
+    #[inline]
+    pub fn from_cols(x: Vec4, y: Vec4, z: Vec4, w: Vec4) -> Mat4 {
+        Mat4 { cols: [x, y, z, w] }
+    }
+
+    pub fn from_cols_array(a: &[f32; 16]) -> Mat4 {
+        Mat4::from_cols(
+            Vec4::new(a[0], a[1], a[2], a[3]),
+            Vec4::new(a[4], a[5], a[6], a[7]),
+            Vec4::new(a[8], a[9], a[10], a[11]),
+            Vec4::new(a[12], a[13], a[14], a[15])
+        )
+    }
+
+    pub fn from_translation(t: Vec3) -> Mat4 {
+        Mat4::from_cols(Vec4::X, Vec4::Y, Vec4::Z, t.extend(1.0))
+    }
+
+    pub fn from_scale(s: Vec3) -> Mat4 {
+        Mat4::from_cols(Vec4::X * s.x, Vec4::Y * s.y, Vec4::Z * s.z, Vec4::W)
+    }
+
+    pub fn from_quat(q: Quat) -> Mat4 {
+        Mat4::from_mat3(&Mat3::from_quat(q))
+    }
+
+    pub fn from_mat3(m: &Mat3) -> Mat4 {
+        Mat4::from_cols(m.cols[0].extend(0.0), m.cols[1].extend(0.0), m.cols[2].extend(0.0), Vec4::W)
+    }
+
+    /**
+     * Matrix that scales, then rotates, then translates
+     **/
+    pub fn from_scale_rotation_translation(scale: Vec3, rotation: Quat, translation: Vec3) -> Mat4 {
+        let r = Mat3::from_quat(rotation);
+        Mat4::from_cols(
+            (r.cols[0] * scale.x).extend(0.0),
+            (r.cols[1] * scale.y).extend(0.0),
+            (r.cols[2] * scale.z).extend(0.0),
+            translation.extend(1.0)
+        )
+    }
+
+    /**
+     * Splits an affine matrix into scale, rotation and translation
+     * Any shear in the matrix is lost
+     **/
+    pub fn to_scale_rotation_translation(&self) -> (Vec3, Quat, Vec3) {
+        let m = Mat3::from_mat4(self);
+        let mut scale = Vec3::new(m.cols[0].length(), m.cols[1].length(), m.cols[2].length());
+        if m.determinant() < 0.0 {
+            scale.x = -scale.x;
+        }
+        let safe = |s: f32| if s != 0.0 { 1.0 / s } else { 1.0 };
+        let rotation = Mat3::from_cols(m.cols[0] * safe(scale.x), m.cols[1] * safe(scale.y), m.cols[2] * safe(scale.z));
+        (scale, Quat::from_mat3(&rotation), self.cols[3].truncate())
+    }
+
+    /**
+     * Right handed view matrix looking from `eye` towards `target`
+     **/
+    pub fn look_at_rh(eye: Vec3, target: Vec3, up: Vec3) -> Mat4 {
+        Mat4::look_to_rh(eye, target - eye, up)
+    }
+
+    pub fn look_to_rh(eye: Vec3, dir: Vec3, up: Vec3) -> Mat4 {
+        let f = dir.normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+        Mat4::from_cols(
+            Vec4::new(s.x, u.x, -f.x, 0.0),
+            Vec4::new(s.y, u.y, -f.y, 0.0),
+            Vec4::new(s.z, u.z, -f.z, 0.0),
+            Vec4::new(-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0)
+        )
+    }
+
+    /**
+     * Right handed perspective projection mapping depth to -1..1 (OpenGL convention)
+     * `fov_y` is in radians
+     **/
+    pub fn perspective_rh_gl(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        let range = near - far;
+        Mat4::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, (far + near) / range, -1.0),
+            Vec4::new(0.0, 0.0, 2.0 * far * near / range, 0.0)
+        )
+    }
+
+    /**
+     * Right handed perspective projection mapping depth to 0..1 (Vulkan/DirectX convention)
+     * `fov_y` is in radians
+     **/
+    pub fn perspective_rh_zo(fov_y: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
+        let f = 1.0 / (fov_y * 0.5).tan();
+        let range = near - far;
+        Mat4::from_cols(
+            Vec4::new(f / aspect, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, f, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, far / range, -1.0),
+            Vec4::new(0.0, 0.0, far * near / range, 0.0)
+        )
+    }
+
+    /**
+     * Right handed orthographic projection mapping depth to -1..1 (OpenGL convention)
+     **/
+    pub fn orthographic_rh_gl(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        let (rl, tb, fnr) = (right - left, top - bottom, far - near);
+        Mat4::from_cols(
+            Vec4::new(2.0 / rl, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / tb, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, -2.0 / fnr, 0.0),
+            Vec4::new(-(right + left) / rl, -(top + bottom) / tb, -(far + near) / fnr, 1.0)
+        )
+    }
+
+    /**
+     * Right handed orthographic projection mapping depth to 0..1 (Vulkan/DirectX convention)
+     **/
+    pub fn orthographic_rh_zo(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+        let (rl, tb, fnr) = (right - left, top - bottom, far - near);
+        Mat4::from_cols(
+            Vec4::new(2.0 / rl, 0.0, 0.0, 0.0),
+            Vec4::new(0.0, 2.0 / tb, 0.0, 0.0),
+            Vec4::new(0.0, 0.0, -1.0 / fnr, 0.0),
+            Vec4::new(-(right + left) / rl, -(top + bottom) / tb, -near / fnr, 1.0)
+        )
+    }
+
+    #[inline]
+    pub fn row(&self, index: usize) -> Vec4 {
+        Vec4::new(self.cols[0][index], self.cols[1][index], self.cols[2][index], self.cols[3][index])
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        Mat4::from_cols(self.row(0), self.row(1), self.row(2), self.row(3))
+    }
+
+    pub fn determinant(&self) -> f32 {
+        let (s, c) = self.cofactor_terms();
+        s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0]
+    }
+
+    pub fn inverse(&self) -> Option<Mat4> {
+        let (s, c) = self.cofactor_terms();
+        let det = s[0] * c[5] - s[1] * c[4] + s[2] * c[3] + s[3] * c[2] - s[4] * c[1] + s[5] * c[0];
+        if det.abs() < f32::EPSILON {
+            return None;
+        }
+        let a = |col: usize, row: usize| self.cols[col][row];
+        let inv = 1.0 / det;
+        Some(Mat4::from_cols(
+            Vec4::new(
+                a(1, 1) * c[5] - a(1, 2) * c[4] + a(1, 3) * c[3],
+                -a(0, 1) * c[5] + a(0, 2) * c[4] - a(0, 3) * c[3],
+                a(3, 1) * s[5] - a(3, 2) * s[4] + a(3, 3) * s[3],
+                -a(2, 1) * s[5] + a(2, 2) * s[4] - a(2, 3) * s[3]
+            ) * inv,
+            Vec4::new(
+                -a(1, 0) * c[5] + a(1, 2) * c[2] - a(1, 3) * c[1],
+                a(0, 0) * c[5] - a(0, 2) * c[2] + a(0, 3) * c[1],
+                -a(3, 0) * s[5] + a(3, 2) * s[2] - a(3, 3) * s[1],
+                a(2, 0) * s[5] - a(2, 2) * s[2] + a(2, 3) * s[1]
+            ) * inv,
+            Vec4::new(
+                a(1, 0) * c[4] - a(1, 1) * c[2] + a(1, 3) * c[0],
+                -a(0, 0) * c[4] + a(0, 1) * c[2] - a(0, 3) * c[0],
+                a(3, 0) * s[4] - a(3, 1) * s[2] + a(3, 3) * s[0],
+                -a(2, 0) * s[4] + a(2, 1) * s[2] - a(2, 3) * s[0]
+            ) * inv,
+            Vec4::new(
+                -a(1, 0) * c[3] + a(1, 1) * c[1] - a(1, 2) * c[0],
+                a(0, 0) * c[3] - a(0, 1) * c[1] + a(0, 2) * c[0],
+                -a(3, 0) * s[3] + a(3, 1) * s[1] - a(3, 2) * s[0],
+                a(2, 0) * s[3] - a(2, 1) * s[1] + a(2, 2) * s[0]
+            ) * inv
+        ))
+    }
+
+    //2x2 sub-determinants shared by determinant() and inverse()
+    fn cofactor_terms(&self) -> ([f32; 6], [f32; 6]) {
+        let a = |col: usize, row: usize| self.cols[col][row];
+        ([
+            a(0, 0) * a(1, 1) - a(1, 0) * a(0, 1),
+            a(0, 0) * a(1, 2) - a(1, 0) * a(0, 2),
+            a(0, 0) * a(1, 3) - a(1, 0) * a(0, 3),
+            a(0, 1) * a(1, 2) - a(1, 1) * a(0, 2),
+            a(0, 1) * a(1, 3) - a(1, 1) * a(0, 3),
+            a(0, 2) * a(1, 3) - a(1, 2) * a(0, 3)
+        ], [
+            a(2, 0) * a(3, 1) - a(3, 0) * a(2, 1),
+            a(2, 0) * a(3, 2) - a(3, 0) * a(2, 2),
+            a(2, 0) * a(3, 3) - a(3, 0) * a(2, 3),
+            a(2, 1) * a(3, 2) - a(3, 1) * a(2, 2),
+            a(2, 1) * a(3, 3) - a(3, 1) * a(2, 3),
+            a(2, 2) * a(3, 3) - a(3, 2) * a(2, 3)
+        ])
+    }
+
+    #[inline]
+    pub fn mul_vec4(&self, v: Vec4) -> Vec4 {
+        self.cols[0] * v.x + self.cols[1] * v.y + self.cols[2] * v.z + self.cols[3] * v.w
+    }
+
+    /**
+     * Transforms a point (w = 1), without a perspective divide
+     **/
+    #[inline]
+    pub fn transform_point3(&self, p: Vec3) -> Vec3 {
+        self.mul_vec4(p.extend(1.0)).truncate()
+    }
+
+    /**
+     * Transforms a direction (w = 0), ignoring translation
+     **/
+    #[inline]
+    pub fn transform_vector3(&self, v: Vec3) -> Vec3 {
+        self.mul_vec4(v.extend(0.0)).truncate()
+    }
+
+    /**
+     * Transforms a point and divides by w, for use with projection matrices
+     **/
+    pub fn project_point3(&self, p: Vec3) -> Vec3 {
+        let v = self.mul_vec4(p.extend(1.0));
+        v.truncate() / v.w
+    }
+
+    #[inline]
+    pub fn translation(&self) -> Vec3 {
+        self.cols[3].truncate()
+    }
+
+    pub fn to_cols_array(&self) -> [f32; 16] {
+        let mut out = [0.0; 16];
+        for (i, col) in self.cols.iter().enumerate() {
+            out[i * 4..i * 4 + 4].copy_from_slice(col.as_array());
+        }
+        out
+    }
+
+    pub fn approx_eq(&self, rhs: &Mat4, epsilon: f32) -> bool {
+        self.cols.iter().zip(rhs.cols.iter()).all(|(a, b)| a.approx_eq(*b, epsilon))
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Mat4 {
+        Mat4::IDENTITY
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        Mat4::from_cols(
+            self.mul_vec4(rhs.cols[0]),
+            self.mul_vec4(rhs.cols[1]),
+            self.mul_vec4(rhs.cols[2]),
+            self.mul_vec4(rhs.cols[3])
+        )
+    }
+}
+
+impl MulAssign for Mat4 {
+    fn mul_assign(&mut self, rhs: Mat4) {
+        *self = *self * rhs;
+    }
+}
+
+impl Mul<Vec4> for Mat4 {
+    type Output = Vec4;
+    #[inline]
+    fn mul(self, rhs: Vec4) -> Vec4 {
+        self.mul_vec4(rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::testing::{ Rng, CASES };
+
+    const EPSILON: f32 = 1e-4;
+
+    fn assert_mat4(a: &Mat4, b: &Mat4, epsilon: f32) {
+        assert!(a.approx_eq(b, epsilon), "\n{:?}\n!=\n{:?}", a, b);
+    }
+
+    #[test]
+    fn identity_inverse_is_identity() {
+        assert_eq!(Mat4::IDENTITY.inverse(), Some(Mat4::IDENTITY));
+        assert_eq!(Mat3::IDENTITY.inverse(), Some(Mat3::IDENTITY));
+    }
+
+    #[test]
+    fn inverse_of_translation_and_scale() {
+        let t = Mat4::from_translation(Vec3::new(1.0, -2.0, 3.0));
+        assert_mat4(&t.inverse().unwrap(), &Mat4::from_translation(Vec3::new(-1.0, 2.0, -3.0)), EPSILON);
+        let s = Mat4::from_scale(Vec3::new(2.0, 4.0, -0.5));
+        assert_mat4(&s.inverse().unwrap(), &Mat4::from_scale(Vec3::new(0.5, 0.25, -2.0)), EPSILON);
+    }
+
+    #[test]
+    fn singular_matrices_have_no_inverse() {
+        assert_eq!(Mat4::ZERO.inverse(), None);
+        assert_eq!(Mat4::from_scale(Vec3::new(1.0, 0.0, 1.0)).inverse(), None);
+        assert_eq!(Mat3::from_scale(Vec3::new(1.0, 1.0, 0.0)).inverse(), None);
+    }
+
+    #[test]
+    fn inverse_round_trips() {
+        let mut rng = Rng::new(10);
+        for _ in 0..CASES {
+            let m = rng.transform();
+            let inv = m.inverse().expect("generated transforms are invertible");
+            assert_mat4(&(m * inv), &Mat4::IDENTITY, EPSILON);
+            assert_mat4(&(inv * m), &Mat4::IDENTITY, EPSILON);
+            //the translation column grows with the matrix, so compare relative to it
+            assert_mat4(&inv.inverse().unwrap(), &m, 1e-3 * m.translation().length().max(1.0));
+        }
+    }
+
+    #[test]
+    fn mat3_inverse_round_trips() {
+        let mut rng = Rng::new(11);
+        for _ in 0..CASES {
+            let m = Mat3::from_mat4(&rng.transform());
+            let p = m * m.inverse().unwrap();
+            for i in 0..3 {
+                assert!(p.cols[i].approx_eq(Mat3::IDENTITY.cols[i], EPSILON), "{:?}", m);
+            }
+        }
+    }
+
+    #[test]
+    fn determinant_of_scale_is_product() {
+        let s = Vec3::new(2.0, 3.0, -4.0);
+        assert!((Mat4::from_scale(s).determinant() + 24.0).abs() < EPSILON);
+        assert!((Mat3::from_scale(s).determinant() + 24.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn transpose_twice_is_unchanged() {
+        let m = Mat4::from_cols_array(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0]);
+        assert_eq!(m.transpose().transpose(), m);
+        assert_eq!(m.transpose().row(0), m.cols[0]);
+        assert_eq!(Mat4::from_cols_array(&m.to_cols_array()), m);
+    }
+
+    #[test]
+    fn look_at_down_negative_z_is_a_translation() {
+        let view = Mat4::look_at_rh(Vec3::new(0.0, 0.0, 5.0), Vec3::ZERO, Vec3::Y);
+        assert_mat4(&view, &Mat4::from_translation(Vec3::new(0.0, 0.0, -5.0)), EPSILON);
+        let origin = Mat4::look_at_rh(Vec3::ZERO, -Vec3::Z, Vec3::Y);
+        assert_mat4(&origin, &Mat4::IDENTITY, EPSILON);
+    }
+
+    #[test]
+    fn look_at_along_x() {
+        let view = Mat4::look_at_rh(Vec3::ZERO, Vec3::X, Vec3::Y);
+        let expected = Mat4::from_cols_array(&[
+            0.0, 0.0, -1.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ]);
+        assert_mat4(&view, &expected, EPSILON);
+    }
+
+    #[test]
+    fn look_at_puts_target_in_front() {
+        let mut rng = Rng::new(12);
+        for _ in 0..CASES {
+            let eye = rng.vec3(-50.0, 50.0);
+            let target = eye + rng.direction() * rng.range(1.0, 50.0);
+            //skip directions too close to the up vector to build a basis from
+            if (target - eye).normalize().dot(Vec3::Y).abs() > 0.99 {
+                continue;
+            }
+            let view = Mat4::look_at_rh(eye, target, Vec3::Y);
+            let p = view.transform_point3(target);
+            assert!(p.truncate().length() < 1e-3, "{}", p);
+            assert!((p.z + eye.distance(target)).abs() < 1e-3, "{}", p);
+            assert!(view.transform_point3(eye).length() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn perspective_gl_known_matrix() {
+        let proj = Mat4::perspective_rh_gl(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 3.0);
+        let expected = Mat4::from_cols_array(&[
+            1.0, 0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            0.0, 0.0, -2.0, -1.0,
+            0.0, 0.0, -3.0, 0.0
+        ]);
+        assert_mat4(&proj, &expected, EPSILON);
+        assert!((proj.project_point3(Vec3::new(0.0, 0.0, -1.0)).z + 1.0).abs() < EPSILON);
+        assert!((proj.project_point3(Vec3::new(0.0, 0.0, -3.0)).z - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn perspective_zo_maps_depth_to_zero_one() {
+        let proj = Mat4::perspective_rh_zo(1.0, 16.0 / 9.0, 0.1, 100.0);
+        assert!(proj.project_point3(Vec3::new(0.0, 0.0, -0.1)).z.abs() < EPSILON);
+        assert!((proj.project_point3(Vec3::new(0.0, 0.0, -100.0)).z - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn perspective_edges_of_view() {
+        //at 90 degrees the top of the view at depth d is at height d
+        let proj = Mat4::perspective_rh_gl(std::f32::consts::FRAC_PI_2, 2.0, 0.5, 10.0);
+        let p = proj.project_point3(Vec3::new(8.0, 4.0, -4.0));
+        assert!((p.x - 1.0).abs() < EPSILON && (p.y - 1.0).abs() < EPSILON, "{}", p);
+    }
+
+    #[test]
+    fn orthographic_maps_box_to_clip_cube() {
+        let gl = Mat4::orthographic_rh_gl(-4.0, 4.0, -2.0, 2.0, 1.0, 11.0);
+        assert!(gl.transform_point3(Vec3::new(-4.0, -2.0, -1.0)).approx_eq(Vec3::new(-1.0, -1.0, -1.0), EPSILON));
+        assert!(gl.transform_point3(Vec3::new(4.0, 2.0, -11.0)).approx_eq(Vec3::new(1.0, 1.0, 1.0), EPSILON));
+        let zo = Mat4::orthographic_rh_zo(-4.0, 4.0, -2.0, 2.0, 1.0, 11.0);
+        assert!(zo.transform_point3(Vec3::new(-4.0, -2.0, -1.0)).approx_eq(Vec3::new(-1.0, -1.0, 0.0), EPSILON));
+        assert!(zo.transform_point3(Vec3::new(4.0, 2.0, -11.0)).approx_eq(Vec3::new(1.0, 1.0, 1.0), EPSILON));
+    }
+
+    #[test]
+    fn scale_rotation_translation_round_trips() {
+        let mut rng = Rng::new(13);
+        for _ in 0..CASES {
+            let (scale, rotation, translation) = (rng.vec3(0.25, 4.0), rng.quat(), rng.vec3(-100.0, 100.0));
+            let m = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+            let (s, r, t) = m.to_scale_rotation_translation();
+            assert!(s.approx_eq(scale, EPSILON), "{} {}", s, scale);
+            assert!(r.approx_eq(rotation, EPSILON), "{:?} {:?}", r, rotation);
+            assert!(t.approx_eq(translation, EPSILON), "{} {}", t, translation);
+        }
+    }
+
+    #[test]
+    fn transform_point_and_vector() {
+        let m = Mat4::from_translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::from_scale(Vec3::splat(2.0));
+        assert_eq!(m.transform_point3(Vec3::ONE), Vec3::new(3.0, 4.0, 5.0));
+        assert_eq!(m.transform_vector3(Vec3::ONE), Vec3::splat(2.0));
+    }
+}
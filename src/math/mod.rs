@@ -0,0 +1,12 @@
+pub mod vector;
+pub mod matrix;
+pub mod quaternion;
+pub mod geometry;
+
+pub use self::vector::{ Vec2, Vec3, Vec4 };
+pub use self::matrix::{ Mat3, Mat4 };
+pub use self::quaternion::Quat;
+pub use self::geometry::{ Aabb, Sphere, Plane, Ray, Frustum };
+
+#[cfg(test)]
+pub(crate) mod testing;
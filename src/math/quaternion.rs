@@ -0,0 +1,397 @@
+use std::ops::{ Mul, MulAssign, Neg };
+
+use serde::{ Deserialize, Serialize };
+
+use crate::math::matrix::Mat3;
+use crate::math::vector::{ Vec3, Vec4 };
+
+/**
+ * Rotation quaternion, stored as x, y, z (vector part) and w (scalar part)
+ * Aligned to 16 bytes like Vec4
+ **/
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Quat {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quat {
+    pub const IDENTITY: Quat = Quat { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    #[inline]
+    pub const fn from_xyzw(x: f32, y: f32, z: f32, w: f32) -> Quat {
+        Quat { x, y, z, w }
+    }
+
+    /**
+     * Rotation of `angle` radians around `axis`, the axis doesn't need to be normalized
+     **/
+    pub fn from_axis_angle(axis: Vec3, angle: f32) -> Quat {
+        let axis = axis.normalize();
+        let (s, c) = (angle * 0.5).sin_cos();
+        Quat { x: axis.x * s, y: axis.y * s, z: axis.z * s, w: c }
+    }
+
+    pub fn from_rotation_x(angle: f32) -> Quat {
+        Quat::from_axis_angle(Vec3::X, angle)
+    }
+
+    pub fn from_rotation_y(angle: f32) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, angle)
+    }
+
+    pub fn from_rotation_z(angle: f32) -> Quat {
+        Quat::from_axis_angle(Vec3::Z, angle)
+    }
+
+    /**
+     * Rotation from euler angles in radians, applied in yaw (y), pitch (x), roll (z) order
+     **/
+    pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Quat {
+        Quat::from_rotation_y(yaw) * Quat::from_rotation_x(pitch) * Quat::from_rotation_z(roll)
+    }
+
+    /**
+     * Shortest rotation that takes unit vector `from` to unit vector `to`
+     **/
+    pub fn from_rotation_arc(from: Vec3, to: Vec3) -> Quat {
+        let d = from.dot(to);
+        if d < -0.999_999 {
+            return Quat::from_axis_angle(from.any_orthogonal(), std::f32::consts::PI);
+        }
+        let c = from.cross(to);
+        Quat { x: c.x, y: c.y, z: c.z, w: 1.0 + d }.normalize()
+    }
+
+    /**
+     * Converts a pure rotation matrix (no scale) to a quaternion
+     **/
+    pub fn from_mat3(m: &Mat3) -> Quat {
+        let r = |c: usize, r: usize| m.cols[c][r];
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Quat { x: (r(1, 2) - r(2, 1)) / s, y: (r(2, 0) - r(0, 2)) / s, z: (r(0, 1) - r(1, 0)) / s, w: 0.25 * s }
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = (1.0 + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.0;
+            Quat { x: 0.25 * s, y: (r(1, 0) + r(0, 1)) / s, z: (r(2, 0) + r(0, 2)) / s, w: (r(1, 2) - r(2, 1)) / s }
+        } else if r(1, 1) > r(2, 2) {
+            let s = (1.0 + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.0;
+            Quat { x: (r(1, 0) + r(0, 1)) / s, y: 0.25 * s, z: (r(2, 1) + r(1, 2)) / s, w: (r(2, 0) - r(0, 2)) / s }
+        } else {
+            let s = (1.0 + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.0;
+            Quat { x: (r(2, 0) + r(0, 2)) / s, y: (r(2, 1) + r(1, 2)) / s, z: 0.25 * s, w: (r(0, 1) - r(1, 0)) / s }
+        };
+        q.normalize()
+    }
+
+    #[inline]
+    pub fn dot(self, rhs: Quat) -> f32 {
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    }
+
+    #[inline]
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /**
+     * Unit length copy of this quaternion, or the identity if it has no length
+     **/
+    pub fn normalize(self) -> Quat {
+        let len = self.length();
+        if len > 0.0 {
+            Quat { x: self.x / len, y: self.y / len, z: self.z / len, w: self.w / len }
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    #[inline]
+    pub fn conjugate(self) -> Quat {
+        Quat { x: -self.x, y: -self.y, z: -self.z, w: self.w }
+    }
+
+    pub fn inverse(self) -> Quat {
+        let len2 = self.dot(self);
+        if len2 > 0.0 {
+            let c = self.conjugate();
+            Quat { x: c.x / len2, y: c.y / len2, z: c.z / len2, w: c.w / len2 }
+        } else {
+            Quat::IDENTITY
+        }
+    }
+
+    /**
+     * Axis and angle in radians of this rotation
+     **/
+    pub fn to_axis_angle(self) -> (Vec3, f32) {
+        let q = self.normalize();
+        let s = (1.0 - q.w * q.w).max(0.0).sqrt();
+        let angle = 2.0 * q.w.clamp(-1.0, 1.0).acos();
+        if s < 1e-6 {
+            (Vec3::X, angle)
+        } else {
+            (Vec3::new(q.x / s, q.y / s, q.z / s), angle)
+        }
+    }
+
+    /**
+     * Angle in radians between two rotations
+     **/
+    pub fn angle_between(self, rhs: Quat) -> f32 {
+        2.0 * self.dot(rhs).abs().min(1.0).acos()
+    }
+
+    #[inline]
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let u = Vec3::new(self.x, self.y, self.z);
+        let t = u.cross(v) * 2.0;
+        v + t * self.w + u.cross(t)
+    }
+
+    /**
+     * Normalized linear interpolation, cheaper than slerp and fine for small angles
+     **/
+    pub fn nlerp(self, rhs: Quat, t: f32) -> Quat {
+        let rhs = if self.dot(rhs) < 0.0 { -rhs } else { rhs };
+        Quat {
+            x: self.x + (rhs.x - self.x) * t,
+            y: self.y + (rhs.y - self.y) * t,
+            z: self.z + (rhs.z - self.z) * t,
+            w: self.w + (rhs.w - self.w) * t
+        }.normalize()
+    }
+
+    /**
+     * Spherical linear interpolation along the shortest path
+     **/
+    pub fn slerp(self, rhs: Quat, t: f32) -> Quat {
+        let mut d = self.dot(rhs);
+        let rhs = if d < 0.0 {
+            d = -d;
+            -rhs
+        } else {
+            rhs
+        };
+        if d > 0.9995 {
+            return self.nlerp(rhs, t);
+        }
+        let theta = d.acos();
+        let sin_theta = theta.sin();
+        let a = ((1.0 - t) * theta).sin() / sin_theta;
+        let b = (t * theta).sin() / sin_theta;
+        Quat {
+            x: self.x * a + rhs.x * b,
+            y: self.y * a + rhs.y * b,
+            z: self.z * a + rhs.z * b,
+            w: self.w * a + rhs.w * b
+        }
+    }
+
+    #[inline]
+    pub fn to_vec4(self) -> Vec4 {
+        Vec4::new(self.x, self.y, self.z, self.w)
+    }
+
+    #[inline]
+    pub fn to_array(self) -> [f32; 4] {
+        [self.x, self.y, self.z, self.w]
+    }
+
+    #[inline]
+    pub fn approx_eq(self, rhs: Quat, epsilon: f32) -> bool {
+        //q and -q are the same rotation
+        (1.0 - self.dot(rhs).abs()) <= epsilon
+    }
+}
+
+impl Default for Quat {
+    #[inline]
+    fn default() -> Quat {
+        Quat::IDENTITY
+    }
+}
+
+impl Mul for Quat {
+    type Output = Quat;
+    #[inline]
+    fn mul(self, rhs: Quat) -> Quat {
+        Quat {
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z
+        }
+    }
+}
+
+impl MulAssign for Quat {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Quat) {
+        *self = *self * rhs;
+    }
+}
+
+impl Mul<Vec3> for Quat {
+    type Output = Vec3;
+    #[inline]
+    fn mul(self, rhs: Vec3) -> Vec3 {
+        self.rotate(rhs)
+    }
+}
+
+impl Neg for Quat {
+    type Output = Quat;
+    #[inline]
+    fn neg(self) -> Quat {
+        Quat { x: -self.x, y: -self.y, z: -self.z, w: -self.w }
+    }
+}
+
+impl From<[f32; 4]> for Quat {
+    #[inline]
+    fn from(a: [f32; 4]) -> Quat {
+        Quat { x: a[0], y: a[1], z: a[2], w: a[3] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::{ FRAC_PI_2, FRAC_PI_4, PI };
+    use crate::math::testing::{ Rng, CASES };
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_quat(a: Quat, b: Quat) {
+        assert!(a.approx_eq(b, EPSILON), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn times_inverse_is_identity() {
+        let mut rng = Rng::new(20);
+        for _ in 0..CASES {
+            let q = rng.quat();
+            assert_quat(q * q.inverse(), Quat::IDENTITY);
+            assert_quat(q.inverse() * q, Quat::IDENTITY);
+            //inverse also undoes quaternions that aren't unit length
+            let scaled = Quat::from_xyzw(q.x * 3.0, q.y * 3.0, q.z * 3.0, q.w * 3.0);
+            let p = scaled * scaled.inverse();
+            assert!(p.to_vec4().approx_eq(Quat::IDENTITY.to_vec4(), EPSILON), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn normalize_gives_unit_length() {
+        let mut rng = Rng::new(21);
+        for _ in 0..CASES {
+            let v = rng.vec3(-10.0, 10.0);
+            let q = Quat::from_xyzw(v.x, v.y, v.z, rng.range(-10.0, 10.0)).normalize();
+            assert!((q.length() - 1.0).abs() < EPSILON, "{:?}", q);
+        }
+        assert_eq!(Quat::from_xyzw(0.0, 0.0, 0.0, 0.0).normalize(), Quat::IDENTITY);
+    }
+
+    #[test]
+    fn rotates_axes() {
+        assert!(Quat::from_rotation_z(FRAC_PI_2).rotate(Vec3::X).approx_eq(Vec3::Y, EPSILON));
+        assert!(Quat::from_rotation_x(FRAC_PI_2).rotate(Vec3::Y).approx_eq(Vec3::Z, EPSILON));
+        assert!(Quat::from_rotation_y(FRAC_PI_2).rotate(Vec3::Z).approx_eq(Vec3::X, EPSILON));
+    }
+
+    #[test]
+    fn rotate_matches_matrix() {
+        let mut rng = Rng::new(22);
+        for _ in 0..CASES {
+            let (q, v) = (rng.quat(), rng.vec3(-10.0, 10.0));
+            assert!(q.rotate(v).approx_eq(Mat3::from_quat(q) * v, 1e-4), "{:?} {}", q, v);
+        }
+    }
+
+    #[test]
+    fn mat3_round_trips() {
+        let mut rng = Rng::new(23);
+        for _ in 0..CASES {
+            let q = rng.quat();
+            assert_quat(Quat::from_mat3(&Mat3::from_quat(q)), q);
+        }
+    }
+
+    #[test]
+    fn mat3_round_trips_half_turns() {
+        //half turns have a trace of -1 and take the other branches of from_mat3
+        for axis in [Vec3::X, Vec3::Y, Vec3::Z, Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 1.0)].iter() {
+            let q = Quat::from_axis_angle(*axis, PI);
+            assert_quat(Quat::from_mat3(&Mat3::from_quat(q)), q);
+        }
+    }
+
+    #[test]
+    fn slerp_hits_endpoints() {
+        let mut rng = Rng::new(24);
+        for _ in 0..CASES {
+            let (a, b) = (rng.quat(), rng.quat());
+            assert_quat(a.slerp(b, 0.0), a);
+            assert_quat(a.slerp(b, 1.0), b);
+        }
+    }
+
+    #[test]
+    fn slerp_halfway() {
+        let halfway = Quat::IDENTITY.slerp(Quat::from_rotation_z(FRAC_PI_2), 0.5);
+        assert_quat(halfway, Quat::from_rotation_z(FRAC_PI_4));
+    }
+
+    #[test]
+    fn slerp_takes_the_short_way() {
+        let a = Quat::from_rotation_z(0.1);
+        let b = -Quat::from_rotation_z(0.3);
+        assert_quat(a.slerp(b, 0.5), Quat::from_rotation_z(0.2));
+    }
+
+    #[test]
+    fn slerp_moves_at_constant_speed() {
+        let mut rng = Rng::new(25);
+        for _ in 0..CASES {
+            let (a, b, t) = (rng.quat(), rng.quat(), rng.unit());
+            let total = a.angle_between(b);
+            let q = a.slerp(b, t);
+            assert!((q.length() - 1.0).abs() < 1e-4, "{:?}", q);
+            assert!((a.angle_between(q) - total * t).abs() < 2e-3, "{:?} {:?} {}", a, b, t);
+        }
+    }
+
+    #[test]
+    fn rotation_arc_takes_from_to() {
+        let mut rng = Rng::new(26);
+        for _ in 0..CASES {
+            let (from, to) = (rng.direction(), rng.direction());
+            assert!(Quat::from_rotation_arc(from, to).rotate(from).approx_eq(to, 1e-4), "{} {}", from, to);
+        }
+        let opposite = Quat::from_rotation_arc(Vec3::X, -Vec3::X);
+        assert!(opposite.rotate(Vec3::X).approx_eq(-Vec3::X, EPSILON));
+    }
+
+    #[test]
+    fn axis_angle_round_trips() {
+        let mut rng = Rng::new(27);
+        for _ in 0..CASES {
+            let (axis, angle) = (rng.direction(), rng.range(0.01, PI - 0.01));
+            let (a, t) = Quat::from_axis_angle(axis, angle).to_axis_angle();
+            assert!(a.approx_eq(axis, 1e-3) && (t - angle).abs() < 1e-3, "{} {} -> {} {}", axis, angle, a, t);
+        }
+    }
+
+    #[test]
+    fn euler_order_is_yaw_pitch_roll() {
+        let q = Quat::from_euler(FRAC_PI_2, 0.0, 0.0);
+        assert_quat(q, Quat::from_rotation_y(FRAC_PI_2));
+        let q = Quat::from_euler(0.3, 0.2, 0.1);
+        assert_quat(q, Quat::from_rotation_y(0.3) * Quat::from_rotation_x(0.2) * Quat::from_rotation_z(0.1));
+    }
+}
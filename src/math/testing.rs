@@ -0,0 +1,68 @@
+use crate::math::matrix::Mat4;
+use crate::math::quaternion::Quat;
+use crate::math::vector::Vec3;
+
+/**
+ * Number of generated inputs each property check runs over
+ **/
+pub const CASES: usize = 500;
+
+/**
+ * Xorshift generator for property checks, seeded so a failure reproduces on every run
+ **/
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /**
+     * Uniform in 0..1
+     **/
+    pub fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.unit()
+    }
+
+    pub fn vec3(&mut self, min: f32, max: f32) -> Vec3 {
+        Vec3::new(self.range(min, max), self.range(min, max), self.range(min, max))
+    }
+
+    /**
+     * Unit vector, never close to zero length before normalizing
+     **/
+    pub fn direction(&mut self) -> Vec3 {
+        loop {
+            let v = self.vec3(-1.0, 1.0);
+            if v.length() > 0.1 {
+                return v.normalize();
+            }
+        }
+    }
+
+    pub fn quat(&mut self) -> Quat {
+        Quat::from_axis_angle(self.direction(), self.range(-std::f32::consts::PI, std::f32::consts::PI))
+    }
+
+    /**
+     * Scale, rotate and translate with every scale axis far enough from 0 to invert
+     **/
+    pub fn transform(&mut self) -> Mat4 {
+        let mut scale = self.vec3(0.25, 4.0);
+        if self.unit() < 0.25 {
+            scale.x = -scale.x;
+        }
+        Mat4::from_scale_rotation_translation(scale, self.quat(), self.vec3(-100.0, 100.0))
+    }
+}
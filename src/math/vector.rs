@@ -0,0 +1,470 @@
+use std::ops::{ Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign };
+
+use serde::{ Deserialize, Serialize };
+
+/**
+ * Implements the component-wise operators shared by all of the vector types
+ **/
+macro_rules! impl_vector {
+    ($t:ident, $n:expr, $($f:ident),+) => {
+        impl $t {
+            pub const ZERO: $t = $t { $($f: 0.0),+ };
+            pub const ONE: $t = $t { $($f: 1.0),+ };
+
+            #[inline]
+            pub fn splat(v: f32) -> $t {
+                $t { $($f: v),+ }
+            }
+
+            #[inline]
+            pub fn dot(self, rhs: $t) -> f32 {
+                0.0 $(+ self.$f * rhs.$f)+
+            }
+
+            #[inline]
+            pub fn length_squared(self) -> f32 {
+                self.dot(self)
+            }
+
+            #[inline]
+            pub fn length(self) -> f32 {
+                self.length_squared().sqrt()
+            }
+
+            /**
+             * Unit length copy of this vector, or zero if the vector has no length
+             **/
+            #[inline]
+            pub fn normalize(self) -> $t {
+                let len = self.length();
+                if len > 0.0 { self / len } else { $t::ZERO }
+            }
+
+            #[inline]
+            pub fn distance(self, rhs: $t) -> f32 {
+                (self - rhs).length()
+            }
+
+            #[inline]
+            pub fn lerp(self, rhs: $t, t: f32) -> $t {
+                self + (rhs - self) * t
+            }
+
+            #[inline]
+            pub fn min(self, rhs: $t) -> $t {
+                $t { $($f: self.$f.min(rhs.$f)),+ }
+            }
+
+            #[inline]
+            pub fn max(self, rhs: $t) -> $t {
+                $t { $($f: self.$f.max(rhs.$f)),+ }
+            }
+
+            #[inline]
+            pub fn abs(self) -> $t {
+                $t { $($f: self.$f.abs()),+ }
+            }
+
+            #[inline]
+            pub fn min_element(self) -> f32 {
+                self.to_array().iter().cloned().fold(f32::INFINITY, f32::min)
+            }
+
+            #[inline]
+            pub fn max_element(self) -> f32 {
+                self.to_array().iter().cloned().fold(f32::NEG_INFINITY, f32::max)
+            }
+
+            #[inline]
+            pub fn to_array(self) -> [f32; $n] {
+                *self.as_array()
+            }
+
+            //safe because every vector type is repr(C) and made only of f32 fields
+            #[inline]
+            pub fn as_array(&self) -> &[f32; $n] {
+                unsafe { &*(self as *const $t as *const [f32; $n]) }
+            }
+
+            #[inline]
+            pub fn as_mut_array(&mut self) -> &mut [f32; $n] {
+                unsafe { &mut *(self as *mut $t as *mut [f32; $n]) }
+            }
+
+            #[inline]
+            pub fn approx_eq(self, rhs: $t, epsilon: f32) -> bool {
+                true $(&& (self.$f - rhs.$f).abs() <= epsilon)+
+            }
+        }
+
+        impl Add for $t {
+            type Output = $t;
+            #[inline]
+            fn add(self, rhs: $t) -> $t {
+                $t { $($f: self.$f + rhs.$f),+ }
+            }
+        }
+
+        impl Sub for $t {
+            type Output = $t;
+            #[inline]
+            fn sub(self, rhs: $t) -> $t {
+                $t { $($f: self.$f - rhs.$f),+ }
+            }
+        }
+
+        impl Mul for $t {
+            type Output = $t;
+            #[inline]
+            fn mul(self, rhs: $t) -> $t {
+                $t { $($f: self.$f * rhs.$f),+ }
+            }
+        }
+
+        impl Mul<f32> for $t {
+            type Output = $t;
+            #[inline]
+            fn mul(self, rhs: f32) -> $t {
+                $t { $($f: self.$f * rhs),+ }
+            }
+        }
+
+        impl Mul<$t> for f32 {
+            type Output = $t;
+            #[inline]
+            fn mul(self, rhs: $t) -> $t {
+                rhs * self
+            }
+        }
+
+        impl Div for $t {
+            type Output = $t;
+            #[inline]
+            fn div(self, rhs: $t) -> $t {
+                $t { $($f: self.$f / rhs.$f),+ }
+            }
+        }
+
+        impl Div<f32> for $t {
+            type Output = $t;
+            #[inline]
+            fn div(self, rhs: f32) -> $t {
+                $t { $($f: self.$f / rhs),+ }
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+            #[inline]
+            fn neg(self) -> $t {
+                $t { $($f: -self.$f),+ }
+            }
+        }
+
+        impl AddAssign for $t {
+            #[inline]
+            fn add_assign(&mut self, rhs: $t) {
+                $(self.$f += rhs.$f;)+
+            }
+        }
+
+        impl SubAssign for $t {
+            #[inline]
+            fn sub_assign(&mut self, rhs: $t) {
+                $(self.$f -= rhs.$f;)+
+            }
+        }
+
+        impl MulAssign<f32> for $t {
+            #[inline]
+            fn mul_assign(&mut self, rhs: f32) {
+                $(self.$f *= rhs;)+
+            }
+        }
+
+        impl DivAssign<f32> for $t {
+            #[inline]
+            fn div_assign(&mut self, rhs: f32) {
+                $(self.$f /= rhs;)+
+            }
+        }
+
+        impl Index<usize> for $t {
+            type Output = f32;
+            #[inline]
+            fn index(&self, index: usize) -> &f32 {
+                &self.as_array()[index]
+            }
+        }
+
+        impl IndexMut<usize> for $t {
+            #[inline]
+            fn index_mut(&mut self, index: usize) -> &mut f32 {
+                &mut self.as_mut_array()[index]
+            }
+        }
+
+        impl From<[f32; $n]> for $t {
+            #[inline]
+            fn from(a: [f32; $n]) -> $t {
+                let mut v = $t::ZERO;
+                *v.as_mut_array() = a;
+                v
+            }
+        }
+
+        impl From<$t> for [f32; $n] {
+            #[inline]
+            fn from(v: $t) -> [f32; $n] {
+                v.to_array()
+            }
+        }
+
+        impl Default for $t {
+            #[inline]
+            fn default() -> $t {
+                $t::ZERO
+            }
+        }
+
+        impl std::fmt::Display for $t {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{:?}", self.to_array())
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl_vector!(Vec2, 2, x, y);
+
+impl Vec2 {
+    pub const X: Vec2 = Vec2 { x: 1.0, y: 0.0 };
+    pub const Y: Vec2 = Vec2 { x: 0.0, y: 1.0 };
+
+    #[inline]
+    pub const fn new(x: f32, y: f32) -> Vec2 {
+        Vec2 { x, y }
+    }
+
+    /**
+     * 2D cross product (z component of the 3D cross product)
+     **/
+    #[inline]
+    pub fn cross(self, rhs: Vec2) -> f32 {
+        self.x * rhs.y - self.y * rhs.x
+    }
+
+    /**
+     * This vector rotated 90 degrees counter clockwise
+     **/
+    #[inline]
+    pub fn perp(self) -> Vec2 {
+        Vec2 { x: -self.y, y: self.x }
+    }
+
+    #[inline]
+    pub fn rotate(self, angle: f32) -> Vec2 {
+        let (s, c) = angle.sin_cos();
+        Vec2 { x: self.x * c - self.y * s, y: self.x * s + self.y * c }
+    }
+
+    #[inline]
+    pub fn extend(self, z: f32) -> Vec3 {
+        Vec3 { x: self.x, y: self.y, z }
+    }
+}
+
+impl From<(f32, f32)> for Vec2 {
+    #[inline]
+    fn from(t: (f32, f32)) -> Vec2 {
+        Vec2 { x: t.0, y: t.1 }
+    }
+}
+
+impl From<Vec2> for (f32, f32) {
+    #[inline]
+    fn from(v: Vec2) -> (f32, f32) {
+        (v.x, v.y)
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl_vector!(Vec3, 3, x, y, z);
+
+impl Vec3 {
+    pub const X: Vec3 = Vec3 { x: 1.0, y: 0.0, z: 0.0 };
+    pub const Y: Vec3 = Vec3 { x: 0.0, y: 1.0, z: 0.0 };
+    pub const Z: Vec3 = Vec3 { x: 0.0, y: 0.0, z: 1.0 };
+
+    #[inline]
+    pub const fn new(x: f32, y: f32, z: f32) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    #[inline]
+    pub fn cross(self, rhs: Vec3) -> Vec3 {
+        Vec3 {
+            x: self.y * rhs.z - self.z * rhs.y,
+            y: self.z * rhs.x - self.x * rhs.z,
+            z: self.x * rhs.y - self.y * rhs.x
+        }
+    }
+
+    #[inline]
+    pub fn extend(self, w: f32) -> Vec4 {
+        Vec4 { x: self.x, y: self.y, z: self.z, w }
+    }
+
+    #[inline]
+    pub fn truncate(self) -> Vec2 {
+        Vec2 { x: self.x, y: self.y }
+    }
+
+    /**
+     * Any unit vector perpendicular to this one
+     **/
+    pub fn any_orthogonal(self) -> Vec3 {
+        let other = if self.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+        self.cross(other).normalize()
+    }
+}
+
+impl From<(f32, f32, f32)> for Vec3 {
+    #[inline]
+    fn from(t: (f32, f32, f32)) -> Vec3 {
+        Vec3 { x: t.0, y: t.1, z: t.2 }
+    }
+}
+
+/**
+ * Four component vector, aligned to 16 bytes so it can be loaded into a SIMD register directly
+ **/
+#[repr(C, align(16))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Vec4 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl_vector!(Vec4, 4, x, y, z, w);
+
+impl Vec4 {
+    pub const X: Vec4 = Vec4 { x: 1.0, y: 0.0, z: 0.0, w: 0.0 };
+    pub const Y: Vec4 = Vec4 { x: 0.0, y: 1.0, z: 0.0, w: 0.0 };
+    pub const Z: Vec4 = Vec4 { x: 0.0, y: 0.0, z: 1.0, w: 0.0 };
+    pub const W: Vec4 = Vec4 { x: 0.0, y: 0.0, z: 0.0, w: 1.0 };
+
+    #[inline]
+    pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Vec4 {
+        Vec4 { x, y, z, w }
+    }
+
+    #[inline]
+    pub fn truncate(self) -> Vec3 {
+        Vec3 { x: self.x, y: self.y, z: self.z }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::testing::{ Rng, CASES };
+
+    const EPSILON: f32 = 1e-5;
+
+    #[test]
+    fn dot_and_cross_of_axes() {
+        assert_eq!(Vec3::X.dot(Vec3::Y), 0.0);
+        assert_eq!(Vec3::X.cross(Vec3::Y), Vec3::Z);
+        assert_eq!(Vec3::Y.cross(Vec3::Z), Vec3::X);
+        assert_eq!(Vec3::Z.cross(Vec3::X), Vec3::Y);
+        assert_eq!(Vec2::X.cross(Vec2::Y), 1.0);
+        assert_eq!(Vec3::new(1.0, 2.0, 3.0).dot(Vec3::new(4.0, 5.0, 6.0)), 32.0);
+    }
+
+    #[test]
+    fn normalize_gives_unit_length() {
+        let mut rng = Rng::new(1);
+        for _ in 0..CASES {
+            let v = rng.vec3(-1000.0, 1000.0);
+            if v.length() > EPSILON {
+                assert!((v.normalize().length() - 1.0).abs() < EPSILON, "{}", v);
+            }
+        }
+        assert!((Vec2::new(3.0, 4.0).normalize().length() - 1.0).abs() < EPSILON);
+        assert!((Vec4::new(1.0, 2.0, 3.0, 4.0).normalize().length() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn normalize_zero_is_zero() {
+        assert_eq!(Vec2::ZERO.normalize(), Vec2::ZERO);
+        assert_eq!(Vec3::ZERO.normalize(), Vec3::ZERO);
+        assert_eq!(Vec4::ZERO.normalize(), Vec4::ZERO);
+    }
+
+    #[test]
+    fn cross_is_orthogonal() {
+        let mut rng = Rng::new(2);
+        for _ in 0..CASES {
+            let (a, b) = (rng.direction(), rng.direction());
+            let c = a.cross(b);
+            assert!(c.dot(a).abs() < EPSILON && c.dot(b).abs() < EPSILON, "{} x {}", a, b);
+        }
+    }
+
+    #[test]
+    fn any_orthogonal_is_orthogonal_unit() {
+        let mut rng = Rng::new(3);
+        for _ in 0..CASES {
+            let v = rng.direction();
+            let o = v.any_orthogonal();
+            assert!(o.dot(v).abs() < EPSILON, "{}", v);
+            assert!((o.length() - 1.0).abs() < EPSILON, "{}", v);
+        }
+    }
+
+    #[test]
+    fn lerp_hits_endpoints() {
+        let (a, b) = (Vec3::new(1.0, -2.0, 3.0), Vec3::new(-4.0, 5.0, 6.0));
+        assert_eq!(a.lerp(b, 0.0), a);
+        assert_eq!(a.lerp(b, 1.0), b);
+        assert!(a.lerp(b, 0.5).approx_eq(Vec3::new(-1.5, 1.5, 4.5), EPSILON));
+    }
+
+    #[test]
+    fn rotate_and_perp() {
+        assert!(Vec2::X.rotate(std::f32::consts::FRAC_PI_2).approx_eq(Vec2::Y, EPSILON));
+        assert_eq!(Vec2::X.perp(), Vec2::Y);
+    }
+
+    #[test]
+    fn array_round_trip_and_index() {
+        let v = Vec4::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(Vec4::from(v.to_array()), v);
+        assert_eq!(v[2], 3.0);
+        let mut w = v;
+        w[3] = 9.0;
+        assert_eq!(w.w, 9.0);
+        assert_eq!(v.min_element(), 1.0);
+        assert_eq!(v.max_element(), 4.0);
+    }
+}
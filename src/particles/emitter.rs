@@ -0,0 +1,234 @@
+use std::fs;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::animation::clip::{ Interpolation, Track };
+use crate::math::{ Plane, Vec3, Vec4 };
+use crate::particles::ParticleError;
+
+/**
+ * A value picked uniformly between `min` and `max` for every particle
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Serialize, Deserialize)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Range {
+    pub fn new(min: f32, max: f32) -> Range {
+        Range { min, max }
+    }
+
+    pub fn constant(value: f32) -> Range {
+        Range { min: value, max: value }
+    }
+
+    /**
+     * `t` is 0 to 1, usually random
+     **/
+    pub fn lerp(&self, t: f32) -> f32 {
+        self.min + (self.max - self.min) * t
+    }
+}
+
+/**
+ * `count` particles at once, `time` seconds into every emitter cycle
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Burst {
+    pub time: f32,
+    pub count: u32,
+}
+
+/**
+ * Where particles start, relative to the emitter
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Serialize, Deserialize)]
+pub enum EmitterShape {
+    #[default]
+    Point,
+    Sphere { radius: f32 },
+    Box { half_extents: Vec3 },
+}
+
+/**
+ * Particles are points as far as collision goes, `bounce` is how much of the speed into the
+ * plane is kept and `friction` how much of the speed along it is lost
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct CollisionPlane {
+    pub plane: Plane,
+    #[serde(default = "default_bounce")]
+    pub bounce: f32,
+    #[serde(default)]
+    pub friction: f32,
+    /**
+     * Particles die on contact instead of bouncing
+     **/
+    #[serde(default)]
+    pub kill: bool,
+}
+
+impl CollisionPlane {
+    pub fn new(plane: Plane, bounce: Option<f32>, friction: Option<f32>) -> CollisionPlane {
+        CollisionPlane { plane, bounce: bounce.unwrap_or_else(default_bounce), friction: friction.unwrap_or(0.0), kill: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+pub enum ParticleBlend {
+    /**
+     * Sorted back to front and blended over the scene
+     **/
+    #[default]
+    Alpha,
+    /**
+     * Adds light, order doesn't matter so nothing is sorted
+     **/
+    Additive,
+}
+
+fn default_bounce() -> f32 {
+    0.5
+}
+
+fn default_max_particles() -> u32 {
+    1000
+}
+
+fn default_duration() -> f32 {
+    5.0
+}
+
+fn default_looping() -> bool {
+    true
+}
+
+fn default_lifetime() -> Range {
+    Range::constant(1.0)
+}
+
+fn default_direction() -> Vec3 {
+    Vec3::Y
+}
+
+fn default_color() -> Track<Vec4> {
+    Track::new(vec![(0.0, Vec4::ONE)], Interpolation::Linear)
+}
+
+fn default_size() -> Track<f32> {
+    Track::new(vec![(0.0, 1.0)], Interpolation::Linear)
+}
+
+/**
+ * Everything an emitter does, loaded from JSON assets
+ * Color and size curves are keyed on the particle's age over its lifetime, 0 at birth and 1
+ * at death. Directions are in the emitter's space and everything else in world units
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct EmitterDef {
+    pub name: String,
+    #[serde(default = "default_max_particles")]
+    pub max_particles: u32,
+    /**
+     * Particles per second while the emitter plays
+     **/
+    #[serde(default)]
+    pub rate: f32,
+    #[serde(default)]
+    pub bursts: Vec<Burst>,
+    /**
+     * Length of one cycle, bursts repeat every cycle when looping
+     **/
+    #[serde(default = "default_duration")]
+    pub duration: f32,
+    #[serde(default = "default_looping")]
+    pub looping: bool,
+    #[serde(default = "default_lifetime")]
+    pub lifetime: Range,
+    #[serde(default)]
+    pub shape: EmitterShape,
+    #[serde(default = "default_direction")]
+    pub direction: Vec3,
+    /**
+     * Angle in radians particles can stray from `direction`
+     **/
+    #[serde(default)]
+    pub spread: f32,
+    #[serde(default)]
+    pub speed: Range,
+    #[serde(default)]
+    pub gravity: Vec3,
+    /**
+     * Fraction of velocity lost per second
+     **/
+    #[serde(default)]
+    pub drag: f32,
+    #[serde(default)]
+    pub rotation: Range,
+    /**
+     * Radians per second
+     **/
+    #[serde(default)]
+    pub spin: Range,
+    #[serde(default = "default_color")]
+    pub color: Track<Vec4>,
+    #[serde(default = "default_size")]
+    pub size: Track<f32>,
+    #[serde(default)]
+    pub collision: Vec<CollisionPlane>,
+    #[serde(default)]
+    pub blend: ParticleBlend,
+}
+
+impl EmitterDef {
+    /**
+     * Emits nothing until given a rate or bursts
+     **/
+    pub fn new(name: &str) -> EmitterDef {
+        EmitterDef {
+            name: name.to_string(),
+            max_particles: default_max_particles(),
+            rate: 0.0,
+            bursts: Vec::new(),
+            duration: default_duration(),
+            looping: default_looping(),
+            lifetime: default_lifetime(),
+            shape: EmitterShape::default(),
+            direction: default_direction(),
+            spread: 0.0,
+            speed: Range::default(),
+            gravity: Vec3::ZERO,
+            drag: 0.0,
+            rotation: Range::default(),
+            spin: Range::default(),
+            color: default_color(),
+            size: default_size(),
+            collision: Vec::new(),
+            blend: ParticleBlend::default(),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<EmitterDef, ParticleError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String, ParticleError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn load(path: &str) -> Result<EmitterDef, ParticleError> {
+        EmitterDef::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), ParticleError> {
+        Ok(fs::write(path, self.to_json()?)?)
+    }
+}
@@ -0,0 +1,58 @@
+pub mod emitter;
+pub mod system;
+pub mod renderer;
+
+pub use self::emitter::{ Burst, CollisionPlane, EmitterDef, EmitterShape, ParticleBlend, Range };
+pub use self::system::{ Particle, ParticleEmitter, ParticleBatch, ParticleHandle, ParticleInstance, ParticleWorld };
+pub use self::renderer::ParticleRenderer;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::ui::UiError;
+
+#[derive(Debug)]
+pub enum ParticleError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Shader(UiError),
+}
+
+impl fmt::Display for ParticleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParticleError::Io(e) => write!(f, "Particle asset IO error: {}", e),
+            ParticleError::Json(e) => write!(f, "Particle asset JSON error: {}", e),
+            ParticleError::Shader(e) => write!(f, "Particle shader error: {}", e),
+        }
+    }
+}
+
+impl Error for ParticleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParticleError::Io(e) => Some(e),
+            ParticleError::Json(e) => Some(e),
+            ParticleError::Shader(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ParticleError {
+    fn from(e: io::Error) -> ParticleError {
+        ParticleError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ParticleError {
+    fn from(e: serde_json::Error) -> ParticleError {
+        ParticleError::Json(e)
+    }
+}
+
+impl From<UiError> for ParticleError {
+    fn from(e: UiError) -> ParticleError {
+        ParticleError::Shader(e)
+    }
+}
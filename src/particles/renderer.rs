@@ -0,0 +1,203 @@
+use std::mem;
+use std::os::raw::c_void;
+
+use gl::types::{ GLint, GLsizei, GLsizeiptr, GLuint };
+
+use crate::core::camera::Camera;
+use crate::core::stats;
+use crate::particles::ParticleError;
+use crate::particles::emitter::ParticleBlend;
+use crate::particles::system::{ ParticleBatch, ParticleInstance };
+use crate::ui::painter::{ compile_shader, link_program };
+
+//billboards face the camera, the right and up axes are read out of the view matrix
+const VERTEX_SHADER: &str = r#"#version 330 core
+uniform mat4 u_view;
+uniform mat4 u_projection;
+layout(location = 0) in vec2 a_corner;
+layout(location = 1) in vec3 i_position;
+layout(location = 2) in float i_size;
+layout(location = 3) in float i_rotation;
+layout(location = 4) in vec4 i_color;
+out vec2 v_uv;
+out vec4 v_color;
+
+void main() {
+    vec3 right = vec3(u_view[0][0], u_view[1][0], u_view[2][0]);
+    vec3 up = vec3(u_view[0][1], u_view[1][1], u_view[2][1]);
+    float c = cos(i_rotation);
+    float s = sin(i_rotation);
+    vec2 corner = vec2(a_corner.x * c - a_corner.y * s, a_corner.x * s + a_corner.y * c) * i_size;
+    vec3 world = i_position + right * corner.x + up * corner.y;
+    gl_Position = u_projection * u_view * vec4(world, 1.0);
+    v_uv = a_corner;
+    v_color = i_color;
+}
+"#;
+
+//a soft round dot, there's no texture support yet
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 frag_color;
+
+void main() {
+    float alpha = v_color.a * (1.0 - smoothstep(0.25, 0.5, length(v_uv)));
+    frag_color = vec4(v_color.rgb * alpha, alpha);
+}
+"#;
+
+//unit quad around the particle as a triangle strip
+const CORNERS: [[f32; 2]; 4] = [[-0.5, -0.5], [0.5, -0.5], [-0.5, 0.5], [0.5, 0.5]];
+
+/**
+ * Draws particle batches as instanced camera facing quads, one draw call per batch
+ * Create and use it only on the thread the context is current on
+ **/
+pub struct ParticleRenderer {
+    program: GLuint,
+    vao: GLuint,
+    quad: GLuint,
+    instances: GLuint,
+    u_view: GLint,
+    u_projection: GLint,
+    depths: Vec<(f32, usize)>,
+    sorted: Vec<ParticleInstance>,
+}
+
+impl std::fmt::Debug for ParticleRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "ParticleRenderer {{ program: {} }}", self.program)
+    }
+}
+
+impl ParticleRenderer {
+    /**
+     * Needs the OpenGL symbols loaded
+     **/
+    pub fn new() -> Result<ParticleRenderer, ParticleError> {
+        unsafe {
+            let vertex = compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment = match compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+                Ok(x) => x,
+                Err(e) => {
+                    gl::DeleteShader(vertex);
+                    return Err(e.into());
+                }
+            };
+            let program = link_program(vertex, fragment)?;
+
+            let mut vao = 0;
+            let mut quad = 0;
+            let mut instances = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut quad);
+            gl::GenBuffers(1, &mut instances);
+            gl::BindVertexArray(vao);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, quad);
+            gl::BufferData(gl::ARRAY_BUFFER, mem::size_of_val(&CORNERS) as GLsizeiptr, CORNERS.as_ptr() as *const c_void, gl::STATIC_DRAW);
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, instances);
+            let stride = mem::size_of::<ParticleInstance>() as GLsizei;
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(ParticleInstance, position) as *const c_void);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 1, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(ParticleInstance, size) as *const c_void);
+            gl::EnableVertexAttribArray(3);
+            gl::VertexAttribPointer(3, 1, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(ParticleInstance, rotation) as *const c_void);
+            gl::EnableVertexAttribArray(4);
+            gl::VertexAttribPointer(4, 4, gl::UNSIGNED_BYTE, gl::TRUE, stride, mem::offset_of!(ParticleInstance, color) as *const c_void);
+            for attribute in 1..=4 {
+                gl::VertexAttribDivisor(attribute, 1);
+            }
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+
+            let u_view = gl::GetUniformLocation(program, b"u_view\0".as_ptr() as *const _);
+            let u_projection = gl::GetUniformLocation(program, b"u_projection\0".as_ptr() as *const _);
+
+            Ok(ParticleRenderer {
+                program,
+                vao,
+                quad,
+                instances,
+                u_view,
+                u_projection,
+                depths: Vec::new(),
+                sorted: Vec::new(),
+            })
+        }
+    }
+
+    /**
+     * Draws over whatever is in the framebuffer
+     * Particles are depth tested against the scene but don't write depth
+     **/
+    pub fn draw(&mut self, batches: &[ParticleBatch], camera: &Camera, framebuffer: (i32, i32)) {
+        if framebuffer.0 <= 0 || framebuffer.1 <= 0 || batches.iter().all(|x| x.instances.is_empty()) {
+            return;
+        }
+        let (view, projection) = (camera.view, camera.projection);
+
+        unsafe {
+            gl::Viewport(0, 0, framebuffer.0, framebuffer.1);
+            gl::Enable(gl::BLEND);
+            gl::BlendEquation(gl::FUNC_ADD);
+            gl::DepthMask(gl::FALSE);
+            gl::Disable(gl::CULL_FACE);
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.u_view, 1, gl::FALSE, view.to_cols_array().as_ptr());
+            gl::UniformMatrix4fv(self.u_projection, 1, gl::FALSE, projection.to_cols_array().as_ptr());
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.instances);
+
+            for batch in batches {
+                if batch.instances.is_empty() {
+                    continue;
+                }
+                let instances = match batch.blend {
+                    ParticleBlend::Alpha => {
+                        gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                        //farthest first, view space looks down -z
+                        self.depths.clear();
+                        self.depths.extend(batch.instances.iter().enumerate().map(|(i, x)| {
+                            (view.transform_point3(x.position.into()).z, i)
+                        }));
+                        self.depths.sort_by(|a, b| a.0.total_cmp(&b.0));
+                        self.sorted.clear();
+                        self.sorted.extend(self.depths.iter().map(|x| batch.instances[x.1]));
+                        &self.sorted
+                    },
+                    ParticleBlend::Additive => {
+                        gl::BlendFunc(gl::ONE, gl::ONE);
+                        &batch.instances
+                    }
+                };
+                gl::BufferData(gl::ARRAY_BUFFER, (instances.len() * mem::size_of::<ParticleInstance>()) as GLsizeiptr,
+                instances.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, CORNERS.len() as GLsizei, instances.len() as GLsizei);
+                stats::add_draw_calls(1);
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::UseProgram(0);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+}
+
+impl Drop for ParticleRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.instances);
+            gl::DeleteBuffers(1, &self.quad);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
@@ -0,0 +1,458 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+use crate::core::transform::Transform;
+use crate::jobs::JobSystem;
+use crate::math::{ Vec3, Vec4 };
+use crate::particles::emitter::{ EmitterDef, EmitterShape, ParticleBlend };
+use crate::physics::arena::Arena;
+
+//most emitter cycles one update plays through, a long hitch skips the rest
+const MAX_CYCLES: u32 = 8;
+
+static NEXT_SEED: AtomicU64 = AtomicU64::new(0x9e37_79b9_7f4a_7c15);
+
+/**
+ * xorshift64*, plenty for scattering particles and cheap enough to call per spawn
+ **/
+#[derive(Debug, Clone, PartialEq)]
+struct Random {
+    state: u64,
+}
+
+impl Random {
+    fn new(seed: u64) -> Random {
+        //the state can't be zero or it stays zero
+        Random { state: seed | 1 }
+    }
+
+    /**
+     * 0 to 1, 1 excluded
+     **/
+    fn next(&mut self) -> f32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn signed(&mut self) -> f32 {
+        self.next() * 2.0 - 1.0
+    }
+
+    fn in_sphere(&mut self) -> Vec3 {
+        loop {
+            let p = Vec3::new(self.signed(), self.signed(), self.signed());
+            if p.length_squared() <= 1.0 {
+                return p;
+            }
+        }
+    }
+
+    /**
+     * Unit vector at most `spread` radians from `axis`, spread evenly over the cap
+     **/
+    fn in_cone(&mut self, axis: Vec3, spread: f32) -> Vec3 {
+        let cos = 1.0 - self.next() * (1.0 - spread.clamp(0.0, PI).cos());
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let angle = self.next() * 2.0 * PI;
+        let u = axis.any_orthogonal();
+        let v = axis.cross(u);
+        axis * cos + (u * angle.cos() + v * angle.sin()) * sin
+    }
+}
+
+/**
+ * One live particle, in world space
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+    pub rotation: f32,
+    pub spin: f32,
+}
+
+impl Particle {
+    /**
+     * 0 at birth, 1 at death
+     **/
+    pub fn life(&self) -> f32 {
+        if self.lifetime > 0.0 { (self.age / self.lifetime).clamp(0.0, 1.0) } else { 1.0 }
+    }
+}
+
+/**
+ * What the renderer gets for each particle, laid out for the instance buffer
+ **/
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleInstance {
+    pub position: [f32; 3],
+    pub size: f32,
+    pub rotation: f32,
+    pub color: [u8; 4],
+}
+
+/**
+ * One emitter's particles ready to draw, what a frame packet carries instead of the emitters
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleBatch {
+    pub blend: ParticleBlend,
+    pub instances: Vec<ParticleInstance>,
+}
+
+/**
+ * A running emitter, simulated on the CPU
+ * Particles stay where they were spawned in world space, moving the emitter only moves where
+ * new ones appear. The transform's scale is ignored
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    def: Arc<EmitterDef>,
+    pub transform: Transform,
+    particles: Vec<Particle>,
+    //time into the current cycle
+    time: f32,
+    //fractional particles owed by the spawn rate
+    owed: f32,
+    emitting: bool,
+    random: Random,
+}
+
+impl ParticleEmitter {
+    /**
+     * Starts emitting straight away, each emitter gets its own random seed
+     **/
+    pub fn new(def: Arc<EmitterDef>, transform: Option<Transform>) -> ParticleEmitter {
+        let seed = NEXT_SEED.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed);
+        ParticleEmitter {
+            def,
+            transform: transform.unwrap_or_else(Transform::identity),
+            particles: Vec::new(),
+            time: 0.0,
+            owed: 0.0,
+            emitting: true,
+            random: Random::new(seed),
+        }
+    }
+
+    pub fn def(&self) -> &Arc<EmitterDef> {
+        &self.def
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    /**
+     * For repeatable effects, the same seed and deltas give the same particles
+     **/
+    pub fn set_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    pub fn is_emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /**
+     * Starts a fresh cycle, live particles carry on
+     **/
+    pub fn play(&mut self) {
+        self.emitting = true;
+        self.time = 0.0;
+        self.owed = 0.0;
+    }
+
+    /**
+     * Stops spawning, live particles carry on until they die
+     **/
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    /**
+     * Not emitting and nothing left alive
+     **/
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.particles.is_empty()
+    }
+
+    /**
+     * Spawns particles right now on top of the definition's bursts
+     **/
+    pub fn burst(&mut self, count: u32) {
+        for _ in 0..count {
+            self.spawn();
+        }
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.def.max_particles as usize {
+            return;
+        }
+        let def = Arc::clone(&self.def);
+        let random = &mut self.random;
+        let offset = match def.shape {
+            EmitterShape::Point => Vec3::ZERO,
+            EmitterShape::Sphere { radius } => random.in_sphere() * radius,
+            EmitterShape::Box { half_extents } => Vec3::new(random.signed(), random.signed(), random.signed()) * half_extents,
+        };
+        let direction = def.direction.normalize();
+        let direction = if direction == Vec3::ZERO { Vec3::ZERO } else { random.in_cone(direction, def.spread) };
+        let rotation = self.transform.rotation;
+        let speed = def.speed.lerp(random.next());
+        self.particles.push(Particle {
+            position: self.transform.translation + rotation.rotate(offset),
+            velocity: rotation.rotate(direction) * speed,
+            age: 0.0,
+            lifetime: def.lifetime.lerp(random.next()).max(0.0),
+            rotation: def.rotation.lerp(random.next()),
+            spin: def.spin.lerp(random.next()),
+        });
+    }
+
+    /**
+     * Moves live particles on by `dt` seconds, then spawns whatever is due
+     **/
+    pub fn update(&mut self, dt: f32) {
+        let dt = dt.max(0.0);
+        self.simulate(dt);
+        if self.emitting {
+            self.emit(dt);
+        }
+    }
+
+    fn simulate(&mut self, dt: f32) {
+        let def = &self.def;
+        let drag = (1.0 - def.drag * dt).max(0.0);
+        let mut i = 0;
+        while i < self.particles.len() {
+            let p = &mut self.particles[i];
+            p.age += dt;
+            if p.age >= p.lifetime {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            p.velocity = (p.velocity + def.gravity * dt) * drag;
+            p.position += p.velocity * dt;
+            p.rotation += p.spin * dt;
+
+            let mut dead = false;
+            for collider in &def.collision {
+                let distance = collider.plane.signed_distance(p.position);
+                let normal = collider.plane.normal;
+                let into = p.velocity.dot(normal);
+                if distance >= 0.0 || into >= 0.0 {
+                    continue;
+                }
+                if collider.kill {
+                    dead = true;
+                    break;
+                }
+                p.position -= normal * distance;
+                let along = p.velocity - normal * into;
+                p.velocity = along * (1.0 - collider.friction).max(0.0) - normal * into * collider.bounce;
+            }
+            if dead {
+                self.particles.swap_remove(i);
+                continue;
+            }
+            i += 1;
+        }
+    }
+
+    fn emit(&mut self, dt: f32) {
+        let def = Arc::clone(&self.def);
+        let mut remaining = dt;
+        let mut cycles = 0;
+        while remaining > 0.0 && self.emitting {
+            let step = if def.duration > 0.0 { remaining.min(def.duration - self.time) } else { remaining };
+            let (from, to) = (self.time, self.time + step);
+            for burst in &def.bursts {
+                if burst.time >= from && burst.time < to {
+                    self.burst(burst.count);
+                }
+            }
+            self.owed += def.rate * step;
+            while self.owed >= 1.0 {
+                self.owed -= 1.0;
+                self.spawn();
+            }
+            self.time = to;
+            remaining -= step;
+
+            if def.duration > 0.0 && self.time >= def.duration {
+                cycles += 1;
+                if !def.looping {
+                    self.emitting = false;
+                } else if cycles >= MAX_CYCLES {
+                    self.time = 0.0;
+                    break;
+                } else {
+                    self.time = 0.0;
+                }
+            }
+        }
+    }
+
+    /**
+     * Appends what the renderer needs for every live particle
+     **/
+    pub fn instances(&self, out: &mut Vec<ParticleInstance>) {
+        out.reserve(self.particles.len());
+        for p in &self.particles {
+            let life = p.life();
+            let color = self.def.color.sample(life).unwrap_or(Vec4::ONE);
+            let to_byte = |x: f32| (x.clamp(0.0, 1.0) * 255.0).round() as u8;
+            out.push(ParticleInstance {
+                position: p.position.to_array(),
+                size: self.def.size.sample(life).unwrap_or(1.0),
+                rotation: p.rotation,
+                color: [to_byte(color.x), to_byte(color.y), to_byte(color.z), to_byte(color.w)],
+            });
+        }
+    }
+
+    pub fn blend(&self) -> ParticleBlend {
+        self.def.blend
+    }
+}
+
+/**
+ * Handle to an emitter in a ParticleWorld, stale once the emitter is removed
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ParticleHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl ParticleHandle {
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> ParticleHandle {
+        ParticleHandle { index: bits as u32, generation: (bits >> 32) as u32 }
+    }
+}
+
+/**
+ * Every emitter in the game, simulated on the update thread and drawn on the render thread
+ * Emitters that don't loop are removed once they've finished, looping ones stay until removed
+ **/
+pub struct ParticleWorld {
+    emitters: Arena<ParticleEmitter>,
+}
+
+impl ParticleWorld {
+    pub fn new() -> ParticleWorld {
+        ParticleWorld { emitters: Arena::new() }
+    }
+
+    pub fn add(&mut self, emitter: ParticleEmitter) -> ParticleHandle {
+        let (index, generation) = self.emitters.insert(emitter);
+        ParticleHandle { index, generation }
+    }
+
+    /**
+     * Starts a new emitter from a definition
+     **/
+    pub fn spawn(&mut self, def: Arc<EmitterDef>, transform: Option<Transform>) -> ParticleHandle {
+        self.add(ParticleEmitter::new(def, transform))
+    }
+
+    pub fn remove(&mut self, handle: ParticleHandle) -> Option<ParticleEmitter> {
+        self.emitters.remove(handle.index, handle.generation)
+    }
+
+    pub fn get(&self, handle: ParticleHandle) -> Option<&ParticleEmitter> {
+        self.emitters.get(handle.index, handle.generation)
+    }
+
+    pub fn get_mut(&mut self, handle: ParticleHandle) -> Option<&mut ParticleEmitter> {
+        self.emitters.get_mut(handle.index, handle.generation)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ParticleHandle, &ParticleEmitter)> {
+        self.emitters.iter().map(|(index, generation, x)| (ParticleHandle { index, generation }, x))
+    }
+
+    pub fn len(&self) -> usize {
+        self.emitters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.emitters.is_empty()
+    }
+
+    /**
+     * Live particles across every emitter
+     **/
+    pub fn particle_count(&self) -> usize {
+        self.emitters.iter().map(|(_, _, x)| x.particles.len()).sum()
+    }
+
+    /**
+     * Replaces `out` with a batch per emitter that has particles alive, reusing its buffers
+     **/
+    pub fn batches(&self, out: &mut Vec<ParticleBatch>) {
+        let mut count = 0;
+        for (_, emitter) in self.iter() {
+            if emitter.particles.is_empty() {
+                continue;
+            }
+            if count == out.len() {
+                out.push(ParticleBatch { blend: emitter.blend(), instances: Vec::new() });
+            }
+            let batch = &mut out[count];
+            batch.blend = emitter.blend();
+            batch.instances.clear();
+            emitter.instances(&mut batch.instances);
+            count += 1;
+        }
+        out.truncate(count);
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for (_, _, emitter) in self.emitters.iter_mut() {
+            emitter.update(dt);
+        }
+        self.remove_finished();
+    }
+
+    /**
+     * Same as update with the emitters spread over the job system
+     **/
+    pub fn update_parallel(&mut self, dt: f32, jobs: &JobSystem) {
+        let mut emitters: Vec<&mut ParticleEmitter> = self.emitters.iter_mut().map(|(_, _, x)| x).collect();
+        jobs.parallel_for_mut(&mut emitters, Some(4), |x| x.update(dt));
+        self.remove_finished();
+    }
+
+    fn remove_finished(&mut self) {
+        let finished: Vec<(u32, u32)> = self.emitters.iter()
+            .filter(|(_, _, x)| x.is_finished() && !x.def.looping)
+            .map(|(index, generation, _)| (index, generation))
+            .collect();
+        for (index, generation) in finished {
+            self.emitters.remove(index, generation);
+        }
+    }
+}
+
+impl Default for ParticleWorld {
+    fn default() -> ParticleWorld {
+        ParticleWorld::new()
+    }
+}
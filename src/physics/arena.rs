@@ -0,0 +1,136 @@
+/**
+ * Generational storage for bodies and colliders
+ * Slots are reused after removal, the generation makes stale handles miss instead of
+ * silently pointing at whatever took the slot
+ **/
+pub struct Arena<T> {
+    entries: Vec<(u32, Option<T>)>,
+    free: Vec<u32>,
+}
+
+impl<T> Arena<T> {
+    pub fn new() -> Arena<T> {
+        Arena { entries: Vec::new(), free: Vec::new() }
+    }
+
+    pub fn insert(&mut self, value: T) -> (u32, u32) {
+        match self.free.pop() {
+            Some(index) => {
+                let entry = &mut self.entries[index as usize];
+                entry.0 += 1;
+                entry.1 = Some(value);
+                (index, entry.0)
+            },
+            None => {
+                self.entries.push((0, Some(value)));
+                (self.entries.len() as u32 - 1, 0)
+            }
+        }
+    }
+
+    pub fn remove(&mut self, index: u32, generation: u32) -> Option<T> {
+        match self.entries.get_mut(index as usize) {
+            Some(entry) if entry.0 == generation && entry.1.is_some() => {
+                self.free.push(index);
+                entry.1.take()
+            },
+            _ => None
+        }
+    }
+
+    pub fn get(&self, index: u32, generation: u32) -> Option<&T> {
+        match self.entries.get(index as usize) {
+            Some((g, Some(value))) if *g == generation => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn get_mut(&mut self, index: u32, generation: u32) -> Option<&mut T> {
+        match self.entries.get_mut(index as usize) {
+            Some((g, Some(value))) if *g == generation => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /**
+     * Live entries with their index and generation, in slot order
+     **/
+    pub fn iter(&self) -> impl Iterator<Item = (u32, u32, &T)> {
+        self.entries.iter().enumerate().filter_map(|(i, (g, x))| x.as_ref().map(|x| (i as u32, *g, x)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut T)> {
+        self.entries.iter_mut().enumerate().filter_map(|(i, (g, x))| x.as_mut().map(|x| (i as u32, *g, x)))
+    }
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Arena<T> {
+        Arena::new()
+    }
+}
+
+macro_rules! physics_handle {
+    ($(#[$meta:meta])* $t:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $t {
+            index: u32,
+            generation: u32,
+        }
+
+        impl $t {
+            pub(crate) fn new(index: u32, generation: u32) -> $t {
+                $t { index, generation }
+            }
+
+            pub(crate) fn index(self) -> u32 {
+                self.index
+            }
+
+            pub(crate) fn generation(self) -> u32 {
+                self.generation
+            }
+
+            /**
+             * Packs the handle into a u64, this is how handles travel in EventData
+             **/
+            pub fn to_bits(self) -> u64 {
+                (self.generation as u64) << 32 | self.index as u64
+            }
+
+            pub fn from_bits(bits: u64) -> $t {
+                $t { index: bits as u32, generation: (bits >> 32) as u32 }
+            }
+        }
+    };
+}
+
+physics_handle!(
+    /**
+     * Handle to a rigid body in a physics world
+     **/
+    BodyHandle
+);
+
+physics_handle!(
+    /**
+     * Handle to a collider attached to a rigid body
+     **/
+    ColliderHandle
+);
+
+physics_handle!(
+    /**
+     * Handle to a joint between two rigid bodies
+     **/
+    JointHandle
+);
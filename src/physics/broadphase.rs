@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+
+use crate::physics::ColliderHandle;
+
+/**
+ * Bounding volume the broadphase can sort and test
+ **/
+pub trait Bounds {
+    fn min_x(&self) -> f32;
+    fn max_x(&self) -> f32;
+    fn overlaps(&self, other: &Self) -> bool;
+}
+
+/**
+ * Sort and sweep along the x axis
+ * The sorted order is kept between steps, objects rarely move far in one step so the
+ * insertion sort that restores it is close to linear
+ **/
+pub struct SweepAndPrune<B: Bounds> {
+    proxies: Vec<(ColliderHandle, B)>,
+}
+
+impl<B: Bounds> SweepAndPrune<B> {
+    pub fn new() -> SweepAndPrune<B> {
+        SweepAndPrune { proxies: Vec::new() }
+    }
+
+    /**
+     * Replaces the bounds of every collider, colliders missing from `bounds` are dropped
+     **/
+    pub fn update<I: IntoIterator<Item = (ColliderHandle, B)>>(&mut self, bounds: I) {
+        let mut incoming: BTreeMap<ColliderHandle, B> = bounds.into_iter().collect();
+
+        //keep the previous order for handles that are still around, then append new ones
+        let mut proxies = Vec::with_capacity(incoming.len());
+        for (handle, _) in self.proxies.drain(..) {
+            if let Some(bounds) = incoming.remove(&handle) {
+                proxies.push((handle, bounds));
+            }
+        }
+        proxies.extend(incoming);
+        self.proxies = proxies;
+
+        for i in 1..self.proxies.len() {
+            let mut j = i;
+            while j > 0 && Self::before(&self.proxies[j], &self.proxies[j - 1]) {
+                self.proxies.swap(j, j - 1);
+                j -= 1;
+            }
+        }
+    }
+
+    /**
+     * Every overlapping pair, each ordered (lower handle, higher handle) and the list sorted,
+     * so the result doesn't depend on the order colliders were added in
+     **/
+    pub fn pairs(&self) -> Vec<(ColliderHandle, ColliderHandle)> {
+        let mut pairs = Vec::new();
+        for (i, (a, bounds_a)) in self.proxies.iter().enumerate() {
+            for (b, bounds_b) in &self.proxies[i + 1..] {
+                if bounds_b.min_x() > bounds_a.max_x() {
+                    break;
+                }
+                if bounds_a.overlaps(bounds_b) {
+                    pairs.push(if a < b { (*a, *b) } else { (*b, *a) });
+                }
+            }
+        }
+        pairs.sort();
+        pairs
+    }
+
+    /**
+     * Colliders whose bounds overlap `query`
+     **/
+    pub fn query(&self, query: &B) -> Vec<ColliderHandle> {
+        let mut hits: Vec<ColliderHandle> = self.proxies.iter()
+            .take_while(|x| x.1.min_x() <= query.max_x())
+            .filter(|x| x.1.overlaps(query))
+            .map(|x| x.0)
+            .collect();
+        hits.sort();
+        hits
+    }
+
+    pub fn bounds(&self) -> impl Iterator<Item = &(ColliderHandle, B)> {
+        self.proxies.iter()
+    }
+
+    fn before(a: &(ColliderHandle, B), b: &(ColliderHandle, B)) -> bool {
+        a.1.min_x() < b.1.min_x() || (a.1.min_x() == b.1.min_x() && a.0 < b.0)
+    }
+}
+
+impl<B: Bounds> Default for SweepAndPrune<B> {
+    fn default() -> SweepAndPrune<B> {
+        SweepAndPrune::new()
+    }
+}
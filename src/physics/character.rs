@@ -0,0 +1,237 @@
+use crate::math::{ Quat, Vec3 };
+use crate::physics::shape3d::{ Isometry3, Shape3D };
+use crate::physics::world3d::{ PhysicsWorld3D, ShapeHit3D };
+use crate::physics::BodyHandle;
+
+const MAX_SLIDES: usize = 4;
+const MAX_DEPENETRATION: usize = 4;
+
+/**
+ * Result of a CharacterController::move_and_slide call
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterMove {
+    /**
+     * How far the character actually moved
+     **/
+    pub translation: Vec3,
+    pub grounded: bool,
+    /**
+     * Everything the capsule ran into on the way, in the order it was hit
+     **/
+    pub hits: Vec<ShapeHit3D>,
+}
+
+/**
+ * Kinematic capsule moved by sweeping through the 3D world instead of by forces
+ * Slopes up to `max_slope` are walked on, steeper ones act as walls, ledges up to
+ * `step_height` are climbed and the character stays glued to the ground going down slopes
+ **/
+#[derive(Debug, Clone)]
+pub struct CharacterController {
+    pub position: Vec3,
+    pub half_height: f32,
+    pub radius: f32,
+    pub up: Vec3,
+    /**
+     * Steepest walkable slope in radians
+     **/
+    pub max_slope: f32,
+    pub step_height: f32,
+    /**
+     * Gap kept between the capsule and everything else so sweeps don't start touching
+     **/
+    pub skin_width: f32,
+    /**
+     * How far down the character is pulled to stay on the ground while walking
+     **/
+    pub snap_distance: f32,
+    pub mask: u32,
+    /**
+     * Kinematic body moved along with the controller so other bodies can collide with it
+     **/
+    pub body: Option<BodyHandle>,
+    grounded: bool,
+    ground_normal: Vec3,
+}
+
+impl CharacterController {
+    pub fn new(position: Vec3, half_height: f32, radius: f32) -> CharacterController {
+        CharacterController {
+            position,
+            half_height,
+            radius,
+            up: Vec3::Y,
+            max_slope: 45f32.to_radians(),
+            step_height: 0.3,
+            skin_width: 0.02,
+            snap_distance: 0.2,
+            mask: u32::MAX,
+            body: None,
+            grounded: false,
+            ground_normal: Vec3::Y,
+        }
+    }
+
+    #[inline]
+    pub fn grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /**
+     * Normal of the ground below, `up` while airborne
+     **/
+    #[inline]
+    pub fn ground_normal(&self) -> Vec3 {
+        self.ground_normal
+    }
+
+    fn shape(&self) -> Shape3D {
+        Shape3D::capsule(self.half_height, self.radius)
+    }
+
+    fn iso(&self, position: Vec3) -> Isometry3 {
+        Isometry3::new(position, Quat::from_rotation_arc(Vec3::Y, self.up))
+    }
+
+    fn walkable(&self, normal: Vec3) -> bool {
+        normal.dot(self.up) >= self.max_slope.cos() - 1e-4
+    }
+
+    /**
+     * Moves the capsule by `translation`, sliding along whatever it hits
+     * The horizontal and vertical parts are handled separately so gravity doesn't push
+     * the character down slopes and walking doesn't launch it off ledges
+     **/
+    pub fn move_and_slide(&mut self, world: &mut PhysicsWorld3D, translation: Vec3) -> CharacterMove {
+        let start = self.position;
+        let mut hits = Vec::new();
+        self.depenetrate(world);
+
+        let vertical = self.up * translation.dot(self.up);
+        let horizontal = translation - vertical;
+
+        //horizontal pass, steep slopes are walls so walking into them doesn't climb
+        let (mut position, blocked, _) = self.slide(world, self.position, horizontal, true, &mut hits);
+
+        //step up, tried when something blocked the way and only kept if it got further
+        if blocked && self.step_height > 0.0 {
+            let mut step_hits = Vec::new();
+            let (raised, _, _) = self.slide(world, self.position, self.up * self.step_height, false, &mut step_hits);
+            let lift = (raised - self.position).dot(self.up);
+            let (moved, _, _) = self.slide(world, raised, horizontal, true, &mut step_hits);
+            let (lowered, _, ground) = self.slide(world, moved, -self.up * (lift + self.skin_width), false, &mut step_hits);
+            let flat = |p: Vec3| {
+                let d = p - self.position;
+                (d - self.up * d.dot(self.up)).length()
+            };
+            if ground.is_some() && flat(lowered) > flat(position) + 1e-4 {
+                position = lowered;
+                hits.extend(step_hits);
+            }
+        }
+
+        //vertical pass, moving down stops on walkable ground and slides down steep slopes
+        let (moved, _, mut ground) = self.slide(world, position, vertical, false, &mut hits);
+        position = moved;
+        if vertical.dot(self.up) > 0.0 {
+            ground = None;
+        }
+
+        //probe the ground, snapping down to it if the character was already standing
+        if ground.is_none() && vertical.dot(self.up) <= 0.0 {
+            let distance = if self.grounded { self.snap_distance } else { 0.0 } + 2.0 * self.skin_width;
+            let probe = -self.up * distance;
+            if let Some(hit) = world.shape_cast_excluding(&self.shape(), &self.iso(position), probe, self.mask, self.body) {
+                if self.walkable(hit.normal) {
+                    position += -self.up * (hit.fraction * distance - self.skin_width).max(0.0);
+                    ground = Some(hit.normal);
+                }
+            }
+        }
+
+        self.grounded = ground.is_some();
+        self.ground_normal = ground.unwrap_or(self.up);
+        self.position = position;
+        if let Some(body) = self.body.and_then(|x| world.body_mut(x)) {
+            let rotation = body.rotation();
+            body.set_transform(position, rotation);
+        }
+
+        CharacterMove {
+            translation: self.position - start,
+            grounded: self.grounded,
+            hits,
+        }
+    }
+
+    /**
+     * Pushes the capsule out of anything it overlaps, e.g. after a body moved into it
+     **/
+    fn depenetrate(&mut self, world: &PhysicsWorld3D) {
+        let shape = self.shape();
+        for _ in 0..MAX_DEPENETRATION {
+            let found = world.penetrations(&shape, &self.iso(self.position), self.mask, self.body);
+            if found.is_empty() {
+                break;
+            }
+            //deepest first, the rest are usually resolved by the same push
+            let (normal, separation) = found.into_iter().fold((Vec3::ZERO, 0.0), |best, x| if x.1 < best.1 { x } else { best });
+            self.position += normal * (separation - self.skin_width);
+        }
+    }
+
+    /**
+     * Sweeps from `position` along `motion`, projecting what's left onto each surface hit
+     * Returns the end position, whether a wall was hit and the last walkable ground touched
+     **/
+    fn slide(&self, world: &PhysicsWorld3D, mut position: Vec3, motion: Vec3, steep_walls: bool, hits: &mut Vec<ShapeHit3D>) -> (Vec3, bool, Option<Vec3>) {
+        let shape = self.shape();
+        let mut remaining = motion;
+        let mut blocked = false;
+        let mut ground = None;
+        for _ in 0..MAX_SLIDES {
+            let length = remaining.length();
+            if length < 1e-5 {
+                break;
+            }
+            let hit = match world.shape_cast_excluding(&shape, &self.iso(position), remaining, self.mask, self.body) {
+                Some(x) => x,
+                None => {
+                    position += remaining;
+                    break;
+                }
+            };
+            hits.push(hit);
+            let direction = remaining / length;
+            let travel = (hit.fraction * length - self.skin_width).max(0.0);
+            position += direction * travel;
+
+            let mut normal = hit.normal;
+            if self.walkable(normal) {
+                ground = Some(normal);
+                //landing on walkable ground ends a vertical move
+                if !steep_walls && motion.dot(self.up) < 0.0 {
+                    break;
+                }
+            } else {
+                blocked = true;
+                if steep_walls {
+                    let flat = normal - self.up * normal.dot(self.up);
+                    if flat.length_squared() > 1e-8 {
+                        normal = flat.normalize();
+                    }
+                }
+            }
+
+            let left = direction * (length - travel);
+            let into = left.dot(normal);
+            remaining = if into < 0.0 { left - normal * into } else { left };
+            //stop instead of bouncing back against the original direction in corners
+            if remaining.dot(motion) <= 0.0 {
+                break;
+            }
+        }
+        (position, blocked, ground)
+    }
+}
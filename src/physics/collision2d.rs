@@ -0,0 +1,417 @@
+use crate::math::Vec2;
+use crate::physics::shape2d::{ Isometry2, Shape2D };
+use crate::physics::LINEAR_SLOP;
+
+/**
+ * A shape's core points and edge normals in world space plus the radius the core is
+ * inflated by. Circles have one point, capsules two, rects and polygons three or more
+ **/
+#[derive(Debug, Clone)]
+pub struct Hull {
+    pub points: Vec<Vec2>,
+    pub normals: Vec<Vec2>,
+    pub radius: f32,
+}
+
+impl Hull {
+    pub fn new(shape: &Shape2D, iso: &Isometry2) -> Hull {
+        let (core, radius) = shape.core();
+        Hull::from_points(core.iter().map(|&p| iso.transform_point(p)).collect(), radius)
+    }
+
+    /**
+     * Hull of counter clockwise core points, computing the outward edge normals
+     **/
+    pub fn from_points(points: Vec<Vec2>, radius: f32) -> Hull {
+        let normals = if points.len() < 2 {
+            Vec::new()
+        } else {
+            (0..points.len()).map(|i| {
+                let edge = points[(i + 1) % points.len()] - points[i];
+                Vec2::new(edge.y, -edge.x).normalize()
+            }).collect()
+        };
+        Hull { points, normals, radius }
+    }
+
+    pub fn translated(&self, offset: Vec2) -> Hull {
+        Hull { points: self.points.iter().map(|&p| p + offset).collect(), normals: self.normals.clone(), radius: self.radius }
+    }
+
+    fn edge(&self, i: usize) -> (Vec2, Vec2) {
+        (self.points[i], self.points[(i + 1) % self.points.len()])
+    }
+
+    /**
+     * Edges of the core, a single point counts as one zero length edge
+     **/
+    fn edges(&self) -> Vec<(Vec2, Vec2)> {
+        match self.points.len() {
+            1 => vec![(self.points[0], self.points[0])],
+            2 => vec![(self.points[0], self.points[1])],
+            n => (0..n).map(|i| self.edge(i)).collect()
+        }
+    }
+
+    fn core_contains(&self, p: Vec2) -> bool {
+        self.points.len() >= 3 && self.normals.iter().zip(&self.points).all(|(n, v)| n.dot(p - *v) <= 0.0)
+    }
+
+    /**
+     * Closest point on the core boundary to `p`
+     **/
+    fn closest_core_point(&self, p: Vec2) -> Vec2 {
+        self.edges().iter()
+            .map(|&(a, b)| closest_on_segment(p, a, b))
+            .fold((Vec2::ZERO, f32::INFINITY), |best, q| {
+                let d = (q - p).length_squared();
+                if d < best.1 { (q, d) } else { best }
+            }).0
+    }
+
+    pub fn contains_point(&self, p: Vec2) -> bool {
+        self.core_contains(p) || self.closest_core_point(p).distance(p) <= self.radius
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactPoint2 {
+    pub point: Vec2,
+    /**
+     * Negative when the shapes overlap
+     **/
+    pub separation: f32,
+    /**
+     * Identifies the features that touch so impulses can be carried to the next step
+     **/
+    pub id: u32,
+}
+
+/**
+ * Contact between two shapes, `normal` points from the first shape towards the second
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifold2 {
+    pub normal: Vec2,
+    pub points: Vec<ContactPoint2>,
+}
+
+/**
+ * Contact manifold of two hulls, including speculative points up to `margin` apart
+ **/
+pub fn collide(a: &Hull, b: &Hull, margin: f32) -> Option<Manifold2> {
+    match (a.points.len(), b.points.len()) {
+        (1, 1) => collide_circles(a.points[0], a.radius, b.points[0], b.radius, margin),
+        (1, _) => collide_hull_circle(b, a.points[0], a.radius, margin).map(|mut m| {
+            m.normal = -m.normal;
+            m
+        }),
+        (_, 1) => collide_hull_circle(a, b.points[0], b.radius, margin),
+        _ => collide_hulls(a, b, margin)
+    }
+}
+
+fn collide_circles(ca: Vec2, ra: f32, cb: Vec2, rb: f32, margin: f32) -> Option<Manifold2> {
+    let d = cb - ca;
+    let distance = d.length();
+    let separation = distance - ra - rb;
+    if separation > margin {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON { d / distance } else { Vec2::Y };
+    let point = ((ca + normal * ra) + (cb - normal * rb)) * 0.5;
+    Some(Manifold2 { normal, points: vec![ContactPoint2 { point, separation, id: 0 }] })
+}
+
+//normal points from the hull to the circle
+fn collide_hull_circle(hull: &Hull, center: Vec2, radius: f32, margin: f32) -> Option<Manifold2> {
+    let (normal, core_distance, surface) = if hull.core_contains(center) {
+        let (i, s) = hull.normals.iter().zip(&hull.points).enumerate()
+            .map(|(i, (n, v))| (i, n.dot(center - *v)))
+            .fold((0, f32::NEG_INFINITY), |best, x| if x.1 > best.1 { x } else { best });
+        (hull.normals[i], s, center - hull.normals[i] * s)
+    } else {
+        let q = hull.closest_core_point(center);
+        let d = center - q;
+        let distance = d.length();
+        let normal = if distance > f32::EPSILON { d / distance } else { Vec2::Y };
+        (normal, distance, q)
+    };
+
+    let separation = core_distance - hull.radius - radius;
+    if separation > margin {
+        return None;
+    }
+    let point = ((surface + normal * hull.radius) + (center - normal * radius)) * 0.5;
+    Some(Manifold2 { normal, points: vec![ContactPoint2 { point, separation, id: 0 }] })
+}
+
+/**
+ * Largest separation of `b` along the edge normals of `a`, and the edge it happens on
+ **/
+fn max_separation(a: &Hull, b: &Hull) -> (usize, f32) {
+    let mut best = (0, f32::NEG_INFINITY);
+    for (i, (n, v)) in a.normals.iter().zip(&a.points).enumerate() {
+        let s = b.points.iter().map(|p| n.dot(*p - *v)).fold(f32::INFINITY, f32::min);
+        if s > best.1 {
+            best = (i, s);
+        }
+    }
+    best
+}
+
+fn collide_hulls(a: &Hull, b: &Hull, margin: f32) -> Option<Manifold2> {
+    let radius = a.radius + b.radius;
+    let (edge_a, sep_a) = max_separation(a, b);
+    let (edge_b, sep_b) = max_separation(b, a);
+    if sep_a - radius > margin || sep_b - radius > margin {
+        return None;
+    }
+
+    //prefer a as the reference so the choice doesn't flicker between steps
+    let flip = sep_b > sep_a + 0.1 * LINEAR_SLOP;
+    let (reference, incident, ref_edge) = if flip { (b, a, edge_b) } else { (a, b, edge_a) };
+    let n = reference.normals[ref_edge];
+    let inc_edge = (0..incident.normals.len())
+        .fold((0, f32::INFINITY), |best, j| {
+            let d = incident.normals[j].dot(n);
+            if d < best.1 { (j, d) } else { best }
+        }).0;
+    let (v11, v12) = reference.edge(ref_edge);
+    let (v21, v22) = incident.edge(inc_edge);
+    let inc_count = incident.points.len();
+    let sign = if flip { -1.0 } else { 1.0 };
+    let feature = |inc_vertex: usize| ((flip as u32) << 16) | ((ref_edge as u32) << 8) | inc_vertex as u32;
+
+    //rounded cores that are apart can be closest at two corners, which the face normal misses
+    if sep_a.max(sep_b) > 0.1 * LINEAR_SLOP && radius > 0.0 {
+        let (s, t, c1, c2) = closest_segments(v11, v12, v21, v22);
+        let corner = |x: f32| x <= 0.0 || x >= 1.0;
+        if corner(s) && corner(t) {
+            let d = c2 - c1;
+            let distance = d.length();
+            if distance - radius > margin {
+                return None;
+            }
+            let normal = if distance > f32::EPSILON { d / distance } else { n };
+            let point = ((c1 + normal * reference.radius) + (c2 - normal * incident.radius)) * 0.5;
+            let inc_vertex = if t <= 0.0 { inc_edge } else { (inc_edge + 1) % inc_count };
+            return Some(Manifold2 {
+                normal: normal * sign,
+                points: vec![ContactPoint2 { point, separation: distance - radius, id: feature(inc_vertex) }]
+            });
+        }
+    }
+
+    let tangent = (v12 - v11).normalize();
+    let incident_points = [(v21, inc_edge), (v22, (inc_edge + 1) % inc_count)];
+    let clipped = clip(&incident_points, -tangent, -tangent.dot(v11))
+        .and_then(|x| clip(&x, tangent, tangent.dot(v12)));
+
+    let mut points = Vec::with_capacity(2);
+    match clipped {
+        Some(clipped) => for &(p, inc_vertex) in &clipped {
+            let s = n.dot(p - v11);
+            if s - radius <= margin {
+                let point = ((p - n * s + n * reference.radius) + (p - n * incident.radius)) * 0.5;
+                points.push(ContactPoint2 { point, separation: s - radius, id: feature(inc_vertex) });
+            }
+        },
+        None => {
+            let (_, _, c1, c2) = closest_segments(v11, v12, v21, v22);
+            let s = n.dot(c2 - c1);
+            if s - radius <= margin {
+                let point = ((c1 + n * reference.radius) + (c2 - n * incident.radius)) * 0.5;
+                points.push(ContactPoint2 { point, separation: s - radius, id: feature(inc_edge) });
+            }
+        }
+    }
+
+    if points.is_empty() {
+        None
+    } else {
+        Some(Manifold2 { normal: n * sign, points })
+    }
+}
+
+/**
+ * Keeps the part of a two point segment where dot(normal, p) <= offset
+ **/
+fn clip(points: &[(Vec2, usize); 2], normal: Vec2, offset: f32) -> Option<[(Vec2, usize); 2]> {
+    let d0 = normal.dot(points[0].0) - offset;
+    let d1 = normal.dot(points[1].0) - offset;
+    let mut out = Vec::with_capacity(2);
+    if d0 <= 0.0 {
+        out.push(points[0]);
+    }
+    if d1 <= 0.0 {
+        out.push(points[1]);
+    }
+    if d0 * d1 < 0.0 {
+        let t = d0 / (d0 - d1);
+        //the new point belongs to whichever vertex was cut away
+        let vertex = if d0 > 0.0 { points[0].1 } else { points[1].1 };
+        out.push((points[0].0 + (points[1].0 - points[0].0) * t, vertex));
+    }
+    if out.len() == 2 { Some([out[0], out[1]]) } else { None }
+}
+
+pub fn closest_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len2 = ab.length_squared();
+    if len2 <= f32::EPSILON {
+        return a;
+    }
+    a + ab * ((p - a).dot(ab) / len2).clamp(0.0, 1.0)
+}
+
+/**
+ * Closest points between segments p1-q1 and p2-q2 with their fractions along each segment
+ **/
+fn closest_segments(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (f32, f32, Vec2, Vec2) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom > f32::EPSILON { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (s, t, p1 + d1 * s, p2 + d2 * t)
+}
+
+fn segments_cross(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> bool {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let denom = d1.cross(d2);
+    if denom.abs() <= f32::EPSILON {
+        return false;
+    }
+    let s = (p2 - p1).cross(d2) / denom;
+    let t = (p2 - p1).cross(d1) / denom;
+    (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t)
+}
+
+/**
+ * Closest points between the cores of two hulls, None when the cores overlap
+ * Subtract both radii from the distance to get the gap between the actual surfaces
+ **/
+pub fn core_distance(a: &Hull, b: &Hull) -> Option<(f32, Vec2, Vec2)> {
+    if a.points.iter().any(|&p| b.core_contains(p)) || b.points.iter().any(|&p| a.core_contains(p)) {
+        return None;
+    }
+    let (edges_a, edges_b) = (a.edges(), b.edges());
+    for &(p1, q1) in &edges_a {
+        for &(p2, q2) in &edges_b {
+            if segments_cross(p1, q1, p2, q2) {
+                return None;
+            }
+        }
+    }
+
+    let mut best = (f32::INFINITY, Vec2::ZERO, Vec2::ZERO);
+    for &(p1, q1) in &edges_a {
+        for &(p2, q2) in &edges_b {
+            let (_, _, c1, c2) = closest_segments(p1, q1, p2, q2);
+            let d = c1.distance(c2);
+            if d < best.0 {
+                best = (d, c1, c2);
+            }
+        }
+    }
+    Some(best)
+}
+
+/**
+ * Distance along a unit direction ray to the hull surface and the surface normal there
+ * Rays starting inside the hull don't hit it
+ **/
+pub fn raycast(hull: &Hull, origin: Vec2, dir: Vec2, max_distance: f32) -> Option<(f32, Vec2)> {
+    match hull.points.len() {
+        1 => ray_circle(hull.points[0], hull.radius, origin, dir, max_distance),
+        2 => {
+            if hull.radius <= 0.0 {
+                return None;
+            }
+            //a capsule is a rectangle around the segment with a circle at each end
+            let (a, b) = (hull.points[0], hull.points[1]);
+            let side = hull.normals[0] * hull.radius;
+            let rect = Hull::from_points(vec![a + side, b + side, b - side, a - side], 0.0);
+            if hull.contains_point(origin) {
+                return None;
+            }
+            [ray_polygon(&rect, origin, dir, max_distance),
+                ray_circle(a, hull.radius, origin, dir, max_distance),
+                ray_circle(b, hull.radius, origin, dir, max_distance)]
+                .iter()
+                .flatten()
+                .fold(None, |best: Option<(f32, Vec2)>, &x| match best {
+                    Some(b) if b.0 <= x.0 => Some(b),
+                    _ => Some(x)
+                })
+        },
+        _ => ray_polygon(hull, origin, dir, max_distance)
+    }
+}
+
+fn ray_circle(center: Vec2, radius: f32, origin: Vec2, dir: Vec2, max_distance: f32) -> Option<(f32, Vec2)> {
+    let m = origin - center;
+    let b = m.dot(dir);
+    let c = m.length_squared() - radius * radius;
+    if c <= 0.0 || b > 0.0 {
+        return None;
+    }
+    let disc = b * b - c;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = -b - disc.sqrt();
+    if t > max_distance {
+        return None;
+    }
+    Some((t, (origin + dir * t - center).normalize()))
+}
+
+fn ray_polygon(hull: &Hull, origin: Vec2, dir: Vec2, max_distance: f32) -> Option<(f32, Vec2)> {
+    let (mut lower, mut upper) = (0.0, max_distance);
+    let mut index = None;
+    for (i, (n, v)) in hull.normals.iter().zip(&hull.points).enumerate() {
+        let numerator = n.dot(*v - origin);
+        let denominator = n.dot(dir);
+        if denominator == 0.0 {
+            if numerator < 0.0 {
+                return None;
+            }
+        } else if denominator < 0.0 && numerator < lower * denominator {
+            lower = numerator / denominator;
+            index = Some(i);
+        } else if denominator > 0.0 && numerator < upper * denominator {
+            upper = numerator / denominator;
+        }
+        if upper < lower {
+            return None;
+        }
+    }
+    index.map(|i| (lower, hull.normals[i]))
+}
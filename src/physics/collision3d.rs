@@ -0,0 +1,710 @@
+use crate::math::{ Ray, Vec3 };
+use crate::physics::shape3d::{ ConvexPolyhedron, Isometry3, Shape3D };
+use crate::physics::LINEAR_SLOP;
+
+/**
+ * A convex shape in world space, core points inflated by a radius
+ * Spheres have one core point and capsules two, boxes, hulls and mesh triangles keep their
+ * faces and edges in `poly` for the separating axis test
+ **/
+#[derive(Debug, Clone)]
+pub struct Hull3 {
+    pub points: Vec<Vec3>,
+    pub radius: f32,
+    pub poly: Option<ConvexPolyhedron>,
+}
+
+impl Hull3 {
+    /**
+     * None for meshes, their triangles are collided one by one through Hull3::triangle
+     **/
+    pub fn new(shape: &Shape3D, iso: &Isometry3) -> Option<Hull3> {
+        match shape {
+            Shape3D::Sphere { radius } => Some(Hull3 { points: vec![iso.position], radius: *radius, poly: None }),
+            Shape3D::Capsule { half_height, radius } => Some(Hull3 {
+                points: vec![iso.transform_point(Vec3::new(0.0, -*half_height, 0.0)), iso.transform_point(Vec3::new(0.0, *half_height, 0.0))],
+                radius: *radius,
+                poly: None,
+            }),
+            Shape3D::Box { half_extents } => Some(Hull3::from_poly(ConvexPolyhedron::cuboid(*half_extents).transformed(iso))),
+            Shape3D::ConvexHull(hull) => Some(Hull3::from_poly(hull.transformed(iso))),
+            Shape3D::Mesh(_) => None
+        }
+    }
+
+    pub fn from_poly(poly: ConvexPolyhedron) -> Hull3 {
+        Hull3 { points: poly.vertices().to_vec(), radius: 0.0, poly: Some(poly) }
+    }
+
+    pub fn triangle(a: Vec3, b: Vec3, c: Vec3) -> Hull3 {
+        Hull3::from_poly(ConvexPolyhedron::triangle(a, b, c))
+    }
+
+    pub fn translated(&self, offset: Vec3) -> Hull3 {
+        let iso = Isometry3::new(offset, crate::math::Quat::IDENTITY);
+        Hull3 {
+            points: self.points.iter().map(|&p| p + offset).collect(),
+            radius: self.radius,
+            poly: self.poly.as_ref().map(|x| x.transformed(&iso)),
+        }
+    }
+
+    pub fn contains_point(&self, p: Vec3) -> bool {
+        match gjk_distance(&self.points, &[p]) {
+            Some((distance, _, _)) => distance <= self.radius,
+            None => true
+        }
+    }
+
+    fn centroid(&self) -> Vec3 {
+        self.points.iter().fold(Vec3::ZERO, |c, &p| c + p) / self.points.len() as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ContactPoint3 {
+    pub point: Vec3,
+    /**
+     * Negative when the shapes overlap
+     **/
+    pub separation: f32,
+    /**
+     * Identifies the features that touch so impulses can be carried to the next step
+     **/
+    pub id: u32,
+}
+
+/**
+ * Contact between two shapes, `normal` points from the first shape towards the second
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Manifold3 {
+    pub normal: Vec3,
+    pub points: Vec<ContactPoint3>,
+}
+
+/**
+ * Contact manifold of two hulls, including speculative points up to `margin` apart
+ **/
+pub fn collide(a: &Hull3, b: &Hull3, margin: f32) -> Option<Manifold3> {
+    match (&a.poly, &b.poly) {
+        (Some(pa), Some(pb)) if a.radius == 0.0 && b.radius == 0.0 => collide_polyhedra(pa, pb, margin),
+        _ => collide_rounded(a, b, margin)
+    }
+}
+
+#[inline]
+fn support(points: &[Vec3], direction: Vec3) -> Vec3 {
+    points.iter().skip(1).fold(points[0], |best, &p| if p.dot(direction) > best.dot(direction) { p } else { best })
+}
+
+fn project(points: &[Vec3], axis: Vec3) -> (f32, f32) {
+    points.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), p| {
+        let d = axis.dot(*p);
+        (lo.min(d), hi.max(d))
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SimplexVertex {
+    a: Vec3,
+    b: Vec3,
+    w: Vec3,
+}
+
+/**
+ * Closest points between the convex hulls of two point sets, None when they overlap
+ **/
+pub fn gjk_distance(a: &[Vec3], b: &[Vec3]) -> Option<(f32, Vec3, Vec3)> {
+    let mut simplex = vec![SimplexVertex { a: a[0], b: b[0], w: b[0] - a[0] }];
+    let mut weights = vec![1.0];
+    for _ in 0..32 {
+        let (v, lambdas) = closest_on_simplex(&simplex);
+        let kept: Vec<(SimplexVertex, f32)> = simplex.iter().zip(&lambdas).filter(|x| *x.1 > 0.0).map(|(s, l)| (*s, *l)).collect();
+        simplex = kept.iter().map(|x| x.0).collect();
+        weights = kept.iter().map(|x| x.1).collect();
+
+        let v2 = v.length_squared();
+        if v2 < 1e-10 || simplex.len() == 4 {
+            return None;
+        }
+        let sa = support(a, v);
+        let sb = support(b, -v);
+        let w = sb - sa;
+        //no support point gets closer to the origin than the current simplex
+        if v2 - v.dot(w) <= 1e-6 * v2 || simplex.iter().any(|x| x.w.approx_eq(w, 1e-7)) {
+            break;
+        }
+        simplex.push(SimplexVertex { a: sa, b: sb, w });
+    }
+
+    let pa = simplex.iter().zip(&weights).fold(Vec3::ZERO, |p, (s, l)| p + s.a * *l);
+    let pb = simplex.iter().zip(&weights).fold(Vec3::ZERO, |p, (s, l)| p + s.b * *l);
+    Some((pa.distance(pb), pa, pb))
+}
+
+/**
+ * Point of the simplex closest to the origin and its barycentric weights
+ **/
+fn closest_on_simplex(s: &[SimplexVertex]) -> (Vec3, Vec<f32>) {
+    match s.len() {
+        1 => (s[0].w, vec![1.0]),
+        2 => {
+            let (a, b) = (s[0].w, s[1].w);
+            let ab = b - a;
+            let len2 = ab.length_squared();
+            let t = if len2 > f32::EPSILON { (-a.dot(ab) / len2).clamp(0.0, 1.0) } else { 0.0 };
+            (a + ab * t, vec![1.0 - t, t])
+        },
+        3 => {
+            let (p, l) = closest_on_triangle(s[0].w, s[1].w, s[2].w);
+            (p, l.to_vec())
+        },
+        _ => {
+            let w = [s[0].w, s[1].w, s[2].w, s[3].w];
+            let faces = [[0, 1, 2, 3], [0, 2, 3, 1], [0, 3, 1, 2], [1, 3, 2, 0]];
+            let mut best: Option<(Vec3, Vec<f32>)> = None;
+            for f in faces {
+                let n = (w[f[1]] - w[f[0]]).cross(w[f[2]] - w[f[0]]);
+                let origin_side = (-w[f[0]]).dot(n);
+                let other_side = (w[f[3]] - w[f[0]]).dot(n);
+                if origin_side * other_side > 0.0 {
+                    continue;
+                }
+                let (p, l) = closest_on_triangle(w[f[0]], w[f[1]], w[f[2]]);
+                if best.as_ref().is_none_or(|b| p.length_squared() < b.0.length_squared()) {
+                    let mut lambdas = vec![0.0; 4];
+                    for k in 0..3 {
+                        lambdas[f[k]] = l[k];
+                    }
+                    best = Some((p, lambdas));
+                }
+            }
+            //the origin is inside the tetrahedron
+            best.unwrap_or((Vec3::ZERO, vec![0.25; 4]))
+        }
+    }
+}
+
+fn closest_on_triangle(a: Vec3, b: Vec3, c: Vec3) -> (Vec3, [f32; 3]) {
+    let ab = b - a;
+    let ac = c - a;
+    let d1 = ab.dot(-a);
+    let d2 = ac.dot(-a);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return (a, [1.0, 0.0, 0.0]);
+    }
+    let d3 = ab.dot(-b);
+    let d4 = ac.dot(-b);
+    if d3 >= 0.0 && d4 <= d3 {
+        return (b, [0.0, 1.0, 0.0]);
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, [1.0 - v, v, 0.0]);
+    }
+    let d5 = ab.dot(-c);
+    let d6 = ac.dot(-c);
+    if d6 >= 0.0 && d5 <= d6 {
+        return (c, [0.0, 0.0, 1.0]);
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, [1.0 - w, 0.0, w]);
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, [0.0, 1.0 - w, w]);
+    }
+    let denom = 1.0 / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+    (a + ab * v + ac * w, [1.0 - v - w, v, w])
+}
+
+/**
+ * Closest points between segments p1-q1 and p2-q2
+ **/
+pub fn closest_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.length_squared();
+    let e = d2.length_squared();
+    let f = d2.dot(r);
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom > f32::EPSILON { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/**
+ * Deepest face of `a` against the vertices of `b`
+ **/
+fn face_query(a: &ConvexPolyhedron, b: &ConvexPolyhedron) -> (usize, f32) {
+    let mut best = (0, f32::NEG_INFINITY);
+    for (i, face) in a.faces().iter().enumerate() {
+        let v0 = a.vertices()[face.vertices[0] as usize];
+        let s = project(b.vertices(), face.normal).0 - face.normal.dot(v0);
+        if s > best.1 {
+            best = (i, s);
+        }
+    }
+    best
+}
+
+/**
+ * Best axis among the edge pair cross products, oriented from a to b
+ **/
+fn edge_query(a: &ConvexPolyhedron, b: &ConvexPolyhedron) -> Option<(f32, Vec3, usize, usize)> {
+    let center_a = a.vertices().iter().fold(Vec3::ZERO, |c, &p| c + p) / a.vertices().len() as f32;
+    let center_b = b.vertices().iter().fold(Vec3::ZERO, |c, &p| c + p) / b.vertices().len() as f32;
+    let mut best: Option<(f32, Vec3, usize, usize)> = None;
+    for (i, &(a0, a1)) in a.edges().iter().enumerate() {
+        let pa = a.vertices()[a0 as usize];
+        let da = a.vertices()[a1 as usize] - pa;
+        for (j, &(b0, b1)) in b.edges().iter().enumerate() {
+            let db = b.vertices()[b1 as usize] - b.vertices()[b0 as usize];
+            let axis = da.cross(db);
+            let len2 = axis.length_squared();
+            if len2 < 1e-6 * da.length_squared() * db.length_squared() {
+                continue;
+            }
+            let mut n = axis / len2.sqrt();
+            let outward = n.dot(pa - center_a);
+            if outward < 0.0 || (outward.abs() < 1e-6 && n.dot(center_b - center_a) < 0.0) {
+                n = -n;
+            }
+            let s = project(b.vertices(), n).0 - project(a.vertices(), n).1;
+            if best.is_none_or(|x| s > x.0) {
+                best = Some((s, n, i, j));
+            }
+        }
+    }
+    best
+}
+
+fn collide_polyhedra(a: &ConvexPolyhedron, b: &ConvexPolyhedron, margin: f32) -> Option<Manifold3> {
+    let (face_a, sep_a) = face_query(a, b);
+    if sep_a > margin {
+        return None;
+    }
+    let (face_b, sep_b) = face_query(b, a);
+    if sep_b > margin {
+        return None;
+    }
+    let edge = edge_query(a, b);
+    if edge.is_some_and(|x| x.0 > margin) {
+        return None;
+    }
+
+    //faces win ties so resting contacts keep their full manifold
+    let tolerance = 0.1 * LINEAR_SLOP;
+    let face_sep = sep_a.max(sep_b);
+    if let Some((sep, n, i, j)) = edge {
+        if sep > face_sep + tolerance {
+            let (a0, a1) = supporting_edge(a, a.edges()[i], n);
+            let (b0, b1) = supporting_edge(b, b.edges()[j], -n);
+            let (ca, cb) = closest_segments(a0, a1, b0, b1);
+            let id = 0x8000_0000 | ((i as u32 & 0xFF) << 8) | (j as u32 & 0xFF);
+            return Some(Manifold3 { normal: n, points: vec![ContactPoint3 { point: (ca + cb) * 0.5, separation: sep, id }] });
+        }
+    }
+
+    let flip = sep_b > sep_a + tolerance;
+    let (reference, incident, ref_face) = if flip { (b, a, face_b) } else { (a, b, face_a) };
+    let points = clip_faces(reference, ref_face, incident, margin, flip);
+    if points.is_empty() {
+        return None;
+    }
+    let n = reference.faces()[ref_face].normal;
+    Some(Manifold3 { normal: if flip { -n } else { n }, points })
+}
+
+/**
+ * Edge of `poly` parallel to `edge` that lies furthest along `axis`
+ **/
+fn supporting_edge(poly: &ConvexPolyhedron, edge: (u32, u32), axis: Vec3) -> (Vec3, Vec3) {
+    let v = poly.vertices();
+    let dir = (v[edge.1 as usize] - v[edge.0 as usize]).normalize();
+    poly.edges().iter()
+        .map(|&(e0, e1)| (v[e0 as usize], v[e1 as usize]))
+        .filter(|&(p, q)| (q - p).normalize().cross(dir).length_squared() < 1e-4)
+        .fold((v[edge.0 as usize], v[edge.1 as usize]), |best, e| {
+            if (e.0 + e.1).dot(axis) > (best.0 + best.1).dot(axis) + 1e-6 { e } else { best }
+        })
+}
+
+fn clip_faces(reference: &ConvexPolyhedron, ref_face: usize, incident: &ConvexPolyhedron, margin: f32, flip: bool) -> Vec<ContactPoint3> {
+    let face = &reference.faces()[ref_face];
+    let n = face.normal;
+    let inc_face = incident.faces().iter().enumerate()
+        .fold((0, f32::INFINITY), |best, (i, f)| {
+            let d = f.normal.dot(n);
+            if d < best.1 { (i, d) } else { best }
+        }).0;
+
+    let mut polygon: Vec<(Vec3, u32)> = incident.faces()[inc_face].vertices.iter()
+        .map(|&i| (incident.vertices()[i as usize], i))
+        .collect();
+    let corners: Vec<Vec3> = face.vertices.iter().map(|&i| reference.vertices()[i as usize]).collect();
+    for k in 0..corners.len() {
+        let (r0, r1) = (corners[k], corners[(k + 1) % corners.len()]);
+        let side = (r1 - r0).cross(n);
+        polygon = clip_polygon(&polygon, side, side.dot(r0), k as u32);
+        if polygon.is_empty() {
+            break;
+        }
+    }
+
+    let feature = ((flip as u32) << 31) | ((ref_face as u32 & 0xFF) << 23) | ((inc_face as u32 & 0xFF) << 15);
+    let points: Vec<ContactPoint3> = polygon.iter().filter_map(|&(p, id)| {
+        let s = n.dot(p - corners[0]);
+        if s <= margin {
+            Some(ContactPoint3 { point: p - n * (s * 0.5), separation: s, id: feature | (id & 0x7FFF) })
+        } else {
+            None
+        }
+    }).collect();
+    reduce_points(points, n)
+}
+
+/**
+ * Sutherland-Hodgman against the plane dot(normal, p) <= offset
+ * New points are tagged with the clip plane and the edge they came from
+ **/
+fn clip_polygon(polygon: &[(Vec3, u32)], normal: Vec3, offset: f32, plane: u32) -> Vec<(Vec3, u32)> {
+    let mut out = Vec::with_capacity(polygon.len() + 1);
+    for i in 0..polygon.len() {
+        let (p, pid) = polygon[i];
+        let (q, _) = polygon[(i + 1) % polygon.len()];
+        let dp = normal.dot(p) - offset;
+        let dq = normal.dot(q) - offset;
+        if dp <= 0.0 {
+            out.push((p, pid));
+        }
+        if dp * dq < 0.0 {
+            let t = dp / (dp - dq);
+            out.push((p + (q - p) * t, 0x4000 | ((plane & 0x7F) << 7) | (i as u32 & 0x7F)));
+        }
+    }
+    out
+}
+
+/**
+ * Keeps the deepest point and three more spanning the largest area
+ **/
+fn reduce_points(points: Vec<ContactPoint3>, normal: Vec3) -> Vec<ContactPoint3> {
+    if points.len() <= 4 {
+        return points;
+    }
+    let argmax = |f: &dyn Fn(&ContactPoint3) -> f32| (0..points.len()).fold(0, |best, i| if f(&points[i]) > f(&points[best]) { i } else { best });
+    let a = argmax(&|p| -p.separation);
+    let pa = points[a].point;
+    let b = argmax(&|p| p.point.distance(pa));
+    let pb = points[b].point;
+    let area = |p: &ContactPoint3| (pb - pa).cross(p.point - pa).dot(normal);
+    let c = argmax(&|p| area(p).abs());
+    let sign = area(&points[c]).signum();
+    let d = argmax(&|p| -sign * area(p));
+    let mut keep = vec![a, b, c, d];
+    keep.sort_unstable();
+    keep.dedup();
+    keep.into_iter().map(|i| points[i]).collect()
+}
+
+fn collide_rounded(a: &Hull3, b: &Hull3, margin: f32) -> Option<Manifold3> {
+    let radius = a.radius + b.radius;
+    let separated = gjk_distance(&a.points, &b.points).filter(|x| x.0 > 1e-4);
+    let (distance, ca, cb) = match separated {
+        Some(x) => x,
+        None => return penetration(a, b, margin)
+    };
+    if distance - radius > margin {
+        return None;
+    }
+    let n = (cb - ca) / distance;
+
+    //a capsule lying flat against something touches along its length, so use both ends
+    let mut ends = Vec::new();
+    for (hull, other, sign, base) in [(a, b, 1.0, 1), (b, a, -1.0, 3)] {
+        if hull.points.len() != 2 {
+            continue;
+        }
+        for (k, &e) in hull.points.iter().enumerate() {
+            if let Some((d, _, q)) = gjk_distance(&[e], &other.points).filter(|x| x.0 > 1e-4) {
+                let ne = (q - e) / d * sign;
+                if ne.dot(n) > 0.995 && d - radius <= margin {
+                    let (on_a, on_b) = if sign > 0.0 { (e, q) } else { (q, e) };
+                    let point = ((on_a + n * a.radius) + (on_b - n * b.radius)) * 0.5;
+                    ends.push(ContactPoint3 { point, separation: d - radius, id: base + k as u32 });
+                }
+            }
+        }
+    }
+    if ends.len() >= 2 {
+        return Some(Manifold3 { normal: n, points: ends });
+    }
+    let point = ((ca + n * a.radius) + (cb - n * b.radius)) * 0.5;
+    Some(Manifold3 { normal: n, points: vec![ContactPoint3 { point, separation: distance - radius, id: 0 }] })
+}
+
+/**
+ * Cores overlap, push out along the axis of least penetration
+ **/
+fn penetration(a: &Hull3, b: &Hull3, margin: f32) -> Option<Manifold3> {
+    let center_a = a.centroid();
+    let center_b = b.centroid();
+    let towards = center_b - center_a;
+
+    let edges = |h: &Hull3| -> Vec<Vec3> {
+        match &h.poly {
+            Some(p) => p.edges().iter().map(|&(i, j)| p.vertices()[j as usize] - p.vertices()[i as usize]).collect(),
+            None if h.points.len() == 2 => vec![h.points[1] - h.points[0]],
+            None => Vec::new()
+        }
+    };
+    let mut axes: Vec<Vec3> = Vec::new();
+    if let Some(p) = &a.poly {
+        axes.extend(p.faces().iter().map(|f| f.normal));
+    }
+    if let Some(p) = &b.poly {
+        axes.extend(p.faces().iter().map(|f| -f.normal));
+    }
+    for ea in edges(a) {
+        for eb in edges(b) {
+            let axis = ea.cross(eb);
+            if axis.length_squared() > 1e-6 * ea.length_squared() * eb.length_squared() {
+                let n = axis.normalize();
+                axes.push(if n.dot(towards) < 0.0 { -n } else { n });
+            }
+        }
+    }
+    //a segment against a point or another segment also separates perpendicular to the segment
+    for (h, other) in [(a, b), (b, a)] {
+        if h.points.len() == 2 {
+            let (p, q) = (h.points[0], h.points[1]);
+            let c = other.centroid();
+            let off = c - (p + (q - p) * ((c - p).dot(q - p) / (q - p).length_squared().max(f32::EPSILON)).clamp(0.0, 1.0));
+            if off.length_squared() > 1e-10 {
+                let n = off.normalize();
+                axes.push(if n.dot(towards) < 0.0 { -n } else { n });
+            }
+        }
+    }
+    if towards.length_squared() > 1e-10 {
+        axes.push(towards.normalize());
+    }
+    if axes.is_empty() {
+        axes.push(Vec3::Y);
+    }
+
+    let radius = a.radius + b.radius;
+    let (separation, n) = axes.iter().fold((f32::NEG_INFINITY, Vec3::Y), |best, &n| {
+        let s = project(&b.points, n).0 - project(&a.points, n).1 - radius;
+        if s > best.0 { (s, n) } else { best }
+    });
+    if separation > margin {
+        return None;
+    }
+    let surface_b = support(&b.points, -n) - n * b.radius;
+    let point = surface_b - n * (separation * 0.5);
+    Some(Manifold3 { normal: n, points: vec![ContactPoint3 { point, separation, id: 0 }] })
+}
+
+/**
+ * Distance along a unit direction ray to the shape and the surface normal there
+ * Rays starting inside a solid shape don't hit it, mesh triangles are hit from both sides
+ **/
+pub fn raycast(shape: &Shape3D, iso: &Isometry3, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+    let o = iso.inverse_transform_point(origin);
+    let d = iso.inverse_transform_vector(dir);
+    let hit = match shape {
+        Shape3D::Sphere { radius } => ray_sphere(Vec3::ZERO, *radius, o, d, max_distance),
+        Shape3D::Box { half_extents: e } => ray_box(*e, o, d, max_distance),
+        Shape3D::Capsule { half_height, radius } => ray_capsule(*half_height, *radius, o, d, max_distance),
+        Shape3D::ConvexHull(hull) => ray_polyhedron(hull, o, d, max_distance),
+        Shape3D::Mesh(mesh) => mesh.raycast(&Ray { origin: o, direction: d }, max_distance).map(|(t, i)| {
+            let [a, b, c] = mesh.triangle(i);
+            let n = (b - a).cross(c - a).normalize();
+            (t, if n.dot(d) > 0.0 { -n } else { n })
+        })
+    };
+    hit.map(|(t, n)| (t, iso.transform_vector(n)))
+}
+
+fn ray_sphere(center: Vec3, radius: f32, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+    let m = origin - center;
+    let b = m.dot(dir);
+    let c = m.length_squared() - radius * radius;
+    if c <= 0.0 || b > 0.0 {
+        return None;
+    }
+    let disc = b * b - c;
+    if disc < 0.0 {
+        return None;
+    }
+    let t = -b - disc.sqrt();
+    if t > max_distance {
+        return None;
+    }
+    Some((t, (origin + dir * t - center).normalize()))
+}
+
+fn ray_box(e: Vec3, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+    let (mut lower, mut upper) = (f32::NEG_INFINITY, max_distance);
+    let mut normal = Vec3::ZERO;
+    for axis in 0..3 {
+        let (o, d) = (origin[axis], dir[axis]);
+        if d.abs() < f32::EPSILON {
+            if o < -e[axis] || o > e[axis] {
+                return None;
+            }
+            continue;
+        }
+        let (mut t0, mut t1) = ((-e[axis] - o) / d, (e[axis] - o) / d);
+        let mut n = Vec3::ZERO;
+        n[axis] = -d.signum();
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+        }
+        if t0 > lower {
+            lower = t0;
+            normal = n;
+        }
+        upper = upper.min(t1);
+        if lower > upper {
+            return None;
+        }
+    }
+    if lower < 0.0 {
+        None
+    } else {
+        Some((lower, normal))
+    }
+}
+
+fn ray_capsule(half_height: f32, radius: f32, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+    let (p, q) = (Vec3::new(0.0, -half_height, 0.0), Vec3::new(0.0, half_height, 0.0));
+    let inside = gjk_distance(&[p, q], &[origin]).is_none_or(|x| x.0 <= radius);
+    if inside {
+        return None;
+    }
+
+    let mut best: Option<(f32, Vec3)> = None;
+    let mut consider = |hit: Option<(f32, Vec3)>| {
+        if let Some(h) = hit {
+            if best.is_none_or(|b| h.0 < b.0) {
+                best = Some(h);
+            }
+        }
+    };
+    consider(ray_sphere(p, radius, origin, dir, max_distance));
+    consider(ray_sphere(q, radius, origin, dir, max_distance));
+
+    //the side of the cylinder around the y axis
+    let (ox, oz, dx, dz) = (origin.x, origin.z, dir.x, dir.z);
+    let a = dx * dx + dz * dz;
+    if a > f32::EPSILON {
+        let b = ox * dx + oz * dz;
+        let c = ox * ox + oz * oz - radius * radius;
+        let disc = b * b - a * c;
+        if disc >= 0.0 {
+            let t = (-b - disc.sqrt()) / a;
+            let y = origin.y + dir.y * t;
+            if t >= 0.0 && t <= max_distance && y.abs() <= half_height {
+                let hit = origin + dir * t;
+                consider(Some((t, Vec3::new(hit.x, 0.0, hit.z).normalize())));
+            }
+        }
+    }
+    best
+}
+
+fn ray_polyhedron(poly: &ConvexPolyhedron, origin: Vec3, dir: Vec3, max_distance: f32) -> Option<(f32, Vec3)> {
+    let (mut lower, mut upper) = (0.0, max_distance);
+    let mut index = None;
+    for (i, face) in poly.faces().iter().enumerate() {
+        let n = face.normal;
+        let numerator = n.dot(poly.vertices()[face.vertices[0] as usize] - origin);
+        let denominator = n.dot(dir);
+        if denominator == 0.0 {
+            if numerator < 0.0 {
+                return None;
+            }
+        } else if denominator < 0.0 && numerator < lower * denominator {
+            lower = numerator / denominator;
+            index = Some(i);
+        } else if denominator > 0.0 && numerator < upper * denominator {
+            upper = numerator / denominator;
+        }
+        if upper < lower {
+            return None;
+        }
+    }
+    index.map(|i| (lower, poly.faces()[i].normal))
+}
+
+/**
+ * Conservative advancement of `cast` along `translation` towards `target`, returns the
+ * fraction, the point and the target's surface normal at the first touch
+ **/
+pub fn sweep(cast: &Hull3, target: &Hull3, translation: Vec3) -> Option<(f32, Vec3, Vec3)> {
+    let radius = cast.radius + target.radius;
+    let mut fraction = 0.0;
+    for _ in 0..32 {
+        let moved: Vec<Vec3> = cast.points.iter().map(|&p| p + translation * fraction).collect();
+        let (distance, on_cast, on_target) = match gjk_distance(&moved, &target.points) {
+            Some(x) => x,
+            None => return Some((fraction, moved[0], -translation.normalize()))
+        };
+        let normal = if distance > f32::EPSILON { (on_cast - on_target) / distance } else { -translation.normalize() };
+        let gap = distance - radius;
+        let closing = -translation.dot(normal);
+        //touching shapes only hit when moving into each other, sliding along a surface is fine
+        if closing <= f32::EPSILON {
+            return None;
+        }
+        if gap < 1.5 * LINEAR_SLOP {
+            return Some((fraction, on_target + normal * target.radius, normal));
+        }
+        fraction += (gap - LINEAR_SLOP) / closing;
+        if fraction > 1.0 {
+            return None;
+        }
+    }
+    None
+}
+
+/**
+ * Whether two hulls overlap
+ **/
+pub fn intersects(a: &Hull3, b: &Hull3) -> bool {
+    match gjk_distance(&a.points, &b.points) {
+        Some((distance, _, _)) => distance < a.radius + b.radius,
+        None => true
+    }
+}
@@ -0,0 +1,49 @@
+use crate::math::Vec3;
+
+/**
+ * Receives the lines a physics world draws for debugging, implemented by whatever renders them
+ **/
+pub trait PhysicsDebugDraw {
+    fn line(&mut self, from: Vec3, to: Vec3, color: [f32; 4]);
+}
+
+/**
+ * What PhysicsWorld3D::debug_draw outputs
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DebugDrawFlags {
+    pub shapes: bool,
+    pub aabbs: bool,
+    pub contacts: bool,
+    pub joints: bool,
+}
+
+impl Default for DebugDrawFlags {
+    fn default() -> DebugDrawFlags {
+        DebugDrawFlags { shapes: true, aabbs: false, contacts: true, joints: true }
+    }
+}
+
+pub const COLOR_STATIC: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+pub const COLOR_DYNAMIC: [f32; 4] = [0.2, 0.9, 0.2, 1.0];
+pub const COLOR_KINEMATIC: [f32; 4] = [0.2, 0.4, 1.0, 1.0];
+pub const COLOR_SENSOR: [f32; 4] = [1.0, 0.9, 0.1, 1.0];
+pub const COLOR_AABB: [f32; 4] = [0.8, 0.3, 0.8, 1.0];
+pub const COLOR_CONTACT: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+pub const COLOR_JOINT: [f32; 4] = [0.2, 0.9, 0.9, 1.0];
+
+/**
+ * Circle around `center` in the plane perpendicular to `normal`
+ **/
+pub fn circle(draw: &mut dyn PhysicsDebugDraw, center: Vec3, normal: Vec3, radius: f32, color: [f32; 4]) {
+    const SEGMENTS: usize = 16;
+    let u = normal.any_orthogonal() * radius;
+    let v = normal.normalize().cross(u);
+    let point = |i: usize| {
+        let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+        center + u * angle.cos() + v * angle.sin()
+    };
+    for i in 0..SEGMENTS {
+        draw.line(point(i), point(i + 1), color);
+    }
+}
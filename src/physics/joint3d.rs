@@ -0,0 +1,236 @@
+use crate::math::{ Mat3, Quat, Vec3 };
+use crate::physics::BodyHandle;
+use crate::physics::BAUMGARTE;
+
+/**
+ * How a joint constrains its two bodies, axes are given in world space when the joint is added
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JointKind {
+    /**
+     * Keeps the bodies at the same relative position and rotation
+     **/
+    Fixed,
+    /**
+     * Shared anchor point, free rotation
+     **/
+    Ball,
+    /**
+     * Shared anchor point, rotation only about `axis`
+     **/
+    Hinge { axis: Vec3 },
+    /**
+     * Keeps the anchor on the first body and the second body's origin between `min` and `max`
+     * apart, equal values make a rigid rod
+     **/
+    Distance { min: f32, max: f32 },
+}
+
+/**
+ * Velocity state of a body while the world solves, shared by contacts and joints
+ **/
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SolverBody {
+    pub linear_velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub inv_mass: f32,
+    pub inv_inertia: Mat3,
+    pub center: Vec3,
+    pub rotation: Quat,
+    pub local_center: Vec3,
+}
+
+impl SolverBody {
+    #[inline]
+    pub fn velocity_at(&self, r: Vec3) -> Vec3 {
+        self.linear_velocity + self.angular_velocity.cross(r)
+    }
+
+    #[inline]
+    pub fn apply(&mut self, r: Vec3, impulse: Vec3) {
+        self.linear_velocity += impulse * self.inv_mass;
+        self.angular_velocity += self.inv_inertia * r.cross(impulse);
+    }
+
+    #[inline]
+    pub fn apply_angular(&mut self, impulse: Vec3) {
+        self.angular_velocity += self.inv_inertia * impulse;
+    }
+
+    /**
+     * Anchor relative to the center of mass in world space, from an anchor relative to the body origin
+     **/
+    #[inline]
+    pub fn anchor(&self, local: Vec3) -> Vec3 {
+        self.rotation.rotate(local - self.local_center)
+    }
+}
+
+/**
+ * Effective mass of a point constraint along `axis`
+ **/
+pub(crate) fn point_mass(a: &SolverBody, b: &SolverBody, ra: Vec3, rb: Vec3, axis: Vec3) -> f32 {
+    let rna = ra.cross(axis);
+    let rnb = rb.cross(axis);
+    let k = a.inv_mass + b.inv_mass + rna.dot(a.inv_inertia * rna) + rnb.dot(b.inv_inertia * rnb);
+    if k > 0.0 { 1.0 / k } else { 0.0 }
+}
+
+fn angular_mass(a: &SolverBody, b: &SolverBody, axis: Vec3) -> f32 {
+    let k = axis.dot(a.inv_inertia * axis) + axis.dot(b.inv_inertia * axis);
+    if k > 0.0 { 1.0 / k } else { 0.0 }
+}
+
+/**
+ * Joint between two bodies, created through PhysicsWorld3D::add_joint
+ **/
+#[derive(Debug, Clone)]
+pub struct Joint3D {
+    kind: JointKind,
+    body_a: BodyHandle,
+    body_b: BodyHandle,
+    //anchors and axes relative to each body's origin and rotation
+    local_anchor_a: Vec3,
+    local_anchor_b: Vec3,
+    local_axis_a: Vec3,
+    local_axis_b: Vec3,
+    //rotation of b relative to a when the joint was made
+    reference: Quat,
+    /**
+     * Whether the two bodies still collide with each other
+     **/
+    pub collide_connected: bool,
+    //accumulated impulses for warm starting, linear rows then angular rows
+    impulses: [f32; 6],
+}
+
+impl Joint3D {
+    pub(crate) fn new(kind: JointKind, body_a: BodyHandle, body_b: BodyHandle, a: (Vec3, Quat), b: (Vec3, Quat), anchor: Vec3) -> Joint3D {
+        let inverse_a = a.1.conjugate();
+        let inverse_b = b.1.conjugate();
+        let axis = match kind {
+            JointKind::Hinge { axis } => axis.normalize(),
+            _ => Vec3::Y
+        };
+        Joint3D {
+            kind,
+            body_a,
+            body_b,
+            local_anchor_a: inverse_a.rotate(anchor - a.0),
+            local_anchor_b: match kind {
+                JointKind::Distance { .. } => Vec3::ZERO,
+                _ => inverse_b.rotate(anchor - b.0)
+            },
+            local_axis_a: inverse_a.rotate(axis),
+            local_axis_b: inverse_b.rotate(axis),
+            reference: (inverse_a * b.1).normalize(),
+            collide_connected: false,
+            impulses: [0.0; 6],
+        }
+    }
+
+    #[inline]
+    pub fn kind(&self) -> JointKind {
+        self.kind
+    }
+
+    #[inline]
+    pub fn bodies(&self) -> (BodyHandle, BodyHandle) {
+        (self.body_a, self.body_b)
+    }
+
+    /**
+     * World space anchors on each body, they only drift apart while the solver catches up
+     **/
+    pub fn anchors(&self, a: (Vec3, Quat), b: (Vec3, Quat)) -> (Vec3, Vec3) {
+        (a.0 + a.1.rotate(self.local_anchor_a), b.0 + b.1.rotate(self.local_anchor_b))
+    }
+
+    pub(crate) fn warm_start(&mut self, a: &mut SolverBody, b: &mut SolverBody) {
+        let ra = a.anchor(self.local_anchor_a);
+        let rb = b.anchor(self.local_anchor_b);
+        let (linear, angular) = self.axes(a, b);
+        for (k, axis) in linear.iter().enumerate() {
+            let impulse = *axis * self.impulses[k];
+            a.apply(ra, -impulse);
+            b.apply(rb, impulse);
+        }
+        for (k, axis) in angular.iter().enumerate() {
+            let impulse = *axis * self.impulses[3 + k];
+            a.apply_angular(-impulse);
+            b.apply_angular(impulse);
+        }
+    }
+
+    /**
+     * World axes of the linear and angular rows this step
+     **/
+    fn axes(&self, a: &SolverBody, b: &SolverBody) -> (Vec<Vec3>, Vec<Vec3>) {
+        match self.kind {
+            JointKind::Fixed => (vec![Vec3::X, Vec3::Y, Vec3::Z], vec![Vec3::X, Vec3::Y, Vec3::Z]),
+            JointKind::Ball => (vec![Vec3::X, Vec3::Y, Vec3::Z], Vec::new()),
+            JointKind::Hinge { .. } => {
+                let axis = a.rotation.rotate(self.local_axis_a);
+                let t1 = axis.any_orthogonal();
+                (vec![Vec3::X, Vec3::Y, Vec3::Z], vec![t1, axis.cross(t1)])
+            },
+            JointKind::Distance { .. } => {
+                let d = (b.center + b.anchor(self.local_anchor_b)) - (a.center + a.anchor(self.local_anchor_a));
+                (vec![if d.length_squared() > f32::EPSILON { d.normalize() } else { Vec3::Y }], Vec::new())
+            }
+        }
+    }
+
+    pub(crate) fn solve(&mut self, a: &mut SolverBody, b: &mut SolverBody, dt: f32) {
+        let ra = a.anchor(self.local_anchor_a);
+        let rb = b.anchor(self.local_anchor_b);
+        let error = (b.center + rb) - (a.center + ra);
+        let (linear, angular) = self.axes(a, b);
+
+        //angular first, the point constraint matters more and goes last
+        let angular_error = match self.kind {
+            JointKind::Fixed => {
+                let target = (a.rotation * self.reference).normalize();
+                let q = (b.rotation * target.conjugate()).normalize();
+                let sign = if q.w < 0.0 { -2.0 } else { 2.0 };
+                Vec3::new(q.x, q.y, q.z) * sign
+            },
+            JointKind::Hinge { .. } => a.rotation.rotate(self.local_axis_a).cross(b.rotation.rotate(self.local_axis_b)),
+            _ => Vec3::ZERO
+        };
+        for (k, axis) in angular.iter().enumerate() {
+            let mass = angular_mass(a, b, *axis);
+            let jv = (b.angular_velocity - a.angular_velocity).dot(*axis);
+            let lambda = -mass * (jv + BAUMGARTE / dt * angular_error.dot(*axis));
+            self.impulses[3 + k] += lambda;
+            a.apply_angular(-*axis * lambda);
+            b.apply_angular(*axis * lambda);
+        }
+
+        for (k, axis) in linear.iter().enumerate() {
+            let (c, lower, upper) = match self.kind {
+                JointKind::Distance { min, max } => {
+                    let length = error.length();
+                    if min == max {
+                        (length - min, f32::NEG_INFINITY, f32::INFINITY)
+                    } else if length < min {
+                        (length - min, 0.0, f32::INFINITY)
+                    } else if length > max {
+                        (length - max, f32::NEG_INFINITY, 0.0)
+                    } else {
+                        self.impulses[k] = 0.0;
+                        continue;
+                    }
+                },
+                _ => (error.dot(*axis), f32::NEG_INFINITY, f32::INFINITY)
+            };
+            let mass = point_mass(a, b, ra, rb, *axis);
+            let jv = (b.velocity_at(rb) - a.velocity_at(ra)).dot(*axis);
+            let old = self.impulses[k];
+            self.impulses[k] = (old - mass * (jv + BAUMGARTE / dt * c)).clamp(lower, upper);
+            let impulse = *axis * (self.impulses[k] - old);
+            a.apply(ra, -impulse);
+            b.apply(rb, impulse);
+        }
+    }
+}
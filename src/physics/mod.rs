@@ -0,0 +1,152 @@
+pub mod arena;
+pub mod broadphase;
+pub mod shape2d;
+pub mod collision2d;
+pub mod world2d;
+pub mod shape3d;
+pub mod collision3d;
+pub mod joint3d;
+pub mod world3d;
+pub mod character;
+pub mod debug;
+
+pub use self::arena::{ BodyHandle, ColliderHandle, JointHandle };
+pub use self::shape2d::{ Aabb2, Isometry2, Shape2D };
+pub use self::world2d::{ Collider2D, PhysicsWorld2D, RayHit2D, RigidBody2D, ShapeHit2D };
+pub use self::shape3d::{ ConvexPolyhedron, Isometry3, Shape3D, TriMesh };
+pub use self::joint3d::{ Joint3D, JointKind };
+pub use self::world3d::{ Collider3D, PhysicsWorld3D, RayHit3D, RigidBody3D, ShapeHit3D };
+pub use self::character::{ CharacterController, CharacterMove };
+pub use self::debug::{ DebugDrawFlags, PhysicsDebugDraw };
+
+use std::error::Error;
+use std::fmt;
+
+use crate::core::signals::{ SyncData, SyncSlotPair };
+use crate::events::event::{ Event, EventType };
+
+use serde::{ Deserialize, Serialize };
+
+/**
+ * Penetration the solver leaves alone, keeps resting contacts from jittering in and out
+ **/
+pub const LINEAR_SLOP: f32 = 0.005;
+
+/**
+ * Contacts are created this far before shapes touch so fast bodies are caught without
+ * tunnelling a whole step into each other
+ **/
+pub const SPECULATIVE_DISTANCE: f32 = 4.0 * LINEAR_SLOP;
+
+/**
+ * Fraction of the remaining penetration removed each step
+ **/
+pub(crate) const BAUMGARTE: f32 = 0.2;
+
+/**
+ * Cap on the separation speed used to push overlapping bodies apart
+ **/
+pub(crate) const MAX_CORRECTION_SPEED: f32 = 4.0;
+
+/**
+ * Approach speeds below this don't bounce, stops resting bodies from vibrating
+ **/
+pub(crate) const RESTITUTION_THRESHOLD: f32 = 1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub enum BodyType {
+    /**
+     * Never moves, infinite mass
+     **/
+    Static,
+    /**
+     * Moved by forces, gravity and contacts
+     **/
+    Dynamic,
+    /**
+     * Moved only by its velocity, pushes dynamic bodies but isn't pushed back
+     **/
+    Kinematic,
+}
+
+/**
+ * Which colliders may touch, two colliders interact when each one's layers
+ * overlap the other's mask
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+pub struct CollisionFilter {
+    pub layers: u32,
+    pub mask: u32,
+}
+
+impl CollisionFilter {
+    pub const ALL: CollisionFilter = CollisionFilter { layers: u32::MAX, mask: u32::MAX };
+
+    pub fn new(layers: u32, mask: u32) -> CollisionFilter {
+        CollisionFilter { layers, mask }
+    }
+
+    pub fn interacts(&self, other: &CollisionFilter) -> bool {
+        self.layers & other.mask != 0 && other.layers & self.mask != 0
+    }
+}
+
+impl Default for CollisionFilter {
+    fn default() -> CollisionFilter {
+        CollisionFilter::ALL
+    }
+}
+
+/**
+ * Start or end of a touch between two colliders, `a` is always the lower handle
+ * Sensor contacts are reported the same way, they just never push anything
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContactEvent {
+    Begin { a: ColliderHandle, b: ColliderHandle, sensor: bool },
+    End { a: ColliderHandle, b: ColliderHandle, sensor: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhysicsError {
+    InvalidBody,
+    InvalidCollider,
+    InvalidShape(String),
+}
+
+impl fmt::Display for PhysicsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PhysicsError::InvalidBody => write!(f, "Invalid Rigid Body Handle"),
+            PhysicsError::InvalidCollider => write!(f, "Invalid Collider Handle"),
+            PhysicsError::InvalidShape(msg) => write!(f, "Invalid collision shape: {}", msg),
+        }
+    }
+}
+
+impl Error for PhysicsError {}
+
+/**
+ * Delivers a contact event to the slots connected for its type
+ **/
+pub(crate) fn emit_contact<'a>(slots: &[SyncSlotPair], ev: &dyn Event, kind: EventType) -> Result<(), &'a str> {
+    let data = SyncData::Sig(match ev.get_data() {
+        Some(x) => x,
+        None => return Err("No data in event!")
+    });
+    let mut handled = ev.get_handled();
+    for slot in slots {
+        if slot.1 == kind && !handled {
+            handled = match slot.0.write() {
+                Ok(mut x) => x.consume(&data),
+                _ => {
+                    debug!("Unable to lock slot for signal consumption");
+                    false
+                }
+            }
+        }
+    }
+    Ok(())
+}
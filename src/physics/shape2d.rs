@@ -0,0 +1,261 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::math::Vec2;
+use crate::physics::broadphase::Bounds;
+use crate::physics::PhysicsError;
+
+/**
+ * Position and rotation (radians, counter clockwise) of a body or collider in 2D
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Isometry2 {
+    pub position: Vec2,
+    pub angle: f32,
+}
+
+impl Isometry2 {
+    pub const IDENTITY: Isometry2 = Isometry2 { position: Vec2::ZERO, angle: 0.0 };
+
+    pub fn new(position: Vec2, angle: f32) -> Isometry2 {
+        Isometry2 { position, angle }
+    }
+
+    #[inline]
+    pub fn transform_point(&self, p: Vec2) -> Vec2 {
+        self.position + p.rotate(self.angle)
+    }
+
+    #[inline]
+    pub fn transform_vector(&self, v: Vec2) -> Vec2 {
+        v.rotate(self.angle)
+    }
+
+    #[inline]
+    pub fn inverse_transform_point(&self, p: Vec2) -> Vec2 {
+        (p - self.position).rotate(-self.angle)
+    }
+
+    #[inline]
+    pub fn inverse_transform_vector(&self, v: Vec2) -> Vec2 {
+        v.rotate(-self.angle)
+    }
+
+    /**
+     * `local` expressed relative to this isometry, e.g. a collider offset on a body
+     **/
+    pub fn mul(&self, local: &Isometry2) -> Isometry2 {
+        Isometry2 { position: self.transform_point(local.position), angle: self.angle + local.angle }
+    }
+}
+
+impl Default for Isometry2 {
+    fn default() -> Isometry2 {
+        Isometry2::IDENTITY
+    }
+}
+
+/**
+ * Axis aligned box in 2D
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Aabb2 {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb2 {
+    pub fn new(min: Vec2, max: Vec2) -> Aabb2 {
+        Aabb2 { min: min.min(max), max: min.max(max) }
+    }
+
+    pub fn from_points(points: &[Vec2]) -> Aabb2 {
+        let first = points.first().cloned().unwrap_or(Vec2::ZERO);
+        points.iter().fold(Aabb2 { min: first, max: first }, |b, &p| Aabb2 { min: b.min.min(p), max: b.max.max(p) })
+    }
+
+    pub fn expand(&self, amount: f32) -> Aabb2 {
+        Aabb2 { min: self.min - Vec2::splat(amount), max: self.max + Vec2::splat(amount) }
+    }
+
+    pub fn merge(&self, other: &Aabb2) -> Aabb2 {
+        Aabb2 { min: self.min.min(other.min), max: self.max.max(other.max) }
+    }
+
+    pub fn contains_point(&self, p: Vec2) -> bool {
+        p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+    }
+
+    pub fn intersects(&self, other: &Aabb2) -> bool {
+        self.min.x <= other.max.x && self.max.x >= other.min.x && self.min.y <= other.max.y && self.max.y >= other.min.y
+    }
+}
+
+impl Bounds for Aabb2 {
+    fn min_x(&self) -> f32 {
+        self.min.x
+    }
+
+    fn max_x(&self) -> f32 {
+        self.max.x
+    }
+
+    fn overlaps(&self, other: &Aabb2) -> bool {
+        self.intersects(other)
+    }
+}
+
+/**
+ * Mass, rotational inertia about the centroid and centroid in local space
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProperties2 {
+    pub mass: f32,
+    pub inertia: f32,
+    pub centroid: Vec2,
+}
+
+/**
+ * Collision shape in the collider's local space
+ * Every shape is a convex core (a point, a segment or a polygon) inflated by a radius,
+ * which lets the narrowphase treat them all the same way
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Shape2D {
+    Circle { radius: f32 },
+    Rect { half_extents: Vec2 },
+    /**
+     * Convex, counter clockwise, built through Shape2D::polygon
+     **/
+    Polygon { vertices: Vec<Vec2> },
+    /**
+     * Segment from (0, -half_height) to (0, half_height) inflated by radius
+     **/
+    Capsule { half_height: f32, radius: f32 },
+}
+
+impl Shape2D {
+    pub fn circle(radius: f32) -> Shape2D {
+        Shape2D::Circle { radius: radius.abs() }
+    }
+
+    pub fn rect(half_width: f32, half_height: f32) -> Shape2D {
+        Shape2D::Rect { half_extents: Vec2::new(half_width.abs(), half_height.abs()) }
+    }
+
+    pub fn capsule(half_height: f32, radius: f32) -> Shape2D {
+        Shape2D::Capsule { half_height: half_height.abs(), radius: radius.abs() }
+    }
+
+    /**
+     * Convex hull of `points`, fails if the points don't enclose any area
+     **/
+    pub fn polygon(points: &[Vec2]) -> Result<Shape2D, PhysicsError> {
+        let vertices = convex_hull(points);
+        if vertices.len() < 3 {
+            return Err(PhysicsError::InvalidShape("polygon needs at least 3 points that aren't collinear".to_string()));
+        }
+        Ok(Shape2D::Polygon { vertices })
+    }
+
+    /**
+     * Core points in local space and the radius they are inflated by
+     **/
+    pub fn core(&self) -> (Vec<Vec2>, f32) {
+        match self {
+            Shape2D::Circle { radius } => (vec![Vec2::ZERO], *radius),
+            Shape2D::Rect { half_extents: e } => (vec![
+                Vec2::new(-e.x, -e.y), Vec2::new(e.x, -e.y), Vec2::new(e.x, e.y), Vec2::new(-e.x, e.y)
+            ], 0.0),
+            Shape2D::Polygon { vertices } => (vertices.clone(), 0.0),
+            Shape2D::Capsule { half_height, radius } =>
+                (vec![Vec2::new(0.0, -*half_height), Vec2::new(0.0, *half_height)], *radius)
+        }
+    }
+
+    pub fn aabb(&self, iso: &Isometry2) -> Aabb2 {
+        let (points, radius) = self.core();
+        let world: Vec<Vec2> = points.iter().map(|&p| iso.transform_point(p)).collect();
+        Aabb2::from_points(&world).expand(radius)
+    }
+
+    pub fn mass_properties(&self, density: f32) -> MassProperties2 {
+        use std::f32::consts::PI;
+        match self {
+            Shape2D::Circle { radius } => {
+                let mass = density * PI * radius * radius;
+                MassProperties2 { mass, inertia: 0.5 * mass * radius * radius, centroid: Vec2::ZERO }
+            },
+            Shape2D::Rect { half_extents: e } => {
+                let mass = density * 4.0 * e.x * e.y;
+                MassProperties2 { mass, inertia: mass * (e.x * e.x + e.y * e.y) / 3.0, centroid: Vec2::ZERO }
+            },
+            Shape2D::Polygon { vertices } => polygon_mass(vertices, density),
+            Shape2D::Capsule { half_height: h, radius: r } => {
+                let rect_mass = density * 2.0 * r * 2.0 * h;
+                let circle_mass = density * PI * r * r;
+                //the two half circles sit at the segment ends, lc is their centroid offset
+                let lc = 4.0 * r / (3.0 * PI);
+                let circle_inertia = circle_mass * (0.5 * r * r + h * h + 2.0 * h * lc);
+                let rect_inertia = rect_mass * (4.0 * r * r + 4.0 * h * h) / 12.0;
+                MassProperties2 { mass: rect_mass + circle_mass, inertia: rect_inertia + circle_inertia, centroid: Vec2::ZERO }
+            }
+        }
+    }
+}
+
+/**
+ * Andrew's monotone chain, counter clockwise with collinear points removed
+ **/
+fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted: Vec<Vec2> = points.to_vec();
+    sorted.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap_or(std::cmp::Ordering::Equal)
+        .then(a.y.partial_cmp(&b.y).unwrap_or(std::cmp::Ordering::Equal)));
+    sorted.dedup_by(|a, b| a.approx_eq(*b, 1e-6));
+    if sorted.len() < 3 {
+        return sorted;
+    }
+
+    fn chain<'a, I: Iterator<Item = &'a Vec2>>(points: I) -> Vec<Vec2> {
+        let mut chain: Vec<Vec2> = Vec::new();
+        for &p in points {
+            while chain.len() >= 2 && (chain[chain.len() - 1] - chain[chain.len() - 2]).cross(p - chain[chain.len() - 2]) <= 1e-7 {
+                chain.pop();
+            }
+            chain.push(p);
+        }
+        //the last point of each chain is the first of the other
+        chain.pop();
+        chain
+    }
+
+    let mut hull = chain(sorted.iter());
+    hull.extend(chain(sorted.iter().rev()));
+    hull
+}
+
+fn polygon_mass(vertices: &[Vec2], density: f32) -> MassProperties2 {
+    //triangle fan around the first vertex, moved to the origin for precision
+    let origin = vertices[0];
+    let mut area = 0.0;
+    let mut center = Vec2::ZERO;
+    let mut inertia = 0.0;
+    for i in 1..vertices.len() - 1 {
+        let e1 = vertices[i] - origin;
+        let e2 = vertices[i + 1] - origin;
+        let d = e1.cross(e2);
+        let tri_area = 0.5 * d;
+        area += tri_area;
+        center += (e1 + e2) * (tri_area / 3.0);
+        let intx2 = e1.x * e1.x + e2.x * e1.x + e2.x * e2.x;
+        let inty2 = e1.y * e1.y + e2.y * e1.y + e2.y * e2.y;
+        inertia += (0.25 / 3.0 * d) * (intx2 + inty2);
+    }
+    let mass = density * area;
+    let center = if area > f32::EPSILON { center / area } else { Vec2::ZERO };
+    //inertia is about the first vertex, shift it to the centroid
+    let inertia = density * inertia - mass * center.dot(center);
+    MassProperties2 { mass, inertia, centroid: origin + center }
+}
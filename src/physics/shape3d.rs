@@ -0,0 +1,536 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::math::{ Aabb, Mat3, Quat, Ray, Vec3 };
+use crate::physics::broadphase::Bounds;
+use crate::physics::PhysicsError;
+
+/**
+ * Position and rotation of a body or collider in 3D
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Isometry3 {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Isometry3 {
+    pub const IDENTITY: Isometry3 = Isometry3 { position: Vec3::ZERO, rotation: Quat::IDENTITY };
+
+    pub fn new(position: Vec3, rotation: Quat) -> Isometry3 {
+        Isometry3 { position, rotation }
+    }
+
+    #[inline]
+    pub fn transform_point(&self, p: Vec3) -> Vec3 {
+        self.position + self.rotation.rotate(p)
+    }
+
+    #[inline]
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        self.rotation.rotate(v)
+    }
+
+    #[inline]
+    pub fn inverse_transform_point(&self, p: Vec3) -> Vec3 {
+        self.rotation.conjugate().rotate(p - self.position)
+    }
+
+    #[inline]
+    pub fn inverse_transform_vector(&self, v: Vec3) -> Vec3 {
+        self.rotation.conjugate().rotate(v)
+    }
+
+    /**
+     * `local` expressed relative to this isometry, e.g. a collider offset on a body
+     **/
+    pub fn mul(&self, local: &Isometry3) -> Isometry3 {
+        Isometry3 { position: self.transform_point(local.position), rotation: (self.rotation * local.rotation).normalize() }
+    }
+}
+
+impl Default for Isometry3 {
+    fn default() -> Isometry3 {
+        Isometry3::IDENTITY
+    }
+}
+
+impl Bounds for Aabb {
+    fn min_x(&self) -> f32 {
+        self.min.x
+    }
+
+    fn max_x(&self) -> f32 {
+        self.max.x
+    }
+
+    fn overlaps(&self, other: &Aabb) -> bool {
+        self.intersects_aabb(other)
+    }
+}
+
+/**
+ * Mass, inertia tensor about the centroid and centroid, all in the shape's local space
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassProperties3 {
+    pub mass: f32,
+    pub inertia: Mat3,
+    pub centroid: Vec3,
+}
+
+/**
+ * Polygon face of a convex polyhedron, vertices counter clockwise seen from outside
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct Face {
+    pub vertices: Vec<u32>,
+    pub normal: Vec3,
+}
+
+/**
+ * Convex polyhedron with coplanar triangles merged into polygon faces
+ * Whole faces are what the narrowphase clips against, so a box resting on a hull gets
+ * four contact points instead of whichever triangle happened to be closest
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ConvexPolyhedron {
+    vertices: Vec<Vec3>,
+    faces: Vec<Face>,
+    edges: Vec<(u32, u32)>,
+}
+
+impl ConvexPolyhedron {
+    /**
+     * Convex hull of `points`, fails if they don't enclose any volume
+     **/
+    pub fn from_points(points: &[Vec3]) -> Result<ConvexPolyhedron, PhysicsError> {
+        let triangles = hull_triangles(points)?;
+        Ok(ConvexPolyhedron::from_triangles(points, &triangles))
+    }
+
+    pub fn cuboid(half_extents: Vec3) -> ConvexPolyhedron {
+        let e = half_extents;
+        let vertices = vec![
+            Vec3::new(-e.x, -e.y, -e.z), Vec3::new(e.x, -e.y, -e.z), Vec3::new(e.x, e.y, -e.z), Vec3::new(-e.x, e.y, -e.z),
+            Vec3::new(-e.x, -e.y, e.z), Vec3::new(e.x, -e.y, e.z), Vec3::new(e.x, e.y, e.z), Vec3::new(-e.x, e.y, e.z),
+        ];
+        let face = |vertices: [u32; 4], normal: Vec3| Face { vertices: vertices.to_vec(), normal };
+        let faces = vec![
+            face([0, 3, 2, 1], -Vec3::Z), face([4, 5, 6, 7], Vec3::Z),
+            face([0, 4, 7, 3], -Vec3::X), face([1, 2, 6, 5], Vec3::X),
+            face([0, 1, 5, 4], -Vec3::Y), face([3, 7, 6, 2], Vec3::Y),
+        ];
+        let edges = vec![(0, 1), (1, 2), (2, 3), (0, 3), (4, 5), (5, 6), (6, 7), (4, 7), (0, 4), (1, 5), (2, 6), (3, 7)];
+        ConvexPolyhedron { vertices, faces, edges }
+    }
+
+    /**
+     * Single triangle with a face on each side, how mesh triangles take part in collisions
+     **/
+    pub fn triangle(a: Vec3, b: Vec3, c: Vec3) -> ConvexPolyhedron {
+        let normal = (b - a).cross(c - a).normalize();
+        ConvexPolyhedron {
+            vertices: vec![a, b, c],
+            faces: vec![Face { vertices: vec![0, 1, 2], normal }, Face { vertices: vec![0, 2, 1], normal: -normal }],
+            edges: vec![(0, 1), (1, 2), (0, 2)],
+        }
+    }
+
+    fn from_triangles(points: &[Vec3], triangles: &[[usize; 3]]) -> ConvexPolyhedron {
+        let normal = |t: &[usize; 3]| (points[t[1]] - points[t[0]]).cross(points[t[2]] - points[t[0]]).normalize();
+
+        //group triangles lying in the same plane
+        let mut groups: Vec<(Vec3, f32, Vec<usize>)> = Vec::new();
+        for t in triangles {
+            let n = normal(t);
+            let d = n.dot(points[t[0]]);
+            match groups.iter_mut().find(|g| g.0.dot(n) > 1.0 - 1e-4 && (g.1 - d).abs() < 1e-4 * (1.0 + d.abs())) {
+                Some(g) => g.2.extend_from_slice(t),
+                None => groups.push((n, d, t.to_vec()))
+            }
+        }
+
+        let mut remap: Vec<Option<u32>> = vec![None; points.len()];
+        let mut vertices = Vec::new();
+        let mut faces = Vec::with_capacity(groups.len());
+        for (n, _, mut indices) in groups {
+            indices.sort_unstable();
+            indices.dedup();
+            //counter clockwise around the normal
+            let center = indices.iter().fold(Vec3::ZERO, |c, &i| c + points[i]) / indices.len() as f32;
+            let u = n.any_orthogonal();
+            let v = n.cross(u);
+            indices.sort_by(|&a, &b| {
+                let angle = |i: usize| (points[i] - center).dot(v).atan2((points[i] - center).dot(u));
+                angle(a).total_cmp(&angle(b))
+            });
+            let face = indices.iter().map(|&i| *remap[i].get_or_insert_with(|| {
+                vertices.push(points[i]);
+                vertices.len() as u32 - 1
+            })).collect();
+            faces.push(Face { vertices: face, normal: n });
+        }
+
+        let mut edges: Vec<(u32, u32)> = faces.iter().flat_map(|f| {
+            (0..f.vertices.len()).map(move |i| {
+                let (a, b) = (f.vertices[i], f.vertices[(i + 1) % f.vertices.len()]);
+                (a.min(b), a.max(b))
+            })
+        }).collect();
+        edges.sort_unstable();
+        edges.dedup();
+        ConvexPolyhedron { vertices, faces, edges }
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    #[inline]
+    pub fn edges(&self) -> &[(u32, u32)] {
+        &self.edges
+    }
+
+    /**
+     * Same polyhedron moved by `iso`
+     **/
+    pub fn transformed(&self, iso: &Isometry3) -> ConvexPolyhedron {
+        ConvexPolyhedron {
+            vertices: self.vertices.iter().map(|&p| iso.transform_point(p)).collect(),
+            faces: self.faces.iter().map(|f| Face { vertices: f.vertices.clone(), normal: iso.transform_vector(f.normal) }).collect(),
+            edges: self.edges.clone(),
+        }
+    }
+
+    fn mass_properties(&self, density: f32) -> MassProperties3 {
+        //tetrahedra from the origin to every face triangle, using the canonical covariance
+        let canonical = Mat3::from_cols(Vec3::new(2.0, 1.0, 1.0), Vec3::new(1.0, 2.0, 1.0), Vec3::new(1.0, 1.0, 2.0)) * (1.0 / 120.0);
+        let mut volume = 0.0;
+        let mut center = Vec3::ZERO;
+        let mut covariance = Mat3::ZERO;
+        for face in &self.faces {
+            let a = self.vertices[face.vertices[0] as usize];
+            for i in 1..face.vertices.len() - 1 {
+                let b = self.vertices[face.vertices[i] as usize];
+                let c = self.vertices[face.vertices[i + 1] as usize];
+                let m = Mat3::from_cols(a, b, c);
+                let det = m.determinant();
+                volume += det / 6.0;
+                center += (a + b + c) * (det / 24.0);
+                covariance = covariance + m * canonical * m.transpose() * det;
+            }
+        }
+        let center = if volume > f32::EPSILON { center / volume } else { Vec3::ZERO };
+        //move the covariance to the centroid, then inertia = trace(C) I - C
+        let covariance = covariance - Mat3::from_outer_product(center, center) * volume;
+        let trace = covariance.cols[0].x + covariance.cols[1].y + covariance.cols[2].z;
+        let inertia = (Mat3::IDENTITY * trace - covariance) * density;
+        MassProperties3 { mass: volume * density, inertia, centroid: center }
+    }
+}
+
+/**
+ * Incremental hull, faces as outward facing triangles of indices into `points`
+ **/
+fn hull_triangles(points: &[Vec3]) -> Result<Vec<[usize; 3]>, PhysicsError> {
+    let degenerate = || PhysicsError::InvalidShape("convex hull needs at least 4 points that aren't coplanar".to_string());
+    if points.len() < 4 {
+        return Err(degenerate());
+    }
+    let extent = Aabb::from_points(points).map_or(0.0, |b| b.size().max_element());
+    let eps = 1e-5 * extent.max(f32::EPSILON);
+
+    //starting tetrahedron from extreme points
+    let i0 = (0..points.len()).min_by(|&a, &b| points[a].x.total_cmp(&points[b].x)).unwrap_or(0);
+    let farthest = |f: &dyn Fn(Vec3) -> f32| (0..points.len()).max_by(|&a, &b| f(points[a]).total_cmp(&f(points[b]))).unwrap_or(0);
+    let i1 = farthest(&|p| p.distance(points[i0]));
+    let line = (points[i1] - points[i0]).normalize();
+    let i2 = farthest(&|p| (p - points[i0] - line * line.dot(p - points[i0])).length());
+    let plane = (points[i1] - points[i0]).cross(points[i2] - points[i0]).normalize();
+    let i3 = farthest(&|p| plane.dot(p - points[i0]).abs());
+    if plane.dot(points[i3] - points[i0]).abs() <= eps || plane.length_squared() < 0.5 {
+        return Err(degenerate());
+    }
+
+    let interior = (points[i0] + points[i1] + points[i2] + points[i3]) * 0.25;
+    let outward = |t: [usize; 3]| {
+        let n = (points[t[1]] - points[t[0]]).cross(points[t[2]] - points[t[0]]);
+        if n.dot(points[t[0]] - interior) < 0.0 { [t[0], t[2], t[1]] } else { t }
+    };
+    let mut faces: Vec<[usize; 3]> = vec![
+        outward([i0, i1, i2]), outward([i0, i1, i3]), outward([i0, i2, i3]), outward([i1, i2, i3])
+    ];
+
+    for (p, point) in points.iter().enumerate() {
+        if [i0, i1, i2, i3].contains(&p) {
+            continue;
+        }
+        let visible: Vec<bool> = faces.iter().map(|t| {
+            let n = (points[t[1]] - points[t[0]]).cross(points[t[2]] - points[t[0]]).normalize();
+            n.dot(*point - points[t[0]]) > eps
+        }).collect();
+        if !visible.contains(&true) {
+            continue;
+        }
+
+        //edges of visible faces that aren't shared with another visible face form the horizon
+        let mut horizon = Vec::new();
+        for (t, _) in faces.iter().zip(&visible).filter(|x| *x.1) {
+            for k in 0..3 {
+                let (a, b) = (t[k], t[(k + 1) % 3]);
+                let shared = faces.iter().zip(&visible).any(|(u, v)| *v && (0..3).any(|j| u[j] == b && u[(j + 1) % 3] == a));
+                if !shared {
+                    horizon.push((a, b));
+                }
+            }
+        }
+        let mut kept: Vec<[usize; 3]> = faces.iter().zip(&visible).filter(|x| !*x.1).map(|x| *x.0).collect();
+        kept.extend(horizon.into_iter().map(|(a, b)| [a, b, p]));
+        faces = kept;
+    }
+    Ok(faces)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+struct BvhNode {
+    aabb: Aabb,
+    //leaves hold `count` triangles starting at `first`, inner nodes have children at first and first + 1
+    first: u32,
+    count: u32,
+}
+
+/**
+ * Triangle mesh for static level geometry, with a bounding volume tree over the triangles
+ * Meshes have no volume, so bodies using them get no mass from them
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct TriMesh {
+    vertices: Vec<Vec3>,
+    indices: Vec<[u32; 3]>,
+    nodes: Vec<BvhNode>,
+}
+
+impl TriMesh {
+    pub fn new(vertices: Vec<Vec3>, indices: Vec<[u32; 3]>) -> Result<TriMesh, PhysicsError> {
+        if indices.is_empty() {
+            return Err(PhysicsError::InvalidShape("mesh has no triangles".to_string()));
+        }
+        if indices.iter().flatten().any(|&i| i as usize >= vertices.len()) {
+            return Err(PhysicsError::InvalidShape("mesh index out of range".to_string()));
+        }
+        let mut mesh = TriMesh { vertices, indices, nodes: Vec::new() };
+        let mut order: Vec<usize> = (0..mesh.indices.len()).collect();
+        mesh.nodes.push(BvhNode { aabb: mesh.triangle_aabb(0), first: 0, count: 0 });
+        mesh.build(0, &mut order, 0);
+        //leaves refer to ranges of the reordered triangles
+        mesh.indices = order.iter().map(|&i| mesh.indices[i]).collect();
+        Ok(mesh)
+    }
+
+    fn build(&mut self, node: usize, order: &mut [usize], offset: usize) {
+        let aabb = order.iter().map(|&t| self.triangle_aabb(t)).reduce(|a, b| a.merge(&b)).unwrap_or(self.nodes[node].aabb);
+        self.nodes[node].aabb = aabb;
+        if order.len() <= 4 {
+            self.nodes[node].first = offset as u32;
+            self.nodes[node].count = order.len() as u32;
+            return;
+        }
+
+        let centroid = |t: usize| {
+            let [a, b, c] = self.triangle(t);
+            (a + b + c) / 3.0
+        };
+        let size = aabb.size();
+        let axis = if size.x >= size.y && size.x >= size.z { 0 } else if size.y >= size.z { 1 } else { 2 };
+        order.sort_by(|&a, &b| centroid(a)[axis].total_cmp(&centroid(b)[axis]).then(a.cmp(&b)));
+
+        let left = self.nodes.len();
+        self.nodes.push(BvhNode { aabb, first: 0, count: 0 });
+        self.nodes.push(BvhNode { aabb, first: 0, count: 0 });
+        self.nodes[node].first = left as u32;
+        let mid = order.len() / 2;
+        let (a, b) = order.split_at_mut(mid);
+        self.build(left, a, offset);
+        self.build(left + 1, b, offset + mid);
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[Vec3] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn indices(&self) -> &[[u32; 3]] {
+        &self.indices
+    }
+
+    pub fn triangle(&self, index: usize) -> [Vec3; 3] {
+        let [a, b, c] = self.indices[index];
+        [self.vertices[a as usize], self.vertices[b as usize], self.vertices[c as usize]]
+    }
+
+    fn triangle_aabb(&self, index: usize) -> Aabb {
+        let [a, b, c] = self.triangle(index);
+        Aabb { min: a.min(b).min(c), max: a.max(b).max(c) }
+    }
+
+    pub fn aabb(&self) -> Aabb {
+        self.nodes[0].aabb
+    }
+
+    /**
+     * Triangles whose bounds overlap `aabb`, in mesh space, sorted by index
+     **/
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<usize> {
+        let mut hits = Vec::new();
+        let mut stack = vec![0usize];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            if !node.aabb.intersects_aabb(aabb) {
+                continue;
+            }
+            if node.count > 0 {
+                let range = node.first as usize..(node.first + node.count) as usize;
+                hits.extend(range.filter(|&t| self.triangle_aabb(t).intersects_aabb(aabb)));
+            } else {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+            }
+        }
+        hits.sort_unstable();
+        hits
+    }
+
+    /**
+     * Closest triangle hit by a ray in mesh space, distance and triangle index
+     **/
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(f32, usize)> {
+        let mut best: Option<(f32, usize)> = None;
+        let mut stack = vec![0usize];
+        while let Some(i) = stack.pop() {
+            let node = &self.nodes[i];
+            let limit = best.map_or(max_distance, |x| x.0);
+            match ray.intersect_aabb(&node.aabb) {
+                Some(t) if t <= limit => {},
+                _ => continue
+            }
+            if node.count > 0 {
+                for t in node.first as usize..(node.first + node.count) as usize {
+                    let [a, b, c] = self.triangle(t);
+                    if let Some(d) = ray.intersect_triangle(a, b, c) {
+                        if d <= best.map_or(max_distance, |x| x.0) && best.is_none_or(|x| d < x.0 || (d == x.0 && t < x.1)) {
+                            best = Some((d, t));
+                        }
+                    }
+                }
+            } else {
+                stack.push(node.first as usize);
+                stack.push(node.first as usize + 1);
+            }
+        }
+        best
+    }
+}
+
+/**
+ * Collision shape in the collider's local space
+ * Spheres and capsules are a point or a segment inflated by a radius, boxes and hulls are
+ * polyhedra, meshes are only meant for static bodies
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum Shape3D {
+    Sphere { radius: f32 },
+    Box { half_extents: Vec3 },
+    /**
+     * Segment from (0, -half_height, 0) to (0, half_height, 0) inflated by radius
+     **/
+    Capsule { half_height: f32, radius: f32 },
+    ConvexHull(ConvexPolyhedron),
+    Mesh(TriMesh),
+}
+
+impl Shape3D {
+    pub fn sphere(radius: f32) -> Shape3D {
+        Shape3D::Sphere { radius: radius.abs() }
+    }
+
+    pub fn cuboid(half_x: f32, half_y: f32, half_z: f32) -> Shape3D {
+        Shape3D::Box { half_extents: Vec3::new(half_x, half_y, half_z).abs() }
+    }
+
+    pub fn capsule(half_height: f32, radius: f32) -> Shape3D {
+        Shape3D::Capsule { half_height: half_height.abs(), radius: radius.abs() }
+    }
+
+    pub fn convex_hull(points: &[Vec3]) -> Result<Shape3D, PhysicsError> {
+        Ok(Shape3D::ConvexHull(ConvexPolyhedron::from_points(points)?))
+    }
+
+    pub fn mesh(vertices: Vec<Vec3>, indices: Vec<[u32; 3]>) -> Result<Shape3D, PhysicsError> {
+        Ok(Shape3D::Mesh(TriMesh::new(vertices, indices)?))
+    }
+
+    pub fn aabb(&self, iso: &Isometry3) -> Aabb {
+        match self {
+            Shape3D::Sphere { radius } => Aabb::from_center_extents(iso.position, Vec3::splat(*radius)),
+            Shape3D::Box { half_extents } => {
+                let m = Mat3::from_quat(iso.rotation);
+                let extent = Vec3::new(
+                    m.row(0).abs().dot(*half_extents), m.row(1).abs().dot(*half_extents), m.row(2).abs().dot(*half_extents)
+                );
+                Aabb::from_center_extents(iso.position, extent)
+            },
+            Shape3D::Capsule { half_height, radius } => {
+                let a = iso.transform_point(Vec3::new(0.0, -*half_height, 0.0));
+                let b = iso.transform_point(Vec3::new(0.0, *half_height, 0.0));
+                Aabb::new(a, b).expand(*radius)
+            },
+            Shape3D::ConvexHull(hull) => {
+                let points: Vec<Vec3> = hull.vertices.iter().map(|&p| iso.transform_point(p)).collect();
+                Aabb::from_points(&points).unwrap_or(Aabb::new(iso.position, iso.position))
+            },
+            Shape3D::Mesh(mesh) => {
+                let corners = mesh.aabb().corners();
+                let points: Vec<Vec3> = corners.iter().map(|&p| iso.transform_point(p)).collect();
+                Aabb::from_points(&points).unwrap_or(Aabb::new(iso.position, iso.position))
+            }
+        }
+    }
+
+    pub fn mass_properties(&self, density: f32) -> MassProperties3 {
+        use std::f32::consts::PI;
+        match self {
+            Shape3D::Sphere { radius: r } => {
+                let mass = density * 4.0 / 3.0 * PI * r * r * r;
+                MassProperties3 { mass, inertia: Mat3::IDENTITY * (0.4 * mass * r * r), centroid: Vec3::ZERO }
+            },
+            Shape3D::Box { half_extents: e } => {
+                let mass = density * 8.0 * e.x * e.y * e.z;
+                let (x2, y2, z2) = (e.x * e.x, e.y * e.y, e.z * e.z);
+                let inertia = Mat3::from_scale(Vec3::new(y2 + z2, x2 + z2, x2 + y2) * (mass / 3.0));
+                MassProperties3 { mass, inertia, centroid: Vec3::ZERO }
+            },
+            Shape3D::Capsule { half_height: h, radius: r } => {
+                let cylinder = density * PI * r * r * 2.0 * h;
+                let spheres = density * 4.0 / 3.0 * PI * r * r * r;
+                let axial = cylinder * r * r * 0.5 + spheres * 0.4 * r * r;
+                let side = cylinder * (h * h / 3.0 + r * r / 4.0) + spheres * (0.4 * r * r + h * h + 0.75 * h * r);
+                MassProperties3 { mass: cylinder + spheres, inertia: Mat3::from_scale(Vec3::new(side, axial, side)), centroid: Vec3::ZERO }
+            },
+            Shape3D::ConvexHull(hull) => hull.mass_properties(density),
+            Shape3D::Mesh(_) => MassProperties3 { mass: 0.0, inertia: Mat3::ZERO, centroid: Vec3::ZERO }
+        }
+    }
+}
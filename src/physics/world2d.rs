@@ -0,0 +1,1077 @@
+use std::collections::BTreeMap;
+use std::sync::{ Arc, RwLock };
+
+use crate::core::signals::{ SyncData, SyncSignal, SyncSlot, SyncSlotPair };
+use crate::events::event::{ EventData, EventType };
+use crate::events::physics_events::{ ContactBeginEvent, ContactEndEvent };
+use crate::math::Vec2;
+use crate::physics::arena::Arena;
+use crate::physics::broadphase::SweepAndPrune;
+use crate::physics::collision2d::{ self, Hull };
+use crate::physics::shape2d::{ Aabb2, Isometry2, Shape2D };
+use crate::physics::{ emit_contact, BodyHandle, BodyType, ColliderHandle, CollisionFilter, ContactEvent, PhysicsError };
+use crate::physics::{ BAUMGARTE, LINEAR_SLOP, MAX_CORRECTION_SPEED, RESTITUTION_THRESHOLD, SPECULATIVE_DISTANCE };
+
+/**
+ * A body the world simulates, colliders attached to it give it a shape and mass
+ * The position is the body origin, the solver works on the center of mass
+ **/
+#[derive(Debug, Clone)]
+pub struct RigidBody2D {
+    body_type: BodyType,
+    iso: Isometry2,
+    local_center: Vec2,
+    center: Vec2,
+    linear_velocity: Vec2,
+    angular_velocity: f32,
+    force: Vec2,
+    torque: f32,
+    mass: f32,
+    inv_mass: f32,
+    inertia: f32,
+    inv_inertia: f32,
+    pub gravity_scale: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /**
+     * Stops contacts and torques from rotating the body, e.g. for characters
+     **/
+    pub fixed_rotation: bool,
+    colliders: Vec<ColliderHandle>,
+    pub user_data: u64,
+}
+
+impl RigidBody2D {
+    pub fn new(body_type: BodyType, position: Vec2) -> RigidBody2D {
+        RigidBody2D {
+            body_type,
+            iso: Isometry2::new(position, 0.0),
+            local_center: Vec2::ZERO,
+            center: position,
+            linear_velocity: Vec2::ZERO,
+            angular_velocity: 0.0,
+            force: Vec2::ZERO,
+            torque: 0.0,
+            mass: 0.0,
+            inv_mass: 0.0,
+            inertia: 0.0,
+            inv_inertia: 0.0,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            fixed_rotation: false,
+            colliders: Vec::new(),
+            user_data: 0,
+        }
+    }
+
+    #[inline]
+    pub fn body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    /**
+     * Changing the type keeps the velocity for kinematic bodies, static bodies are stopped
+     **/
+    pub fn set_body_type(&mut self, body_type: BodyType) {
+        self.body_type = body_type;
+        if body_type == BodyType::Static {
+            self.linear_velocity = Vec2::ZERO;
+            self.angular_velocity = 0.0;
+        }
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec2 {
+        self.iso.position
+    }
+
+    #[inline]
+    pub fn angle(&self) -> f32 {
+        self.iso.angle
+    }
+
+    #[inline]
+    pub fn transform(&self) -> Isometry2 {
+        self.iso
+    }
+
+    /**
+     * Teleports the body, contacts are rebuilt on the next step
+     **/
+    pub fn set_transform(&mut self, position: Vec2, angle: f32) {
+        self.iso = Isometry2::new(position, angle);
+        self.center = self.iso.transform_point(self.local_center);
+    }
+
+    /**
+     * Center of mass in world space
+     **/
+    #[inline]
+    pub fn center_of_mass(&self) -> Vec2 {
+        self.center
+    }
+
+    #[inline]
+    pub fn linear_velocity(&self) -> Vec2 {
+        self.linear_velocity
+    }
+
+    pub fn set_linear_velocity(&mut self, velocity: Vec2) {
+        if self.body_type != BodyType::Static {
+            self.linear_velocity = velocity;
+        }
+    }
+
+    #[inline]
+    pub fn angular_velocity(&self) -> f32 {
+        self.angular_velocity
+    }
+
+    pub fn set_angular_velocity(&mut self, velocity: f32) {
+        if self.body_type != BodyType::Static {
+            self.angular_velocity = velocity;
+        }
+    }
+
+    /**
+     * Velocity of a world space point moving with the body
+     **/
+    pub fn velocity_at_point(&self, point: Vec2) -> Vec2 {
+        self.linear_velocity + (point - self.center).perp() * self.angular_velocity
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    #[inline]
+    pub fn inertia(&self) -> f32 {
+        self.inertia
+    }
+
+    pub fn colliders(&self) -> &[ColliderHandle] {
+        &self.colliders
+    }
+
+    /**
+     * Force applied at the center of mass until the end of the next step
+     **/
+    pub fn apply_force(&mut self, force: Vec2) {
+        if self.body_type == BodyType::Dynamic {
+            self.force += force;
+        }
+    }
+
+    pub fn apply_force_at_point(&mut self, force: Vec2, point: Vec2) {
+        if self.body_type == BodyType::Dynamic {
+            self.force += force;
+            self.torque += (point - self.center).cross(force);
+        }
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        if self.body_type == BodyType::Dynamic {
+            self.torque += torque;
+        }
+    }
+
+    /**
+     * Changes the velocity immediately
+     **/
+    pub fn apply_impulse(&mut self, impulse: Vec2) {
+        if self.body_type == BodyType::Dynamic {
+            self.linear_velocity += impulse * self.inv_mass;
+        }
+    }
+
+    pub fn apply_impulse_at_point(&mut self, impulse: Vec2, point: Vec2) {
+        if self.body_type == BodyType::Dynamic {
+            self.linear_velocity += impulse * self.inv_mass;
+            self.angular_velocity += self.inv_inertia * (point - self.center).cross(impulse);
+        }
+    }
+}
+
+/**
+ * Shape attached to a body, sensors report contacts without pushing anything
+ **/
+#[derive(Debug, Clone)]
+pub struct Collider2D {
+    pub shape: Shape2D,
+    /**
+     * Placement relative to the body origin
+     **/
+    pub offset: Isometry2,
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    pub sensor: bool,
+    pub filter: CollisionFilter,
+    pub user_data: u64,
+    body: Option<BodyHandle>,
+}
+
+impl Collider2D {
+    pub fn new(shape: Shape2D) -> Collider2D {
+        Collider2D {
+            shape,
+            offset: Isometry2::IDENTITY,
+            density: 1.0,
+            friction: 0.6,
+            restitution: 0.0,
+            sensor: false,
+            filter: CollisionFilter::ALL,
+            user_data: 0,
+            body: None,
+        }
+    }
+
+    /**
+     * Body this collider is attached to, None until it's added to a world
+     **/
+    #[inline]
+    pub fn body(&self) -> Option<BodyHandle> {
+        self.body
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit2D {
+    pub collider: ColliderHandle,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub distance: f32,
+}
+
+/**
+ * First hit of a moving shape, `fraction` is how much of the translation happened before it
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeHit2D {
+    pub collider: ColliderHandle,
+    pub fraction: f32,
+    pub point: Vec2,
+    pub normal: Vec2,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ContactPoint {
+    id: u32,
+    //anchors relative to each body's center of mass
+    ra: Vec2,
+    rb: Vec2,
+    separation: f32,
+    normal_impulse: f32,
+    tangent_impulse: f32,
+    normal_mass: f32,
+    tangent_mass: f32,
+    //normal velocity before solving, what restitution bounces off
+    approach: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Contact {
+    body_a: BodyHandle,
+    body_b: BodyHandle,
+    sensor: bool,
+    touching: bool,
+    friction: f32,
+    restitution: f32,
+    normal: Vec2,
+    points: Vec<ContactPoint>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SolverBody {
+    linear_velocity: Vec2,
+    angular_velocity: f32,
+    inv_mass: f32,
+    inv_inertia: f32,
+}
+
+/**
+ * 2D rigid body simulation, stepped from the engine's fixed update tick
+ **/
+pub struct PhysicsWorld2D {
+    gravity: Vec2,
+    bodies: Arena<RigidBody2D>,
+    colliders: Arena<Collider2D>,
+    broadphase: SweepAndPrune<Aabb2>,
+    contacts: BTreeMap<(ColliderHandle, ColliderHandle), Contact>,
+    events: Vec<ContactEvent>,
+    //ends caused by removing colliders between steps, reported with the next step
+    pending_events: Vec<ContactEvent>,
+    slots: Vec<SyncSlotPair>,
+    pub velocity_iterations: u32,
+}
+
+impl PhysicsWorld2D {
+    pub fn new(gravity: Vec2) -> PhysicsWorld2D {
+        PhysicsWorld2D {
+            gravity,
+            bodies: Arena::new(),
+            colliders: Arena::new(),
+            broadphase: SweepAndPrune::new(),
+            contacts: BTreeMap::new(),
+            events: Vec::new(),
+            pending_events: Vec::new(),
+            slots: vec![],
+            velocity_iterations: 8,
+        }
+    }
+
+    #[inline]
+    pub fn gravity(&self) -> Vec2 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vec2) {
+        self.gravity = gravity;
+    }
+
+    pub fn add_body(&mut self, body: RigidBody2D) -> BodyHandle {
+        let (index, generation) = self.bodies.insert(body);
+        BodyHandle::new(index, generation)
+    }
+
+    /**
+     * Removes the body along with all of its colliders
+     **/
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<RigidBody2D> {
+        let colliders = self.body(handle)?.colliders.clone();
+        for collider in colliders {
+            self.remove_collider(collider);
+        }
+        self.bodies.remove(handle.index(), handle.generation())
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&RigidBody2D> {
+        self.bodies.get(handle.index(), handle.generation())
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut RigidBody2D> {
+        self.bodies.get_mut(handle.index(), handle.generation())
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &RigidBody2D)> {
+        self.bodies.iter().map(|(i, g, x)| (BodyHandle::new(i, g), x))
+    }
+
+    pub fn add_collider(&mut self, body: BodyHandle, mut collider: Collider2D) -> Result<ColliderHandle, PhysicsError> {
+        if self.body(body).is_none() {
+            return Err(PhysicsError::InvalidBody);
+        }
+        collider.body = Some(body);
+        let (index, generation) = self.colliders.insert(collider);
+        let handle = ColliderHandle::new(index, generation);
+        if let Some(x) = self.body_mut(body) {
+            x.colliders.push(handle);
+        }
+        self.update_mass(body);
+        Ok(handle)
+    }
+
+    pub fn remove_collider(&mut self, handle: ColliderHandle) -> Option<Collider2D> {
+        let collider = self.colliders.remove(handle.index(), handle.generation())?;
+        let removed: Vec<(ColliderHandle, ColliderHandle)> = self.contacts.keys()
+            .filter(|k| k.0 == handle || k.1 == handle)
+            .cloned()
+            .collect();
+        for key in removed {
+            if let Some(contact) = self.contacts.remove(&key) {
+                if contact.touching {
+                    self.pending_events.push(ContactEvent::End { a: key.0, b: key.1, sensor: contact.sensor });
+                }
+            }
+        }
+        if let Some(body) = collider.body {
+            if let Some(x) = self.body_mut(body) {
+                x.colliders.retain(|c| *c != handle);
+            }
+            self.update_mass(body);
+        }
+        Some(collider)
+    }
+
+    pub fn collider(&self, handle: ColliderHandle) -> Option<&Collider2D> {
+        self.colliders.get(handle.index(), handle.generation())
+    }
+
+    /**
+     * Density changes are picked up by the body on the next step
+     **/
+    pub fn collider_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider2D> {
+        self.colliders.get_mut(handle.index(), handle.generation())
+    }
+
+    /**
+     * Contacts that started or ended during the last step
+     **/
+    pub fn contact_events(&self) -> &[ContactEvent] {
+        &self.events
+    }
+
+    /**
+     * Whether two colliders are currently touching or overlapping
+     **/
+    pub fn in_contact(&self, a: ColliderHandle, b: ColliderHandle) -> bool {
+        let key = if a < b { (a, b) } else { (b, a) };
+        self.contacts.get(&key).is_some_and(|x| x.touching)
+    }
+
+    fn update_mass(&mut self, handle: BodyHandle) {
+        let body = match self.bodies.get(handle.index(), handle.generation()) {
+            Some(x) => x,
+            None => return
+        };
+        let mut mass = 0.0;
+        let mut center = Vec2::ZERO;
+        let mut inertia = 0.0;
+        if body.body_type == BodyType::Dynamic {
+            for c in &body.colliders {
+                if let Some(collider) = self.colliders.get(c.index(), c.generation()) {
+                    let props = collider.shape.mass_properties(collider.density);
+                    let centroid = collider.offset.transform_point(props.centroid);
+                    mass += props.mass;
+                    center += centroid * props.mass;
+                    //about the body origin for now, shifted to the center below
+                    inertia += props.inertia + props.mass * centroid.dot(centroid);
+                }
+            }
+        }
+
+        let body = match self.bodies.get_mut(handle.index(), handle.generation()) {
+            Some(x) => x,
+            None => return
+        };
+        if body.body_type != BodyType::Dynamic {
+            body.mass = 0.0;
+            body.inv_mass = 0.0;
+            body.inertia = 0.0;
+            body.inv_inertia = 0.0;
+            body.local_center = Vec2::ZERO;
+        } else if mass > 0.0 {
+            center /= mass;
+            body.mass = mass;
+            body.inv_mass = 1.0 / mass;
+            body.inertia = inertia - mass * center.dot(center);
+            body.inv_inertia = if body.fixed_rotation || body.inertia <= 0.0 { 0.0 } else { 1.0 / body.inertia };
+            body.local_center = center;
+        } else {
+            //a dynamic body without any colliders still has to respond to forces
+            body.mass = 1.0;
+            body.inv_mass = 1.0;
+            body.inertia = 0.0;
+            body.inv_inertia = 0.0;
+            body.local_center = Vec2::ZERO;
+        }
+        body.center = body.iso.transform_point(body.local_center);
+    }
+
+    fn collider_transform(&self, collider: &Collider2D) -> Option<(&RigidBody2D, Isometry2)> {
+        let handle = collider.body?;
+        let body = self.body(handle)?;
+        Some((body, body.iso.mul(&collider.offset)))
+    }
+
+    /**
+     * Advances the simulation by `dt` seconds, meant to be called with a fixed step
+     **/
+    pub fn step(&mut self, dt: f32) {
+        self.events = std::mem::take(&mut self.pending_events);
+        if dt <= 0.0 {
+            return;
+        }
+
+        let handles: Vec<BodyHandle> = self.bodies().map(|x| x.0).collect();
+        for handle in &handles {
+            self.update_mass(*handle);
+        }
+
+        for (_, _, body) in self.bodies.iter_mut() {
+            if body.body_type != BodyType::Dynamic {
+                continue;
+            }
+            body.linear_velocity += (self.gravity * body.gravity_scale + body.force * body.inv_mass) * dt;
+            body.angular_velocity += body.inv_inertia * body.torque * dt;
+            body.linear_velocity *= 1.0 / (1.0 + dt * body.linear_damping);
+            body.angular_velocity *= 1.0 / (1.0 + dt * body.angular_damping);
+        }
+
+        let previous = self.update_contacts(dt);
+        self.solve(dt);
+        self.update_events(previous);
+
+        for (_, _, body) in self.bodies.iter_mut() {
+            if body.body_type != BodyType::Static {
+                body.center += body.linear_velocity * dt;
+                body.iso.angle += body.angular_velocity * dt;
+                body.iso.position = body.center - body.local_center.rotate(body.iso.angle);
+            }
+            body.force = Vec2::ZERO;
+            body.torque = 0.0;
+        }
+
+        for event in self.events.clone() {
+            let res = match event {
+                ContactEvent::Begin { a, b, sensor } => self.emit(SyncData::Sig(ContactBeginEvent::new(
+                    format!("Contact began between colliders {:?} and {:?} (sensor: {})", a, b, sensor),
+                    a.to_bits(), b.to_bits()))),
+                ContactEvent::End { a, b, sensor } => self.emit(SyncData::Sig(ContactEndEvent::new(
+                    format!("Contact ended between colliders {:?} and {:?} (sensor: {})", a, b, sensor),
+                    a.to_bits(), b.to_bits())))
+            };
+            if let Err(e) = res {
+                error!("Failed to emit contact event: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Rebuilds the contact list, returns whether each old contact was touching and a sensor
+     **/
+    fn update_contacts(&mut self, dt: f32) -> BTreeMap<(ColliderHandle, ColliderHandle), (bool, bool)> {
+        let mut bounds = Vec::with_capacity(self.colliders.len());
+        for (i, g, collider) in self.colliders.iter() {
+            if let Some((body, iso)) = self.collider_transform(collider) {
+                //swept by the body's motion so fast bodies still find their pairs
+                let aabb = collider.shape.aabb(&iso).expand(SPECULATIVE_DISTANCE);
+                let motion = body.linear_velocity * dt;
+                let moved = Aabb2 { min: aabb.min + motion, max: aabb.max + motion };
+                bounds.push((ColliderHandle::new(i, g), aabb.merge(&moved)));
+            }
+        }
+        self.broadphase.update(bounds);
+
+        let old = std::mem::take(&mut self.contacts);
+        for (a, b) in self.broadphase.pairs() {
+            let (ca, cb) = match (self.collider(a), self.collider(b)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue
+            };
+            if ca.body == cb.body || !ca.filter.interacts(&cb.filter) {
+                continue;
+            }
+            let ((ba, iso_a), (bb, iso_b)) = match (self.collider_transform(ca), self.collider_transform(cb)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue
+            };
+            let sensor = ca.sensor || cb.sensor;
+            let moving = |x: &RigidBody2D| x.body_type != BodyType::Static;
+            let dynamic = |x: &RigidBody2D| x.body_type == BodyType::Dynamic;
+            //sensors notice anything that moves, solid contacts need something to push
+            if (sensor && !moving(ba) && !moving(bb)) || (!sensor && !dynamic(ba) && !dynamic(bb)) {
+                continue;
+            }
+
+            let hull_a = Hull::new(&ca.shape, &iso_a);
+            let hull_b = Hull::new(&cb.shape, &iso_b);
+            let mut contact = Contact {
+                body_a: ca.body.unwrap(),
+                body_b: cb.body.unwrap(),
+                sensor,
+                touching: false,
+                friction: (ca.friction * cb.friction).sqrt(),
+                restitution: ca.restitution.max(cb.restitution),
+                normal: Vec2::ZERO,
+                points: Vec::new(),
+            };
+
+            if sensor {
+                contact.touching = match collision2d::core_distance(&hull_a, &hull_b) {
+                    Some((distance, _, _)) => distance < hull_a.radius + hull_b.radius,
+                    None => true
+                };
+                if !contact.touching {
+                    continue;
+                }
+            } else {
+                let margin = SPECULATIVE_DISTANCE + (bb.linear_velocity - ba.linear_velocity).length() * dt;
+                let manifold = match collision2d::collide(&hull_a, &hull_b, margin) {
+                    Some(x) => x,
+                    None => continue
+                };
+                let previous = old.get(&(a, b));
+                contact.normal = manifold.normal;
+                contact.touching = manifold.points.iter().any(|p| p.separation < LINEAR_SLOP);
+                contact.points = manifold.points.iter().map(|p| {
+                    //warm start from the last step's impulses on the same features
+                    let (normal_impulse, tangent_impulse) = previous
+                        .and_then(|c| c.points.iter().find(|x| x.id == p.id))
+                        .map_or((0.0, 0.0), |x| (x.normal_impulse, x.tangent_impulse));
+                    ContactPoint {
+                        id: p.id,
+                        ra: p.point - ba.center,
+                        rb: p.point - bb.center,
+                        separation: p.separation,
+                        normal_impulse,
+                        tangent_impulse,
+                        normal_mass: 0.0,
+                        tangent_mass: 0.0,
+                        approach: 0.0,
+                    }
+                }).collect();
+            }
+
+            self.contacts.insert((a, b), contact);
+        }
+
+        old.into_iter().map(|(k, c)| (k, (c.touching, c.sensor))).collect()
+    }
+
+    fn update_events(&mut self, previous: BTreeMap<(ColliderHandle, ColliderHandle), (bool, bool)>) {
+        for contact in self.contacts.values_mut() {
+            //a speculative contact that had to push closes its gap during this step
+            contact.touching |= contact.points.iter().any(|p| p.normal_impulse > 0.0);
+        }
+        for (&(a, b), contact) in &self.contacts {
+            let was_touching = previous.get(&(a, b)).is_some_and(|x| x.0);
+            if contact.touching && !was_touching {
+                self.events.push(ContactEvent::Begin { a, b, sensor: contact.sensor });
+            } else if !contact.touching && was_touching {
+                self.events.push(ContactEvent::End { a, b, sensor: contact.sensor });
+            }
+        }
+        for (&(a, b), &(touching, sensor)) in &previous {
+            if touching && !self.contacts.contains_key(&(a, b)) {
+                self.events.push(ContactEvent::End { a, b, sensor });
+            }
+        }
+    }
+
+    /**
+     * Sequential impulses with warm starting, friction is solved before the normal
+     * constraint so non penetration wins when they disagree
+     **/
+    fn solve(&mut self, dt: f32) {
+        let inv_dt = 1.0 / dt;
+        let mut solver: BTreeMap<BodyHandle, SolverBody> = BTreeMap::new();
+        for (i, g, body) in self.bodies.iter() {
+            solver.insert(BodyHandle::new(i, g), SolverBody {
+                linear_velocity: body.linear_velocity,
+                angular_velocity: body.angular_velocity,
+                inv_mass: body.inv_mass,
+                inv_inertia: body.inv_inertia,
+            });
+        }
+
+        let relative_velocity = |a: &SolverBody, b: &SolverBody, p: &ContactPoint| {
+            (b.linear_velocity + p.rb.perp() * b.angular_velocity) - (a.linear_velocity + p.ra.perp() * a.angular_velocity)
+        };
+        let apply = |solver: &mut BTreeMap<BodyHandle, SolverBody>, c: &Contact, p: &ContactPoint, impulse: Vec2| {
+            if let Some(a) = solver.get_mut(&c.body_a) {
+                a.linear_velocity -= impulse * a.inv_mass;
+                a.angular_velocity -= a.inv_inertia * p.ra.cross(impulse);
+            }
+            if let Some(b) = solver.get_mut(&c.body_b) {
+                b.linear_velocity += impulse * b.inv_mass;
+                b.angular_velocity += b.inv_inertia * p.rb.cross(impulse);
+            }
+        };
+        let pair = |solver: &BTreeMap<BodyHandle, SolverBody>, c: &Contact| {
+            match (solver.get(&c.body_a), solver.get(&c.body_b)) {
+                (Some(a), Some(b)) => Some((*a, *b)),
+                _ => None
+            }
+        };
+
+        //prepare and warm start
+        for contact in self.contacts.values_mut().filter(|c| !c.sensor) {
+            let (a, b) = match pair(&solver, contact) {
+                Some(x) => x,
+                None => continue
+            };
+            let normal = contact.normal;
+            let tangent = normal.perp();
+            for p in contact.points.iter_mut() {
+                let (ra, rb) = (p.ra, p.rb);
+                let effective_mass = |axis: Vec2| {
+                    let rna = ra.cross(axis);
+                    let rnb = rb.cross(axis);
+                    let k = a.inv_mass + b.inv_mass + a.inv_inertia * rna * rna + b.inv_inertia * rnb * rnb;
+                    if k > 0.0 { 1.0 / k } else { 0.0 }
+                };
+                p.normal_mass = effective_mass(normal);
+                p.tangent_mass = effective_mass(tangent);
+                p.approach = relative_velocity(&a, &b, p).dot(normal);
+            }
+            for p in &contact.points {
+                apply(&mut solver, contact, p, normal * p.normal_impulse + tangent * p.tangent_impulse);
+            }
+        }
+
+        for _ in 0..self.velocity_iterations {
+            for contact in self.contacts.values_mut().filter(|c| !c.sensor) {
+                let normal = contact.normal;
+                let tangent = normal.perp();
+                for i in 0..contact.points.len() {
+                    let (a, b) = match pair(&solver, contact) {
+                        Some(x) => x,
+                        None => break
+                    };
+                    let p = contact.points[i];
+                    let vt = relative_velocity(&a, &b, &p).dot(tangent);
+                    let max_friction = contact.friction * p.normal_impulse;
+                    let total = (p.tangent_impulse - p.tangent_mass * vt).clamp(-max_friction, max_friction);
+                    let lambda = total - p.tangent_impulse;
+                    contact.points[i].tangent_impulse = total;
+                    apply(&mut solver, contact, &p, tangent * lambda);
+                }
+
+                for i in 0..contact.points.len() {
+                    let (a, b) = match pair(&solver, contact) {
+                        Some(x) => x,
+                        None => break
+                    };
+                    let p = contact.points[i];
+                    let vn = relative_velocity(&a, &b, &p).dot(normal);
+                    let bias = if p.separation > 0.0 {
+                        //speculative, only allow the approach that closes the gap this step
+                        p.separation * inv_dt
+                    } else {
+                        (BAUMGARTE * inv_dt * (p.separation + LINEAR_SLOP)).clamp(-MAX_CORRECTION_SPEED, 0.0)
+                    };
+                    let total = (p.normal_impulse - p.normal_mass * (vn + bias)).max(0.0);
+                    let lambda = total - p.normal_impulse;
+                    contact.points[i].normal_impulse = total;
+                    apply(&mut solver, contact, &p, normal * lambda);
+                }
+            }
+        }
+
+        //restitution runs after the main solve so the bounce uses the approach speed
+        for contact in self.contacts.values_mut().filter(|c| !c.sensor && c.restitution > 0.0) {
+            let normal = contact.normal;
+            for i in 0..contact.points.len() {
+                let (a, b) = match pair(&solver, contact) {
+                    Some(x) => x,
+                    None => break
+                };
+                let p = contact.points[i];
+                if p.approach > -RESTITUTION_THRESHOLD || p.normal_impulse <= 0.0 {
+                    continue;
+                }
+                let vn = relative_velocity(&a, &b, &p).dot(normal);
+                let total = (p.normal_impulse - p.normal_mass * (vn + contact.restitution * p.approach)).max(0.0);
+                let lambda = total - p.normal_impulse;
+                contact.points[i].normal_impulse = total;
+                apply(&mut solver, contact, &p, normal * lambda);
+            }
+        }
+
+        for (handle, velocity) in solver {
+            if let Some(body) = self.bodies.get_mut(handle.index(), handle.generation()) {
+                if body.body_type == BodyType::Dynamic {
+                    body.linear_velocity = velocity.linear_velocity;
+                    body.angular_velocity = if body.fixed_rotation { 0.0 } else { velocity.angular_velocity };
+                }
+            }
+        }
+    }
+
+    /**
+     * Colliders matching `mask` with their world space hull, in handle order
+     * Queries test every collider against fresh bounds so they see changes made since the
+     * last step, the broadphase only holds the bounds from when it ran
+     **/
+    fn query_hulls(&self, mask: u32, bounds: Option<&Aabb2>) -> Vec<(ColliderHandle, &Collider2D, Hull)> {
+        let mut hulls = Vec::new();
+        for (i, g, collider) in self.colliders.iter() {
+            if collider.filter.layers & mask == 0 {
+                continue;
+            }
+            if let Some((_, iso)) = self.collider_transform(collider) {
+                if bounds.is_none_or(|b| collider.shape.aabb(&iso).intersects(b)) {
+                    hulls.push((ColliderHandle::new(i, g), collider, Hull::new(&collider.shape, &iso)));
+                }
+            }
+        }
+        hulls
+    }
+
+    /**
+     * Closest collider hit by a ray, sensors are ignored
+     * `direction` doesn't need to be normalized, `distance` in the hit is along the normalized direction
+     **/
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32, mask: u32) -> Option<RayHit2D> {
+        let dir = direction.normalize();
+        if dir.length_squared() <= f32::EPSILON {
+            return None;
+        }
+        let bounds = Aabb2::new(origin, origin + dir * max_distance);
+        let mut best: Option<RayHit2D> = None;
+        for (handle, collider, hull) in self.query_hulls(mask, Some(&bounds)) {
+            if collider.sensor {
+                continue;
+            }
+            let limit = best.map_or(max_distance, |x| x.distance);
+            if let Some((t, normal)) = collision2d::raycast(&hull, origin, dir, limit) {
+                if best.is_none_or(|x| t < x.distance) {
+                    best = Some(RayHit2D { collider: handle, point: origin + dir * t, normal, distance: t });
+                }
+            }
+        }
+        best
+    }
+
+    /**
+     * Colliders containing `point`, sensors included
+     **/
+    pub fn intersect_point(&self, point: Vec2, mask: u32) -> Vec<ColliderHandle> {
+        let bounds = Aabb2::new(point, point);
+        self.query_hulls(mask, Some(&bounds)).into_iter()
+            .filter(|x| x.2.contains_point(point))
+            .map(|x| x.0)
+            .collect()
+    }
+
+    /**
+     * Colliders whose bounds overlap `aabb`, sensors included
+     **/
+    pub fn query_aabb(&self, aabb: &Aabb2, mask: u32) -> Vec<ColliderHandle> {
+        self.query_hulls(mask, Some(aabb)).into_iter().map(|x| x.0).collect()
+    }
+
+    /**
+     * Sweeps `shape` from `iso` along `translation` and returns the first solid collider it hits
+     * Shapes already touching at the start hit with fraction 0
+     **/
+    pub fn shape_cast(&self, shape: &Shape2D, iso: &Isometry2, translation: Vec2, mask: u32) -> Option<ShapeHit2D> {
+        let start = shape.aabb(iso);
+        let end = Aabb2 { min: start.min + translation, max: start.max + translation };
+        let cast = Hull::new(shape, iso);
+        let mut best: Option<ShapeHit2D> = None;
+        for (handle, collider, hull) in self.query_hulls(mask, Some(&start.merge(&end))) {
+            if collider.sensor {
+                continue;
+            }
+            if let Some(hit) = cast_hull(&cast, &hull, translation) {
+                if best.is_none_or(|x| hit.0 < x.fraction) {
+                    best = Some(ShapeHit2D { collider: handle, fraction: hit.0, point: hit.1, normal: hit.2 });
+                }
+            }
+        }
+        best
+    }
+}
+
+/**
+ * Conservative advancement of `cast` towards `target`, returns the fraction, the point and
+ * the target's surface normal at the first touch
+ * Translation only, so each step lands exactly on the gap and it converges in a few iterations
+ **/
+fn cast_hull(cast: &Hull, target: &Hull, translation: Vec2) -> Option<(f32, Vec2, Vec2)> {
+    let radius = cast.radius + target.radius;
+    let mut fraction = 0.0;
+    for _ in 0..32 {
+        let moved = cast.translated(translation * fraction);
+        let (distance, on_cast, on_target) = match collision2d::core_distance(&moved, target) {
+            Some(x) => x,
+            None => {
+                let normal = -translation.normalize();
+                return Some((fraction, moved.points[0], normal));
+            }
+        };
+        let normal = if distance > f32::EPSILON { (on_cast - on_target) / distance } else { -translation.normalize() };
+        let gap = distance - radius;
+        if gap < 1.5 * LINEAR_SLOP {
+            return Some((fraction, on_target + normal * target.radius, normal));
+        }
+        let closing = -translation.dot(normal);
+        if closing <= f32::EPSILON {
+            return None;
+        }
+        fraction += (gap - LINEAR_SLOP) / closing;
+        if fraction > 1.0 {
+            return None;
+        }
+    }
+    None
+}
+
+impl SyncSignal<ContactBeginEvent, EventData> for PhysicsWorld2D {
+    fn connect<ContactBeginEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::ContactBegin));
+    }
+
+    fn emit(&self, event: SyncData<ContactBeginEvent>) -> Result<(), &str> {
+        emit_contact(&self.slots, event.sig(), EventType::ContactBegin)
+    }
+}
+
+impl SyncSignal<ContactEndEvent, EventData> for PhysicsWorld2D {
+    fn connect<ContactEndEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::ContactEnd));
+    }
+
+    fn emit(&self, event: SyncData<ContactEndEvent>) -> Result<(), &str> {
+        emit_contact(&self.slots, event.sig(), EventType::ContactEnd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    //static floor whose top is at y = 0
+    fn floor(world: &mut PhysicsWorld2D) -> ColliderHandle {
+        let ground = world.add_body(RigidBody2D::new(BodyType::Static, Vec2::new(0.0, -0.5)));
+        world.add_collider(ground, Collider2D::new(Shape2D::rect(20.0, 0.5))).unwrap()
+    }
+
+    fn dropped(world: &mut PhysicsWorld2D, shape: Shape2D, position: Vec2, restitution: f32) -> (BodyHandle, ColliderHandle) {
+        let body = world.add_body(RigidBody2D::new(BodyType::Dynamic, position));
+        let mut collider = Collider2D::new(shape);
+        collider.restitution = restitution;
+        let collider = world.add_collider(body, collider).unwrap();
+        (body, collider)
+    }
+
+    #[test]
+    fn resting_box_settles_without_penetration() {
+        let mut world = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        floor(&mut world);
+        let (body, _) = dropped(&mut world, Shape2D::rect(0.5, 0.5), Vec2::new(0.0, 1.0), 0.0);
+        for _ in 0..180 {
+            world.step(DT);
+        }
+        let body = world.body(body).unwrap();
+        //the bottom face should be resting on y = 0, allowing for the slop the solver leaves alone
+        let bottom = body.position().y - 0.5;
+        assert!(bottom > -2.0 * LINEAR_SLOP, "box sank to {}", bottom);
+        assert!(bottom < SPECULATIVE_DISTANCE, "box floats at {}", bottom);
+        assert!(body.linear_velocity().length() < 1e-2);
+        assert!(body.angle().abs() < 1e-3);
+    }
+
+    #[test]
+    fn restitution_bounce_height() {
+        let restitution = 0.5;
+        let drop = 2.0;
+        let mut world = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        floor(&mut world);
+        let (body, _) = dropped(&mut world, Shape2D::circle(0.5), Vec2::new(0.0, drop + 0.5), restitution);
+
+        //fall, bounce, then track the top of the first arc
+        let mut bounced = false;
+        let mut peak = 0.0f32;
+        for _ in 0..240 {
+            world.step(DT);
+            let b = world.body(body).unwrap();
+            if b.linear_velocity().y > 0.0 {
+                bounced = true;
+            }
+            if bounced {
+                peak = peak.max(b.position().y - 0.5);
+                if b.linear_velocity().y < 0.0 {
+                    break;
+                }
+            }
+        }
+        assert!(bounced);
+        //height scales with the square of the speed kept
+        let expected = drop * restitution * restitution;
+        assert!((peak - expected).abs() < 0.1 * drop, "bounced to {} expected {}", peak, expected);
+    }
+
+    #[test]
+    fn contact_begin_comes_before_end() {
+        let mut world = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        let ground = floor(&mut world);
+        let (_, ball) = dropped(&mut world, Shape2D::circle(0.5), Vec2::new(0.0, 1.5), 0.8);
+
+        let mut events = Vec::new();
+        for _ in 0..120 {
+            world.step(DT);
+            events.extend_from_slice(world.contact_events());
+        }
+        assert!(events.len() >= 2);
+        //every begin is matched by an end before the next begin
+        for (i, event) in events.iter().enumerate() {
+            match *event {
+                ContactEvent::Begin { a, b, sensor } => {
+                    assert!(i % 2 == 0, "begin out of order at {}", i);
+                    assert_eq!((a, b, sensor), (ground.min(ball), ground.max(ball), false));
+                }
+                ContactEvent::End { a, b, sensor } => {
+                    assert!(i % 2 == 1, "end out of order at {}", i);
+                    assert_eq!((a, b, sensor), (ground.min(ball), ground.max(ball), false));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sensor_reports_contacts_without_pushing() {
+        let mut world = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        let trigger = world.add_body(RigidBody2D::new(BodyType::Static, Vec2::new(0.0, 0.0)));
+        let mut collider = Collider2D::new(Shape2D::rect(2.0, 0.25));
+        collider.sensor = true;
+        let trigger = world.add_collider(trigger, collider).unwrap();
+        let mut free = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        let (reference, _) = dropped(&mut free, Shape2D::rect(0.25, 0.25), Vec2::new(0.0, 2.0), 0.0);
+        let (body, _) = dropped(&mut world, Shape2D::rect(0.25, 0.25), Vec2::new(0.0, 2.0), 0.0);
+
+        let mut events = Vec::new();
+        for _ in 0..90 {
+            world.step(DT);
+            free.step(DT);
+            events.extend_from_slice(world.contact_events());
+        }
+        //the box fell straight through as if the sensor wasn't there
+        let (a, b) = (world.body(body).unwrap(), free.body(reference).unwrap());
+        assert_eq!(a.position(), b.position());
+        assert_eq!(a.linear_velocity(), b.linear_velocity());
+        assert!(!world.in_contact(trigger, a.colliders()[0]));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], ContactEvent::Begin { sensor: true, .. }));
+        assert!(matches!(events[1], ContactEvent::End { sensor: true, .. }));
+    }
+
+    #[test]
+    fn kinematic_body_pushes_without_being_pushed() {
+        let mut world = PhysicsWorld2D::new(Vec2::new(0.0, -10.0));
+        floor(&mut world);
+        let (crate_box, _) = dropped(&mut world, Shape2D::rect(0.5, 0.5), Vec2::new(2.0, 0.5), 0.0);
+        let platform = world.add_body(RigidBody2D::new(BodyType::Kinematic, Vec2::new(0.0, 0.5)));
+        world.add_collider(platform, Collider2D::new(Shape2D::capsule(0.25, 0.25))).unwrap();
+        world.body_mut(platform).unwrap().set_linear_velocity(Vec2::new(2.0, 0.0));
+
+        for _ in 0..60 {
+            world.step(DT);
+        }
+        //gravity and the box don't slow it down
+        let platform = world.body(platform).unwrap();
+        assert_eq!(platform.linear_velocity(), Vec2::new(2.0, 0.0));
+        assert!((platform.position() - Vec2::new(2.0, 0.5)).length() < 1e-3);
+        assert!(world.body(crate_box).unwrap().position().x > 2.5);
+    }
+
+    #[test]
+    fn queries_hit_polygons_and_skip_sensors() {
+        let mut world = PhysicsWorld2D::new(Vec2::ZERO);
+        let wall = world.add_body(RigidBody2D::new(BodyType::Static, Vec2::new(3.0, 0.0)));
+        let triangle = Shape2D::polygon(&[Vec2::new(-0.5, -1.0), Vec2::new(0.5, -1.0), Vec2::new(-0.5, 1.0)]).unwrap();
+        let wall = world.add_collider(wall, Collider2D::new(triangle)).unwrap();
+        let trigger = world.add_body(RigidBody2D::new(BodyType::Static, Vec2::new(1.5, 0.0)));
+        let mut collider = Collider2D::new(Shape2D::circle(0.25));
+        collider.sensor = true;
+        let trigger = world.add_collider(trigger, collider).unwrap();
+
+        let hit = world.raycast(Vec2::ZERO, Vec2::new(2.0, 0.0), 10.0, u32::MAX).unwrap();
+        assert_eq!(hit.collider, wall);
+        assert!((hit.distance - 2.5).abs() < 1e-3);
+        assert!((hit.normal - Vec2::new(-1.0, 0.0)).length() < 1e-3);
+        assert!(world.raycast(Vec2::ZERO, Vec2::new(1.0, 0.0), 10.0, 0).is_none());
+
+        let start = Isometry2::new(Vec2::ZERO, 0.0);
+        let hit = world.shape_cast(&Shape2D::circle(0.25), &start, Vec2::new(5.0, 0.0), u32::MAX).unwrap();
+        assert_eq!(hit.collider, wall);
+        assert!((hit.fraction * 5.0 - 2.25).abs() < 0.02, "stopped after {}", hit.fraction * 5.0);
+        assert_eq!(world.intersect_point(Vec2::new(1.5, 0.1), u32::MAX), vec![trigger]);
+    }
+}
@@ -0,0 +1,1425 @@
+use std::collections::{ BTreeMap, BTreeSet };
+use std::sync::{ Arc, RwLock };
+
+use crate::core::signals::{ SyncData, SyncSignal, SyncSlot, SyncSlotPair };
+use crate::events::event::{ EventData, EventType };
+use crate::events::physics_events::{ ContactBeginEvent, ContactEndEvent };
+use crate::math::{ Aabb, Mat3, Quat, Vec3 };
+use crate::physics::arena::Arena;
+use crate::physics::broadphase::SweepAndPrune;
+use crate::physics::collision3d::{ self, Hull3, Manifold3 };
+use crate::physics::debug::{ self, DebugDrawFlags, PhysicsDebugDraw };
+use crate::physics::joint3d::{ point_mass, Joint3D, JointKind, SolverBody };
+use crate::physics::shape3d::{ Isometry3, Shape3D };
+use crate::physics::{ emit_contact, BodyHandle, BodyType, ColliderHandle, CollisionFilter, ContactEvent, JointHandle, PhysicsError };
+use crate::physics::{ BAUMGARTE, LINEAR_SLOP, MAX_CORRECTION_SPEED, RESTITUTION_THRESHOLD, SPECULATIVE_DISTANCE };
+
+/**
+ * A body the world simulates, colliders attached to it give it a shape and mass
+ * The position is the body origin, the solver works on the center of mass
+ **/
+#[derive(Debug, Clone)]
+pub struct RigidBody3D {
+    body_type: BodyType,
+    iso: Isometry3,
+    local_center: Vec3,
+    center: Vec3,
+    linear_velocity: Vec3,
+    angular_velocity: Vec3,
+    force: Vec3,
+    torque: Vec3,
+    mass: f32,
+    inv_mass: f32,
+    inertia: Mat3,
+    inv_inertia: Mat3,
+    pub gravity_scale: f32,
+    pub linear_damping: f32,
+    pub angular_damping: f32,
+    /**
+     * Stops contacts and torques from rotating the body, e.g. for characters
+     **/
+    pub fixed_rotation: bool,
+    colliders: Vec<ColliderHandle>,
+    pub user_data: u64,
+}
+
+impl RigidBody3D {
+    pub fn new(body_type: BodyType, position: Vec3) -> RigidBody3D {
+        RigidBody3D {
+            body_type,
+            iso: Isometry3::new(position, Quat::IDENTITY),
+            local_center: Vec3::ZERO,
+            center: position,
+            linear_velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            force: Vec3::ZERO,
+            torque: Vec3::ZERO,
+            mass: 0.0,
+            inv_mass: 0.0,
+            inertia: Mat3::ZERO,
+            inv_inertia: Mat3::ZERO,
+            gravity_scale: 1.0,
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            fixed_rotation: false,
+            colliders: Vec::new(),
+            user_data: 0,
+        }
+    }
+
+    #[inline]
+    pub fn body_type(&self) -> BodyType {
+        self.body_type
+    }
+
+    /**
+     * Changing the type keeps the velocity for kinematic bodies, static bodies are stopped
+     **/
+    pub fn set_body_type(&mut self, body_type: BodyType) {
+        self.body_type = body_type;
+        if body_type == BodyType::Static {
+            self.linear_velocity = Vec3::ZERO;
+            self.angular_velocity = Vec3::ZERO;
+        }
+    }
+
+    #[inline]
+    pub fn position(&self) -> Vec3 {
+        self.iso.position
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> Quat {
+        self.iso.rotation
+    }
+
+    #[inline]
+    pub fn transform(&self) -> Isometry3 {
+        self.iso
+    }
+
+    /**
+     * Teleports the body, contacts are rebuilt on the next step
+     **/
+    pub fn set_transform(&mut self, position: Vec3, rotation: Quat) {
+        self.iso = Isometry3::new(position, rotation.normalize());
+        self.center = self.iso.transform_point(self.local_center);
+    }
+
+    /**
+     * Center of mass in world space
+     **/
+    #[inline]
+    pub fn center_of_mass(&self) -> Vec3 {
+        self.center
+    }
+
+    #[inline]
+    pub fn linear_velocity(&self) -> Vec3 {
+        self.linear_velocity
+    }
+
+    pub fn set_linear_velocity(&mut self, velocity: Vec3) {
+        if self.body_type != BodyType::Static {
+            self.linear_velocity = velocity;
+        }
+    }
+
+    #[inline]
+    pub fn angular_velocity(&self) -> Vec3 {
+        self.angular_velocity
+    }
+
+    pub fn set_angular_velocity(&mut self, velocity: Vec3) {
+        if self.body_type != BodyType::Static {
+            self.angular_velocity = velocity;
+        }
+    }
+
+    /**
+     * Velocity of a world space point moving with the body
+     **/
+    pub fn velocity_at_point(&self, point: Vec3) -> Vec3 {
+        self.linear_velocity + self.angular_velocity.cross(point - self.center)
+    }
+
+    #[inline]
+    pub fn mass(&self) -> f32 {
+        self.mass
+    }
+
+    /**
+     * Inertia tensor about the center of mass in body space
+     **/
+    #[inline]
+    pub fn inertia(&self) -> Mat3 {
+        self.inertia
+    }
+
+    pub fn colliders(&self) -> &[ColliderHandle] {
+        &self.colliders
+    }
+
+    fn world_inv_inertia(&self) -> Mat3 {
+        let r = Mat3::from_quat(self.iso.rotation);
+        r * self.inv_inertia * r.transpose()
+    }
+
+    /**
+     * Force applied at the center of mass until the end of the next step
+     **/
+    pub fn apply_force(&mut self, force: Vec3) {
+        if self.body_type == BodyType::Dynamic {
+            self.force += force;
+        }
+    }
+
+    pub fn apply_force_at_point(&mut self, force: Vec3, point: Vec3) {
+        if self.body_type == BodyType::Dynamic {
+            self.force += force;
+            self.torque += (point - self.center).cross(force);
+        }
+    }
+
+    pub fn apply_torque(&mut self, torque: Vec3) {
+        if self.body_type == BodyType::Dynamic {
+            self.torque += torque;
+        }
+    }
+
+    /**
+     * Changes the velocity immediately
+     **/
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        if self.body_type == BodyType::Dynamic {
+            self.linear_velocity += impulse * self.inv_mass;
+        }
+    }
+
+    pub fn apply_impulse_at_point(&mut self, impulse: Vec3, point: Vec3) {
+        if self.body_type == BodyType::Dynamic {
+            self.linear_velocity += impulse * self.inv_mass;
+            self.angular_velocity += self.world_inv_inertia() * (point - self.center).cross(impulse);
+        }
+    }
+}
+
+/**
+ * Shape attached to a body, sensors report contacts without pushing anything
+ **/
+#[derive(Debug, Clone)]
+pub struct Collider3D {
+    pub shape: Shape3D,
+    /**
+     * Placement relative to the body origin
+     **/
+    pub offset: Isometry3,
+    pub density: f32,
+    pub friction: f32,
+    pub restitution: f32,
+    pub sensor: bool,
+    pub filter: CollisionFilter,
+    pub user_data: u64,
+    body: Option<BodyHandle>,
+}
+
+impl Collider3D {
+    pub fn new(shape: Shape3D) -> Collider3D {
+        Collider3D {
+            shape,
+            offset: Isometry3::IDENTITY,
+            density: 1.0,
+            friction: 0.6,
+            restitution: 0.0,
+            sensor: false,
+            filter: CollisionFilter::ALL,
+            user_data: 0,
+            body: None,
+        }
+    }
+
+    /**
+     * Body this collider is attached to, None until it's added to a world
+     **/
+    #[inline]
+    pub fn body(&self) -> Option<BodyHandle> {
+        self.body
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit3D {
+    pub collider: ColliderHandle,
+    pub point: Vec3,
+    pub normal: Vec3,
+    pub distance: f32,
+}
+
+/**
+ * First hit of a moving shape, `fraction` is how much of the translation happened before it
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShapeHit3D {
+    pub collider: ColliderHandle,
+    pub fraction: f32,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ContactPoint {
+    //mesh triangle in the high bits, manifold feature in the low ones
+    id: u64,
+    normal: Vec3,
+    //anchors relative to each body's center of mass
+    ra: Vec3,
+    rb: Vec3,
+    separation: f32,
+    normal_impulse: f32,
+    tangent_impulse: [f32; 2],
+    normal_mass: f32,
+    tangent_mass: [f32; 2],
+    //normal velocity before solving, what restitution bounces off
+    approach: f32,
+}
+
+impl ContactPoint {
+    fn tangents(&self) -> [Vec3; 2] {
+        let t1 = self.normal.any_orthogonal();
+        [t1, self.normal.cross(t1)]
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Contact {
+    body_a: BodyHandle,
+    body_b: BodyHandle,
+    sensor: bool,
+    touching: bool,
+    friction: f32,
+    restitution: f32,
+    points: Vec<ContactPoint>,
+}
+
+/**
+ * 3D rigid body simulation, stepped from the engine's fixed update tick
+ * Everything is processed in handle order with no hashing or threads, so the same calls
+ * in the same order give bit for bit the same results on the same build
+ **/
+pub struct PhysicsWorld3D {
+    gravity: Vec3,
+    bodies: Arena<RigidBody3D>,
+    colliders: Arena<Collider3D>,
+    joints: Arena<Joint3D>,
+    broadphase: SweepAndPrune<Aabb>,
+    contacts: BTreeMap<(ColliderHandle, ColliderHandle), Contact>,
+    events: Vec<ContactEvent>,
+    //ends caused by removing colliders between steps, reported with the next step
+    pending_events: Vec<ContactEvent>,
+    slots: Vec<SyncSlotPair>,
+    pub velocity_iterations: u32,
+}
+
+impl PhysicsWorld3D {
+    pub fn new(gravity: Vec3) -> PhysicsWorld3D {
+        PhysicsWorld3D {
+            gravity,
+            bodies: Arena::new(),
+            colliders: Arena::new(),
+            joints: Arena::new(),
+            broadphase: SweepAndPrune::new(),
+            contacts: BTreeMap::new(),
+            events: Vec::new(),
+            pending_events: Vec::new(),
+            slots: vec![],
+            velocity_iterations: 8,
+        }
+    }
+
+    #[inline]
+    pub fn gravity(&self) -> Vec3 {
+        self.gravity
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vec3) {
+        self.gravity = gravity;
+    }
+
+    pub fn add_body(&mut self, body: RigidBody3D) -> BodyHandle {
+        let (index, generation) = self.bodies.insert(body);
+        BodyHandle::new(index, generation)
+    }
+
+    /**
+     * Removes the body along with its colliders and joints
+     **/
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<RigidBody3D> {
+        let colliders = self.body(handle)?.colliders.clone();
+        for collider in colliders {
+            self.remove_collider(collider);
+        }
+        let joints: Vec<JointHandle> = self.joints()
+            .filter(|(_, j)| j.bodies().0 == handle || j.bodies().1 == handle)
+            .map(|x| x.0)
+            .collect();
+        for joint in joints {
+            self.remove_joint(joint);
+        }
+        self.bodies.remove(handle.index(), handle.generation())
+    }
+
+    pub fn body(&self, handle: BodyHandle) -> Option<&RigidBody3D> {
+        self.bodies.get(handle.index(), handle.generation())
+    }
+
+    pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut RigidBody3D> {
+        self.bodies.get_mut(handle.index(), handle.generation())
+    }
+
+    pub fn bodies(&self) -> impl Iterator<Item = (BodyHandle, &RigidBody3D)> {
+        self.bodies.iter().map(|(i, g, x)| (BodyHandle::new(i, g), x))
+    }
+
+    pub fn add_collider(&mut self, body: BodyHandle, mut collider: Collider3D) -> Result<ColliderHandle, PhysicsError> {
+        if self.body(body).is_none() {
+            return Err(PhysicsError::InvalidBody);
+        }
+        collider.body = Some(body);
+        let (index, generation) = self.colliders.insert(collider);
+        let handle = ColliderHandle::new(index, generation);
+        if let Some(x) = self.body_mut(body) {
+            x.colliders.push(handle);
+        }
+        self.update_mass(body);
+        Ok(handle)
+    }
+
+    pub fn remove_collider(&mut self, handle: ColliderHandle) -> Option<Collider3D> {
+        let collider = self.colliders.remove(handle.index(), handle.generation())?;
+        let removed: Vec<(ColliderHandle, ColliderHandle)> = self.contacts.keys()
+            .filter(|k| k.0 == handle || k.1 == handle)
+            .cloned()
+            .collect();
+        for key in removed {
+            if let Some(contact) = self.contacts.remove(&key) {
+                if contact.touching {
+                    self.pending_events.push(ContactEvent::End { a: key.0, b: key.1, sensor: contact.sensor });
+                }
+            }
+        }
+        if let Some(body) = collider.body {
+            if let Some(x) = self.body_mut(body) {
+                x.colliders.retain(|c| *c != handle);
+            }
+            self.update_mass(body);
+        }
+        Some(collider)
+    }
+
+    pub fn collider(&self, handle: ColliderHandle) -> Option<&Collider3D> {
+        self.colliders.get(handle.index(), handle.generation())
+    }
+
+    /**
+     * Density changes are picked up by the body on the next step
+     **/
+    pub fn collider_mut(&mut self, handle: ColliderHandle) -> Option<&mut Collider3D> {
+        self.colliders.get_mut(handle.index(), handle.generation())
+    }
+
+    /**
+     * Joins two bodies at a world space anchor, using their current placement as the rest pose
+     **/
+    pub fn add_joint(&mut self, kind: JointKind, body_a: BodyHandle, body_b: BodyHandle, anchor: Vec3) -> Result<JointHandle, PhysicsError> {
+        let a = self.body(body_a).ok_or(PhysicsError::InvalidBody)?;
+        let b = self.body(body_b).ok_or(PhysicsError::InvalidBody)?;
+        if body_a == body_b {
+            return Err(PhysicsError::InvalidBody);
+        }
+        let joint = Joint3D::new(kind, body_a, body_b, (a.iso.position, a.iso.rotation), (b.iso.position, b.iso.rotation), anchor);
+        let (index, generation) = self.joints.insert(joint);
+        Ok(JointHandle::new(index, generation))
+    }
+
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint3D> {
+        self.joints.remove(handle.index(), handle.generation())
+    }
+
+    pub fn joint(&self, handle: JointHandle) -> Option<&Joint3D> {
+        self.joints.get(handle.index(), handle.generation())
+    }
+
+    pub fn joint_mut(&mut self, handle: JointHandle) -> Option<&mut Joint3D> {
+        self.joints.get_mut(handle.index(), handle.generation())
+    }
+
+    pub fn joints(&self) -> impl Iterator<Item = (JointHandle, &Joint3D)> {
+        self.joints.iter().map(|(i, g, x)| (JointHandle::new(i, g), x))
+    }
+
+    /**
+     * Contacts that started or ended during the last step
+     **/
+    pub fn contact_events(&self) -> &[ContactEvent] {
+        &self.events
+    }
+
+    /**
+     * Whether two colliders are currently touching or overlapping
+     **/
+    pub fn in_contact(&self, a: ColliderHandle, b: ColliderHandle) -> bool {
+        let key = if a < b { (a, b) } else { (b, a) };
+        self.contacts.get(&key).is_some_and(|x| x.touching)
+    }
+
+    /**
+     * Hash of every body's position, rotation and velocity, bit for bit
+     * Running the same inputs twice and comparing checksums is how headless regression
+     * tests check that a simulation hasn't changed
+     **/
+    pub fn state_checksum(&self) -> u64 {
+        //FNV-1a
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        let mut feed = |x: f32| {
+            for byte in x.to_bits().to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        for (_, _, body) in self.bodies.iter() {
+            let (p, q) = (body.iso.position, body.iso.rotation);
+            for x in [p.x, p.y, p.z, q.x, q.y, q.z, q.w] {
+                feed(x);
+            }
+            for v in [body.linear_velocity, body.angular_velocity] {
+                for x in [v.x, v.y, v.z] {
+                    feed(x);
+                }
+            }
+        }
+        hash
+    }
+
+    fn update_mass(&mut self, handle: BodyHandle) {
+        let body = match self.bodies.get(handle.index(), handle.generation()) {
+            Some(x) => x,
+            None => return
+        };
+        let mut mass = 0.0;
+        let mut center = Vec3::ZERO;
+        //about the body origin for now, shifted to the center below
+        let mut inertia = Mat3::ZERO;
+        if body.body_type == BodyType::Dynamic {
+            for c in &body.colliders {
+                if let Some(collider) = self.colliders.get(c.index(), c.generation()) {
+                    let props = collider.shape.mass_properties(collider.density);
+                    let rotation = Mat3::from_quat(collider.offset.rotation);
+                    let centroid = collider.offset.transform_point(props.centroid);
+                    mass += props.mass;
+                    center += centroid * props.mass;
+                    inertia = inertia + rotation * props.inertia * rotation.transpose() + parallel_axis(centroid, props.mass);
+                }
+            }
+        }
+
+        let body = match self.bodies.get_mut(handle.index(), handle.generation()) {
+            Some(x) => x,
+            None => return
+        };
+        if body.body_type != BodyType::Dynamic {
+            body.mass = 0.0;
+            body.inv_mass = 0.0;
+            body.inertia = Mat3::ZERO;
+            body.inv_inertia = Mat3::ZERO;
+            body.local_center = Vec3::ZERO;
+        } else if mass > 0.0 {
+            center /= mass;
+            body.mass = mass;
+            body.inv_mass = 1.0 / mass;
+            body.inertia = inertia - parallel_axis(center, mass);
+            body.inv_inertia = if body.fixed_rotation { Mat3::ZERO } else { inverse_inertia(&body.inertia) };
+            body.local_center = center;
+        } else {
+            //a dynamic body without any colliders still has to respond to forces
+            body.mass = 1.0;
+            body.inv_mass = 1.0;
+            body.inertia = Mat3::ZERO;
+            body.inv_inertia = Mat3::ZERO;
+            body.local_center = Vec3::ZERO;
+        }
+        body.center = body.iso.transform_point(body.local_center);
+    }
+
+    fn collider_transform(&self, collider: &Collider3D) -> Option<(&RigidBody3D, Isometry3)> {
+        let handle = collider.body?;
+        let body = self.body(handle)?;
+        Some((body, body.iso.mul(&collider.offset)))
+    }
+
+    /**
+     * Advances the simulation by `dt` seconds, meant to be called with a fixed step
+     **/
+    pub fn step(&mut self, dt: f32) {
+        self.events = std::mem::take(&mut self.pending_events);
+        if dt <= 0.0 {
+            return;
+        }
+
+        let handles: Vec<BodyHandle> = self.bodies().map(|x| x.0).collect();
+        for handle in &handles {
+            self.update_mass(*handle);
+        }
+
+        for (_, _, body) in self.bodies.iter_mut() {
+            if body.body_type != BodyType::Dynamic {
+                continue;
+            }
+            let inv_inertia = body.world_inv_inertia();
+            body.linear_velocity += (self.gravity * body.gravity_scale + body.force * body.inv_mass) * dt;
+            body.angular_velocity += inv_inertia * body.torque * dt;
+            body.linear_velocity *= 1.0 / (1.0 + dt * body.linear_damping);
+            body.angular_velocity *= 1.0 / (1.0 + dt * body.angular_damping);
+        }
+
+        let previous = self.update_contacts(dt);
+        self.solve(dt);
+        self.update_events(previous);
+
+        for (_, _, body) in self.bodies.iter_mut() {
+            if body.body_type != BodyType::Static {
+                body.center += body.linear_velocity * dt;
+                let w = body.angular_velocity * (0.5 * dt);
+                let q = body.iso.rotation;
+                let dq = Quat::from_xyzw(w.x, w.y, w.z, 0.0) * q;
+                body.iso.rotation = Quat::from_xyzw(q.x + dq.x, q.y + dq.y, q.z + dq.z, q.w + dq.w).normalize();
+                body.iso.position = body.center - body.iso.rotation.rotate(body.local_center);
+            }
+            body.force = Vec3::ZERO;
+            body.torque = Vec3::ZERO;
+        }
+
+        for event in self.events.clone() {
+            let res = match event {
+                ContactEvent::Begin { a, b, sensor } => self.emit(SyncData::Sig(ContactBeginEvent::new(
+                    format!("Contact began between 3D colliders {:?} and {:?} (sensor: {})", a, b, sensor),
+                    a.to_bits(), b.to_bits()))),
+                ContactEvent::End { a, b, sensor } => self.emit(SyncData::Sig(ContactEndEvent::new(
+                    format!("Contact ended between 3D colliders {:?} and {:?} (sensor: {})", a, b, sensor),
+                    a.to_bits(), b.to_bits())))
+            };
+            if let Err(e) = res {
+                error!("Failed to emit contact event: {}", e);
+            }
+        }
+    }
+
+    /**
+     * Contact manifolds between a world space hull and one collider, meshes give one per triangle
+     * Feature ids are offset by the triangle index so they stay unique across triangles
+     **/
+    fn manifolds(hull: &Hull3, hull_first: bool, shape: &Shape3D, iso: &Isometry3, cached: Option<&Hull3>, margin: f32) -> Vec<(u64, Manifold3)> {
+        let collide = |other: &Hull3| if hull_first {
+            collision3d::collide(hull, other, margin)
+        } else {
+            collision3d::collide(other, hull, margin)
+        };
+        match shape {
+            Shape3D::Mesh(mesh) => {
+                let bounds = Aabb::from_points(&hull.points).map(|b| b.expand(hull.radius + margin));
+                let local = match bounds {
+                    Some(b) => {
+                        let corners: Vec<Vec3> = b.corners().iter().map(|&p| iso.inverse_transform_point(p)).collect();
+                        Aabb::from_points(&corners)
+                    },
+                    None => None
+                };
+                local.map_or(Vec::new(), |local| mesh.query_aabb(&local).into_iter().filter_map(|t| {
+                    let [a, b, c] = mesh.triangle(t);
+                    let triangle = Hull3::triangle(iso.transform_point(a), iso.transform_point(b), iso.transform_point(c));
+                    collide(&triangle).map(|m| ((t as u64) << 32, m))
+                }).collect())
+            },
+            _ => {
+                let other = match cached {
+                    Some(x) => x.clone(),
+                    None => match Hull3::new(shape, iso) {
+                        Some(x) => x,
+                        None => return Vec::new()
+                    }
+                };
+                collide(&other).map(|m| vec![(0, m)]).unwrap_or_default()
+            }
+        }
+    }
+
+    /**
+     * Rebuilds the contact list, returns whether each old contact was touching and a sensor
+     **/
+    fn update_contacts(&mut self, dt: f32) -> BTreeMap<(ColliderHandle, ColliderHandle), (bool, bool)> {
+        let mut hulls: BTreeMap<ColliderHandle, (Isometry3, Option<Hull3>)> = BTreeMap::new();
+        let mut bounds = Vec::with_capacity(self.colliders.len());
+        for (i, g, collider) in self.colliders.iter() {
+            if let Some((body, iso)) = self.collider_transform(collider) {
+                let handle = ColliderHandle::new(i, g);
+                //swept by the body's motion so fast bodies still find their pairs
+                let aabb = collider.shape.aabb(&iso).expand(SPECULATIVE_DISTANCE);
+                let motion = body.linear_velocity * dt;
+                let moved = Aabb { min: aabb.min + motion, max: aabb.max + motion };
+                bounds.push((handle, aabb.merge(&moved)));
+                hulls.insert(handle, (iso, Hull3::new(&collider.shape, &iso)));
+            }
+        }
+        self.broadphase.update(bounds);
+
+        let jointed: BTreeSet<(BodyHandle, BodyHandle)> = self.joints.iter()
+            .filter(|x| !x.2.collide_connected)
+            .map(|x| {
+                let (a, b) = x.2.bodies();
+                (a.min(b), a.max(b))
+            })
+            .collect();
+
+        let old = std::mem::take(&mut self.contacts);
+        for (a, b) in self.broadphase.pairs() {
+            let (ca, cb) = match (self.collider(a), self.collider(b)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue
+            };
+            if ca.body == cb.body || !ca.filter.interacts(&cb.filter) {
+                continue;
+            }
+            let ((ba, _), (bb, _)) = match (self.collider_transform(ca), self.collider_transform(cb)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue
+            };
+            let (body_a, body_b) = (ca.body.unwrap(), cb.body.unwrap());
+            if jointed.contains(&(body_a.min(body_b), body_a.max(body_b))) {
+                continue;
+            }
+            let sensor = ca.sensor || cb.sensor;
+            let moving = |x: &RigidBody3D| x.body_type != BodyType::Static;
+            let dynamic = |x: &RigidBody3D| x.body_type == BodyType::Dynamic;
+            //sensors notice anything that moves, solid contacts need something to push
+            if (sensor && !moving(ba) && !moving(bb)) || (!sensor && !dynamic(ba) && !dynamic(bb)) {
+                continue;
+            }
+
+            let (iso_a, hull_a) = &hulls[&a];
+            let (iso_b, hull_b) = &hulls[&b];
+            //at least one side has to be convex, mesh against mesh isn't supported
+            let margin = if sensor { 0.0 } else { SPECULATIVE_DISTANCE + (bb.linear_velocity - ba.linear_velocity).length() * dt };
+            let manifolds = match (hull_a, hull_b) {
+                (Some(h), _) => PhysicsWorld3D::manifolds(h, true, &cb.shape, iso_b, hull_b.as_ref(), margin),
+                (None, Some(h)) => PhysicsWorld3D::manifolds(h, false, &ca.shape, iso_a, None, margin),
+                _ => continue
+            };
+
+            let mut contact = Contact {
+                body_a,
+                body_b,
+                sensor,
+                touching: false,
+                friction: (ca.friction * cb.friction).sqrt(),
+                restitution: ca.restitution.max(cb.restitution),
+                points: Vec::new(),
+            };
+
+            if sensor {
+                contact.touching = manifolds.iter().any(|(_, m)| m.points.iter().any(|p| p.separation < 0.0));
+                if !contact.touching {
+                    continue;
+                }
+            } else {
+                let previous = old.get(&(a, b));
+                for (base, manifold) in &manifolds {
+                    for p in &manifold.points {
+                        let id = base | p.id as u64;
+                        //warm start from the last step's impulses on the same features
+                        let (normal_impulse, tangent_impulse) = previous
+                            .and_then(|c| c.points.iter().find(|x| x.id == id))
+                            .map_or((0.0, [0.0; 2]), |x| (x.normal_impulse, x.tangent_impulse));
+                        contact.points.push(ContactPoint {
+                            id,
+                            normal: manifold.normal,
+                            ra: p.point - ba.center,
+                            rb: p.point - bb.center,
+                            separation: p.separation,
+                            normal_impulse,
+                            tangent_impulse,
+                            normal_mass: 0.0,
+                            tangent_mass: [0.0; 2],
+                            approach: 0.0,
+                        });
+                    }
+                }
+                if contact.points.is_empty() {
+                    continue;
+                }
+                contact.touching = contact.points.iter().any(|p| p.separation < LINEAR_SLOP);
+            }
+            self.contacts.insert((a, b), contact);
+        }
+
+        old.into_iter().map(|(k, c)| (k, (c.touching, c.sensor))).collect()
+    }
+
+    fn update_events(&mut self, previous: BTreeMap<(ColliderHandle, ColliderHandle), (bool, bool)>) {
+        for contact in self.contacts.values_mut() {
+            //a speculative contact that had to push closes its gap during this step
+            contact.touching |= contact.points.iter().any(|p| p.normal_impulse > 0.0);
+        }
+        for (&(a, b), contact) in &self.contacts {
+            let was_touching = previous.get(&(a, b)).is_some_and(|x| x.0);
+            if contact.touching && !was_touching {
+                self.events.push(ContactEvent::Begin { a, b, sensor: contact.sensor });
+            } else if !contact.touching && was_touching {
+                self.events.push(ContactEvent::End { a, b, sensor: contact.sensor });
+            }
+        }
+        for (&(a, b), &(touching, sensor)) in &previous {
+            if touching && !self.contacts.contains_key(&(a, b)) {
+                self.events.push(ContactEvent::End { a, b, sensor });
+            }
+        }
+    }
+
+    /**
+     * Sequential impulses with warm starting, joints first, then friction before the normal
+     * constraint so non penetration wins when they disagree
+     **/
+    fn solve(&mut self, dt: f32) {
+        let inv_dt = 1.0 / dt;
+        let mut solver: BTreeMap<BodyHandle, SolverBody> = BTreeMap::new();
+        for (i, g, body) in self.bodies.iter() {
+            solver.insert(BodyHandle::new(i, g), SolverBody {
+                linear_velocity: body.linear_velocity,
+                angular_velocity: body.angular_velocity,
+                inv_mass: body.inv_mass,
+                inv_inertia: body.world_inv_inertia(),
+                center: body.center,
+                rotation: body.iso.rotation,
+                local_center: body.local_center,
+            });
+        }
+
+        fn pair(solver: &mut BTreeMap<BodyHandle, SolverBody>, a: BodyHandle, b: BodyHandle) -> Option<(SolverBody, SolverBody)> {
+            match (solver.get(&a), solver.get(&b)) {
+                (Some(x), Some(y)) => Some((*x, *y)),
+                _ => None
+            }
+        }
+        fn store(solver: &mut BTreeMap<BodyHandle, SolverBody>, a: BodyHandle, b: BodyHandle, sa: SolverBody, sb: SolverBody) {
+            solver.insert(a, sa);
+            solver.insert(b, sb);
+        }
+
+        //prepare and warm start
+        for (_, _, joint) in self.joints.iter_mut() {
+            let (ha, hb) = joint.bodies();
+            if let Some((mut a, mut b)) = pair(&mut solver, ha, hb) {
+                joint.warm_start(&mut a, &mut b);
+                store(&mut solver, ha, hb, a, b);
+            }
+        }
+        for contact in self.contacts.values_mut().filter(|c| !c.sensor) {
+            let (mut a, mut b) = match pair(&mut solver, contact.body_a, contact.body_b) {
+                Some(x) => x,
+                None => continue
+            };
+            for p in contact.points.iter_mut() {
+                let tangents = p.tangents();
+                p.normal_mass = point_mass(&a, &b, p.ra, p.rb, p.normal);
+                p.tangent_mass = [point_mass(&a, &b, p.ra, p.rb, tangents[0]), point_mass(&a, &b, p.ra, p.rb, tangents[1])];
+                p.approach = (b.velocity_at(p.rb) - a.velocity_at(p.ra)).dot(p.normal);
+                let impulse = p.normal * p.normal_impulse + tangents[0] * p.tangent_impulse[0] + tangents[1] * p.tangent_impulse[1];
+                a.apply(p.ra, -impulse);
+                b.apply(p.rb, impulse);
+            }
+            store(&mut solver, contact.body_a, contact.body_b, a, b);
+        }
+
+        for _ in 0..self.velocity_iterations {
+            for (_, _, joint) in self.joints.iter_mut() {
+                let (ha, hb) = joint.bodies();
+                if let Some((mut a, mut b)) = pair(&mut solver, ha, hb) {
+                    joint.solve(&mut a, &mut b, dt);
+                    store(&mut solver, ha, hb, a, b);
+                }
+            }
+
+            for contact in self.contacts.values_mut().filter(|c| !c.sensor) {
+                let (mut a, mut b) = match pair(&mut solver, contact.body_a, contact.body_b) {
+                    Some(x) => x,
+                    None => continue
+                };
+                for p in contact.points.iter_mut() {
+                    let tangents = p.tangents();
+                    let max_friction = contact.friction * p.normal_impulse;
+                    for (k, &tangent) in tangents.iter().enumerate() {
+                        let vt = (b.velocity_at(p.rb) - a.velocity_at(p.ra)).dot(tangent);
+                        let total = (p.tangent_impulse[k] - p.tangent_mass[k] * vt).clamp(-max_friction, max_friction);
+                        let impulse = tangent * (total - p.tangent_impulse[k]);
+                        p.tangent_impulse[k] = total;
+                        a.apply(p.ra, -impulse);
+                        b.apply(p.rb, impulse);
+                    }
+                }
+                for p in contact.points.iter_mut() {
+                    let vn = (b.velocity_at(p.rb) - a.velocity_at(p.ra)).dot(p.normal);
+                    let bias = if p.separation > 0.0 {
+                        //speculative, only allow the approach that closes the gap this step
+                        p.separation * inv_dt
+                    } else {
+                        (BAUMGARTE * inv_dt * (p.separation + LINEAR_SLOP)).clamp(-MAX_CORRECTION_SPEED, 0.0)
+                    };
+                    let total = (p.normal_impulse - p.normal_mass * (vn + bias)).max(0.0);
+                    let impulse = p.normal * (total - p.normal_impulse);
+                    p.normal_impulse = total;
+                    a.apply(p.ra, -impulse);
+                    b.apply(p.rb, impulse);
+                }
+                store(&mut solver, contact.body_a, contact.body_b, a, b);
+            }
+        }
+
+        //restitution runs after the main solve so the bounce uses the approach speed
+        for contact in self.contacts.values_mut().filter(|c| !c.sensor && c.restitution > 0.0) {
+            let (mut a, mut b) = match pair(&mut solver, contact.body_a, contact.body_b) {
+                Some(x) => x,
+                None => continue
+            };
+            for p in contact.points.iter_mut() {
+                if p.approach > -RESTITUTION_THRESHOLD || p.normal_impulse <= 0.0 {
+                    continue;
+                }
+                let vn = (b.velocity_at(p.rb) - a.velocity_at(p.ra)).dot(p.normal);
+                let total = (p.normal_impulse - p.normal_mass * (vn + contact.restitution * p.approach)).max(0.0);
+                let impulse = p.normal * (total - p.normal_impulse);
+                p.normal_impulse = total;
+                a.apply(p.ra, -impulse);
+                b.apply(p.rb, impulse);
+            }
+            store(&mut solver, contact.body_a, contact.body_b, a, b);
+        }
+
+        for (handle, velocity) in solver {
+            if let Some(body) = self.bodies.get_mut(handle.index(), handle.generation()) {
+                if body.body_type == BodyType::Dynamic {
+                    body.linear_velocity = velocity.linear_velocity;
+                    body.angular_velocity = if body.fixed_rotation { Vec3::ZERO } else { velocity.angular_velocity };
+                }
+            }
+        }
+    }
+
+    /**
+     * Colliders matching `mask` whose fresh bounds overlap `bounds`, in handle order
+     * Queries don't use the broadphase so they see changes made since the last step
+     **/
+    fn query_colliders(&self, mask: u32, bounds: &Aabb, exclude: Option<BodyHandle>) -> Vec<(ColliderHandle, &Collider3D, Isometry3)> {
+        let mut found = Vec::new();
+        for (i, g, collider) in self.colliders.iter() {
+            if collider.filter.layers & mask == 0 || (exclude.is_some() && collider.body == exclude) {
+                continue;
+            }
+            if let Some((_, iso)) = self.collider_transform(collider) {
+                if collider.shape.aabb(&iso).intersects_aabb(bounds) {
+                    found.push((ColliderHandle::new(i, g), collider, iso));
+                }
+            }
+        }
+        found
+    }
+
+    /**
+     * Closest collider hit by a ray, sensors are ignored
+     * `direction` doesn't need to be normalized, `distance` in the hit is along the normalized direction
+     **/
+    pub fn raycast(&self, origin: Vec3, direction: Vec3, max_distance: f32, mask: u32) -> Option<RayHit3D> {
+        let dir = direction.normalize();
+        if dir.length_squared() <= f32::EPSILON {
+            return None;
+        }
+        let bounds = Aabb::new(origin, origin + dir * max_distance);
+        let mut best: Option<RayHit3D> = None;
+        for (handle, collider, iso) in self.query_colliders(mask, &bounds, None) {
+            if collider.sensor {
+                continue;
+            }
+            let limit = best.map_or(max_distance, |x| x.distance);
+            if let Some((t, normal)) = collision3d::raycast(&collider.shape, &iso, origin, dir, limit) {
+                if best.is_none_or(|x| t < x.distance) {
+                    best = Some(RayHit3D { collider: handle, point: origin + dir * t, normal, distance: t });
+                }
+            }
+        }
+        best
+    }
+
+    /**
+     * Colliders overlapping `shape` placed at `iso`, sensors included
+     **/
+    pub fn overlap(&self, shape: &Shape3D, iso: &Isometry3, mask: u32) -> Vec<ColliderHandle> {
+        let hull = match Hull3::new(shape, iso) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+        self.query_colliders(mask, &shape.aabb(iso), None).into_iter()
+            .filter(|(_, collider, other)| match &collider.shape {
+                Shape3D::Mesh(mesh) => {
+                    let local = Aabb::from_points(&shape.aabb(iso).corners().map(|p| other.inverse_transform_point(p)));
+                    local.is_some_and(|local| mesh.query_aabb(&local).into_iter().any(|t| {
+                        let [a, b, c] = mesh.triangle(t);
+                        collision3d::intersects(&hull, &Hull3::triangle(other.transform_point(a), other.transform_point(b), other.transform_point(c)))
+                    }))
+                },
+                s => Hull3::new(s, other).is_some_and(|x| collision3d::intersects(&hull, &x))
+            })
+            .map(|x| x.0)
+            .collect()
+    }
+
+    /**
+     * Colliders containing `point`, sensors included
+     **/
+    pub fn intersect_point(&self, point: Vec3, mask: u32) -> Vec<ColliderHandle> {
+        self.query_colliders(mask, &Aabb::new(point, point), None).into_iter()
+            .filter(|(_, collider, iso)| Hull3::new(&collider.shape, iso).is_some_and(|x| x.contains_point(point)))
+            .map(|x| x.0)
+            .collect()
+    }
+
+    /**
+     * Colliders whose bounds overlap `aabb`, sensors included
+     **/
+    pub fn query_aabb(&self, aabb: &Aabb, mask: u32) -> Vec<ColliderHandle> {
+        self.query_colliders(mask, aabb, None).into_iter().map(|x| x.0).collect()
+    }
+
+    /**
+     * Sweeps `shape` from `iso` along `translation` and returns the first solid collider it hits
+     * Shapes already overlapping or touching and moving closer at the start hit with fraction 0
+     **/
+    pub fn shape_cast(&self, shape: &Shape3D, iso: &Isometry3, translation: Vec3, mask: u32) -> Option<ShapeHit3D> {
+        self.shape_cast_excluding(shape, iso, translation, mask, None)
+    }
+
+    pub(crate) fn shape_cast_excluding(&self, shape: &Shape3D, iso: &Isometry3, translation: Vec3, mask: u32, exclude: Option<BodyHandle>) -> Option<ShapeHit3D> {
+        let cast = Hull3::new(shape, iso)?;
+        if translation.length_squared() <= f32::EPSILON * f32::EPSILON {
+            return None;
+        }
+        let start = shape.aabb(iso);
+        let swept = start.merge(&Aabb { min: start.min + translation, max: start.max + translation });
+        let mut best: Option<ShapeHit3D> = None;
+        let mut consider = |handle: ColliderHandle, hit: Option<(f32, Vec3, Vec3)>| {
+            if let Some((fraction, point, normal)) = hit {
+                if best.is_none_or(|x| fraction < x.fraction) {
+                    best = Some(ShapeHit3D { collider: handle, fraction, point, normal });
+                }
+            }
+        };
+        for (handle, collider, other) in self.query_colliders(mask, &swept, exclude) {
+            if collider.sensor {
+                continue;
+            }
+            match &collider.shape {
+                Shape3D::Mesh(mesh) => {
+                    let local = Aabb::from_points(&swept.expand(cast.radius).corners().map(|p| other.inverse_transform_point(p)));
+                    for t in local.map_or(Vec::new(), |local| mesh.query_aabb(&local)) {
+                        let [a, b, c] = mesh.triangle(t);
+                        let triangle = Hull3::triangle(other.transform_point(a), other.transform_point(b), other.transform_point(c));
+                        consider(handle, collision3d::sweep(&cast, &triangle, translation));
+                    }
+                },
+                s => if let Some(target) = Hull3::new(s, &other) {
+                    consider(handle, collision3d::sweep(&cast, &target, translation));
+                }
+            }
+        }
+        best
+    }
+
+    /**
+     * Deepest overlap of `shape` with each solid collider, as (normal from the shape, separation)
+     **/
+    pub(crate) fn penetrations(&self, shape: &Shape3D, iso: &Isometry3, mask: u32, exclude: Option<BodyHandle>) -> Vec<(Vec3, f32)> {
+        let hull = match Hull3::new(shape, iso) {
+            Some(x) => x,
+            None => return Vec::new()
+        };
+        let mut found = Vec::new();
+        for (_, collider, other) in self.query_colliders(mask, &shape.aabb(iso), exclude) {
+            if collider.sensor {
+                continue;
+            }
+            for (_, manifold) in PhysicsWorld3D::manifolds(&hull, true, &collider.shape, &other, None, 0.0) {
+                let deepest = manifold.points.iter().map(|p| p.separation).fold(f32::INFINITY, f32::min);
+                if deepest < 0.0 {
+                    found.push((manifold.normal, deepest));
+                }
+            }
+        }
+        found
+    }
+
+    /**
+     * Draws colliders, bounds, contacts and joints as lines
+     **/
+    pub fn debug_draw(&self, draw: &mut dyn PhysicsDebugDraw, flags: DebugDrawFlags) {
+        for (_, _, collider) in self.colliders.iter() {
+            let (body, iso) = match self.collider_transform(collider) {
+                Some(x) => x,
+                None => continue
+            };
+            let color = if collider.sensor {
+                debug::COLOR_SENSOR
+            } else {
+                match body.body_type {
+                    BodyType::Static => debug::COLOR_STATIC,
+                    BodyType::Dynamic => debug::COLOR_DYNAMIC,
+                    BodyType::Kinematic => debug::COLOR_KINEMATIC
+                }
+            };
+            if flags.shapes {
+                draw_shape(draw, &collider.shape, &iso, color);
+            }
+            if flags.aabbs {
+                let c = collider.shape.aabb(&iso).corners();
+                for (i, j) in [(0, 1), (1, 3), (3, 2), (2, 0), (4, 5), (5, 7), (7, 6), (6, 4), (0, 4), (1, 5), (2, 6), (3, 7)] {
+                    draw.line(c[i], c[j], debug::COLOR_AABB);
+                }
+            }
+        }
+
+        if flags.contacts {
+            for contact in self.contacts.values().filter(|c| !c.sensor) {
+                if let Some(a) = self.body(contact.body_a) {
+                    for p in &contact.points {
+                        let point = a.center + p.ra;
+                        draw.line(point, point + p.normal * 0.2, debug::COLOR_CONTACT);
+                    }
+                }
+            }
+        }
+
+        if flags.joints {
+            for (_, _, joint) in self.joints.iter() {
+                let (ha, hb) = joint.bodies();
+                if let (Some(a), Some(b)) = (self.body(ha), self.body(hb)) {
+                    let (pa, pb) = joint.anchors((a.iso.position, a.iso.rotation), (b.iso.position, b.iso.rotation));
+                    draw.line(a.center, pa, debug::COLOR_JOINT);
+                    draw.line(pa, pb, debug::COLOR_JOINT);
+                    draw.line(pb, b.center, debug::COLOR_JOINT);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Inertia of a point mass at `offset`, what moving an inertia tensor away from the centroid adds
+ **/
+fn parallel_axis(offset: Vec3, mass: f32) -> Mat3 {
+    (Mat3::IDENTITY * offset.dot(offset) - Mat3::from_outer_product(offset, offset)) * mass
+}
+
+/**
+ * Inverse of an inertia tensor, scaled to unit size first so small bodies don't fall under
+ * the determinant cutoff of Mat3::inverse and lose their rotation
+ **/
+fn inverse_inertia(inertia: &Mat3) -> Mat3 {
+    let size = inertia.cols[0].x.max(inertia.cols[1].y).max(inertia.cols[2].z);
+    if size <= 0.0 {
+        return Mat3::ZERO;
+    }
+    match (*inertia * (1.0 / size)).inverse() {
+        Some(x) => x * (1.0 / size),
+        None => Mat3::ZERO
+    }
+}
+
+fn draw_shape(draw: &mut dyn PhysicsDebugDraw, shape: &Shape3D, iso: &Isometry3, color: [f32; 4]) {
+    match shape {
+        Shape3D::Sphere { radius } => {
+            for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                debug::circle(draw, iso.position, iso.transform_vector(axis), *radius, color);
+            }
+        },
+        Shape3D::Capsule { half_height, radius } => {
+            let a = iso.transform_point(Vec3::new(0.0, -*half_height, 0.0));
+            let b = iso.transform_point(Vec3::new(0.0, *half_height, 0.0));
+            for end in [a, b] {
+                for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+                    debug::circle(draw, end, iso.transform_vector(axis), *radius, color);
+                }
+            }
+            for side in [Vec3::X, -Vec3::X, Vec3::Z, -Vec3::Z] {
+                let offset = iso.transform_vector(side) * *radius;
+                draw.line(a + offset, b + offset, color);
+            }
+        },
+        Shape3D::Box { .. } | Shape3D::ConvexHull(_) => {
+            if let Some(poly) = Hull3::new(shape, iso).and_then(|x| x.poly) {
+                for &(i, j) in poly.edges() {
+                    draw.line(poly.vertices()[i as usize], poly.vertices()[j as usize], color);
+                }
+            }
+        },
+        Shape3D::Mesh(mesh) => {
+            for t in 0..mesh.indices().len() {
+                let [a, b, c] = mesh.triangle(t).map(|p| iso.transform_point(p));
+                draw.line(a, b, color);
+                draw.line(b, c, color);
+                draw.line(c, a, color);
+            }
+        }
+    }
+}
+
+impl SyncSignal<ContactBeginEvent, EventData> for PhysicsWorld3D {
+    fn connect<ContactBeginEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::ContactBegin));
+    }
+
+    fn emit(&self, event: SyncData<ContactBeginEvent>) -> Result<(), &str> {
+        emit_contact(&self.slots, event.sig(), EventType::ContactBegin)
+    }
+}
+
+impl SyncSignal<ContactEndEvent, EventData> for PhysicsWorld3D {
+    fn connect<ContactEndEvent>(&mut self, slot: Arc<RwLock<dyn SyncSlot<EventData>>>) {
+        self.slots.push((slot, EventType::ContactEnd));
+    }
+
+    fn emit(&self, event: SyncData<ContactEndEvent>) -> Result<(), &str> {
+        emit_contact(&self.slots, event.sig(), EventType::ContactEnd)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::character::CharacterController;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn static_box(world: &mut PhysicsWorld3D, position: Vec3, rotation: Quat, half: Vec3) -> ColliderHandle {
+        let mut body = RigidBody3D::new(BodyType::Static, position);
+        body.set_transform(position, rotation);
+        let body = world.add_body(body);
+        world.add_collider(body, Collider3D::new(Shape3D::cuboid(half.x, half.y, half.z))).unwrap()
+    }
+
+    fn ball(world: &mut PhysicsWorld3D, position: Vec3) -> BodyHandle {
+        let body = world.add_body(RigidBody3D::new(BodyType::Dynamic, position));
+        world.add_collider(body, Collider3D::new(Shape3D::sphere(0.25))).unwrap();
+        body
+    }
+
+    //a small stack knocked over by a ball, enough contacts to make solver order matter
+    fn knocked_stack() -> u64 {
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        static_box(&mut world, Vec3::new(0.0, -0.5, 0.0), Quat::IDENTITY, Vec3::new(20.0, 0.5, 20.0));
+        for level in 0..4 {
+            let offset = if level % 2 == 0 { 0.0 } else { 0.1 };
+            let body = world.add_body(RigidBody3D::new(BodyType::Dynamic, Vec3::new(offset, 0.5 + level as f32 * 1.01, 0.0)));
+            world.add_collider(body, Collider3D::new(Shape3D::cuboid(0.5, 0.5, 0.5))).unwrap();
+        }
+        let ball = ball(&mut world, Vec3::new(-4.0, 2.0, 0.2));
+        world.body_mut(ball).unwrap().set_linear_velocity(Vec3::new(8.0, 0.0, 0.0));
+
+        for _ in 0..240 {
+            world.step(DT);
+        }
+        world.state_checksum()
+    }
+
+    #[test]
+    fn identical_runs_have_the_same_checksum() {
+        assert_eq!(knocked_stack(), knocked_stack());
+    }
+
+    #[test]
+    fn ball_joint_swings_at_a_fixed_radius() {
+        let pivot = Vec3::new(0.0, 5.0, 0.0);
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        let anchor = world.add_body(RigidBody3D::new(BodyType::Static, pivot));
+        let bob = ball(&mut world, pivot + Vec3::new(2.0, 0.0, 0.0));
+        world.add_joint(JointKind::Ball, anchor, bob, pivot).unwrap();
+
+        let mut lowest = f32::INFINITY;
+        for _ in 0..120 {
+            world.step(DT);
+            let position = world.body(bob).unwrap().position();
+            assert!((position.distance(pivot) - 2.0).abs() < 0.05, "bob drifted to {}", position.distance(pivot));
+            lowest = lowest.min(position.y);
+        }
+        //it swung through the bottom of the arc rather than hanging where it started
+        assert!(lowest < pivot.y - 1.9, "lowest point {}", lowest);
+    }
+
+    #[test]
+    fn hinge_joint_only_turns_about_its_axis() {
+        let pivot = Vec3::new(0.0, 5.0, 0.0);
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        let anchor = world.add_body(RigidBody3D::new(BodyType::Static, pivot));
+        let door = world.add_body(RigidBody3D::new(BodyType::Dynamic, pivot + Vec3::new(1.0, 0.0, 0.0)));
+        world.add_collider(door, Collider3D::new(Shape3D::cuboid(1.0, 0.1, 0.5))).unwrap();
+        world.add_joint(JointKind::Hinge { axis: Vec3::Z }, anchor, door, pivot).unwrap();
+        //try to twist it off the hinge
+        world.body_mut(door).unwrap().set_angular_velocity(Vec3::new(3.0, 3.0, 0.0));
+
+        for _ in 0..120 {
+            world.step(DT);
+        }
+        let door = world.body(door).unwrap();
+        assert!(door.rotation().rotate(Vec3::Z).distance(Vec3::Z) < 0.05);
+        assert!(door.position().distance(pivot) - 1.0 < 0.05);
+        assert!(door.position().z.abs() < 0.05);
+        //gravity still swings it about the hinge
+        assert!(door.position().y < pivot.y - 0.5);
+    }
+
+    #[test]
+    fn fixed_joint_carries_the_relative_pose() {
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        let a = ball(&mut world, Vec3::new(0.0, 5.0, 0.0));
+        let b = ball(&mut world, Vec3::new(1.0, 5.0, 0.0));
+        world.add_joint(JointKind::Fixed, a, b, Vec3::new(0.5, 5.0, 0.0)).unwrap();
+        world.body_mut(a).unwrap().set_angular_velocity(Vec3::new(0.0, 4.0, 0.0));
+
+        for _ in 0..60 {
+            world.step(DT);
+        }
+        let (a, b) = (world.body(a).unwrap(), world.body(b).unwrap());
+        let offset = a.rotation().conjugate().rotate(b.position() - a.position());
+        assert!(offset.distance(Vec3::new(1.0, 0.0, 0.0)) < 0.05, "offset became {:?}", offset);
+        assert!(a.rotation().approx_eq(b.rotation(), 0.05) || a.rotation().approx_eq(-b.rotation(), 0.05));
+    }
+
+    #[test]
+    fn distance_joint_only_pulls_past_its_max() {
+        let pivot = Vec3::new(0.0, 5.0, 0.0);
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        let anchor = world.add_body(RigidBody3D::new(BodyType::Static, pivot));
+        //starts with slack in the rope
+        let weight = ball(&mut world, pivot - Vec3::new(0.0, 1.0, 0.0));
+        world.add_joint(JointKind::Distance { min: 0.0, max: 2.0 }, anchor, weight, pivot).unwrap();
+
+        world.step(DT);
+        let falling = world.body(weight).unwrap().linear_velocity().y;
+        assert!((falling - -10.0 * DT).abs() < 1e-3, "slack rope held the weight back, vy {}", falling);
+        for _ in 0..180 {
+            world.step(DT);
+        }
+        let hanging = world.body(weight).unwrap().position().distance(pivot);
+        assert!((hanging - 2.0).abs() < 0.05, "hangs {} from the pivot", hanging);
+    }
+
+    //floor with a ledge starting at x = 1, the character starts standing on the floor
+    fn ledge(height: f32) -> (PhysicsWorld3D, CharacterController) {
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        static_box(&mut world, Vec3::new(0.0, -0.5, 0.0), Quat::IDENTITY, Vec3::new(20.0, 0.5, 20.0));
+        static_box(&mut world, Vec3::new(3.0, height * 0.5, 0.0), Quat::IDENTITY, Vec3::new(2.0, height * 0.5, 2.0));
+        let mut character = CharacterController::new(Vec3::new(0.0, 0.9, 0.0), 0.5, 0.3);
+        character.move_and_slide(&mut world, Vec3::new(0.0, -0.2, 0.0));
+        assert!(character.grounded());
+        (world, character)
+    }
+
+    #[test]
+    fn character_steps_up_low_ledges() {
+        let (mut world, mut character) = ledge(0.2);
+        let start = character.position.y;
+        for _ in 0..20 {
+            character.move_and_slide(&mut world, Vec3::new(0.1, -0.05, 0.0));
+        }
+        assert!(character.grounded());
+        assert!(character.position.x > 1.5, "stopped at {}", character.position.x);
+        assert!((character.position.y - start - 0.2).abs() < 0.05, "ended at height {}", character.position.y - start);
+    }
+
+    #[test]
+    fn character_is_blocked_by_high_ledges() {
+        let (mut world, mut character) = ledge(0.6);
+        let start = character.position.y;
+        for _ in 0..20 {
+            character.move_and_slide(&mut world, Vec3::new(0.1, -0.05, 0.0));
+        }
+        assert!(character.grounded());
+        assert!(character.position.x < 1.0 - character.radius + 0.05, "walked to {}", character.position.x);
+        assert!((character.position.y - start).abs() < 0.05);
+    }
+
+    //a wide ramp rising towards +x, the character is dropped onto it and only pulled by gravity
+    fn ramp(degrees: f32) -> CharacterController {
+        let mut world = PhysicsWorld3D::new(Vec3::new(0.0, -10.0, 0.0));
+        static_box(&mut world, Vec3::new(0.0, -0.5, 0.0), Quat::from_rotation_z(degrees.to_radians()), Vec3::new(10.0, 0.5, 10.0));
+        let mut character = CharacterController::new(Vec3::new(0.0, 1.5, 0.0), 0.5, 0.3);
+        for _ in 0..60 {
+            character.move_and_slide(&mut world, Vec3::new(0.0, -0.1, 0.0));
+        }
+        character
+    }
+
+    #[test]
+    fn character_stands_on_walkable_slopes() {
+        let character = ramp(30.0);
+        assert!(character.grounded());
+        assert!(character.ground_normal().distance(Quat::from_rotation_z(30f32.to_radians()).rotate(Vec3::Y)) < 0.05);
+        assert!(character.position.x.abs() < 0.05, "slid to {}", character.position.x);
+    }
+
+    #[test]
+    fn character_slides_off_steep_slopes() {
+        let character = ramp(60.0);
+        assert!(!character.grounded());
+        assert!(character.position.x < -0.5, "stayed at {}", character.position.x);
+    }
+
+    //solid box and a sensor in front of it along +x, both at the height of the origin
+    fn targets() -> (PhysicsWorld3D, ColliderHandle, ColliderHandle) {
+        let mut world = PhysicsWorld3D::new(Vec3::ZERO);
+        let solid = static_box(&mut world, Vec3::new(3.0, 0.0, 0.0), Quat::IDENTITY, Vec3::new(0.5, 0.5, 0.5));
+        let sensor = world.add_body(RigidBody3D::new(BodyType::Static, Vec3::new(1.5, 0.0, 0.0)));
+        let mut collider = Collider3D::new(Shape3D::cuboid(0.25, 0.25, 0.25));
+        collider.sensor = true;
+        let sensor = world.add_collider(sensor, collider).unwrap();
+        (world, solid, sensor)
+    }
+
+    #[test]
+    fn raycast_passes_sensors_and_stops_at_solids() {
+        let (world, solid, _) = targets();
+        let hit = world.raycast(Vec3::ZERO, Vec3::new(2.0, 0.0, 0.0), 10.0, u32::MAX).unwrap();
+        assert_eq!(hit.collider, solid);
+        assert!((hit.distance - 2.5).abs() < 1e-3);
+        assert!(hit.normal.distance(-Vec3::X) < 1e-3);
+        assert!(world.raycast(Vec3::ZERO, Vec3::X, 2.0, u32::MAX).is_none());
+        assert!(world.raycast(Vec3::ZERO, Vec3::X, 10.0, 0).is_none());
+    }
+
+    #[test]
+    fn overlap_includes_sensors() {
+        let (world, solid, sensor) = targets();
+        let iso = Isometry3::new(Vec3::new(2.2, 0.0, 0.0), Quat::IDENTITY);
+        let mut found = world.overlap(&Shape3D::sphere(0.5), &iso, u32::MAX);
+        found.sort();
+        assert_eq!(found, vec![solid.min(sensor), solid.max(sensor)]);
+        let away = Isometry3::new(Vec3::new(0.0, 3.0, 0.0), Quat::IDENTITY);
+        assert!(world.overlap(&Shape3D::sphere(0.5), &away, u32::MAX).is_empty());
+    }
+
+    #[test]
+    fn sweep_reports_the_first_solid_touch() {
+        let (world, solid, _) = targets();
+        let start = Isometry3::new(Vec3::ZERO, Quat::IDENTITY);
+        let hit = world.shape_cast(&Shape3D::sphere(0.25), &start, Vec3::new(5.0, 0.0, 0.0), u32::MAX).unwrap();
+        assert_eq!(hit.collider, solid);
+        //the sphere's front touches the box face at x = 2.5
+        assert!((hit.fraction * 5.0 - 2.25).abs() < 0.02, "stopped after {}", hit.fraction * 5.0);
+        assert!(hit.normal.distance(-Vec3::X) < 1e-2);
+        assert!(world.shape_cast(&Shape3D::sphere(0.25), &start, Vec3::new(0.0, 5.0, 0.0), u32::MAX).is_none());
+    }
+}
@@ -0,0 +1,214 @@
+use std::cell::{ Cell, RefCell };
+use std::collections::VecDeque;
+use std::sync::{ Arc, Mutex, OnceLock, PoisonError };
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+use std::time::{ Duration, Instant };
+
+//events kept per thread before the oldest are dropped, about 20MB at the default
+const DEFAULT_CAPACITY: usize = 1 << 18;
+
+/**
+ * One finished scope
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEvent {
+    pub name: &'static str,
+    pub detail: Option<String>,
+    //since the profiler was created
+    pub start: Duration,
+    pub duration: Duration,
+    //how many scopes were open around this one on its thread
+    pub depth: u32,
+}
+
+/**
+ * Everything recorded on one thread, events ordered by when they started
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreadProfile {
+    pub id: u32,
+    pub name: String,
+    pub events: Vec<ProfileEvent>,
+}
+
+#[derive(Debug)]
+struct ThreadBuffer {
+    id: u32,
+    name: String,
+    events: VecDeque<ProfileEvent>,
+}
+
+thread_local! {
+    static BUFFER: RefCell<Option<Arc<Mutex<ThreadBuffer>>>> = const { RefCell::new(None) };
+    static DEPTH: Cell<u32> = const { Cell::new(0) };
+}
+
+/**
+ * Collects scope timings from every thread, each thread writes to a buffer of its own so
+ * recording never waits on another thread
+ **/
+#[derive(Debug)]
+pub struct Profiler {
+    epoch: Instant,
+    recording: AtomicBool,
+    capacity: AtomicUsize,
+    dropped: AtomicUsize,
+    threads: Mutex<Vec<Arc<Mutex<ThreadBuffer>>>>,
+    //the timeline GPU timers report to, shown next to the threads
+    gpu: OnceLock<Arc<Mutex<ThreadBuffer>>>,
+}
+
+static PROFILER: OnceLock<Profiler> = OnceLock::new();
+
+impl Profiler {
+    pub fn global() -> &'static Profiler {
+        PROFILER.get_or_init(Profiler::new)
+    }
+
+    fn new() -> Profiler {
+        Profiler {
+            epoch: Instant::now(),
+            recording: AtomicBool::new(true),
+            capacity: AtomicUsize::new(DEFAULT_CAPACITY),
+            dropped: AtomicUsize::new(0),
+            threads: Mutex::new(Vec::new()),
+            gpu: OnceLock::new(),
+        }
+    }
+
+    pub fn recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Scopes opened while not recording are ignored, ones already open still finish
+     **/
+    pub fn set_recording(&self, recording: bool) {
+        self.recording.store(recording, Ordering::Relaxed);
+    }
+
+    /**
+     * How many events each thread keeps, older ones are dropped to make room
+     **/
+    pub fn set_capacity(&self, events: usize) {
+        self.capacity.store(events.max(1), Ordering::Relaxed);
+    }
+
+    /**
+     * Events dropped for being over capacity since the last clear
+     **/
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Time since the profiler was created, what event start times are measured from
+     **/
+    pub fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+
+    /**
+     * Adds a finished event to the calling thread's buffer
+     **/
+    pub fn record(&self, event: ProfileEvent) {
+        let buffer = BUFFER.with(|x| {
+            Arc::clone(x.borrow_mut().get_or_insert_with(|| self.register(std::thread::current().name())))
+        });
+        self.push(&buffer, event);
+    }
+
+    /**
+     * Adds a pass measured on the GPU, `start` is when the CPU recorded it
+     **/
+    pub fn record_gpu(&self, event: ProfileEvent) {
+        let buffer = self.gpu.get_or_init(|| self.register(Some("GPU")));
+        self.push(buffer, event);
+    }
+
+    fn push(&self, buffer: &Mutex<ThreadBuffer>, event: ProfileEvent) {
+        let mut buffer = buffer.lock().unwrap_or_else(PoisonError::into_inner);
+        if buffer.events.len() >= self.capacity.load(Ordering::Relaxed) {
+            buffer.events.pop_front();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        buffer.events.push_back(event);
+    }
+
+    fn register(&self, name: Option<&str>) -> Arc<Mutex<ThreadBuffer>> {
+        let mut threads = self.threads.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = threads.len() as u32;
+        let name = match name {
+            Some(x) => x.to_string(),
+            None => format!("thread {}", id)
+        };
+        let buffer = Arc::new(Mutex::new(ThreadBuffer { id, name, events: VecDeque::new() }));
+        threads.push(Arc::clone(&buffer));
+        buffer
+    }
+
+    /**
+     * Throws away everything recorded so far, threads keep their ids
+     **/
+    pub fn clear(&self) {
+        for x in self.threads.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            x.lock().unwrap_or_else(PoisonError::into_inner).events.clear();
+        }
+        self.dropped.store(0, Ordering::Relaxed);
+    }
+
+    /**
+     * A copy of what every thread and the GPU recorded, in the order they first recorded something
+     **/
+    pub fn threads(&self) -> Vec<ThreadProfile> {
+        let threads: Vec<Arc<Mutex<ThreadBuffer>>> = self.threads.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        threads.iter().map(|x| {
+            let buffer = x.lock().unwrap_or_else(PoisonError::into_inner);
+            let mut events: Vec<ProfileEvent> = buffer.events.iter().cloned().collect();
+            //scopes are recorded when they end, so parents come after their children
+            events.sort_by(|a, b| a.start.cmp(&b.start).then(a.depth.cmp(&b.depth)));
+            ThreadProfile { id: buffer.id, name: buffer.name.clone(), events }
+        }).collect()
+    }
+}
+
+/**
+ * Records the time from its creation until it's dropped, made by profile_scope!
+ **/
+#[derive(Debug)]
+#[must_use = "the scope ends as soon as the guard is dropped"]
+pub struct ScopeGuard {
+    name: &'static str,
+    detail: Option<String>,
+    //None when the profiler wasn't recording
+    start: Option<(Duration, u32)>,
+}
+
+impl ScopeGuard {
+    pub fn new(name: &'static str, detail: Option<String>) -> ScopeGuard {
+        let profiler = Profiler::global();
+        let start = if profiler.recording() {
+            let depth = DEPTH.with(|x| {
+                let depth = x.get();
+                x.set(depth + 1);
+                depth
+            });
+            Some((profiler.now(), depth))
+        }
+        else {
+            None
+        };
+        ScopeGuard { name, detail, start }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        if let Some((start, depth)) = self.start {
+            let profiler = Profiler::global();
+            let duration = profiler.now().saturating_sub(start);
+            DEPTH.with(|x| x.set(depth));
+            profiler.record(ProfileEvent { name: self.name, detail: self.detail.take(), start, duration, depth });
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::os::raw::{ c_char, c_void };
+use std::sync::Arc;
+use std::time::Duration;
+
+use gl::types::{ GLint, GLuint, GLuint64 };
+use vulkano::VulkanObject;
+use vulkano::command_buffer::pool::standard::StandardCommandPoolAlloc;
+use vulkano::command_buffer::submit::SubmitCommandBufferBuilder;
+use vulkano::command_buffer::sys::{ Flags, Kind, UnsafeCommandBuffer, UnsafeCommandBufferBuilder };
+use vulkano::device::{ Device, Queue };
+use vulkano::instance::loader::auto_loader;
+use vulkano::query::{ QueryType, UnsafeQueryPool };
+use vulkano::sync::{ Fence, PipelineStages };
+
+use crate::profiling::ProfileError;
+use crate::profiling::cpu::{ ProfileEvent, Profiler };
+
+//frames a timer keeps queries for, results are read back at most this many frames late
+const FRAMES: usize = 4;
+const DEFAULT_VULKAN_PASSES: u32 = 32;
+
+/**
+ * A pass the GPU finished
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct GpuPass {
+    pub name: &'static str,
+    //when the CPU recorded the pass, on the profiler's clock
+    pub start: Duration,
+    //how long the GPU spent on it
+    pub time: Duration,
+    pub depth: u32,
+}
+
+/**
+ * Hands a finished frame's passes to the profiler's GPU timeline
+ **/
+fn report(passes: &[GpuPass]) {
+    let profiler = Profiler::global();
+    for x in passes {
+        profiler.record_gpu(ProfileEvent { name: x.name, detail: None, start: x.start, duration: x.time, depth: x.depth });
+    }
+}
+
+fn frame_time(passes: &[GpuPass]) -> Option<Duration> {
+    if passes.is_empty() {
+        return None;
+    }
+    Some(passes.iter().filter(|x| x.depth == 0).map(|x| x.time).sum())
+}
+
+#[derive(Debug, Default)]
+struct GlFrame {
+    //name, when the CPU began it and the query timing it
+    passes: Vec<(&'static str, Duration, GLuint)>,
+}
+
+/**
+ * Times passes on the GPU with GL_TIME_ELAPSED queries
+ * Those can't nest, so beginning a pass ends the one before it. Results are read back once the
+ * GPU has them, without waiting, and if it falls more than a few frames behind the oldest
+ * frame's results are dropped instead
+ * Create and use it only on the thread the context is current on
+ **/
+#[derive(Debug)]
+pub struct GlGpuTimer {
+    enabled: bool,
+    frames: Vec<GlFrame>,
+    current: usize,
+    open: bool,
+    spare: Vec<GLuint>,
+    latest: Vec<GpuPass>,
+    dropped: usize,
+}
+
+impl GlGpuTimer {
+    /**
+     * Needs the OpenGL symbols loaded
+     **/
+    pub fn new() -> GlGpuTimer {
+        GlGpuTimer {
+            enabled: true,
+            frames: (0..FRAMES).map(|_| GlFrame::default()).collect(),
+            current: 0,
+            open: false,
+            spare: Vec::new(),
+            latest: Vec::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /**
+     * While disabled begin and end make no OpenGL calls, passes already measured still come in
+     **/
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /**
+     * Starts a new frame, first collecting every earlier frame the GPU has finished
+     **/
+    pub fn begin_frame(&mut self) {
+        self.end();
+        self.collect();
+        self.current = (self.current + 1) % FRAMES;
+        let stale = mem::take(&mut self.frames[self.current].passes);
+        if !stale.is_empty() {
+            self.dropped += stale.len();
+            self.spare.extend(stale.into_iter().map(|x| x.2));
+        }
+    }
+
+    pub fn begin(&mut self, name: &'static str) {
+        self.end();
+        if !self.enabled || !Profiler::global().recording() {
+            return;
+        }
+        let query = match self.spare.pop() {
+            Some(x) => x,
+            None => {
+                let mut x = 0;
+                unsafe {
+                    gl::GenQueries(1, &mut x);
+                }
+                x
+            }
+        };
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query);
+        }
+        self.frames[self.current].passes.push((name, Profiler::global().now(), query));
+        self.open = true;
+    }
+
+    pub fn end(&mut self) {
+        if self.open {
+            unsafe {
+                gl::EndQuery(gl::TIME_ELAPSED);
+            }
+            self.open = false;
+        }
+    }
+
+    /**
+     * Passes of the newest frame the GPU finished
+     **/
+    pub fn last_frame(&self) -> &[GpuPass] {
+        &self.latest
+    }
+
+    /**
+     * GPU time of the newest finished frame, None until one has been measured
+     **/
+    pub fn last_frame_time(&self) -> Option<Duration> {
+        frame_time(&self.latest)
+    }
+
+    /**
+     * Passes thrown away because the GPU was too far behind
+     **/
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn collect(&mut self) {
+        //oldest first, a frame the GPU hasn't finished means the newer ones aren't either
+        for i in 1..FRAMES {
+            let index = (self.current + i) % FRAMES;
+            let frame = &mut self.frames[index];
+            if frame.passes.is_empty() {
+                continue;
+            }
+            let ready = frame.passes.iter().all(|x| {
+                let mut available: GLint = 0;
+                unsafe {
+                    gl::GetQueryObjectiv(x.2, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                }
+                available != 0
+            });
+            if !ready {
+                break;
+            }
+            self.latest.clear();
+            for (name, start, query) in frame.passes.drain(..) {
+                let mut nanos: GLuint64 = 0;
+                unsafe {
+                    gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanos);
+                }
+                self.latest.push(GpuPass { name, start, time: Duration::from_nanos(nanos), depth: 0 });
+                self.spare.push(query);
+            }
+            report(&self.latest);
+        }
+    }
+}
+
+impl Default for GlGpuTimer {
+    fn default() -> GlGpuTimer {
+        GlGpuTimer::new()
+    }
+}
+
+impl Drop for GlGpuTimer {
+    fn drop(&mut self) {
+        self.end();
+        let queries: Vec<GLuint> = self.frames.iter().flat_map(|x| x.passes.iter().map(|p| p.2)).chain(self.spare.drain(..)).collect();
+        if !queries.is_empty() {
+            unsafe {
+                gl::DeleteQueries(queries.len() as i32, queries.as_ptr());
+            }
+        }
+    }
+}
+
+//vulkano doesn't wrap reading query results, so vkGetQueryPoolResults is loaded by hand
+type GetDeviceProcAddr = unsafe extern "system" fn(usize, *const c_char) -> Option<unsafe extern "system" fn()>;
+type GetQueryPoolResults = unsafe extern "system" fn(usize, u64, u32, u32, usize, *mut c_void, u64, u32) -> u32;
+
+const QUERY_RESULT_64_BIT: u32 = 0x1;
+const QUERY_RESULT_WITH_AVAILABILITY_BIT: u32 = 0x4;
+
+#[derive(Debug)]
+struct VulkanPass {
+    name: &'static str,
+    start: Duration,
+    depth: u32,
+    ended: bool,
+}
+
+/**
+ * Times passes on the GPU with a timestamp query pool, passes can nest
+ * Command buffers are recorded through vulkano's UnsafeCommandBufferBuilder, since the
+ * automatic builder can't write timestamps. Results are read back without waiting, a few
+ * frames late
+ **/
+pub struct VulkanGpuTimer {
+    device: Arc<Device>,
+    pool: UnsafeQueryPool,
+    get_results: GetQueryPoolResults,
+    //nanoseconds per timestamp tick
+    period: f64,
+    //the bits of a timestamp that count
+    mask: u64,
+    max_passes: u32,
+    enabled: bool,
+    frames: Vec<Vec<VulkanPass>>,
+    current: usize,
+    //passes begun and not ended, None for ones that weren't recorded
+    open: Vec<Option<usize>>,
+    latest: Vec<GpuPass>,
+    dropped: usize,
+    //command buffers submit_frame handed to the GPU, kept until their fence signals
+    submitted: VecDeque<(UnsafeCommandBuffer<StandardCommandPoolAlloc>, Fence)>,
+}
+
+impl std::fmt::Debug for VulkanGpuTimer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "VulkanGpuTimer {{ max_passes: {}, period: {}ns }}", self.max_passes, self.period)
+    }
+}
+
+impl VulkanGpuTimer {
+    /**
+     * Room for `max_passes` passes a frame, 32 by default
+     * Fails when the graphics queue can't write timestamps
+     **/
+    pub fn new(device: Arc<Device>, max_passes: Option<u32>) -> Result<VulkanGpuTimer, ProfileError> {
+        let max_passes = max_passes.unwrap_or(DEFAULT_VULKAN_PASSES).max(1);
+        let physical = device.physical_device();
+        let bits = physical.queue_families().find(|x| x.supports_graphics()).and_then(|x| x.timestamp_valid_bits());
+        let bits = match bits {
+            Some(x) if x > 0 => x,
+            _ => return Err(ProfileError::Gpu("the graphics queue doesn't support timestamps".to_string()))
+        };
+        let period = physical.limits().timestamp_period() as f64;
+
+        let loader = auto_loader().map_err(|e| ProfileError::Gpu(e.to_string()))?;
+        let get_device_proc_addr = loader.get_instance_proc_addr(device.instance().internal_object(), b"vkGetDeviceProcAddr\0".as_ptr() as *const c_char);
+        // SAFETY: the loader returned vkGetDeviceProcAddr for this instance, whose signature is
+        // GetDeviceProcAddr, and the device handle and name are valid for the call. What it returns
+        // for vkGetQueryPoolResults has that function's signature, GetQueryPoolResults
+        let get_results = unsafe {
+            let get_device_proc_addr: GetDeviceProcAddr = mem::transmute(get_device_proc_addr);
+            match get_device_proc_addr(device.internal_object(), b"vkGetQueryPoolResults\0".as_ptr() as *const c_char) {
+                Some(x) => mem::transmute::<unsafe extern "system" fn(), GetQueryPoolResults>(x),
+                None => return Err(ProfileError::Gpu("vkGetQueryPoolResults couldn't be loaded".to_string()))
+            }
+        };
+        let pool = UnsafeQueryPool::new(Arc::clone(&device), QueryType::Timestamp, FRAMES as u32 * max_passes * 2)?;
+
+        Ok(VulkanGpuTimer {
+            device,
+            pool,
+            get_results,
+            period,
+            mask: if bits >= 64 { u64::MAX } else { (1 << bits) - 1 },
+            max_passes,
+            enabled: true,
+            frames: (0..FRAMES).map(|_| Vec::new()).collect(),
+            current: 0,
+            open: Vec::new(),
+            latest: Vec::new(),
+            dropped: 0,
+            submitted: VecDeque::new(),
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /**
+     * Starts a frame and times one `name` pass in a command buffer of its own, submitted to `queue`
+     * For loops that record nothing else the timestamps could go in. Nothing waits on the GPU
+     * unless it's a whole ring of frames behind
+     **/
+    pub fn submit_frame(&mut self, queue: &Arc<Queue>, name: &'static str) -> Result<(), ProfileError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        //command buffers the GPU is done with can go, the oldest is waited on if every frame's still queued
+        while let Some((_, fence)) = self.submitted.front() {
+            let done = fence.ready().map_err(|e| ProfileError::Gpu(e.to_string()))?;
+            if !done && self.submitted.len() < FRAMES - 1 {
+                break;
+            }
+            if !done {
+                fence.wait(None).map_err(|e| ProfileError::Gpu(e.to_string()))?;
+            }
+            self.submitted.pop_front();
+        }
+
+        let pool = Device::standard_command_pool(&self.device, queue.family());
+        let fence = Fence::alloc(Arc::clone(&self.device)).map_err(|e| ProfileError::Gpu(e.to_string()))?;
+        // SAFETY: the command buffer is recorded outside of a render pass and only resets and writes
+        // this timer's queries. It's submitted once, and it and the pool it came from are kept alive
+        // in `submitted` until its fence signals
+        let cmd = unsafe {
+            let mut cmd = UnsafeCommandBufferBuilder::new(&pool, Kind::primary(), Flags::OneTimeSubmit)
+                .map_err(|e| ProfileError::Gpu(e.to_string()))?;
+            self.begin_frame(&mut cmd);
+            self.begin(&mut cmd, name);
+            self.end(&mut cmd);
+            let cmd = cmd.build().map_err(|e| ProfileError::Gpu(e.to_string()))?;
+            let mut submit = SubmitCommandBufferBuilder::new();
+            submit.add_command_buffer(&cmd);
+            submit.set_fence_signal(&fence);
+            submit.submit(queue).map_err(|e| ProfileError::Gpu(e.to_string()))?;
+            cmd
+        };
+        self.submitted.push_back((cmd, fence));
+        Ok(())
+    }
+
+    fn first_query(&self, frame: usize) -> u32 {
+        frame as u32 * self.max_passes * 2
+    }
+
+    /**
+     * Starts a new frame, collecting every earlier frame the GPU has finished and resetting this
+     * frame's queries
+     * # Safety
+     * `cmd` has to be recording outside of a render pass, and be submitted before any command
+     * buffer holding this frame's passes
+     **/
+    pub unsafe fn begin_frame<P>(&mut self, cmd: &mut UnsafeCommandBufferBuilder<P>) {
+        //passes left open can't be finished in a new frame
+        self.open.clear();
+        self.collect();
+        self.current = (self.current + 1) % FRAMES;
+        let stale = mem::take(&mut self.frames[self.current]);
+        self.dropped += stale.len();
+        let first = self.first_query(self.current);
+        if let Some(range) = self.pool.queries_range(first, self.max_passes * 2) {
+            cmd.reset_query_pool(range);
+        }
+    }
+
+    /**
+     * # Safety
+     * `cmd` has to be recording, and submitted after the one given to begin_frame
+     **/
+    pub unsafe fn begin<P>(&mut self, cmd: &mut UnsafeCommandBufferBuilder<P>, name: &'static str) {
+        let frame = &mut self.frames[self.current];
+        if !self.enabled || !Profiler::global().recording() || frame.len() as u32 >= self.max_passes {
+            if self.enabled && frame.len() as u32 >= self.max_passes {
+                self.dropped += 1;
+            }
+            self.open.push(None);
+            return;
+        }
+        let index = frame.len();
+        frame.push(VulkanPass { name, start: Profiler::global().now(), depth: self.open.len() as u32, ended: false });
+        self.open.push(Some(index));
+        let first = self.first_query(self.current);
+        if let Some(query) = self.pool.query(first + index as u32 * 2) {
+            cmd.write_timestamp(query, PipelineStages { top_of_pipe: true, ..PipelineStages::none() });
+        }
+    }
+
+    /**
+     * Ends the innermost open pass
+     * # Safety
+     * Same as begin
+     **/
+    pub unsafe fn end<P>(&mut self, cmd: &mut UnsafeCommandBufferBuilder<P>) {
+        let index = match self.open.pop() {
+            Some(Some(x)) => x,
+            _ => return
+        };
+        self.frames[self.current][index].ended = true;
+        let first = self.first_query(self.current);
+        if let Some(query) = self.pool.query(first + index as u32 * 2 + 1) {
+            cmd.write_timestamp(query, PipelineStages { bottom_of_pipe: true, ..PipelineStages::none() });
+        }
+    }
+
+    pub fn last_frame(&self) -> &[GpuPass] {
+        &self.latest
+    }
+
+    pub fn last_frame_time(&self) -> Option<Duration> {
+        frame_time(&self.latest)
+    }
+
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn collect(&mut self) {
+        for i in 1..FRAMES {
+            let index = (self.current + i) % FRAMES;
+            if self.frames[index].is_empty() {
+                continue;
+            }
+            let count = self.frames[index].len() as u32 * 2;
+            //each query gives its timestamp then whether it's available
+            let mut data = vec![0u64; count as usize * 2];
+            //NOT_READY still fills in what is available, so the result code isn't needed
+            // SAFETY: the queries are inside the pool, which belongs to the device, and `data` has room
+            // for `count` results of two u64s each at the stride given
+            unsafe {
+                (self.get_results)(self.device.internal_object(), self.pool.internal_object(), self.first_query(index), count,
+                    data.len() * mem::size_of::<u64>(), data.as_mut_ptr() as *mut c_void, 2 * mem::size_of::<u64>() as u64,
+                    QUERY_RESULT_64_BIT | QUERY_RESULT_WITH_AVAILABILITY_BIT);
+            }
+            let frame = &self.frames[index];
+            let ready = frame.iter().enumerate().filter(|(_, x)| x.ended).all(|(i, _)| data[i * 4 + 1] != 0 && data[i * 4 + 3] != 0);
+            if !ready {
+                break;
+            }
+            self.latest.clear();
+            for (i, pass) in mem::take(&mut self.frames[index]).into_iter().enumerate().filter(|(_, x)| x.ended) {
+                let ticks = data[i * 4 + 2].wrapping_sub(data[i * 4]) & self.mask;
+                let time = Duration::from_nanos((ticks as f64 * self.period) as u64);
+                self.latest.push(GpuPass { name: pass.name, start: pass.start, time, depth: pass.depth });
+            }
+            report(&self.latest);
+        }
+    }
+}
+
+impl Drop for VulkanGpuTimer {
+    fn drop(&mut self) {
+        //the query pool and command buffers can't go while the GPU still uses them
+        for (_, fence) in self.submitted.drain(..) {
+            if let Err(e) = fence.wait(None) {
+                error!("Couldn't wait for a GPU timing submission: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+pub mod cpu;
+pub mod trace;
+pub mod gpu;
+
+pub use self::cpu::{ ProfileEvent, Profiler, ScopeGuard, ThreadProfile };
+pub use self::gpu::{ GlGpuTimer, GpuPass, VulkanGpuTimer };
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use vulkano::query::QueryPoolCreationError;
+
+//where run writes the trace when the application closes
+pub const TRACE_FILE: &str = "magnus_trace.json";
+
+/**
+ * Times the rest of the enclosing block on the global Profiler
+ * An optional second argument is shown with the scope, it's only evaluated when profiling
+ * Without the `profiling` feature this expands to nothing
+ * usage: profile_scope!("physics step"); or profile_scope!("layer update", layer.debug_name());
+ **/
+#[cfg(feature = "profiling")]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiling::ScopeGuard::new($name, None);
+    };
+    ($name:expr, $detail:expr) => {
+        let _profile_scope = $crate::profiling::ScopeGuard::new($name, Some(::std::string::ToString::to_string(&$detail)));
+    };
+}
+
+#[cfg(not(feature = "profiling"))]
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {};
+    //the closure is never called, it keeps variables only used for the detail from warning
+    ($name:expr, $detail:expr) => {
+        let _ = || { let _ = &$detail; };
+    };
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    QueryPool(QueryPoolCreationError),
+    /**
+     * The GPU or driver can't do timing queries
+     **/
+    Gpu(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Io(e) => write!(f, "Profiler IO error: {}", e),
+            ProfileError::Json(e) => write!(f, "Profiler trace error: {}", e),
+            ProfileError::QueryPool(e) => write!(f, "GPU timer query pool error: {}", e),
+            ProfileError::Gpu(msg) => write!(f, "GPU timing unavailable: {}", msg),
+        }
+    }
+}
+
+impl Error for ProfileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProfileError::Io(e) => Some(e),
+            ProfileError::Json(e) => Some(e),
+            ProfileError::QueryPool(e) => Some(e),
+            ProfileError::Gpu(_) => None
+        }
+    }
+}
+
+impl From<io::Error> for ProfileError {
+    fn from(e: io::Error) -> ProfileError {
+        ProfileError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ProfileError {
+    fn from(e: serde_json::Error) -> ProfileError {
+        ProfileError::Json(e)
+    }
+}
+
+impl From<QueryPoolCreationError> for ProfileError {
+    fn from(e: QueryPoolCreationError) -> ProfileError {
+        ProfileError::QueryPool(e)
+    }
+}
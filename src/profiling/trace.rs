@@ -0,0 +1,60 @@
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::Path;
+
+use serde_json::{ json, Value };
+
+use crate::profiling::ProfileError;
+use crate::profiling::cpu::{ Profiler, ThreadProfile };
+
+fn micros(x: std::time::Duration) -> f64 {
+    x.as_secs_f64() * 1_000_000.0
+}
+
+/**
+ * Timelines in the JSON format chrome://tracing and Perfetto open, one row per timeline
+ **/
+pub fn chrome_trace(timelines: &[ThreadProfile]) -> Value {
+    let mut events = Vec::new();
+    for timeline in timelines {
+        events.push(json!({
+            "name": "thread_name",
+            "ph": "M",
+            "pid": 1,
+            "tid": timeline.id,
+            "args": { "name": timeline.name }
+        }));
+        for x in &timeline.events {
+            let mut event = json!({
+                "name": x.name,
+                "cat": "magnus",
+                "ph": "X",
+                "ts": micros(x.start),
+                "dur": micros(x.duration),
+                "pid": 1,
+                "tid": timeline.id
+            });
+            if let Some(detail) = &x.detail {
+                event["args"] = json!({ "detail": detail });
+            }
+            events.push(event);
+        }
+    }
+    json!({ "traceEvents": events, "displayTimeUnit": "ms" })
+}
+
+impl Profiler {
+    /**
+     * Everything recorded so far as a Chrome trace
+     **/
+    pub fn chrome_trace(&self) -> Value {
+        chrome_trace(&self.threads())
+    }
+
+    pub fn write_chrome_trace<P: AsRef<Path>>(&self, path: P) -> Result<(), ProfileError> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, &self.chrome_trace())?;
+        out.flush()?;
+        Ok(())
+    }
+}
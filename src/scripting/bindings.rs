@@ -0,0 +1,183 @@
+use rhai::{ Dynamic, Engine, Map, FLOAT, INT };
+
+use crate::events::event::{ Event, EventData, EventType };
+use crate::math::{ Quat, Vec2, Vec3 };
+use crate::scripting::host::{ ScriptHost, SpawnRequest };
+
+/**
+ * Registers the math types, input queries and spawning functions scripts can use
+ **/
+pub fn register(engine: &mut Engine) {
+    register_vec2(engine);
+    register_vec3(engine);
+    register_quat(engine);
+    register_input(engine);
+    register_spawning(engine);
+}
+
+fn register_vec2(engine: &mut Engine) {
+    engine.register_type_with_name::<Vec2>("Vec2")
+        .register_fn("vec2", Vec2::new)
+        .register_get_set("x", |v: &mut Vec2| v.x, |v: &mut Vec2, x: FLOAT| v.x = x)
+        .register_get_set("y", |v: &mut Vec2| v.y, |v: &mut Vec2, y: FLOAT| v.y = y)
+        .register_fn("+", |a: Vec2, b: Vec2| a + b)
+        .register_fn("-", |a: Vec2, b: Vec2| a - b)
+        .register_fn("-", |a: Vec2| -a)
+        .register_fn("*", |a: Vec2, s: FLOAT| a * s)
+        .register_fn("*", |s: FLOAT, a: Vec2| a * s)
+        .register_fn("/", |a: Vec2, s: FLOAT| a / s)
+        .register_fn("==", |a: Vec2, b: Vec2| a == b)
+        .register_fn("length", |v: &mut Vec2| v.length())
+        .register_fn("normalize", |v: &mut Vec2| v.normalize())
+        .register_fn("dot", |a: &mut Vec2, b: Vec2| a.dot(b))
+        .register_fn("to_string", |v: &mut Vec2| format!("({}, {})", v.x, v.y))
+        .register_fn("to_debug", |v: &mut Vec2| format!("vec2({}, {})", v.x, v.y));
+}
+
+fn register_vec3(engine: &mut Engine) {
+    engine.register_type_with_name::<Vec3>("Vec3")
+        .register_fn("vec3", Vec3::new)
+        .register_get_set("x", |v: &mut Vec3| v.x, |v: &mut Vec3, x: FLOAT| v.x = x)
+        .register_get_set("y", |v: &mut Vec3| v.y, |v: &mut Vec3, y: FLOAT| v.y = y)
+        .register_get_set("z", |v: &mut Vec3| v.z, |v: &mut Vec3, z: FLOAT| v.z = z)
+        .register_fn("+", |a: Vec3, b: Vec3| a + b)
+        .register_fn("-", |a: Vec3, b: Vec3| a - b)
+        .register_fn("-", |a: Vec3| -a)
+        .register_fn("*", |a: Vec3, s: FLOAT| a * s)
+        .register_fn("*", |s: FLOAT, a: Vec3| a * s)
+        .register_fn("/", |a: Vec3, s: FLOAT| a / s)
+        .register_fn("==", |a: Vec3, b: Vec3| a == b)
+        .register_fn("length", |v: &mut Vec3| v.length())
+        .register_fn("normalize", |v: &mut Vec3| v.normalize())
+        .register_fn("dot", |a: &mut Vec3, b: Vec3| a.dot(b))
+        .register_fn("cross", |a: &mut Vec3, b: Vec3| a.cross(b))
+        .register_fn("lerp", |a: &mut Vec3, b: Vec3, t: FLOAT| a.lerp(b, t))
+        .register_fn("to_string", |v: &mut Vec3| format!("({}, {}, {})", v.x, v.y, v.z))
+        .register_fn("to_debug", |v: &mut Vec3| format!("vec3({}, {}, {})", v.x, v.y, v.z));
+}
+
+fn register_quat(engine: &mut Engine) {
+    engine.register_type_with_name::<Quat>("Quat")
+        .register_fn("quat_identity", || Quat::IDENTITY)
+        .register_fn("quat_euler", Quat::from_euler)
+        .register_fn("quat_axis_angle", Quat::from_axis_angle)
+        .register_fn("*", |a: Quat, b: Quat| a * b)
+        .register_fn("*", |q: Quat, v: Vec3| q.rotate(v))
+        .register_fn("normalize", |q: &mut Quat| q.normalize())
+        .register_fn("inverse", |q: &mut Quat| q.inverse())
+        .register_fn("slerp", |a: &mut Quat, b: Quat, t: FLOAT| a.slerp(b, t))
+        .register_fn("to_string", |q: &mut Quat| format!("({}, {}, {}, {})", q.x, q.y, q.z, q.w))
+        .register_fn("to_debug", |q: &mut Quat| format!("quat({}, {}, {}, {})", q.x, q.y, q.z, q.w));
+}
+
+/**
+ * Glfw keycode for a key name, so scripts can say key_down("W") instead of key_down(87)
+ **/
+pub fn key_code(name: &str) -> Option<i32> {
+    let upper = name.to_ascii_uppercase();
+    let mut chars = upper.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        if c.is_ascii_alphanumeric() {
+            return Some(c as i32);
+        }
+    }
+    let code = match upper.as_str() {
+        "SPACE" => 32,
+        "ESCAPE" => 256,
+        "ENTER" => 257,
+        "TAB" => 258,
+        "BACKSPACE" => 259,
+        "RIGHT" => 262,
+        "LEFT" => 263,
+        "DOWN" => 264,
+        "UP" => 265,
+        "LEFT_SHIFT" | "SHIFT" => 340,
+        "LEFT_CONTROL" | "CONTROL" => 341,
+        "LEFT_ALT" | "ALT" => 342,
+        "RIGHT_SHIFT" => 344,
+        "RIGHT_CONTROL" => 345,
+        "RIGHT_ALT" => 346,
+        _ => return None
+    };
+    Some(code)
+}
+
+fn register_input(engine: &mut Engine) {
+    fn key_down(key: i32) -> bool {
+        match ScriptHost::global().input().read() {
+            Ok(x) => x.key_down(key),
+            _ => false
+        }
+    }
+
+    engine.register_fn("key_down", |key: INT| key_down(key as i32))
+        .register_fn("key_down", |name: &str| key_code(name).is_some_and(key_down))
+        .register_fn("mouse_down", |button: INT| match ScriptHost::global().input().read() {
+            Ok(x) => x.mouse_button_down(button as i32),
+            _ => false
+        })
+        .register_fn("mouse_position", || match ScriptHost::global().input().read() {
+            Ok(x) => x.mouse_position(),
+            _ => Vec2::ZERO
+        });
+}
+
+fn register_spawning(engine: &mut Engine) {
+    fn spawn(name: &str, prefab: Option<&str>, position: Vec3) {
+        ScriptHost::global().request_spawn(SpawnRequest {
+            name: name.to_string(),
+            prefab: prefab.map(|x| x.to_string()),
+            position,
+            layer: ScriptHost::global().layer(),
+        });
+    }
+
+    engine.register_fn("spawn_entity", |name: &str| spawn(name, None, Vec3::ZERO))
+        .register_fn("spawn_entity", |name: &str, position: Vec3| spawn(name, None, position))
+        .register_fn("spawn_prefab", |path: &str, name: &str| spawn(name, Some(path), Vec3::ZERO))
+        .register_fn("spawn_prefab", |path: &str, name: &str, position: Vec3| spawn(name, Some(path), position));
+}
+
+/**
+ * An event as a script sees it, a map with a `kind` and fields named after the event's data
+ **/
+pub fn event_map(e: &dyn Event) -> Map {
+    let mut map = Map::new();
+    let kind = e.get_event_type();
+    map.insert("kind".into(), kind.to_string().into());
+    map.insert("message".into(), e.get_msg().clone().into());
+    let mut set = |name: &str, value: Dynamic| {
+        map.insert(name.into(), value);
+    };
+    match e.get_data() {
+        Some(EventData::I32p(a, b, _)) => {
+            let first = match kind {
+                EventType::MouseButtonPressed | EventType::MouseButtonReleased => "button",
+                _ => "key"
+            };
+            set(first, (*a as INT).into());
+            set("mods", (*b as INT).into());
+        },
+        Some(EventData::U32p(c, _, _)) if kind == EventType::TextInput => {
+            set("char", std::char::from_u32(*c).map_or_else(String::new, |x| x.to_string()).into());
+        },
+        Some(EventData::U32p(a, b, _)) => {
+            set("x", (*a as INT).into());
+            set("y", (*b as INT).into());
+        },
+        Some(EventData::F32p(x, y, _)) => {
+            set("x", (*x as FLOAT).into());
+            set("y", (*y as FLOAT).into());
+        },
+        Some(EventData::U64p(a, b, _)) => {
+            //collider handles, passed as their bit pattern
+            set("a", (*a as INT).into());
+            set("b", (*b as INT).into());
+        },
+        Some(EventData::Bool(x, _)) => set("value", (*x).into()),
+        Some(EventData::StringD(x, _)) => set("value", x.clone().into()),
+        Some(EventData::PathBufD(x, _)) => set("path", x.to_string_lossy().into_owned().into()),
+        _ => {}
+    }
+    map
+}
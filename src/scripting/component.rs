@@ -0,0 +1,140 @@
+use std::any::Any;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::core::component::{ to_value, Component, ComponentKind, Value, ValueError };
+use crate::core::object::Object;
+use crate::core::scene::AssetRef;
+use crate::core::transform::Transform;
+use crate::events::event::Event;
+use crate::scripting::script::{ Script, ScriptTarget };
+
+/**
+ * Runs a script file on the Entity it's attached to, only the path is saved to scenes
+ * The script is loaded the first time the entity updates
+ **/
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScriptComponent {
+    path: String,
+    #[serde(skip)]
+    script: Option<Script>,
+}
+
+impl ScriptComponent {
+    pub fn new(path: &str) -> ScriptComponent {
+        ScriptComponent { path: path.to_string(), script: None }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /**
+     * The running script, None until the first update
+     **/
+    pub fn script(&self) -> Option<&Script> {
+        self.script.as_ref()
+    }
+
+    pub fn script_mut(&mut self) -> &mut Script {
+        let path = &self.path;
+        self.script.get_or_insert_with(|| Script::watch(path))
+    }
+}
+
+impl ComponentKind for ScriptComponent {
+    const KIND: &'static str = "script";
+
+    const VERSION: u32 = 1;
+}
+
+impl Component for ScriptComponent {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn version(&self) -> u32 {
+        Self::VERSION
+    }
+
+    fn save(&self) -> Result<Value, ValueError> {
+        to_value(self)
+    }
+
+    fn assets(&self) -> Vec<AssetRef> {
+        vec![AssetRef::new("script", &self.path)]
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/**
+ * An Object driven entirely by a script, for things that don't need to be an Entity
+ * It isn't saved to scenes
+ **/
+#[derive(Debug)]
+pub struct ScriptObject {
+    id: u32,
+    name: String,
+    transform: Transform,
+    script: Script,
+}
+
+impl ScriptObject {
+    pub fn new(id: u32, name: String, script: Script) -> ScriptObject {
+        ScriptObject { id, name, transform: Transform::identity(), script }
+    }
+
+    #[inline]
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+    }
+
+    #[inline]
+    pub fn script(&self) -> &Script {
+        &self.script
+    }
+
+    pub fn script_mut(&mut self) -> &mut Script {
+        &mut self.script
+    }
+
+    fn run<F: FnOnce(&mut Script, &mut ScriptTarget)>(&mut self, run: F) {
+        let mut target = ScriptTarget::new(self.id, &self.name, self.transform);
+        run(&mut self.script, &mut target);
+        self.transform = target.transform;
+    }
+}
+
+impl Object for ScriptObject {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn on_update(&mut self) {
+        self.run(|script, target| script.update(target));
+    }
+
+    fn on_fixed_update(&mut self, dt: f32) {
+        self.run(|script, target| script.fixed_update(target, dt));
+    }
+
+    fn on_event(&mut self, e: &mut dyn Event) {
+        self.run(|script, target| script.event(target, e));
+    }
+}
@@ -0,0 +1,187 @@
+use std::sync::atomic::{ AtomicBool, AtomicU32, AtomicUsize, Ordering };
+use std::sync::{ Arc, Mutex, OnceLock, RwLock };
+
+use rhai::Engine;
+
+use crate::core::component::ComponentRegistry;
+use crate::core::entity::Entity;
+use crate::core::input::InputState;
+use crate::core::prefab::PrefabLibrary;
+use crate::core::scene::SceneError;
+use crate::core::transform::Transform;
+use crate::math::Vec3;
+use crate::scripting::bindings;
+
+/**
+ * Ids handed to entities spawned by scripts start here, well clear of ids assigned in scenes
+ **/
+pub const SPAWN_ID_START: u32 = 1 << 31;
+
+/**
+ * Operations a single script call may run before it's stopped, catches infinite loops
+ **/
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+//ScriptHost::layer while no layer is updating
+const NO_LAYER: usize = usize::MAX;
+
+/**
+ * Entity a script asked for, the application creates it once every layer has updated
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpawnRequest {
+    pub name: String,
+    pub prefab: Option<String>,
+    pub position: Vec3,
+    /**
+     * Layer stack index of the layer whose script asked, None for scripts run outside a layer
+     * update like the console's
+     **/
+    pub layer: Option<usize>,
+}
+
+impl SpawnRequest {
+    pub fn instantiate(&self) -> Result<Entity, SceneError> {
+        let host = ScriptHost::global();
+        let id = host.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut entity = match &self.prefab {
+            Some(path) => {
+                let registry = host.registry.read().map_err(|_| SceneError::InvalidData("Component registry lock is poisoned".to_string()))?;
+                let mut prefabs = host.prefabs.lock().map_err(|_| SceneError::InvalidData("Prefab library lock is poisoned".to_string()))?;
+                prefabs.instantiate(path, id, self.name.clone(), &registry)?
+            },
+            None => Entity::new(id, self.name.clone())
+        };
+        match entity.component_mut::<Transform>() {
+            Some(x) => x.translation = self.position,
+            None => entity.add_component(Box::new(Transform::from_translation(self.position)))
+        }
+        Ok(entity)
+    }
+}
+
+/**
+ * The scripting engine along with everything scripts can reach through it
+ * There's one per process so scripts loaded out of scenes find it without being handed anything
+ **/
+pub struct ScriptHost {
+    engine: Engine,
+    input: RwLock<Arc<RwLock<InputState>>>,
+    spawns: Mutex<Vec<SpawnRequest>>,
+    prefabs: Mutex<PrefabLibrary>,
+    registry: RwLock<ComponentRegistry>,
+    next_id: AtomicU32,
+    hot_reload: AtomicBool,
+    layer: AtomicUsize,
+}
+
+static HOST: OnceLock<ScriptHost> = OnceLock::new();
+
+impl ScriptHost {
+    pub fn global() -> &'static ScriptHost {
+        HOST.get_or_init(ScriptHost::new)
+    }
+
+    fn new() -> ScriptHost {
+        let mut engine = Engine::new();
+        //sandboxing, a broken script has to fail its call rather than hang or eat memory
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(1 << 16);
+        engine.set_max_array_size(1 << 16);
+        engine.set_max_map_size(1 << 12);
+        engine.disable_symbol("eval");
+        engine.on_print(|x| info!("[script] {}", x));
+        engine.on_debug(|x, source, pos| debug!("[script {}] {} ({})", source.unwrap_or("?"), x, pos));
+        bindings::register(&mut engine);
+
+        ScriptHost {
+            engine,
+            input: RwLock::new(Arc::new(RwLock::new(InputState::new()))),
+            spawns: Mutex::new(Vec::new()),
+            prefabs: Mutex::new(PrefabLibrary::new()),
+            registry: RwLock::new(ComponentRegistry::new()),
+            next_id: AtomicU32::new(SPAWN_ID_START),
+            hot_reload: AtomicBool::new(cfg!(debug_assertions)),
+            layer: AtomicUsize::new(NO_LAYER),
+        }
+    }
+
+    #[inline]
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /**
+     * Input state scripts read from, the application hands over the one its window feeds
+     **/
+    pub fn set_input(&self, input: Arc<RwLock<InputState>>) {
+        match self.input.write() {
+            Ok(mut x) => *x = input,
+            _ => error!("Script host input RWLock is Poisoned")
+        }
+    }
+
+    pub fn input(&self) -> Arc<RwLock<InputState>> {
+        match self.input.read() {
+            Ok(x) => Arc::clone(&x),
+            _ => Arc::new(RwLock::new(InputState::new()))
+        }
+    }
+
+    /**
+     * Registry used to load the components of prefabs spawned from scripts
+     * Register custom components here as well as in the scene's registry
+     **/
+    pub fn registry(&self) -> &RwLock<ComponentRegistry> {
+        &self.registry
+    }
+
+    pub fn prefabs(&self) -> &Mutex<PrefabLibrary> {
+        &self.prefabs
+    }
+
+    /**
+     * Whether scripts loaded from files are recompiled when the file changes, on in debug builds
+     **/
+    pub fn hot_reload(&self) -> bool {
+        self.hot_reload.load(Ordering::Relaxed)
+    }
+
+    pub fn set_hot_reload(&self, enabled: bool) {
+        self.hot_reload.store(enabled, Ordering::Relaxed);
+    }
+
+    /**
+     * The layer stack index of the layer being updated, the application sets it around each
+     * layer's update so spawns can be traced back to the layer that asked
+     **/
+    pub fn set_layer(&self, layer: Option<usize>) {
+        self.layer.store(layer.unwrap_or(NO_LAYER), Ordering::Relaxed);
+    }
+
+    pub fn layer(&self) -> Option<usize> {
+        match self.layer.load(Ordering::Relaxed) {
+            NO_LAYER => None,
+            x => Some(x)
+        }
+    }
+
+    pub fn request_spawn(&self, request: SpawnRequest) {
+        match self.spawns.lock() {
+            Ok(mut x) => x.push(request),
+            _ => error!("Script spawn queue lock is Poisoned, dropping spawn of {}", request.name)
+        }
+    }
+
+    /**
+     * Spawns requested since the last call, in request order
+     **/
+    pub fn take_spawns(&self) -> Vec<SpawnRequest> {
+        match self.spawns.lock() {
+            Ok(mut x) => std::mem::take(&mut *x),
+            _ => Vec::new()
+        }
+    }
+}
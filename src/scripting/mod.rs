@@ -0,0 +1,64 @@
+pub mod bindings;
+pub mod host;
+pub mod script;
+pub mod component;
+
+pub use self::host::{ ScriptHost, SpawnRequest };
+pub use self::script::Script;
+pub use self::component::{ ScriptComponent, ScriptObject };
+
+use std::error::Error;
+use std::fmt;
+
+use crate::core::component::ComponentRegistry;
+
+/**
+ * Registers the scripting components so scenes and prefabs holding them can be loaded
+ **/
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<ScriptComponent>();
+}
+
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(std::io::Error),
+    Parse(String),
+    Runtime(String),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScriptError::Io(e) => write!(f, "Script IO error: {}", e),
+            ScriptError::Parse(msg) => write!(f, "Script parse error: {}", msg),
+            ScriptError::Runtime(msg) => write!(f, "Script runtime error: {}", msg),
+        }
+    }
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ScriptError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for ScriptError {
+    fn from(e: std::io::Error) -> ScriptError {
+        ScriptError::Io(e)
+    }
+}
+
+impl From<rhai::ParseError> for ScriptError {
+    fn from(e: rhai::ParseError) -> ScriptError {
+        ScriptError::Parse(e.to_string())
+    }
+}
+
+impl From<Box<rhai::EvalAltResult>> for ScriptError {
+    fn from(e: Box<rhai::EvalAltResult>) -> ScriptError {
+        ScriptError::Runtime(e.to_string())
+    }
+}
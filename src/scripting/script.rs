@@ -0,0 +1,295 @@
+use std::fs;
+use std::panic::AssertUnwindSafe;
+use std::path::{ Path, PathBuf };
+use std::time::{ Duration, Instant, SystemTime };
+
+use rhai::{ CallFnOptions, Dynamic, Map, Scope, AST, FLOAT };
+
+use crate::core::crash;
+use crate::core::transform::Transform;
+use crate::events::event::Event;
+use crate::math::{ Quat, Vec3 };
+use crate::scripting::bindings;
+use crate::scripting::host::ScriptHost;
+use crate::scripting::ScriptError;
+
+/**
+ * How often a script file is checked for changes when hot reloading
+ **/
+pub const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+/**
+ * What a script is attached to, copied in before each call and back out after
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptTarget {
+    pub id: u32,
+    pub name: String,
+    pub transform: Transform,
+}
+
+impl ScriptTarget {
+    pub fn new(id: u32, name: &str, transform: Transform) -> ScriptTarget {
+        ScriptTarget { id, name: name.to_string(), transform }
+    }
+}
+
+/**
+ * A compiled Rhai script and the state it keeps between calls
+ * Scripts define any of on_start(), on_update(dt), on_fixed_update(dt), on_event(event) and
+ * on_reload(), `this` in those is a map holding id, name, position, rotation and scale of
+ * what the script is attached to, and any other field the script puts on it survives between
+ * calls and across reloads
+ * A script that fails to compile or errors at runtime is logged and disabled, scripts loaded
+ * from a file re-enable themselves once the file is fixed
+ **/
+pub struct Script {
+    name: String,
+    path: Option<PathBuf>,
+    ast: Option<AST>,
+    scope: Scope<'static>,
+    state: Map,
+    enabled: bool,
+    started: bool,
+    reloaded: bool,
+    error: Option<String>,
+    modified: Option<SystemTime>,
+    last_check: Option<Instant>,
+    last_update: Option<Instant>,
+}
+
+impl Script {
+    fn empty(name: String, path: Option<PathBuf>) -> Script {
+        Script {
+            name,
+            path,
+            ast: None,
+            scope: Scope::new(),
+            state: Map::new(),
+            enabled: false,
+            started: false,
+            reloaded: false,
+            error: None,
+            modified: None,
+            last_check: None,
+            last_update: None,
+        }
+    }
+
+    /**
+     * Compiles a script file, failing if it can't be read or doesn't compile
+     **/
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Script, ScriptError> {
+        let mut script = Script::empty(path.as_ref().display().to_string(), Some(path.as_ref().to_path_buf()));
+        script.reload()?;
+        Ok(script)
+    }
+
+    /**
+     * Like load, but a script that fails to load is returned disabled and keeps being
+     * watched, so fixing the file brings it back
+     **/
+    pub fn watch<P: AsRef<Path>>(path: P) -> Script {
+        let mut script = Script::empty(path.as_ref().display().to_string(), Some(path.as_ref().to_path_buf()));
+        if let Err(e) = script.reload() {
+            script.fail(e);
+        }
+        script
+    }
+
+    /**
+     * Compiles a script from a string, these have no file so they never hot reload
+     **/
+    pub fn from_source(name: &str, source: &str) -> Result<Script, ScriptError> {
+        let mut script = Script::empty(name.to_string(), None);
+        script.compile(source)?;
+        Ok(script)
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    #[inline]
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /**
+     * Re-enables a script disabled by an error, only if it compiled
+     **/
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled && self.ast.is_some();
+    }
+
+    /**
+     * The error that disabled this script, if any
+     **/
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /**
+     * Fields the script has stored on `this`
+     **/
+    pub fn state(&self) -> &Map {
+        &self.state
+    }
+
+    /**
+     * Recompiles from the script's file, does nothing for scripts made from source
+     **/
+    pub fn reload(&mut self) -> Result<(), ScriptError> {
+        let path = match &self.path {
+            Some(x) => x.clone(),
+            None => return Ok(())
+        };
+        self.modified = fs::metadata(&path).and_then(|x| x.modified()).ok();
+        let source = fs::read_to_string(&path)?;
+        self.compile(&source)
+    }
+
+    fn compile(&mut self, source: &str) -> Result<(), ScriptError> {
+        let engine = ScriptHost::global().engine();
+        let mut ast = engine.compile(source)?;
+        ast.set_source(self.name.as_str());
+        //top level statements run once, for constants and setup that doesn't need `this`
+        let mut scope = Scope::new();
+        engine.run_ast_with_scope(&mut scope, &ast)?;
+        self.reloaded = self.ast.is_some();
+        self.ast = Some(ast);
+        self.scope = scope;
+        self.enabled = true;
+        self.error = None;
+        Ok(())
+    }
+
+    fn fail(&mut self, e: ScriptError) {
+        error!("Script {} disabled: {}", self.name, e);
+        self.enabled = false;
+        self.error = Some(e.to_string());
+    }
+
+    fn check_reload(&mut self) {
+        if self.path.is_none() || !ScriptHost::global().hot_reload() {
+            return;
+        }
+        let now = Instant::now();
+        if self.last_check.is_some_and(|x| now.duration_since(x) < RELOAD_INTERVAL) {
+            return;
+        }
+        self.last_check = Some(now);
+        let modified = self.path.as_ref().and_then(|x| fs::metadata(x).and_then(|m| m.modified()).ok());
+        if modified.is_some() && modified != self.modified {
+            match self.reload() {
+                Ok(()) => info!("Reloaded script {}", self.name),
+                Err(e) => self.fail(e)
+            }
+        }
+    }
+
+    /**
+     * Calls a script function if the script defines it, None if it doesn't or the call failed
+     * Arguments past what the function declares are dropped so callbacks can skip parameters
+     **/
+    pub fn call(&mut self, target: &mut ScriptTarget, function: &str, mut args: Vec<Dynamic>) -> Option<Dynamic> {
+        if !self.enabled {
+            return None;
+        }
+        let ast = self.ast.as_ref()?;
+        let params = ast.iter_functions().find(|x| x.name == function).map(|x| x.params.len())?;
+        args.truncate(params);
+
+        let mut state = std::mem::take(&mut self.state);
+        state.insert("id".into(), Dynamic::from_int(target.id as i64));
+        state.insert("name".into(), target.name.clone().into());
+        state.insert("position".into(), Dynamic::from(target.transform.translation));
+        state.insert("rotation".into(), Dynamic::from(target.transform.rotation));
+        state.insert("scale".into(), Dynamic::from(target.transform.scale));
+        let mut this = Dynamic::from_map(state);
+
+        let engine = ScriptHost::global().engine();
+        let scope = &mut self.scope;
+        //a panic in a binding is treated like any other script error
+        let result = crash::recover(AssertUnwindSafe(|| {
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut this);
+            engine.call_fn_with_options::<Dynamic>(options, scope, ast, function, args)
+        }));
+
+        self.state = this.try_cast::<Map>().unwrap_or_default();
+        if let Some(x) = self.state.get("position").and_then(|x| x.clone().try_cast::<Vec3>()) {
+            target.transform.translation = x;
+        }
+        if let Some(x) = self.state.get("rotation").and_then(|x| x.clone().try_cast::<Quat>()) {
+            target.transform.rotation = x.normalize();
+        }
+        if let Some(x) = self.state.get("scale").and_then(|x| x.clone().try_cast::<Vec3>()) {
+            target.transform.scale = x;
+        }
+
+        match result {
+            Ok(Ok(x)) => Some(x),
+            Ok(Err(e)) => {
+                self.fail(e.into());
+                None
+            },
+            Err(_) => {
+                self.fail(ScriptError::Runtime(format!("{} panicked", function)));
+                None
+            }
+        }
+    }
+
+    /**
+     * Runs on_start the first time, then on_update with the seconds since the last update
+     **/
+    pub fn update(&mut self, target: &mut ScriptTarget) {
+        self.check_reload();
+        if !self.started && self.enabled {
+            self.started = true;
+            self.call(target, "on_start", Vec::new());
+        }
+        if self.reloaded && self.enabled {
+            self.reloaded = false;
+            self.call(target, "on_reload", Vec::new());
+        }
+        let now = Instant::now();
+        let dt = self.last_update.map_or(0.0, |x| now.duration_since(x).as_secs_f32());
+        self.last_update = Some(now);
+        self.call(target, "on_update", vec![Dynamic::from_float(dt as FLOAT)]);
+    }
+
+    pub fn fixed_update(&mut self, target: &mut ScriptTarget, dt: f32) {
+        if self.started {
+            self.call(target, "on_fixed_update", vec![Dynamic::from_float(dt as FLOAT)]);
+        }
+    }
+
+    /**
+     * Hands the event to on_event, returning true from it marks the event handled
+     **/
+    pub fn event(&mut self, target: &mut ScriptTarget, e: &mut dyn Event) {
+        let map = bindings::event_map(e);
+        if let Some(x) = self.call(target, "on_event", vec![Dynamic::from_map(map)]) {
+            if x.as_bool().unwrap_or(false) {
+                e.set_handled(true);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Script {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Script")
+            .field("name", &self.name)
+            .field("enabled", &self.enabled)
+            .field("error", &self.error)
+            .finish()
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+use ab_glyph::{ point, Font as _, GlyphId };
+
+use crate::math::Vec2;
+use crate::text::font::{ FontId, FontSet };
+
+/**
+ * Gap left around every glyph so linear filtering doesn't pick up the neighbours
+ **/
+const PADDING: u32 = 1;
+
+/**
+ * How glyphs are rasterized into the atlas
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RasterMode {
+    /**
+     * Antialiased coverage at the exact size drawn, sharpest at 1:1 but every size is
+     * rasterized separately
+     **/
+    Coverage,
+    /**
+     * Signed distance fields rasterized once at `size` and scaled when drawn, 0.5 is the edge
+     * and the field fades over `spread` pixels either side
+     **/
+    Sdf { size: f32, spread: f32 },
+}
+
+/**
+ * A rasterized glyph, sizes are in pixels at the raster size
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub font: FontId,
+    pub glyph: u16,
+    //quarter pixels so nearby sizes share glyphs
+    pub size: u32,
+}
+
+impl GlyphKey {
+    pub fn new(font: FontId, glyph: GlyphId, size: f32) -> GlyphKey {
+        GlyphKey { font, glyph: glyph.0, size: (size * 4.0).round().max(1.0) as u32 }
+    }
+
+    #[inline]
+    pub fn size(&self) -> f32 {
+        self.size as f32 / 4.0
+    }
+}
+
+/**
+ * Where a glyph is in the atlas
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AtlasGlyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /**
+     * From the pen position on the baseline to the bitmap's top left, at the raster size
+     **/
+    pub offset: Vec2,
+    /**
+     * Size the glyph was rasterized at, SDF glyphs are scaled by the drawn size over this
+     **/
+    pub raster_size: f32,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    x: u32,
+}
+
+/**
+ * Single channel texture of rasterized glyphs, filled as glyphs are first drawn
+ * Shelves are packed from the top left, so when it fills up it doubles without moving what's
+ * already there. Past the maximum size it's cleared and the generation goes up, anything
+ * holding AtlasGlyphs from the old generation needs to look them up again
+ **/
+pub struct GlyphAtlas {
+    mode: RasterMode,
+    size: u32,
+    max_size: u32,
+    pixels: Vec<u8>,
+    shelves: Vec<Shelf>,
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    dirty: bool,
+    generation: u32,
+}
+
+impl std::fmt::Debug for GlyphAtlas {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "GlyphAtlas {{ mode: {:?}, size: {}, glyphs: {}, generation: {} }}",
+        self.mode, self.size, self.glyphs.len(), self.generation)
+    }
+}
+
+impl GlyphAtlas {
+    pub fn new(mode: RasterMode, size: Option<u32>, max_size: Option<u32>) -> GlyphAtlas {
+        let size = size.unwrap_or(512).max(64);
+        let max_size = max_size.unwrap_or(4096).max(size);
+        GlyphAtlas {
+            mode,
+            size,
+            max_size,
+            pixels: vec![0; (size * size) as usize],
+            shelves: Vec::new(),
+            glyphs: HashMap::new(),
+            dirty: true,
+            generation: 0,
+        }
+    }
+
+    #[inline]
+    pub fn mode(&self) -> RasterMode {
+        self.mode
+    }
+
+    /**
+     * Width and height, the atlas is always square
+     **/
+    #[inline]
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /**
+     * Whether the pixels changed since the last call, for knowing when to upload
+     **/
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::replace(&mut self.dirty, false)
+    }
+
+    pub fn clear(&mut self) {
+        self.pixels.iter_mut().for_each(|x| *x = 0);
+        self.shelves.clear();
+        self.glyphs.clear();
+        self.generation += 1;
+        self.dirty = true;
+    }
+
+    /**
+     * The glyph's place in the atlas, rasterizing it the first time it's asked for at a size
+     * None for glyphs with nothing to draw, like spaces, or ones too big to ever fit
+     **/
+    pub fn glyph(&mut self, fonts: &FontSet, font: FontId, glyph: GlyphId, size: f32) -> Option<AtlasGlyph> {
+        let raster_size = match self.mode {
+            RasterMode::Coverage => size,
+            RasterMode::Sdf { size, .. } => size
+        };
+        let key = GlyphKey::new(font, glyph, raster_size);
+        if let Some(x) = self.glyphs.get(&key) {
+            return *x;
+        }
+        let placed = self.rasterize(fonts, key);
+        self.glyphs.insert(key, placed);
+        placed
+    }
+
+    fn rasterize(&mut self, fonts: &FontSet, key: GlyphKey) -> Option<AtlasGlyph> {
+        let font = fonts.get(key.font)?;
+        let scale = font.scale(key.size());
+        let outlined = font.raw().outline_glyph(GlyphId(key.glyph).with_scale_and_position(scale, point(0.0, 0.0)))?;
+        let bounds = outlined.px_bounds();
+        let spread = match self.mode {
+            RasterMode::Coverage => 0,
+            RasterMode::Sdf { spread, .. } => spread.ceil().max(1.0) as u32
+        };
+        let (w, h) = (bounds.width() as u32, bounds.height() as u32);
+        let (width, height) = (w + spread * 2, h + spread * 2);
+        if w == 0 || h == 0 {
+            return None;
+        }
+
+        let mut coverage = vec![0.0f32; (width * height) as usize];
+        outlined.draw(|x, y, c| {
+            if x < w && y < h {
+                coverage[((y + spread) * width + x + spread) as usize] = c;
+            }
+        });
+        let bitmap: Vec<u8> = match self.mode {
+            RasterMode::Coverage => coverage.iter().map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8).collect(),
+            RasterMode::Sdf { spread, .. } => distance_field(&coverage, width, height, spread)
+        };
+
+        let (x, y) = self.allocate(width, height)?;
+        for row in 0..height {
+            let src = (row * width) as usize;
+            let dst = ((y + row) * self.size + x) as usize;
+            self.pixels[dst..dst + width as usize].copy_from_slice(&bitmap[src..src + width as usize]);
+        }
+        self.dirty = true;
+        Some(AtlasGlyph {
+            x,
+            y,
+            width,
+            height,
+            offset: Vec2::new(bounds.min.x - spread as f32, bounds.min.y - spread as f32),
+            raster_size: key.size(),
+        })
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (w, h) = (width + PADDING, height + PADDING);
+        if w > self.max_size || h > self.max_size {
+            return None;
+        }
+        loop {
+            //the shortest shelf the glyph fits on, without wasting too much height
+            let size = self.size;
+            let shelf = self.shelves.iter_mut()
+                .filter(|x| x.height >= h && x.height <= h + h / 2 + 2 && x.x + w <= size)
+                .min_by_key(|x| x.height);
+            if let Some(shelf) = shelf {
+                let position = (shelf.x, shelf.y);
+                shelf.x += w;
+                return Some(position);
+            }
+            let top = self.shelves.last().map_or(0, |x| x.y + x.height);
+            if top + h <= self.size && w <= self.size {
+                self.shelves.push(Shelf { y: top, height: h, x: w });
+                return Some((0, top));
+            }
+            if self.size < self.max_size {
+                self.grow();
+            } else if self.shelves.is_empty() {
+                return None;
+            } else {
+                warn!("Glyph atlas is full at {}x{}, clearing it", self.size, self.size);
+                self.clear();
+            }
+        }
+    }
+
+    fn grow(&mut self) {
+        let size = (self.size * 2).min(self.max_size);
+        let mut pixels = vec![0; (size * size) as usize];
+        for row in 0..self.size as usize {
+            let src = row * self.size as usize;
+            pixels[row * size as usize..row * size as usize + self.size as usize].copy_from_slice(&self.pixels[src..src + self.size as usize]);
+        }
+        debug!("Glyph atlas grown to {}x{}", size, size);
+        self.pixels = pixels;
+        self.size = size;
+        self.dirty = true;
+    }
+}
+
+/**
+ * Signed distance to the nearest edge for every pixel, mapped so 0.5 is the edge, inside is
+ * above it and `spread` pixels away hits 0 or 1
+ * Brute force over a window of `spread` pixels, glyph bitmaps are small enough for that
+ **/
+fn distance_field(coverage: &[f32], width: u32, height: u32, spread: f32) -> Vec<u8> {
+    let (w, h) = (width as i32, height as i32);
+    let radius = spread.ceil() as i32;
+    let inside = |x: i32, y: i32| x >= 0 && y >= 0 && x < w && y < h && coverage[(y * w + x) as usize] >= 0.5;
+    let mut field = Vec::with_capacity(coverage.len());
+    for y in 0..h {
+        for x in 0..w {
+            let state = inside(x, y);
+            let mut nearest = spread * spread;
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    if inside(x + dx, y + dy) != state {
+                        nearest = nearest.min((dx * dx + dy * dy) as f32);
+                    }
+                }
+            }
+            //the edge sits half a pixel from the centres on either side of it
+            let distance = (nearest.sqrt() - 0.5).max(0.0);
+            let signed = if state { distance } else { -distance };
+            field.push(((0.5 + signed / (2.0 * spread)).clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+    field
+}
@@ -0,0 +1,33 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+/**
+ * Start of the grapheme before a byte offset, so a base letter and its combining marks are
+ * stepped over and deleted together
+ **/
+pub fn prev_grapheme(text: &str, cursor: usize) -> Option<usize> {
+    text.get(..cursor)?.grapheme_indices(true).next_back().map(|(i, _)| i)
+}
+
+/**
+ * End of the grapheme after a byte offset
+ **/
+pub fn next_grapheme(text: &str, cursor: usize) -> Option<usize> {
+    text.get(cursor..)?.graphemes(true).next().map(|x| cursor + x.len())
+}
+
+/**
+ * User perceived characters in the text
+ **/
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/**
+ * The text cut down to at most `max` graphemes
+ **/
+pub fn truncate_graphemes(text: &str, max: usize) -> &str {
+    match text.grapheme_indices(true).nth(max) {
+        Some((i, _)) => &text[..i],
+        None => text
+    }
+}
@@ -0,0 +1,206 @@
+use std::fs;
+use std::path::Path;
+
+use ab_glyph::{ Font as _, FontArc, GlyphId, PxScale, ScaleFont };
+
+use crate::math::Vec2;
+use crate::text::TextError;
+use crate::text::layout::{ TextLayout, TextStyle };
+
+/**
+ * Index of a font in its FontSet
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FontId(pub usize);
+
+/**
+ * Vertical metrics at a font size, descent is below the baseline so it's negative
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl LineMetrics {
+    /**
+     * Distance from one baseline to the next
+     **/
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.ascent - self.descent + self.line_gap
+    }
+}
+
+/**
+ * A parsed TTF or OTF font, cheap to clone
+ * Sizes are em sizes in pixels, a 16 pixel font has 16 pixel tall em squares like CSS
+ **/
+#[derive(Clone)]
+pub struct Font {
+    name: String,
+    font: FontArc,
+    //ab_glyph scales by ascent - descent, this turns an em size into that
+    em_scale: f32,
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Font {{ name: {}, glyphs: {} }}", self.name, self.font.glyph_count())
+    }
+}
+
+impl Font {
+    pub fn from_bytes(name: &str, data: Vec<u8>) -> Result<Font, TextError> {
+        let font = FontArc::try_from_vec(data).map_err(|_| TextError::InvalidFont(name.to_string()))?;
+        let em_scale = match font.units_per_em() {
+            Some(x) if x > 0.0 => font.height_unscaled() / x,
+            _ => 1.0
+        };
+        Ok(Font { name: name.to_string(), font, em_scale })
+    }
+
+    /**
+     * Loads a font file, named after the file
+     **/
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Font, TextError> {
+        let path = path.as_ref();
+        let name = path.file_stem().map_or_else(|| path.to_string_lossy(), |x| x.to_string_lossy());
+        Font::from_bytes(&name, fs::read(path)?)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /**
+     * The underlying ab_glyph font, for rasterizing
+     **/
+    pub fn raw(&self) -> &FontArc {
+        &self.font
+    }
+
+    pub fn scale(&self, size: f32) -> PxScale {
+        PxScale::from(size * self.em_scale)
+    }
+
+    /**
+     * The glyph for a character, glyph 0 (.notdef) if the font doesn't have it
+     **/
+    pub fn glyph(&self, c: char) -> GlyphId {
+        self.font.glyph_id(c)
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        self.glyph(c).0 != 0
+    }
+
+    pub fn metrics(&self, size: f32) -> LineMetrics {
+        let font = self.font.as_scaled(self.scale(size));
+        LineMetrics { ascent: font.ascent(), descent: font.descent(), line_gap: font.line_gap() }
+    }
+
+    pub fn advance(&self, glyph: GlyphId, size: f32) -> f32 {
+        self.font.as_scaled(self.scale(size)).h_advance(glyph)
+    }
+
+    /**
+     * Extra advance between a pair of glyphs from the font's kern table, GPOS kerning isn't read
+     **/
+    pub fn kern(&self, first: GlyphId, second: GlyphId, size: f32) -> f32 {
+        self.font.as_scaled(self.scale(size)).kern(first, second)
+    }
+}
+
+/**
+ * A primary font and the fallbacks tried in order for characters it's missing
+ **/
+#[derive(Debug, Clone)]
+pub struct FontSet {
+    fonts: Vec<Font>,
+}
+
+impl FontSet {
+    pub fn new(primary: Font) -> FontSet {
+        FontSet { fonts: vec![primary] }
+    }
+
+    /**
+     * The fonts egui ships with, Ubuntu Light with Noto Emoji and egui's icon font as fallbacks
+     **/
+    pub fn builtin() -> FontSet {
+        let mut data = egui::FontDefinitions::default().font_data;
+        let mut fonts = ["Ubuntu-Light", "NotoEmoji-Regular", "emoji-icon-font"].iter().filter_map(|name| {
+            let bytes = data.remove(*name)?.font.to_vec();
+            match Font::from_bytes(name, bytes) {
+                Ok(x) => Some(x),
+                Err(e) => {
+                    error!("Built in font failed to load: {}", e);
+                    None
+                }
+            }
+        });
+        let mut set = FontSet::new(fonts.next().expect("Built in font is missing"));
+        for font in fonts {
+            set.push(font);
+        }
+        set
+    }
+
+    /**
+     * Adds a fallback after the ones already in the set
+     **/
+    pub fn push(&mut self, font: Font) -> FontId {
+        self.fonts.push(font);
+        FontId(self.fonts.len() - 1)
+    }
+
+    pub fn get(&self, id: FontId) -> Option<&Font> {
+        self.fonts.get(id.0)
+    }
+
+    pub fn primary(&self) -> &Font {
+        &self.fonts[0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.fonts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fonts.is_empty()
+    }
+
+    /**
+     * The first font with a glyph for the character, the primary's .notdef if none do
+     **/
+    pub fn glyph(&self, c: char) -> (FontId, GlyphId) {
+        self.fonts.iter().enumerate()
+            .find_map(|(i, x)| {
+                let glyph = x.glyph(c);
+                if glyph.0 != 0 { Some((FontId(i), glyph)) } else { None }
+            })
+            .unwrap_or((FontId(0), GlyphId(0)))
+    }
+
+    /**
+     * Line metrics come from the primary font so fallbacks don't change line spacing
+     **/
+    pub fn metrics(&self, size: f32) -> LineMetrics {
+        self.primary().metrics(size)
+    }
+
+    /**
+     * Size of unwrapped text, what UI layout measures labels with
+     **/
+    pub fn measure(&self, text: &str, size: f32) -> Vec2 {
+        TextLayout::new(self, text, TextStyle::new(size)).size()
+    }
+}
+
+impl Default for FontSet {
+    fn default() -> FontSet {
+        FontSet::builtin()
+    }
+}
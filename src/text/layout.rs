@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+use ab_glyph::GlyphId;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::math::Vec2;
+use crate::text::font::{ FontId, FontSet };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStyle {
+    /**
+     * Em size in whatever units the layout should come out in
+     **/
+    pub size: f32,
+    /**
+     * Multiplier on the font's own line spacing
+     **/
+    pub line_height: f32,
+    /**
+     * Lines wrap at word boundaries past this width, words wider than it break anywhere
+     **/
+    pub max_width: Option<f32>,
+    pub align: TextAlign,
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> TextStyle {
+        TextStyle { size, line_height: 1.0, max_width: None, align: TextAlign::Left }
+    }
+
+    pub fn wrapped(size: f32, max_width: f32, align: TextAlign) -> TextStyle {
+        TextStyle { size, line_height: 1.0, max_width: Some(max_width), align }
+    }
+}
+
+/**
+ * A glyph and where its pen position on the baseline ends up
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    pub font: FontId,
+    pub glyph: GlyphId,
+    pub position: Vec2,
+    /**
+     * Byte offset of the grapheme the glyph belongs to
+     **/
+    pub cluster: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /**
+     * Bytes of the text on this line, trailing whitespace and the newline included
+     **/
+    pub range: Range<usize>,
+    pub glyphs: Range<usize>,
+    pub x: f32,
+    pub width: f32,
+    pub top: f32,
+    pub baseline: f32,
+}
+
+//a grapheme shaped on its own
+struct Cluster {
+    start: usize,
+    end: usize,
+    glyphs: Vec<(FontId, GlyphId, f32)>,
+    advance: f32,
+    whitespace: bool,
+}
+
+/**
+ * Text broken into lines and positioned, y grows downwards from the top of the first line
+ * Each grapheme is laid out as its base character plus combining marks, marks the font gives no
+ * advance are placed where the font designed them to sit over the base
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayout {
+    glyphs: Vec<PositionedGlyph>,
+    lines: Vec<TextLine>,
+    //grapheme boundaries with their caret x and line
+    boundaries: Vec<(usize, f32, usize)>,
+    size: Vec2,
+    line_height: f32,
+}
+
+impl TextLayout {
+    pub fn new(fonts: &FontSet, text: &str, style: TextStyle) -> TextLayout {
+        let size = style.size;
+        let metrics = fonts.metrics(size);
+        let line_height = metrics.height() * style.line_height;
+
+        //lines as (byte range, clusters on the line)
+        let mut lines: Vec<(Range<usize>, Vec<Cluster>)> = Vec::new();
+        let mut offset = 0;
+        for paragraph in text.split('\n') {
+            let clusters = shape(fonts, paragraph, offset, size);
+            let end = offset + paragraph.len();
+            wrap(clusters, offset..end, style.max_width, &mut lines);
+            //the newline belongs to the line it ends
+            if end < text.len() {
+                if let Some(x) = lines.last_mut() {
+                    x.0.end = end + 1;
+                }
+            }
+            offset = end + 1;
+        }
+
+        let widths: Vec<f32> = lines.iter().map(|(_, x)| content_width(x)).collect();
+        let widest = widths.iter().cloned().fold(0.0, f32::max);
+        let bounds = style.max_width.unwrap_or(widest);
+
+        let mut layout = TextLayout {
+            glyphs: Vec::new(),
+            lines: Vec::new(),
+            boundaries: Vec::new(),
+            size: Vec2::new(widest, line_height * lines.len() as f32),
+            line_height,
+        };
+        for (i, ((range, clusters), width)) in lines.into_iter().zip(widths).enumerate() {
+            let x = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (bounds - width) * 0.5,
+                TextAlign::Right => bounds - width
+            };
+            let top = line_height * i as f32;
+            let baseline = top + metrics.ascent;
+            let first = layout.glyphs.len();
+            let mut pen = x;
+            for cluster in &clusters {
+                layout.boundaries.push((cluster.start, pen, i));
+                for (font, glyph, dx) in &cluster.glyphs {
+                    layout.glyphs.push(PositionedGlyph {
+                        font: *font,
+                        glyph: *glyph,
+                        position: Vec2::new(pen + dx, baseline),
+                        cluster: cluster.start,
+                    });
+                }
+                pen += cluster.advance;
+            }
+            let end = clusters.last().map_or(range.start, |x| x.end);
+            layout.boundaries.push((end, pen, i));
+            layout.lines.push(TextLine { range, glyphs: first..layout.glyphs.len(), x, width, top, baseline });
+        }
+        layout
+    }
+
+    pub fn glyphs(&self) -> &[PositionedGlyph] {
+        &self.glyphs
+    }
+
+    pub fn lines(&self) -> &[TextLine] {
+        &self.lines
+    }
+
+    /**
+     * Width of the widest line by the height of all of them
+     **/
+    pub fn size(&self) -> Vec2 {
+        self.size
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /**
+     * Top of the caret at a byte offset, snapped back to the grapheme it's in
+     **/
+    pub fn caret(&self, index: usize) -> Vec2 {
+        let found = self.boundaries.iter().rev().find(|x| x.0 <= index).or_else(|| self.boundaries.first());
+        match found {
+            Some((_, x, line)) => Vec2::new(*x, self.lines[*line].top),
+            None => Vec2::ZERO
+        }
+    }
+
+    /**
+     * The grapheme boundary closest to a point
+     **/
+    pub fn index_at(&self, point: Vec2) -> usize {
+        let line = if self.line_height > 0.0 { (point.y / self.line_height).floor().max(0.0) as usize } else { 0 };
+        let line = line.min(self.lines.len().saturating_sub(1));
+        self.boundaries.iter()
+            .filter(|x| x.2 == line)
+            .fold(None, |best: Option<(usize, f32)>, x| {
+                let distance = (x.1 - point.x).abs();
+                match best {
+                    Some((_, d)) if d <= distance => best,
+                    _ => Some((x.0, distance))
+                }
+            })
+            .map_or(0, |x| x.0)
+    }
+}
+
+//clusters for one paragraph, kerned against the grapheme before them
+fn shape(fonts: &FontSet, text: &str, offset: usize, size: f32) -> Vec<Cluster> {
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut previous: Option<(FontId, GlyphId)> = None;
+    for (start, grapheme) in text.grapheme_indices(true) {
+        let mut chars = grapheme.chars();
+        let base = match chars.next() {
+            Some(x) => x,
+            None => continue
+        };
+        let whitespace = grapheme.chars().all(char::is_whitespace);
+        let (font_id, glyph) = fonts.glyph(if base == '\t' { ' ' } else { base });
+        let font = &fonts.get(font_id).expect("FontSet handed out a font it doesn't have");
+        let mut advance = font.advance(glyph, size);
+        if base == '\t' {
+            advance *= 4.0;
+        }
+        if let (Some((prev_font, prev)), Some(last)) = (previous, clusters.last_mut()) {
+            if prev_font == font_id {
+                last.advance += font.kern(prev, glyph, size);
+            }
+        }
+
+        let mut glyphs = vec![(font_id, glyph, 0.0)];
+        for mark in chars {
+            //marks come from the base's font when it has them so they line up
+            let (mark_font, mark_glyph) = if font.has_glyph(mark) { (font_id, font.glyph(mark)) } else { fonts.glyph(mark) };
+            //a mark none of the fonts have is left off rather than drawn as a box over the base
+            if mark_glyph.0 == 0 {
+                continue;
+            }
+            let mark_advance = fonts.get(mark_font).map_or(0.0, |x| x.advance(mark_glyph, size));
+            let dx = if mark_advance == 0.0 { advance } else { (advance - mark_advance) * 0.5 };
+            glyphs.push((mark_font, mark_glyph, dx));
+        }
+
+        previous = Some((font_id, glyph));
+        clusters.push(Cluster { start: offset + start, end: offset + start + grapheme.len(), glyphs, advance, whitespace });
+    }
+    clusters
+}
+
+//breaks a paragraph's clusters into lines
+fn wrap(clusters: Vec<Cluster>, range: Range<usize>, max_width: Option<f32>, lines: &mut Vec<(Range<usize>, Vec<Cluster>)>) {
+    let max_width = match max_width {
+        Some(x) => x,
+        None => {
+            lines.push((range, clusters));
+            return;
+        }
+    };
+
+    let mut line: Vec<Cluster> = Vec::new();
+    let mut line_start = range.start;
+    let mut pen = 0.0;
+    let mut clusters = clusters.into_iter().peekable();
+    while clusters.peek().is_some() {
+        //a word and the whitespace after it break together, the whitespace can hang past the edge
+        let mut word = Vec::new();
+        while let Some(x) = clusters.next_if(|x| !x.whitespace) {
+            word.push(x);
+        }
+        let width: f32 = word.iter().map(|x| x.advance).sum();
+        if pen + width > max_width && line.iter().any(|x| !x.whitespace) {
+            let start = word.first().map_or(line_start, |x| x.start);
+            lines.push((line_start..start, std::mem::take(&mut line)));
+            line_start = start;
+            pen = 0.0;
+        }
+        for cluster in word {
+            //words wider than the line break between graphemes
+            if pen + cluster.advance > max_width && !line.is_empty() {
+                lines.push((line_start..cluster.start, std::mem::take(&mut line)));
+                line_start = cluster.start;
+                pen = 0.0;
+            }
+            pen += cluster.advance;
+            line.push(cluster);
+        }
+        while let Some(x) = clusters.next_if(|x| x.whitespace) {
+            pen += x.advance;
+            line.push(x);
+        }
+    }
+    lines.push((line_start..range.end, line));
+}
+
+//line width without the whitespace hanging off the end
+fn content_width(clusters: &[Cluster]) -> f32 {
+    let trailing = clusters.iter().rev().take_while(|x| x.whitespace).count();
+    clusters[..clusters.len() - trailing].iter().map(|x| x.advance).sum()
+}
@@ -0,0 +1,44 @@
+pub mod font;
+pub mod atlas;
+pub mod layout;
+pub mod edit;
+
+pub use self::font::{ Font, FontId, FontSet, LineMetrics };
+pub use self::atlas::{ AtlasGlyph, GlyphAtlas, GlyphKey, RasterMode };
+pub use self::layout::{ PositionedGlyph, TextAlign, TextLayout, TextLine, TextStyle };
+pub use self::edit::{ grapheme_count, next_grapheme, prev_grapheme, truncate_graphemes };
+
+pub use ab_glyph::GlyphId;
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum TextError {
+    Io(std::io::Error),
+    InvalidFont(String),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextError::Io(e) => write!(f, "Font IO error: {}", e),
+            TextError::InvalidFont(name) => write!(f, "{} is not a TTF or OTF font", name),
+        }
+    }
+}
+
+impl Error for TextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TextError::Io(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for TextError {
+    fn from(e: std::io::Error) -> TextError {
+        TextError::Io(e)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::math::Vec2;
+use crate::physics::{ BodyHandle, BodyType, Collider2D, Isometry2, PhysicsWorld2D, RigidBody2D, Shape2D };
+use crate::tilemap::TilemapError;
+use crate::tilemap::map::{ MapObject, ObjectShape, Tile, TileLayer, Tilemap };
+
+//sides given to ellipses that aren't circles
+const ELLIPSE_SEGMENTS: u32 = 12;
+
+/**
+ * A collision shape generated from the map, `offset` places it in world space
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileCollider {
+    pub shape: Shape2D,
+    pub offset: Isometry2,
+}
+
+impl Tilemap {
+    /**
+     * Collision for every tile layer, layers with a `collision` property set to false are skipped
+     * Tiles with shapes drawn in Tiled's collision editor use those, concave polygons become their
+     * convex hull and polylines are ignored. Otherwise tiles with a `solid` property set to true
+     * fill their cell, and runs of those along a row are merged into one box
+     **/
+    pub fn colliders(&self) -> Vec<TileCollider> {
+        let mut out = Vec::new();
+        for layer in self.tile_layers() {
+            if layer.properties.get("collision").and_then(|x| x.as_bool()) == Some(false) {
+                continue;
+            }
+            self.layer_colliders(layer, &mut out);
+        }
+        out
+    }
+
+    /**
+     * Adds one static body holding every collider, at the world origin
+     **/
+    pub fn add_colliders(&self, world: &mut PhysicsWorld2D) -> Result<BodyHandle, TilemapError> {
+        let body = world.add_body(RigidBody2D::new(BodyType::Static, Vec2::ZERO));
+        for x in self.colliders() {
+            let mut collider = Collider2D::new(x.shape);
+            collider.offset = x.offset;
+            world.add_collider(body, collider)?;
+        }
+        Ok(body)
+    }
+
+    fn layer_colliders(&self, layer: &TileLayer, out: &mut Vec<TileCollider>) {
+        let (cell_w, cell_h) = (self.tile_size.0 as f32, self.tile_size.1 as f32);
+        for y in 0..layer.height {
+            //start of the run of solid cells we're in
+            let mut run: Option<u32> = None;
+            for x in 0..=layer.width {
+                let tile = layer.tile(x, y);
+                let solid = tile.is_some_and(|t| self.is_solid(t));
+                if solid && run.is_none() {
+                    run = Some(x);
+                }
+                if !solid {
+                    if let Some(start) = run.take() {
+                        let min = layer.offset + Vec2::new(start as f32 * cell_w, y as f32 * cell_h);
+                        let max = layer.offset + Vec2::new(x as f32 * cell_w, (y + 1) as f32 * cell_h);
+                        out.push(self.rect_collider(min, max));
+                    }
+                }
+                if let Some(tile) = tile {
+                    self.shape_colliders(layer, x, y, tile, out);
+                }
+            }
+        }
+    }
+
+    /**
+     * Only counts tiles that have no drawn shapes, those are handled by shape_colliders
+     **/
+    fn is_solid(&self, tile: Tile) -> bool {
+        match self.tile_data(tile.gid) {
+            Some(data) => data.collision.is_empty() && data.properties.get("solid").and_then(|x| x.as_bool()) == Some(true),
+            None => false
+        }
+    }
+
+    fn rect_collider(&self, min: Vec2, max: Vec2) -> TileCollider {
+        let (a, b) = (self.to_world(min), self.to_world(max));
+        let half = (b - a) * 0.5;
+        TileCollider { shape: Shape2D::rect(half.x, half.y), offset: Isometry2::new((a + b) * 0.5, 0.0) }
+    }
+
+    fn shape_colliders(&self, layer: &TileLayer, x: u32, y: u32, tile: Tile, out: &mut Vec<TileCollider>) {
+        let (index, local) = match self.tileset(tile.gid) {
+            Some(x) => x,
+            None => return
+        };
+        let tileset = &self.tilesets[index];
+        let data = match tileset.tile(local) {
+            Some(x) if !x.collision.is_empty() => x,
+            _ => return
+        };
+        let size = tileset.tile_size();
+        //tiles taller than the grid stick up out of their cell
+        let origin = layer.offset + Vec2::new(
+            x as f32 * self.tile_size.0 as f32,
+            (y + 1) as f32 * self.tile_size.1 as f32 - size.y
+        );
+        for object in &data.collision {
+            let points: Vec<Vec2> = object.map_outline(ELLIPSE_SEGMENTS).into_iter()
+                .map(|p| self.to_world(origin + tile.flip_point(p, size)))
+                .collect();
+            if let Some(collider) = self.object_collider(object, &points) {
+                out.push(collider);
+            }
+        }
+    }
+
+    fn object_collider(&self, object: &MapObject, points: &[Vec2]) -> Option<TileCollider> {
+        if points.is_empty() {
+            return None;
+        }
+        let center = points.iter().fold(Vec2::ZERO, |sum, &p| sum + p) / points.len() as f32;
+        //round ellipses are kept as circles
+        if object.shape == ObjectShape::Ellipse && (object.size.x - object.size.y).abs() < f32::EPSILON {
+            let radius = object.size.x * 0.5 / self.pixels_per_unit;
+            return Some(TileCollider { shape: Shape2D::circle(radius), offset: Isometry2::new(center, 0.0) });
+        }
+        let local: Vec<Vec2> = points.iter().map(|&p| p - center).collect();
+        match Shape2D::polygon(&local) {
+            Ok(shape) => Some(TileCollider { shape, offset: Isometry2::new(center, 0.0) }),
+            Err(e) => {
+                warn!("Skipping tile collision shape {}: {}", object.id, e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::animation::sprite::SpriteSheet;
+use crate::math::Vec2;
+
+//the top bits of a gid say how the tile is flipped, the rest is the tile
+const FLIP_HORIZONTAL: u32 = 0x8000_0000;
+const FLIP_VERTICAL: u32 = 0x4000_0000;
+const FLIP_DIAGONAL: u32 = 0x2000_0000;
+//only used by hexagonal maps, cleared so it can't corrupt the id
+const ROTATE_HEXAGONAL: u32 = 0x1000_0000;
+const GID_MASK: u32 = !(FLIP_HORIZONTAL | FLIP_VERTICAL | FLIP_DIAGONAL | ROTATE_HEXAGONAL);
+
+/**
+ * A custom property set in Tiled, colors, files and object references come through as
+ * strings and ints, class properties as their JSON
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum PropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f32),
+    String(String),
+}
+
+impl PropertyValue {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            PropertyValue::Bool(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            PropertyValue::Int(x) => Some(*x),
+            _ => None
+        }
+    }
+
+    /**
+     * Ints convert too, Tiled writes whole floats without a decimal point
+     **/
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            PropertyValue::Float(x) => Some(*x),
+            PropertyValue::Int(x) => Some(*x as f32),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropertyValue::String(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+pub type Properties = HashMap<String, PropertyValue>;
+
+/**
+ * A placed tile, `gid` is global across every tileset in the map
+ * Diagonal flips swap x and y and happen before the horizontal and vertical ones
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Tile {
+    pub gid: u32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub flip_diagonal: bool,
+}
+
+impl Tile {
+    /**
+     * From a gid as Tiled stores it, None for an empty cell
+     **/
+    pub fn from_raw(raw: u32) -> Option<Tile> {
+        let gid = raw & GID_MASK;
+        if gid == 0 {
+            return None;
+        }
+        Some(Tile {
+            gid,
+            flip_horizontal: raw & FLIP_HORIZONTAL != 0,
+            flip_vertical: raw & FLIP_VERTICAL != 0,
+            flip_diagonal: raw & FLIP_DIAGONAL != 0,
+        })
+    }
+
+    pub fn to_raw(self) -> u32 {
+        let mut raw = self.gid & GID_MASK;
+        if self.flip_horizontal {
+            raw |= FLIP_HORIZONTAL;
+        }
+        if self.flip_vertical {
+            raw |= FLIP_VERTICAL;
+        }
+        if self.flip_diagonal {
+            raw |= FLIP_DIAGONAL;
+        }
+        raw
+    }
+
+    /**
+     * Where a point in a `size` tile ends up once the flips are applied, both in pixels
+     **/
+    pub fn flip_point(&self, point: Vec2, size: Vec2) -> Vec2 {
+        let mut p = if self.flip_diagonal { Vec2::new(point.y, point.x) } else { point };
+        if self.flip_horizontal {
+            p.x = size.x - p.x;
+        }
+        if self.flip_vertical {
+            p.y = size.y - p.y;
+        }
+        p
+    }
+}
+
+/**
+ * One frame of an animated tile, `tile` is a local id in the same tileset
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileFrame {
+    pub tile: u32,
+    pub duration: f32,
+}
+
+/**
+ * Extra data a tileset has for one of its tiles
+ * `collision` holds the shapes drawn in Tiled's collision editor, in pixels from the tile's
+ * top left
+ **/
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TileData {
+    pub class: String,
+    pub properties: Properties,
+    pub animation: Vec<TileFrame>,
+    pub collision: Vec<MapObject>,
+}
+
+/**
+ * Tiles cut from one image, `sheet.texture` is the image path resolved against the file
+ * the tileset came from
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    pub name: String,
+    pub first_gid: u32,
+    pub tile_count: u32,
+    pub sheet: SpriteSheet,
+    pub tiles: HashMap<u32, TileData>,
+    pub properties: Properties,
+}
+
+impl Tileset {
+    pub fn contains(&self, gid: u32) -> bool {
+        gid >= self.first_gid && gid - self.first_gid < self.tile_count
+    }
+
+    pub fn tile_size(&self) -> Vec2 {
+        Vec2::new(self.sheet.frame_size.0 as f32, self.sheet.frame_size.1 as f32)
+    }
+
+    pub fn tile(&self, local: u32) -> Option<&TileData> {
+        self.tiles.get(&local)
+    }
+
+    /**
+     * The frame an animated tile shows `time` seconds in, other tiles show themselves
+     **/
+    pub fn animated_tile(&self, local: u32, time: f32) -> u32 {
+        let frames = match self.tiles.get(&local) {
+            Some(x) if !x.animation.is_empty() => &x.animation,
+            _ => return local
+        };
+        let total: f32 = frames.iter().map(|x| x.duration).sum();
+        if total <= 0.0 {
+            return frames[0].tile;
+        }
+        let mut time = time.rem_euclid(total);
+        for frame in frames {
+            if time < frame.duration {
+                return frame.tile;
+            }
+            time -= frame.duration;
+        }
+        frames[frames.len() - 1].tile
+    }
+
+    pub fn is_animated(&self, local: u32) -> bool {
+        self.tiles.get(&local).is_some_and(|x| !x.animation.is_empty())
+    }
+}
+
+/**
+ * Grid of tiles, row by row from the top left, stored as raw gids
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileLayer {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub offset: Vec2,
+    pub opacity: f32,
+    pub visible: bool,
+    pub tiles: Vec<u32>,
+    pub properties: Properties,
+}
+
+impl TileLayer {
+    pub fn new(name: &str, width: u32, height: u32) -> TileLayer {
+        TileLayer {
+            name: name.to_string(),
+            width,
+            height,
+            offset: Vec2::ZERO,
+            opacity: 1.0,
+            visible: true,
+            tiles: vec![0; (width * height) as usize],
+            properties: Properties::new(),
+        }
+    }
+
+    pub fn tile(&self, x: u32, y: u32) -> Option<Tile> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Tile::from_raw(self.tiles[(y * self.width + x) as usize])
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub enum ObjectShape {
+    Rect,
+    Ellipse,
+    Point,
+    /**
+     * Points are relative to the object's position
+     **/
+    Polygon(Vec<Vec2>),
+    Polyline(Vec<Vec2>),
+}
+
+/**
+ * An object from an object layer or a tile's collision shapes, in pixels
+ * Rotation is in degrees clockwise around `position`, which is the top left except for tile
+ * objects, which Tiled anchors at the bottom left
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct MapObject {
+    pub id: u32,
+    pub name: String,
+    pub class: String,
+    pub position: Vec2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub gid: Option<u32>,
+    pub shape: ObjectShape,
+    pub visible: bool,
+    pub properties: Properties,
+}
+
+impl MapObject {
+    /**
+     * Outline in pixels, before rotation and relative to `position`
+     * Ellipses become `segments` sided polygons, points and polylines have no area
+     **/
+    pub fn outline(&self, segments: u32) -> Vec<Vec2> {
+        match &self.shape {
+            ObjectShape::Rect if self.gid.is_some() => vec![
+                Vec2::new(0.0, -self.size.y), Vec2::new(self.size.x, -self.size.y), Vec2::new(self.size.x, 0.0), Vec2::ZERO
+            ],
+            ObjectShape::Rect => vec![Vec2::ZERO, Vec2::new(self.size.x, 0.0), self.size, Vec2::new(0.0, self.size.y)],
+            ObjectShape::Ellipse => {
+                let radius = self.size * 0.5;
+                (0..segments.max(3)).map(|i| {
+                    let angle = i as f32 / segments.max(3) as f32 * std::f32::consts::TAU;
+                    radius + Vec2::new(angle.cos() * radius.x, angle.sin() * radius.y)
+                }).collect()
+            },
+            ObjectShape::Polygon(points) => points.clone(),
+            ObjectShape::Point | ObjectShape::Polyline(_) => Vec::new(),
+        }
+    }
+
+    /**
+     * Outline in map pixels with the rotation applied
+     **/
+    pub fn map_outline(&self, segments: u32) -> Vec<Vec2> {
+        //clockwise on screen is clockwise with y down, which is what rotate does there
+        let angle = self.rotation.to_radians();
+        self.outline(segments).into_iter().map(|x| self.position + x.rotate(angle)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectLayer {
+    pub name: String,
+    pub offset: Vec2,
+    pub visible: bool,
+    pub objects: Vec<MapObject>,
+    pub properties: Properties,
+}
+
+/**
+ * Group layers are flattened when a map is imported, their offsets and visibility carry down
+ * to the layers in them. Image layers are skipped
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapLayer {
+    Tiles(TileLayer),
+    Objects(ObjectLayer),
+}
+
+impl MapLayer {
+    pub fn name(&self) -> &str {
+        match self {
+            MapLayer::Tiles(x) => &x.name,
+            MapLayer::Objects(x) => &x.name
+        }
+    }
+}
+
+/**
+ * An orthogonal tile map, in Tiled's pixel space with y pointing down
+ * `pixels_per_unit` converts to world space, where y points up. It starts at the tile width
+ * so a tile is one world unit across
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tilemap {
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: (u32, u32),
+    pub layers: Vec<MapLayer>,
+    pub tilesets: Vec<Tileset>,
+    pub properties: Properties,
+    pub pixels_per_unit: f32,
+    time: f32,
+    revision: u64,
+}
+
+impl Tilemap {
+    pub fn new(width: u32, height: u32, tile_size: (u32, u32)) -> Tilemap {
+        Tilemap {
+            width,
+            height,
+            tile_size,
+            layers: Vec::new(),
+            tilesets: Vec::new(),
+            properties: Properties::new(),
+            pixels_per_unit: tile_size.0.max(1) as f32,
+            time: 0.0,
+            revision: 0,
+        }
+    }
+
+    /**
+     * Tileset a gid belongs to and the tile's id within it
+     **/
+    pub fn tileset(&self, gid: u32) -> Option<(usize, u32)> {
+        let (index, tileset) = self.tilesets.iter().enumerate().find(|(_, x)| x.contains(gid))?;
+        Some((index, gid - tileset.first_gid))
+    }
+
+    pub fn tile_data(&self, gid: u32) -> Option<&TileData> {
+        let (index, local) = self.tileset(gid)?;
+        self.tilesets[index].tile(local)
+    }
+
+    pub fn layer(&self, name: &str) -> Option<&MapLayer> {
+        self.layers.iter().find(|x| x.name() == name)
+    }
+
+    pub fn tile_layers(&self) -> impl Iterator<Item = &TileLayer> {
+        self.layers.iter().filter_map(|x| match x {
+            MapLayer::Tiles(x) => Some(x),
+            _ => None
+        })
+    }
+
+    pub fn object_layers(&self) -> impl Iterator<Item = &ObjectLayer> {
+        self.layers.iter().filter_map(|x| match x {
+            MapLayer::Objects(x) => Some(x),
+            _ => None
+        })
+    }
+
+    /**
+     * Changes a tile, `tile` None empties the cell. False when the layer or cell doesn't exist
+     **/
+    pub fn set_tile(&mut self, layer: &str, x: u32, y: u32, tile: Option<Tile>) -> bool {
+        let layer = self.layers.iter_mut().find_map(|l| match l {
+            MapLayer::Tiles(l) if l.name == layer => Some(l),
+            _ => None
+        });
+        match layer {
+            Some(l) if x < l.width && y < l.height => {
+                l.tiles[(y * l.width + x) as usize] = tile.map_or(0, |t| t.to_raw());
+                self.revision += 1;
+                true
+            },
+            _ => false
+        }
+    }
+
+    /**
+     * Goes up every time tiles change, renderers rebuild their meshes when it does
+     **/
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /**
+     * Seconds of tile animation played
+     **/
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /**
+     * Moves animated tiles on
+     **/
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt.max(0.0);
+    }
+
+    pub fn to_world(&self, pixel: Vec2) -> Vec2 {
+        Vec2::new(pixel.x, -pixel.y) / self.pixels_per_unit
+    }
+
+    pub fn to_pixels(&self, world: Vec2) -> Vec2 {
+        Vec2::new(world.x, -world.y) * self.pixels_per_unit
+    }
+
+    /**
+     * Cell under a world position, ignoring layer offsets
+     **/
+    pub fn world_to_tile(&self, world: Vec2) -> Option<(u32, u32)> {
+        let pixel = self.to_pixels(world);
+        let (x, y) = ((pixel.x / self.tile_size.0 as f32).floor(), (pixel.y / self.tile_size.1 as f32).floor());
+        if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+            return None;
+        }
+        Some((x as u32, y as u32))
+    }
+}
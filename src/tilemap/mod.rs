@@ -0,0 +1,113 @@
+pub mod map;
+pub mod tiled;
+pub mod collision;
+pub mod objects;
+pub mod renderer;
+
+pub use self::map::{ MapLayer, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, Tile, TileData, TileFrame, TileLayer, Tilemap, Tileset };
+pub use self::collision::TileCollider;
+pub use self::objects::TiledObject;
+pub use self::renderer::TilemapRenderer;
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::core::component::ComponentRegistry;
+use crate::core::scene::SceneError;
+use crate::physics::PhysicsError;
+use crate::ui::UiError;
+
+/**
+ * Registers the tilemap components so scenes and prefabs holding them can be loaded
+ **/
+pub fn register_components(registry: &mut ComponentRegistry) {
+    registry.register::<TiledObject>();
+}
+
+#[derive(Debug)]
+pub enum TilemapError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Xml(roxmltree::Error),
+    Image(image::ImageError),
+    Shader(UiError),
+    Physics(PhysicsError),
+    Scene(SceneError),
+    /**
+     * The file parsed but uses something the importer doesn't understand
+     **/
+    Format(String),
+}
+
+impl fmt::Display for TilemapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TilemapError::Io(e) => write!(f, "Tilemap IO error: {}", e),
+            TilemapError::Json(e) => write!(f, "Tilemap JSON error: {}", e),
+            TilemapError::Xml(e) => write!(f, "Tilemap TMX error: {}", e),
+            TilemapError::Image(e) => write!(f, "Tileset image error: {}", e),
+            TilemapError::Shader(e) => write!(f, "Tilemap shader error: {}", e),
+            TilemapError::Physics(e) => write!(f, "Tilemap collision error: {}", e),
+            TilemapError::Scene(e) => write!(f, "Tilemap object error: {}", e),
+            TilemapError::Format(msg) => write!(f, "Unsupported tilemap: {}", msg),
+        }
+    }
+}
+
+impl Error for TilemapError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TilemapError::Io(e) => Some(e),
+            TilemapError::Json(e) => Some(e),
+            TilemapError::Xml(e) => Some(e),
+            TilemapError::Image(e) => Some(e),
+            TilemapError::Shader(e) => Some(e),
+            TilemapError::Physics(e) => Some(e),
+            TilemapError::Scene(e) => Some(e),
+            TilemapError::Format(_) => None
+        }
+    }
+}
+
+impl From<io::Error> for TilemapError {
+    fn from(e: io::Error) -> TilemapError {
+        TilemapError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for TilemapError {
+    fn from(e: serde_json::Error) -> TilemapError {
+        TilemapError::Json(e)
+    }
+}
+
+impl From<roxmltree::Error> for TilemapError {
+    fn from(e: roxmltree::Error) -> TilemapError {
+        TilemapError::Xml(e)
+    }
+}
+
+impl From<image::ImageError> for TilemapError {
+    fn from(e: image::ImageError) -> TilemapError {
+        TilemapError::Image(e)
+    }
+}
+
+impl From<UiError> for TilemapError {
+    fn from(e: UiError) -> TilemapError {
+        TilemapError::Shader(e)
+    }
+}
+
+impl From<PhysicsError> for TilemapError {
+    fn from(e: PhysicsError) -> TilemapError {
+        TilemapError::Physics(e)
+    }
+}
+
+impl From<SceneError> for TilemapError {
+    fn from(e: SceneError) -> TilemapError {
+        TilemapError::Scene(e)
+    }
+}
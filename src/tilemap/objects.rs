@@ -0,0 +1,90 @@
+use serde::{ Deserialize, Serialize };
+
+use crate::core::component::ComponentRegistry;
+use crate::core::entity::Entity;
+use crate::core::layers::Layer;
+use crate::core::object::Object;
+use crate::core::prefab::PrefabLibrary;
+use crate::core::transform::Transform;
+use crate::math::{ Quat, Vec2 };
+use crate::tilemap::TilemapError;
+use crate::tilemap::map::{ MapObject, ObjectShape, Properties, Tilemap };
+
+/**
+ * What Tiled knew about the object an Entity was spawned from
+ * `size` and the shape's points are in world units, relative to the entity's Transform
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct TiledObject {
+    pub id: u32,
+    pub class: String,
+    pub size: Vec2,
+    pub shape: ObjectShape,
+    pub gid: Option<u32>,
+    pub properties: Properties,
+}
+
+impl_component!(TiledObject, "tiled_object", 1);
+
+impl Tilemap {
+    /**
+     * An engine Layer for every object layer, holding an Entity per object
+     * Objects with a `prefab` property are instantiated from that prefab. Every entity gets a
+     * Transform at the object's origin (top left, or bottom left for tile objects) and a
+     * TiledObject component. Entity ids are the object ids plus `id_offset`
+     **/
+    pub fn spawn_objects(&self, mut prefabs: Option<&mut PrefabLibrary>, registry: &ComponentRegistry, id_offset: Option<u32>)
+        -> Result<Vec<Layer>, TilemapError> {
+        let id_offset = id_offset.unwrap_or(0);
+        let mut layers = Vec::new();
+        for layer in self.object_layers() {
+            let mut objects: Vec<Box<dyn Object>> = Vec::new();
+            for object in &layer.objects {
+                let id = id_offset + object.id;
+                let name = if object.name.is_empty() { object.class.clone() } else { object.name.clone() };
+                let mut entity = match object.properties.get("prefab").and_then(|x| x.as_str()) {
+                    Some(path) => match prefabs.as_deref_mut() {
+                        Some(library) => library.instantiate(path, id, name, registry)?,
+                        None => return Err(TilemapError::Format(format!(
+                            "object {} uses prefab {} but no prefab library was given", object.id, path
+                        )))
+                    },
+                    None => Entity::new(id, name)
+                };
+
+                let translation = self.to_world(layer.offset + object.position).extend(0.0);
+                let rotation = Quat::from_rotation_z(-object.rotation.to_radians());
+                match entity.component_mut::<Transform>() {
+                    //prefabs keep their scale
+                    Some(transform) => {
+                        transform.translation = translation;
+                        transform.rotation = rotation;
+                    },
+                    None => entity.add_component(Box::new(Transform { translation, rotation, ..Transform::identity() }))
+                }
+                entity.add_component(Box::new(self.tiled_object(object)));
+                objects.push(Box::new(entity));
+            }
+            layers.push(Layer::new(layer.visible, Some(objects), layer.name.clone()));
+        }
+        Ok(layers)
+    }
+
+    fn tiled_object(&self, object: &MapObject) -> TiledObject {
+        let scale = |p: &Vec2| Vec2::new(p.x, -p.y) / self.pixels_per_unit;
+        let shape = match &object.shape {
+            ObjectShape::Polygon(points) => ObjectShape::Polygon(points.iter().map(scale).collect()),
+            ObjectShape::Polyline(points) => ObjectShape::Polyline(points.iter().map(scale).collect()),
+            x => x.clone()
+        };
+        TiledObject {
+            id: object.id,
+            class: object.class.clone(),
+            size: object.size / self.pixels_per_unit,
+            shape,
+            gid: object.gid,
+            properties: object.properties.clone(),
+        }
+    }
+}
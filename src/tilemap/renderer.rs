@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+use std::mem;
+use std::os::raw::c_void;
+
+use gl::types::{ GLint, GLsizei, GLsizeiptr, GLuint };
+
+use crate::core::camera::Camera;
+use crate::core::stats;
+use crate::math::Vec2;
+use crate::tilemap::TilemapError;
+use crate::tilemap::map::{ MapLayer, Tilemap };
+use crate::ui::painter::{ compile_shader, link_program };
+
+//chunks are this many tiles across and down
+const CHUNK_SIZE: u32 = 16;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+uniform mat4 u_view_projection;
+layout(location = 0) in vec2 a_pos;
+layout(location = 1) in vec2 a_uv;
+out vec2 v_uv;
+
+void main() {
+    gl_Position = u_view_projection * vec4(a_pos, 0.0, 1.0);
+    v_uv = a_uv;
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform sampler2D u_texture;
+uniform float u_opacity;
+in vec2 v_uv;
+out vec4 frag_color;
+
+void main() {
+    vec4 color = texture(u_texture, v_uv);
+    frag_color = vec4(color.rgb, color.a * u_opacity);
+}
+"#;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct TileVertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+}
+
+/**
+ * The tiles of one layer in one tileset inside a CHUNK_SIZE square of cells
+ **/
+struct Chunk {
+    layer: usize,
+    tileset: usize,
+    cell: (u32, u32),
+    min: Vec2,
+    max: Vec2,
+    animated: bool,
+    vao: GLuint,
+    vbo: GLuint,
+    count: GLsizei,
+}
+
+/**
+ * Draws a Tilemap's tile layers, split into chunks so offscreen parts of the map are skipped
+ * Chunks are rebuilt when the map's revision changes, animated ones every draw. Tileset
+ * images are loaded the first time they're needed
+ * Create and use it only on the thread the context is current on
+ **/
+pub struct TilemapRenderer {
+    program: GLuint,
+    u_view_projection: GLint,
+    u_texture: GLint,
+    u_opacity: GLint,
+    textures: HashMap<String, GLuint>,
+    chunks: Vec<Chunk>,
+    revision: Option<u64>,
+    vertices: Vec<TileVertex>,
+}
+
+impl std::fmt::Debug for TilemapRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "TilemapRenderer {{ program: {}, chunks: {} }}", self.program, self.chunks.len())
+    }
+}
+
+impl TilemapRenderer {
+    /**
+     * Needs the OpenGL symbols loaded
+     **/
+    pub fn new() -> Result<TilemapRenderer, TilemapError> {
+        unsafe {
+            let vertex = compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment = match compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+                Ok(x) => x,
+                Err(e) => {
+                    gl::DeleteShader(vertex);
+                    return Err(e.into());
+                }
+            };
+            let program = link_program(vertex, fragment)?;
+            Ok(TilemapRenderer {
+                program,
+                u_view_projection: gl::GetUniformLocation(program, b"u_view_projection\0".as_ptr() as *const _),
+                u_texture: gl::GetUniformLocation(program, b"u_texture\0".as_ptr() as *const _),
+                u_opacity: gl::GetUniformLocation(program, b"u_opacity\0".as_ptr() as *const _),
+                textures: HashMap::new(),
+                chunks: Vec::new(),
+                revision: None,
+                vertices: Vec::new(),
+            })
+        }
+    }
+
+    /**
+     * Rebuilds every chunk on the next draw, for changes made without Tilemap::set_tile
+     **/
+    pub fn invalidate(&mut self) {
+        self.revision = None;
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /**
+     * Tileset images uploaded so far
+     **/
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    /**
+     * Draws over whatever is in the framebuffer, layers in order with alpha blending
+     **/
+    pub fn draw(&mut self, map: &Tilemap, camera: &Camera, framebuffer: (i32, i32)) {
+        self.draw_at(map, map.time(), camera, framebuffer);
+    }
+
+    /**
+     * Draws with animated tiles at `time` instead of the map's own clock, for snapshots of a
+     * map that keep running while the copy stays the same
+     **/
+    pub fn draw_at(&mut self, map: &Tilemap, time: f32, camera: &Camera, framebuffer: (i32, i32)) {
+        if framebuffer.0 <= 0 || framebuffer.1 <= 0 {
+            return;
+        }
+        if self.revision != Some(map.revision()) {
+            self.rebuild(map, time);
+            self.revision = Some(map.revision());
+        }
+        let visible = camera.visible_rect();
+
+        unsafe {
+            let depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            gl::Viewport(0, 0, framebuffer.0, framebuffer.1);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::BLEND);
+            gl::BlendEquation(gl::FUNC_ADD);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.u_view_projection, 1, gl::FALSE, camera.view_projection().to_cols_array().as_ptr());
+            gl::Uniform1i(self.u_texture, 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            for i in 0..self.chunks.len() {
+                let chunk = &self.chunks[i];
+                let layer = match map.layers.get(chunk.layer) {
+                    Some(MapLayer::Tiles(x)) if x.visible && x.opacity > 0.0 => x,
+                    _ => continue
+                };
+                if let Some((min, max)) = visible {
+                    if chunk.max.x < min.x || chunk.min.x > max.x || chunk.max.y < min.y || chunk.min.y > max.y {
+                        continue;
+                    }
+                }
+                let texture = self.texture(&map.tilesets[chunk.tileset].sheet.texture);
+                if texture == 0 {
+                    continue;
+                }
+
+                let chunk = &self.chunks[i];
+                if chunk.animated {
+                    self.vertices.clear();
+                    fill_chunk(map, time, chunk.layer, chunk.tileset, chunk.cell, &mut self.vertices);
+                    gl::BindBuffer(gl::ARRAY_BUFFER, chunk.vbo);
+                    gl::BufferSubData(gl::ARRAY_BUFFER, 0, (self.vertices.len() * mem::size_of::<TileVertex>()) as GLsizeiptr,
+                    self.vertices.as_ptr() as *const c_void);
+                }
+                gl::Uniform1f(self.u_opacity, layer.opacity);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::BindVertexArray(chunk.vao);
+                gl::DrawArrays(gl::TRIANGLES, 0, chunk.count);
+                stats::add_draw_calls(1);
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::UseProgram(0);
+            gl::Disable(gl::BLEND);
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+        }
+    }
+
+    fn rebuild(&mut self, map: &Tilemap, time: f32) {
+        self.delete_chunks();
+        for (index, layer) in map.layers.iter().enumerate() {
+            let layer = match layer {
+                MapLayer::Tiles(x) => x,
+                MapLayer::Objects(_) => continue
+            };
+            let columns = layer.width.div_ceil(CHUNK_SIZE);
+            let rows = layer.height.div_ceil(CHUNK_SIZE);
+            for cy in 0..rows {
+                for cx in 0..columns {
+                    for tileset in 0..map.tilesets.len() {
+                        self.vertices.clear();
+                        let cell = (cx * CHUNK_SIZE, cy * CHUNK_SIZE);
+                        let animated = fill_chunk(map, time, index, tileset, cell, &mut self.vertices);
+                        if self.vertices.is_empty() {
+                            continue;
+                        }
+                        let chunk = self.upload(index, tileset, cell, animated);
+                        self.chunks.push(chunk);
+                    }
+                }
+            }
+        }
+        debug!("Built {} tilemap chunks", self.chunks.len());
+    }
+
+    fn upload(&self, layer: usize, tileset: usize, cell: (u32, u32), animated: bool) -> Chunk {
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for x in &self.vertices {
+            min = min.min(Vec2::from(x.pos));
+            max = max.max(Vec2::from(x.pos));
+        }
+        unsafe {
+            let mut vao = 0;
+            let mut vbo = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BufferData(gl::ARRAY_BUFFER, (self.vertices.len() * mem::size_of::<TileVertex>()) as GLsizeiptr,
+            self.vertices.as_ptr() as *const c_void, if animated { gl::DYNAMIC_DRAW } else { gl::STATIC_DRAW });
+            let stride = mem::size_of::<TileVertex>() as GLsizei;
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(TileVertex, pos) as *const c_void);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(TileVertex, uv) as *const c_void);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            Chunk { layer, tileset, cell, min, max, animated, vao, vbo, count: self.vertices.len() as GLsizei }
+        }
+    }
+
+    /**
+     * GL texture for a tileset image, 0 if it couldn't be loaded
+     **/
+    fn texture(&mut self, path: &str) -> GLuint {
+        if let Some(x) = self.textures.get(path) {
+            return *x;
+        }
+        let texture = match image::open(path) {
+            Ok(image) => {
+                let image = image.to_rgba();
+                let (w, h) = image.dimensions();
+                let mut x = 0;
+                unsafe {
+                    gl::GenTextures(1, &mut x);
+                    gl::BindTexture(gl::TEXTURE_2D, x);
+                    gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+                    //pixel art would blur with linear filtering
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+                    gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, w as GLsizei, h as GLsizei, 0, gl::RGBA, gl::UNSIGNED_BYTE,
+                    image.as_ptr() as *const c_void);
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                }
+                x
+            },
+            Err(e) => {
+                error!("Couldn't load tileset image {}: {}", path, e);
+                0
+            }
+        };
+        //failures are remembered too so they're only reported once
+        self.textures.insert(path.to_string(), texture);
+        texture
+    }
+
+    fn delete_chunks(&mut self) {
+        for chunk in self.chunks.drain(..) {
+            unsafe {
+                gl::DeleteBuffers(1, &chunk.vbo);
+                gl::DeleteVertexArrays(1, &chunk.vao);
+            }
+        }
+    }
+}
+
+/**
+ * Appends 6 vertices for every tile from `tileset` in the chunk starting at `cell`
+ * Returns whether any of them are animated
+ **/
+fn fill_chunk(map: &Tilemap, time: f32, layer: usize, tileset: usize, cell: (u32, u32), out: &mut Vec<TileVertex>) -> bool {
+    let layer = match &map.layers[layer] {
+        MapLayer::Tiles(x) => x,
+        MapLayer::Objects(_) => return false
+    };
+    let set = &map.tilesets[tileset];
+    let size = set.tile_size();
+    let mut animated = false;
+    for y in cell.1..(cell.1 + CHUNK_SIZE).min(layer.height) {
+        for x in cell.0..(cell.0 + CHUNK_SIZE).min(layer.width) {
+            let tile = match layer.tile(x, y) {
+                Some(t) if set.contains(t.gid) => t,
+                _ => continue
+            };
+            let local = tile.gid - set.first_gid;
+            animated |= set.is_animated(local);
+            let (uv_min, uv_max) = match set.sheet.uv(set.animated_tile(local, time)) {
+                Some(x) => x,
+                None => continue
+            };
+            //tiles taller than the grid stick up out of their cell
+            let origin = layer.offset + Vec2::new(
+                x as f32 * map.tile_size.0 as f32,
+                (y + 1) as f32 * map.tile_size.1 as f32 - size.y
+            );
+            let corner = |c: Vec2| {
+                //undo the flips to find which part of the image lands on this corner
+                let mut q = c;
+                if tile.flip_vertical {
+                    q.y = 1.0 - q.y;
+                }
+                if tile.flip_horizontal {
+                    q.x = 1.0 - q.x;
+                }
+                if tile.flip_diagonal {
+                    q = Vec2::new(q.y, q.x);
+                }
+                TileVertex {
+                    pos: map.to_world(origin + c * size).to_array(),
+                    uv: (uv_min + q * (uv_max - uv_min)).to_array(),
+                }
+            };
+            let (tl, tr) = (corner(Vec2::new(0.0, 0.0)), corner(Vec2::new(1.0, 0.0)));
+            let (bl, br) = (corner(Vec2::new(0.0, 1.0)), corner(Vec2::new(1.0, 1.0)));
+            out.extend_from_slice(&[bl, br, tr, bl, tr, tl]);
+        }
+    }
+    animated
+}
+
+impl Drop for TilemapRenderer {
+    fn drop(&mut self) {
+        self.delete_chunks();
+        unsafe {
+            for x in self.textures.values() {
+                gl::DeleteTextures(1, x);
+            }
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
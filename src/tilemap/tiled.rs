@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+use std::str::FromStr;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use flate2::read::{ GzDecoder, ZlibDecoder };
+use roxmltree::{ Document, Node };
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::animation::sprite::SpriteSheet;
+use crate::math::Vec2;
+use crate::tilemap::TilemapError;
+use crate::tilemap::map::{
+    MapLayer, MapObject, ObjectLayer, ObjectShape, Properties, PropertyValue, TileData, TileFrame, TileLayer, Tilemap, Tileset
+};
+
+impl Tilemap {
+    /**
+     * Loads a map saved by Tiled, .tmx files as XML and anything else as JSON
+     * Tilesets and images are looked up relative to the map
+     **/
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Tilemap, TilemapError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let is_tmx = path.extension().is_some_and(|x| x.eq_ignore_ascii_case("tmx"));
+        if is_tmx {
+            Tilemap::from_tmx(&text, path.parent())
+        } else {
+            Tilemap::from_tiled_json(&text, path.parent())
+        }
+    }
+
+    /**
+     * Parses a map in Tiled's JSON format, `base` is the directory relative paths start from
+     **/
+    pub fn from_tiled_json(json: &str, base: Option<&Path>) -> Result<Tilemap, TilemapError> {
+        let raw: JsonMap = serde_json::from_str(json)?;
+        check_map(&raw.orientation, raw.infinite)?;
+
+        let mut map = Tilemap::new(raw.width, raw.height, (raw.tilewidth, raw.tileheight));
+        map.properties = json_properties(&raw.properties);
+        for tileset in raw.tilesets {
+            let first_gid = tileset.firstgid;
+            map.tilesets.push(match &tileset.source {
+                Some(source) => external_tileset(source, first_gid, base)?,
+                None => json_tileset(tileset, first_gid, base)?
+            });
+        }
+        json_layers(raw.layers, Vec2::ZERO, true, 1.0, &mut map.layers)?;
+        Ok(map)
+    }
+
+    /**
+     * Parses a map in Tiled's TMX format, `base` is the directory relative paths start from
+     **/
+    pub fn from_tmx(xml: &str, base: Option<&Path>) -> Result<Tilemap, TilemapError> {
+        let document = Document::parse(xml)?;
+        let root = document.root_element();
+        if !root.has_tag_name("map") {
+            return Err(TilemapError::Format(format!("expected <map>, found <{}>", root.tag_name().name())));
+        }
+        let orientation = root.attribute("orientation").unwrap_or("orthogonal");
+        check_map(orientation, root.attribute("infinite") == Some("1"))?;
+
+        let mut map = Tilemap::new(
+            required(root, "width")?,
+            required(root, "height")?,
+            (required(root, "tilewidth")?, required(root, "tileheight")?)
+        );
+        for child in root.children().filter(|x| x.is_element()) {
+            match child.tag_name().name() {
+                "properties" => map.properties = tmx_properties(child),
+                "tileset" => {
+                    let first_gid = required(child, "firstgid")?;
+                    map.tilesets.push(match child.attribute("source") {
+                        Some(source) => external_tileset(source, first_gid, base)?,
+                        None => tmx_tileset(child, first_gid, base)?
+                    });
+                },
+                _ => {}
+            }
+        }
+        tmx_layers(root, Vec2::ZERO, true, 1.0, &mut map.layers)?;
+        Ok(map)
+    }
+}
+
+fn check_map(orientation: &str, infinite: bool) -> Result<(), TilemapError> {
+    if orientation != "orthogonal" {
+        return Err(TilemapError::Format(format!("{} maps aren't supported, only orthogonal", orientation)));
+    }
+    if infinite {
+        return Err(TilemapError::Format("infinite maps aren't supported".to_string()));
+    }
+    Ok(())
+}
+
+fn resolve(base: Option<&Path>, path: &str) -> PathBuf {
+    match base {
+        Some(base) => base.join(path),
+        None => PathBuf::from(path)
+    }
+}
+
+/**
+ * Tilesets saved in their own file, .tsx as XML and anything else as JSON
+ **/
+fn external_tileset(source: &str, first_gid: u32, base: Option<&Path>) -> Result<Tileset, TilemapError> {
+    let path = resolve(base, source);
+    let text = fs::read_to_string(&path)?;
+    let base = path.parent();
+    if path.extension().is_some_and(|x| x.eq_ignore_ascii_case("tsx")) {
+        let document = Document::parse(&text)?;
+        tmx_tileset(document.root_element(), first_gid, base)
+    } else {
+        json_tileset(serde_json::from_str(&text)?, first_gid, base)
+    }
+}
+
+/**
+ * Only tilesets cut from a single image are supported, image collections have no sheet
+ **/
+#[allow(clippy::too_many_arguments)]
+fn build_tileset(
+    name: String,
+    first_gid: u32,
+    tile_size: (u32, u32),
+    tile_count: u32,
+    image: Option<(String, u32, u32)>,
+    margin: u32,
+    spacing: u32,
+    tiles: HashMap<u32, TileData>,
+    properties: Properties,
+    base: Option<&Path>
+) -> Result<Tileset, TilemapError> {
+    let (source, width, height) = match image {
+        Some(x) => x,
+        None => return Err(TilemapError::Format(format!("tileset {} is an image collection, which isn't supported", name)))
+    };
+    let path = resolve(base, &source);
+    //older files leave the size out
+    let texture_size = if width == 0 || height == 0 { image::image_dimensions(&path)? } else { (width, height) };
+    let mut sheet = SpriteSheet::new(&path.to_string_lossy(), texture_size, tile_size);
+    sheet.margin = margin;
+    sheet.spacing = spacing;
+    let tile_count = if tile_count == 0 { sheet.len() } else { tile_count };
+    Ok(Tileset { name, first_gid, tile_count, sheet, tiles, properties })
+}
+
+fn check_layer(name: &str, tiles: &[u32], width: u32, height: u32) -> Result<(), TilemapError> {
+    if tiles.len() != (width * height) as usize {
+        return Err(TilemapError::Format(format!(
+            "layer {} has {} tiles, expected {}x{}", name, tiles.len(), width, height
+        )));
+    }
+    Ok(())
+}
+
+/**
+ * Layer data saved as text, csv or base64 with optional zlib or gzip compression
+ **/
+fn decode_data(text: &str, encoding: &str, compression: &str) -> Result<Vec<u32>, TilemapError> {
+    match encoding {
+        "csv" => text.split(',')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty())
+            .map(|x| x.parse::<u32>().map_err(|_| TilemapError::Format(format!("bad tile in csv data: {}", x))))
+            .collect(),
+        "base64" => {
+            let cleaned: String = text.chars().filter(|x| !x.is_whitespace()).collect();
+            let bytes = STANDARD.decode(cleaned).map_err(|e| TilemapError::Format(format!("bad base64 data: {}", e)))?;
+            let bytes = match compression {
+                "" => bytes,
+                "zlib" => {
+                    let mut out = Vec::new();
+                    ZlibDecoder::new(&bytes[..]).read_to_end(&mut out)?;
+                    out
+                },
+                "gzip" => {
+                    let mut out = Vec::new();
+                    GzDecoder::new(&bytes[..]).read_to_end(&mut out)?;
+                    out
+                },
+                other => return Err(TilemapError::Format(format!("{} compression isn't supported", other)))
+            };
+            if bytes.len() % 4 != 0 {
+                return Err(TilemapError::Format("base64 data isn't a whole number of tiles".to_string()));
+            }
+            Ok(bytes.chunks_exact(4).map(|x| u32::from_le_bytes([x[0], x[1], x[2], x[3]])).collect())
+        },
+        other => Err(TilemapError::Format(format!("{} encoding isn't supported", other)))
+    }
+}
+
+//Tiled's JSON format
+
+fn orthogonal() -> String {
+    "orthogonal".to_string()
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn yes() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct JsonMap {
+    #[serde(default = "orthogonal")]
+    orientation: String,
+    width: u32,
+    height: u32,
+    tilewidth: u32,
+    tileheight: u32,
+    #[serde(default)]
+    infinite: bool,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    tilesets: Vec<JsonTileset>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonProperty {
+    name: String,
+    #[serde(default, rename = "type")]
+    kind: String,
+    #[serde(default)]
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct JsonLayer {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+    data: Option<Value>,
+    #[serde(default)]
+    encoding: String,
+    #[serde(default)]
+    compression: String,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default = "one")]
+    opacity: f32,
+    #[serde(default = "yes")]
+    visible: bool,
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+    #[serde(default)]
+    layers: Vec<JsonLayer>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonTileset {
+    #[serde(default)]
+    firstgid: u32,
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    tilewidth: u32,
+    #[serde(default)]
+    tileheight: u32,
+    #[serde(default)]
+    tilecount: u32,
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: u32,
+    #[serde(default)]
+    imageheight: u32,
+    #[serde(default)]
+    margin: u32,
+    #[serde(default)]
+    spacing: u32,
+    #[serde(default)]
+    tiles: Vec<JsonTile>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonTile {
+    id: u32,
+    //"type" before Tiled 1.9, "class" after
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    class: Option<String>,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+    #[serde(default)]
+    animation: Vec<JsonFrame>,
+    objectgroup: Option<JsonObjectGroup>,
+}
+
+#[derive(Deserialize)]
+struct JsonFrame {
+    tileid: u32,
+    duration: u32,
+}
+
+#[derive(Deserialize)]
+struct JsonObjectGroup {
+    #[serde(default)]
+    objects: Vec<JsonObject>,
+}
+
+#[derive(Deserialize)]
+struct JsonObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: Option<String>,
+    class: Option<String>,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    #[serde(default)]
+    rotation: f32,
+    gid: Option<u32>,
+    #[serde(default)]
+    ellipse: bool,
+    #[serde(default)]
+    point: bool,
+    polygon: Option<Vec<JsonPoint>>,
+    polyline: Option<Vec<JsonPoint>>,
+    #[serde(default = "yes")]
+    visible: bool,
+    #[serde(default)]
+    properties: Vec<JsonProperty>,
+}
+
+#[derive(Deserialize)]
+struct JsonPoint {
+    x: f32,
+    y: f32,
+}
+
+fn json_properties(raw: &[JsonProperty]) -> Properties {
+    raw.iter().map(|x| {
+        let value = match (x.kind.as_str(), &x.value) {
+            ("bool", Value::Bool(b)) => PropertyValue::Bool(*b),
+            ("int" | "object", v) => PropertyValue::Int(v.as_i64().or_else(|| v.as_f64().map(|f| f as i64)).unwrap_or(0)),
+            ("float", v) => PropertyValue::Float(v.as_f64().unwrap_or(0.0) as f32),
+            (_, Value::String(s)) => PropertyValue::String(s.clone()),
+            //class properties keep their JSON
+            (_, v) => PropertyValue::String(v.to_string())
+        };
+        (x.name.clone(), value)
+    }).collect()
+}
+
+fn json_object(raw: JsonObject) -> MapObject {
+    let points = |x: Vec<JsonPoint>| x.into_iter().map(|p| Vec2::new(p.x, p.y)).collect();
+    let shape = if let Some(x) = raw.polygon {
+        ObjectShape::Polygon(points(x))
+    } else if let Some(x) = raw.polyline {
+        ObjectShape::Polyline(points(x))
+    } else if raw.ellipse {
+        ObjectShape::Ellipse
+    } else if raw.point {
+        ObjectShape::Point
+    } else {
+        ObjectShape::Rect
+    };
+    MapObject {
+        id: raw.id,
+        name: raw.name,
+        class: raw.class.or(raw.kind).unwrap_or_default(),
+        position: Vec2::new(raw.x, raw.y),
+        size: Vec2::new(raw.width, raw.height),
+        rotation: raw.rotation,
+        gid: raw.gid,
+        shape,
+        visible: raw.visible,
+        properties: json_properties(&raw.properties),
+    }
+}
+
+fn json_tileset(raw: JsonTileset, first_gid: u32, base: Option<&Path>) -> Result<Tileset, TilemapError> {
+    let tiles = raw.tiles.into_iter().map(|x| {
+        let data = TileData {
+            class: x.class.or(x.kind).unwrap_or_default(),
+            properties: json_properties(&x.properties),
+            animation: x.animation.iter().map(|f| TileFrame { tile: f.tileid, duration: f.duration as f32 / 1000.0 }).collect(),
+            collision: x.objectgroup.map(|g| g.objects.into_iter().map(json_object).collect()).unwrap_or_default(),
+        };
+        (x.id, data)
+    }).collect();
+    let (width, height) = (raw.imagewidth, raw.imageheight);
+    let image = raw.image.map(|x| (x, width, height));
+    build_tileset(
+        raw.name, first_gid, (raw.tilewidth, raw.tileheight), raw.tilecount, image, raw.margin, raw.spacing, tiles,
+        json_properties(&raw.properties), base
+    )
+}
+
+fn json_layers(raw: Vec<JsonLayer>, offset: Vec2, visible: bool, opacity: f32, out: &mut Vec<MapLayer>) -> Result<(), TilemapError> {
+    for layer in raw {
+        let offset = offset + Vec2::new(layer.offsetx, layer.offsety);
+        let visible = visible && layer.visible;
+        let opacity = opacity * layer.opacity;
+        match layer.kind.as_str() {
+            "tilelayer" => {
+                let tiles = match &layer.data {
+                    Some(Value::Array(x)) => x.iter().map(|t| t.as_u64().unwrap_or(0) as u32).collect(),
+                    Some(Value::String(x)) => decode_data(x, &layer.encoding, &layer.compression)?,
+                    _ => return Err(TilemapError::Format(format!("layer {} has no tile data", layer.name)))
+                };
+                check_layer(&layer.name, &tiles, layer.width, layer.height)?;
+                out.push(MapLayer::Tiles(TileLayer {
+                    properties: json_properties(&layer.properties),
+                    name: layer.name,
+                    width: layer.width,
+                    height: layer.height,
+                    offset,
+                    opacity,
+                    visible,
+                    tiles,
+                }));
+            },
+            "objectgroup" => out.push(MapLayer::Objects(ObjectLayer {
+                properties: json_properties(&layer.properties),
+                name: layer.name,
+                offset,
+                visible,
+                objects: layer.objects.into_iter().map(json_object).collect(),
+            })),
+            "group" => json_layers(layer.layers, offset, visible, opacity, out)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+//Tiled's XML format
+
+fn attribute<T: FromStr>(node: Node, name: &str) -> Option<T> {
+    node.attribute(name).and_then(|x| x.parse().ok())
+}
+
+fn required<T: FromStr>(node: Node, name: &str) -> Result<T, TilemapError> {
+    attribute(node, name).ok_or_else(|| {
+        TilemapError::Format(format!("<{}> is missing {} or it isn't valid", node.tag_name().name(), name))
+    })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name(name))
+}
+
+fn tmx_properties(node: Node) -> Properties {
+    node.children().filter(|x| x.has_tag_name("property")).filter_map(|x| {
+        let name = x.attribute("name")?.to_string();
+        //multiline strings go in the element's text instead
+        let text = x.attribute("value").or_else(|| x.text()).unwrap_or("");
+        let value = match x.attribute("type").unwrap_or("string") {
+            "bool" => PropertyValue::Bool(text == "true"),
+            "int" | "object" => text.parse().map(PropertyValue::Int).unwrap_or_else(|_| PropertyValue::String(text.to_string())),
+            "float" => text.parse().map(PropertyValue::Float).unwrap_or_else(|_| PropertyValue::String(text.to_string())),
+            _ => PropertyValue::String(text.to_string())
+        };
+        Some((name, value))
+    }).collect()
+}
+
+fn tmx_own_properties(node: Node) -> Properties {
+    child(node, "properties").map(tmx_properties).unwrap_or_default()
+}
+
+fn tmx_points(text: &str) -> Vec<Vec2> {
+    text.split_whitespace().filter_map(|x| {
+        let (x, y) = x.split_once(',')?;
+        Some(Vec2::new(x.parse().ok()?, y.parse().ok()?))
+    }).collect()
+}
+
+fn tmx_object(node: Node) -> MapObject {
+    let shape = if let Some(x) = child(node, "polygon") {
+        ObjectShape::Polygon(tmx_points(x.attribute("points").unwrap_or("")))
+    } else if let Some(x) = child(node, "polyline") {
+        ObjectShape::Polyline(tmx_points(x.attribute("points").unwrap_or("")))
+    } else if child(node, "ellipse").is_some() {
+        ObjectShape::Ellipse
+    } else if child(node, "point").is_some() {
+        ObjectShape::Point
+    } else {
+        ObjectShape::Rect
+    };
+    MapObject {
+        id: attribute(node, "id").unwrap_or(0),
+        name: node.attribute("name").unwrap_or("").to_string(),
+        class: node.attribute("class").or_else(|| node.attribute("type")).unwrap_or("").to_string(),
+        position: Vec2::new(attribute(node, "x").unwrap_or(0.0), attribute(node, "y").unwrap_or(0.0)),
+        size: Vec2::new(attribute(node, "width").unwrap_or(0.0), attribute(node, "height").unwrap_or(0.0)),
+        rotation: attribute(node, "rotation").unwrap_or(0.0),
+        gid: attribute(node, "gid"),
+        shape,
+        visible: node.attribute("visible") != Some("0"),
+        properties: tmx_own_properties(node),
+    }
+}
+
+fn tmx_tileset(node: Node, first_gid: u32, base: Option<&Path>) -> Result<Tileset, TilemapError> {
+    if !node.has_tag_name("tileset") {
+        return Err(TilemapError::Format(format!("expected <tileset>, found <{}>", node.tag_name().name())));
+    }
+    let tiles = node.children().filter(|x| x.has_tag_name("tile")).map(|x| {
+        let animation = child(x, "animation").map(|a| {
+            a.children().filter(|f| f.has_tag_name("frame")).map(|f| TileFrame {
+                tile: attribute(f, "tileid").unwrap_or(0),
+                duration: attribute::<u32>(f, "duration").unwrap_or(0) as f32 / 1000.0,
+            }).collect()
+        }).unwrap_or_default();
+        let collision = child(x, "objectgroup").map(|g| {
+            g.children().filter(|o| o.has_tag_name("object")).map(tmx_object).collect()
+        }).unwrap_or_default();
+        let data = TileData {
+            class: x.attribute("class").or_else(|| x.attribute("type")).unwrap_or("").to_string(),
+            properties: tmx_own_properties(x),
+            animation,
+            collision,
+        };
+        Ok((required(x, "id")?, data))
+    }).collect::<Result<HashMap<u32, TileData>, TilemapError>>()?;
+    let image = child(node, "image").and_then(|x| {
+        Some((x.attribute("source")?.to_string(), attribute(x, "width").unwrap_or(0), attribute(x, "height").unwrap_or(0)))
+    });
+    build_tileset(
+        node.attribute("name").unwrap_or("").to_string(),
+        first_gid,
+        (required(node, "tilewidth")?, required(node, "tileheight")?),
+        attribute(node, "tilecount").unwrap_or(0),
+        image,
+        attribute(node, "margin").unwrap_or(0),
+        attribute(node, "spacing").unwrap_or(0),
+        tiles,
+        tmx_own_properties(node),
+        base
+    )
+}
+
+fn tmx_layers(parent: Node, offset: Vec2, visible: bool, opacity: f32, out: &mut Vec<MapLayer>) -> Result<(), TilemapError> {
+    for node in parent.children().filter(|x| x.is_element()) {
+        let name = node.attribute("name").unwrap_or("").to_string();
+        let offset = offset + Vec2::new(attribute(node, "offsetx").unwrap_or(0.0), attribute(node, "offsety").unwrap_or(0.0));
+        let visible = visible && node.attribute("visible") != Some("0");
+        let opacity = opacity * attribute(node, "opacity").unwrap_or(1.0);
+        match node.tag_name().name() {
+            "layer" => {
+                let (width, height) = (required(node, "width")?, required(node, "height")?);
+                let data = match child(node, "data") {
+                    Some(x) => x,
+                    None => return Err(TilemapError::Format(format!("layer {} has no tile data", name)))
+                };
+                let tiles = match data.attribute("encoding") {
+                    Some(encoding) => decode_data(data.text().unwrap_or(""), encoding, data.attribute("compression").unwrap_or(""))?,
+                    //unencoded data is one <tile> per cell
+                    None => data.children().filter(|x| x.has_tag_name("tile")).map(|x| attribute(x, "gid").unwrap_or(0)).collect()
+                };
+                check_layer(&name, &tiles, width, height)?;
+                out.push(MapLayer::Tiles(TileLayer {
+                    name,
+                    width,
+                    height,
+                    offset,
+                    opacity,
+                    visible,
+                    tiles,
+                    properties: tmx_own_properties(node),
+                }));
+            },
+            "objectgroup" => out.push(MapLayer::Objects(ObjectLayer {
+                name,
+                offset,
+                visible,
+                objects: node.children().filter(|x| x.has_tag_name("object")).map(tmx_object).collect(),
+                properties: tmx_own_properties(node),
+            })),
+            "group" => tmx_layers(node, offset, visible, opacity, out)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
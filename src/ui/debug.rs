@@ -0,0 +1,648 @@
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::time::Instant;
+
+use egui::{ ClippedPrimitive, Color32, Context, Key, Modifiers, Pos2, RawInput, Rect, TexturesDelta, Vec2, ViewportId };
+use egui::text::{ CCursor, CCursorRange };
+use log::Level;
+
+use crate::audio::Bus;
+use crate::console::{ self, Console, ConsoleError, LineKind };
+use crate::console::registry::common_prefix;
+use crate::core::layers::{ Layer, LayerStack };
+use crate::core::object::Object;
+use crate::core::stats::{ FrameStats, FRAME_HISTORY };
+use crate::core::settings::{ GraphicsMode, LogLevel, Settings };
+use crate::events::event::{ Event, EventData, EventType };
+use crate::ui::input::InputTranslator;
+
+/**
+ * Name of the overlay layer the debug UI lives in
+ **/
+pub const DEBUG_UI_LAYER: &str = "Debug UI";
+
+/**
+ * Object id of the debug UI, well past the ids scenes and scripts hand out
+ **/
+pub const DEBUG_UI_ID: u32 = u32::MAX;
+
+/**
+ * Glfw keycode of F1, which shows and hides the debug UI
+ **/
+pub const TOGGLE_KEY: i32 = 290;
+
+/**
+ * A tessellated UI frame, ready for the render thread to draw
+ **/
+#[derive(Debug, Clone)]
+pub struct UiFrame {
+    pub primitives: Vec<ClippedPrimitive>,
+    pub pixels_per_point: f32,
+}
+
+/**
+ * What the layer panel shows about a layer
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerInfo {
+    pub name: String,
+    pub enabled: bool,
+    pub overlay: bool,
+    pub objects: usize,
+}
+
+#[derive(Debug, Default)]
+struct UiOutput {
+    frame: Option<Arc<UiFrame>>,
+    //texture changes pile up until the render thread takes them, none can be skipped
+    textures: TexturesDelta,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Screen {
+    size: Vec2,
+    pixels_per_point: f32,
+}
+
+#[derive(Debug, Default)]
+struct LayerPanel {
+    layers: Vec<LayerInfo>,
+    toggles: Vec<(usize, String, bool)>,
+}
+
+#[derive(Debug)]
+struct SettingsPanel {
+    name: String,
+    settings: Settings,
+    changed: bool,
+}
+
+/**
+ * Shared between the debug UI on the update thread and the application's render loop
+ * The render loop feeds it the window size and draws what the UI publishes,
+ * the update loop lets it see and toggle the layer stack
+ * It also holds the developer console, whose cvars edit the same settings the editor does
+ **/
+#[derive(Debug)]
+pub struct DebugUiState {
+    visible: AtomicBool,
+    console_open: AtomicBool,
+    console: Arc<Console>,
+    output: Mutex<UiOutput>,
+    screen: Mutex<Option<Screen>>,
+    layers: Mutex<LayerPanel>,
+    settings: Mutex<SettingsPanel>,
+}
+
+impl DebugUiState {
+    /**
+     * `name` is the application name, the settings editor saves to {name}.json
+     **/
+    pub fn new(name: &str, settings: Settings) -> DebugUiState {
+        DebugUiState {
+            visible: AtomicBool::new(true),
+            console_open: AtomicBool::new(false),
+            console: Arc::new(Console::new()),
+            output: Mutex::new(UiOutput::default()),
+            screen: Mutex::new(None),
+            layers: Mutex::new(LayerPanel::default()),
+            settings: Mutex::new(SettingsPanel { name: name.to_string(), settings, changed: false }),
+        }
+    }
+
+    #[inline]
+    pub fn visible(&self) -> bool {
+        self.visible.load(Ordering::Relaxed)
+    }
+
+    pub fn set_visible(&self, visible: bool) {
+        self.visible.store(visible, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn console_open(&self) -> bool {
+        self.console_open.load(Ordering::Relaxed)
+    }
+
+    pub fn set_console_open(&self, open: bool) {
+        self.console_open.store(open, Ordering::Relaxed);
+    }
+
+    /**
+     * The console, for registering commands and cvars
+     **/
+    pub fn console(&self) -> Arc<Console> {
+        Arc::clone(&self.console)
+    }
+
+    /**
+     * Runs a console line as if it was typed in, settings it changes go to the application
+     * like the settings editor's do
+     **/
+    pub fn execute(&self, line: &str) {
+        self.edit_settings(|settings| self.console.submit(line, settings));
+    }
+
+    /**
+     * Runs a console script file, see Console::run_script
+     **/
+    pub fn run_script(&self, path: &str) -> Result<(), ConsoleError> {
+        self.edit_settings(|settings| self.console.run_script(path, settings))
+    }
+
+    //commands run without the lock held, so they're free to use the debug UI themselves
+    fn edit_settings<R, F: FnOnce(&mut Settings) -> R>(&self, edit: F) -> R {
+        let before = self.settings();
+        let mut settings = before.clone();
+        let result = edit(&mut settings);
+        if settings != before {
+            self.set_settings(settings);
+        }
+        result
+    }
+
+    /**
+     * Window size in screen coordinates and framebuffer size in pixels
+     * The UI stays inactive until this is set, so backends that can't draw it never set it
+     **/
+    pub fn set_screen(&self, window: (i32, i32), framebuffer: (i32, i32)) {
+        if window.0 <= 0 || window.1 <= 0 {
+            return;
+        }
+        let screen = Screen {
+            size: Vec2::new(window.0 as f32, window.1 as f32),
+            pixels_per_point: (framebuffer.0 as f32 / window.0 as f32).max(0.5),
+        };
+        if let Ok(mut x) = self.screen.lock() {
+            *x = Some(screen);
+        }
+    }
+
+    fn screen(&self) -> Option<Screen> {
+        self.screen.lock().ok().and_then(|x| *x)
+    }
+
+    fn publish(&self, frame: Option<Arc<UiFrame>>, textures: TexturesDelta) {
+        match self.output.lock() {
+            Ok(mut x) => {
+                x.frame = frame;
+                x.textures.append(textures);
+            },
+            _ => error!("Debug UI output lock is Poisoned")
+        }
+    }
+
+    /**
+     * The latest frame with the texture changes made up to it, None while the UI is hidden
+     * Textures are handed over once, the frame is handed over until a newer one replaces it
+     **/
+    pub fn take_output(&self) -> Option<(Arc<UiFrame>, TexturesDelta)> {
+        let mut output = self.output.lock().ok()?;
+        let frame = Arc::clone(output.frame.as_ref()?);
+        Some((frame, std::mem::take(&mut output.textures)))
+    }
+
+    /**
+     * Applies toggles made in the layer panel and refreshes what it shows
+     * Called by the update loop, which is the one holding the layer stack
+     **/
+    pub fn sync_layers(&self, stack: &mut LayerStack) {
+        let mut panel = match self.layers.lock() {
+            Ok(x) => x,
+            _ => return
+        };
+        let overlay_start = stack.overlay_start();
+        for (index, name, enabled) in std::mem::take(&mut panel.toggles) {
+            //skip toggles for layers that moved since the panel saw them
+            match stack.iter_mut().nth(index) {
+                Some(layer) if *layer.debug_name() == name => layer.set_enabled(enabled),
+                _ => debug!("Layer {} changed before its toggle applied", name)
+            }
+        }
+        panel.layers = stack.iter().enumerate().map(|(i, x)| LayerInfo {
+            name: x.debug_name().clone(),
+            enabled: x.enabled(),
+            overlay: i >= overlay_start,
+            objects: x.object_count(),
+        }).collect();
+    }
+
+    pub fn layers(&self) -> Vec<LayerInfo> {
+        match self.layers.lock() {
+            Ok(x) => x.layers.clone(),
+            _ => Vec::new()
+        }
+    }
+
+    fn toggle_layer(&self, index: usize, name: &str, enabled: bool) {
+        if let Ok(mut x) = self.layers.lock() {
+            x.toggles.push((index, name.to_string(), enabled));
+            if let Some(layer) = x.layers.get_mut(index) {
+                layer.enabled = enabled;
+            }
+        }
+    }
+
+    pub fn settings(&self) -> Settings {
+        match self.settings.lock() {
+            Ok(x) => x.settings.clone(),
+            Err(e) => e.into_inner().settings.clone()
+        }
+    }
+
+    fn set_settings(&self, settings: Settings) {
+        if let Ok(mut x) = self.settings.lock() {
+            x.settings = settings;
+            x.changed = true;
+        }
+    }
+
+    /**
+     * Settings edited since the last call, for the application to apply
+     **/
+    pub fn take_settings_change(&self) -> Option<Settings> {
+        let mut x = self.settings.lock().ok()?;
+        if !x.changed {
+            return None;
+        }
+        x.changed = false;
+        Some(x.settings.clone())
+    }
+
+    fn save_settings(&self) -> Result<(), String> {
+        match self.settings.lock() {
+            Ok(x) => x.settings.write(&x.name).map_err(|e| e.to_string()),
+            _ => Err("Debug UI settings lock is Poisoned".to_string())
+        }
+    }
+}
+
+/**
+ * Immediate mode debug overlay built on egui, with frame timing, layer toggles and a settings editor
+ * Push it with LayerStack::push_overlay, it takes the clicks and keys it uses so layers below
+ * don't see them. F1 shows and hides it, the key left of 1 opens the console on its own
+ * It's only drawn by the OpenGL backend for now, elsewhere it stays inactive
+ **/
+pub struct DebugUi {
+    name: String,
+    ctx: Context,
+    state: Arc<DebugUiState>,
+    input: InputTranslator,
+    events: Vec<egui::Event>,
+    focused: bool,
+    start: Instant,
+    console_input: String,
+    //position in the console history while browsing it with the arrow keys
+    history_index: Option<usize>,
+}
+
+impl DebugUi {
+    pub fn new(state: Arc<DebugUiState>) -> DebugUi {
+        DebugUi {
+            name: DEBUG_UI_LAYER.to_string(),
+            ctx: Context::default(),
+            state,
+            input: InputTranslator::new(),
+            events: Vec::new(),
+            focused: true,
+            start: Instant::now(),
+            console_input: String::new(),
+            history_index: None,
+        }
+    }
+
+    /**
+     * The overlay layer holding just this UI
+     **/
+    pub fn into_layer(self) -> Layer {
+        Layer::new(true, Some(vec![Box::new(self)]), DEBUG_UI_LAYER.to_string())
+    }
+
+    #[inline]
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    fn show(&mut self, ctx: &Context, stats: &FrameStats, layers: &[LayerInfo]) {
+        if self.state.console_open() {
+            self.console_panel(ctx);
+        }
+        if self.state.visible() {
+            performance_panel(ctx, stats);
+            self.layers_panel(ctx, layers);
+            self.settings_panel(ctx);
+        }
+    }
+
+    /**
+     * Drops down from the top of the window, Enter runs the line, Tab completes it and
+     * the arrow keys go through earlier lines
+     **/
+    fn console_panel(&mut self, ctx: &Context) {
+        let console = self.state.console();
+        console.sync_log();
+        let lines = console.lines();
+        egui::TopBottomPanel::top("magnus_console").resizable(true).default_height(280.0).show(ctx, |ui| {
+            //taken before the text box sees them, it would move focus or the cursor otherwise
+            let (enter, tab, up, down) = ui.input_mut(|i| (
+                i.consume_key(Modifiers::NONE, Key::Enter),
+                i.consume_key(Modifiers::NONE, Key::Tab),
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown)
+            ));
+            let mut edited = false;
+            if enter {
+                let line = std::mem::take(&mut self.console_input);
+                self.history_index = None;
+                self.state.execute(&line);
+            }
+            if tab {
+                let candidates = console.complete(&self.console_input);
+                match candidates.len() {
+                    0 => {},
+                    1 => self.console_input = format!("{} ", candidates[0]),
+                    _ => {
+                        self.console_input = common_prefix(&candidates);
+                        console.print(candidates.join("  "));
+                    }
+                }
+                edited = true;
+            }
+            if up || down {
+                let history = console.history();
+                let index = match (self.history_index, up) {
+                    (None, true) => history.len().checked_sub(1),
+                    (Some(i), true) => Some(i.saturating_sub(1)),
+                    (Some(i), false) if i + 1 < history.len() => Some(i + 1),
+                    _ => None
+                };
+                self.console_input = index.map(|i| history[i].clone()).unwrap_or_default();
+                self.history_index = index;
+                edited = true;
+            }
+
+            let input_height = ui.spacing().interact_size.y + ui.spacing().item_spacing.y * 2.0;
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .max_height((ui.available_height() - input_height).max(0.0))
+                .show(ui, |ui| {
+                    for line in &lines {
+                        ui.label(egui::RichText::new(&line.text).monospace().color(line_color(ui.visuals(), line.kind)));
+                    }
+                });
+            let response = ui.add(egui::TextEdit::singleline(&mut self.console_input)
+                .font(egui::TextStyle::Monospace)
+                .desired_width(f32::INFINITY)
+                .hint_text("help lists every command"));
+            response.request_focus();
+            if edited {
+                if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), response.id) {
+                    let end = CCursor::new(self.console_input.chars().count());
+                    state.cursor.set_char_range(Some(CCursorRange::one(end)));
+                    state.store(ui.ctx(), response.id);
+                }
+            }
+        });
+    }
+
+    fn layers_panel(&self, ctx: &Context, layers: &[LayerInfo]) {
+        egui::Window::new("Layers").default_pos([12.0, 240.0]).show(ctx, |ui| {
+            if layers.is_empty() {
+                ui.weak("No layers");
+            }
+            for (i, layer) in layers.iter().enumerate() {
+                let mut enabled = layer.enabled;
+                let text = if layer.overlay { format!("{} (overlay)", layer.name) } else { layer.name.clone() };
+                ui.horizontal(|ui| {
+                    //turning off the layer holding this panel would leave no way to turn it back on
+                    let toggle = ui.add_enabled(layer.name != DEBUG_UI_LAYER, egui::Checkbox::new(&mut enabled, text));
+                    ui.weak(format!("{} objects", layer.objects));
+                    if toggle.changed() {
+                        self.state.toggle_layer(i, &layer.name, enabled);
+                    }
+                });
+            }
+        });
+    }
+
+    fn settings_panel(&self, ctx: &Context) {
+        let before = self.state.settings();
+        let mut settings = before.clone();
+        egui::Window::new("Settings").default_pos([12.0, 390.0]).show(ctx, |ui| {
+            let graphics = settings.graphics();
+            egui::Grid::new("magnus_settings").num_columns(2).show(ui, |ui| {
+                let (mut width, mut height) = graphics.size();
+                ui.label("Width");
+                ui.add(egui::DragValue::new(&mut width).range(320..=7680));
+                ui.end_row();
+                ui.label("Height");
+                ui.add(egui::DragValue::new(&mut height).range(240..=4320));
+                ui.end_row();
+                if (width, height) != graphics.size() {
+                    settings.set_size(width, height);
+                }
+
+                let mut mode = graphics.mode();
+                ui.label("Graphics mode");
+                egui::ComboBox::from_id_salt("magnus_graphics_mode").selected_text(format!("{:?}", mode)).show_ui(ui, |ui| {
+                    for &x in [GraphicsMode::OpenGL, GraphicsMode::Vulkan, GraphicsMode::DirectX].iter() {
+                        ui.selectable_value(&mut mode, x, format!("{:?}", x));
+                    }
+                });
+                ui.end_row();
+                if mode != graphics.mode() {
+                    settings.set_graphics_mode(mode);
+                }
+
+                let mut vsync = graphics.vsync();
+                ui.label("VSync");
+                egui::ComboBox::from_id_salt("magnus_vsync").selected_text(vsync_name(vsync)).show_ui(ui, |ui| {
+                    for x in 0..=2 {
+                        ui.selectable_value(&mut vsync, x, vsync_name(x));
+                    }
+                });
+                ui.end_row();
+                if vsync != graphics.vsync() {
+                    settings.set_vsync(vsync);
+                }
+
+                ui.label("Vulkan device");
+                ui.label(graphics.vulkan_id().to_string());
+                ui.end_row();
+
+                for &(bus, label) in [(Bus::Master, "Master volume"), (Bus::Music, "Music volume"), (Bus::Sfx, "Sfx volume")].iter() {
+                    let mut volume = settings.audio().volume(bus);
+                    ui.label(label);
+                    if ui.add(egui::Slider::new(&mut volume, 0.0..=1.0)).changed() {
+                        settings.set_volume(bus, volume);
+                    }
+                    ui.end_row();
+                }
+
+                let mut level = settings.logging().level();
+                ui.label("Log level");
+                egui::ComboBox::from_id_salt("magnus_log_level").selected_text(format!("{:?}", level)).show_ui(ui, |ui| {
+                    for &x in [LogLevel::Off, LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace].iter() {
+                        ui.selectable_value(&mut level, x, format!("{:?}", x));
+                    }
+                });
+                ui.end_row();
+                if level != settings.logging().level() {
+                    settings.logging_mut().set_level(level);
+                }
+            });
+            ui.weak("Graphics mode changes apply on the next launch");
+            if ui.button("Save").clicked() {
+                match self.state.save_settings() {
+                    Ok(()) => info!("Settings saved from the debug UI"),
+                    Err(e) => error!("Failed to save settings: {}", e)
+                }
+            }
+        });
+        if settings != before {
+            self.state.set_settings(settings);
+        }
+    }
+}
+
+fn performance_panel(ctx: &Context, stats: &FrameStats) {
+    egui::Window::new("Performance").default_pos([12.0, 12.0]).resizable(false).show(ctx, |ui| {
+        ui.label(format!("{:.0} FPS", stats.fps()));
+        ui.label(format!("Frame time {:.2} ms (min {:.2}, avg {:.2}, max {:.2}, p99 {:.2})", stats.last() * 1000.0,
+        stats.min() * 1000.0, stats.average() * 1000.0, stats.max() * 1000.0, stats.p99() * 1000.0));
+        ui.label(format!("Update {:.2} ms", stats.update_time() * 1000.0));
+        if let Some(x) = stats.gpu_time() {
+            ui.label(format!("GPU {:.2} ms", x * 1000.0));
+        }
+        ui.label(format!("{} draw calls", stats.draw_calls()));
+        if let Some(x) = stats.gpu_memory() {
+            ui.label(format!("GPU memory {:.0} MB", x as f64 / (1024.0 * 1024.0)));
+        }
+        frame_graph(ui, stats);
+    });
+}
+
+fn vsync_name(interval: u8) -> &'static str {
+    match interval {
+        1 => "Adaptive",
+        2 => "On",
+        _ => "Off"
+    }
+}
+
+fn line_color(visuals: &egui::Visuals, kind: LineKind) -> Color32 {
+    match kind {
+        LineKind::Input => visuals.strong_text_color(),
+        LineKind::Output | LineKind::Log(Level::Info) => visuals.text_color(),
+        LineKind::Error | LineKind::Log(Level::Error) => visuals.error_fg_color,
+        LineKind::Log(Level::Warn) => visuals.warn_fg_color,
+        LineKind::Log(_) => visuals.weak_text_color()
+    }
+}
+
+/**
+ * Frame times as a line, scaled so 30 FPS or the slowest frame reaches the top
+ * The faint line marks 60 FPS
+ **/
+fn frame_graph(ui: &mut egui::Ui, stats: &FrameStats) {
+    let (rect, _) = ui.allocate_exact_size(Vec2::new(FRAME_HISTORY as f32, 48.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let top = stats.max().max(1.0 / 30.0);
+    let y = |dt: f32| rect.bottom() - rect.height() * (dt / top).min(1.0);
+    painter.hline(rect.x_range(), y(1.0 / 60.0), egui::Stroke::new(1.0, ui.visuals().weak_text_color()));
+    let offset = FRAME_HISTORY.saturating_sub(stats.history().len());
+    let points: Vec<Pos2> = stats.history().iter().enumerate()
+        .map(|(i, x)| Pos2::new(rect.left() + (offset + i) as f32, y(x.frame_time)))
+        .collect();
+    painter.line(points, egui::Stroke::new(1.0, ui.visuals().text_color()));
+}
+
+impl std::fmt::Debug for DebugUi {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "DebugUi {{ visible: {}, pending events: {} }}", self.state.visible(), self.events.len())
+    }
+}
+
+impl Object for DebugUi {
+    fn id(&self) -> u32 {
+        DEBUG_UI_ID
+    }
+
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn on_update(&mut self) {
+        let screen = match self.state.screen() {
+            Some(x) if self.state.visible() || self.state.console_open() => x,
+            _ => {
+                self.events.clear();
+                self.state.publish(None, TexturesDelta::default());
+                return;
+            }
+        };
+
+        let mut input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, screen.size)),
+            time: Some(self.start.elapsed().as_secs_f64()),
+            modifiers: self.input.modifiers(),
+            events: std::mem::take(&mut self.events),
+            focused: self.focused,
+            ..RawInput::default()
+        };
+        input.viewports.entry(ViewportId::ROOT).or_default().native_pixels_per_point = Some(screen.pixels_per_point);
+
+        let stats = FrameStats::snapshot();
+        let layers = self.state.layers();
+        let ctx = self.ctx.clone();
+        let output = ctx.run(input, |ctx| self.show(ctx, &stats, &layers));
+        let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+        let frame = UiFrame { primitives, pixels_per_point: output.pixels_per_point };
+        self.state.publish(Some(Arc::new(frame)), output.textures_delta);
+    }
+
+    fn on_event(&mut self, e: &mut dyn Event) {
+        let data = match e.get_data() {
+            Some(x) => x,
+            None => return
+        };
+        if let EventData::I32p(TOGGLE_KEY, _, EventType::KeyPressed) = data {
+            self.state.set_visible(!self.state.visible());
+            e.set_handled(true);
+            return;
+        }
+        if let EventData::I32p(console::TOGGLE_KEY, _, EventType::KeyPressed) = data {
+            self.state.set_console_open(!self.state.console_open());
+            e.set_handled(true);
+            return;
+        }
+        if let EventData::Bool(focused, EventType::WindowFocus) = data {
+            self.focused = *focused;
+        }
+        if !(self.state.visible() || self.state.console_open()) || self.state.screen().is_none() {
+            return;
+        }
+        let event = match self.input.translate(data) {
+            Some(x) => x,
+            None => return
+        };
+        //the key that opens the console also types its character
+        if matches!(&event, egui::Event::Text(x) if x == "`") {
+            e.set_handled(true);
+            return;
+        }
+        //what egui wants is known from the last frame, which is as current as it gets
+        let handled = match &event {
+            egui::Event::PointerButton { .. } => self.ctx.wants_pointer_input(),
+            egui::Event::MouseWheel { .. } => self.ctx.is_pointer_over_area(),
+            egui::Event::Key { .. } | egui::Event::Text(_) => self.ctx.wants_keyboard_input(),
+            _ => false
+        };
+        self.events.push(event);
+        if handled {
+            e.set_handled(true);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use egui::{ Event as UiEvent, Key, Modifiers, MouseWheelUnit, PointerButton, Pos2, Vec2 };
+
+use crate::events::event::{ EventData, EventType };
+
+//glfw modifier bits
+pub const MOD_SHIFT: i32 = 0x1;
+pub const MOD_CONTROL: i32 = 0x2;
+pub const MOD_ALT: i32 = 0x4;
+pub const MOD_SUPER: i32 = 0x8;
+
+/**
+ * Glfw scrolls by lines, egui wants points
+ **/
+pub const SCROLL_LINE: f32 = 24.0;
+
+pub fn modifiers(mods: i32) -> Modifiers {
+    let ctrl = mods & MOD_CONTROL != 0;
+    let command = if cfg!(target_os = "macos") { mods & MOD_SUPER != 0 } else { ctrl };
+    Modifiers {
+        alt: mods & MOD_ALT != 0,
+        ctrl,
+        shift: mods & MOD_SHIFT != 0,
+        mac_cmd: cfg!(target_os = "macos") && mods & MOD_SUPER != 0,
+        command,
+    }
+}
+
+/**
+ * The egui key for a glfw keycode, None for keys egui doesn't know about
+ **/
+pub fn key(code: i32) -> Option<Key> {
+    match code {
+        //printable keys use their ascii value as the keycode
+        32..=96 => Key::from_name(&(code as u8 as char).to_string()),
+        256 => Some(Key::Escape),
+        257 | 335 => Some(Key::Enter),
+        258 => Some(Key::Tab),
+        259 => Some(Key::Backspace),
+        260 => Some(Key::Insert),
+        261 => Some(Key::Delete),
+        262 => Some(Key::ArrowRight),
+        263 => Some(Key::ArrowLeft),
+        264 => Some(Key::ArrowDown),
+        265 => Some(Key::ArrowUp),
+        266 => Some(Key::PageUp),
+        267 => Some(Key::PageDown),
+        268 => Some(Key::Home),
+        269 => Some(Key::End),
+        290..=301 => Key::from_name(&format!("F{}", code - 289)),
+        _ => None
+    }
+}
+
+pub fn pointer_button(button: i32) -> Option<PointerButton> {
+    match button {
+        0 => Some(PointerButton::Primary),
+        1 => Some(PointerButton::Secondary),
+        2 => Some(PointerButton::Middle),
+        3 => Some(PointerButton::Extra1),
+        4 => Some(PointerButton::Extra2),
+        _ => None
+    }
+}
+
+/**
+ * Tracks what egui needs between window events, glfw only gives the pointer position on moves
+ * and modifiers on key and button events
+ **/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InputTranslator {
+    pointer: Pos2,
+    modifiers: Modifiers,
+}
+
+impl InputTranslator {
+    pub fn new() -> InputTranslator {
+        InputTranslator::default()
+    }
+
+    #[inline]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /**
+     * The egui event for a window event
+     * Glfw cursor positions are in screen coordinates, which are egui's points as long as
+     * pixels per point is the framebuffer to window size ratio
+     **/
+    pub fn translate(&mut self, data: &EventData) -> Option<UiEvent> {
+        match (data.event_type(), data) {
+            (EventType::MouseMoved, EventData::F32p(x, y, _)) => {
+                self.pointer = Pos2::new(*x, *y);
+                Some(UiEvent::PointerMoved(self.pointer))
+            },
+            (EventType::MouseButtonPressed, EventData::I32p(button, mods, _))
+            | (EventType::MouseButtonReleased, EventData::I32p(button, mods, _)) => {
+                self.modifiers = modifiers(*mods);
+                Some(UiEvent::PointerButton {
+                    pos: self.pointer,
+                    button: pointer_button(*button)?,
+                    pressed: *data.event_type() == EventType::MouseButtonPressed,
+                    modifiers: self.modifiers,
+                })
+            },
+            (EventType::MouseScrolled, EventData::F32p(x, y, _)) => Some(UiEvent::MouseWheel {
+                unit: MouseWheelUnit::Point,
+                delta: Vec2::new(*x, *y) * SCROLL_LINE,
+                modifiers: self.modifiers,
+            }),
+            (EventType::KeyPressed, EventData::I32p(code, mods, _))
+            | (EventType::KeyReleased, EventData::I32p(code, mods, _)) => {
+                self.modifiers = modifiers(*mods);
+                Some(UiEvent::Key {
+                    key: key(*code)?,
+                    physical_key: None,
+                    pressed: *data.event_type() == EventType::KeyPressed,
+                    repeat: false,
+                    modifiers: self.modifiers,
+                })
+            },
+            (EventType::TextInput, EventData::U32p(c, _, _)) => {
+                let c = std::char::from_u32(*c)?;
+                //control characters come through as key events
+                if c.is_control() {
+                    None
+                } else {
+                    Some(UiEvent::Text(c.to_string()))
+                }
+            },
+            (EventType::WindowFocus, EventData::Bool(focused, _)) => Some(UiEvent::WindowFocused(*focused)),
+            _ => None
+        }
+    }
+}
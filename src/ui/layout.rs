@@ -0,0 +1,245 @@
+use crate::math::Vec2;
+
+/**
+ * Width or height of a widget, percentages are of the parent's inner size
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Size {
+    Auto,
+    Px(f32),
+    Percent(f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Row,
+    Column,
+}
+
+/**
+ * Where children go along the main axis when there's space left over
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+}
+
+/**
+ * Where children go on the cross axis
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+/**
+ * Where a root widget sits on screen, Stretch fills the screen inside its margin
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    Stretch,
+}
+
+impl Anchor {
+    /**
+     * Fraction of the free space to the left and above the widget
+     **/
+    pub fn fraction(&self) -> Vec2 {
+        match self {
+            Anchor::TopLeft | Anchor::Stretch => Vec2::ZERO,
+            Anchor::Top => Vec2::new(0.5, 0.0),
+            Anchor::TopRight => Vec2::new(1.0, 0.0),
+            Anchor::Left => Vec2::new(0.0, 0.5),
+            Anchor::Center => Vec2::new(0.5, 0.5),
+            Anchor::Right => Vec2::new(1.0, 0.5),
+            Anchor::BottomLeft => Vec2::new(0.0, 1.0),
+            Anchor::Bottom => Vec2::new(0.5, 1.0),
+            Anchor::BottomRight => Vec2::new(1.0, 1.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Edges {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Edges {
+    pub const ZERO: Edges = Edges { top: 0.0, right: 0.0, bottom: 0.0, left: 0.0 };
+
+    pub fn new(top: f32, right: f32, bottom: f32, left: f32) -> Edges {
+        Edges { top, right, bottom, left }
+    }
+
+    pub fn all(x: f32) -> Edges {
+        Edges { top: x, right: x, bottom: x, left: x }
+    }
+
+    pub fn symmetric(horizontal: f32, vertical: f32) -> Edges {
+        Edges { top: vertical, right: horizontal, bottom: vertical, left: horizontal }
+    }
+
+    #[inline]
+    pub fn horizontal(&self) -> f32 {
+        self.left + self.right
+    }
+
+    #[inline]
+    pub fn vertical(&self) -> f32 {
+        self.top + self.bottom
+    }
+
+    #[inline]
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.horizontal(), self.vertical())
+    }
+}
+
+/**
+ * Axis aligned rectangle, y grows downwards like window coordinates
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rect {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl Rect {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect { position: Vec2::new(x, y), size: Vec2::new(width, height) }
+    }
+
+    #[inline]
+    pub fn min(&self) -> Vec2 {
+        self.position
+    }
+
+    #[inline]
+    pub fn max(&self) -> Vec2 {
+        self.position + self.size
+    }
+
+    #[inline]
+    pub fn center(&self) -> Vec2 {
+        self.position + self.size * 0.5
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        let max = self.max();
+        point.x >= self.position.x && point.y >= self.position.y && point.x < max.x && point.y < max.y
+    }
+
+    /**
+     * The overlap of both rectangles, zero sized when they don't touch
+     **/
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let min = self.min().max(other.min());
+        let max = self.max().min(other.max()).max(min);
+        Rect { position: min, size: max - min }
+    }
+
+    pub fn shrink(&self, edges: &Edges) -> Rect {
+        Rect {
+            position: self.position + Vec2::new(edges.left, edges.top),
+            size: (self.size - edges.size()).max(Vec2::ZERO),
+        }
+    }
+
+    pub fn scale(&self, factor: f32) -> Rect {
+        Rect { position: self.position * factor, size: self.size * factor }
+    }
+}
+
+/**
+ * How a widget sizes itself and lays out its children, a subset of CSS flexbox
+ * Units are logical pixels, window coordinates divided by the content scale
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Layout {
+    pub width: Size,
+    pub height: Size,
+    pub min_size: Vec2,
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align: Align,
+    /**
+     * Space between children along the main axis
+     **/
+    pub gap: f32,
+    pub padding: Edges,
+    pub margin: Edges,
+    /**
+     * Share of the parent's leftover main axis space this widget takes
+     **/
+    pub grow: f32,
+    /**
+     * Share of the parent's overflow this widget gives up, weighted by its size
+     **/
+    pub shrink: f32,
+    /**
+     * Only used by root widgets
+     **/
+    pub anchor: Anchor,
+    pub offset: Vec2,
+}
+
+impl Layout {
+    pub fn new() -> Layout {
+        Layout::default()
+    }
+
+    pub fn row() -> Layout {
+        Layout { direction: Direction::Row, ..Layout::default() }
+    }
+
+    pub fn column() -> Layout {
+        Layout::default()
+    }
+
+    pub fn sized(width: Size, height: Size) -> Layout {
+        Layout { width, height, ..Layout::default() }
+    }
+
+    pub fn anchored(anchor: Anchor) -> Layout {
+        Layout { anchor, ..Layout::default() }
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            width: Size::Auto,
+            height: Size::Auto,
+            min_size: Vec2::ZERO,
+            direction: Direction::Column,
+            justify: Justify::Start,
+            align: Align::Stretch,
+            gap: 0.0,
+            padding: Edges::ZERO,
+            margin: Edges::ZERO,
+            grow: 0.0,
+            shrink: 1.0,
+            anchor: Anchor::TopLeft,
+            offset: Vec2::ZERO,
+        }
+    }
+}
@@ -0,0 +1,54 @@
+pub mod input;
+pub mod painter;
+pub mod debug;
+pub mod style;
+pub mod layout;
+pub mod widgets;
+pub mod nav;
+pub mod tree;
+pub mod renderer;
+
+pub use self::debug::{ DebugUi, DebugUiState, LayerInfo, UiFrame };
+pub use self::painter::UiPainter;
+pub use self::style::{ Color, ResolvedStyle, Style, StyleSheet, Theme, WidgetState };
+pub use self::layout::{ Align, Anchor, Direction, Edges, Justify, Layout, Rect, Size };
+pub use self::widgets::{ Widget, WidgetKind };
+pub use self::nav::{ GamepadNav, NavInput };
+pub use self::tree::{ DrawCommand, GameUi, UiAction, UiTree, WidgetId };
+pub use self::renderer::UiRenderer;
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum UiError {
+    ShaderCompile(String),
+    ShaderLink(String),
+    Io(std::io::Error),
+    Style(String),
+}
+
+impl fmt::Display for UiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UiError::ShaderCompile(msg) => write!(f, "UI shader failed to compile: {}", msg),
+            UiError::ShaderLink(msg) => write!(f, "UI shader failed to link: {}", msg),
+            UiError::Io(e) => write!(f, "Failed to read UI style: {}", e),
+            UiError::Style(msg) => write!(f, "Invalid UI style: {}", msg),
+        }
+    }
+}
+
+impl Error for UiError {}
+
+impl From<std::io::Error> for UiError {
+    fn from(e: std::io::Error) -> UiError {
+        UiError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for UiError {
+    fn from(e: serde_json::Error) -> UiError {
+        UiError::Style(e.to_string())
+    }
+}
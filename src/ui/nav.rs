@@ -0,0 +1,146 @@
+use glfw::{ Action, GamepadAxis, GamepadButton, GamepadState };
+
+/**
+ * Focus navigation independent of the device it came from
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NavInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Next,
+    Previous,
+    Activate,
+    Back,
+}
+
+impl NavInput {
+    pub const ALL: [NavInput; 8] = [
+        NavInput::Up, NavInput::Down, NavInput::Left, NavInput::Right,
+        NavInput::Next, NavInput::Previous, NavInput::Activate, NavInput::Back
+    ];
+
+    /**
+     * Directions repeat while held, everything else only fires on press
+     **/
+    pub fn repeats(&self) -> bool {
+        matches!(self, NavInput::Up | NavInput::Down | NavInput::Left | NavInput::Right)
+    }
+
+    /**
+     * The input for a glfw key press, Tab goes backwards with shift held
+     **/
+    pub fn from_key(code: i32, shift: bool) -> Option<NavInput> {
+        match code {
+            258 if shift => Some(NavInput::Previous),
+            258 => Some(NavInput::Next),
+            262 => Some(NavInput::Right),
+            263 => Some(NavInput::Left),
+            264 => Some(NavInput::Down),
+            265 => Some(NavInput::Up),
+            32 | 257 | 335 => Some(NavInput::Activate),
+            256 => Some(NavInput::Back),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Stick deflection that counts as a direction being held
+ **/
+pub const STICK_THRESHOLD: f32 = 0.5;
+
+/**
+ * Seconds a direction is held before it starts repeating, then the time between repeats
+ **/
+pub const REPEAT_DELAY: f32 = 0.4;
+pub const REPEAT_INTERVAL: f32 = 0.12;
+
+/**
+ * Turns polled gamepad state into navigation inputs
+ * Glfw has no gamepad events, so this does the edge detection and repeats key presses get
+ * from the OS
+ **/
+#[derive(Debug, Clone, Default)]
+pub struct GamepadNav {
+    held: [Option<f32>; 8],
+}
+
+impl GamepadNav {
+    pub fn new() -> GamepadNav {
+        GamepadNav::default()
+    }
+
+    /**
+     * Navigation held on a gamepad: the d-pad or left stick for directions, A to activate,
+     * B to go back and the bumpers to cycle focus
+     **/
+    pub fn held(state: &GamepadState) -> Vec<NavInput> {
+        let button = |x| state.get_button_state(x) == Action::Press;
+        let x = state.get_axis(GamepadAxis::AxisLeftX);
+        let y = state.get_axis(GamepadAxis::AxisLeftY);
+        let mut held = Vec::new();
+        //glfw's stick y points down, same as the UI
+        if button(GamepadButton::ButtonDpadUp) || y < -STICK_THRESHOLD {
+            held.push(NavInput::Up);
+        }
+        if button(GamepadButton::ButtonDpadDown) || y > STICK_THRESHOLD {
+            held.push(NavInput::Down);
+        }
+        if button(GamepadButton::ButtonDpadLeft) || x < -STICK_THRESHOLD {
+            held.push(NavInput::Left);
+        }
+        if button(GamepadButton::ButtonDpadRight) || x > STICK_THRESHOLD {
+            held.push(NavInput::Right);
+        }
+        if button(GamepadButton::ButtonRightBumper) {
+            held.push(NavInput::Next);
+        }
+        if button(GamepadButton::ButtonLeftBumper) {
+            held.push(NavInput::Previous);
+        }
+        if button(GamepadButton::ButtonA) {
+            held.push(NavInput::Activate);
+        }
+        if button(GamepadButton::ButtonB) {
+            held.push(NavInput::Back);
+        }
+        held
+    }
+
+    pub fn poll(&mut self, state: &GamepadState, dt: f32) -> Vec<NavInput> {
+        self.update(&GamepadNav::held(state), dt)
+    }
+
+    /**
+     * The inputs to apply this update given everything held right now and the time since the
+     * last update
+     **/
+    pub fn update(&mut self, held: &[NavInput], dt: f32) -> Vec<NavInput> {
+        let mut fired = Vec::new();
+        for (i, input) in NavInput::ALL.iter().enumerate() {
+            if !held.contains(input) {
+                self.held[i] = None;
+                continue;
+            }
+            match self.held[i] {
+                None => {
+                    fired.push(*input);
+                    self.held[i] = Some(REPEAT_DELAY);
+                },
+                Some(wait) if input.repeats() => {
+                    let wait = wait - dt;
+                    if wait <= 0.0 {
+                        fired.push(*input);
+                        self.held[i] = Some(wait + REPEAT_INTERVAL);
+                    } else {
+                        self.held[i] = Some(wait);
+                    }
+                },
+                Some(_) => {}
+            }
+        }
+        fired
+    }
+}
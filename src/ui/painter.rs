@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::c_void;
+use std::ptr;
+
+use egui::epaint::{ ImageData, ImageDelta, Primitive, TextureId, Vertex };
+use egui::epaint::textures::{ TextureFilter, TextureWrapMode, TexturesDelta };
+use gl::types::{ GLenum, GLint, GLsizei, GLsizeiptr, GLuint };
+
+use crate::core::stats;
+use crate::ui::UiError;
+use crate::ui::debug::UiFrame;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+uniform vec2 u_screen_size;
+layout(location = 0) in vec2 a_pos;
+layout(location = 1) in vec2 a_uv;
+layout(location = 2) in vec4 a_color;
+out vec2 v_uv;
+out vec4 v_color;
+
+void main() {
+    gl_Position = vec4(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y, 0.0, 1.0);
+    v_uv = a_uv;
+    v_color = a_color;
+}
+"#;
+
+//egui hands over premultiplied colors in gamma space, they're blended as they are
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform sampler2D u_texture;
+in vec2 v_uv;
+in vec4 v_color;
+out vec4 frag_color;
+
+void main() {
+    frag_color = v_color * texture(u_texture, v_uv);
+}
+"#;
+
+/**
+ * Draws egui output with OpenGL, create and use it only on the thread the context is current on
+ **/
+pub struct UiPainter {
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    u_screen_size: GLint,
+    u_texture: GLint,
+    textures: HashMap<TextureId, GLuint>,
+}
+
+impl std::fmt::Debug for UiPainter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "UiPainter {{ program: {}, textures: {} }}", self.program, self.textures.len())
+    }
+}
+
+impl UiPainter {
+    /**
+     * Needs the OpenGL symbols loaded
+     **/
+    pub fn new() -> Result<UiPainter, UiError> {
+        unsafe {
+            let vertex = compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment = match compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+                Ok(x) => x,
+                Err(e) => {
+                    gl::DeleteShader(vertex);
+                    return Err(e);
+                }
+            };
+            let program = link_program(vertex, fragment)?;
+
+            let mut vao = 0;
+            let mut vbo = 0;
+            let mut ebo = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::GenBuffers(1, &mut ebo);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            let stride = mem::size_of::<Vertex>() as GLsizei;
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(Vertex, pos) as *const c_void);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(Vertex, uv) as *const c_void);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 4, gl::UNSIGNED_BYTE, gl::TRUE, stride, mem::offset_of!(Vertex, color) as *const c_void);
+            gl::BindVertexArray(0);
+
+            let u_screen_size = gl::GetUniformLocation(program, b"u_screen_size\0".as_ptr() as *const _);
+            let u_texture = gl::GetUniformLocation(program, b"u_texture\0".as_ptr() as *const _);
+
+            Ok(UiPainter { program, vao, vbo, ebo, u_screen_size, u_texture, textures: HashMap::new() })
+        }
+    }
+
+    /**
+     * egui textures currently uploaded
+     **/
+    pub fn texture_count(&self) -> usize {
+        self.textures.len()
+    }
+
+    /**
+     * Uploads texture changes and draws the frame over whatever is in the framebuffer
+     * `size` is the framebuffer size in pixels
+     **/
+    pub fn paint(&mut self, frame: &UiFrame, textures: TexturesDelta, size: (u32, u32)) {
+        for (id, delta) in &textures.set {
+            self.set_texture(*id, delta);
+        }
+
+        let (width, height) = (size.0 as f32, size.1 as f32);
+        let ppp = frame.pixels_per_point;
+        unsafe {
+            gl::Viewport(0, 0, size.0 as GLsizei, size.1 as GLsizei);
+            gl::Enable(gl::BLEND);
+            gl::BlendEquation(gl::FUNC_ADD);
+            gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE_MINUS_DST_ALPHA, gl::ONE);
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::UseProgram(self.program);
+            gl::Uniform2f(self.u_screen_size, width / ppp, height / ppp);
+            gl::Uniform1i(self.u_texture, 0);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+
+            for clipped in &frame.primitives {
+                let mesh = match &clipped.primitive {
+                    Primitive::Mesh(x) => x,
+                    //no paint callbacks from the debug panels
+                    Primitive::Callback(_) => continue
+                };
+                let texture = match self.textures.get(&mesh.texture_id) {
+                    Some(x) => *x,
+                    None => continue
+                };
+                if mesh.indices.is_empty() {
+                    continue;
+                }
+
+                //scissor is in pixels with the origin at the bottom left
+                let clip = clipped.clip_rect;
+                let min_x = (clip.min.x * ppp).round().clamp(0.0, width);
+                let min_y = (clip.min.y * ppp).round().clamp(0.0, height);
+                let max_x = (clip.max.x * ppp).round().clamp(min_x, width);
+                let max_y = (clip.max.y * ppp).round().clamp(min_y, height);
+                gl::Scissor(min_x as GLint, (height - max_y) as GLint, (max_x - min_x) as GLsizei, (max_y - min_y) as GLsizei);
+
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::BufferData(gl::ARRAY_BUFFER, (mesh.vertices.len() * mem::size_of::<Vertex>()) as GLsizeiptr,
+                mesh.vertices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (mesh.indices.len() * mem::size_of::<u32>()) as GLsizeiptr,
+                mesh.indices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+                gl::DrawElements(gl::TRIANGLES, mesh.indices.len() as GLsizei, gl::UNSIGNED_INT, ptr::null());
+                stats::add_draw_calls(1);
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::UseProgram(0);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::BLEND);
+        }
+
+        for id in &textures.free {
+            if let Some(x) = self.textures.remove(id) {
+                unsafe {
+                    gl::DeleteTextures(1, &x);
+                }
+            }
+        }
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let ImageData::Color(image) = &delta.image;
+        let (w, h) = (image.size[0] as GLsizei, image.size[1] as GLsizei);
+        let pixels = image.pixels.as_ptr() as *const c_void;
+        unsafe {
+            let texture = match (self.textures.get(&id), delta.pos) {
+                (Some(x), _) => *x,
+                (None, Some(_)) => {
+                    warn!("UI texture {:?} updated before it was created", id);
+                    return;
+                },
+                (None, None) => {
+                    let mut x = 0;
+                    gl::GenTextures(1, &mut x);
+                    self.textures.insert(id, x);
+                    x
+                }
+            };
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter(delta.options.magnification) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter(delta.options.minification) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap(delta.options.wrap_mode) as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap(delta.options.wrap_mode) as GLint);
+            match delta.pos {
+                Some([x, y]) => gl::TexSubImage2D(gl::TEXTURE_2D, 0, x as GLint, y as GLint, w, h, gl::RGBA, gl::UNSIGNED_BYTE, pixels),
+                None => gl::TexImage2D(gl::TEXTURE_2D, 0, gl::RGBA8 as GLint, w, h, 0, gl::RGBA, gl::UNSIGNED_BYTE, pixels)
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+}
+
+impl Drop for UiPainter {
+    fn drop(&mut self) {
+        unsafe {
+            for x in self.textures.values() {
+                gl::DeleteTextures(1, x);
+            }
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
+
+fn filter(filter: TextureFilter) -> GLenum {
+    match filter {
+        TextureFilter::Nearest => gl::NEAREST,
+        TextureFilter::Linear => gl::LINEAR
+    }
+}
+
+fn wrap(mode: TextureWrapMode) -> GLenum {
+    match mode {
+        TextureWrapMode::ClampToEdge => gl::CLAMP_TO_EDGE,
+        TextureWrapMode::Repeat => gl::REPEAT,
+        TextureWrapMode::MirroredRepeat => gl::MIRRORED_REPEAT
+    }
+}
+
+pub(crate) unsafe fn compile_shader(kind: GLenum, source: &str) -> Result<GLuint, UiError> {
+    let shader = gl::CreateShader(kind);
+    let source = CString::new(source).map_err(|e| UiError::ShaderCompile(e.to_string()))?;
+    gl::ShaderSource(shader, 1, &source.as_ptr(), ptr::null());
+    gl::CompileShader(shader);
+    let mut status = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+    if status == gl::TRUE as GLint {
+        return Ok(shader);
+    }
+    let mut len = 0;
+    gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut len);
+    let mut log = vec![0u8; len.max(1) as usize];
+    gl::GetShaderInfoLog(shader, len, ptr::null_mut(), log.as_mut_ptr() as *mut _);
+    gl::DeleteShader(shader);
+    Err(UiError::ShaderCompile(String::from_utf8_lossy(&log).trim_end_matches('\0').to_string()))
+}
+
+pub(crate) unsafe fn link_program(vertex: GLuint, fragment: GLuint) -> Result<GLuint, UiError> {
+    let program = gl::CreateProgram();
+    gl::AttachShader(program, vertex);
+    gl::AttachShader(program, fragment);
+    gl::LinkProgram(program);
+    //the program keeps what it needs once linked
+    gl::DetachShader(program, vertex);
+    gl::DetachShader(program, fragment);
+    gl::DeleteShader(vertex);
+    gl::DeleteShader(fragment);
+    let mut status = 0;
+    gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
+    if status == gl::TRUE as GLint {
+        return Ok(program);
+    }
+    let mut len = 0;
+    gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
+    let mut log = vec![0u8; len.max(1) as usize];
+    gl::GetProgramInfoLog(program, len, ptr::null_mut(), log.as_mut_ptr() as *mut _);
+    gl::DeleteProgram(program);
+    Err(UiError::ShaderLink(String::from_utf8_lossy(&log).trim_end_matches('\0').to_string()))
+}
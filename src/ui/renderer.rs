@@ -0,0 +1,398 @@
+use std::mem;
+use std::os::raw::c_void;
+
+use gl::types::{ GLint, GLsizei, GLsizeiptr, GLuint };
+
+use crate::core::stats;
+use crate::math::Vec2;
+use crate::text::{ FontSet, GlyphAtlas, RasterMode, TextLayout, TextStyle };
+use crate::ui::UiError;
+use crate::ui::layout::{ Edges, Rect };
+use crate::ui::painter::{ compile_shader, link_program };
+use crate::ui::style::Color;
+use crate::ui::tree::DrawCommand;
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+uniform vec2 u_screen_size;
+layout(location = 0) in vec2 a_pos;
+layout(location = 1) in vec2 a_uv;
+layout(location = 2) in vec4 a_color;
+layout(location = 3) in float a_mode;
+out vec2 v_uv;
+out vec4 v_color;
+out float v_mode;
+
+void main() {
+    gl_Position = vec4(2.0 * a_pos.x / u_screen_size.x - 1.0, 1.0 - 2.0 * a_pos.y / u_screen_size.y, 0.0, 1.0);
+    v_uv = a_uv;
+    v_color = a_color;
+    v_mode = a_mode;
+}
+"#;
+
+//mode 0 is a solid color, 1 samples glyph coverage and 2 a glyph distance field
+const FRAGMENT_SHADER: &str = r#"#version 330 core
+uniform sampler2D u_atlas;
+in vec2 v_uv;
+in vec4 v_color;
+in float v_mode;
+out vec4 frag_color;
+
+void main() {
+    float alpha = 1.0;
+    if (v_mode > 1.5) {
+        float distance = texture(u_atlas, v_uv).r;
+        float width = max(fwidth(distance), 0.0001);
+        alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+    } else if (v_mode > 0.5) {
+        alpha = texture(u_atlas, v_uv).r;
+    }
+    alpha *= v_color.a;
+    frag_color = vec4(v_color.rgb * alpha, alpha);
+}
+"#;
+
+const SOLID: f32 = 0.0;
+const COVERAGE: f32 = 1.0;
+const SDF: f32 = 2.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct UiVertex {
+    pos: [f32; 2],
+    uv: [f32; 2],
+    color: [u8; 4],
+    mode: f32,
+}
+
+//indices from `start` drawn with one scissor rect
+struct Batch {
+    clip: Option<[GLint; 4]>,
+    start: usize,
+}
+
+/**
+ * Draws game UI draw lists with OpenGL, text goes through a glyph atlas rasterized from the
+ * fonts as it's needed
+ * Create and use it only on the thread the context is current on
+ **/
+pub struct UiRenderer {
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    ebo: GLuint,
+    texture: GLuint,
+    u_screen_size: GLint,
+    u_atlas: GLint,
+    atlas: GlyphAtlas,
+    vertices: Vec<UiVertex>,
+    indices: Vec<u32>,
+    batches: Vec<Batch>,
+}
+
+impl std::fmt::Debug for UiRenderer {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "UiRenderer {{ program: {}, atlas: {:?} }}", self.program, self.atlas)
+    }
+}
+
+impl UiRenderer {
+    /**
+     * Needs the OpenGL symbols loaded, glyphs are rasterized as coverage unless told otherwise
+     **/
+    pub fn new(mode: Option<RasterMode>) -> Result<UiRenderer, UiError> {
+        unsafe {
+            let vertex = compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER)?;
+            let fragment = match compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER) {
+                Ok(x) => x,
+                Err(e) => {
+                    gl::DeleteShader(vertex);
+                    return Err(e);
+                }
+            };
+            let program = link_program(vertex, fragment)?;
+
+            let mut vao = 0;
+            let mut vbo = 0;
+            let mut ebo = 0;
+            let mut texture = 0;
+            gl::GenVertexArrays(1, &mut vao);
+            gl::GenBuffers(1, &mut vbo);
+            gl::GenBuffers(1, &mut ebo);
+            gl::GenTextures(1, &mut texture);
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+            let stride = mem::size_of::<UiVertex>() as GLsizei;
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(UiVertex, pos) as *const c_void);
+            gl::EnableVertexAttribArray(1);
+            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(UiVertex, uv) as *const c_void);
+            gl::EnableVertexAttribArray(2);
+            gl::VertexAttribPointer(2, 4, gl::UNSIGNED_BYTE, gl::TRUE, stride, mem::offset_of!(UiVertex, color) as *const c_void);
+            gl::EnableVertexAttribArray(3);
+            gl::VertexAttribPointer(3, 1, gl::FLOAT, gl::FALSE, stride, mem::offset_of!(UiVertex, mode) as *const c_void);
+            gl::BindVertexArray(0);
+
+            gl::BindTexture(gl::TEXTURE_2D, texture);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+
+            let u_screen_size = gl::GetUniformLocation(program, b"u_screen_size\0".as_ptr() as *const _);
+            let u_atlas = gl::GetUniformLocation(program, b"u_atlas\0".as_ptr() as *const _);
+
+            Ok(UiRenderer {
+                program,
+                vao,
+                vbo,
+                ebo,
+                texture,
+                u_screen_size,
+                u_atlas,
+                atlas: GlyphAtlas::new(mode.unwrap_or(RasterMode::Coverage), None, None),
+                vertices: Vec::new(),
+                indices: Vec::new(),
+                batches: Vec::new(),
+            })
+        }
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    /**
+     * Draws a list over whatever is in the framebuffer
+     * The list is in window coordinates, text is rasterized at the framebuffer's pixel size so
+     * it stays sharp on HiDPI screens
+     **/
+    pub fn draw(&mut self, fonts: &FontSet, commands: &[DrawCommand], window: (i32, i32), framebuffer: (i32, i32)) {
+        if framebuffer.0 <= 0 || framebuffer.1 <= 0 {
+            return;
+        }
+        let ppp = if window.0 > 0 { framebuffer.0 as f32 / window.0 as f32 } else { 1.0 };
+        let generation = self.atlas.generation();
+        self.build(fonts, commands, ppp, framebuffer.1);
+        //a full atlas was cleared part way through, look every glyph up again
+        if self.atlas.generation() != generation {
+            self.build(fonts, commands, ppp, framebuffer.1);
+        }
+        if self.indices.is_empty() {
+            return;
+        }
+
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, self.texture);
+            if self.atlas.take_dirty() {
+                let size = self.atlas.size() as GLsizei;
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::R8 as GLint, size, size, 0, gl::RED, gl::UNSIGNED_BYTE,
+                self.atlas.pixels().as_ptr() as *const c_void);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            }
+
+            gl::Viewport(0, 0, framebuffer.0, framebuffer.1);
+            gl::Enable(gl::BLEND);
+            gl::BlendEquation(gl::FUNC_ADD);
+            gl::BlendFuncSeparate(gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE_MINUS_DST_ALPHA, gl::ONE);
+            gl::Disable(gl::CULL_FACE);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::UseProgram(self.program);
+            gl::Uniform2f(self.u_screen_size, framebuffer.0 as f32, framebuffer.1 as f32);
+            gl::Uniform1i(self.u_atlas, 0);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
+            gl::BufferData(gl::ARRAY_BUFFER, (self.vertices.len() * mem::size_of::<UiVertex>()) as GLsizeiptr,
+            self.vertices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+            gl::BufferData(gl::ELEMENT_ARRAY_BUFFER, (self.indices.len() * mem::size_of::<u32>()) as GLsizeiptr,
+            self.indices.as_ptr() as *const c_void, gl::STREAM_DRAW);
+
+            for (i, batch) in self.batches.iter().enumerate() {
+                let end = self.batches.get(i + 1).map_or(self.indices.len(), |x| x.start);
+                if end == batch.start {
+                    continue;
+                }
+                match batch.clip {
+                    Some([x, y, w, h]) => {
+                        gl::Enable(gl::SCISSOR_TEST);
+                        gl::Scissor(x, y, w, h);
+                    },
+                    None => gl::Disable(gl::SCISSOR_TEST)
+                }
+                gl::DrawElements(gl::TRIANGLES, (end - batch.start) as GLsizei, gl::UNSIGNED_INT,
+                (batch.start * mem::size_of::<u32>()) as *const c_void);
+                stats::add_draw_calls(1);
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gl::UseProgram(0);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    //turns the list into vertices in framebuffer pixels
+    fn build(&mut self, fonts: &FontSet, commands: &[DrawCommand], ppp: f32, height: i32) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.batches.clear();
+        self.batches.push(Batch { clip: None, start: 0 });
+        let mut clips: Vec<Rect> = Vec::new();
+        for command in commands {
+            match command {
+                DrawCommand::Rect { rect, color, radius, border, border_width } => {
+                    self.rect(rect.scale(ppp), *color, radius * ppp, *border, border_width * ppp);
+                },
+                DrawCommand::Text { position, text, size, color } => self.text(fonts, *position * ppp, text, size * ppp, *color),
+                //images wait on textures from the asset system
+                DrawCommand::Image { .. } => {},
+                DrawCommand::PushClip(rect) => {
+                    clips.push(rect.scale(ppp));
+                    self.clip(clips.last().copied(), height);
+                },
+                DrawCommand::PopClip => {
+                    clips.pop();
+                    self.clip(clips.last().copied(), height);
+                }
+            }
+        }
+        //glyphs are placed in atlas pixels, the atlas is done growing for this frame now
+        let size = self.atlas.size() as f32;
+        for x in self.vertices.iter_mut().filter(|x| x.mode != SOLID) {
+            x.uv = [x.uv[0] / size, x.uv[1] / size];
+        }
+    }
+
+    fn clip(&mut self, clip: Option<Rect>, height: i32) {
+        //scissor is in pixels with the origin at the bottom left
+        let clip = clip.map(|x| {
+            let min = x.min();
+            let max = x.max();
+            let (x0, x1) = (min.x.round() as GLint, max.x.round() as GLint);
+            let (y0, y1) = (min.y.round() as GLint, max.y.round() as GLint);
+            [x0, height - y1, (x1 - x0).max(0), (y1 - y0).max(0)]
+        });
+        let start = self.indices.len();
+        match self.batches.last_mut() {
+            Some(x) if x.start == start => x.clip = clip,
+            _ => self.batches.push(Batch { clip, start })
+        }
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color, radius: f32, border: Color, border_width: f32) {
+        let half = rect.size.x.min(rect.size.y) * 0.5;
+        let radius = radius.clamp(0.0, half.max(0.0));
+        let segments = if radius < 0.5 { 0 } else { ((radius * 0.5).ceil() as usize).clamp(2, 12) };
+        let outer = rounded_path(rect, radius, segments);
+        let border_width = border_width.min(half);
+        if border_width > 0.0 && border.alpha() > 0 {
+            let inner = rounded_path(rect.shrink(&Edges::all(border_width)), (radius - border_width).max(0.0), segments);
+            self.ring(&outer, &inner, border);
+            if color.alpha() > 0 {
+                self.fill(&inner, color);
+            }
+        } else if color.alpha() > 0 {
+            self.fill(&outer, color);
+        }
+    }
+
+    fn text(&mut self, fonts: &FontSet, position: Vec2, text: &str, size: f32, color: Color) {
+        let layout = TextLayout::new(fonts, text, TextStyle::new(size));
+        for glyph in layout.glyphs() {
+            let placed = match self.atlas.glyph(fonts, glyph.font, glyph.glyph, size) {
+                Some(x) => x,
+                None => continue
+            };
+            let pen = position + glyph.position;
+            let (min, scale, mode) = match self.atlas.mode() {
+                //whole pixels keep coverage glyphs as crisp as they were rasterized
+                RasterMode::Coverage => (Vec2::new(pen.x.round(), pen.y.round()) + placed.offset, 1.0, COVERAGE),
+                RasterMode::Sdf { .. } => {
+                    let scale = size / placed.raster_size;
+                    (pen + placed.offset * scale, scale, SDF)
+                }
+            };
+            let max = min + Vec2::new(placed.width as f32, placed.height as f32) * scale;
+            let uv_min = [placed.x as f32, placed.y as f32];
+            let uv_max = [(placed.x + placed.width) as f32, (placed.y + placed.height) as f32];
+            let base = self.vertices.len() as u32;
+            let corners = [
+                ([min.x, min.y], uv_min),
+                ([max.x, min.y], [uv_max[0], uv_min[1]]),
+                ([max.x, max.y], uv_max),
+                ([min.x, max.y], [uv_min[0], uv_max[1]])
+            ];
+            for &(pos, uv) in corners.iter() {
+                self.vertices.push(UiVertex { pos, uv, color: color.0, mode });
+            }
+            self.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+        }
+    }
+
+    //paths from rounded_path are convex, so a fan covers them
+    fn fill(&mut self, path: &[Vec2], color: Color) {
+        let base = self.vertices.len() as u32;
+        for x in path {
+            self.vertices.push(UiVertex { pos: [x.x, x.y], uv: [0.0, 0.0], color: color.0, mode: SOLID });
+        }
+        for i in 1..path.len().saturating_sub(1) as u32 {
+            self.indices.extend_from_slice(&[base, base + i, base + i + 1]);
+        }
+    }
+
+    fn ring(&mut self, outer: &[Vec2], inner: &[Vec2], color: Color) {
+        let base = self.vertices.len() as u32;
+        let n = outer.len() as u32;
+        for x in outer.iter().chain(inner.iter()) {
+            self.vertices.push(UiVertex { pos: [x.x, x.y], uv: [0.0, 0.0], color: color.0, mode: SOLID });
+        }
+        for i in 0..n {
+            let j = (i + 1) % n;
+            self.indices.extend_from_slice(&[base + i, base + j, base + n + j, base + i, base + n + j, base + n + i]);
+        }
+    }
+}
+
+impl Drop for UiRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.texture);
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteBuffers(1, &self.ebo);
+            gl::DeleteVertexArrays(1, &self.vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
+
+/**
+ * Outline of a rounded rectangle clockwise from the top right corner
+ * The point count only depends on `segments`, so borders can join an outer and inner path
+ **/
+fn rounded_path(rect: Rect, radius: f32, segments: usize) -> Vec<Vec2> {
+    let (min, max) = (rect.min(), rect.max());
+    if segments == 0 {
+        return vec![Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y), min];
+    }
+    let r = radius;
+    let corners = [
+        (Vec2::new(max.x - r, min.y + r), -90.0f32),
+        (Vec2::new(max.x - r, max.y - r), 0.0),
+        (Vec2::new(min.x + r, max.y - r), 90.0),
+        (Vec2::new(min.x + r, min.y + r), 180.0)
+    ];
+    let mut path = Vec::with_capacity((segments + 1) * 4);
+    for &(center, start) in corners.iter() {
+        for i in 0..=segments {
+            let angle = (start + 90.0 * i as f32 / segments as f32).to_radians();
+            path.push(center + Vec2::new(angle.cos(), angle.sin()) * r);
+        }
+    }
+    path
+}
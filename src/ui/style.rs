@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::ui::UiError;
+
+/**
+ * sRGB color with straight alpha, written as [r, g, b, a] in style files
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[derive(Serialize, Deserialize)]
+pub struct Color(pub [u8; 4]);
+
+impl Color {
+    pub const TRANSPARENT: Color = Color([0, 0, 0, 0]);
+    pub const BLACK: Color = Color([0, 0, 0, 255]);
+    pub const WHITE: Color = Color([255, 255, 255, 255]);
+
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Color {
+        Color([r, g, b, 255])
+    }
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color([r, g, b, a])
+    }
+
+    #[inline]
+    pub fn alpha(self) -> u8 {
+        self.0[3]
+    }
+
+    pub fn with_alpha(self, a: u8) -> Color {
+        Color([self.0[0], self.0[1], self.0[2], a])
+    }
+}
+
+/**
+ * Visual properties of a widget, unset fields fall through to the less specific style
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    pub background: Option<Color>,
+    pub text: Option<Color>,
+    pub border: Option<Color>,
+    pub border_width: Option<f32>,
+    pub radius: Option<f32>,
+    pub font_size: Option<f32>,
+    /**
+     * Slider fill, text cursor and similar highlights
+     **/
+    pub accent: Option<Color>,
+}
+
+impl Style {
+    /**
+     * Takes every field `other` sets
+     **/
+    pub fn merge(&mut self, other: &Style) {
+        self.background = other.background.or(self.background);
+        self.text = other.text.or(self.text);
+        self.border = other.border.or(self.border);
+        self.border_width = other.border_width.or(self.border_width);
+        self.radius = other.radius.or(self.radius);
+        self.font_size = other.font_size.or(self.font_size);
+        self.accent = other.accent.or(self.accent);
+    }
+}
+
+/**
+ * A style with every field filled in, what layout and drawing work from
+ **/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolvedStyle {
+    pub background: Color,
+    pub text: Color,
+    pub border: Color,
+    pub border_width: f32,
+    pub radius: f32,
+    pub font_size: f32,
+    pub accent: Color,
+}
+
+impl ResolvedStyle {
+    pub const BASE: ResolvedStyle = ResolvedStyle {
+        background: Color::TRANSPARENT,
+        text: Color::WHITE,
+        border: Color::TRANSPARENT,
+        border_width: 0.0,
+        radius: 0.0,
+        font_size: 16.0,
+        accent: Color::rgb(74, 144, 226),
+    };
+
+    fn apply(&mut self, style: &Style) {
+        self.background = style.background.unwrap_or(self.background);
+        self.text = style.text.unwrap_or(self.text);
+        self.border = style.border.unwrap_or(self.border);
+        self.border_width = style.border_width.unwrap_or(self.border_width);
+        self.radius = style.radius.unwrap_or(self.radius);
+        self.font_size = style.font_size.unwrap_or(self.font_size);
+        self.accent = style.accent.unwrap_or(self.accent);
+    }
+}
+
+/**
+ * The interaction state a style is picked for
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WidgetState {
+    Normal,
+    Hovered,
+    Pressed,
+    Focused,
+    Disabled,
+}
+
+/**
+ * A style plus overrides for each interaction state
+ **/
+#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct StyleSheet {
+    #[serde(flatten)]
+    pub base: Style,
+    pub hovered: Option<Style>,
+    pub pressed: Option<Style>,
+    pub focused: Option<Style>,
+    pub disabled: Option<Style>,
+}
+
+impl StyleSheet {
+    pub fn new(base: Style) -> StyleSheet {
+        StyleSheet { base, ..StyleSheet::default() }
+    }
+
+    pub fn state(&self, state: WidgetState) -> Option<&Style> {
+        match state {
+            WidgetState::Normal => None,
+            WidgetState::Hovered => self.hovered.as_ref(),
+            WidgetState::Pressed => self.pressed.as_ref(),
+            WidgetState::Focused => self.focused.as_ref(),
+            WidgetState::Disabled => self.disabled.as_ref()
+        }
+    }
+
+    pub fn merge(&mut self, other: &StyleSheet) {
+        fn merge_state(into: &mut Option<Style>, other: &Option<Style>) {
+            if let Some(x) = other {
+                into.get_or_insert_with(Style::default).merge(x);
+            }
+        }
+        self.base.merge(&other.base);
+        merge_state(&mut self.hovered, &other.hovered);
+        merge_state(&mut self.pressed, &other.pressed);
+        merge_state(&mut self.focused, &other.focused);
+        merge_state(&mut self.disabled, &other.disabled);
+    }
+}
+
+/**
+ * Style sheets by class name, a widget is styled by the sheet for its kind ("button", "label",
+ * "slider", "text_field", "scroll_view", "image", "panel") and then by its own class
+ * Style files are JSON objects of classes, for example
+ * { "button": { "background": [40, 40, 48, 255], "hovered": { "background": [60, 60, 72, 255] } } }
+ **/
+#[derive(Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Theme {
+    classes: HashMap<String, StyleSheet>,
+}
+
+impl Theme {
+    /**
+     * A theme with no classes, everything falls back to ResolvedStyle::BASE
+     **/
+    pub fn empty() -> Theme {
+        Theme { classes: HashMap::new() }
+    }
+
+    /**
+     * The default theme with the JSON's classes layered over it
+     **/
+    pub fn from_json(json: &str) -> Result<Theme, UiError> {
+        let mut theme = Theme::default();
+        theme.merge(&serde_json::from_str(json)?);
+        Ok(theme)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Theme, UiError> {
+        Theme::from_json(&fs::read_to_string(path)?)
+    }
+
+    pub fn get(&self, class: &str) -> Option<&StyleSheet> {
+        self.classes.get(class)
+    }
+
+    pub fn set(&mut self, class: &str, sheet: StyleSheet) {
+        self.classes.insert(class.to_string(), sheet);
+    }
+
+    /**
+     * Layers `other` over this theme, field by field
+     **/
+    pub fn merge(&mut self, other: &Theme) {
+        for (class, sheet) in &other.classes {
+            self.classes.entry(class.clone()).or_default().merge(sheet);
+        }
+    }
+
+    pub fn resolve(&self, kind: &str, class: Option<&str>, state: WidgetState) -> ResolvedStyle {
+        let mut style = ResolvedStyle::BASE;
+        let sheets = [Some(kind), class];
+        let sheets = sheets.iter().flatten().filter_map(|x| self.classes.get(*x));
+        for sheet in sheets.clone() {
+            style.apply(&sheet.base);
+        }
+        for sheet in sheets {
+            if let Some(x) = sheet.state(state) {
+                style.apply(x);
+            }
+        }
+        style
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        let accent = Color::rgb(74, 144, 226);
+        let mut theme = Theme::empty();
+        theme.set("label", StyleSheet::new(Style { text: Some(Color::rgb(230, 230, 235)), ..Style::default() }));
+        theme.set("button", StyleSheet {
+            base: Style {
+                background: Some(Color::rgb(48, 50, 60)),
+                text: Some(Color::rgb(230, 230, 235)),
+                border: Some(Color::rgb(70, 72, 84)),
+                border_width: Some(1.0),
+                radius: Some(4.0),
+                ..Style::default()
+            },
+            hovered: Some(Style { background: Some(Color::rgb(62, 65, 78)), ..Style::default() }),
+            pressed: Some(Style { background: Some(Color::rgb(36, 38, 46)), ..Style::default() }),
+            focused: Some(Style { border: Some(accent), border_width: Some(2.0), ..Style::default() }),
+            disabled: Some(Style { text: Some(Color::rgb(120, 120, 128)), ..Style::default() }),
+        });
+        theme.set("slider", StyleSheet {
+            base: Style { background: Some(Color::rgb(36, 38, 46)), accent: Some(accent), radius: Some(2.0), ..Style::default() },
+            focused: Some(Style { border: Some(accent), border_width: Some(1.0), ..Style::default() }),
+            disabled: Some(Style { accent: Some(Color::rgb(90, 90, 98)), ..Style::default() }),
+            ..StyleSheet::default()
+        });
+        theme.set("text_field", StyleSheet {
+            base: Style {
+                background: Some(Color::rgb(24, 25, 30)),
+                text: Some(Color::rgb(230, 230, 235)),
+                border: Some(Color::rgb(70, 72, 84)),
+                border_width: Some(1.0),
+                radius: Some(3.0),
+                accent: Some(accent),
+                ..Style::default()
+            },
+            focused: Some(Style { border: Some(accent), ..Style::default() }),
+            ..StyleSheet::default()
+        });
+        theme.set("scroll_view", StyleSheet::new(Style { accent: Some(Color::rgba(255, 255, 255, 60)), ..Style::default() }));
+        theme.set("image", StyleSheet::new(Style::default()));
+        theme.set("panel", StyleSheet::new(Style::default()));
+        theme
+    }
+}
@@ -0,0 +1,1213 @@
+use std::sync::{ Arc, Mutex };
+
+use crate::core::layers::Layer;
+use crate::core::object::Object;
+use crate::core::scene::AssetRef;
+use crate::events::event::{ Event, EventData, EventType };
+use crate::math::Vec2;
+use crate::physics::arena::Arena;
+use crate::text::{ grapheme_count, next_grapheme, prev_grapheme };
+use crate::ui::input::{ MOD_SHIFT, SCROLL_LINE };
+use crate::ui::layout::{ Align, Anchor, Direction, Justify, Rect, Size };
+use crate::ui::nav::NavInput;
+use crate::ui::style::{ Color, ResolvedStyle, Theme, WidgetState };
+use crate::ui::widgets::{ Widget, WidgetKind };
+
+/**
+ * Handle to a widget in a UiTree, stale once the widget is removed
+ **/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WidgetId {
+    index: u32,
+    generation: u32,
+}
+
+/**
+ * What the player did with the UI, collected until the game takes them
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub enum UiAction {
+    Clicked(WidgetId),
+    ValueChanged(WidgetId, f32),
+    TextChanged(WidgetId, String),
+    /**
+     * Enter in a text field
+     **/
+    Submitted(WidgetId, String),
+    FocusChanged(Option<WidgetId>),
+    /**
+     * Escape or the gamepad's back button, menus usually close on it
+     **/
+    Back,
+}
+
+/**
+ * Backend neutral drawing for a UI, in window coordinates
+ * Renderers scale by the framebuffer to window size ratio, the same way the debug UI's
+ * pixels per point work
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub enum DrawCommand {
+    Rect { rect: Rect, color: Color, radius: f32, border: Color, border_width: f32 },
+    /**
+     * Position is the top left of the text's line box
+     **/
+    Text { position: Vec2, text: String, size: f32, color: Color },
+    Image { rect: Rect, path: String, tint: Color },
+    /**
+     * Already intersected with the enclosing clip
+     **/
+    PushClip(Rect),
+    PopClip,
+}
+
+/**
+ * Size of a string at a font size, used for layout until a real font is set
+ **/
+pub type TextMeasure = Box<dyn Fn(&str, f32) -> Vec2 + Send + Sync>;
+
+/**
+ * Rough text size from a fixed advance, good enough to lay out before fonts are loaded
+ **/
+pub fn estimate_text(text: &str, size: f32) -> Vec2 {
+    let lines = text.split('\n');
+    let width = lines.clone().map(|x| x.chars().count()).max().unwrap_or(0);
+    Vec2::new(width as f32 * size * 0.55, lines.count() as f32 * size * 1.25)
+}
+
+struct Node {
+    widget: Widget,
+    parent: Option<WidgetId>,
+    children: Vec<WidgetId>,
+    rect: Rect,
+    //main axis length of a scroll view's content
+    content: f32,
+}
+
+/**
+ * A retained widget tree for menus and HUDs
+ * Layout works in logical pixels, window coordinates divided by the UI scale, so the same
+ * layout fits any resolution and grows with the monitor's content scale
+ **/
+pub struct UiTree {
+    nodes: Arena<Node>,
+    roots: Vec<WidgetId>,
+    theme: Theme,
+    screen: Vec2,
+    framebuffer: Option<Vec2>,
+    content_scale: f32,
+    dirty: bool,
+    pointer: Vec2,
+    hovered: Option<WidgetId>,
+    pressed: Option<WidgetId>,
+    focused: Option<WidgetId>,
+    actions: Vec<UiAction>,
+    measure: TextMeasure,
+}
+
+impl UiTree {
+    /**
+     * An empty tree for a window of the given size in window coordinates
+     **/
+    pub fn new(screen: Vec2, content_scale: Option<f32>, theme: Option<Theme>) -> UiTree {
+        UiTree {
+            nodes: Arena::new(),
+            roots: Vec::new(),
+            theme: theme.unwrap_or_default(),
+            screen,
+            framebuffer: None,
+            content_scale: content_scale.unwrap_or(1.0),
+            dirty: true,
+            pointer: Vec2::ZERO,
+            hovered: None,
+            pressed: None,
+            focused: None,
+            actions: Vec::new(),
+            measure: Box::new(estimate_text),
+        }
+    }
+
+    fn node(&self, id: WidgetId) -> Option<&Node> {
+        self.nodes.get(id.index, id.generation)
+    }
+
+    fn node_mut(&mut self, id: WidgetId) -> Option<&mut Node> {
+        self.nodes.get_mut(id.index, id.generation)
+    }
+
+    /**
+     * Adds a widget as the last child of `parent`, or as a new root drawn over the others
+     * None if the parent doesn't exist
+     **/
+    pub fn add(&mut self, parent: Option<WidgetId>, widget: Widget) -> Option<WidgetId> {
+        if let Some(x) = parent {
+            self.node(x)?;
+        }
+        let node = Node { widget, parent, children: Vec::new(), rect: Rect::default(), content: 0.0 };
+        let (index, generation) = self.nodes.insert(node);
+        let id = WidgetId { index, generation };
+        match parent.and_then(|x| self.node_mut(x)) {
+            Some(x) => x.children.push(id),
+            None => self.roots.push(id)
+        }
+        self.dirty = true;
+        Some(id)
+    }
+
+    /**
+     * Removes a widget and everything under it
+     **/
+    pub fn remove(&mut self, id: WidgetId) -> Option<Widget> {
+        let parent = self.node(id)?.parent;
+        match parent.and_then(|x| self.node_mut(x)) {
+            Some(x) => x.children.retain(|x| *x != id),
+            None => self.roots.retain(|x| *x != id)
+        }
+        let node = self.remove_node(id)?;
+        if self.hovered.is_some_and(|x| self.node(x).is_none()) {
+            self.hovered = None;
+        }
+        if self.pressed.is_some_and(|x| self.node(x).is_none()) {
+            self.pressed = None;
+        }
+        if self.focused.is_some_and(|x| self.node(x).is_none()) {
+            self.set_focus(None);
+        }
+        self.dirty = true;
+        Some(node.widget)
+    }
+
+    fn remove_node(&mut self, id: WidgetId) -> Option<Node> {
+        let node = self.nodes.remove(id.index, id.generation)?;
+        for child in &node.children {
+            self.remove_node(*child);
+        }
+        Some(node)
+    }
+
+    pub fn contains(&self, id: WidgetId) -> bool {
+        self.node(id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn widget(&self, id: WidgetId) -> Option<&Widget> {
+        self.node(id).map(|x| &x.widget)
+    }
+
+    /**
+     * Layout is redone on the next update after any change made through this
+     **/
+    pub fn widget_mut(&mut self, id: WidgetId) -> Option<&mut Widget> {
+        self.dirty = true;
+        self.node_mut(id).map(|x| &mut x.widget)
+    }
+
+    pub fn parent(&self, id: WidgetId) -> Option<WidgetId> {
+        self.node(id)?.parent
+    }
+
+    pub fn children(&self, id: WidgetId) -> &[WidgetId] {
+        self.node(id).map_or(&[], |x| &x.children)
+    }
+
+    pub fn roots(&self) -> &[WidgetId] {
+        &self.roots
+    }
+
+    /**
+     * Where the widget was last laid out, in logical pixels
+     **/
+    pub fn rect(&self, id: WidgetId) -> Option<Rect> {
+        self.node(id).map(|x| x.rect)
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.dirty = true;
+    }
+
+    /**
+     * Replaces the text measurement used for layout, fonts hook in here
+     **/
+    pub fn set_text_measure<F>(&mut self, measure: F) where F: Fn(&str, f32) -> Vec2 + Send + Sync + 'static {
+        self.measure = Box::new(measure);
+        self.dirty = true;
+    }
+
+    /**
+     * Window size in window coordinates
+     **/
+    #[inline]
+    pub fn screen_size(&self) -> Vec2 {
+        self.screen
+    }
+
+    pub fn set_screen_size(&mut self, size: Vec2) {
+        self.screen = size;
+        self.dirty = true;
+    }
+
+    pub fn set_framebuffer_size(&mut self, size: Vec2) {
+        self.framebuffer = Some(size);
+        self.dirty = true;
+    }
+
+    /**
+     * Sets the window size, framebuffer size and content scale together, only relaying out if
+     * one of them changed, for renderers that read them from the window every frame
+     **/
+    pub fn set_window(&mut self, size: Vec2, framebuffer: Vec2, content_scale: f32) {
+        if self.screen != size || self.framebuffer != Some(framebuffer) || (content_scale > 0.0 && self.content_scale != content_scale) {
+            self.screen = size;
+            self.framebuffer = Some(framebuffer);
+            if content_scale > 0.0 {
+                self.content_scale = content_scale;
+            }
+            self.dirty = true;
+        }
+    }
+
+    #[inline]
+    pub fn content_scale(&self) -> f32 {
+        self.content_scale
+    }
+
+    pub fn set_content_scale(&mut self, scale: f32) {
+        if scale > 0.0 {
+            self.content_scale = scale;
+            self.dirty = true;
+        }
+    }
+
+    /**
+     * Logical pixels to window coordinates
+     * Where window coordinates are already scaled (macOS) the framebuffer is bigger than the
+     * window, and that part of the content scale is taken back out
+     **/
+    pub fn scale(&self) -> f32 {
+        let density = match self.framebuffer {
+            Some(x) if x.x > 0.0 && self.screen.x > 0.0 => x.x / self.screen.x,
+            _ => 1.0
+        };
+        (self.content_scale / density).max(0.01)
+    }
+
+    /**
+     * Screen size in logical pixels
+     **/
+    pub fn logical_size(&self) -> Vec2 {
+        self.screen / self.scale()
+    }
+
+    #[inline]
+    pub fn hovered(&self) -> Option<WidgetId> {
+        self.hovered
+    }
+
+    #[inline]
+    pub fn focused(&self) -> Option<WidgetId> {
+        self.focused
+    }
+
+    pub fn set_focus(&mut self, id: Option<WidgetId>) {
+        if self.focused == id {
+            return;
+        }
+        self.focused = id;
+        if let Some(x) = id {
+            self.scroll_into_view(x);
+        }
+        self.actions.push(UiAction::FocusChanged(id));
+    }
+
+    pub fn take_actions(&mut self) -> Vec<UiAction> {
+        std::mem::take(&mut self.actions)
+    }
+
+    pub fn assets(&self) -> Vec<AssetRef> {
+        self.nodes.iter().filter_map(|(_, _, x)| match &x.widget.kind {
+            WidgetKind::Image { path, .. } => Some(AssetRef::new("image", path)),
+            _ => None
+        }).collect()
+    }
+
+    /**
+     * The style a widget draws with right now
+     **/
+    pub fn style(&self, id: WidgetId) -> Option<ResolvedStyle> {
+        let widget = &self.node(id)?.widget;
+        Some(self.theme.resolve(widget.kind.name(), widget.class.as_deref(), self.state(id)))
+    }
+
+    fn state(&self, id: WidgetId) -> WidgetState {
+        if !self.enabled(id) {
+            WidgetState::Disabled
+        } else if self.pressed == Some(id) {
+            WidgetState::Pressed
+        } else if self.focused == Some(id) {
+            WidgetState::Focused
+        } else if self.hovered == Some(id) {
+            WidgetState::Hovered
+        } else {
+            WidgetState::Normal
+        }
+    }
+
+    //widgets are disabled and hidden along with their parents
+    fn enabled(&self, id: WidgetId) -> bool {
+        self.lineage(id).iter().all(|x| self.node(*x).is_some_and(|x| x.widget.enabled))
+    }
+
+    fn visible(&self, id: WidgetId) -> bool {
+        self.lineage(id).iter().all(|x| self.node(*x).is_some_and(|x| x.widget.visible))
+    }
+
+    //the widget then its ancestors up to the root
+    fn lineage(&self, id: WidgetId) -> Vec<WidgetId> {
+        let mut lineage = vec![id];
+        let mut current = self.parent(id);
+        while let Some(x) = current {
+            lineage.push(x);
+            current = self.parent(x);
+        }
+        lineage
+    }
+
+    fn font_size(&self, widget: &Widget) -> f32 {
+        //layout uses the normal style so hovering can't move things around
+        self.theme.resolve(widget.kind.name(), widget.class.as_deref(), WidgetState::Normal).font_size
+    }
+
+    /**
+     * Lays the tree out if anything changed since the last time
+     **/
+    pub fn layout(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        let screen = self.logical_size();
+        for root in self.roots.clone() {
+            let layout = match self.node(root) {
+                Some(x) if x.widget.visible => x.widget.layout,
+                _ => continue
+            };
+            let origin = Vec2::new(layout.margin.left, layout.margin.top) + layout.offset;
+            let area = (screen - layout.margin.size()).max(Vec2::ZERO);
+            let rect = if layout.anchor == Anchor::Stretch {
+                Rect { position: origin, size: area }
+            } else {
+                let measured = self.measure(root);
+                let size = Vec2::new(
+                    resolve_size(layout.width, area.x, measured.x),
+                    resolve_size(layout.height, area.y, measured.y)
+                ).max(layout.min_size);
+                Rect { position: origin + (area - size) * layout.anchor.fraction(), size }
+            };
+            self.place(root, rect);
+        }
+    }
+
+    //border box size a widget wants, ignoring percentages and growing
+    fn measure(&self, id: WidgetId) -> Vec2 {
+        let node = match self.node(id) {
+            Some(x) => x,
+            None => return Vec2::ZERO
+        };
+        let widget = &node.widget;
+        let layout = &widget.layout;
+        let font = self.font_size(widget);
+        let content = match &widget.kind {
+            WidgetKind::Label { text } => (self.measure)(text, font),
+            WidgetKind::Button { text } => (self.measure)(text, font) + Vec2::new(font * 1.5, font * 0.5),
+            WidgetKind::Slider { .. } => Vec2::new(font * 10.0, font),
+            WidgetKind::TextField { .. } => Vec2::new(font * 12.0, (self.measure)("", font).y + font * 0.5),
+            WidgetKind::Image { size, .. } => *size,
+            WidgetKind::Panel | WidgetKind::ScrollView { .. } => {
+                let row = layout.direction == Direction::Row;
+                let mut main = 0.0;
+                let mut cross: f32 = 0.0;
+                let mut count = 0;
+                for child in &node.children {
+                    let margin = match self.node(*child) {
+                        Some(x) if x.widget.visible => x.widget.layout.margin.size(),
+                        _ => continue
+                    };
+                    let (m, c) = split(self.measure(*child) + margin, row);
+                    main += m;
+                    cross = cross.max(c);
+                    count += 1;
+                }
+                main += layout.gap * (count.max(1) - 1) as f32;
+                join(main, cross, row)
+            }
+        };
+        let size = content + layout.padding.size();
+        Vec2::new(
+            match layout.width { Size::Px(x) => x, _ => size.x },
+            match layout.height { Size::Px(x) => x, _ => size.y }
+        ).max(layout.min_size)
+    }
+
+    fn place(&mut self, id: WidgetId, rect: Rect) {
+        struct Item {
+            id: WidgetId,
+            main: f32,
+            cross: f32,
+            min_main: f32,
+            margin_main: (f32, f32),
+            margin_cross: (f32, f32),
+            grow: f32,
+            shrink: f32,
+        }
+
+        let (layout, children, scroll) = match self.node_mut(id) {
+            Some(node) => {
+                node.rect = rect;
+                let scroll = match node.widget.kind {
+                    WidgetKind::ScrollView { offset } => Some(offset),
+                    _ => None
+                };
+                (node.widget.layout, node.children.clone(), scroll)
+            },
+            None => return
+        };
+        let inner = rect.shrink(&layout.padding);
+        let row = layout.direction == Direction::Row;
+        let (inner_main, inner_cross) = split(inner.size, row);
+
+        let mut items = Vec::with_capacity(children.len());
+        for child in children {
+            let l = match self.node(child) {
+                Some(x) if x.widget.visible => x.widget.layout,
+                _ => continue
+            };
+            let measured = self.measure(child);
+            let size = Vec2::new(
+                resolve_size(l.width, inner.size.x, measured.x),
+                resolve_size(l.height, inner.size.y, measured.y)
+            ).max(l.min_size);
+            let (main, cross) = split(size, row);
+            let (min_main, min_cross) = split(l.min_size, row);
+            let (margin_main, margin_cross, cross_size) = if row {
+                ((l.margin.left, l.margin.right), (l.margin.top, l.margin.bottom), l.height)
+            } else {
+                ((l.margin.top, l.margin.bottom), (l.margin.left, l.margin.right), l.width)
+            };
+            let cross = if cross_size == Size::Auto && layout.align == Align::Stretch {
+                (inner_cross - margin_cross.0 - margin_cross.1).max(min_cross)
+            } else {
+                cross
+            };
+            items.push(Item { id: child, main, cross, min_main, margin_main, margin_cross, grow: l.grow, shrink: l.shrink });
+        }
+
+        let gaps = layout.gap * (items.len().max(1) - 1) as f32;
+        let used = items.iter().map(|x| x.main + x.margin_main.0 + x.margin_main.1).sum::<f32>() + gaps;
+        let mut free = inner_main - used;
+        //scroll views keep their content's size and scroll it instead
+        if scroll.is_none() {
+            let grow: f32 = items.iter().map(|x| x.grow).sum();
+            let shrink: f32 = items.iter().map(|x| x.shrink * x.main).sum();
+            if free > 0.0 && grow > 0.0 {
+                for x in &mut items {
+                    x.main += free * x.grow / grow;
+                }
+                free = 0.0;
+            } else if free < 0.0 && shrink > 0.0 {
+                for x in &mut items {
+                    x.main = (x.main + free * x.shrink * x.main / shrink).max(x.min_main);
+                }
+                free = 0.0;
+            }
+        }
+
+        let free = free.max(0.0);
+        let count = items.len() as f32;
+        let (start, spacing) = match layout.justify {
+            Justify::Start => (0.0, 0.0),
+            Justify::Center => (free * 0.5, 0.0),
+            Justify::End => (free, 0.0),
+            Justify::SpaceBetween if items.len() > 1 => (0.0, free / (count - 1.0)),
+            Justify::SpaceBetween => (0.0, 0.0),
+            Justify::SpaceAround if !items.is_empty() => (free / count * 0.5, free / count),
+            Justify::SpaceAround => (0.0, 0.0)
+        };
+
+        let offset = match scroll {
+            Some(offset) => {
+                let offset = offset.clamp(0.0, (used - inner_main).max(0.0));
+                if let Some(node) = self.node_mut(id) {
+                    node.widget.kind = WidgetKind::ScrollView { offset };
+                    node.content = used;
+                }
+                offset
+            },
+            None => 0.0
+        };
+
+        let (main_start, cross_start) = split(inner.position, row);
+        let mut cursor = main_start + start - offset;
+        for item in items {
+            cursor += item.margin_main.0;
+            let free_cross = inner_cross - item.cross - item.margin_cross.0 - item.margin_cross.1;
+            let cross = cross_start + item.margin_cross.0 + match layout.align {
+                Align::Start | Align::Stretch => 0.0,
+                Align::Center => free_cross * 0.5,
+                Align::End => free_cross
+            };
+            let rect = Rect { position: join(cursor, cross, row), size: join(item.main, item.cross, row) };
+            self.place(item.id, rect);
+            cursor += item.main + item.margin_main.1 + layout.gap + spacing;
+        }
+    }
+
+    /**
+     * The topmost visible widget under a point in logical pixels
+     **/
+    pub fn widget_at(&mut self, point: Vec2) -> Option<WidgetId> {
+        self.layout();
+        self.hit(point)
+    }
+
+    fn hit(&self, point: Vec2) -> Option<WidgetId> {
+        self.roots.iter().rev().find_map(|x| self.hit_node(*x, point))
+    }
+
+    fn hit_node(&self, id: WidgetId, point: Vec2) -> Option<WidgetId> {
+        let node = self.node(id)?;
+        if !node.widget.visible {
+            return None;
+        }
+        let inside = node.rect.contains(point);
+        //scroll views clip their children
+        if !inside && matches!(node.widget.kind, WidgetKind::ScrollView { .. }) {
+            return None;
+        }
+        node.children.iter().rev().find_map(|x| self.hit_node(*x, point)).or(if inside { Some(id) } else { None })
+    }
+
+    //the enabled widget taking pointer input at a hit
+    fn interactive(&self, hit: Option<WidgetId>) -> Option<WidgetId> {
+        let id = hit?;
+        self.lineage(id).into_iter()
+            .find(|x| self.node(*x).is_some_and(|x| x.widget.kind.interactive()))
+            .filter(|x| self.enabled(*x))
+    }
+
+    //whether a hit keeps the pointer from reaching the game, transparent panels don't
+    fn blocks(&self, hit: Option<WidgetId>) -> bool {
+        let id = match hit {
+            Some(x) => x,
+            None => return false
+        };
+        self.lineage(id).iter().any(|x| match self.node(*x) {
+            Some(node) => match node.widget.kind {
+                WidgetKind::Panel | WidgetKind::Label { .. } => {
+                    let style = self.theme.resolve(node.widget.kind.name(), node.widget.class.as_deref(), WidgetState::Normal);
+                    style.background.alpha() > 0
+                },
+                _ => true
+            },
+            None => false
+        })
+    }
+
+    //focusable widgets in tree order
+    fn focus_order(&self) -> Vec<WidgetId> {
+        fn visit(tree: &UiTree, id: WidgetId, order: &mut Vec<WidgetId>) {
+            let node = match tree.node(id) {
+                Some(x) if x.widget.visible && x.widget.enabled => x,
+                _ => return
+            };
+            if node.widget.kind.interactive() {
+                order.push(id);
+            }
+            for child in &node.children {
+                visit(tree, *child, order);
+            }
+        }
+        let mut order = Vec::new();
+        for root in &self.roots {
+            visit(self, *root, &mut order);
+        }
+        order
+    }
+
+    fn focusable(&self, id: WidgetId) -> bool {
+        self.node(id).is_some_and(|x| x.widget.kind.interactive()) && self.visible(id) && self.enabled(id)
+    }
+
+    /**
+     * Moves focus or acts on the focused widget, true if the input did anything
+     * Nothing focused yet focuses the first widget on any direction
+     **/
+    pub fn navigate(&mut self, input: NavInput) -> bool {
+        self.layout();
+        let focused = self.focused.filter(|x| self.focusable(*x));
+        match input {
+            NavInput::Back => {
+                self.actions.push(UiAction::Back);
+                return true;
+            },
+            NavInput::Activate => return match focused {
+                Some(x) => self.activate(x),
+                None => false
+            },
+            _ => {}
+        }
+        let id = match focused {
+            Some(x) => x,
+            None => {
+                let first = self.focus_order().first().copied();
+                self.set_focus(first);
+                return first.is_some();
+            }
+        };
+        //sliders and text fields use left and right themselves
+        let next = match input {
+            NavInput::Left if self.adjust(id, false) => return true,
+            NavInput::Right if self.adjust(id, true) => return true,
+            NavInput::Next | NavInput::Previous => {
+                let order = self.focus_order();
+                let i = order.iter().position(|x| *x == id).unwrap_or(0);
+                let n = order.len();
+                match input {
+                    NavInput::Next => order.get((i + 1) % n).copied(),
+                    _ => order.get((i + n - 1) % n).copied()
+                }
+            },
+            NavInput::Up => self.nearest(id, Vec2::new(0.0, -1.0)),
+            NavInput::Down => self.nearest(id, Vec2::new(0.0, 1.0)),
+            NavInput::Left => self.nearest(id, Vec2::new(-1.0, 0.0)),
+            NavInput::Right => self.nearest(id, Vec2::new(1.0, 0.0)),
+            _ => None
+        };
+        match next {
+            Some(x) => {
+                self.set_focus(Some(x));
+                true
+            },
+            None => false
+        }
+    }
+
+    //closest focusable widget in a direction, straying off the axis costs double
+    fn nearest(&self, from: WidgetId, direction: Vec2) -> Option<WidgetId> {
+        let origin = self.rect(from)?.center();
+        let mut best = None;
+        for id in self.focus_order() {
+            let rect = match self.rect(id) {
+                Some(x) if id != from => x,
+                _ => continue
+            };
+            let delta = rect.center() - origin;
+            let along = delta.dot(direction);
+            if along <= 0.5 {
+                continue;
+            }
+            let score = along + (delta - direction * along).length() * 2.0;
+            if best.is_none_or(|(_, x)| score < x) {
+                best = Some((id, score));
+            }
+        }
+        best.map(|(x, _)| x)
+    }
+
+    fn activate(&mut self, id: WidgetId) -> bool {
+        let action = match &self.node(id).map(|x| &x.widget.kind) {
+            Some(WidgetKind::Button { .. }) => UiAction::Clicked(id),
+            Some(WidgetKind::TextField { text, .. }) => UiAction::Submitted(id, text.clone()),
+            _ => return false
+        };
+        self.actions.push(action);
+        true
+    }
+
+    //left and right on sliders and text fields, false to let focus move instead
+    fn adjust(&mut self, id: WidgetId, forward: bool) -> bool {
+        let node = match self.node_mut(id) {
+            Some(x) => x,
+            None => return false
+        };
+        match &mut node.widget.kind {
+            WidgetKind::Slider { value, min, max, step } => {
+                let step = if *step > 0.0 { *step } else { (*max - *min) / 20.0 };
+                let target = if forward { *value + step } else { *value - step };
+                if node.widget.set_value(target) {
+                    let value = node.widget.value().unwrap_or(0.0);
+                    self.actions.push(UiAction::ValueChanged(id, value));
+                }
+                true
+            },
+            WidgetKind::TextField { text, cursor, .. } => {
+                let moved = if forward { next_grapheme(text, *cursor) } else { prev_grapheme(text, *cursor) };
+                match moved {
+                    Some(x) => {
+                        *cursor = x;
+                        true
+                    },
+                    None => false
+                }
+            },
+            _ => false
+        }
+    }
+
+    //scrolls every scroll view holding the widget until it's inside them
+    fn scroll_into_view(&mut self, id: WidgetId) {
+        self.layout();
+        let mut rect = match self.rect(id) {
+            Some(x) => x,
+            None => return
+        };
+        for ancestor in self.lineage(id).into_iter().skip(1) {
+            let node = match self.node_mut(ancestor) {
+                Some(x) => x,
+                None => return
+            };
+            if let WidgetKind::ScrollView { offset } = &mut node.widget.kind {
+                let row = node.widget.layout.direction == Direction::Row;
+                let inner = node.rect.shrink(&node.widget.layout.padding);
+                let (start, _) = split(inner.position, row);
+                let (length, _) = split(inner.size, row);
+                let (min, _) = split(rect.position, row);
+                let (size, _) = split(rect.size, row);
+                let shift = if min < start {
+                    min - start
+                } else if min + size > start + length {
+                    (min + size - start - length).min(min - start)
+                } else {
+                    0.0
+                };
+                if shift != 0.0 {
+                    *offset += shift;
+                    rect.position -= join(shift, 0.0, row);
+                    self.dirty = true;
+                }
+            }
+        }
+    }
+
+    fn drag_slider(&mut self, id: WidgetId) {
+        let pointer = self.pointer;
+        let node = match self.node_mut(id) {
+            Some(x) => x,
+            None => return
+        };
+        let (min, max) = match node.widget.kind {
+            WidgetKind::Slider { min, max, .. } => (min, max),
+            _ => return
+        };
+        let inner = node.rect.shrink(&node.widget.layout.padding);
+        let t = if inner.size.x > 0.0 { ((pointer.x - inner.position.x) / inner.size.x).clamp(0.0, 1.0) } else { 0.0 };
+        if node.widget.set_value(min + (max - min) * t) {
+            let value = node.widget.value().unwrap_or(0.0);
+            self.actions.push(UiAction::ValueChanged(id, value));
+        }
+    }
+
+    //puts a text field's cursor at the character boundary nearest the pointer
+    fn place_cursor(&mut self, id: WidgetId) {
+        let pointer = self.pointer;
+        let font = match self.node(id) {
+            Some(x) => self.font_size(&x.widget),
+            None => return
+        };
+        let measure = &self.measure;
+        let node = match self.nodes.get_mut(id.index, id.generation) {
+            Some(x) => x,
+            None => return
+        };
+        let x = pointer.x - node.rect.shrink(&node.widget.layout.padding).position.x;
+        if let WidgetKind::TextField { text, cursor, .. } = &mut node.widget.kind {
+            let boundaries = text.char_indices().map(|(i, _)| i).chain(std::iter::once(text.len()));
+            *cursor = boundaries
+                .map(|i| (i, (measure(&text[..i], font).x - x).abs()))
+                .fold((0, f32::MAX), |best, x| if x.1 < best.1 { x } else { best }).0;
+        }
+    }
+
+    //keys a focused text field takes, None when it isn't focused on one
+    fn edit_key(&mut self, code: i32) -> Option<bool> {
+        let id = self.focused.filter(|x| self.enabled(*x))?;
+        let node = self.node_mut(id)?;
+        let (text, cursor) = match &mut node.widget.kind {
+            WidgetKind::TextField { text, cursor, .. } => (text, cursor),
+            _ => return None
+        };
+        let changed = match code {
+            //backspace
+            259 => match prev_grapheme(text, *cursor) {
+                Some(x) => {
+                    text.replace_range(x..*cursor, "");
+                    *cursor = x;
+                    true
+                },
+                None => false
+            },
+            //delete
+            261 => match next_grapheme(text, *cursor) {
+                Some(x) => {
+                    text.replace_range(*cursor..x, "");
+                    true
+                },
+                None => false
+            },
+            //home and end
+            268 => {
+                *cursor = 0;
+                false
+            },
+            269 => {
+                *cursor = text.len();
+                false
+            },
+            //printable keys arrive again as text input, enter, tab, arrows and escape navigate
+            32..=255 => false,
+            _ => return None
+        };
+        if changed {
+            let text = text.clone();
+            self.actions.push(UiAction::TextChanged(id, text));
+        }
+        Some(true)
+    }
+
+    fn insert_text(&mut self, c: char) -> bool {
+        let id = match self.focused.filter(|x| self.enabled(*x)) {
+            Some(x) => x,
+            None => return false
+        };
+        let node = match self.node_mut(id) {
+            Some(x) => x,
+            None => return false
+        };
+        if let WidgetKind::TextField { text, cursor, max_chars, .. } = &mut node.widget.kind {
+            if !c.is_control() {
+                //combining marks join the grapheme before them, so they still fit at the limit
+                let mut edited = text.clone();
+                edited.insert(*cursor, c);
+                if grapheme_count(&edited) <= *max_chars {
+                    *text = edited;
+                    *cursor += c.len_utf8();
+                    let text = text.clone();
+                    self.actions.push(UiAction::TextChanged(id, text));
+                }
+            }
+            return true;
+        }
+        false
+    }
+
+    /**
+     * Feeds a window event to the UI, true if the UI used it and the game shouldn't see it
+     **/
+    pub fn handle_event(&mut self, data: &EventData) -> bool {
+        match (data.event_type(), data) {
+            (EventType::WindowResize, EventData::F32p(x, y, _)) => {
+                self.set_screen_size(Vec2::new(*x, *y));
+                false
+            },
+            (EventType::RenderFramebufferResize, EventData::F32p(x, y, _)) => {
+                self.set_framebuffer_size(Vec2::new(*x, *y));
+                false
+            },
+            (EventType::RenderContentScaleResize, EventData::F32p(x, _, _)) => {
+                self.set_content_scale(*x);
+                false
+            },
+            (EventType::MouseMoved, EventData::F32p(x, y, _)) => {
+                self.layout();
+                self.pointer = Vec2::new(*x, *y) / self.scale();
+                self.hovered = self.interactive(self.hit(self.pointer));
+                if let Some(x) = self.pressed {
+                    self.drag_slider(x);
+                }
+                false
+            },
+            (EventType::MouseButtonPressed, EventData::I32p(button, _, _)) => {
+                self.layout();
+                let hit = self.hit(self.pointer);
+                if *button == 0 {
+                    let target = self.interactive(hit);
+                    self.pressed = target;
+                    self.set_focus(target);
+                    if let Some(x) = target {
+                        self.drag_slider(x);
+                        self.place_cursor(x);
+                    }
+                }
+                self.blocks(hit)
+            },
+            (EventType::MouseButtonReleased, EventData::I32p(button, _, _)) => {
+                self.layout();
+                let hit = self.hit(self.pointer);
+                if *button != 0 {
+                    return self.blocks(hit);
+                }
+                let pressed = self.pressed.take();
+                if let Some(x) = pressed {
+                    let button = matches!(self.widget(x).map(|x| &x.kind), Some(WidgetKind::Button { .. }));
+                    if button && self.interactive(hit) == Some(x) {
+                        self.actions.push(UiAction::Clicked(x));
+                    }
+                }
+                pressed.is_some() || self.blocks(hit)
+            },
+            (EventType::MouseScrolled, EventData::F32p(x, y, _)) => {
+                self.layout();
+                let view = self.hit(self.pointer).and_then(|hit| self.lineage(hit).into_iter().find(|x| {
+                    matches!(self.widget(*x).map(|x| &x.kind), Some(WidgetKind::ScrollView { .. }))
+                }));
+                let node = match view.and_then(|x| self.node_mut(x)) {
+                    Some(x) => x,
+                    None => return false
+                };
+                let delta = if node.widget.layout.direction == Direction::Row && *x != 0.0 { *x } else { *y };
+                if let WidgetKind::ScrollView { offset } = &mut node.widget.kind {
+                    *offset -= delta * SCROLL_LINE;
+                }
+                self.dirty = true;
+                true
+            },
+            (EventType::KeyPressed, EventData::I32p(code, mods, _)) => {
+                if let Some(x) = self.edit_key(*code) {
+                    return x;
+                }
+                match NavInput::from_key(*code, mods & MOD_SHIFT != 0) {
+                    Some(x) => self.navigate(x),
+                    None => false
+                }
+            },
+            (EventType::TextInput, EventData::U32p(c, _, _)) => match std::char::from_u32(*c) {
+                Some(c) => self.insert_text(c),
+                None => false
+            },
+            _ => false
+        }
+    }
+
+    /**
+     * Draw commands for the whole tree, roots drawn in the order they were added
+     **/
+    pub fn draw(&mut self) -> Vec<DrawCommand> {
+        self.layout();
+        let mut list = Vec::new();
+        for root in &self.roots {
+            self.draw_node(*root, None, &mut list);
+        }
+        list
+    }
+
+    fn draw_node(&self, id: WidgetId, clip: Option<Rect>, list: &mut Vec<DrawCommand>) {
+        let node = match self.node(id) {
+            Some(x) if x.widget.visible => x,
+            _ => return
+        };
+        let widget = &node.widget;
+        let style = self.theme.resolve(widget.kind.name(), widget.class.as_deref(), self.state(id));
+        let scale = self.scale();
+        let font = style.font_size;
+        let inner = node.rect.shrink(&widget.layout.padding);
+        let text_height = (self.measure)("", font).y;
+
+        if style.background.alpha() > 0 || (style.border_width > 0.0 && style.border.alpha() > 0) {
+            list.push(DrawCommand::Rect {
+                rect: node.rect.scale(scale),
+                color: style.background,
+                radius: style.radius * scale,
+                border: style.border,
+                border_width: style.border_width * scale,
+            });
+        }
+
+        let text = |position: Vec2, text: &str, color: Color| DrawCommand::Text {
+            position: position * scale,
+            text: text.to_string(),
+            size: font * scale,
+            color,
+        };
+
+        match &widget.kind {
+            WidgetKind::Label { text: value } => list.push(text(inner.position, value, style.text)),
+            WidgetKind::Button { text: value } => {
+                let position = inner.center() - (self.measure)(value, font) * 0.5;
+                list.push(text(position, value, style.text));
+            },
+            WidgetKind::Slider { value, min, max, .. } => {
+                let t = if max > min { (value - min) / (max - min) } else { 0.0 };
+                let knob = inner.size.y.min(inner.size.x);
+                let fill = Rect { position: inner.position, size: Vec2::new(inner.size.x * t, inner.size.y) };
+                let knob = Rect::new(inner.position.x + (inner.size.x - knob) * t, inner.position.y, knob, inner.size.y);
+                for (rect, color) in [(fill, style.accent), (knob, style.text)] {
+                    list.push(DrawCommand::Rect {
+                        rect: rect.scale(scale),
+                        color,
+                        radius: style.radius * scale,
+                        border: Color::TRANSPARENT,
+                        border_width: 0.0,
+                    });
+                }
+            },
+            WidgetKind::TextField { text: value, cursor, placeholder, .. } => {
+                let area = clip.map_or(inner, |x| x.intersect(&inner));
+                list.push(DrawCommand::PushClip(area.scale(scale)));
+                let focused = self.focused == Some(id);
+                let caret = (self.measure)(&value[..*cursor], font).x;
+                //keep the cursor in view once the text is wider than the field
+                let shift = (caret - inner.size.x + 2.0).max(0.0);
+                let position = Vec2::new(inner.position.x - shift, inner.center().y - text_height * 0.5);
+                if value.is_empty() && !focused {
+                    list.push(text(position, placeholder, style.text.with_alpha(style.text.alpha() / 2)));
+                } else {
+                    list.push(text(position, value, style.text));
+                }
+                if focused {
+                    let rect = Rect::new(position.x + caret, position.y, 1.5, text_height);
+                    list.push(DrawCommand::Rect {
+                        rect: rect.scale(scale),
+                        color: style.accent,
+                        radius: 0.0,
+                        border: Color::TRANSPARENT,
+                        border_width: 0.0,
+                    });
+                }
+                list.push(DrawCommand::PopClip);
+            },
+            WidgetKind::Image { path, tint, .. } => list.push(DrawCommand::Image {
+                rect: inner.scale(scale),
+                path: path.clone(),
+                tint: *tint,
+            }),
+            WidgetKind::ScrollView { offset } => {
+                let area = clip.map_or(inner, |x| x.intersect(&inner));
+                list.push(DrawCommand::PushClip(area.scale(scale)));
+                for child in &node.children {
+                    self.draw_node(*child, Some(area), list);
+                }
+                list.push(DrawCommand::PopClip);
+                let row = widget.layout.direction == Direction::Row;
+                let (length, _) = split(inner.size, row);
+                if node.content > length && length > 0.0 {
+                    let (start, cross) = split(inner.position, row);
+                    let (_, thickness) = split(inner.size, row);
+                    let thumb = length * length / node.content;
+                    let position = start + (length - thumb) * offset / (node.content - length);
+                    let bar = 4.0_f32.min(thickness);
+                    let rect = Rect {
+                        position: join(position, cross + thickness - bar, row),
+                        size: join(thumb, bar, row),
+                    };
+                    list.push(DrawCommand::Rect {
+                        rect: rect.scale(scale),
+                        color: style.accent,
+                        radius: bar * 0.5 * scale,
+                        border: Color::TRANSPARENT,
+                        border_width: 0.0,
+                    });
+                }
+                return;
+            },
+            WidgetKind::Panel => {}
+        }
+
+        for child in &node.children {
+            self.draw_node(*child, clip, list);
+        }
+    }
+}
+
+fn resolve_size(size: Size, available: f32, measured: f32) -> f32 {
+    match size {
+        Size::Auto => measured,
+        Size::Px(x) => x,
+        Size::Percent(x) => available * x / 100.0
+    }
+}
+
+//a vector as (main, cross) for a layout direction
+fn split(v: Vec2, row: bool) -> (f32, f32) {
+    if row { (v.x, v.y) } else { (v.y, v.x) }
+}
+
+fn join(main: f32, cross: f32, row: bool) -> Vec2 {
+    if row { Vec2::new(main, cross) } else { Vec2::new(cross, main) }
+}
+
+/**
+ * Object that puts a shared UiTree in a layer so it gets window events
+ * The game keeps its own handle to the tree to build it and take actions
+ **/
+pub struct GameUi {
+    id: u32,
+    name: String,
+    tree: Arc<Mutex<UiTree>>,
+}
+
+impl GameUi {
+    pub fn new(id: u32, name: &str, tree: Arc<Mutex<UiTree>>) -> GameUi {
+        GameUi { id, name: name.to_string(), tree }
+    }
+
+    pub fn tree(&self) -> Arc<Mutex<UiTree>> {
+        Arc::clone(&self.tree)
+    }
+
+    /**
+     * A layer holding just this UI, push it as an overlay to get input before the game
+     **/
+    pub fn into_layer(self) -> Layer {
+        let name = self.name.clone();
+        Layer::new(true, Some(vec![Box::new(self)]), name)
+    }
+}
+
+impl Object for GameUi {
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn assets(&self) -> Vec<AssetRef> {
+        match self.tree.lock() {
+            Ok(x) => x.assets(),
+            _ => Vec::new()
+        }
+    }
+
+    fn on_update(&mut self) {
+        match self.tree.lock() {
+            Ok(mut x) => x.layout(),
+            _ => error!("UI tree Mutex is Poisoned, {} won't lay out", self.name)
+        }
+    }
+
+    fn on_event(&mut self, e: &mut dyn Event) {
+        let data = match e.get_data() {
+            Some(x) => x.clone(),
+            None => return
+        };
+        let handled = match self.tree.lock() {
+            Ok(mut x) => x.handle_event(&data),
+            _ => false
+        };
+        if handled {
+            e.set_handled(true);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::math::Vec2;
+use crate::text::truncate_graphemes;
+use crate::ui::layout::Layout;
+use crate::ui::style::Color;
+
+/**
+ * What a widget is and the state that goes with it
+ **/
+#[derive(Debug, Clone, PartialEq)]
+pub enum WidgetKind {
+    Panel,
+    Label { text: String },
+    Button { text: String },
+    Slider { value: f32, min: f32, max: f32, step: f32 },
+    /**
+     * The cursor is a byte offset into the text, always on a grapheme boundary
+     * max_chars counts graphemes, so a letter with combining accents is one
+     **/
+    TextField { text: String, cursor: usize, placeholder: String, max_chars: usize },
+    /**
+     * Scrolls its children along its layout direction
+     **/
+    ScrollView { offset: f32 },
+    Image { path: String, size: Vec2, tint: Color },
+}
+
+impl WidgetKind {
+    /**
+     * Theme class every widget of this kind is styled by
+     **/
+    pub fn name(&self) -> &'static str {
+        match self {
+            WidgetKind::Panel => "panel",
+            WidgetKind::Label { .. } => "label",
+            WidgetKind::Button { .. } => "button",
+            WidgetKind::Slider { .. } => "slider",
+            WidgetKind::TextField { .. } => "text_field",
+            WidgetKind::ScrollView { .. } => "scroll_view",
+            WidgetKind::Image { .. } => "image"
+        }
+    }
+
+    /**
+     * Whether the widget takes focus and pointer presses
+     **/
+    pub fn interactive(&self) -> bool {
+        matches!(self, WidgetKind::Button { .. } | WidgetKind::Slider { .. } | WidgetKind::TextField { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Widget {
+    pub kind: WidgetKind,
+    pub layout: Layout,
+    /**
+     * Theme class layered over the kind's style
+     **/
+    pub class: Option<String>,
+    pub enabled: bool,
+    pub visible: bool,
+}
+
+impl Widget {
+    pub fn new(kind: WidgetKind, layout: Layout) -> Widget {
+        Widget { kind, layout, class: None, enabled: true, visible: true }
+    }
+
+    pub fn panel(layout: Layout) -> Widget {
+        Widget::new(WidgetKind::Panel, layout)
+    }
+
+    pub fn label(text: &str) -> Widget {
+        Widget::new(WidgetKind::Label { text: text.to_string() }, Layout::default())
+    }
+
+    pub fn button(text: &str) -> Widget {
+        Widget::new(WidgetKind::Button { text: text.to_string() }, Layout::default())
+    }
+
+    /**
+     * A step of 0 makes the slider continuous
+     **/
+    pub fn slider(value: f32, min: f32, max: f32, step: f32) -> Widget {
+        Widget::new(WidgetKind::Slider { value: value.clamp(min, max), min, max, step }, Layout::default())
+    }
+
+    pub fn text_field(placeholder: &str, max_chars: usize) -> Widget {
+        let kind = WidgetKind::TextField {
+            text: String::new(),
+            cursor: 0,
+            placeholder: placeholder.to_string(),
+            max_chars,
+        };
+        Widget::new(kind, Layout::default())
+    }
+
+    pub fn scroll_view(layout: Layout) -> Widget {
+        Widget::new(WidgetKind::ScrollView { offset: 0.0 }, layout)
+    }
+
+    pub fn image(path: &str, size: Vec2) -> Widget {
+        Widget::new(WidgetKind::Image { path: path.to_string(), size, tint: Color::WHITE }, Layout::default())
+    }
+
+    pub fn with_layout(mut self, layout: Layout) -> Widget {
+        self.layout = layout;
+        self
+    }
+
+    pub fn with_class(mut self, class: &str) -> Widget {
+        self.class = Some(class.to_string());
+        self
+    }
+
+    /**
+     * Text shown by labels, buttons and text fields
+     **/
+    pub fn text(&self) -> Option<&str> {
+        match &self.kind {
+            WidgetKind::Label { text } | WidgetKind::Button { text } | WidgetKind::TextField { text, .. } => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn set_text(&mut self, value: &str) {
+        match &mut self.kind {
+            WidgetKind::Label { text } | WidgetKind::Button { text } => *text = value.to_string(),
+            WidgetKind::TextField { text, cursor, max_chars, .. } => {
+                *text = truncate_graphemes(value, *max_chars).to_string();
+                *cursor = text.len();
+            },
+            _ => {}
+        }
+    }
+
+    pub fn value(&self) -> Option<f32> {
+        match self.kind {
+            WidgetKind::Slider { value, .. } => Some(value),
+            _ => None
+        }
+    }
+
+    /**
+     * Sets a slider's value, snapped to its step and clamped to its range
+     **/
+    pub fn set_value(&mut self, new: f32) -> bool {
+        match &mut self.kind {
+            WidgetKind::Slider { value, min, max, step } => {
+                let mut new = new.clamp(*min, *max);
+                if *step > 0.0 {
+                    new = (*min + ((new - *min) / *step).round() * *step).clamp(*min, *max);
+                }
+                let changed = new != *value;
+                *value = new;
+                changed
+            },
+            _ => false
+        }
+    }
+}